@@ -0,0 +1,76 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Source EID access control lists for listeners
+
+use mctp::Eid;
+
+/// Set of source EIDs a listener accepts requests from
+///
+/// Either an allow-list (only the listed EIDs are accepted)
+/// or a deny-list (all but the listed EIDs are accepted).
+/// Applied with [Router::set_listener_acl()](crate::Router::set_listener_acl).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EidAcl {
+    /// Accept the listed EIDs instead of rejecting them
+    allow: bool,
+    /// One bit per EID
+    eids: [u32; 8],
+}
+
+impl EidAcl {
+    /// Accept only requests from `eids`
+    pub fn allow(eids: &[Eid]) -> Self {
+        Self::with(true, eids)
+    }
+
+    /// Accept requests from all but `eids`
+    pub fn deny(eids: &[Eid]) -> Self {
+        Self::with(false, eids)
+    }
+
+    fn with(allow: bool, eids: &[Eid]) -> Self {
+        let mut acl = EidAcl {
+            allow,
+            eids: [0; 8],
+        };
+        for eid in eids {
+            acl.insert(*eid);
+        }
+        acl
+    }
+
+    /// Add `eid` to the list
+    pub fn insert(&mut self, eid: Eid) {
+        if let Some(word) = self.eids.get_mut(usize::from(eid.0 / 32)) {
+            *word |= 1 << (eid.0 % 32);
+        }
+    }
+
+    /// Remove `eid` from the list
+    pub fn remove(&mut self, eid: Eid) {
+        if let Some(word) = self.eids.get_mut(usize::from(eid.0 / 32)) {
+            *word &= !(1 << (eid.0 % 32));
+        }
+    }
+
+    /// Check if requests from `eid` are accepted
+    pub fn permits(&self, eid: Eid) -> bool {
+        let listed = self
+            .eids
+            .get(usize::from(eid.0 / 32))
+            .is_some_and(|word| word & (1 << (eid.0 % 32)) != 0);
+        listed == self.allow
+    }
+}
@@ -0,0 +1,77 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Aggregate results of inbound batches
+
+use crate::{Disposition, Handle};
+
+/// Number of distinct handles a [BatchReport] records deliveries for
+pub const BATCH_HANDLES: usize = 8;
+
+/// What happened to the packets passed to [inbound_batch()](crate::GenericRouter::inbound_batch)
+///
+/// Every handle with a delivered message is recorded once, however many messages it received,
+/// so the application wakes each waiting listener or request once per batch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BatchReport {
+    /// Packets processed
+    pub packets: usize,
+    /// Messages completed and delivered
+    pub delivered: usize,
+    /// Packets accepted for messages that are not complete yet
+    pub incomplete: usize,
+    /// Packets forwarded to another bus
+    pub forwarded: usize,
+    /// Packets dropped, see [Disposition::is_dropped()]
+    pub dropped: usize,
+    /// More than [BATCH_HANDLES] distinct handles had messages delivered
+    ///
+    /// Only the first ones are returned by [handles()](Self::handles), the application has to
+    /// check all its handles.
+    pub handles_overflowed: bool,
+    handles: [Option<Handle>; BATCH_HANDLES],
+}
+
+impl BatchReport {
+    /// Get the handles messages were delivered to, each one once
+    pub fn handles(&self) -> impl Iterator<Item = Handle> + '_ {
+        self.handles.iter().flatten().copied()
+    }
+
+    /// Account for a packet with `disposition`
+    pub(crate) fn add(&mut self, disposition: Disposition) {
+        self.packets += 1;
+        match disposition {
+            Disposition::Delivered(handle) => {
+                self.delivered += 1;
+                self.record(handle);
+            }
+            Disposition::Incomplete => self.incomplete += 1,
+            Disposition::Forwarded => self.forwarded += 1,
+            d if d.is_dropped() => self.dropped += 1,
+            _ => (),
+        }
+    }
+
+    fn record(&mut self, handle: Handle) {
+        if self.handles().any(|h| h == handle) {
+            return;
+        }
+        match self.handles.iter_mut().find(|h| h.is_none()) {
+            Some(slot) => *slot = Some(handle),
+            None => self.handles_overflowed = true,
+        }
+    }
+}
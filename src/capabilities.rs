@@ -0,0 +1,151 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Registry of the protocols the application implements
+//!
+//! Components declare each message type they implement together with its versions and, for
+//! the vendor defined message types, the vendor command sets, see
+//! [declare_capability()](crate::GenericRouter::declare_capability). The registry is the single
+//! source answering Get Message Type Support, Get MCTP Version Support and Get Vendor Defined
+//! Message Support, restricting the types listeners can be bound to (see
+//! [set_declared_types_only()](crate::GenericRouter::set_declared_types_only)) and describing
+//! the endpoint in [local_endpoint()](crate::GenericRouter::local_endpoint).
+
+use mctp::{Error, MsgType, Result};
+
+use crate::control::codec::MAX_VERSIONS;
+use crate::control::{MSG_TYPE_CONTROL, VendorSupport};
+use crate::message_types::MessageTypeSet;
+use crate::vendors::Vendors;
+use crate::versions::Versions;
+
+/// A message type implemented by the application, see
+/// [declare_capability()](crate::GenericRouter::declare_capability)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capability<'a> {
+    /// The implemented message type
+    pub typ: MsgType,
+    /// Versions of the message type reported by Get MCTP Version Support, encoded as major,
+    /// minor, update and alpha byte. Empty if the type has no versions to report.
+    pub versions: &'a [[u8; 4]],
+    /// Vendor command sets reported by Get Vendor Defined Message Support, only for the
+    /// [PCI](crate::control::MSG_TYPE_VENDOR_PCI) and
+    /// [IANA](crate::control::MSG_TYPE_VENDOR_IANA) vendor defined message types
+    pub vendors: &'a [VendorSupport],
+}
+
+impl<'a> Capability<'a> {
+    /// Declare `typ` without versions or vendor command sets
+    pub fn new(typ: MsgType) -> Self {
+        Capability {
+            typ,
+            versions: &[],
+            vendors: &[],
+        }
+    }
+
+    /// Report `versions` for the message type
+    pub fn versions(mut self, versions: &'a [[u8; 4]]) -> Self {
+        self.versions = versions;
+        self
+    }
+
+    /// Report the vendor command sets `vendors`
+    pub fn vendors(mut self, vendors: &'a [VendorSupport]) -> Self {
+        self.vendors = vendors;
+        self
+    }
+}
+
+/// The declared message types, versions and vendor command sets
+#[derive(Debug)]
+pub(crate) struct Capabilities {
+    /// Types reported by Get Message Type Support
+    pub(crate) types: MessageTypeSet,
+    /// Versions reported by Get MCTP Version Support
+    pub(crate) versions: Versions,
+    /// Command sets reported by Get Vendor Defined Message Support
+    pub(crate) vendors: Vendors,
+    /// Listeners can only be bound to declared types
+    pub(crate) declared_only: bool,
+}
+
+impl Capabilities {
+    pub(crate) fn new() -> Self {
+        Capabilities {
+            types: MessageTypeSet::new(),
+            versions: Versions::new(),
+            vendors: Vendors::new(),
+            declared_only: false,
+        }
+    }
+
+    /// Declare `capability`, replacing the versions previously declared for its type
+    ///
+    /// Nothing is registered unless the whole capability fits.
+    /// Returns [BadArgument](Error::BadArgument) for the control message type and for vendor
+    /// command sets of another message type, [NoSpace](Error::NoSpace) if a table is full.
+    pub(crate) fn declare(&mut self, capability: &Capability<'_>) -> Result<()> {
+        let typ = capability.typ;
+        if typ.0 == MSG_TYPE_CONTROL.0
+            || capability
+                .vendors
+                .iter()
+                .any(|v| v.vendor.msg_type().0 != typ.0)
+        {
+            return Err(Error::BadArgument);
+        }
+        let new_vendors = capability
+            .vendors
+            .iter()
+            .filter(|v| !self.vendors.contains(v))
+            .count();
+        if !self.types.has_room(typ)
+            || capability.versions.len() > MAX_VERSIONS
+            || (!capability.versions.is_empty() && !self.versions.has_room(typ.0))
+            || new_vendors > self.vendors.free()
+        {
+            return Err(Error::NoSpace);
+        }
+        self.types.add(typ)?;
+        self.versions.set(typ.0, capability.versions)?;
+        for &support in capability.vendors {
+            self.vendors.add(support)?;
+        }
+        Ok(())
+    }
+
+    /// Withdraw the declaration of `typ` with its versions and vendor command sets
+    ///
+    /// Returns whether `typ` was declared.
+    pub(crate) fn withdraw(&mut self, typ: MsgType) -> bool {
+        let declared = self.is_declared(typ);
+        self.types.remove(typ);
+        if typ.0 != MSG_TYPE_CONTROL.0 {
+            let _ = self.versions.set(typ.0, &[]);
+        }
+        self.vendors.retain(|v| v.vendor.msg_type().0 != typ.0);
+        declared
+    }
+
+    /// Check whether `typ` was declared, or has versions registered
+    pub(crate) fn is_declared(&self, typ: MsgType) -> bool {
+        self.types.contains(typ) || self.versions.get(typ.0).is_some()
+    }
+
+    /// Check whether a listener can be bound to `typ`, `None` for the catch-all listener
+    pub(crate) fn allows_listener(&self, typ: Option<MsgType>) -> bool {
+        !self.declared_only || typ.is_none_or(|t| self.is_declared(t))
+    }
+}
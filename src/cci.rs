@@ -0,0 +1,258 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! CXL CCI over MCTP channels
+//!
+//! CXL Component Command Interface and Fabric Manager API messages are sent with MCTP
+//! message type [MSG_TYPE_CXL_CCI] or [MSG_TYPE_CXL_FM_API]. Each one starts with a
+//! [CciHeader]; a response carries the message tag and opcode of its request.
+//!
+//! [CciRequester] and [CciResponder] work on the channels of a
+//! [SharedRouter](crate::shared::SharedRouter). The messages passed to and returned from
+//! them are complete CCI messages, header included.
+
+use mctp::{Error, MsgIC, MsgType, Result};
+
+use crate::shared::{SharedListener, SharedRequest};
+use crate::{Clock, HandleTables, Hooks, MessageInfo, Sender};
+
+/// MCTP message type of CXL Fabric Manager API messages
+pub const MSG_TYPE_CXL_FM_API: MsgType = MsgType(0x07);
+
+/// MCTP message type of CXL Component Command Interface messages
+pub const MSG_TYPE_CXL_CCI: MsgType = MsgType(0x08);
+
+/// Length of the CCI message header
+pub const CCI_HEADER_LEN: usize = 12;
+
+/// Largest payload length the header can describe
+pub const CCI_MAX_PAYLOAD: usize = (1 << 21) - 1;
+
+const CATEGORY_REQUEST: u8 = 0;
+const CATEGORY_RESPONSE: u8 = 1;
+const CATEGORY_MASK: u8 = 0x0f;
+const FLAG_BACKGROUND: u8 = 0x80;
+const LENGTH_HIGH_MASK: u8 = 0x1f;
+
+/// The header of a CCI message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CciHeader {
+    /// Request rather than response
+    pub request: bool,
+    /// Message tag, correlating a response with its request
+    pub tag: u8,
+    /// Command opcode
+    pub opcode: u16,
+    /// Length of the payload following the header
+    pub payload_len: usize,
+    /// The command was started as background operation (responses only)
+    pub background: bool,
+    /// Return code (responses only)
+    pub return_code: u16,
+    /// Vendor specific extended status (responses only)
+    pub vendor_status: u16,
+}
+
+impl CciHeader {
+    /// Parse the header at the start of `msg`
+    ///
+    /// Returns `None` if `msg` is too short or of an unknown message category.
+    pub fn parse(msg: &[u8]) -> Option<Self> {
+        let &[
+            category,
+            tag,
+            _,
+            op0,
+            op1,
+            len0,
+            len1,
+            len2,
+            rc0,
+            rc1,
+            vs0,
+            vs1,
+            ..,
+        ] = msg
+        else {
+            return None;
+        };
+        let request = match category & CATEGORY_MASK {
+            CATEGORY_REQUEST => true,
+            CATEGORY_RESPONSE => false,
+            _ => return None,
+        };
+        Some(CciHeader {
+            request,
+            tag,
+            opcode: u16::from_le_bytes([op0, op1]),
+            payload_len: u32::from_le_bytes([len0, len1, len2 & LENGTH_HIGH_MASK, 0]) as usize,
+            background: len2 & FLAG_BACKGROUND != 0,
+            return_code: u16::from_le_bytes([rc0, rc1]),
+            vendor_status: u16::from_le_bytes([vs0, vs1]),
+        })
+    }
+
+    /// Encode the header
+    ///
+    /// Returns [BadArgument](Error::BadArgument) if the payload length exceeds
+    /// [CCI_MAX_PAYLOAD].
+    pub fn to_bytes(self) -> Result<[u8; CCI_HEADER_LEN]> {
+        if self.payload_len > CCI_MAX_PAYLOAD {
+            return Err(Error::BadArgument);
+        }
+        let category = if self.request {
+            CATEGORY_REQUEST
+        } else {
+            CATEGORY_RESPONSE
+        };
+        let [op0, op1] = self.opcode.to_le_bytes();
+        let [len0, len1, mut len2, _] = (self.payload_len as u32).to_le_bytes();
+        if self.background {
+            len2 |= FLAG_BACKGROUND;
+        }
+        let [rc0, rc1] = self.return_code.to_le_bytes();
+        let [vs0, vs1] = self.vendor_status.to_le_bytes();
+        Ok([
+            category, self.tag, 0, op0, op1, len0, len1, len2, rc0, rc1, vs0, vs1,
+        ])
+    }
+}
+
+/// A CCI requester talking to a single component
+///
+/// Only one request is outstanding at a time, responses with a different message tag or
+/// opcode are discarded.
+#[derive(Debug)]
+pub struct CciRequester<'r, S: Sender, T: HandleTables, H: Hooks, C: Clock> {
+    channel: SharedRequest<'r, S, T, H, C>,
+    typ: MsgType,
+    next_tag: u8,
+    outstanding: Option<CciHeader>,
+}
+
+impl<'r, S: Sender, T: HandleTables, H: Hooks, C: Clock> CciRequester<'r, S, T, H, C> {
+    /// Send messages of type `typ` ([MSG_TYPE_CXL_CCI] or [MSG_TYPE_CXL_FM_API])
+    /// through `channel`
+    pub fn new(channel: SharedRequest<'r, S, T, H, C>, typ: MsgType) -> Self {
+        CciRequester {
+            channel,
+            typ,
+            next_tag: 0,
+            outstanding: None,
+        }
+    }
+
+    /// Send a request for `opcode` with `payload`
+    ///
+    /// Allocates the next message tag, a previously outstanding request is abandoned.
+    /// Returns the header that was sent.
+    pub fn send(&mut self, opcode: u16, payload: &[u8]) -> Result<CciHeader> {
+        let header = CciHeader {
+            request: true,
+            tag: self.next_tag,
+            opcode,
+            payload_len: payload.len(),
+            background: false,
+            return_code: 0,
+            vendor_status: 0,
+        };
+        self.channel
+            .send(self.typ, MsgIC(false), &[&header.to_bytes()?, payload])?;
+        self.next_tag = self.next_tag.wrapping_add(1);
+        self.outstanding = Some(header);
+        Ok(header)
+    }
+
+    /// Receive the response to the outstanding request into `buf` without blocking
+    ///
+    /// The CCI message, header included, is stored in `buf[..len]`.
+    /// Returns the header and `len`, `Ok(None)` when no matching response is available.
+    pub fn receive(&mut self, buf: &mut [u8]) -> Result<Option<(CciHeader, usize)>> {
+        let Some(outstanding) = self.outstanding else {
+            return Err(Error::BadArgument);
+        };
+        while let Some(info) = self.channel.try_recv(buf)? {
+            match buf.get(..info.len).and_then(CciHeader::parse) {
+                Some(header)
+                    if info.typ == self.typ
+                        && !header.request
+                        && header.tag == outstanding.tag
+                        && header.opcode == outstanding.opcode =>
+                {
+                    self.outstanding = None;
+                    return Ok(Some((header, info.len)));
+                }
+                _ => debug!("discarded unexpected cci response from {}", info.source.0),
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A request received by a [CciResponder]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CciRequest {
+    /// Metadata of the MCTP message
+    pub info: MessageInfo,
+    /// Header of the request
+    pub header: CciHeader,
+}
+
+/// A CCI responder
+#[derive(Debug)]
+pub struct CciResponder<'r, S: Sender, T: HandleTables, H: Hooks, C: Clock> {
+    listener: SharedListener<'r, S, T, H, C>,
+}
+
+impl<'r, S: Sender, T: HandleTables, H: Hooks, C: Clock> CciResponder<'r, S, T, H, C> {
+    /// Receive requests on `listener`, bound for [MSG_TYPE_CXL_CCI] or [MSG_TYPE_CXL_FM_API]
+    pub fn new(listener: SharedListener<'r, S, T, H, C>) -> Self {
+        CciResponder { listener }
+    }
+
+    /// Receive a request into `buf` without blocking
+    ///
+    /// The CCI message, header included, is stored in `buf[..request.info.len]`.
+    /// Messages that are not CCI requests are discarded.
+    /// Returns `Ok(None)` when no request is available.
+    pub fn receive(&mut self, buf: &mut [u8]) -> Result<Option<CciRequest>> {
+        while let Some(info) = self.listener.try_recv(buf)? {
+            match buf.get(..info.len).and_then(CciHeader::parse) {
+                Some(header) if header.request => {
+                    return Ok(Some(CciRequest { info, header }));
+                }
+                _ => debug!("discarded invalid cci request from {}", info.source.0),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Respond to `request` with `return_code` and `payload`
+    pub fn respond(
+        &mut self,
+        request: &CciRequest,
+        return_code: u16,
+        payload: &[u8],
+    ) -> Result<()> {
+        let header = CciHeader {
+            request: false,
+            payload_len: payload.len(),
+            return_code,
+            ..request.header
+        };
+        self.listener
+            .respond(&request.info, MsgIC(false), &[&header.to_bytes()?, payload])?;
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Time sources for the [Router](crate::Router)
+
+use core::cell::Cell;
+
+/// A monotonic time source in milliseconds
+///
+/// The [Router](crate::Router) reads all timestamps (timeouts, capture timestamps, request ages)
+/// from its clock, so a single consistent time source is used.
+pub trait Clock {
+    /// Current time in milliseconds
+    ///
+    /// Must never decrease. The epoch is arbitrary.
+    fn now_millis(&self) -> u64;
+
+    /// Wait for `millis`, e.g. before sending to a busy receiver again
+    ///
+    /// The default implementation spins until the time of the clock advanced by `millis`.
+    fn delay_millis(&self, millis: u64) {
+        let until = self.now_millis().saturating_add(millis);
+        while self.now_millis() < until {
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Smallest step in milliseconds the time advances by
+    ///
+    /// Timing settings finer than this are rejected by
+    /// [try_new_with_tables()](crate::GenericRouter::try_new_with_tables).
+    /// The default is 1.
+    fn resolution_millis(&self) -> u64 {
+        1
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now_millis(&self) -> u64 {
+        (**self).now_millis()
+    }
+
+    fn delay_millis(&self, millis: u64) {
+        (**self).delay_millis(millis)
+    }
+
+    fn resolution_millis(&self) -> u64 {
+        (**self).resolution_millis()
+    }
+}
+
+/// A clock that only advances when told to
+///
+/// Used by routers created with an explicit `now_millis`, where the time is passed to
+/// [update()](crate::GenericRouter::update).
+/// Also useful for deterministic tests, a `&ManualClock` can be shared by several routers.
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    now: Cell<u64>,
+}
+
+impl ManualClock {
+    /// Create a clock starting at `now_millis`
+    pub const fn new(now_millis: u64) -> Self {
+        ManualClock {
+            now: Cell::new(now_millis),
+        }
+    }
+
+    /// Set the current time
+    pub fn set(&self, now_millis: u64) {
+        self.now.set(now_millis);
+    }
+
+    /// Advance the current time by `millis`
+    pub fn advance(&self, millis: u64) {
+        self.now.set(self.now.get().saturating_add(millis));
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_millis(&self) -> u64 {
+        self.now.get()
+    }
+
+    /// Advances the clock by `millis` instead of waiting
+    fn delay_millis(&self, millis: u64) {
+        self.advance(millis);
+    }
+}
+
+/// A clock based on [std::time::Instant], counting from its creation
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub struct StdClock {
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "std")]
+impl StdClock {
+    /// Create a clock starting at 0
+    pub fn new() -> Self {
+        StdClock {
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for StdClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for StdClock {
+    fn now_millis(&self) -> u64 {
+        self.start.elapsed().as_millis() as u64
+    }
+
+    fn delay_millis(&self, millis: u64) {
+        std::thread::sleep(std::time::Duration::from_millis(millis));
+    }
+}
+
+/// A clock based on [embassy_time::Instant]
+#[cfg(feature = "embassy-time")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EmbassyClock;
+
+#[cfg(feature = "embassy-time")]
+impl Clock for EmbassyClock {
+    fn now_millis(&self) -> u64 {
+        embassy_time::Instant::now().as_millis()
+    }
+
+    fn delay_millis(&self, millis: u64) {
+        embassy_time::block_for(embassy_time::Duration::from_millis(millis));
+    }
+}
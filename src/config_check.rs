@@ -0,0 +1,206 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Validation of a [RouterConfig] against the tables, transport and clock of a router
+//!
+//! See [try_new_with_tables()](crate::GenericRouter::try_new_with_tables).
+
+use core::fmt;
+
+use mctp::Eid;
+
+use crate::RouterConfig;
+use crate::header::HEADER_LEN;
+use crate::tables::MAX_HANDLES;
+
+/// A timing setting of a [RouterConfig], see [ConfigError::BelowResolution]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Timing {
+    /// [RouterConfig::request_timeout_millis()]
+    RequestTimeout,
+    /// [RouterConfig::tx_timeout_millis()]
+    TxTimeout,
+    /// [RouterConfig::tag_reclaim_millis()]
+    TagReclaim,
+    /// The delay of [RouterConfig::busy_retry()]
+    BusyRetryDelay,
+    /// The probe interval of [RouterConfig::keep_alive()]
+    KeepAliveInterval,
+    /// The probe timeout of [RouterConfig::keep_alive()]
+    KeepAliveTimeout,
+    /// The interval of [RouterConfig::notify_retransmit()]
+    NotifyRetransmitInterval,
+}
+
+/// A configuration a router can't honor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// The handle tables have more slots than cookies can address
+    TooManyHandles {
+        /// Listener and request slots of the tables
+        handles: usize,
+        /// Slots cookies can address
+        max: usize,
+    },
+    /// An MTU can't hold an MCTP header and payload
+    MtuTooSmall {
+        /// EID of a static MTU entry, `None` for the MTU of the [Sender](crate::Sender)
+        eid: Option<Eid>,
+        /// The MTU
+        mtu: usize,
+    },
+    /// A timing setting is finer than the [resolution](crate::Clock::resolution_millis) of
+    /// the clock, so it would expire early, spin or fire on every poll
+    BelowResolution {
+        /// The setting
+        timing: Timing,
+        /// Its value
+        millis: u64,
+        /// Resolution of the clock
+        resolution_millis: u64,
+    },
+    /// The [RateLimit](crate::RateLimit) never admits a packet or never refills
+    RateLimitStalled,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::TooManyHandles { handles, max } => {
+                write!(f, "{handles} handle slots, at most {max} supported")
+            }
+            ConfigError::MtuTooSmall {
+                eid: Some(eid),
+                mtu,
+            } => {
+                write!(f, "MTU {mtu} of eid {} too small", eid.0)
+            }
+            ConfigError::MtuTooSmall { eid: None, mtu } => {
+                write!(f, "MTU {mtu} of the sender too small")
+            }
+            ConfigError::BelowResolution {
+                timing,
+                millis,
+                resolution_millis,
+            } => write!(
+                f,
+                "{timing:?} of {millis} ms below the clock resolution of {resolution_millis} ms"
+            ),
+            ConfigError::RateLimitStalled => f.write_str("rate limit admits no packets"),
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ConfigError {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            ConfigError::TooManyHandles { handles, max } => defmt::write!(
+                f,
+                "TooManyHandles {{ handles: {=usize}, max: {=usize} }}",
+                handles,
+                max
+            ),
+            ConfigError::MtuTooSmall { eid, mtu } => defmt::write!(
+                f,
+                "MtuTooSmall {{ eid: {}, mtu: {=usize} }}",
+                eid.map(|e| e.0),
+                mtu
+            ),
+            ConfigError::BelowResolution {
+                timing,
+                millis,
+                resolution_millis,
+            } => defmt::write!(
+                f,
+                "BelowResolution {{ timing: {}, millis: {=u64}, resolution_millis: {=u64} }}",
+                timing,
+                millis,
+                resolution_millis
+            ),
+            ConfigError::RateLimitStalled => defmt::write!(f, "RateLimitStalled"),
+        }
+    }
+}
+
+impl core::error::Error for ConfigError {}
+
+/// Check `config` for a router with `handles` slots, a sender MTU of `port_mtu` and a clock
+/// advancing in steps of `resolution_millis`
+pub(crate) fn check(
+    config: &RouterConfig,
+    handles: usize,
+    port_mtu: usize,
+    resolution_millis: u64,
+) -> Result<(), ConfigError> {
+    if handles > MAX_HANDLES {
+        return Err(ConfigError::TooManyHandles {
+            handles,
+            max: MAX_HANDLES,
+        });
+    }
+    if port_mtu <= HEADER_LEN {
+        return Err(ConfigError::MtuTooSmall {
+            eid: None,
+            mtu: port_mtu,
+        });
+    }
+    if let Some(&(eid, mtu, _)) = config.mtus.iter().flatten().find(|m| m.1 <= HEADER_LEN) {
+        return Err(ConfigError::MtuTooSmall {
+            eid: Some(eid),
+            mtu,
+        });
+    }
+    let timings = [
+        (Timing::RequestTimeout, config.request_timeout_millis),
+        (Timing::TxTimeout, config.tx_timeout_millis),
+        (Timing::TagReclaim, config.tag_reclaim_millis),
+        (
+            Timing::BusyRetryDelay,
+            config
+                .busy_retry
+                .filter(|r| r.max_retries > 0)
+                .map(|r| r.delay_millis),
+        ),
+        (
+            Timing::KeepAliveInterval,
+            config.keep_alive.map(|k| k.interval_millis),
+        ),
+        (
+            Timing::KeepAliveTimeout,
+            config.keep_alive.map(|k| k.timeout_millis),
+        ),
+        (
+            Timing::NotifyRetransmitInterval,
+            config.notify_retransmit.map(|n| n.interval_millis),
+        ),
+    ];
+    let resolution_millis = resolution_millis.max(1);
+    if let Some((timing, Some(millis))) = timings
+        .into_iter()
+        .find(|t| t.1.is_some_and(|m| m < resolution_millis))
+    {
+        return Err(ConfigError::BelowResolution {
+            timing,
+            millis,
+            resolution_millis,
+        });
+    }
+    if config.rate_limit.is_some_and(|l| l.is_stalled()) {
+        return Err(ConfigError::RateLimitStalled);
+    }
+    Ok(())
+}
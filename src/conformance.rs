@@ -0,0 +1,321 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Packet generators and checkers for conformance tests of transport bindings
+//!
+//! Requires the `test-util` feature.
+//!
+//! A [PacketTrain] synthesizes the packets of a message as the base specification requires
+//! them, and can be bent into deliberately non-compliant sequences: wrong SOM/EOM flags,
+//! sequence numbers or tags, missing packets or uneven fragments. [check()] goes the other
+//! way and reports every deviation from the specification in a capture of packets, e.g.
+//! the output of a binding under test.
+//!
+//! ```
+//! use mctp::{Eid, Tag, TagValue};
+//! use mctp_lib::conformance::{Nonconformance, PacketTrain, check};
+//!
+//! let train = PacketTrain::message(Eid(9), Eid(8), Tag::Owned(TagValue(1)), &[1; 100], 64);
+//! assert_eq!(train.packets().len(), 2);
+//! assert!(check(train.packets(), 64).is_empty());
+//!
+//! let bad = train.set_seq(1, 3);
+//! let findings = check(bad.packets(), 64);
+//! assert_eq!(
+//!     findings.first().map(|f| f.issue),
+//!     Some(Nonconformance::SequenceError { expected: 1, found: 3 })
+//! );
+//! ```
+
+#[cfg(not(test))]
+use alloc::vec::Vec;
+
+use mctp::{Eid, Tag, TagValue};
+
+use crate::header::{HEADER_LEN, Header};
+use crate::validation::{self, Violation};
+
+/// Header version of the base specification
+const VERSION: u8 = 1;
+
+const FLAG_SOM: u8 = 0x80;
+const FLAG_EOM: u8 = 0x40;
+const FLAG_TO: u8 = 0x08;
+const SEQ_SHIFT: u8 = 4;
+const SEQ_MASK: u8 = 0x03;
+const TAG_MASK: u8 = 0x07;
+
+/// The packets of a single message, see the [module documentation](self)
+///
+/// Modifications address packets by their index, indices past the last packet are ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PacketTrain {
+    packets: Vec<Vec<u8>>,
+}
+
+impl PacketTrain {
+    /// Fragment `msg`, starting with the message type, from `source` to `dest` for `mtu`
+    ///
+    /// All packets but the last carry `mtu` bytes of payload, at least one byte per packet.
+    pub fn message(source: Eid, dest: Eid, tag: Tag, msg: &[u8], mtu: usize) -> Self {
+        let chunks: Vec<&[u8]> = if msg.is_empty() {
+            Vec::from([msg])
+        } else {
+            msg.chunks(mtu.max(1)).collect()
+        };
+        let last = chunks.len() - 1;
+        let packets = chunks
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                let hdr = Header {
+                    dest,
+                    source,
+                    som: i == 0,
+                    eom: i == last,
+                    seq: (i as u8) & SEQ_MASK,
+                    tag,
+                };
+                let mut pkt = Vec::from(hdr.to_bytes());
+                pkt.extend_from_slice(chunk);
+                pkt
+            })
+            .collect();
+        PacketTrain { packets }
+    }
+
+    /// Get the packets, starting with the transport header
+    pub fn packets(&self) -> &[Vec<u8>] {
+        &self.packets
+    }
+
+    /// Take the packets
+    pub fn into_packets(self) -> Vec<Vec<u8>> {
+        self.packets
+    }
+
+    /// Set the start of message flag of packet `index`
+    pub fn set_som(self, index: usize, som: bool) -> Self {
+        self.with_flags(index, |f| set_bit(f, FLAG_SOM, som))
+    }
+
+    /// Set the end of message flag of packet `index`
+    pub fn set_eom(self, index: usize, eom: bool) -> Self {
+        self.with_flags(index, |f| set_bit(f, FLAG_EOM, eom))
+    }
+
+    /// Set the sequence number of packet `index`, masked to 2 bits
+    pub fn set_seq(self, index: usize, seq: u8) -> Self {
+        self.with_flags(index, |f| {
+            f & !(SEQ_MASK << SEQ_SHIFT) | (seq & SEQ_MASK) << SEQ_SHIFT
+        })
+    }
+
+    /// Set the tag and tag owner flag of packet `index`
+    pub fn set_tag(self, index: usize, tag: Tag) -> Self {
+        self.with_flags(index, |f| {
+            let f = set_bit(f & !TAG_MASK, FLAG_TO, tag.is_owner());
+            f | tag.tag().0 & TAG_MASK
+        })
+    }
+
+    /// Set the header version byte of packet `index`, including the reserved bits
+    pub fn set_version(mut self, index: usize, version: u8) -> Self {
+        if let Some(b) = self.packets.get_mut(index).and_then(|p| p.first_mut()) {
+            *b = version;
+        }
+        self
+    }
+
+    /// Cut or pad the payload of packet `index` to `len` bytes
+    pub fn set_payload_len(mut self, index: usize, len: usize) -> Self {
+        if let Some(pkt) = self.packets.get_mut(index) {
+            pkt.resize(HEADER_LEN + len, 0);
+        }
+        self
+    }
+
+    /// Remove packet `index`
+    pub fn remove(mut self, index: usize) -> Self {
+        if index < self.packets.len() {
+            self.packets.remove(index);
+        }
+        self
+    }
+
+    /// Send packet `index` twice in a row
+    pub fn duplicate(mut self, index: usize) -> Self {
+        if let Some(pkt) = self.packets.get(index).cloned() {
+            self.packets.insert(index, pkt);
+        }
+        self
+    }
+
+    fn with_flags(mut self, index: usize, f: impl FnOnce(u8) -> u8) -> Self {
+        if let Some(b) = self.packets.get_mut(index).and_then(|p| p.get_mut(3)) {
+            *b = f(*b);
+        }
+        self
+    }
+}
+
+fn set_bit(flags: u8, bit: u8, set: bool) -> u8 {
+    if set { flags | bit } else { flags & !bit }
+}
+
+/// A deviation from the base specification found by [check()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Nonconformance {
+    /// The packet is shorter than the transport header
+    Truncated,
+    /// The header version is not 1
+    UnsupportedVersion(u8),
+    /// The header breaks a rule the router can enforce, see [Validation](crate::Validation)
+    Violation(Violation),
+    /// The packet carries no payload
+    EmptyPayload,
+    /// The payload exceeds the MTU
+    TooLarge {
+        /// Payload length
+        len: usize,
+        /// MTU of the check
+        mtu: usize,
+    },
+    /// A packet without SOM flag belongs to no message in progress
+    MissingSom,
+    /// A packet with SOM flag started a message while the previous one with the same
+    /// source, destination and tag was incomplete
+    Interrupted,
+    /// The sequence number doesn't follow the previous packet of the message
+    SequenceError {
+        /// Expected sequence number
+        expected: u8,
+        /// Sequence number of the packet
+        found: u8,
+    },
+    /// A packet other than the last of its message differs in payload length from the first
+    UnevenFragment {
+        /// Payload length
+        len: usize,
+        /// Payload length of the first packet
+        expected: usize,
+    },
+    /// The message was not completed by the end of the capture
+    Unterminated,
+}
+
+/// A [Nonconformance] at packet `index` of the capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Finding {
+    /// Index of the packet in the capture, the number of packets for
+    /// [Unterminated](Nonconformance::Unterminated) messages
+    pub index: usize,
+    /// The deviation found
+    pub issue: Nonconformance,
+}
+
+/// A message in progress in a capture
+struct Flow {
+    source: Eid,
+    dest: Eid,
+    owner: bool,
+    tag: TagValue,
+    next_seq: u8,
+    fragment_len: usize,
+}
+
+/// Check the packets of a capture for conformance with the base specification
+///
+/// `packets` start with the transport header. Payloads larger than `mtu` are reported.
+/// Returns the findings in the order of the packets.
+pub fn check<P: AsRef<[u8]>>(packets: impl IntoIterator<Item = P>, mtu: usize) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut flows: Vec<Flow> = Vec::new();
+    let mut count = 0;
+    for (index, pkt) in packets.into_iter().enumerate() {
+        count = index + 1;
+        let pkt = pkt.as_ref();
+        let mut found = |issue| findings.push(Finding { index, issue });
+        let Some(hdr) = Header::parse(pkt) else {
+            found(Nonconformance::Truncated);
+            continue;
+        };
+        if let Some(&version) = pkt.first()
+            && version & 0x0f != VERSION
+        {
+            found(Nonconformance::UnsupportedVersion(version));
+        }
+        validation::check(pkt, |v| found(Nonconformance::Violation(v)));
+        let len = pkt.len() - HEADER_LEN;
+        if len == 0 {
+            found(Nonconformance::EmptyPayload);
+        }
+        if len > mtu {
+            found(Nonconformance::TooLarge { len, mtu });
+        }
+        let pos = flows.iter().position(|f| {
+            f.source == hdr.source
+                && f.dest == hdr.dest
+                && f.owner == hdr.tag.is_owner()
+                && f.tag == hdr.tag.tag()
+        });
+        if hdr.som {
+            if let Some(pos) = pos {
+                found(Nonconformance::Interrupted);
+                flows.remove(pos);
+            }
+            if !hdr.eom {
+                flows.push(Flow {
+                    source: hdr.source,
+                    dest: hdr.dest,
+                    owner: hdr.tag.is_owner(),
+                    tag: hdr.tag.tag(),
+                    next_seq: (hdr.seq + 1) & SEQ_MASK,
+                    fragment_len: len,
+                });
+            }
+            continue;
+        }
+        let Some(pos) = pos else {
+            found(Nonconformance::MissingSom);
+            continue;
+        };
+        let Some(flow) = flows.get_mut(pos) else {
+            continue;
+        };
+        if hdr.seq != flow.next_seq {
+            found(Nonconformance::SequenceError {
+                expected: flow.next_seq,
+                found: hdr.seq,
+            });
+        }
+        flow.next_seq = (hdr.seq + 1) & SEQ_MASK;
+        if (!hdr.eom && len != flow.fragment_len) || (hdr.eom && len > flow.fragment_len) {
+            found(Nonconformance::UnevenFragment {
+                len,
+                expected: flow.fragment_len,
+            });
+        }
+        if hdr.eom {
+            flows.remove(pos);
+        }
+    }
+    for _ in flows {
+        findings.push(Finding {
+            index: count,
+            issue: Nonconformance::Unterminated,
+        });
+    }
+    findings
+}
@@ -0,0 +1,276 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Further consumers of the messages delivered to a listener
+//!
+//! A message delivered to a listener with [consumers](crate::GenericRouter::add_consumer)
+//! stays in the reassembly buffer of the stack until the listener and every consumer
+//! received it. Each reader borrows the same buffer, the payload is never copied.
+//! The router counts the readers still due for each shared message and hands the buffer
+//! back to the stack once the last one is done.
+
+use mctp::{Eid, Tag};
+use mctp_estack::config::NUM_RECEIVE;
+
+use crate::{ListenerHandle, REASSEMBLY_TIMEOUT_MILLIS};
+
+/// Number of consumers a [Router](crate::Router) can have across all listeners
+pub const CONSUMER_TABLE_SIZE: usize = 4;
+
+/// Handle of a further consumer of a listener, see
+/// [add_consumer()](crate::GenericRouter::add_consumer)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConsumerHandle {
+    pub(crate) listener: ListenerHandle,
+    pub(crate) index: u8,
+}
+
+impl ConsumerHandle {
+    /// Get the listener whose messages are consumed
+    pub fn listener(&self) -> ListenerHandle {
+        self.listener
+    }
+}
+
+/// A message retained for more than one reader
+#[derive(Debug, Clone, Copy)]
+struct Share {
+    listener: ListenerHandle,
+    source: Eid,
+    tag: Tag,
+    since_millis: u64,
+    /// Delivery order
+    seq: u32,
+    /// Consumers that didn't receive the message yet, by index
+    pending: u8,
+    /// The listener didn't receive the message yet
+    primary: bool,
+}
+
+impl Share {
+    fn released(&self) -> bool {
+        self.pending == 0 && !self.primary
+    }
+}
+
+/// Consumers and the messages shared with them
+#[derive(Debug)]
+pub(crate) struct Consumers {
+    consumers: [Option<ListenerHandle>; CONSUMER_TABLE_SIZE],
+    shares: [Option<Share>; NUM_RECEIVE],
+    seq: u32,
+    /// Messages consumers did not get
+    pub(crate) missed: usize,
+}
+
+impl Consumers {
+    pub(crate) const fn new() -> Self {
+        Consumers {
+            consumers: [None; CONSUMER_TABLE_SIZE],
+            shares: [None; NUM_RECEIVE],
+            seq: 0,
+            missed: 0,
+        }
+    }
+
+    pub(crate) fn add(&mut self, listener: ListenerHandle) -> Option<ConsumerHandle> {
+        let (index, slot) = self
+            .consumers
+            .iter_mut()
+            .enumerate()
+            .find(|(_, c)| c.is_none())?;
+        *slot = Some(listener);
+        Some(ConsumerHandle {
+            listener,
+            index: index as u8,
+        })
+    }
+
+    pub(crate) fn is_registered(&self, consumer: ConsumerHandle) -> bool {
+        self.consumers.get(usize::from(consumer.index)) == Some(&Some(consumer.listener))
+    }
+
+    /// Remove `consumer`, returns the shared messages no reader is due for anymore
+    pub(crate) fn remove(
+        &mut self,
+        consumer: ConsumerHandle,
+    ) -> impl Iterator<Item = (Eid, Tag)> + use<> {
+        if let Some(slot) = self.consumers.get_mut(usize::from(consumer.index)) {
+            *slot = None;
+        }
+        let bit = 1 << consumer.index;
+        self.release(|s| {
+            s.pending &= !bit;
+            false
+        })
+        .into_iter()
+        .flatten()
+    }
+
+    /// Remove the consumers of `listener` and its shared messages
+    ///
+    /// Returns the messages the listener already received, they are no longer held for it.
+    pub(crate) fn forget(
+        &mut self,
+        listener: ListenerHandle,
+    ) -> impl Iterator<Item = (Eid, Tag)> + use<> {
+        for slot in self.consumers.iter_mut() {
+            if *slot == Some(listener) {
+                *slot = None;
+            }
+        }
+        // Messages the listener didn't receive yet are still held under its cookie
+        self.release(|s| {
+            if s.listener != listener {
+                return false;
+            }
+            s.pending = 0;
+            s.primary
+        })
+        .into_iter()
+        .flatten()
+    }
+
+    /// Apply `f` to all shares and remove those without readers
+    ///
+    /// Shares for which `f` returns `true` are removed without being returned.
+    fn release(
+        &mut self,
+        mut f: impl FnMut(&mut Share) -> bool,
+    ) -> [Option<(Eid, Tag)>; NUM_RECEIVE] {
+        let mut released = [None; NUM_RECEIVE];
+        for (slot, out) in self.shares.iter_mut().zip(released.iter_mut()) {
+            let Some(share) = slot else {
+                continue;
+            };
+            let drop = f(share);
+            if drop || share.released() {
+                if !drop {
+                    *out = Some((share.source, share.tag));
+                }
+                *slot = None;
+            }
+        }
+        released
+    }
+
+    /// Share a message just retained for `listener` with its consumers
+    pub(crate) fn delivered(
+        &mut self,
+        listener: ListenerHandle,
+        source: Eid,
+        tag: Tag,
+        now_millis: u64,
+    ) {
+        let pending = self
+            .consumers
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| **c == Some(listener))
+            .fold(0u8, |bits, (i, _)| bits | 1 << i);
+        if pending == 0 {
+            return;
+        }
+        let Some(slot) = self.shares.iter_mut().find(|s| s.is_none()) else {
+            self.missed = self.missed.wrapping_add(1);
+            return;
+        };
+        self.seq = self.seq.wrapping_add(1);
+        *slot = Some(Share {
+            listener,
+            source,
+            tag,
+            since_millis: now_millis,
+            seq: self.seq,
+            pending,
+            primary: true,
+        });
+    }
+
+    /// Account for `listener` receiving the message from `source` with `tag`
+    ///
+    /// Returns whether consumers are still due, the message has to stay retained.
+    pub(crate) fn received(&mut self, listener: ListenerHandle, source: Eid, tag: Tag) -> bool {
+        let Some(slot) = self.shares.iter_mut().find(|s| {
+            s.is_some_and(|s| {
+                s.listener == listener && s.primary && (s.source, s.tag) == (source, tag)
+            })
+        }) else {
+            return false;
+        };
+        let Some(share) = slot.as_mut() else {
+            return false;
+        };
+        share.primary = false;
+        if share.released() {
+            *slot = None;
+            return false;
+        }
+        true
+    }
+
+    /// Undo [received()](Self::received), the message was put back for `listener`
+    pub(crate) fn requeued(&mut self, listener: ListenerHandle, source: Eid, tag: Tag) {
+        if let Some(share) =
+            self.shares.iter_mut().flatten().find(|s| {
+                s.listener == listener && !s.primary && (s.source, s.tag) == (source, tag)
+            })
+        {
+            share.primary = true;
+        }
+    }
+
+    /// Forget the message `listener` lost before it was received, consumers miss it
+    pub(crate) fn dropped(&mut self, listener: ListenerHandle, source: Eid, tag: Tag) {
+        if let Some(slot) = self.shares.iter_mut().find(|s| {
+            s.is_some_and(|s| {
+                s.listener == listener && s.primary && (s.source, s.tag) == (source, tag)
+            })
+        }) {
+            *slot = None;
+            self.missed = self.missed.wrapping_add(1);
+        }
+    }
+
+    /// Take the oldest message due for `consumer`
+    ///
+    /// Returns its source and tag, and whether other readers are still due for it.
+    pub(crate) fn next(&mut self, consumer: ConsumerHandle) -> Option<(Eid, Tag, bool)> {
+        let bit = 1 << consumer.index;
+        let slot = self
+            .shares
+            .iter_mut()
+            .filter(|s| s.is_some_and(|s| s.listener == consumer.listener && s.pending & bit != 0))
+            .min_by_key(|s| s.map(|s| s.seq))?;
+        let share = slot.as_mut()?;
+        share.pending &= !bit;
+        let (source, tag, released) = (share.source, share.tag, share.released());
+        if released {
+            *slot = None;
+        }
+        Some((source, tag, !released))
+    }
+
+    /// Forget shared messages the stack has dropped by now
+    pub(crate) fn expire(&mut self, now_millis: u64) {
+        for slot in self.shares.iter_mut() {
+            if slot.is_some_and(|s| {
+                now_millis.saturating_sub(s.since_millis) > REASSEMBLY_TIMEOUT_MILLIS
+            }) {
+                *slot = None;
+                self.missed = self.missed.wrapping_add(1);
+            }
+        }
+    }
+}
@@ -0,0 +1,745 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! MCTP control messages (DSP0236)
+//!
+//! Control messages are sent with MCTP message type [MSG_TYPE_CONTROL]. Each one starts with a
+//! [ControlHeader]; responses continue with a completion code.
+//!
+//! The application owns the control listener and handles most commands itself.
+//! Commands answered from router state can be passed to
+//! [control_response()](crate::GenericRouter::control_response).
+//! Common commands are sent to other endpoints with a [ControlRequester].
+//! Applications with their own control handling encode and decode messages with [codec].
+
+use core::fmt;
+
+use mctp::{Eid, Error, MsgIC, MsgType, Result};
+
+use crate::shared::{SharedRequest, SharedRouter};
+use crate::{Clock, HandleTables, Hooks, RouterError, Sender};
+
+pub mod codec;
+
+use codec::Payload;
+
+/// MCTP message type of control messages
+pub const MSG_TYPE_CONTROL: MsgType = MsgType(0x00);
+/// MCTP message type of messages defined by a PCI vendor
+pub const MSG_TYPE_VENDOR_PCI: MsgType = MsgType(0x7e);
+/// MCTP message type of messages defined by an IANA enterprise
+pub const MSG_TYPE_VENDOR_IANA: MsgType = MsgType(0x7f);
+
+/// Length of the control message header
+pub const CONTROL_HEADER_LEN: usize = 2;
+
+/// Set Endpoint ID command code
+pub const CMD_SET_ENDPOINT_ID: u8 = 0x01;
+/// Get Endpoint ID command code
+pub const CMD_GET_ENDPOINT_ID: u8 = 0x02;
+/// Get Endpoint UUID command code
+pub const CMD_GET_ENDPOINT_UUID: u8 = 0x03;
+/// Get MCTP Version Support command code
+pub const CMD_GET_VERSION_SUPPORT: u8 = 0x04;
+/// Get Message Type Support command code
+pub const CMD_GET_MESSAGE_TYPE_SUPPORT: u8 = 0x05;
+/// Get Vendor Defined Message Support command code
+pub const CMD_GET_VENDOR_MESSAGE_SUPPORT: u8 = 0x06;
+/// Discovery Notify command code
+pub const CMD_DISCOVERY_NOTIFY: u8 = 0x0d;
+/// Get Network ID command code
+pub const CMD_GET_NETWORK_ID: u8 = 0x0e;
+
+/// Completion code of successful commands
+pub const CC_SUCCESS: u8 = 0x00;
+/// Completion code of failed commands
+pub const CC_ERROR: u8 = 0x01;
+/// Completion code of requests with invalid data
+pub const CC_ERROR_INVALID_DATA: u8 = 0x02;
+/// Completion code of requests with an invalid length
+pub const CC_ERROR_INVALID_LENGTH: u8 = 0x03;
+/// Completion code of endpoints not ready to handle the command
+pub const CC_ERROR_NOT_READY: u8 = 0x04;
+/// Completion code of commands the endpoint does not support
+pub const CC_ERROR_UNSUPPORTED_CMD: u8 = 0x05;
+/// Completion code of Get MCTP Version Support for message types the endpoint does not
+/// support
+pub const CC_UNSUPPORTED_MSG_TYPE: u8 = 0x80;
+
+/// Set Endpoint ID response status: EID assignment accepted
+pub(crate) const SET_EID_ACCEPTED: u8 = 0x00;
+/// Set Endpoint ID response status: EID assignment rejected
+pub(crate) const SET_EID_REJECTED: u8 = 0x10;
+
+/// Vendor ID set selector of Get Vendor Defined Message Support responses without further sets
+pub const VENDOR_SELECTOR_END: u8 = 0xff;
+
+/// Vendor ID format of PCI vendor IDs
+pub(crate) const VENDOR_FORMAT_PCI: u8 = 0x00;
+/// Vendor ID format of IANA enterprise numbers
+pub(crate) const VENDOR_FORMAT_IANA: u8 = 0x01;
+
+/// Maximum number of message types decoded from a Get Message Type Support response
+pub const MAX_MESSAGE_TYPES: usize = 32;
+
+/// Size of the buffer responses are received into
+const RESPONSE_BUF_LEN: usize = CONTROL_HEADER_LEN + 2 + MAX_MESSAGE_TYPES;
+
+const FLAG_REQUEST: u8 = 0x80;
+const FLAG_DATAGRAM: u8 = 0x40;
+const INSTANCE_MASK: u8 = 0x1f;
+
+/// The header of a control message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+pub struct ControlHeader {
+    /// Request (or datagram) rather than response
+    pub request: bool,
+    /// Unacknowledged request
+    pub datagram: bool,
+    /// Instance ID, 0 to 31
+    pub instance_id: u8,
+    /// Command code
+    pub command: u8,
+}
+
+impl ControlHeader {
+    /// Parse the header at the start of `msg`
+    ///
+    /// Returns `None` if `msg` is too short.
+    pub fn parse(msg: &[u8]) -> Option<Self> {
+        let &[flags, command, ..] = msg else {
+            return None;
+        };
+        Some(ControlHeader {
+            request: flags & FLAG_REQUEST != 0,
+            datagram: flags & FLAG_DATAGRAM != 0,
+            instance_id: flags & INSTANCE_MASK,
+            command,
+        })
+    }
+
+    /// Encode the header
+    pub fn to_bytes(self) -> [u8; CONTROL_HEADER_LEN] {
+        let mut flags = self.instance_id & INSTANCE_MASK;
+        if self.request {
+            flags |= FLAG_REQUEST;
+        }
+        if self.datagram {
+            flags |= FLAG_DATAGRAM;
+        }
+        [flags, self.command]
+    }
+
+    /// Get the header of the response to this request
+    pub fn response(&self) -> Self {
+        ControlHeader {
+            request: false,
+            datagram: false,
+            ..*self
+        }
+    }
+}
+
+/// Operation requested by a Set Endpoint ID command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetEidOperation {
+    /// Assign the EID, the endpoint may reject it
+    Set,
+    /// Assign the EID regardless of a previous assignment
+    Force,
+    /// Revert to the static EID of the endpoint
+    Reset,
+    /// Mark the endpoint as discovered, keeping its EID
+    SetDiscovered,
+}
+
+impl SetEidOperation {
+    /// Decode the operation field of the request
+    pub fn from_bits(bits: u8) -> Self {
+        match bits & 0x03 {
+            0 => SetEidOperation::Set,
+            1 => SetEidOperation::Force,
+            2 => SetEidOperation::Reset,
+            _ => SetEidOperation::SetDiscovered,
+        }
+    }
+
+    /// Encode the operation field of the request
+    pub fn to_bits(self) -> u8 {
+        match self {
+            SetEidOperation::Set => 0,
+            SetEidOperation::Force => 1,
+            SetEidOperation::Reset => 2,
+            SetEidOperation::SetDiscovered => 3,
+        }
+    }
+}
+
+/// How an endpoint gets its EID, reported by Get Endpoint ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum EidType {
+    /// The EID is assigned by the bus owner
+    #[default]
+    Dynamic,
+    /// The EID is fixed, Set Endpoint ID can't assign a different one
+    Static,
+    /// A static EID is used until the bus owner assigns a different one
+    StaticWithFallback,
+}
+
+impl EidType {
+    /// Decode the EID type field of a Get Endpoint ID response
+    pub fn from_bits(bits: u8) -> Self {
+        match bits & 0x03 {
+            0 => EidType::Dynamic,
+            1 => EidType::Static,
+            _ => EidType::StaticWithFallback,
+        }
+    }
+
+    /// Encode the EID type field of a Get Endpoint ID response
+    ///
+    /// `is_static` tells whether the current EID is the static one, reported for
+    /// [StaticWithFallback](EidType::StaticWithFallback) endpoints.
+    pub fn to_bits(self, is_static: bool) -> u8 {
+        match self {
+            EidType::Dynamic => 0,
+            EidType::Static => 1,
+            EidType::StaticWithFallback if is_static => 2,
+            EidType::StaticWithFallback => 3,
+        }
+    }
+}
+
+/// The role of an endpoint, reported by Get Endpoint ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum EndpointType {
+    /// An endpoint that is neither bus owner nor bridge
+    #[default]
+    Simple,
+    /// A bus owner or bridge
+    BusOwnerOrBridge,
+}
+
+impl EndpointType {
+    /// Decode the endpoint type field of a Get Endpoint ID response
+    pub fn from_bits(bits: u8) -> Self {
+        match bits & 0x30 {
+            0 => EndpointType::Simple,
+            _ => EndpointType::BusOwnerOrBridge,
+        }
+    }
+
+    /// Encode the endpoint type field of a Get Endpoint ID response
+    pub fn to_bits(self) -> u8 {
+        match self {
+            EndpointType::Simple => 0,
+            EndpointType::BusOwnerOrBridge => 0x10,
+        }
+    }
+}
+
+/// A Set Endpoint ID request, see [Hooks::set_eid_request()](crate::Hooks::set_eid_request)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetEidRequest {
+    /// EID of the requester
+    pub source: Eid,
+    /// Requested operation
+    pub operation: SetEidOperation,
+    /// EID to assign, only meaningful for [Set](SetEidOperation::Set) and
+    /// [Force](SetEidOperation::Force)
+    pub eid: Eid,
+    /// Current EID of the endpoint
+    pub current: Eid,
+    /// Requester of the last accepted assignment, taken as the bus owner
+    pub bus_owner: Option<Eid>,
+}
+
+impl SetEidRequest {
+    /// Check if the request comes from the bus owner
+    ///
+    /// True if no assignment was accepted yet or the last one came from the same requester.
+    pub fn from_bus_owner(&self) -> bool {
+        self.bus_owner.is_none_or(|owner| owner == self.source)
+    }
+}
+
+/// Decision of the application on a [SetEidRequest]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetEidDecision {
+    /// Leave the request to the application,
+    /// [control_response()](crate::GenericRouter::control_response) returns `Ok(None)`
+    Ignore,
+    /// Apply the requested operation
+    Accept,
+    /// Keep the current EID and report the assignment as rejected
+    Reject,
+}
+
+/// The vendor of a vendor defined message type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum VendorId {
+    /// A PCI vendor ID, for the PCI vendor defined message type
+    Pci(u16),
+    /// An IANA enterprise number, for the IANA vendor defined message type
+    Iana(u32),
+}
+
+impl VendorId {
+    /// Get the vendor defined message type of the vendor
+    pub fn msg_type(&self) -> MsgType {
+        match self {
+            VendorId::Pci(_) => MSG_TYPE_VENDOR_PCI,
+            VendorId::Iana(_) => MSG_TYPE_VENDOR_IANA,
+        }
+    }
+}
+
+/// A vendor command set, reported by Get Vendor Defined Message Support
+///
+/// Registered with [add_vendor_support()](crate::GenericRouter::add_vendor_support).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct VendorSupport {
+    /// Vendor defining the commands
+    pub vendor: VendorId,
+    /// Vendor specific command set type or version
+    pub command_set: u16,
+}
+
+/// Message types supported by an endpoint, see [ControlResponse::MessageTypes]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageTypes {
+    types: [MsgType; MAX_MESSAGE_TYPES],
+    len: usize,
+}
+
+impl Default for MessageTypes {
+    /// No message types besides the control message type
+    fn default() -> Self {
+        MessageTypes {
+            types: [MsgType(0); MAX_MESSAGE_TYPES],
+            len: 0,
+        }
+    }
+}
+
+impl MessageTypes {
+    /// Report `types`, excluding the control message type
+    ///
+    /// Returns [NoSpace](Error::NoSpace) for more than [MAX_MESSAGE_TYPES] types.
+    pub fn new(types: &[MsgType]) -> Result<Self> {
+        let mut message_types = MessageTypes {
+            types: [MsgType(0); MAX_MESSAGE_TYPES],
+            len: types.len(),
+        };
+        message_types
+            .types
+            .get_mut(..types.len())
+            .ok_or(Error::NoSpace)?
+            .copy_from_slice(types);
+        Ok(message_types)
+    }
+
+    /// Get the supported message types, excluding the control message type
+    pub fn as_slice(&self) -> &[MsgType] {
+        self.types.get(..self.len).unwrap_or_default()
+    }
+}
+
+/// A decoded response to a request sent by a [ControlRequester]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlResponse {
+    /// Response to Get Endpoint ID
+    EndpointId {
+        /// EID of the endpoint
+        eid: Eid,
+        /// Endpoint type and EID type, see [EndpointType::from_bits()] and
+        /// [EidType::from_bits()]
+        eid_type: u8,
+        /// Medium-specific information
+        medium_specific: u8,
+    },
+    /// Response to Set Endpoint ID
+    SetEndpointId {
+        /// The assignment was accepted
+        accepted: bool,
+        /// EID of the endpoint after the request
+        eid: Eid,
+        /// Size of the dynamic EID pool of the endpoint
+        pool_size: u8,
+    },
+    /// Response to Get Endpoint UUID
+    Uuid([u8; 16]),
+    /// Response to Get Message Type Support
+    MessageTypes(MessageTypes),
+    /// Response to Get MCTP Version Support
+    Versions(codec::GetVersionSupportResponse),
+    /// Response to Get Vendor Defined Message Support
+    VendorSupport {
+        /// Selector of the next vendor ID set, `None` for the last set
+        next_selector: Option<u8>,
+        /// The vendor ID set of the queried selector
+        support: VendorSupport,
+    },
+    /// The command failed with this completion code
+    Failed(u8),
+}
+
+impl ControlResponse {
+    /// Decode the response to `command`, `body` follows the [ControlHeader]
+    ///
+    /// Returns [InvalidInput](Error::InvalidInput) if `body` is too short or `command` unknown.
+    pub fn decode(command: u8, body: &[u8]) -> Result<Self> {
+        let (&cc, data) = body.split_first().ok_or(Error::InvalidInput)?;
+        if cc != CC_SUCCESS {
+            return Ok(ControlResponse::Failed(cc));
+        }
+        match command {
+            CMD_GET_ENDPOINT_ID => {
+                let r = codec::GetEndpointIdResponse::decode(data)?;
+                Ok(ControlResponse::EndpointId {
+                    eid: r.eid,
+                    eid_type: r.eid_type,
+                    medium_specific: r.medium_specific,
+                })
+            }
+            CMD_SET_ENDPOINT_ID => {
+                let r = codec::SetEndpointIdResponse::decode(data)?;
+                Ok(ControlResponse::SetEndpointId {
+                    accepted: r.accepted,
+                    eid: r.eid,
+                    pool_size: r.pool_size,
+                })
+            }
+            CMD_GET_ENDPOINT_UUID => {
+                let r = codec::GetEndpointUuidResponse::decode(data)?;
+                Ok(ControlResponse::Uuid(r.uuid))
+            }
+            CMD_GET_MESSAGE_TYPE_SUPPORT => {
+                let r = codec::GetMessageTypeSupportResponse::decode(data)?;
+                Ok(ControlResponse::MessageTypes(r.types))
+            }
+            CMD_GET_VERSION_SUPPORT => Ok(ControlResponse::Versions(
+                codec::GetVersionSupportResponse::decode(data)?,
+            )),
+            CMD_GET_VENDOR_MESSAGE_SUPPORT => {
+                let r = codec::GetVendorMessageSupportResponse::decode(data)?;
+                Ok(ControlResponse::VendorSupport {
+                    next_selector: r.next_selector,
+                    support: r.support,
+                })
+            }
+            _ => Err(Error::InvalidInput),
+        }
+    }
+}
+
+/// A command a peer answered with a completion code other than [CC_SUCCESS]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CompletionFailure {
+    /// Command code of the request
+    pub command: u8,
+    /// Completion code of the response, e.g. [CC_ERROR_INVALID_DATA]
+    pub code: u8,
+}
+
+/// Result type of [ControlRequester::receive()]
+pub type ControlResult<T> = core::result::Result<T, ControlError>;
+
+/// An error of a [ControlRequester]
+///
+/// Converts from and into a plain [mctp::Error], so `?` keeps working in functions returning
+/// [mctp::Result]. Failed commands convert into [RxFailure](Error::RxFailure).
+#[derive(Debug)]
+pub enum ControlError {
+    /// The peer answered the request with a non-success completion code
+    Failed(CompletionFailure),
+    /// Sending, receiving or decoding failed
+    Mctp(Error),
+}
+
+impl ControlError {
+    /// Get the completion code of a failed command
+    pub fn completion_code(&self) -> Option<u8> {
+        match self {
+            ControlError::Failed(failure) => Some(failure.code),
+            ControlError::Mctp(_) => None,
+        }
+    }
+}
+
+impl From<Error> for ControlError {
+    fn from(error: Error) -> Self {
+        ControlError::Mctp(error)
+    }
+}
+
+impl From<RouterError> for ControlError {
+    fn from(error: RouterError) -> Self {
+        ControlError::Mctp(error.into_inner())
+    }
+}
+
+impl From<ControlError> for Error {
+    fn from(error: ControlError) -> Self {
+        match error {
+            ControlError::Failed(_) => Error::RxFailure,
+            ControlError::Mctp(e) => e,
+        }
+    }
+}
+
+impl fmt::Display for ControlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlError::Failed(failure) => write!(
+                f,
+                "command {:#04x} failed with completion code {:#04x}",
+                failure.command, failure.code
+            ),
+            ControlError::Mctp(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ControlError {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            ControlError::Failed(failure) => defmt::write!(f, "Failed({})", failure),
+            ControlError::Mctp(e) => defmt::write!(f, "Mctp({})", crate::defmt_util::FmtError(e)),
+        }
+    }
+}
+
+impl core::error::Error for ControlError {}
+
+/// Counters of the responses received by a [ControlRequester]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ControlStats {
+    /// Responses matching the outstanding request
+    pub responses: usize,
+    /// Responses with a non-success completion code
+    pub failures: usize,
+    /// Responses with [CC_ERROR_INVALID_DATA]
+    pub invalid_data: usize,
+    /// Responses with [CC_ERROR_UNSUPPORTED_CMD]
+    pub unsupported: usize,
+    /// The most recent failed command
+    pub last_failure: Option<CompletionFailure>,
+}
+
+/// A requester for common control commands
+///
+/// Encodes requests, allocates instance IDs with
+/// [alloc_instance_id()](crate::GenericRouter::alloc_instance_id) and decodes the responses.
+/// Responses are recorded in the [topology()](crate::GenericRouter::topology) of `router`.
+/// Requests go out through an internal request handle on `router`, which is
+/// [retargeted](crate::GenericRouter::retarget) when a request is sent to a different peer.
+/// Only one request is outstanding at a time, responses with a different instance ID or
+/// command (e.g. late responses to an abandoned request) are discarded.
+/// Responses with a non-success completion code are returned as [ControlError::Failed] and
+/// counted in [stats()](Self::stats).
+#[derive(Debug)]
+pub struct ControlRequester<'r, S: Sender, T: HandleTables, H: Hooks, C: Clock> {
+    router: &'r SharedRouter<S, T, H, C>,
+    channel: Option<(Eid, SharedRequest<'r, S, T, H, C>)>,
+    outstanding: Option<ControlHeader>,
+    stats: ControlStats,
+}
+
+impl<'r, S: Sender, T: HandleTables, H: Hooks, C: Clock> ControlRequester<'r, S, T, H, C> {
+    /// Send control requests through `router`
+    pub fn new(router: &'r SharedRouter<S, T, H, C>) -> Self {
+        ControlRequester {
+            router,
+            channel: None,
+            outstanding: None,
+            stats: ControlStats::default(),
+        }
+    }
+
+    /// Get the counters of the responses received so far
+    pub fn stats(&self) -> ControlStats {
+        self.stats
+    }
+
+    /// Send Get Endpoint ID to `peer`
+    pub fn get_eid(&mut self, peer: Eid) -> Result<ControlHeader> {
+        self.send(peer, CMD_GET_ENDPOINT_ID, &[])
+    }
+
+    /// Send Set Endpoint ID to `peer`, assigning `eid` with `operation`
+    ///
+    /// Endpoints answering from the newly assigned EID aren't matched, since the response
+    /// has to come from `peer`.
+    pub fn set_eid(
+        &mut self,
+        peer: Eid,
+        operation: SetEidOperation,
+        eid: Eid,
+    ) -> Result<ControlHeader> {
+        self.send(peer, CMD_SET_ENDPOINT_ID, &[operation.to_bits(), eid.0])
+    }
+
+    /// Send Get Endpoint UUID to `peer`
+    pub fn get_uuid(&mut self, peer: Eid) -> Result<ControlHeader> {
+        self.send(peer, CMD_GET_ENDPOINT_UUID, &[])
+    }
+
+    /// Send Get Message Type Support to `peer`
+    pub fn get_message_types(&mut self, peer: Eid) -> Result<ControlHeader> {
+        self.send(peer, CMD_GET_MESSAGE_TYPE_SUPPORT, &[])
+    }
+
+    /// Send Get MCTP Version Support to `peer`, querying `message_type`
+    ///
+    /// `message_type` is [VERSION_BASE_SPEC](crate::VERSION_BASE_SPEC) for the base
+    /// specification.
+    pub fn get_versions(&mut self, peer: Eid, message_type: u8) -> Result<ControlHeader> {
+        self.send(peer, CMD_GET_VERSION_SUPPORT, &[message_type])
+    }
+
+    /// Send Get Vendor Defined Message Support to `peer`, querying the vendor ID set `selector`
+    ///
+    /// Sets are numbered from 0, the response tells the selector of the next set.
+    pub fn get_vendor_support(&mut self, peer: Eid, selector: u8) -> Result<ControlHeader> {
+        self.send(peer, CMD_GET_VENDOR_MESSAGE_SUPPORT, &[selector])
+    }
+
+    /// Receive the response to the outstanding request without blocking
+    ///
+    /// Returns the peer and the decoded response, `Ok(None)` when no matching response is
+    /// available. A response with a non-success completion code is returned as
+    /// [ControlError::Failed] with the code and the command of the request.
+    pub fn receive(&mut self) -> ControlResult<Option<(Eid, ControlResponse)>> {
+        let (Some(outstanding), Some((peer, channel))) = (self.outstanding, self.channel) else {
+            return Err(Error::BadArgument.into());
+        };
+        let mut buf = [0; RESPONSE_BUF_LEN];
+        while let Some(info) = channel.try_recv(&mut buf)? {
+            let msg = buf.get(..info.len).unwrap_or_default();
+            match ControlHeader::parse(msg) {
+                Some(header)
+                    if info.typ == MSG_TYPE_CONTROL && header == outstanding.response() =>
+                {
+                    self.release()?;
+                    let body = msg.get(CONTROL_HEADER_LEN..).unwrap_or_default();
+                    let response = ControlResponse::decode(header.command, body)?;
+                    self.stats.responses += 1;
+                    if let ControlResponse::Failed(code) = response {
+                        let failure = CompletionFailure {
+                            command: header.command,
+                            code,
+                        };
+                        self.stats.failures += 1;
+                        match code {
+                            CC_ERROR_INVALID_DATA => self.stats.invalid_data += 1,
+                            CC_ERROR_UNSUPPORTED_CMD => self.stats.unsupported += 1,
+                            _ => (),
+                        }
+                        self.stats.last_failure = Some(failure);
+                        return Err(ControlError::Failed(failure));
+                    }
+                    if self
+                        .router
+                        .with(|r| r.learn_control_response(peer, &response))?
+                        .is_err()
+                    {
+                        debug!("topology full, {} not recorded", peer.0);
+                    }
+                    return Ok(Some((peer, response)));
+                }
+                _ => debug!(
+                    "discarded unexpected control response from {}",
+                    info.source.0
+                ),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Release the internal request handle
+    pub fn unbind(mut self) -> Result<()> {
+        self.release()?;
+        if let Some((_, channel)) = self.channel {
+            channel.unbind()?;
+        }
+        Ok(())
+    }
+
+    /// Send a request for `command` to `peer`, with `payload` following the header
+    ///
+    /// A previously outstanding request is abandoned.
+    fn send(&mut self, peer: Eid, command: u8, payload: &[u8]) -> Result<ControlHeader> {
+        self.release()?;
+        let channel = *self.channel(peer)?;
+        let header = ControlHeader {
+            request: true,
+            datagram: false,
+            instance_id: self.router.with(|r| r.alloc_instance_id(peer))??,
+            command,
+        };
+        let sent = channel.send(
+            MSG_TYPE_CONTROL,
+            MsgIC(false),
+            &[&header.to_bytes(), payload],
+        );
+        if sent.is_err() {
+            self.router
+                .with(|r| r.release_instance_id(peer, header.instance_id))?;
+        }
+        sent?;
+        self.outstanding = Some(header);
+        Ok(header)
+    }
+
+    /// Release the instance ID of the outstanding request
+    fn release(&mut self) -> Result<()> {
+        if let (Some(header), Some((peer, _))) = (self.outstanding.take(), &self.channel) {
+            let peer = *peer;
+            self.router
+                .with(|r| r.release_instance_id(peer, header.instance_id))?;
+        }
+        Ok(())
+    }
+
+    /// Get the request handle for `peer`, retargeting it from the previous peer
+    fn channel(&mut self, peer: Eid) -> Result<&SharedRequest<'r, S, T, H, C>> {
+        match self.channel {
+            Some((eid, _)) if eid == peer => (),
+            Some((_, channel)) => {
+                self.release()?;
+                channel.retarget(peer)?;
+                self.channel = Some((peer, channel));
+            }
+            None => self.channel = Some((peer, self.router.req(peer)?)),
+        }
+        self.channel
+            .as_ref()
+            .map(|(_, channel)| channel)
+            .ok_or(Error::InternalError)
+    }
+}
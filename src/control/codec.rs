@@ -0,0 +1,564 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed encoding and decoding of control messages
+//!
+//! Each baseline command has a request and a response type implementing [Payload], the data
+//! following the [ControlHeader] (and the completion code of responses).
+//! [encode_request()], [encode_response()] and the matching decoders take care of the header
+//! and completion code, for applications handling control messages themselves:
+//!
+//! ```
+//! use mctp::Eid;
+//! use mctp_lib::control::codec::{self, GetEndpointIdRequest, GetEndpointIdResponse};
+//!
+//! let mut request = [0; 8];
+//! let len = codec::encode_request(3, &GetEndpointIdRequest, &mut request).unwrap();
+//! let (header, GetEndpointIdRequest) = codec::decode_request(&request[..len]).unwrap();
+//!
+//! let mut response = [0; 8];
+//! let eid = GetEndpointIdResponse { eid: Eid(8), eid_type: 0, medium_specific: 0 };
+//! let len = codec::encode_response(header, &eid, &mut response).unwrap();
+//! let (_, decoded) = codec::decode_response::<GetEndpointIdResponse>(&response[..len]).unwrap();
+//! assert_eq!(decoded, Ok(eid));
+//! ```
+
+use mctp::{Eid, Error, MsgType, Result};
+
+use super::{
+    CC_ERROR, CC_ERROR_INVALID_DATA, CC_ERROR_INVALID_LENGTH, CC_ERROR_NOT_READY,
+    CC_ERROR_UNSUPPORTED_CMD, CC_SUCCESS, CMD_GET_ENDPOINT_ID, CMD_GET_ENDPOINT_UUID,
+    CMD_GET_MESSAGE_TYPE_SUPPORT, CMD_GET_NETWORK_ID, CMD_GET_VENDOR_MESSAGE_SUPPORT,
+    CMD_GET_VERSION_SUPPORT, CMD_SET_ENDPOINT_ID, CONTROL_HEADER_LEN, ControlHeader,
+    MAX_MESSAGE_TYPES, MessageTypes, SET_EID_ACCEPTED, SET_EID_REJECTED, SetEidOperation,
+    VENDOR_FORMAT_IANA, VENDOR_FORMAT_PCI, VENDOR_SELECTOR_END, VendorId, VendorSupport,
+};
+
+/// Maximum number of versions decoded from a Get MCTP Version Support response
+pub const MAX_VERSIONS: usize = 8;
+
+/// Completion code of a control response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionCode {
+    /// [CC_SUCCESS]
+    Success,
+    /// [CC_ERROR]
+    Error,
+    /// [CC_ERROR_INVALID_DATA]
+    InvalidData,
+    /// [CC_ERROR_INVALID_LENGTH]
+    InvalidLength,
+    /// [CC_ERROR_NOT_READY]
+    NotReady,
+    /// [CC_ERROR_UNSUPPORTED_CMD]
+    UnsupportedCmd,
+    /// Any other, e.g. command-specific code
+    Other(u8),
+}
+
+impl From<u8> for CompletionCode {
+    fn from(cc: u8) -> Self {
+        match cc {
+            CC_SUCCESS => CompletionCode::Success,
+            CC_ERROR => CompletionCode::Error,
+            CC_ERROR_INVALID_DATA => CompletionCode::InvalidData,
+            CC_ERROR_INVALID_LENGTH => CompletionCode::InvalidLength,
+            CC_ERROR_NOT_READY => CompletionCode::NotReady,
+            CC_ERROR_UNSUPPORTED_CMD => CompletionCode::UnsupportedCmd,
+            cc => CompletionCode::Other(cc),
+        }
+    }
+}
+
+impl From<CompletionCode> for u8 {
+    fn from(cc: CompletionCode) -> Self {
+        match cc {
+            CompletionCode::Success => CC_SUCCESS,
+            CompletionCode::Error => CC_ERROR,
+            CompletionCode::InvalidData => CC_ERROR_INVALID_DATA,
+            CompletionCode::InvalidLength => CC_ERROR_INVALID_LENGTH,
+            CompletionCode::NotReady => CC_ERROR_NOT_READY,
+            CompletionCode::UnsupportedCmd => CC_ERROR_UNSUPPORTED_CMD,
+            CompletionCode::Other(cc) => cc,
+        }
+    }
+}
+
+/// The data of a control request or response of a single command
+pub trait Payload: Sized {
+    /// Command code
+    const COMMAND: u8;
+
+    /// Encode the payload to `out`, returning its length
+    ///
+    /// Returns [NoSpace](Error::NoSpace) if `out` is too small.
+    fn encode(&self, out: &mut [u8]) -> Result<usize>;
+
+    /// Decode the payload from `data`
+    ///
+    /// Returns [InvalidInput](Error::InvalidInput) if `data` is too short or malformed.
+    fn decode(data: &[u8]) -> Result<Self>;
+}
+
+/// Encode a request with `instance_id` and `payload` to `out`, returning its length
+///
+/// Returns [NoSpace](Error::NoSpace) if `out` is too small.
+pub fn encode_request<P: Payload>(instance_id: u8, payload: &P, out: &mut [u8]) -> Result<usize> {
+    let header = ControlHeader {
+        request: true,
+        datagram: false,
+        instance_id,
+        command: P::COMMAND,
+    };
+    let (hdr, rest) = out
+        .split_first_chunk_mut::<CONTROL_HEADER_LEN>()
+        .ok_or(Error::NoSpace)?;
+    *hdr = header.to_bytes();
+    Ok(CONTROL_HEADER_LEN + payload.encode(rest)?)
+}
+
+/// Decode a request for the command of `P`
+///
+/// Returns [InvalidInput](Error::InvalidInput) for responses, other commands and malformed
+/// payloads.
+pub fn decode_request<P: Payload>(msg: &[u8]) -> Result<(ControlHeader, P)> {
+    let header = ControlHeader::parse(msg).ok_or(Error::InvalidInput)?;
+    if !header.request || header.command != P::COMMAND {
+        return Err(Error::InvalidInput);
+    }
+    let data = msg.get(CONTROL_HEADER_LEN..).unwrap_or_default();
+    Ok((header, P::decode(data)?))
+}
+
+/// Encode the successful response to `request` with `payload` to `out`, returning its length
+///
+/// Returns [NoSpace](Error::NoSpace) if `out` is too small.
+pub fn encode_response<P: Payload>(
+    request: ControlHeader,
+    payload: &P,
+    out: &mut [u8],
+) -> Result<usize> {
+    let len = encode_error(request, CompletionCode::Success, out)?;
+    let rest = out.get_mut(len..).ok_or(Error::NoSpace)?;
+    Ok(len + payload.encode(rest)?)
+}
+
+/// Encode a response to `request` consisting of the completion code `cc` only
+///
+/// Returns [NoSpace](Error::NoSpace) if `out` is too small.
+pub fn encode_error(request: ControlHeader, cc: CompletionCode, out: &mut [u8]) -> Result<usize> {
+    let (hdr, rest) = out
+        .split_first_chunk_mut::<CONTROL_HEADER_LEN>()
+        .ok_or(Error::NoSpace)?;
+    *hdr = request.response().to_bytes();
+    *rest.first_mut().ok_or(Error::NoSpace)? = cc.into();
+    Ok(CONTROL_HEADER_LEN + 1)
+}
+
+/// Decode a response for the command of `P`
+///
+/// Returns the payload of successful responses, the completion code of failed ones.
+/// Returns [InvalidInput](Error::InvalidInput) for requests, other commands and malformed
+/// payloads.
+pub fn decode_response<P: Payload>(
+    msg: &[u8],
+) -> Result<(ControlHeader, core::result::Result<P, CompletionCode>)> {
+    let header = ControlHeader::parse(msg).ok_or(Error::InvalidInput)?;
+    if header.request || header.command != P::COMMAND {
+        return Err(Error::InvalidInput);
+    }
+    let body = msg.get(CONTROL_HEADER_LEN..).unwrap_or_default();
+    let (&cc, data) = body.split_first().ok_or(Error::InvalidInput)?;
+    match CompletionCode::from(cc) {
+        CompletionCode::Success => Ok((header, Ok(P::decode(data)?))),
+        cc => Ok((header, Err(cc))),
+    }
+}
+
+/// Copy `data` to the start of `out`, returning its length
+fn put(out: &mut [u8], data: &[u8]) -> Result<usize> {
+    out.get_mut(..data.len())
+        .ok_or(Error::NoSpace)?
+        .copy_from_slice(data);
+    Ok(data.len())
+}
+
+/// Declare a request without data
+macro_rules! empty_request {
+    ($(#[$doc:meta])* $name:ident, $command:expr) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct $name;
+
+        impl Payload for $name {
+            const COMMAND: u8 = $command;
+
+            fn encode(&self, _out: &mut [u8]) -> Result<usize> {
+                Ok(0)
+            }
+
+            fn decode(_data: &[u8]) -> Result<Self> {
+                Ok($name)
+            }
+        }
+    };
+}
+
+/// Set Endpoint ID request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetEndpointIdRequest {
+    /// Requested operation
+    pub operation: SetEidOperation,
+    /// EID to assign
+    pub eid: Eid,
+}
+
+impl Payload for SetEndpointIdRequest {
+    const COMMAND: u8 = CMD_SET_ENDPOINT_ID;
+
+    fn encode(&self, out: &mut [u8]) -> Result<usize> {
+        put(out, &[self.operation.to_bits(), self.eid.0])
+    }
+
+    fn decode(data: &[u8]) -> Result<Self> {
+        let &[operation, eid, ..] = data else {
+            return Err(Error::InvalidInput);
+        };
+        Ok(SetEndpointIdRequest {
+            operation: SetEidOperation::from_bits(operation),
+            eid: Eid(eid),
+        })
+    }
+}
+
+/// Set Endpoint ID response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetEndpointIdResponse {
+    /// The assignment was accepted
+    pub accepted: bool,
+    /// EID of the endpoint after the request
+    pub eid: Eid,
+    /// Size of the dynamic EID pool of the endpoint
+    pub pool_size: u8,
+}
+
+impl Payload for SetEndpointIdResponse {
+    const COMMAND: u8 = CMD_SET_ENDPOINT_ID;
+
+    fn encode(&self, out: &mut [u8]) -> Result<usize> {
+        let status = if self.accepted {
+            SET_EID_ACCEPTED
+        } else {
+            SET_EID_REJECTED
+        };
+        put(out, &[status, self.eid.0, self.pool_size])
+    }
+
+    fn decode(data: &[u8]) -> Result<Self> {
+        let &[status, eid, pool_size, ..] = data else {
+            return Err(Error::InvalidInput);
+        };
+        Ok(SetEndpointIdResponse {
+            accepted: status & 0x30 == SET_EID_ACCEPTED,
+            eid: Eid(eid),
+            pool_size,
+        })
+    }
+}
+
+empty_request!(
+    /// Get Endpoint ID request
+    GetEndpointIdRequest,
+    CMD_GET_ENDPOINT_ID
+);
+
+/// Get Endpoint ID response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GetEndpointIdResponse {
+    /// EID of the endpoint
+    pub eid: Eid,
+    /// Endpoint type and EID type
+    pub eid_type: u8,
+    /// Medium-specific information
+    pub medium_specific: u8,
+}
+
+impl Payload for GetEndpointIdResponse {
+    const COMMAND: u8 = CMD_GET_ENDPOINT_ID;
+
+    fn encode(&self, out: &mut [u8]) -> Result<usize> {
+        put(out, &[self.eid.0, self.eid_type, self.medium_specific])
+    }
+
+    fn decode(data: &[u8]) -> Result<Self> {
+        let &[eid, eid_type, medium_specific, ..] = data else {
+            return Err(Error::InvalidInput);
+        };
+        Ok(GetEndpointIdResponse {
+            eid: Eid(eid),
+            eid_type,
+            medium_specific,
+        })
+    }
+}
+
+empty_request!(
+    /// Get Endpoint UUID request
+    GetEndpointUuidRequest,
+    CMD_GET_ENDPOINT_UUID
+);
+
+/// Get Endpoint UUID response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GetEndpointUuidResponse {
+    /// UUID of the endpoint
+    pub uuid: [u8; 16],
+}
+
+impl Payload for GetEndpointUuidResponse {
+    const COMMAND: u8 = CMD_GET_ENDPOINT_UUID;
+
+    fn encode(&self, out: &mut [u8]) -> Result<usize> {
+        put(out, &self.uuid)
+    }
+
+    fn decode(data: &[u8]) -> Result<Self> {
+        let uuid = data.first_chunk().ok_or(Error::InvalidInput)?;
+        Ok(GetEndpointUuidResponse { uuid: *uuid })
+    }
+}
+
+/// Get MCTP Version Support request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GetVersionSupportRequest {
+    /// Message type to query, `0xff` for the base specification
+    pub message_type: u8,
+}
+
+impl Payload for GetVersionSupportRequest {
+    const COMMAND: u8 = CMD_GET_VERSION_SUPPORT;
+
+    fn encode(&self, out: &mut [u8]) -> Result<usize> {
+        put(out, &[self.message_type])
+    }
+
+    fn decode(data: &[u8]) -> Result<Self> {
+        let &[message_type, ..] = data else {
+            return Err(Error::InvalidInput);
+        };
+        Ok(GetVersionSupportRequest { message_type })
+    }
+}
+
+/// Get MCTP Version Support response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GetVersionSupportResponse {
+    versions: [[u8; 4]; MAX_VERSIONS],
+    len: usize,
+}
+
+impl GetVersionSupportResponse {
+    /// Report `versions`, each encoded as major, minor, update and alpha byte
+    ///
+    /// Returns [NoSpace](Error::NoSpace) for more than [MAX_VERSIONS] versions.
+    pub fn new(versions: &[[u8; 4]]) -> Result<Self> {
+        let mut response = GetVersionSupportResponse {
+            versions: [[0; 4]; MAX_VERSIONS],
+            len: versions.len(),
+        };
+        response
+            .versions
+            .get_mut(..versions.len())
+            .ok_or(Error::NoSpace)?
+            .copy_from_slice(versions);
+        Ok(response)
+    }
+
+    /// Get the supported versions
+    pub fn versions(&self) -> &[[u8; 4]] {
+        self.versions.get(..self.len).unwrap_or_default()
+    }
+}
+
+impl Payload for GetVersionSupportResponse {
+    const COMMAND: u8 = CMD_GET_VERSION_SUPPORT;
+
+    fn encode(&self, out: &mut [u8]) -> Result<usize> {
+        let mut len = put(out, &[self.len as u8])?;
+        for version in self.versions() {
+            len += put(out.get_mut(len..).ok_or(Error::NoSpace)?, version)?;
+        }
+        Ok(len)
+    }
+
+    fn decode(data: &[u8]) -> Result<Self> {
+        let (&count, mut entries) = data.split_first().ok_or(Error::InvalidInput)?;
+        let mut response = GetVersionSupportResponse {
+            versions: [[0; 4]; MAX_VERSIONS],
+            len: usize::from(count).min(MAX_VERSIONS),
+        };
+        for version in response.versions.iter_mut().take(count.into()) {
+            let (entry, rest) = entries.split_first_chunk().ok_or(Error::InvalidInput)?;
+            *version = *entry;
+            entries = rest;
+        }
+        Ok(response)
+    }
+}
+
+empty_request!(
+    /// Get Message Type Support request
+    GetMessageTypeSupportRequest,
+    CMD_GET_MESSAGE_TYPE_SUPPORT
+);
+
+/// Get Message Type Support response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GetMessageTypeSupportResponse {
+    /// Supported message types, excluding the control message type
+    pub types: MessageTypes,
+}
+
+impl Payload for GetMessageTypeSupportResponse {
+    const COMMAND: u8 = CMD_GET_MESSAGE_TYPE_SUPPORT;
+
+    fn encode(&self, out: &mut [u8]) -> Result<usize> {
+        let types = self.types.as_slice();
+        let mut len = put(out, &[types.len() as u8])?;
+        for typ in types {
+            len += put(out.get_mut(len..).ok_or(Error::NoSpace)?, &[typ.0])?;
+        }
+        Ok(len)
+    }
+
+    fn decode(data: &[u8]) -> Result<Self> {
+        let (&count, list) = data.split_first().ok_or(Error::InvalidInput)?;
+        let list = list.get(..usize::from(count)).ok_or(Error::InvalidInput)?;
+        let mut types = [MsgType(0); MAX_MESSAGE_TYPES];
+        for (t, &typ) in types.iter_mut().zip(list) {
+            *t = MsgType(typ);
+        }
+        let len = list.len().min(MAX_MESSAGE_TYPES);
+        Ok(GetMessageTypeSupportResponse {
+            types: MessageTypes::new(types.get(..len).unwrap_or_default())?,
+        })
+    }
+}
+
+/// Get Vendor Defined Message Support request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GetVendorMessageSupportRequest {
+    /// Vendor ID set to query, starting at 0
+    pub selector: u8,
+}
+
+impl Payload for GetVendorMessageSupportRequest {
+    const COMMAND: u8 = CMD_GET_VENDOR_MESSAGE_SUPPORT;
+
+    fn encode(&self, out: &mut [u8]) -> Result<usize> {
+        put(out, &[self.selector])
+    }
+
+    fn decode(data: &[u8]) -> Result<Self> {
+        let &[selector, ..] = data else {
+            return Err(Error::InvalidInput);
+        };
+        Ok(GetVendorMessageSupportRequest { selector })
+    }
+}
+
+/// Get Vendor Defined Message Support response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GetVendorMessageSupportResponse {
+    /// Selector of the next vendor ID set, `None` for the last set
+    pub next_selector: Option<u8>,
+    /// The vendor ID set of the queried selector
+    pub support: VendorSupport,
+}
+
+impl Payload for GetVendorMessageSupportResponse {
+    const COMMAND: u8 = CMD_GET_VENDOR_MESSAGE_SUPPORT;
+
+    fn encode(&self, out: &mut [u8]) -> Result<usize> {
+        let next = self.next_selector.unwrap_or(VENDOR_SELECTOR_END);
+        let mut len = match self.support.vendor {
+            VendorId::Pci(id) => {
+                let [id0, id1] = id.to_be_bytes();
+                put(out, &[next, VENDOR_FORMAT_PCI, id0, id1])?
+            }
+            VendorId::Iana(id) => {
+                let [id0, id1, id2, id3] = id.to_be_bytes();
+                put(out, &[next, VENDOR_FORMAT_IANA, id0, id1, id2, id3])?
+            }
+        };
+        len += put(
+            out.get_mut(len..).ok_or(Error::NoSpace)?,
+            &self.support.command_set.to_be_bytes(),
+        )?;
+        Ok(len)
+    }
+
+    fn decode(data: &[u8]) -> Result<Self> {
+        let &[next, format, ref rest @ ..] = data else {
+            return Err(Error::InvalidInput);
+        };
+        let (vendor, rest) = match format {
+            VENDOR_FORMAT_PCI => {
+                let (id, rest) = rest.split_first_chunk().ok_or(Error::InvalidInput)?;
+                (VendorId::Pci(u16::from_be_bytes(*id)), rest)
+            }
+            VENDOR_FORMAT_IANA => {
+                let (id, rest) = rest.split_first_chunk().ok_or(Error::InvalidInput)?;
+                (VendorId::Iana(u32::from_be_bytes(*id)), rest)
+            }
+            _ => return Err(Error::InvalidInput),
+        };
+        let command_set = rest.first_chunk().ok_or(Error::InvalidInput)?;
+        Ok(GetVendorMessageSupportResponse {
+            next_selector: (next != VENDOR_SELECTOR_END).then_some(next),
+            support: VendorSupport {
+                vendor,
+                command_set: u16::from_be_bytes(*command_set),
+            },
+        })
+    }
+}
+
+empty_request!(
+    /// Get Network ID request
+    GetNetworkIdRequest,
+    CMD_GET_NETWORK_ID
+);
+
+/// Get Network ID response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GetNetworkIdResponse {
+    /// ID of the network
+    pub network_id: [u8; 16],
+}
+
+impl Payload for GetNetworkIdResponse {
+    const COMMAND: u8 = CMD_GET_NETWORK_ID;
+
+    fn encode(&self, out: &mut [u8]) -> Result<usize> {
+        put(out, &self.network_id)
+    }
+
+    fn decode(data: &[u8]) -> Result<Self> {
+        let network_id = data.first_chunk().ok_or(Error::InvalidInput)?;
+        Ok(GetNetworkIdResponse {
+            network_id: *network_id,
+        })
+    }
+}
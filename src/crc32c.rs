@@ -0,0 +1,64 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! CRC-32C (Castagnoli) used for the message integrity check
+//!
+//! The check covers the message type byte (including the IC bit) and the message body,
+//! it is appended to the message in little endian byte order.
+
+/// Length of the integrity check appended to messages with the IC bit set
+pub(crate) const IC_LEN: usize = 4;
+
+/// Reversed Castagnoli polynomial
+const POLY: u32 = 0x82f6_3b78;
+
+/// Incremental CRC-32C computation
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Crc32c(u32);
+
+impl Crc32c {
+    pub(crate) fn new() -> Self {
+        Crc32c(!0)
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        for b in data {
+            self.0 ^= u32::from(*b);
+            for _ in 0..8 {
+                self.0 = if self.0 & 1 != 0 {
+                    (self.0 >> 1) ^ POLY
+                } else {
+                    self.0 >> 1
+                };
+            }
+        }
+    }
+
+    pub(crate) fn finish(self) -> u32 {
+        !self.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Crc32c;
+
+    #[test]
+    fn check_value() {
+        let mut crc = Crc32c::new();
+        crc.update(b"1234");
+        crc.update(b"56789");
+        assert_eq!(crc.finish(), 0xe306_9283);
+    }
+}
@@ -0,0 +1,39 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `defmt` formatting of the `mctp` types embedded in public types
+
+use defmt::{Format, Formatter};
+use mctp::Tag;
+
+/// Formats a [Tag] with its owner flag
+pub(crate) struct FmtTag(pub(crate) Tag);
+
+impl Format for FmtTag {
+    fn format(&self, f: Formatter) {
+        match self.0 {
+            Tag::Owned(t) => defmt::write!(f, "Owned({=u8})", t.0),
+            Tag::Unowned(t) => defmt::write!(f, "Unowned({=u8})", t.0),
+        }
+    }
+}
+
+/// Formats an [mctp::Error] through its `Debug` implementation
+pub(crate) struct FmtError<'a>(pub(crate) &'a mctp::Error);
+
+impl Format for FmtError<'_> {
+    fn format(&self, f: Formatter) {
+        defmt::write!(f, "{}", defmt::Debug2Format(self.0));
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Retransmission of Discovery Notify until the endpoint is discovered
+//!
+//! On hot-pluggable buses a single Discovery Notify sent on [link
+//! up](crate::GenericRouter::link_up) can get lost, or reach the bus owner before it is ready.
+//! A [NotifyRetransmit] policy sends it again from [update()](crate::GenericRouter::update)
+//! until the bus owner answers or assigns an EID.
+
+use mctp::Eid;
+
+/// How Discovery Notify is sent again, see
+/// [set_notify_retransmit()](crate::GenericRouter::set_notify_retransmit)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NotifyRetransmit {
+    pub(crate) interval_millis: u64,
+    pub(crate) max_retransmits: u8,
+}
+
+impl NotifyRetransmit {
+    /// Send Discovery Notify again every `interval_millis`, up to `max_retransmits` times
+    pub fn new(interval_millis: u64, max_retransmits: u8) -> Self {
+        NotifyRetransmit {
+            interval_millis: interval_millis.max(1),
+            max_retransmits,
+        }
+    }
+}
+
+/// State of the Discovery Notify sent last
+#[derive(Debug)]
+pub(crate) struct NotifySchedule {
+    pub(crate) policy: Option<NotifyRetransmit>,
+    /// Destination and instance ID of the Discovery Notify waiting for a response
+    outstanding: Option<(Eid, u8)>,
+    next_millis: Option<u64>,
+    left: u8,
+}
+
+impl NotifySchedule {
+    pub(crate) const fn new(policy: Option<NotifyRetransmit>) -> Self {
+        NotifySchedule {
+            policy,
+            outstanding: None,
+            next_millis: None,
+            left: 0,
+        }
+    }
+
+    /// Check whether a retransmission is due at `now_millis`
+    pub(crate) fn due(&self, now_millis: u64) -> bool {
+        self.next_millis.is_some_and(|t| t <= now_millis)
+    }
+
+    pub(crate) fn next_deadline(&self) -> Option<u64> {
+        self.next_millis
+    }
+
+    /// Record a Discovery Notify sent to `eid`, `first` after a link came up
+    ///
+    /// Returns the previously outstanding one, its instance ID is no longer needed.
+    pub(crate) fn sent(
+        &mut self,
+        eid: Eid,
+        instance_id: u8,
+        now_millis: u64,
+        first: bool,
+    ) -> Option<(Eid, u8)> {
+        self.left = match (first, self.policy) {
+            (true, Some(policy)) => policy.max_retransmits,
+            (true, None) => 0,
+            (false, _) => self.left.saturating_sub(1),
+        };
+        self.next_millis = self
+            .policy
+            .filter(|_| self.left > 0)
+            .map(|p| now_millis.saturating_add(p.interval_millis));
+        self.outstanding.replace((eid, instance_id))
+    }
+
+    /// Match a response from `source` to the outstanding Discovery Notify
+    ///
+    /// Stops retransmitting and returns the destination and instance ID on a match.
+    /// Discovery Notify sent to the null EID is answered by the bus owner from its own EID.
+    pub(crate) fn answered(&mut self, source: Eid, instance_id: u8) -> Option<(Eid, u8)> {
+        let (eid, id) = self.outstanding?;
+        if id != instance_id || (eid != Eid(0) && eid != source) {
+            return None;
+        }
+        self.cancel()
+    }
+
+    /// Stop retransmitting, returns the outstanding Discovery Notify
+    pub(crate) fn cancel(&mut self) -> Option<(Eid, u8)> {
+        self.next_millis = None;
+        self.left = 0;
+        self.outstanding.take()
+    }
+}
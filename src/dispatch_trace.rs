@@ -0,0 +1,172 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A ring of the most recent routing decisions, for post-mortem debugging
+//!
+//! With the trace enabled (see [RouterConfig::dispatch_trace()](crate::RouterConfig::dispatch_trace)),
+//! the router records a summary of each inbound packet together with its [Disposition],
+//! overwriting the oldest entry once [DISPATCH_TRACE_SIZE] are recorded. A fault handler can
+//! dump the entries with [dispatch_trace()](crate::GenericRouter::dispatch_trace) to find out
+//! why a message disappeared on devices without live logging.
+
+use core::fmt;
+
+use mctp::{Eid, MsgType, Tag};
+
+use crate::Disposition;
+use crate::header::{HEADER_LEN, Header};
+
+/// Number of routing decisions a [Router](crate::Router) keeps in its dispatch trace
+pub const DISPATCH_TRACE_SIZE: usize = 16;
+
+/// Summary of an inbound packet and what the router did with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// Time of the router when the packet was processed
+    pub now_millis: u64,
+    /// Source EID, `Eid(0)` for packets without a header
+    pub source: Eid,
+    /// Destination EID, `Eid(0)` for packets without a header
+    pub dest: Eid,
+    /// Start of message flag
+    pub som: bool,
+    /// End of message flag
+    pub eom: bool,
+    /// Packet sequence number
+    pub seq: u8,
+    /// Message tag, `None` for packets without a header
+    pub tag: Option<Tag>,
+    /// Message type of packets starting a message
+    pub typ: Option<MsgType>,
+    /// Length of the packet, including the transport header
+    pub len: usize,
+    /// The routing decision, [DroppedReassemblyError](Disposition::DroppedReassemblyError)
+    /// for packets rejected by the reassembly of the stack and [Failed](Disposition::Failed)
+    /// for packets that failed processing otherwise
+    pub disposition: Disposition,
+}
+
+impl TraceEntry {
+    fn new(now_millis: u64, pkt: &[u8], disposition: Disposition) -> Self {
+        let hdr = Header::parse(pkt);
+        TraceEntry {
+            now_millis,
+            source: hdr.map_or(Eid(0), |h| h.source),
+            dest: hdr.map_or(Eid(0), |h| h.dest),
+            som: hdr.is_some_and(|h| h.som),
+            eom: hdr.is_some_and(|h| h.eom),
+            seq: hdr.map_or(0, |h| h.seq),
+            tag: hdr.map(|h| h.tag),
+            typ: hdr
+                .filter(|h| h.som)
+                .and_then(|_| pkt.get(HEADER_LEN))
+                .map(|t| MsgType(t & 0x7f)),
+            len: pkt.len(),
+            disposition,
+        }
+    }
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ms: {} -> {}, {} bytes",
+            self.now_millis, self.source.0, self.dest.0, self.len
+        )?;
+        if let Some(tag) = self.tag {
+            let owner = if tag.is_owner() { " TO" } else { "" };
+            write!(f, ", tag {}{owner}, seq {}", tag.tag().0, self.seq)?;
+        }
+        if self.som {
+            f.write_str(", SOM")?;
+        }
+        if self.eom {
+            f.write_str(", EOM")?;
+        }
+        if let Some(typ) = self.typ {
+            write!(f, ", type {}", typ.0)?;
+        }
+        write!(f, ": {:?}", self.disposition)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for TraceEntry {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "{=u64} ms: {=u8} -> {=u8}, {=usize} bytes, som {=bool}, eom {=bool}, seq {=u8}, tag {}, type {}: {}",
+            self.now_millis,
+            self.source.0,
+            self.dest.0,
+            self.len,
+            self.som,
+            self.eom,
+            self.seq,
+            self.tag.map(|t| t.tag().0),
+            self.typ.map(|t| t.0),
+            self.disposition
+        )
+    }
+}
+
+/// Ring of the last [DISPATCH_TRACE_SIZE] routing decisions
+#[derive(Debug)]
+pub(crate) struct DispatchTrace {
+    entries: [Option<TraceEntry>; DISPATCH_TRACE_SIZE],
+    /// Index the next entry is written to
+    next: usize,
+    enabled: bool,
+}
+
+impl DispatchTrace {
+    pub(crate) fn new(enabled: bool) -> Self {
+        DispatchTrace {
+            entries: [None; DISPATCH_TRACE_SIZE],
+            next: 0,
+            enabled,
+        }
+    }
+
+    /// Enable or disable recording, disabling discards the recorded entries
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.clear();
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries = [None; DISPATCH_TRACE_SIZE];
+        self.next = 0;
+    }
+
+    /// Record the `disposition` of `pkt`, overwriting the oldest entry when full
+    pub(crate) fn record(&mut self, now_millis: u64, pkt: &[u8], disposition: Disposition) {
+        if !self.enabled {
+            return;
+        }
+        if let Some(slot) = self.entries.get_mut(self.next) {
+            *slot = Some(TraceEntry::new(now_millis, pkt, disposition));
+        }
+        self.next = (self.next + 1) % DISPATCH_TRACE_SIZE;
+    }
+
+    /// Iterate over the recorded entries, oldest first
+    pub(crate) fn entries(&self) -> impl Iterator<Item = TraceEntry> + '_ {
+        let (newer, older) = self.entries.split_at(self.next);
+        older.iter().chain(newer).flatten().copied()
+    }
+}
@@ -0,0 +1,205 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Embassy integration
+//!
+//! [run_maintenance()] drives the timeouts of a [GenericRouter] shared behind an
+//! [embassy_sync] mutex. Embassy tasks can't be generic, so it is spawned through a
+//! small task for the concrete router type of the application:
+//!
+//! ```text
+//! type AppRouter = Router<MySender, 4, 4, NoHooks, EmbassyClock>;
+//!
+//! static ROUTER: StaticCell<Mutex<CriticalSectionRawMutex, AppRouter>> = StaticCell::new();
+//! static EXPIRED: Watch<CriticalSectionRawMutex, u64, 4> = Watch::new();
+//!
+//! #[embassy_executor::task]
+//! async fn mctp_maintenance(router: &'static Mutex<CriticalSectionRawMutex, AppRouter>) -> ! {
+//!     mctp_lib::embassy::run_maintenance(router, &EXPIRED).await
+//! }
+//!
+//! spawner.spawn(mctp_maintenance(router)).unwrap();
+//! ```
+//!
+//! Tasks waiting for a message select on a [Watch] receiver and re-check their handle
+//! (e.g. with [GenericRouter::requests()]) when it changes.
+//! [call()] does so for a single request and its response, [select()] for a set of handles.
+
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::mutex::Mutex;
+use embassy_sync::watch::{Receiver, Watch};
+use embassy_time::{Duration, Timer, with_timeout};
+use mctp::{Eid, Error, MsgIC, MsgType};
+
+use crate::{
+    Clock, GenericRouter, Handle, HandleTables, Hooks, MessageInfo, RouterError, RouterResult,
+    Sender,
+};
+
+/// Interval used when updating the router fails
+const FALLBACK_INTERVAL_MILLIS: u64 = 100;
+
+/// Update `router` whenever the stack asks for it, forever
+///
+/// The mutex is only held while updating.
+/// When flows, reassemblies or requests time out, the current time of the router clock is sent
+/// to `expired`, waking all receivers waiting on it.
+pub async fn run_maintenance<M, S, T, H, C, const N: usize>(
+    router: &Mutex<M, GenericRouter<S, T, H, C>>,
+    expired: &Watch<M, u64, N>,
+) -> !
+where
+    M: RawMutex,
+    S: Sender,
+    T: HandleTables,
+    H: Hooks,
+    C: Clock,
+{
+    loop {
+        let interval = {
+            let mut router = router.lock().await;
+            match router.poll_expired() {
+                Ok((interval, true)) => {
+                    expired.sender().send(router.clock().now_millis());
+                    interval
+                }
+                Ok((interval, false)) => interval,
+                Err(_) => {
+                    warn!("failed to update router");
+                    FALLBACK_INTERVAL_MILLIS
+                }
+            }
+        };
+        Timer::after_millis(interval).await;
+    }
+}
+
+/// Send a request of type `typ` to `eid` and wait up to `timeout_millis` for the response
+///
+/// Allocates a request for the exchange and releases it before returning.
+/// The response payload is copied to `buf`, its metadata returned. A response larger than
+/// `buf` is dropped and reported as [RxFailure](Error::RxFailure).
+/// The mutex is only held while sending and checking for the response. In between, the
+/// task waits for `wake` to change, which the application sends to when it passed an
+/// inbound packet to the router (e.g. the [Watch] also passed to [run_maintenance()]).
+/// Without a free receiver of `wake`, the router is checked every 100 ms.
+///
+/// Returns [TimedOut](Error::TimedOut) if no response arrived in time.
+#[allow(clippy::too_many_arguments)]
+pub async fn call<M, S, T, H, C, const N: usize>(
+    router: &Mutex<M, GenericRouter<S, T, H, C>>,
+    wake: &Watch<M, u64, N>,
+    eid: Eid,
+    typ: MsgType,
+    ic: MsgIC,
+    payload: &[u8],
+    timeout_millis: u64,
+    buf: &mut [u8],
+) -> RouterResult<MessageInfo>
+where
+    M: RawMutex,
+    S: Sender,
+    T: HandleTables,
+    H: Hooks,
+    C: Clock,
+{
+    let mut receiver = wake.receiver();
+    let (req, deadline) = {
+        let mut router = router.lock().await;
+        let req = router.req(eid)?;
+        let deadline = router.clock().now_millis().saturating_add(timeout_millis);
+        if let Err(e) = router.send(None, typ, None, ic, req, payload) {
+            let _ = router.unbind(req);
+            return Err(e);
+        }
+        (req, deadline)
+    };
+    loop {
+        let remaining = {
+            let mut router = router.lock().await;
+            let mut sink = &mut *buf;
+            let received = router.recv_into(req, &mut sink);
+            let now_millis = router.clock().now_millis();
+            if !matches!(received, Ok(None)) || now_millis >= deadline {
+                let _ = router.unbind(req);
+                return match received {
+                    Ok(Some(info)) => Ok(info),
+                    Ok(None) => Err(RouterError::from(Error::TimedOut).with_handle(req.into())),
+                    Err(e) => Err(e),
+                };
+            }
+            deadline - now_millis
+        };
+        wait_wake(&mut receiver, Some(remaining)).await;
+    }
+}
+
+/// Wait until any of `handles` has a message waiting to be received
+///
+/// Returns the first handle in `handles` with a message queued, without receiving it, so a
+/// task serving several listeners and requests can wait for all of them at once.
+/// Like [call()], the task waits for `wake` to change between checks.
+///
+/// Returns [BadArgument](Error::BadArgument) with the handle if one of `handles` is not
+/// bound (or gets unbound while waiting).
+pub async fn select<M, S, T, H, C, const N: usize>(
+    router: &Mutex<M, GenericRouter<S, T, H, C>>,
+    wake: &Watch<M, u64, N>,
+    handles: &[Handle],
+) -> RouterResult<Handle>
+where
+    M: RawMutex,
+    S: Sender,
+    T: HandleTables,
+    H: Hooks,
+    C: Clock,
+{
+    let mut receiver = wake.receiver();
+    loop {
+        {
+            let router = router.lock().await;
+            for &handle in handles {
+                match router.queued(handle) {
+                    None => return Err(RouterError::from(Error::BadArgument).with_handle(handle)),
+                    Some(0) => {}
+                    Some(_) => return Ok(handle),
+                }
+            }
+        }
+        wait_wake(&mut receiver, None).await;
+    }
+}
+
+/// Wait for the value of `receiver` to change, at most `max_millis`
+///
+/// Without a receiver, waits at most [FALLBACK_INTERVAL_MILLIS].
+async fn wait_wake<M: RawMutex, const N: usize>(
+    receiver: &mut Option<Receiver<'_, M, u64, N>>,
+    max_millis: Option<u64>,
+) {
+    match (receiver.as_mut(), max_millis) {
+        (Some(receiver), Some(max)) => {
+            let _ = with_timeout(Duration::from_millis(max), receiver.changed()).await;
+        }
+        (Some(receiver), None) => {
+            receiver.changed().await;
+        }
+        (None, max) => {
+            let millis = max.map_or(FALLBACK_INTERVAL_MILLIS, |m| {
+                m.min(FALLBACK_INTERVAL_MILLIS)
+            });
+            Timer::after_millis(millis).await;
+        }
+    }
+}
@@ -0,0 +1,259 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Type-erased access to a router
+//!
+//! Higher-level crates (e.g. protocol implementations) can accept "a router" as
+//! `&mut dyn` [DynRouter] or, when it is shared between handles, as a [DynCore], instead of
+//! being generic over the [Sender], the handle tables, [Hooks] and [Clock] of the
+//! [GenericRouter]. [DynListener] and [DynRequest] are the type-erased counterparts of the
+//! [shared](crate::shared) channels.
+//!
+//! [Sender] is object safe and implemented for `&mut S`, so a router can also send through
+//! a `&mut dyn Sender<Packet = [u8; 64]>` chosen at runtime.
+//!
+//! ```
+//! use core::cell::RefCell;
+//! use mctp::{Eid, MsgIC, MsgType};
+//! use mctp_lib::erased::{DynCore, DynRequest};
+//! use mctp_lib::{Router, Sender};
+//! # struct NullSender;
+//! # impl Sender for NullSender {
+//! #     type Packet = [u8; 64];
+//! #     fn send_packet(&mut self, _eid: Eid, _pkt: &[u8]) -> mctp::Result<()> { Ok(()) }
+//! #     fn get_mtu(&self) -> usize { 64 }
+//! # }
+//!
+//! /// Not generic over the router
+//! fn ping(core: &DynCore<'_>, eid: Eid) -> mctp::Result<()> {
+//!     let req = DynRequest::new(core, eid)?;
+//!     req.send(MsgType(1), MsgIC(false), &[&[0x80]])?;
+//!     req.unbind()?;
+//!     Ok(())
+//! }
+//!
+//! let router: Router<_, 4, 4> = Router::new(Eid(8), 0, NullSender);
+//! let core = RefCell::new(router);
+//! ping(&core, Eid(9))?;
+//! # Ok::<(), mctp::Error>(())
+//! ```
+
+use core::cell::{RefCell, RefMut};
+
+use mctp::{Eid, Error, MsgIC, MsgType, Result, Tag};
+
+use crate::{
+    Clock, GenericRouter, Handle, HandleTables, Hooks, ListenerHandle, MessageInfo, RequestHandle,
+    RouterError, RouterResult, SendReport, Sender,
+};
+
+/// Object-safe interface of a [GenericRouter]
+///
+/// The methods forward to the router methods of the same name.
+pub trait DynRouter {
+    /// Get the EID of the endpoint, see [GenericRouter::get_eid()]
+    fn get_eid(&self) -> Eid;
+    /// Provide an incoming packet, see [GenericRouter::inbound()]
+    fn inbound(&mut self, pkt: &[u8]) -> Result<Option<Handle>>;
+    /// Update the stack, see [GenericRouter::poll()]
+    fn poll(&mut self) -> Result<u64>;
+    /// Bind a listener for `typ`, see [GenericRouter::listener()]
+    fn listener(&mut self, typ: MsgType) -> Result<ListenerHandle>;
+    /// Allocate a request to `eid`, see [GenericRouter::req()]
+    fn req(&mut self, eid: Eid) -> Result<RequestHandle>;
+    /// Send a message, see [GenericRouter::send_vectored()]
+    fn send_vectored(
+        &mut self,
+        eid: Option<Eid>,
+        typ: MsgType,
+        tag: Option<Tag>,
+        ic: MsgIC,
+        handle: Handle,
+        bufs: &[&[u8]],
+    ) -> RouterResult<SendReport>;
+    /// Receive a message into `buf`, see [GenericRouter::recv_into()]
+    fn recv_into(&mut self, handle: Handle, buf: &mut [u8]) -> RouterResult<Option<MessageInfo>>;
+    /// Respond to a request, see [GenericRouter::respond()]
+    fn respond(
+        &mut self,
+        listener: ListenerHandle,
+        request: &MessageInfo,
+        bufs: &[&[u8]],
+    ) -> RouterResult<SendReport>;
+    /// Unbind a listener or release a request, see [GenericRouter::unbind()]
+    fn unbind(&mut self, handle: Handle) -> RouterResult<()>;
+}
+
+impl<S: Sender, T: HandleTables, H: Hooks, C: Clock> DynRouter for GenericRouter<S, T, H, C> {
+    fn get_eid(&self) -> Eid {
+        GenericRouter::get_eid(self)
+    }
+
+    fn inbound(&mut self, pkt: &[u8]) -> Result<Option<Handle>> {
+        GenericRouter::inbound(self, pkt)
+    }
+
+    fn poll(&mut self) -> Result<u64> {
+        GenericRouter::poll(self)
+    }
+
+    fn listener(&mut self, typ: MsgType) -> Result<ListenerHandle> {
+        GenericRouter::listener(self, typ)
+    }
+
+    fn req(&mut self, eid: Eid) -> Result<RequestHandle> {
+        GenericRouter::req(self, eid)
+    }
+
+    fn send_vectored(
+        &mut self,
+        eid: Option<Eid>,
+        typ: MsgType,
+        tag: Option<Tag>,
+        ic: MsgIC,
+        handle: Handle,
+        bufs: &[&[u8]],
+    ) -> RouterResult<SendReport> {
+        GenericRouter::send_vectored(self, eid, typ, tag, ic, handle, bufs)
+    }
+
+    fn recv_into(&mut self, handle: Handle, buf: &mut [u8]) -> RouterResult<Option<MessageInfo>> {
+        let mut sink = buf;
+        GenericRouter::recv_into(self, handle, &mut sink)
+    }
+
+    fn respond(
+        &mut self,
+        listener: ListenerHandle,
+        request: &MessageInfo,
+        bufs: &[&[u8]],
+    ) -> RouterResult<SendReport> {
+        GenericRouter::respond(self, listener, request, bufs)
+    }
+
+    fn unbind(&mut self, handle: Handle) -> RouterResult<()> {
+        GenericRouter::unbind(self, handle)
+    }
+}
+
+/// A router shared by type-erased channels
+///
+/// Any `&RefCell<GenericRouter<..>>` coerces to it, see also
+/// [SharedRouter::as_dyn()](crate::shared::SharedRouter::as_dyn).
+pub type DynCore<'r> = RefCell<dyn DynRouter + 'r>;
+
+fn lock<'c, 'r>(
+    core: &'c DynCore<'r>,
+    handle: Handle,
+) -> RouterResult<RefMut<'c, dyn DynRouter + 'r>> {
+    core.try_borrow_mut()
+        .map_err(|_| RouterError::from(Error::InternalError).with_handle(handle))
+}
+
+/// A listener bound on a [DynCore]
+///
+/// Copies refer to the same listener, it stays bound until [unbind()](Self::unbind) is called.
+#[derive(Clone, Copy)]
+pub struct DynListener<'c, 'r> {
+    core: &'c DynCore<'r>,
+    handle: ListenerHandle,
+}
+
+impl<'c, 'r> DynListener<'c, 'r> {
+    /// Bind a listener for `typ` on `core`
+    pub fn new(core: &'c DynCore<'r>, typ: MsgType) -> Result<Self> {
+        let handle = core
+            .try_borrow_mut()
+            .map_err(|_| Error::InternalError)?
+            .listener(typ)?;
+        Ok(DynListener { core, handle })
+    }
+
+    /// Get the handle of the listener
+    pub fn handle(&self) -> ListenerHandle {
+        self.handle
+    }
+
+    /// Receive a request into `buf` without blocking
+    ///
+    /// Returns `Ok(None)` when no request is available.
+    /// The payload is stored in `buf[..info.len]`.
+    pub fn try_recv(&self, buf: &mut [u8]) -> RouterResult<Option<MessageInfo>> {
+        lock(self.core, self.handle.into())?.recv_into(self.handle.into(), buf)
+    }
+
+    /// Respond to a request received with [try_recv()](Self::try_recv)
+    pub fn respond(&self, request: &MessageInfo, ic: MsgIC, bufs: &[&[u8]]) -> RouterResult<()> {
+        let request = MessageInfo { ic, ..*request };
+        lock(self.core, self.handle.into())?
+            .respond(self.handle, &request, bufs)
+            .map(|_| ())
+    }
+
+    /// Unbind the listener
+    pub fn unbind(self) -> RouterResult<()> {
+        lock(self.core, self.handle.into())?.unbind(self.handle.into())
+    }
+}
+
+/// A request allocated on a [DynCore]
+///
+/// Copies refer to the same request, it stays allocated until [unbind()](Self::unbind) is
+/// called.
+#[derive(Clone, Copy)]
+pub struct DynRequest<'c, 'r> {
+    core: &'c DynCore<'r>,
+    handle: RequestHandle,
+}
+
+impl<'c, 'r> DynRequest<'c, 'r> {
+    /// Allocate a request to `eid` on `core`
+    pub fn new(core: &'c DynCore<'r>, eid: Eid) -> Result<Self> {
+        let handle = core
+            .try_borrow_mut()
+            .map_err(|_| Error::InternalError)?
+            .req(eid)?;
+        Ok(DynRequest { core, handle })
+    }
+
+    /// Get the handle of the request
+    pub fn handle(&self) -> RequestHandle {
+        self.handle
+    }
+
+    /// Send a request message, allocating a new tag
+    pub fn send(&self, typ: MsgType, ic: MsgIC, bufs: &[&[u8]]) -> RouterResult<SendReport> {
+        lock(self.core, self.handle.into())?.send_vectored(
+            None,
+            typ,
+            None,
+            ic,
+            self.handle.into(),
+            bufs,
+        )
+    }
+
+    /// Receive a response into `buf` without blocking
+    ///
+    /// Returns `Ok(None)` when no response is available.
+    /// The payload is stored in `buf[..info.len]`.
+    pub fn try_recv(&self, buf: &mut [u8]) -> RouterResult<Option<MessageInfo>> {
+        lock(self.core, self.handle.into())?.recv_into(self.handle.into(), buf)
+    }
+
+    /// Release the request
+    pub fn unbind(self) -> RouterResult<()> {
+        lock(self.core, self.handle.into())?.unbind(self.handle.into())
+    }
+}
@@ -0,0 +1,136 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Errors with context about the handle, peer and tag involved
+
+use core::fmt;
+
+use crate::Handle;
+use mctp::{Eid, Tag};
+
+/// Result type of [Router](crate::Router) operations that report a [RouterError]
+pub type RouterResult<T> = core::result::Result<T, RouterError>;
+
+/// An [mctp::Error] with context about where it occurred
+///
+/// Converts into a plain [mctp::Error], so `?` keeps working in functions returning
+/// [mctp::Result].
+#[derive(Debug)]
+pub struct RouterError {
+    error: mctp::Error,
+    handle: Option<Handle>,
+    eid: Option<Eid>,
+    tag: Option<Tag>,
+}
+
+impl RouterError {
+    /// Create a new error without context
+    pub fn new(error: mctp::Error) -> Self {
+        RouterError {
+            error,
+            handle: None,
+            eid: None,
+            tag: None,
+        }
+    }
+
+    /// Get the underlying error
+    pub fn error(&self) -> &mctp::Error {
+        &self.error
+    }
+
+    /// Consume the context and return the underlying error
+    pub fn into_inner(self) -> mctp::Error {
+        self.error
+    }
+
+    /// Handle of the listener or request the operation was performed on
+    pub fn handle(&self) -> Option<Handle> {
+        self.handle
+    }
+
+    /// Remote EID involved in the operation
+    pub fn eid(&self) -> Option<Eid> {
+        self.eid
+    }
+
+    /// Message tag involved in the operation
+    pub fn tag(&self) -> Option<Tag> {
+        self.tag
+    }
+
+    pub(crate) fn with_handle(mut self, handle: Handle) -> Self {
+        self.handle = Some(handle);
+        self
+    }
+
+    pub(crate) fn with_eid(mut self, eid: Eid) -> Self {
+        self.eid = Some(eid);
+        self
+    }
+
+    pub(crate) fn with_tag(mut self, tag: Option<Tag>) -> Self {
+        self.tag = tag.or(self.tag);
+        self
+    }
+}
+
+impl From<mctp::Error> for RouterError {
+    fn from(error: mctp::Error) -> Self {
+        RouterError::new(error)
+    }
+}
+
+impl From<RouterError> for mctp::Error {
+    fn from(error: RouterError) -> Self {
+        error.error
+    }
+}
+
+impl fmt::Display for RouterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        match self.handle {
+            Some(Handle::Listener(h)) => write!(f, ", listener {}", h.cookie().0)?,
+            Some(Handle::Request(h)) => write!(f, ", request {}", h.cookie().0)?,
+            None => (),
+        }
+        if let Some(eid) = self.eid {
+            write!(f, ", eid {}", eid.0)?;
+        }
+        if let Some(tag) = self.tag {
+            let owner = if tag.is_owner() { "owned" } else { "unowned" };
+            write!(f, ", tag {} ({owner})", tag.tag().0)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for RouterError {
+    fn format(&self, f: defmt::Formatter) {
+        use crate::defmt_util::{FmtError, FmtTag};
+
+        defmt::write!(
+            f,
+            "RouterError {{ error: {}, handle: {}, eid: {}, tag: {} }}",
+            FmtError(&self.error),
+            self.handle,
+            self.eid.map(|eid| eid.0),
+            self.tag.map(FmtTag)
+        );
+    }
+}
+
+impl core::error::Error for RouterError {}
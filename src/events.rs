@@ -0,0 +1,142 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A queue of router events, an alternative to [Hooks](crate::hooks::Hooks) for applications
+//! polling the router from a superloop
+//!
+//! With the queue enabled (see [RouterConfig::event_queue()](crate::RouterConfig::event_queue)),
+//! the router records what happened while processing packets and timers, the application
+//! drains the events with [next_event()](crate::GenericRouter::next_event) after each
+//! [inbound()](crate::GenericRouter::inbound) or [update()](crate::GenericRouter::update).
+
+use mctp::Eid;
+
+use crate::hooks::ExpiryReason;
+use crate::{Disposition, Handle, PeerState};
+
+/// Number of events a [Router](crate::Router) queues before dropping further ones
+pub const EVENT_QUEUE_SIZE: usize = 8;
+
+/// Something that happened in a [Router](crate::Router), see
+/// [next_event()](crate::GenericRouter::next_event)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RouterEvent {
+    /// A message was delivered to the listener or request with this handle
+    MessageReady(Handle),
+    /// A message of the handle was lost, see [Hooks::message_expired()](crate::hooks::Hooks::message_expired)
+    Expired(Handle, ExpiryReason),
+    /// The EID of the endpoint changed
+    EidChanged {
+        /// Previous EID
+        old: Eid,
+        /// Current EID
+        new: Eid,
+    },
+    /// A monitored peer went up or down, see
+    /// [monitor_peer()](crate::GenericRouter::monitor_peer)
+    PeerStateChanged(Eid, PeerState),
+    /// The link of a port went down, see [link_down()](crate::GenericRouter::link_down)
+    LinkDown(u8),
+    /// The link of a port came up, see [link_up()](crate::GenericRouter::link_up)
+    LinkUp(u8),
+    /// An inbound packet was dropped
+    Dropped(Disposition),
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for RouterEvent {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            RouterEvent::MessageReady(handle) => defmt::write!(f, "MessageReady({})", handle),
+            RouterEvent::Expired(handle, reason) => {
+                defmt::write!(f, "Expired({}, {})", handle, reason)
+            }
+            RouterEvent::EidChanged { old, new } => {
+                defmt::write!(f, "EidChanged({=u8} -> {=u8})", old.0, new.0)
+            }
+            RouterEvent::PeerStateChanged(eid, state) => {
+                defmt::write!(f, "PeerStateChanged({=u8}, {})", eid.0, state)
+            }
+            RouterEvent::LinkDown(port) => defmt::write!(f, "LinkDown({=u8})", port),
+            RouterEvent::LinkUp(port) => defmt::write!(f, "LinkUp({=u8})", port),
+            RouterEvent::Dropped(disposition) => defmt::write!(f, "Dropped({})", disposition),
+        }
+    }
+}
+
+/// Bounded FIFO of events, keeping the oldest ones when full
+#[derive(Debug)]
+pub(crate) struct EventQueue {
+    events: [Option<RouterEvent>; EVENT_QUEUE_SIZE],
+    /// Index of the oldest event
+    head: usize,
+    len: usize,
+    enabled: bool,
+    /// Events dropped because the queue was full
+    lost: usize,
+}
+
+impl EventQueue {
+    pub(crate) fn new(enabled: bool) -> Self {
+        EventQueue {
+            events: [None; EVENT_QUEUE_SIZE],
+            head: 0,
+            len: 0,
+            enabled,
+            lost: 0,
+        }
+    }
+
+    /// Enable or disable recording, disabling discards queued events
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.events = [None; EVENT_QUEUE_SIZE];
+            self.head = 0;
+            self.len = 0;
+        }
+    }
+
+    pub(crate) fn push(&mut self, event: RouterEvent) {
+        if !self.enabled {
+            return;
+        }
+        if self.len == EVENT_QUEUE_SIZE {
+            self.lost = self.lost.wrapping_add(1);
+            return;
+        }
+        if let Some(slot) = self
+            .events
+            .get_mut((self.head + self.len) % EVENT_QUEUE_SIZE)
+        {
+            *slot = Some(event);
+            self.len += 1;
+        }
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<RouterEvent> {
+        if self.len == 0 {
+            return None;
+        }
+        let event = self.events.get_mut(self.head)?.take();
+        self.head = (self.head + 1) % EVENT_QUEUE_SIZE;
+        self.len -= 1;
+        event
+    }
+
+    pub(crate) fn lost(&self) -> usize {
+        self.lost
+    }
+}
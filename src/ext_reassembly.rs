@@ -0,0 +1,470 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reassembly of requests into application-provided buffers
+//!
+//! Requests for a listener with a buffer set by
+//! [set_reassembly_buffer()](crate::GenericRouter::set_reassembly_buffer) bypass the fixed
+//! reassembly buffers of the stack, so their size is only limited by the buffer.
+//! In streaming mode the application drains the buffer in [MessageChunk]s while the message
+//! is received, lifting that limit as well.
+
+use mctp::{Eid, MsgIC, MsgType, Tag};
+
+use crate::crc32c::{Crc32c, IC_LEN};
+use crate::header::{HEADER_LEN, Header};
+use crate::reservations::Flow;
+use crate::{ListenerHandle, MessageInfo, REASSEMBLY_TIMEOUT_MILLIS};
+
+/// Maximum number of listeners with a reassembly buffer
+pub const REASSEMBLY_BUFFER_TABLE_SIZE: usize = 4;
+
+const SEQ_MASK: u8 = 0x03;
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Idle,
+    Receiving {
+        source: Eid,
+        dest: Eid,
+        tag: Tag,
+        ic: MsgIC,
+        next_seq: u8,
+        len: usize,
+        started_millis: u64,
+        last_millis: u64,
+    },
+    Complete(MessageInfo),
+}
+
+/// A part of a message received in streaming mode
+///
+/// Chunks of a message are delivered in order, the first one at `offset` 0 and the
+/// last one with `last` set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageChunk {
+    /// Source EID
+    pub source: Eid,
+    /// Message tag
+    pub tag: Tag,
+    /// Message type
+    pub typ: MsgType,
+    /// Offset of the chunk in the message payload
+    pub offset: usize,
+    /// Length of the chunk in bytes
+    pub len: usize,
+    /// Whether the chunk completes the message
+    pub last: bool,
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for MessageChunk {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "MessageChunk {{ source: {=u8}, tag: {}, typ: {=u8}, offset: {=usize}, len: {=usize}, last: {=bool} }}",
+            self.source.0,
+            crate::defmt_util::FmtTag(self.tag),
+            self.typ.0,
+            self.offset,
+            self.len,
+            self.last
+        );
+    }
+}
+
+/// An application-provided buffer of a listener
+#[derive(Debug)]
+struct Buffer {
+    listener: ListenerHandle,
+    typ: MsgType,
+    data: &'static mut [u8],
+    state: State,
+    streaming: bool,
+    /// Bytes of the message drained in chunks, `data` starts at this offset
+    drained: usize,
+    /// Integrity check over the message type and the drained bytes
+    crc: Crc32c,
+}
+
+/// Outcome of a packet passed to [ExternalBuffers::admit()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Admit {
+    /// The message is not complete yet
+    Incomplete,
+    /// The message of the listener is complete
+    Complete(ListenerHandle),
+    /// The message is not complete yet, the listener has to drain its buffer
+    Chunk(ListenerHandle),
+    /// The buffer holds a different message
+    Busy,
+    /// The message exceeds the buffer
+    TooLarge,
+    /// A packet was lost
+    OutOfSequence,
+    /// The integrity check of the message failed
+    IntegrityError,
+}
+
+/// The reassembly buffers of all listeners
+#[derive(Debug)]
+pub(crate) struct ExternalBuffers {
+    slots: [Option<Buffer>; REASSEMBLY_BUFFER_TABLE_SIZE],
+}
+
+impl ExternalBuffers {
+    pub(crate) const fn new() -> Self {
+        ExternalBuffers {
+            slots: [const { None }; REASSEMBLY_BUFFER_TABLE_SIZE],
+        }
+    }
+
+    /// Set the buffer of `listener` for messages of type `typ`
+    ///
+    /// Returns the previous buffer of the listener, `Err(data)` if the table is full.
+    pub(crate) fn set(
+        &mut self,
+        listener: ListenerHandle,
+        typ: MsgType,
+        data: &'static mut [u8],
+    ) -> Result<Option<&'static mut [u8]>, &'static mut [u8]> {
+        let previous = self.take(listener);
+        let Some(slot) = self.slots.iter_mut().find(|s| s.is_none()) else {
+            return Err(data);
+        };
+        *slot = Some(Buffer {
+            listener,
+            typ,
+            data,
+            state: State::Idle,
+            streaming: false,
+            drained: 0,
+            crc: Crc32c::new(),
+        });
+        Ok(previous)
+    }
+
+    /// Set whether the message in the buffer of `listener` is drained in chunks
+    ///
+    /// Discards the message in the buffer. Returns `false` if the listener has no buffer.
+    pub(crate) fn set_streaming(&mut self, listener: ListenerHandle, streaming: bool) -> bool {
+        let Some(b) = self.buffer_mut(listener) else {
+            return false;
+        };
+        b.streaming = streaming;
+        b.state = State::Idle;
+        true
+    }
+
+    /// Reassemble messages of type `typ` into the buffer of `listener`
+    ///
+    /// A message of another type still being reassembled is discarded, a complete one is kept.
+    pub(crate) fn retype(&mut self, listener: ListenerHandle, typ: MsgType) {
+        if let Some(b) = self.buffer_mut(listener) {
+            if b.typ != typ && matches!(b.state, State::Receiving { .. }) {
+                b.state = State::Idle;
+            }
+            b.typ = typ;
+        }
+    }
+
+    /// Remove the buffer of `listener`, discarding its message
+    pub(crate) fn take(&mut self, listener: ListenerHandle) -> Option<&'static mut [u8]> {
+        self.slots
+            .iter_mut()
+            .find(|s| s.as_ref().is_some_and(|b| b.listener == listener))
+            .and_then(Option::take)
+            .map(|b| b.data)
+    }
+
+    /// Reassemble the request packet `pkt` if it belongs to a buffer
+    ///
+    /// Returns `None` for packets left to the stack.
+    pub(crate) fn admit(&mut self, hdr: &Header, pkt: &[u8], now_millis: u64) -> Option<Admit> {
+        if !hdr.tag.is_owner() {
+            return None;
+        }
+        let body = pkt.get(HEADER_LEN..)?;
+        let buffer = if hdr.som {
+            let typ = MsgType(body.first()? & 0x7f);
+            self.slots.iter_mut().flatten().find(|b| b.typ == typ)?
+        } else {
+            self.slots.iter_mut().flatten().find(|b| {
+                matches!(b.state, State::Receiving { source, tag, .. }
+                    if source == hdr.source && tag == hdr.tag)
+            })?
+        };
+        let (payload, len) = match buffer.state {
+            State::Receiving {
+                source,
+                tag,
+                next_seq,
+                len,
+                ..
+            } if source == hdr.source && tag == hdr.tag && !hdr.som => {
+                if hdr.seq != next_seq {
+                    buffer.state = State::Idle;
+                    return Some(Admit::OutOfSequence);
+                }
+                (body, len)
+            }
+            State::Receiving {
+                source,
+                tag,
+                last_millis,
+                ..
+            } if (source, tag) != (hdr.source, hdr.tag)
+                && now_millis.saturating_sub(last_millis) < REASSEMBLY_TIMEOUT_MILLIS =>
+            {
+                return Some(Admit::Busy);
+            }
+            State::Complete(_) => return Some(Admit::Busy),
+            _ => {
+                // A new message, possibly restarting the one on the same flow
+                let (&typ, payload) = body.split_first()?;
+                buffer.drained = 0;
+                buffer.crc = Crc32c::new();
+                buffer.crc.update(&[buffer.typ.0 | 0x80]);
+                buffer.state = State::Receiving {
+                    source: hdr.source,
+                    dest: hdr.dest,
+                    tag: hdr.tag,
+                    ic: MsgIC(typ & 0x80 != 0),
+                    next_seq: hdr.seq,
+                    len: 0,
+                    started_millis: now_millis,
+                    last_millis: now_millis,
+                };
+                (payload, 0)
+            }
+        };
+        let start = len - buffer.drained;
+        let end = start + payload.len();
+        let Some(dst) = buffer.data.get_mut(start..end) else {
+            buffer.state = State::Idle;
+            return Some(Admit::TooLarge);
+        };
+        dst.copy_from_slice(payload);
+        let State::Receiving {
+            source,
+            dest,
+            tag,
+            ic,
+            started_millis,
+            ..
+        } = buffer.state
+        else {
+            return None;
+        };
+        if !hdr.eom {
+            buffer.state = State::Receiving {
+                source,
+                dest,
+                tag,
+                ic,
+                next_seq: hdr.seq.wrapping_add(1) & SEQ_MASK,
+                len: len + payload.len(),
+                started_millis,
+                last_millis: now_millis,
+            };
+            if buffer.streaming && end * 2 >= buffer.data.len() {
+                return Some(Admit::Chunk(buffer.listener));
+            }
+            return Some(Admit::Incomplete);
+        }
+        let len = if ic.0 {
+            match check_integrity(buffer.crc, buffer.data.get(..end)?) {
+                Some(len) => buffer.drained + len,
+                None => {
+                    buffer.state = State::Idle;
+                    return Some(Admit::IntegrityError);
+                }
+            }
+        } else {
+            buffer.drained + end
+        };
+        buffer.state = State::Complete(MessageInfo {
+            source,
+            dest,
+            tag,
+            typ: buffer.typ,
+            ic,
+            len,
+            received_millis: Some(now_millis),
+        });
+        Some(Admit::Complete(buffer.listener))
+    }
+
+    /// Discard the message in the buffer of `listener`
+    pub(crate) fn discard(&mut self, listener: ListenerHandle) {
+        if let Some(b) = self.buffer_mut(listener) {
+            b.state = State::Idle;
+        }
+    }
+
+    /// Get the complete message of `listener` without taking it
+    ///
+    /// Messages partly drained in chunks are only available to [drain()](Self::drain).
+    pub(crate) fn complete(&self, listener: ListenerHandle) -> Option<MessageInfo> {
+        self.slots
+            .iter()
+            .flatten()
+            .find(|b| b.listener == listener)
+            .and_then(|b| match b.state {
+                State::Complete(info) if b.drained == 0 => Some(info),
+                _ => None,
+            })
+    }
+
+    /// Take the complete message of `listener`, passing it to `f`
+    pub(crate) fn receive<R>(
+        &mut self,
+        listener: ListenerHandle,
+        f: impl FnOnce(&MessageInfo, &[u8]) -> R,
+    ) -> Option<R> {
+        let b = self.buffer_mut(listener)?;
+        let State::Complete(info) = b.state else {
+            return None;
+        };
+        if b.drained != 0 {
+            return None;
+        }
+        b.state = State::Idle;
+        Some(f(&info, b.data.get(..info.len).unwrap_or_default()))
+    }
+
+    /// Move the next chunk of the message of a streaming `listener` to the start of `out`
+    ///
+    /// The integrity check of a message is withheld until it is complete, so the last
+    /// chunk is only delivered once the message passed it.
+    /// Returns `None` if no data is available.
+    pub(crate) fn drain(
+        &mut self,
+        listener: ListenerHandle,
+        out: &mut [u8],
+    ) -> Option<MessageChunk> {
+        let b = self.buffer_mut(listener).filter(|b| b.streaming)?;
+        let (source, tag, held, available, complete) = match b.state {
+            State::Receiving {
+                source,
+                tag,
+                ic,
+                len,
+                ..
+            } => {
+                let held = len - b.drained;
+                let withheld = if ic.0 { IC_LEN } else { 0 };
+                (source, tag, held, held.saturating_sub(withheld), false)
+            }
+            State::Complete(info) => {
+                let held = info.len - b.drained;
+                (info.source, info.tag, held, held, true)
+            }
+            State::Idle => return None,
+        };
+        let n = available.min(out.len());
+        if n == 0 && !complete {
+            return None;
+        }
+        let chunk = b.data.get(..n)?;
+        out.get_mut(..n)?.copy_from_slice(chunk);
+        b.crc.update(chunk);
+        b.data.copy_within(n..held, 0);
+        let offset = b.drained;
+        let last = complete && n == available;
+        if last {
+            b.state = State::Idle;
+            b.drained = 0;
+        } else {
+            b.drained += n;
+        }
+        Some(MessageChunk {
+            source,
+            tag,
+            typ: b.typ,
+            offset,
+            len: n,
+            last,
+        })
+    }
+
+    /// Iterate over the messages being reassembled, with the listener of their buffer
+    pub(crate) fn flows(&self) -> impl Iterator<Item = (ListenerHandle, Flow)> + '_ {
+        self.slots.iter().flatten().filter_map(|b| match b.state {
+            State::Receiving {
+                source,
+                tag,
+                len,
+                started_millis,
+                last_millis,
+                ..
+            } => Some((
+                b.listener,
+                Flow {
+                    source,
+                    tag,
+                    typ: b.typ,
+                    bytes: len,
+                    started_millis,
+                    last_millis,
+                },
+            )),
+            _ => None,
+        })
+    }
+
+    /// Get the time at which the next incomplete message is discarded
+    pub(crate) fn next_deadline(&self) -> Option<u64> {
+        self.slots
+            .iter()
+            .flatten()
+            .filter_map(|b| match b.state {
+                State::Receiving { last_millis, .. } => {
+                    Some(last_millis.saturating_add(REASSEMBLY_TIMEOUT_MILLIS))
+                }
+                _ => None,
+            })
+            .min()
+    }
+
+    /// Discard incomplete messages without progress for too long
+    ///
+    /// Returns whether a message was discarded.
+    pub(crate) fn expire(&mut self, now_millis: u64) -> bool {
+        let mut expired = false;
+        for b in self.slots.iter_mut().flatten() {
+            if let State::Receiving { last_millis, .. } = b.state
+                && now_millis.saturating_sub(last_millis) >= REASSEMBLY_TIMEOUT_MILLIS
+            {
+                b.state = State::Idle;
+                expired = true;
+            }
+        }
+        expired
+    }
+
+    fn buffer_mut(&mut self, listener: ListenerHandle) -> Option<&mut Buffer> {
+        self.slots
+            .iter_mut()
+            .flatten()
+            .find(|b| b.listener == listener)
+    }
+}
+
+/// Verify the CRC-32C at the end of `msg`, continuing `crc`, returning the length without it
+fn check_integrity(mut crc: Crc32c, msg: &[u8]) -> Option<usize> {
+    let (body, check) = msg.split_last_chunk::<IC_LEN>()?;
+    crc.update(body);
+    (crc.finish() == u32::from_le_bytes(*check)).then_some(body.len())
+}
@@ -0,0 +1,314 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Generators of adversarial input for fuzzing and property tests
+//!
+//! Requires the `fuzz` feature.
+//!
+//! The protocol structures implement [Arbitrary] for fuzzers like `cargo fuzz`, and the
+//! functions of this module return equivalent `proptest` [Strategies](Strategy).
+//! Generated values cover malformed input: reserved EIDs, unsupported header versions,
+//! inconsistent flags and [FragmentSequence]s with dropped, duplicated, reordered,
+//! truncated or corrupted packets.
+//!
+//! ```
+//! use mctp::Eid;
+//! use mctp_lib::Router;
+//! use mctp_lib::fuzz::fragment_sequence;
+//! use mctp_lib::test_util::NullSender;
+//! use proptest::prelude::*;
+//!
+//! proptest!(|(seq in fragment_sequence())| {
+//!     let mut router: Router<_, 2, 2> = Router::new(Eid(8), 0, NullSender);
+//!     for pkt in seq.packets() {
+//!         let _ = router.inbound(&pkt);
+//!     }
+//! });
+//! ```
+
+use std::vec::Vec;
+
+use arbitrary::Arbitrary;
+use proptest::prelude::*;
+
+use crate::control::{ControlHeader, MSG_TYPE_CONTROL};
+use crate::header::HEADER_LEN;
+
+/// Largest packet built by a [FragmentSequence]
+const MAX_PACKET_LEN: usize = 512;
+
+const FLAG_SOM: u8 = 0x80;
+const FLAG_EOM: u8 = 0x40;
+const FLAG_TO: u8 = 0x08;
+
+/// An MCTP transport header, with all fields unchecked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Arbitrary)]
+pub struct PacketHeader {
+    /// Header version, the low 4 bits are the version, the others reserved
+    pub version: u8,
+    /// Destination EID
+    pub dest: u8,
+    /// Source EID
+    pub source: u8,
+    /// Start of message flag
+    pub som: bool,
+    /// End of message flag
+    pub eom: bool,
+    /// Packet sequence number, masked to 2 bits
+    pub seq: u8,
+    /// Tag owner flag
+    pub owner: bool,
+    /// Message tag, masked to 3 bits
+    pub tag: u8,
+}
+
+impl PacketHeader {
+    /// Encode the header
+    pub fn to_bytes(&self) -> [u8; HEADER_LEN] {
+        let mut flags = (self.seq & 0x03) << 4 | self.tag & 0x07;
+        if self.som {
+            flags |= FLAG_SOM;
+        }
+        if self.eom {
+            flags |= FLAG_EOM;
+        }
+        if self.owner {
+            flags |= FLAG_TO;
+        }
+        [self.version, self.dest, self.source, flags]
+    }
+
+    /// Build a packet of this header followed by `body`
+    pub fn packet(&self, body: &[u8]) -> Vec<u8> {
+        let mut pkt = Vec::from(self.to_bytes());
+        pkt.extend_from_slice(body);
+        pkt
+    }
+}
+
+/// An MCTP control message, with an arbitrary body
+#[derive(Debug, Clone, PartialEq, Eq, Arbitrary)]
+pub struct ControlMessage {
+    /// Control message header
+    pub header: ControlHeader,
+    /// Command specific data, following the header
+    pub body: Vec<u8>,
+}
+
+impl ControlMessage {
+    /// Encode the message, starting with the message type
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut msg = Vec::from([MSG_TYPE_CONTROL.0]);
+        msg.extend_from_slice(&self.header.to_bytes());
+        msg.extend_from_slice(&self.body);
+        msg
+    }
+}
+
+/// A fault applied to the packets of a [FragmentSequence]
+///
+/// Packet indices wrap around the number of packets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Arbitrary)]
+pub enum SequenceFault {
+    /// Remove a packet
+    Drop(u8),
+    /// Send a packet twice in a row
+    Duplicate(u8),
+    /// Exchange two packets
+    Swap(u8, u8),
+    /// Cut a packet to at most `len` bytes
+    Truncate {
+        /// Index of the packet
+        packet: u8,
+        /// Remaining length
+        len: u8,
+    },
+    /// XOR a byte of a packet with `mask`, offsets wrap around the packet length
+    Corrupt {
+        /// Index of the packet
+        packet: u8,
+        /// Offset in the packet, including the header
+        offset: u16,
+        /// Bits to flip
+        mask: u8,
+    },
+}
+
+/// A message fragmented into packets, followed by faults applied to them
+#[derive(Debug, Clone, PartialEq, Eq, Arbitrary)]
+pub struct FragmentSequence {
+    /// Header of the first packet, later packets continue its sequence
+    pub header: PacketHeader,
+    /// Message type byte, including the integrity check flag
+    pub typ: u8,
+    /// Message payload
+    pub payload: Vec<u8>,
+    /// Payload bytes per packet, clamped to 1 to 512 bytes less the header
+    pub chunk: u16,
+    /// Faults applied in order
+    pub faults: Vec<SequenceFault>,
+}
+
+impl FragmentSequence {
+    /// Build the packets of the sequence
+    ///
+    /// The message type and payload are split into chunks, each packet sets SOM, EOM and
+    /// the sequence number accordingly, the remaining header fields are taken from
+    /// [header](Self::header). The faults are applied afterwards.
+    pub fn packets(&self) -> Vec<Vec<u8>> {
+        let chunk = usize::from(self.chunk).clamp(1, MAX_PACKET_LEN - HEADER_LEN);
+        let mut body = Vec::from([self.typ]);
+        body.extend_from_slice(&self.payload);
+        let count = body.len().div_ceil(chunk);
+        let mut packets: Vec<Vec<u8>> = body
+            .chunks(chunk)
+            .enumerate()
+            .map(|(i, part)| {
+                let header = PacketHeader {
+                    som: i == 0,
+                    eom: i + 1 == count,
+                    seq: self.header.seq.wrapping_add(i as u8),
+                    ..self.header
+                };
+                header.packet(part)
+            })
+            .collect();
+        for fault in &self.faults {
+            apply(&mut packets, *fault);
+        }
+        packets
+    }
+}
+
+fn apply(packets: &mut Vec<Vec<u8>>, fault: SequenceFault) {
+    let count = packets.len();
+    if count == 0 {
+        return;
+    }
+    let index = |i: u8| usize::from(i) % count;
+    match fault {
+        SequenceFault::Drop(i) => {
+            packets.remove(index(i));
+        }
+        SequenceFault::Duplicate(i) => {
+            if let Some(pkt) = packets.get(index(i)).cloned() {
+                packets.insert(index(i), pkt);
+            }
+        }
+        SequenceFault::Swap(a, b) => packets.swap(index(a), index(b)),
+        SequenceFault::Truncate { packet, len } => {
+            if let Some(pkt) = packets.get_mut(index(packet)) {
+                pkt.truncate(len.into());
+            }
+        }
+        SequenceFault::Corrupt {
+            packet,
+            offset,
+            mask,
+        } => {
+            if let Some(pkt) = packets.get_mut(index(packet))
+                && !pkt.is_empty()
+            {
+                let offset = usize::from(offset) % pkt.len();
+                if let Some(b) = pkt.get_mut(offset) {
+                    *b ^= mask;
+                }
+            }
+        }
+    }
+}
+
+/// Strategy for packet headers, mostly of the supported version
+pub fn packet_header() -> impl Strategy<Value = PacketHeader> {
+    let version = prop_oneof![4 => Just(1u8), 1 => any::<u8>()];
+    (
+        version,
+        any::<u8>(),
+        any::<u8>(),
+        any::<(bool, bool, bool)>(),
+        0u8..4,
+        0u8..8,
+    )
+        .prop_map(
+            |(version, dest, source, (som, eom, owner), seq, tag)| PacketHeader {
+                version,
+                dest,
+                source,
+                som,
+                eom,
+                seq,
+                owner,
+                tag,
+            },
+        )
+}
+
+/// Strategy for control messages with bodies of up to 64 bytes
+pub fn control_message() -> impl Strategy<Value = ControlMessage> {
+    (
+        any::<(bool, bool)>(),
+        0u8..32,
+        any::<u8>(),
+        proptest::collection::vec(any::<u8>(), 0..64),
+    )
+        .prop_map(
+            |((request, datagram), instance_id, command, body)| ControlMessage {
+                header: ControlHeader {
+                    request,
+                    datagram,
+                    instance_id,
+                    command,
+                },
+                body,
+            },
+        )
+}
+
+/// Strategy for a single packet: a header followed by up to 80 arbitrary bytes
+pub fn packet() -> impl Strategy<Value = Vec<u8>> {
+    (
+        packet_header(),
+        proptest::collection::vec(any::<u8>(), 0..80),
+    )
+        .prop_map(|(header, body)| header.packet(&body))
+}
+
+/// Strategy for fragment sequences of messages up to 1024 bytes with up to 4 faults
+pub fn fragment_sequence() -> impl Strategy<Value = FragmentSequence> {
+    let fault = prop_oneof![
+        any::<u8>().prop_map(SequenceFault::Drop),
+        any::<u8>().prop_map(SequenceFault::Duplicate),
+        any::<(u8, u8)>().prop_map(|(a, b)| SequenceFault::Swap(a, b)),
+        any::<(u8, u8)>().prop_map(|(packet, len)| SequenceFault::Truncate { packet, len }),
+        any::<(u8, u16, u8)>().prop_map(|(packet, offset, mask)| SequenceFault::Corrupt {
+            packet,
+            offset,
+            mask
+        }),
+    ];
+    (
+        packet_header(),
+        any::<u8>(),
+        proptest::collection::vec(any::<u8>(), 0..1024),
+        1u16..128,
+        proptest::collection::vec(fault, 0..4),
+    )
+        .prop_map(|(header, typ, payload, chunk, faults)| FragmentSequence {
+            header,
+            typ,
+            payload,
+            chunk,
+            faults,
+        })
+}
@@ -0,0 +1,143 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed handles for listeners and requests
+//!
+//! The [Router](crate::Router) identifies listeners and requests towards the stack with an
+//! [AppCookie]. The handles wrap it, so listener and request handles can't be mixed up.
+
+use mctp_estack::AppCookie;
+
+/// Handle of a listener, returned by [Router::listener()](crate::Router::listener)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ListenerHandle(pub(crate) AppCookie);
+
+/// Handle of a request, returned by [Router::req()](crate::Router::req)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RequestHandle(pub(crate) AppCookie);
+
+/// The sending half of a split request, see [split()](crate::GenericRouter::split)
+///
+/// Not [Clone], a single owner sends the requests.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SendHalf(pub(crate) RequestHandle);
+
+/// The receiving half of a split request, see [split()](crate::GenericRouter::split)
+///
+/// Not [Clone], a single owner receives the responses.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RecvHalf(pub(crate) RequestHandle);
+
+/// Bit of a [SendHalf] in the halves of a split request
+pub(crate) const SEND_HALF: u8 = 0x01;
+/// Bit of a [RecvHalf] in the halves of a split request
+pub(crate) const RECV_HALF: u8 = 0x02;
+
+/// How a request is accessed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Access {
+    /// Through its [RequestHandle], only while it is not split
+    Handle,
+    /// Through its [SendHalf], while the [RecvHalf] is still there to receive responses
+    Send,
+    /// Through its [RecvHalf]
+    Recv,
+}
+
+impl Access {
+    /// Check whether the request with the unreleased `halves` can be accessed this way
+    pub(crate) fn permits(self, halves: u8) -> bool {
+        match self {
+            Access::Handle => halves == 0,
+            Access::Send => halves == SEND_HALF | RECV_HALF,
+            Access::Recv => halves & RECV_HALF != 0,
+        }
+    }
+}
+
+/// Either a [ListenerHandle] or a [RequestHandle]
+///
+/// Accepted by operations that work on both, both handle types convert into it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Handle {
+    /// A listener handle
+    Listener(ListenerHandle),
+    /// A request handle
+    Request(RequestHandle),
+}
+
+impl ListenerHandle {
+    /// Get the [AppCookie] used for this listener by the stack
+    pub fn cookie(&self) -> AppCookie {
+        self.0
+    }
+}
+
+impl RequestHandle {
+    /// Get the [AppCookie] used for this request by the stack
+    pub fn cookie(&self) -> AppCookie {
+        self.0
+    }
+}
+
+impl SendHalf {
+    /// Get the handle of the split request
+    pub fn handle(&self) -> RequestHandle {
+        self.0
+    }
+}
+
+impl RecvHalf {
+    /// Get the handle of the split request
+    pub fn handle(&self) -> RequestHandle {
+        self.0
+    }
+}
+
+impl Handle {
+    /// Get the [AppCookie] used for this listener or request by the stack
+    pub fn cookie(&self) -> AppCookie {
+        match self {
+            Handle::Listener(h) => h.0,
+            Handle::Request(h) => h.0,
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ListenerHandle {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "ListenerHandle({=usize})", self.0.0);
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for RequestHandle {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "RequestHandle({=usize})", self.0.0);
+    }
+}
+
+impl From<ListenerHandle> for Handle {
+    fn from(handle: ListenerHandle) -> Self {
+        Handle::Listener(handle)
+    }
+}
+
+impl From<RequestHandle> for Handle {
+    fn from(handle: RequestHandle) -> Self {
+        Handle::Request(handle)
+    }
+}
@@ -0,0 +1,36 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Latched error status of listeners and requests
+//!
+//! Applications that only interact with a handle through receive and send loops don't see
+//! the [hooks](crate::hooks) or [events](crate::RouterEvent) explaining why its traffic
+//! stopped. The router latches the last error of each handle until
+//! [take_error()](crate::GenericRouter::take_error) collects it.
+
+use crate::Disposition;
+use crate::hooks::ExpiryReason;
+
+/// Last error encountered on behalf of a handle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HandleError {
+    /// Sending a message of the handle failed in the transport or the transmit buffer
+    SendFailed,
+    /// A message of the handle expired, attempts with a retry due are not latched
+    Expired(ExpiryReason),
+    /// A message for the handle was dropped before it was delivered, e.g. the reassembly
+    /// was aborted as the message grew beyond the maximum size of the listener
+    Dropped(Disposition),
+}
@@ -0,0 +1,101 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! MCTP transport header parsing
+//!
+//! Used to inspect packets before they are handed to the reassembly in the stack.
+
+use mctp::{Eid, Tag, TagValue};
+
+/// Length of the MCTP transport header preceding each packet payload
+pub(crate) const HEADER_LEN: usize = 4;
+
+/// Header version supported by this implementation
+const VERSION: u8 = 1;
+
+const FLAG_SOM: u8 = 0x80;
+const FLAG_EOM: u8 = 0x40;
+const FLAG_TO: u8 = 0x08;
+const SEQ_SHIFT: u8 = 4;
+const SEQ_MASK: u8 = 0x03;
+const TAG_MASK: u8 = 0x07;
+const VERSION_MASK: u8 = 0x0f;
+
+/// A parsed MCTP transport header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Header {
+    /// Destination EID
+    pub dest: Eid,
+    /// Source EID
+    pub source: Eid,
+    /// Start of message flag
+    pub som: bool,
+    /// End of message flag
+    pub eom: bool,
+    /// Packet sequence number
+    pub seq: u8,
+    /// Message tag, including the tag owner bit
+    pub tag: Tag,
+}
+
+impl Header {
+    /// Parse the header of `pkt`
+    ///
+    /// Returns `None` if `pkt` is too short to contain a header.
+    pub(crate) fn parse(pkt: &[u8]) -> Option<Header> {
+        let &[_ver, dest, source, flags, ..] = pkt else {
+            return None;
+        };
+        let tv = TagValue(flags & TAG_MASK);
+        Some(Header {
+            dest: Eid(dest),
+            source: Eid(source),
+            som: flags & FLAG_SOM != 0,
+            eom: flags & FLAG_EOM != 0,
+            seq: (flags >> SEQ_SHIFT) & SEQ_MASK,
+            tag: if flags & FLAG_TO != 0 {
+                Tag::Owned(tv)
+            } else {
+                Tag::Unowned(tv)
+            },
+        })
+    }
+
+    /// Replace the source EID in the header of `pkt`
+    pub(crate) fn set_source(pkt: &mut [u8], source: Eid) {
+        if let Some(b) = pkt.get_mut(2) {
+            *b = source.0;
+        }
+    }
+
+    /// Check whether `pkt` has a complete header of the supported version
+    pub(crate) fn is_valid(pkt: &[u8]) -> bool {
+        pkt.len() >= HEADER_LEN && pkt.first().is_some_and(|v| v & VERSION_MASK == VERSION)
+    }
+
+    /// Encode the header
+    pub(crate) fn to_bytes(self) -> [u8; HEADER_LEN] {
+        let mut flags = (self.seq & SEQ_MASK) << SEQ_SHIFT | self.tag.tag().0 & TAG_MASK;
+        if self.som {
+            flags |= FLAG_SOM;
+        }
+        if self.eom {
+            flags |= FLAG_EOM;
+        }
+        if self.tag.is_owner() {
+            flags |= FLAG_TO;
+        }
+        [VERSION, self.dest.0, self.source.0, flags]
+    }
+}
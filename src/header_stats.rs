@@ -0,0 +1,136 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Statistics of inbound transport headers, classifying the problems seen on the wire
+//!
+//! The stack rejects malformed packets with a single error. To tell a peer sending the wrong
+//! header version apart from one losing packets, the router follows the messages in progress
+//! per source and tag on its own.
+
+use mctp::{Eid, Tag};
+
+use crate::header::{HEADER_LEN, Header};
+
+/// Number of messages in progress followed by the [HeaderStats]
+///
+/// When more messages are in progress, the oldest one is no longer followed and its
+/// remaining packets are counted as [unknown_source](HeaderStats::unknown_source).
+const FLOW_TABLE_SIZE: usize = 8;
+
+/// Counters of inbound header problems, see [header_stats()](crate::GenericRouter::header_stats)
+///
+/// Packets are counted as they arrive, before validation and regardless of their
+/// destination, so forwarded traffic is included.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct HeaderStats {
+    /// Packets with a header version other than 1
+    pub bad_version: usize,
+    /// Start of message packets arriving while a message with the same source and tag is in
+    /// progress, its end of message packet was lost
+    pub bad_som_eom: usize,
+    /// Continuation packets with a sequence number not following the previous packet
+    pub unexpected_seq: usize,
+    /// Continuation packets without a message in progress for their source and tag
+    pub unknown_source: usize,
+}
+
+/// A message in progress
+#[derive(Debug, Clone, Copy)]
+struct Flow {
+    source: Eid,
+    tag: Tag,
+    seq: u8,
+    /// Start order, the lowest is evicted first
+    started: u32,
+}
+
+/// Follows inbound messages and counts their header problems
+#[derive(Debug, Default)]
+pub(crate) struct HeaderTracker {
+    flows: [Option<Flow>; FLOW_TABLE_SIZE],
+    started: u32,
+    stats: HeaderStats,
+    /// Source of the last packet counted
+    offender: Option<Eid>,
+}
+
+impl HeaderTracker {
+    /// Check the header of the inbound `pkt`
+    pub(crate) fn inspect(&mut self, pkt: &[u8]) {
+        if pkt.len() < HEADER_LEN {
+            return;
+        }
+        let Some(hdr) = Header::parse(pkt) else {
+            return;
+        };
+        if !Header::is_valid(pkt) {
+            self.stats.bad_version = self.stats.bad_version.wrapping_add(1);
+            self.offender = Some(hdr.source);
+            return;
+        }
+        let slot = self
+            .flows
+            .iter()
+            .position(|f| f.is_some_and(|f| f.source == hdr.source && f.tag == hdr.tag));
+        let flow = slot.and_then(|i| self.flows.get_mut(i));
+        let counter = if hdr.som {
+            let restarted = flow.is_some_and(|f| f.take().is_some());
+            if !hdr.eom {
+                self.start(&hdr);
+            }
+            restarted.then_some(&mut self.stats.bad_som_eom)
+        } else if let Some(flow) = flow {
+            let expected = flow.is_some_and(|f| f.seq.wrapping_add(1) & 0x03 == hdr.seq);
+            if hdr.eom || !expected {
+                *flow = None;
+            } else if let Some(f) = flow {
+                f.seq = hdr.seq;
+            }
+            (!expected).then_some(&mut self.stats.unexpected_seq)
+        } else {
+            Some(&mut self.stats.unknown_source)
+        };
+        if let Some(counter) = counter {
+            *counter = counter.wrapping_add(1);
+            self.offender = Some(hdr.source);
+        }
+    }
+
+    /// Follow the message started by `hdr`, evicting the oldest one if all slots are used
+    fn start(&mut self, hdr: &Header) {
+        let slot = match self.flows.iter().position(Option::is_none) {
+            Some(i) => self.flows.get_mut(i),
+            None => self.flows.iter_mut().min_by_key(|f| f.map(|f| f.started)),
+        };
+        if let Some(slot) = slot {
+            *slot = Some(Flow {
+                source: hdr.source,
+                tag: hdr.tag,
+                seq: hdr.seq,
+                started: self.started,
+            });
+            self.started = self.started.wrapping_add(1);
+        }
+    }
+
+    pub(crate) fn stats(&self) -> HeaderStats {
+        self.stats
+    }
+
+    pub(crate) fn offender(&self) -> Option<Eid> {
+        self.offender
+    }
+}
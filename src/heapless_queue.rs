@@ -0,0 +1,142 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Packet queues backed by [heapless]
+//!
+//! Requires the `heapless` feature.
+//!
+//! A [QueueSender] is a [Sender] passing outbound packets into a `heapless::spsc::Queue`,
+//! drained by the task or interrupt handler driving the transport.
+//! [inbound_from()] passes the packets of a queue filled by the receive side to a router.
+//!
+//! Capacities are chosen with the const generics of the queue and the packet buffers,
+//! nothing is allocated. A full queue rejects outbound packets with
+//! [NoSpace](Error::NoSpace), which the sender reports as a
+//! [busy receiver](Sender::is_receiver_busy): the router retries as configured with
+//! [RouterConfig::busy_retry()](crate::RouterConfig::busy_retry), otherwise the send fails.
+//!
+//! ```
+//! use heapless::spsc::Queue;
+//! use mctp::{Eid, MsgIC, MsgType};
+//! use mctp_lib::Router;
+//! use mctp_lib::heapless_queue::{QueueSender, QueuedPacket};
+//!
+//! let mut queue: Queue<QueuedPacket<64>, 8> = Queue::new();
+//! let (producer, mut consumer) = queue.split();
+//! let mut router: Router<_, 2, 2> = Router::new(Eid(8), 0, QueueSender::new(producer));
+//!
+//! let req = router.req(Eid(9)).unwrap();
+//! router.send(None, MsgType(1), None, MsgIC(false), req, &[0; 100]).unwrap();
+//! while let Some((eid, pkt)) = consumer.dequeue() {
+//!     assert_eq!(eid, Eid(9));
+//!     // transmit pkt
+//! }
+//! ```
+
+use heapless::Vec;
+use heapless::spsc::{Consumer, Producer};
+use mctp::{Eid, Error, Result};
+
+use crate::{Clock, GenericRouter, HandleTables, Hooks, Sender};
+
+/// An outbound packet of up to `MTU` bytes, with the EID passed to [Sender::send_packet()]
+pub type QueuedPacket<const MTU: usize> = (Eid, Vec<u8, MTU>);
+
+/// A [Sender] with an MTU of `MTU`, enqueueing packets into a queue of capacity `N`
+///
+/// The queue holds up to `N - 1` packets.
+pub struct QueueSender<'q, const N: usize, const MTU: usize> {
+    producer: Producer<'q, QueuedPacket<MTU>, N>,
+    rejected: usize,
+}
+
+impl<'q, const N: usize, const MTU: usize> QueueSender<'q, N, MTU> {
+    /// Enqueue packets with `producer`
+    pub fn new(producer: Producer<'q, QueuedPacket<MTU>, N>) -> Self {
+        QueueSender {
+            producer,
+            rejected: 0,
+        }
+    }
+
+    /// Number of packets rejected because the queue was full
+    ///
+    /// Packets sent again by a busy retry are counted for each attempt. Wraps around on
+    /// overflow.
+    pub fn rejected(&self) -> usize {
+        self.rejected
+    }
+
+    /// Check if the queue is full
+    pub fn is_full(&self) -> bool {
+        !self.producer.ready()
+    }
+}
+
+impl<const N: usize, const MTU: usize> core::fmt::Debug for QueueSender<'_, N, MTU> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("QueueSender")
+            .field("queued", &self.producer.len())
+            .field("rejected", &self.rejected)
+            .finish()
+    }
+}
+
+impl<const N: usize, const MTU: usize> Sender for QueueSender<'_, N, MTU> {
+    type Packet = [u8; MTU];
+
+    /// Enqueue `pkt`
+    ///
+    /// Returns [BadArgument](Error::BadArgument) if `pkt` is larger than `MTU`
+    /// and [NoSpace](Error::NoSpace) if the queue is full.
+    fn send_packet(&mut self, eid: Eid, pkt: &[u8]) -> Result<()> {
+        let pkt = Vec::from_slice(pkt).map_err(|_| Error::BadArgument)?;
+        self.producer.enqueue((eid, pkt)).map_err(|_| {
+            self.rejected = self.rejected.wrapping_add(1);
+            Error::NoSpace
+        })
+    }
+
+    fn get_mtu(&self) -> usize {
+        MTU
+    }
+
+    fn is_receiver_busy(&self, err: &Error) -> bool {
+        matches!(err, Error::NoSpace)
+    }
+}
+
+/// Pass all packets queued in `consumer` to `router`
+///
+/// Packets are passed with [inbound_disposition()](GenericRouter::inbound_disposition),
+/// invalid packets are dropped like any other inbound packet.
+/// Returns the number of packets passed.
+pub fn inbound_from<
+    S: Sender,
+    T: HandleTables,
+    H: Hooks,
+    C: Clock,
+    const N: usize,
+    const MTU: usize,
+>(
+    consumer: &mut Consumer<'_, Vec<u8, MTU>, N>,
+    router: &mut GenericRouter<S, T, H, C>,
+) -> usize {
+    let mut count = 0;
+    while let Some(pkt) = consumer.dequeue() {
+        router.inbound_disposition(&pkt);
+        count += 1;
+    }
+    count
+}
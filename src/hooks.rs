@@ -0,0 +1,269 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Hooks to observe and influence the processing of a [Router](crate::Router)
+//!
+//! All hook methods have a no-op default implementation,
+//! so implementations only need to provide the hooks they are interested in.
+
+use mctp::{Eid, Error, MsgIC, MsgType, Result, Tag};
+
+use crate::Handle;
+use crate::control::{SetEidDecision, SetEidRequest};
+use crate::header::HEADER_LEN;
+use crate::liveness::PeerState;
+
+/// Direction of a packet relative to the [Router](crate::Router)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Direction {
+    /// Packet received from a transport binding
+    Inbound,
+    /// Packet sent to a transport binding
+    Outbound,
+}
+
+/// Evidence of another endpoint using the EID of the [Router](crate::Router), see
+/// [Hooks::eid_conflict()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EidConflict {
+    /// A packet arrived with the EID of the router as source
+    SourcedFromOwnEid,
+    /// The peer answered a liveness probe with the EID of the router as its own
+    ReportedByPeer(Eid),
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for EidConflict {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            EidConflict::SourcedFromOwnEid => defmt::write!(f, "SourcedFromOwnEid"),
+            EidConflict::ReportedByPeer(eid) => defmt::write!(f, "ReportedByPeer({=u8})", eid.0),
+        }
+    }
+}
+
+/// Why a message was lost, see [Hooks::message_expired()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ExpiryReason {
+    /// A message delivered to the handle expired before it was received
+    Unread,
+    /// No response arrived for the last message sent by the request within the
+    /// [request timeout](crate::RouterConfig::request_timeout_millis), or to the last
+    /// attempt of its [RetryPolicy](crate::RetryPolicy)
+    NoResponse,
+    /// No response arrived within the timeout of this attempt of the
+    /// [RetryPolicy](crate::RetryPolicy), the request is due to be sent again
+    RetryDue {
+        /// Number of attempts made so far
+        attempts: u8,
+    },
+    /// The last message of the request could not be sent completely within the
+    /// [transmit timeout](crate::RouterConfig::tx_timeout_millis), its tag was released
+    TxStalled,
+    /// The last message of the request could not be sent completely before the link of its
+    /// route went down, see [link_down()](crate::GenericRouter::link_down); its tag was released
+    LinkDown,
+    /// A message sent with a [deadline](crate::SendArgs::deadline) was dropped, as pacing,
+    /// a busy receiver or a late send kept it from being sent completely in time
+    DeadlinePassed,
+}
+
+/// The first packet of an inbound message, see [Hooks::first_fragment()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FirstFragment<'a> {
+    /// Source EID
+    pub source: Eid,
+    /// Destination EID
+    pub dest: Eid,
+    /// Message tag
+    pub tag: Tag,
+    /// Message type
+    pub typ: MsgType,
+    /// Integrity check flag
+    pub ic: MsgIC,
+    /// Raw MCTP transport header of the packet
+    pub header: [u8; HEADER_LEN],
+    /// Payload of the packet, excluding the message type
+    pub payload: &'a [u8],
+}
+
+/// A response matching no outstanding request, see [Hooks::orphan_response()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrphanResponse<'a> {
+    /// Source EID
+    pub source: Eid,
+    /// Destination EID
+    pub dest: Eid,
+    /// Message tag
+    pub tag: Tag,
+    /// Message type
+    pub typ: MsgType,
+    /// Integrity check flag
+    pub ic: MsgIC,
+    /// Time of the router when the response was completed
+    pub now_millis: u64,
+    /// Payload of the reassembled message, excluding the message type
+    pub payload: &'a [u8],
+}
+
+/// An inbound packet observed in promiscuous mode, see [Hooks::snoop()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnoopedPacket<'a> {
+    /// Source EID
+    pub source: Eid,
+    /// Destination EID
+    pub dest: Eid,
+    /// Start of message flag
+    pub som: bool,
+    /// End of message flag
+    pub eom: bool,
+    /// Packet sequence number
+    pub seq: u8,
+    /// Message tag
+    pub tag: Tag,
+    /// Raw MCTP transport header of the packet
+    pub header: [u8; HEADER_LEN],
+    /// Payload of the packet, starting with the message type if `som` is set
+    pub payload: &'a [u8],
+}
+
+/// Application hooks called by a [Router](crate::Router)
+pub trait Hooks {
+    /// Called for every inbound and outbound packet
+    ///
+    /// `pkt` is the MCTP packet without transport binding header,
+    /// `now_millis` the time of the router when the packet was processed.
+    fn capture(&mut self, direction: Direction, now_millis: u64, pkt: &[u8]) {
+        let _ = (direction, now_millis, pkt);
+    }
+
+    /// Called for every valid inbound packet while the router is in promiscuous mode
+    ///
+    /// Packets are passed before any filtering, regardless of their destination EID
+    /// or whether a listener or request matches them.
+    /// See [set_promiscuous()](crate::GenericRouter::set_promiscuous).
+    fn snoop(&mut self, pkt: &SnoopedPacket<'_>) {
+        let _ = pkt;
+    }
+
+    /// Called for the first packet of every inbound message, before it is reassembled
+    ///
+    /// Returning `false` drops the packet with
+    /// [DroppedByHook](crate::Disposition::DroppedByHook) before any reassembly state
+    /// is allocated, the remaining packets of the message are then discarded by the stack.
+    fn first_fragment(&mut self, fragment: &FirstFragment<'_>) -> bool {
+        let _ = fragment;
+        true
+    }
+
+    /// Called for every reassembled response that matches no outstanding request
+    ///
+    /// Such responses are dropped with
+    /// [DroppedNoRequest](crate::Disposition::DroppedNoRequest) after the hook returns,
+    /// e.g. late responses to requests that already timed out or were released, or responses
+    /// with a tag the peer got wrong.
+    fn orphan_response(&mut self, response: &OrphanResponse<'_>) {
+        let _ = response;
+    }
+
+    /// Called after the EID of the endpoint changed from `old` to `new`
+    ///
+    /// Lets the application restart protocols that depend on the EID (e.g. discovery or
+    /// registrations with peers). Not called when the EID is set to its current value.
+    fn eid_changed(&mut self, old: Eid, new: Eid) {
+        let _ = (old, new);
+    }
+
+    /// Called when inbound traffic indicates that another endpoint uses `eid`, the EID of the
+    /// router
+    ///
+    /// Packets sourced from the own EID are dropped with
+    /// [DroppedEidConflict](crate::Disposition::DroppedEidConflict), the hook is called for
+    /// each of them. The application (or the bus owner) can recover by assigning a new EID.
+    fn eid_conflict(&mut self, eid: Eid, conflict: EidConflict) {
+        let _ = (eid, conflict);
+    }
+
+    /// Called when a message of `handle` times out in the router
+    ///
+    /// Retained messages are dropped by the stack once they are older than the reassembly
+    /// timeout, they are reported when [update()](crate::GenericRouter::update) notices, at
+    /// the latest one timeout after their delivery. Requests waiting for a response past the
+    /// request timeout are reported once.
+    fn message_expired(&mut self, handle: Handle, reason: ExpiryReason) {
+        let _ = (handle, reason);
+    }
+
+    /// Called when a monitored peer goes [Up](PeerState::Up) or [Down](PeerState::Down)
+    ///
+    /// See [monitor_peer()](crate::GenericRouter::monitor_peer).
+    fn peer_state_changed(&mut self, peer: Eid, state: PeerState) {
+        let _ = (peer, state);
+    }
+
+    /// Called for Set Endpoint ID requests passed to
+    /// [control_response()](crate::GenericRouter::control_response)
+    ///
+    /// Decides whether the router applies the request, e.g. accepting assignments only
+    /// [from the bus owner](SetEidRequest::from_bus_owner).
+    /// The default ignores the request, leaving it to the application.
+    fn set_eid_request(&mut self, request: &SetEidRequest) -> SetEidDecision {
+        let _ = request;
+        SetEidDecision::Ignore
+    }
+
+    /// Encrypt an application message for the secured session `session_id` with `eid`
+    ///
+    /// `typ` and `bufs` make up the application message. Everything following the session ID
+    /// of the secured message (length, encrypted data and MAC) is written to `out`,
+    /// returns the number of bytes written.
+    /// See [secured](crate::secured), the default returns [Unsupported](Error::Unsupported).
+    fn encrypt(
+        &mut self,
+        eid: Eid,
+        session_id: u32,
+        typ: MsgType,
+        bufs: &[&[u8]],
+        out: &mut [u8],
+    ) -> Result<usize> {
+        let _ = (eid, session_id, typ, bufs, out);
+        Err(Error::Unsupported)
+    }
+
+    /// Decrypt a secured message received from `eid` on the session `session_id`
+    ///
+    /// `secured` is everything following the session ID. The payload of the application
+    /// message is written to `out`, returns its message type and length.
+    /// See [secured](crate::secured), the default returns [Unsupported](Error::Unsupported).
+    fn decrypt(
+        &mut self,
+        eid: Eid,
+        session_id: u32,
+        secured: &[u8],
+        out: &mut [u8],
+    ) -> Result<(MsgType, usize)> {
+        let _ = (eid, session_id, secured, out);
+        Err(Error::Unsupported)
+    }
+}
+
+/// [Hooks] implementation that does nothing
+///
+/// Used by [Router](crate::Router) unless other hooks are provided.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoHooks;
+
+impl Hooks for NoHooks {}
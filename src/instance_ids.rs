@@ -0,0 +1,102 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Allocation of control message instance IDs
+//!
+//! Instance IDs are allocated per destination EID, an ID is not handed out again for the same
+//! destination while it is in use. IDs not released expire after
+//! [INSTANCE_ID_EXPIRY_MILLIS], so lost responses don't leak them.
+
+use mctp::{Eid, Error, Result};
+
+/// Number of instance IDs a [Router](crate::Router) keeps track of across all destinations
+pub const INSTANCE_ID_TABLE_SIZE: usize = 16;
+
+/// Time after which an allocated instance ID is released
+pub const INSTANCE_ID_EXPIRY_MILLIS: u64 = 5000;
+
+const INSTANCE_IDS: u8 = 32;
+
+/// An instance ID in use
+#[derive(Debug, Clone, Copy)]
+struct Allocation {
+    peer: Eid,
+    instance_id: u8,
+    expires_millis: u64,
+}
+
+/// Instance IDs in use, see [GenericRouter::alloc_instance_id()](crate::GenericRouter::alloc_instance_id)
+#[derive(Debug)]
+pub(crate) struct InstanceIds {
+    /// Next candidate, shared by all destinations
+    next: u8,
+    active: [Option<Allocation>; INSTANCE_ID_TABLE_SIZE],
+}
+
+impl InstanceIds {
+    pub(crate) const fn new() -> Self {
+        InstanceIds {
+            next: 0,
+            active: [None; INSTANCE_ID_TABLE_SIZE],
+        }
+    }
+
+    /// Allocate an instance ID for `peer` that is not in use
+    ///
+    /// Returns [NoSpace](Error::NoSpace) if all entries are in use.
+    pub(crate) fn alloc(&mut self, peer: Eid, now_millis: u64) -> Result<u8> {
+        for slot in self.active.iter_mut() {
+            if slot.is_some_and(|a| a.expires_millis <= now_millis) {
+                *slot = None;
+            }
+        }
+        for _ in 0..INSTANCE_IDS {
+            let instance_id = self.next;
+            self.next = (self.next + 1) % INSTANCE_IDS;
+            if self.in_use(peer, instance_id) {
+                continue;
+            }
+            let slot = self
+                .active
+                .iter_mut()
+                .find(|a| a.is_none())
+                .ok_or(Error::NoSpace)?;
+            *slot = Some(Allocation {
+                peer,
+                instance_id,
+                expires_millis: now_millis.saturating_add(INSTANCE_ID_EXPIRY_MILLIS),
+            });
+            return Ok(instance_id);
+        }
+        Err(Error::NoSpace)
+    }
+
+    /// Release `instance_id` of `peer`
+    ///
+    /// Returns whether it was in use.
+    pub(crate) fn release(&mut self, peer: Eid, instance_id: u8) -> bool {
+        let slot = self
+            .active
+            .iter_mut()
+            .find(|a| a.is_some_and(|a| a.peer == peer && a.instance_id == instance_id));
+        slot.and_then(Option::take).is_some()
+    }
+
+    pub(crate) fn in_use(&self, peer: Eid, instance_id: u8) -> bool {
+        self.active
+            .iter()
+            .flatten()
+            .any(|a| a.peer == peer && a.instance_id == instance_id)
+    }
+}
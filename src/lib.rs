@@ -16,7 +16,7 @@
 //!
 //! It uses the [mctp-estack](https://docs.rs/mctp-estack/latest/mctp_estack/) and re-exports most
 //! parts of it.
-#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
 #![deny(unsafe_code)]
 #![deny(missing_docs)]
 #![deny(clippy::missing_panics_doc)]
@@ -24,493 +24,13302 @@
 #![deny(clippy::panicking_overflow_checks)]
 #![deny(clippy::indexing_slicing)]
 
-use mctp::{Eid, Error, MsgIC, MsgType, Result, Tag};
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[macro_use]
+mod logging;
+
+mod acl;
+mod batch;
+mod capabilities;
+pub mod cci;
+pub mod clock;
+mod config_check;
+#[cfg(any(test, feature = "test-util"))]
+pub mod conformance;
+mod consumers;
+pub mod control;
+mod crc32c;
+#[cfg(feature = "defmt")]
+mod defmt_util;
+mod discovery;
+mod dispatch_trace;
+#[cfg(feature = "embassy")]
+pub mod embassy;
+pub mod erased;
+mod error;
+mod events;
+mod ext_reassembly;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+mod handle;
+mod handle_error;
+mod header;
+mod header_stats;
+#[cfg(feature = "heapless")]
+pub mod heapless_queue;
+pub mod hooks;
+mod instance_ids;
+mod liveness;
+mod message_types;
+mod mic;
+mod min_size;
+mod msg_pool;
+pub mod networks;
+mod padding;
+#[cfg(feature = "std")]
+pub mod pcapng;
+mod peer_stats;
+pub mod pldm;
+pub mod queue;
+mod rate_limit;
+mod recv_queue;
+mod refragment;
+mod reorder;
+mod reservations;
+mod respond;
+mod retained;
+mod retry;
+mod router_config;
+mod routes;
+mod rx_buffers;
+mod scheduling;
+pub mod secured;
+mod self_test;
+pub mod send_args;
+#[cfg(feature = "serde")]
+mod serde_util;
+pub mod shared;
+mod shutdown;
+#[cfg(feature = "std")]
+pub mod sim;
+mod size_limit;
+pub mod smbus_arp;
+mod snapshot;
+pub mod spdm;
+#[cfg(feature = "std")]
+pub mod sync;
+mod tables;
+mod tag_alloc;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+mod throttle;
+mod timers;
+mod topology;
+pub mod typed;
+mod validation;
+mod vendors;
+mod versions;
+mod wakers;
+
+use core::mem::MaybeUninit;
+use core::task::{Context, Poll, Waker};
+
+use mctp::{Eid, Error, MsgIC, MsgType, Result, Tag, TagValue};
 
 use mctp_estack::fragment::Fragmenter;
 pub use mctp_estack::*;
 
+pub use acl::EidAcl;
+pub use batch::{BATCH_HANDLES, BatchReport};
+use capabilities::Capabilities;
+pub use capabilities::Capability;
+pub use clock::{Clock, ManualClock};
+pub use config_check::{ConfigError, Timing};
+use consumers::Consumers;
+pub use consumers::{CONSUMER_TABLE_SIZE, ConsumerHandle};
+pub use discovery::NotifyRetransmit;
+use discovery::NotifySchedule;
+use dispatch_trace::DispatchTrace;
+pub use dispatch_trace::{DISPATCH_TRACE_SIZE, TraceEntry};
+pub use error::{RouterError, RouterResult};
+use events::EventQueue;
+pub use events::{EVENT_QUEUE_SIZE, RouterEvent};
+use ext_reassembly::{Admit, ExternalBuffers};
+pub use ext_reassembly::{MessageChunk, REASSEMBLY_BUFFER_TABLE_SIZE};
+use handle::{Access, RECV_HALF, SEND_HALF};
+pub use handle::{Handle, ListenerHandle, RecvHalf, RequestHandle, SendHalf};
+pub use handle_error::HandleError;
+pub use header_stats::HeaderStats;
+use header_stats::HeaderTracker;
+pub use hooks::{
+    Direction, EidConflict, ExpiryReason, FirstFragment, Hooks, NoHooks, OrphanResponse,
+    SnoopedPacket,
+};
+use instance_ids::InstanceIds;
+pub use instance_ids::{INSTANCE_ID_EXPIRY_MILLIS, INSTANCE_ID_TABLE_SIZE};
+pub use liveness::{KeepAlive, LIVENESS_TABLE_SIZE, PeerState};
+use liveness::{Monitor, Peer, Probe};
+pub use message_types::MESSAGE_TYPE_TABLE_SIZE;
+use mic::MicHandlers;
+pub use mic::{MAX_MIC_LEN, MIC_TABLE_SIZE, MessageIntegrity};
+use min_size::MinSizes;
+pub use min_size::{
+    ErrorResponder, MAX_ERROR_RESPONSE_LEN, MIN_SIZE_TABLE_SIZE, MinSize, Undersized,
+};
+pub use msg_pool::{MessagePool, PooledMessage};
+use padding::PortPadding;
+pub use padding::{PADDING_TABLE_SIZE, Padding};
+use peer_stats::PeerStatsTable;
+pub use peer_stats::{PEER_STATS_TABLE_SIZE, PeerStats};
+use rate_limit::RateLimiter;
+pub use rate_limit::{RATE_LIMIT_TABLE_SIZE, RateLimit};
+use recv_queue::{Admission, RecvQueue};
+pub use recv_queue::{OverflowPolicy, QueueLimit};
+pub use refragment::REFRAGMENT_FLOWS;
+use refragment::{Refragment, Refragmenter};
+pub use reorder::MAX_REORDER_WINDOW;
+use reorder::{Order, ReorderBuffer};
+use reservations::Reservations;
+pub use reservations::{MAX_REASSEMBLIES, RESERVATION_TABLE_SIZE};
+pub use respond::{ReplyContext, Responder};
+use retained::RetainedMessages;
+use retry::TypePolicies;
+pub use retry::{Backoff, RETRY_POLICY_TABLE_SIZE, RetryPolicy};
+pub use router_config::RouterConfig;
+pub use routes::{
+    FORWARD_STATS_PORTS, ForwardStats, NextHop, PortStats, ROUTE_FAILBACK_MILLIS,
+    ROUTE_FAILOVER_THRESHOLD, ROUTE_TABLE_SIZE, Route,
+};
+use routes::{Failover, Hop, LinkStates, Routes};
+use rx_buffers::RxPool;
+pub use rx_buffers::{RX_BUFFER_POOL_SIZE, RxBuffer};
+use scheduling::Scheduler;
+pub use scheduling::Scheduling;
+use secured::{SecuredInfo, Sessions};
+use self_test::SELF_TEST_TYPE;
+pub use self_test::{SELF_TEST_MAX_LEN, SelfTestReport};
+pub use send_args::SendArgs;
+use send_args::{Payload, SendKind};
+pub use shutdown::{ShutdownPolicy, ShutdownReport};
+use size_limit::{SizeCheck, SizeTracker};
+pub use snapshot::{RouterSnapshot, SNAPSHOT_MAX_LEN};
+#[cfg(feature = "alloc")]
+pub use tables::VecTables;
+pub use tables::{ArrayTables, HandleTables, LISTENER_GROUP_SIZE};
+pub use tag_alloc::TagAllocation;
+use topology::Topology;
+pub use topology::{TOPOLOGY_TABLE_SIZE, TopologyEntry};
+use validation::ViolationCounters;
+pub use validation::{VIOLATION_CLASSES, Validation, Violation};
+pub use vendors::VENDOR_TABLE_SIZE;
+pub use versions::{BASE_SPEC_VERSION, VERSION_BASE_SPEC, VERSION_TABLE_SIZE};
+
+use crc32c::{Crc32c, IC_LEN};
+use tables::{Cookies, INTERNAL_COOKIE, ListenerEntry, ReqHandle, SHARED_COOKIE, Slot};
+use throttle::Throttles;
+pub use throttle::{THROTTLE_TABLE_SIZE, Throttle};
+pub use timers::WorkBudget;
+use timers::{Allowance, NextDeadline};
+pub use wakers::WAKER_TABLE_SIZE;
+use wakers::{Interest, Wakers};
+
+/// Number of entries in the per-destination MTU table of a [Router]
+pub const MTU_TABLE_SIZE: usize = 16;
+
+/// Maximum number of EIDs a router accepts traffic for besides its own EID
+///
+/// See [GenericRouter::add_local_eid()].
+pub const LOCAL_EID_TABLE_SIZE: usize = 4;
+
+/// Maximum number of buffers of a message sent with an integrity check or a MIC
+///
+/// See [GenericRouter::send_vectored()].
+pub const MAX_IC_BUFS: usize = 15;
+
+/// Time after the last packet an incomplete message is discarded, as in the stack
+const REASSEMBLY_TIMEOUT_MILLIS: u64 = 6000;
+
+/// Bytes of a control request kept to answer it without a listener
+///
+/// Covers the request data of all commands handled by
+/// [control_response()](GenericRouter::control_response), longer requests are truncated.
+const CONTROL_REQUEST_LEN: usize = 16;
+
+/// State of an active request, see [GenericRouter::requests()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestInfo {
+    /// Handle of the request
+    pub handle: RequestHandle,
+    /// Destination EID
+    pub eid: Eid,
+    /// Tag of the last request sent, `None` if nothing was sent or the response was received
+    pub tag: Option<Tag>,
+    /// Milliseconds since the request handle was allocated
+    pub age_millis: u64,
+    /// Value attached with [set_handle_context()](GenericRouter::set_handle_context)
+    pub context: usize,
+}
+
+/// Progress of a message being reassembled, see [GenericRouter::reassemblies()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReassemblyInfo {
+    /// Source EID
+    pub source: Eid,
+    /// Message tag, including the tag owner bit
+    pub tag: Tag,
+    /// Message type
+    pub typ: MsgType,
+    /// Payload bytes received so far, without the message type
+    pub bytes: usize,
+    /// Milliseconds since the first packet was received
+    pub age_millis: u64,
+    /// Milliseconds since the last packet was received
+    ///
+    /// The message is discarded when no packet was received for 6 seconds.
+    pub idle_millis: u64,
+}
+
+/// Outcome of a message sent with [send_vectored()](GenericRouter::send_vectored)
+///
+/// The router passes all packets to the [Sender] right away, messages are only delayed when
+/// they go through a [SendQueue](queue::SendQueue) or packets are held back by a [Throttle].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SendReport {
+    /// Tag the message was sent with
+    pub tag: Tag,
+    /// Packets passed to the [Sender], including held ones
+    pub packets: usize,
+    /// Bytes passed to the [Sender], including MCTP headers
+    pub bytes: usize,
+    /// The message was held in a [SendQueue](queue::SendQueue) before it was sent
+    pub queued: bool,
+    /// Packets held back by a [Throttle], passed to the [Sender] by
+    /// [poll()](GenericRouter::poll) later
+    pub held: usize,
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for SendReport {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "SendReport {{ tag: {}, packets: {=usize}, bytes: {=usize}, queued: {=bool}, held: {=usize} }}",
+            defmt_util::FmtTag(self.tag),
+            self.packets,
+            self.bytes,
+            self.queued,
+            self.held
+        );
+    }
+}
+
+/// Counters of the send path, see [GenericRouter::tx_stats()]
+///
+/// Messages are fragmented straight from the buffers passed to
+/// [send_vectored()](GenericRouter::send_vectored), each packet is assembled in a scratch
+/// buffer of a single packet. `copied_bytes` counts the bytes copied there, which is the only
+/// copy of the payload on the send path.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TxStats {
+    /// Messages sent
+    pub messages: usize,
+    /// Packets sent for these messages
+    pub packets: usize,
+    /// Bytes copied to assemble the packets, including headers
+    pub copied_bytes: usize,
+    /// Packets sent again after the receiver was busy, see [BusyRetry]
+    pub busy_retries: usize,
+    /// Messages dropped as their [deadline](SendArgs::deadline) passed
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub expired: usize,
+}
+
+/// How packets are retried when the receiver is busy, see [RouterConfig::busy_retry()]
+///
+/// When the [Sender] reports a failed packet as
+/// [receiver busy](Sender::is_receiver_busy), the router waits `delay_millis` using
+/// [Clock::delay_millis()] and passes the same packet again, up to `max_retries` times
+/// before the send fails with the error of the last attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BusyRetry {
+    /// Time to wait before sending the packet again
+    pub delay_millis: u64,
+    /// Retries of a single packet
+    pub max_retries: u8,
+}
+
+impl BusyRetry {
+    /// Retry up to `max_retries` times, `delay_millis` apart
+    pub fn new(delay_millis: u64, max_retries: u8) -> Self {
+        BusyRetry {
+            delay_millis,
+            max_retries,
+        }
+    }
+}
+
+/// What happened to a packet passed to [Router::inbound_disposition()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum Disposition {
+    /// A message was completed and delivered to the listener or request with this handle
+    Delivered(Handle),
+    /// The packet was accepted, but the message is not complete yet
+    Incomplete,
+    /// The packet was forwarded to another bus, see [set_forwarding()](GenericRouter::set_forwarding)
+    Forwarded,
+    /// A request was dropped because no listener is bound for its message type
+    DroppedNoListener,
+    /// A response was dropped because it is not associated with an active request
+    DroppedNoRequest,
+    /// A message was dropped because it is addressed to a different EID
+    DroppedWrongEid,
+    /// A request was dropped because the [EidAcl] of the listener rejects its source
+    DroppedAccessDenied,
+    /// The packet was dropped because its source exceeded the [RateLimit]
+    DroppedRateLimited,
+    /// The first packet of a message was rejected by [Hooks::first_fragment()]
+    DroppedByHook,
+    /// A message was dropped because the receive queue of its handle is full
+    ///
+    /// See [set_queue_limit()](GenericRouter::set_queue_limit).
+    DroppedQueueFull,
+    /// Like [DroppedQueueFull](Disposition::DroppedQueueFull), for handles with the
+    /// [Reject](OverflowPolicy::Reject) policy
+    RejectedQueueFull,
+    /// A secured message was dropped because its session is not open
+    ///
+    /// See [open_session()](GenericRouter::open_session).
+    DroppedNoSession,
+    /// A message with the IC bit set was dropped because its CRC-32C does not match
+    DroppedIntegrityError,
+    /// A request was dropped because it exceeds the maximum message size of the listener
+    ///
+    /// See [set_listener_max_size()](GenericRouter::set_listener_max_size).
+    DroppedTooLarge,
+    /// A message was dropped because it is empty or shorter than the minimum of its type
+    ///
+    /// See [set_min_size()](GenericRouter::set_min_size).
+    DroppedTooSmall,
+    /// A packet to be forwarded was dropped because the router is quiesced
+    ///
+    /// See [quiesce()](GenericRouter::quiesce).
+    DroppedQuiesced,
+    /// A packet to be forwarded was dropped because the link of its route is down
+    ///
+    /// See [link_down()](GenericRouter::link_down).
+    DroppedLinkDown,
+    /// The packet was dropped because it violates the specification
+    ///
+    /// See [set_validation()](GenericRouter::set_validation).
+    DroppedInvalid(Violation),
+    /// The packet was rejected by the reassembly (malformed, out of sequence, out of space)
+    DroppedReassemblyError,
+    /// Processing the packet failed for another reason, e.g. forwarding it or sending the
+    /// response to a control request
+    ///
+    /// [inbound()](GenericRouter::inbound) returns the error.
+    Failed,
+    /// The packet was dropped because it is sourced from the EID of the router
+    ///
+    /// See [Hooks::eid_conflict()].
+    DroppedEidConflict,
+    /// A response was dropped because its message type differs from the type of the request
+    DroppedTypeMismatch,
+    /// A message was dropped because all reassembly contexts are in use
+    ///
+    /// See [set_max_reassemblies()](GenericRouter::set_max_reassemblies).
+    DroppedNoContext,
+    /// A message was dropped because the free reassembly contexts are reserved for other
+    /// message types
+    ///
+    /// See [reserve_contexts()](GenericRouter::reserve_contexts).
+    DroppedContextReserved,
+    /// A response to a liveness probe was consumed by the router
+    ///
+    /// See [monitor_peer()](GenericRouter::monitor_peer).
+    ProbeAnswered,
+    /// A response to Discovery Notify was consumed by the router
+    ///
+    /// See [set_notify_retransmit()](GenericRouter::set_notify_retransmit).
+    NotifyAnswered,
+    /// A control request without a listener was answered by the router
+    ///
+    /// See [set_manual_control()](GenericRouter::set_manual_control).
+    ControlAnswered,
+}
+
+impl Disposition {
+    /// Get the handle of the listener or request a message was delivered to
+    ///
+    /// Returns `None` for all dispositions but [Delivered](Disposition::Delivered).
+    pub fn handle(&self) -> Option<Handle> {
+        match self {
+            Disposition::Delivered(handle) => Some(*handle),
+            _ => None,
+        }
+    }
+
+    /// Check if the packet was dropped
+    pub fn is_dropped(&self) -> bool {
+        matches!(
+            self,
+            Disposition::DroppedNoListener
+                | Disposition::DroppedNoRequest
+                | Disposition::DroppedWrongEid
+                | Disposition::DroppedAccessDenied
+                | Disposition::DroppedRateLimited
+                | Disposition::DroppedByHook
+                | Disposition::DroppedTooLarge
+                | Disposition::DroppedTooSmall
+                | Disposition::DroppedIntegrityError
+                | Disposition::DroppedNoSession
+                | Disposition::DroppedQueueFull
+                | Disposition::RejectedQueueFull
+                | Disposition::DroppedQuiesced
+                | Disposition::DroppedLinkDown
+                | Disposition::DroppedInvalid(_)
+                | Disposition::DroppedReassemblyError
+                | Disposition::Failed
+                | Disposition::DroppedEidConflict
+                | Disposition::DroppedTypeMismatch
+                | Disposition::DroppedContextReserved
+                | Disposition::DroppedNoContext
+        )
+    }
+}
+
+/// The error of a packet that could not be processed, with the [Disposition] it is
+/// traced and counted as
 #[derive(Debug)]
-struct ReqHandle {
+struct Rejected {
+    err: Error,
+    disposition: Disposition,
+}
+
+impl From<Error> for Rejected {
+    fn from(err: Error) -> Self {
+        Rejected {
+            err,
+            disposition: Disposition::Failed,
+        }
+    }
+}
+
+impl From<Rejected> for Error {
+    fn from(rejected: Rejected) -> Self {
+        rejected.err
+    }
+}
+
+/// Metadata of a received message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageInfo {
+    /// Source EID
+    pub source: Eid,
     /// Destination EID
-    eid: Eid,
-    /// Tag from last send operation
+    pub dest: Eid,
+    /// Message tag
+    pub tag: Tag,
+    /// Message type
+    pub typ: MsgType,
+    /// Integrity check flag
+    pub ic: MsgIC,
+    /// Payload length in bytes (excluding the message type and integrity check)
+    pub len: usize,
+    /// Time the final fragment arrived, as read from the [Clock] of the router
     ///
-    /// Has to be cleared upon receiving a response.
-    // A no-expire option might be added as a future improvement.
-    last_tag: Option<Tag>,
+    /// `None` for metadata taken from a message with `MessageInfo::from(&msg)`, see
+    /// [received_millis()](GenericRouter::received_millis).
+    pub received_millis: Option<u64>,
 }
-impl ReqHandle {
-    fn new(eid: Eid) -> ReqHandle {
-        ReqHandle {
-            eid,
-            last_tag: None,
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for RequestInfo {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "RequestInfo {{ handle: {}, eid: {=u8}, tag: {}, age_millis: {=u64}, context: {=usize} }}",
+            self.handle,
+            self.eid.0,
+            self.tag.map(defmt_util::FmtTag),
+            self.age_millis,
+            self.context
+        );
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for MessageInfo {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "MessageInfo {{ source: {=u8}, dest: {=u8}, tag: {}, typ: {=u8}, ic: {=bool}, len: {=usize}, received_millis: {} }}",
+            self.source.0,
+            self.dest.0,
+            defmt_util::FmtTag(self.tag),
+            self.typ.0,
+            self.ic.0,
+            self.len,
+            self.received_millis
+        );
+    }
+}
+
+impl From<&MctpMessage<'_>> for MessageInfo {
+    /// The metadata of `msg`, e.g. to [respond()](GenericRouter::respond) to it later
+    fn from(msg: &MctpMessage<'_>) -> Self {
+        MessageInfo {
+            source: msg.source,
+            dest: msg.dest,
+            tag: msg.tag,
+            typ: msg.typ,
+            ic: msg.ic,
+            len: message_body(msg).len(),
+            received_millis: None,
         }
     }
 }
 
+/// An entry in the per-destination MTU table
+#[derive(Debug, Clone, Copy)]
+struct MtuEntry {
+    eid: Eid,
+    mtu: usize,
+    /// Learned by MTU discovery rather than configured statically
+    ///
+    /// Learned entries never replace static ones.
+    learned: bool,
+    /// When a learned entry was last updated, the oldest one is evicted first
+    learned_millis: u64,
+}
+
 /// A platform-agnostic MCTP stack with routing
 ///
 /// Only a single port/bus is supported
+///
+/// The listener and request handles are stored in [HandleTables],
+/// usually this is used through the [Router] alias with tables sized at compile time.
+/// Handles and channels don't depend on the table sizes; code that stores a router without
+/// caring about its sizes can use a [BorrowedRouter] instead.
+///
+/// Application [Hooks] can be supplied with [new_with_hooks()](Router::new_with_hooks),
+/// further options with [new_with_config()](Router::new_with_config).
+///
+/// All timestamps are read from a [Clock].
+/// Routers created with an explicit `now_millis` use a [ManualClock] that is advanced by
+/// [update()](Self::update), [new_with_clock()](Router::new_with_clock) takes any clock.
 #[derive(Debug)]
-pub struct Router<S: Sender, const MAX_LISTENER_HANDLES: usize, const MAX_REQ_HANDLES: usize> {
+pub struct GenericRouter<S: Sender, T: HandleTables, H: Hooks = NoHooks, C: Clock = ManualClock> {
     stack: Stack,
     sender: S,
-    /// Listener handles
-    ///
-    /// The index is used to construct the AppCookie.
-    listeners: [Option<MsgType>; MAX_LISTENER_HANDLES],
-    /// Request handles
+    hooks: H,
+    clock: C,
+    /// Listener and request handles
+    tables: T,
+    /// Per-destination MTU overrides
     ///
-    /// The index is used to construct the AppCookie.
-    requests: [Option<ReqHandle>; MAX_REQ_HANDLES],
+    /// Consulted when building the fragmenter for outbound messages.
+    mtu_overrides: [Option<MtuEntry>; MTU_TABLE_SIZE],
+    /// Learn peer MTUs from inbound traffic
+    mtu_discovery: bool,
+    /// Ports whose link is down
+    links: LinkStates,
+    /// Health of the primary next hops of the routes
+    failover: Failover,
+    /// Send Discovery Notify when a link comes up
+    discovery_notify: bool,
+    /// A Discovery Notify is due in the next update
+    notify_pending: bool,
+    /// Retransmission of the last Discovery Notify
+    notify: NotifySchedule,
+    /// Pass packets for other EIDs on to the sender
+    forwarding: bool,
+    /// Counters of forwarded packets
+    forward_stats: ForwardStats,
+    /// Forwarded messages refragmented for a smaller MTU
+    refragmenter: Refragmenter<S::Packet>,
+    /// How handles share receive processing and bus time
+    scheduler: Scheduler,
+    /// Static routes to EIDs behind bridges
+    routes: Routes,
+    /// Pass all valid inbound packets to the snoop hook
+    promiscuous: bool,
+    /// Leave control requests the router can't answer to the application
+    manual_control: bool,
+    /// Sending and forwarding is stopped
+    quiesced: bool,
+    /// All handles were unbound by [shutdown()](Self::shutdown)
+    shut_down: bool,
+    /// Treatment of out-of-spec packets
+    validation: Validation,
+    /// Out-of-spec packets seen
+    violations: ViolationCounters,
+    /// Header problems of inbound packets
+    headers: HeaderTracker,
+    /// Traffic counters per peer
+    peer_stats: PeerStatsTable,
+    /// Counters of the send path
+    tx_stats: TxStats,
+    /// Inbound packets sourced from the own EID
+    eid_conflicts: usize,
+    /// UUID of this endpoint
+    uuid: Option<[u8; 16]>,
+    /// ID of the MCTP network this endpoint is part of
+    network_id: Option<[u8; 16]>,
+    /// EID configured at creation, restored by a Set Endpoint ID reset
+    static_eid: Eid,
+    /// Further EIDs of logical endpoints hosted by this router
+    local_eids: [Option<Eid>; LOCAL_EID_TABLE_SIZE],
+    /// How the EID is assigned, reported by Get Endpoint ID
+    eid_type: control::EidType,
+    /// Role reported by Get Endpoint ID
+    endpoint_type: control::EndpointType,
+    /// Message types, versions and vendor command sets implemented by the application
+    capabilities: Capabilities,
+    /// Endpoints learned from control responses
+    topology: Topology,
+    /// Requester of the last accepted Set Endpoint ID
+    bus_owner: Option<Eid>,
+    /// Control message instance IDs in use
+    instance_ids: InstanceIds,
+    /// Peers probed for liveness
+    monitor: Monitor,
+    /// Application-provided reassembly buffers of listeners
+    reassembly_buffers: ExternalBuffers,
+    /// Reassembly contexts reserved for message types
+    reservations: Reservations,
+    /// Delivery times of retained messages, to report their expiry
+    retained: RetainedMessages,
+    /// Further consumers of listeners and the messages held for them
+    consumers: Consumers,
+    /// Tasks waiting on handles
+    wakers: Wakers,
+    /// Retry policies of message types
+    retry_policies: TypePolicies,
+    /// MIC handlers of message types
+    mics: MicHandlers,
+    /// Minimum sizes of message types
+    min_sizes: MinSizes,
+    /// Time after which outstanding requests are abandoned
+    request_timeout_millis: Option<u64>,
+    /// Time after which sending a message is abandoned
+    tx_timeout_millis: Option<u64>,
+    /// Time after which the tags of requests are reclaimed, `None` leaves it to the stack
+    tag_reclaim_millis: Option<u64>,
+    /// How owned tags of requests are allocated
+    tag_allocation: TagAllocation,
+    /// Work done per call of [poll()](Self::poll)
+    work_budget: WorkBudget,
+    /// Events waiting for [next_event()](Self::next_event)
+    events: EventQueue,
+    /// Recent routing decisions, see [dispatch_trace()](Self::dispatch_trace)
+    dispatch_trace: DispatchTrace,
+    /// Retries of packets the receiver was too busy for
+    busy_retry: Option<BusyRetry>,
+    /// Per-source inbound packet limit
+    rate_limiter: Option<RateLimiter>,
+    /// Per-destination outbound bandwidth limits and the packets held back by them
+    throttles: Throttles,
+    /// Requests being reassembled for size limited listeners
+    size_tracker: SizeTracker,
+    /// Continuation packets held back until the packets before them arrived
+    reorder: ReorderBuffer<S::Packet>,
+    /// Open secured message sessions
+    sessions: Sessions,
+    /// Receive buffers waiting to be lent to the transport
+    rx_pool: RxPool,
+    /// Padding of the packets sent on each port
+    padding: PortPadding,
 }
 
+/// A [GenericRouter] with handle tables sized at compile time
+pub type Router<
+    S,
+    const MAX_LISTENER_HANDLES: usize,
+    const MAX_REQ_HANDLES: usize,
+    H = NoHooks,
+    C = ManualClock,
+> = GenericRouter<S, ArrayTables<MAX_LISTENER_HANDLES, MAX_REQ_HANDLES>, H, C>;
+
+/// A [Router] for endpoints that only answer requests, without request handles
+///
+/// No RAM is spent on request slots, e.g. for the smallest RoT devices that never initiate
+/// a transaction. [req()](GenericRouter::req) fails with [NoSpace](Error::NoSpace) and
+/// responses are dropped with [DroppedNoRequest](Disposition::DroppedNoRequest).
+pub type ResponderRouter<S, const MAX_LISTENER_HANDLES: usize, H = NoHooks, C = ManualClock> =
+    Router<S, MAX_LISTENER_HANDLES, 0, H, C>;
+
+/// A [Router] for endpoints that only send requests, without listeners
+///
+/// No RAM is spent on listener slots, e.g. for a requester querying its peers.
+/// [listener()](GenericRouter::listener) fails with [NoSpace](Error::NoSpace) and requests
+/// are dropped with [DroppedNoListener](Disposition::DroppedNoListener).
+pub type RequesterRouter<S, const MAX_REQ_HANDLES: usize, H = NoHooks, C = ManualClock> =
+    Router<S, 0, MAX_REQ_HANDLES, H, C>;
+
+/// A [GenericRouter] with borrowed handle tables of any size
+///
+/// The table sizes are not part of the type, so structs storing the router and functions
+/// taking it don't need the const generics of [Router]. The tables are usually an
+/// [ArrayTables] in a `static` or on the stack of the task running the router:
+///
+/// ```
+/// # use mctp::{Eid, Result};
+/// # struct NullSender;
+/// # impl mctp_lib::Sender for NullSender {
+/// #     type Packet = [u8; 64];
+/// #     fn send_packet(&mut self, _eid: Eid, _pkt: &[u8]) -> Result<()> { Ok(()) }
+/// #     fn get_mtu(&self) -> usize { 64 }
+/// # }
+/// use mctp_lib::{ArrayTables, BorrowedRouter, ManualClock, NoHooks, RouterConfig};
+///
+/// struct App<'t> {
+///     router: BorrowedRouter<'t, NullSender>,
+/// }
+///
+/// let mut tables = ArrayTables::<4, 2>::new();
+/// let config = RouterConfig::new(Eid(8));
+/// let router = BorrowedRouter::new_with_tables(
+///     config, ManualClock::new(0), NullSender, NoHooks, &mut tables,
+/// );
+/// let app = App { router };
+/// ```
+pub type BorrowedRouter<'t, S, H = NoHooks, C = ManualClock> =
+    GenericRouter<S, &'t mut dyn HandleTables, H, C>;
+
+/// A [GenericRouter] with handle tables sized at runtime
+#[cfg(feature = "alloc")]
+pub type VecRouter<S, H = NoHooks, C = ManualClock> = GenericRouter<S, VecTables, H, C>;
+
 impl<S: Sender, const MAX_LISTENER_HANDLES: usize, const MAX_REQ_HANDLES: usize>
     Router<S, MAX_LISTENER_HANDLES, MAX_REQ_HANDLES>
 {
     /// Create a new `Router` that routes `outbound` trafic to [S](Sender)
     pub fn new(own_eid: Eid, now_millis: u64, outbound: S) -> Self {
-        let stack = Stack::new(own_eid, now_millis);
-        Router {
+        Self::new_with_hooks(own_eid, now_millis, outbound, NoHooks)
+    }
+}
+
+impl<S: Sender, const MAX_LISTENER_HANDLES: usize, const MAX_REQ_HANDLES: usize, H: Hooks>
+    Router<S, MAX_LISTENER_HANDLES, MAX_REQ_HANDLES, H>
+{
+    /// Create a new `Router` that routes `outbound` trafic to [S](Sender)
+    /// and calls the application `hooks`
+    pub fn new_with_hooks(own_eid: Eid, now_millis: u64, outbound: S, hooks: H) -> Self {
+        Self::new_with_config(RouterConfig::new(own_eid), now_millis, outbound, hooks)
+    }
+
+    /// Create a new `Router` from a [RouterConfig]
+    ///
+    /// Routes `outbound` trafic to [S](Sender) and calls the application `hooks`,
+    /// pass [NoHooks] when none are needed.
+    pub fn new_with_config(config: RouterConfig, now_millis: u64, outbound: S, hooks: H) -> Self {
+        Self::new_with_clock(config, ManualClock::new(now_millis), outbound, hooks)
+    }
+
+    /// Create a new `Router` from a [RouterConfig], validating it first
+    ///
+    /// Like [new_with_config()](Self::new_with_config), see
+    /// [try_new_with_tables()](GenericRouter::try_new_with_tables) for the checks.
+    pub fn try_new_with_config(
+        config: RouterConfig,
+        now_millis: u64,
+        outbound: S,
+        hooks: H,
+    ) -> core::result::Result<Self, ConfigError> {
+        Self::try_new_with_tables(
+            config,
+            ManualClock::new(now_millis),
+            outbound,
+            hooks,
+            ArrayTables::new(),
+        )
+    }
+}
+
+impl<S: Sender, const MAX_LISTENER_HANDLES: usize, const MAX_REQ_HANDLES: usize, H: Hooks, C: Clock>
+    Router<S, MAX_LISTENER_HANDLES, MAX_REQ_HANDLES, H, C>
+{
+    /// Create a new `Router` from a [RouterConfig] that reads the time from `clock`
+    ///
+    /// Routes `outbound` trafic to [S](Sender) and calls the application `hooks`,
+    /// pass [NoHooks] when none are needed.
+    /// Use [poll()](Self::poll) to update the stack.
+    pub fn new_with_clock(config: RouterConfig, clock: C, outbound: S, hooks: H) -> Self {
+        Self::new_with_tables(config, clock, outbound, hooks, ArrayTables::new())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S: Sender, H: Hooks, C: Clock> VecRouter<S, H, C> {
+    /// Create a new `Router` with room for `listeners` listener and `requests` request handles
+    ///
+    /// Routes `outbound` trafic to [S](Sender), calls the application `hooks`
+    /// (pass [NoHooks] when none are needed) and reads the time from `clock`.
+    /// See [VecTables::new()] for the limits of the table sizes.
+    pub fn new_with_capacity(
+        config: RouterConfig,
+        clock: C,
+        outbound: S,
+        hooks: H,
+        listeners: usize,
+        requests: usize,
+    ) -> Self {
+        let tables = VecTables::new(listeners, requests);
+        Self::new_with_tables(config, clock, outbound, hooks, tables)
+    }
+}
+
+impl<S: Sender, T: HandleTables, H: Hooks> GenericRouter<S, T, H, ManualClock> {
+    /// Update the stack
+    ///
+    /// Advances the clock of the router to `now_millis`, see [poll()](Self::poll).
+    pub fn update(&mut self, now_millis: u64) -> Result<u64> {
+        self.clock.set(now_millis);
+        self.poll()
+    }
+}
+
+impl<S: Sender, T: HandleTables, H: Hooks, C: Clock> GenericRouter<S, T, H, C> {
+    /// Create a new `Router` from a [RouterConfig], storing handles in `tables`
+    ///
+    /// Routes `outbound` trafic to [S](Sender), calls the application `hooks`
+    /// (pass [NoHooks] when none are needed) and reads the time from `clock`.
+    pub fn new_with_tables(
+        config: RouterConfig,
+        clock: C,
+        outbound: S,
+        hooks: H,
+        tables: T,
+    ) -> Self {
+        let stack = Stack::new(config.own_eid, clock.now_millis());
+        GenericRouter {
             stack,
             sender: outbound,
-            listeners: [None; MAX_LISTENER_HANDLES],
-            requests: [const { None }; MAX_REQ_HANDLES],
+            hooks,
+            clock,
+            tables,
+            mtu_overrides: config.mtus.map(|x| {
+                x.map(|(eid, mtu, learned)| MtuEntry {
+                    eid,
+                    mtu,
+                    learned,
+                    learned_millis: 0,
+                })
+            }),
+            mtu_discovery: config.mtu_discovery,
+            links: LinkStates::default(),
+            failover: Failover::default(),
+            discovery_notify: config.discovery_notify,
+            notify_pending: false,
+            notify: NotifySchedule::new(config.notify_retransmit),
+            forwarding: config.forwarding,
+            refragmenter: Refragmenter::new(config.refragment),
+            scheduler: Scheduler::new(config.scheduling),
+            forward_stats: ForwardStats::default(),
+            routes: config.routes,
+            promiscuous: config.promiscuous,
+            manual_control: config.manual_control,
+            quiesced: false,
+            shut_down: false,
+            validation: config.validation,
+            violations: ViolationCounters::default(),
+            headers: HeaderTracker::default(),
+            peer_stats: PeerStatsTable::new(),
+            tx_stats: TxStats::default(),
+            eid_conflicts: 0,
+            uuid: config.uuid,
+            network_id: config.network_id,
+            static_eid: config.static_eid.unwrap_or(config.own_eid),
+            local_eids: [None; LOCAL_EID_TABLE_SIZE],
+            eid_type: config.eid_type,
+            endpoint_type: config.endpoint_type,
+            capabilities: Capabilities {
+                declared_only: config.declared_types_only,
+                ..Capabilities::new()
+            },
+            topology: Topology::new(),
+            bus_owner: None,
+            instance_ids: InstanceIds::new(),
+            monitor: Monitor::new(config.keep_alive),
+            reassembly_buffers: ExternalBuffers::new(),
+            reservations: Reservations::new(config.max_reassemblies.unwrap_or(MAX_REASSEMBLIES)),
+            retained: RetainedMessages::new(),
+            consumers: Consumers::new(),
+            wakers: Wakers::new(),
+            retry_policies: TypePolicies::new(),
+            mics: MicHandlers::new(),
+            min_sizes: MinSizes::new(),
+            request_timeout_millis: config.request_timeout_millis,
+            tx_timeout_millis: config.tx_timeout_millis,
+            tag_reclaim_millis: config.tag_reclaim_millis,
+            tag_allocation: config.tag_allocation,
+            work_budget: config.work_budget,
+            events: EventQueue::new(config.event_queue),
+            dispatch_trace: DispatchTrace::new(config.dispatch_trace),
+            busy_retry: config.busy_retry,
+            rate_limiter: config.rate_limit.map(RateLimiter::new),
+            throttles: Throttles::default(),
+            size_tracker: SizeTracker::new(),
+            reorder: ReorderBuffer::new(config.reorder_window),
+            sessions: Sessions::new(),
+            rx_pool: RxPool::new(),
+            padding: PortPadding::new(),
         }
     }
 
-    /// Update the stack
+    /// Create a new `Router` from a [RouterConfig] like [new_with_tables()](Self::new_with_tables),
+    /// validating the configuration first
+    ///
+    /// Rejects tables with more slots than cookies can address, a [Sender] or static MTU
+    /// that can't carry an MCTP header and payload, timeouts and intervals finer than the
+    /// [resolution](Clock::resolution_millis) of `clock`, and a [RateLimit] that admits no
+    /// packets. [new_with_tables()](Self::new_with_tables) accepts such configurations, the
+    /// router then misbehaves, e.g. by failing every send or expiring requests right away.
+    pub fn try_new_with_tables(
+        config: RouterConfig,
+        clock: C,
+        outbound: S,
+        hooks: H,
+        tables: T,
+    ) -> core::result::Result<Self, ConfigError> {
+        let handles = tables.listeners().len() + tables.requests().len();
+        config_check::check(
+            &config,
+            handles,
+            outbound.get_mtu(),
+            clock.resolution_millis(),
+        )?;
+        Ok(Self::new_with_tables(
+            config, clock, outbound, hooks, tables,
+        ))
+    }
+
+    /// Create a new `Router` in `slot`, see [new_with_tables()](Self::new_with_tables)
+    ///
+    /// For routers living in a `static`, e.g. `slot` is the `&'static mut MaybeUninit`
+    /// of a `StaticCell::uninit()`. This is always inlined, which lets the compiler build the
+    /// router directly in `slot` instead of on the stack of the caller.
+    ///
+    /// The handle tables can be initialized at compile time, as [ArrayTables::new()] is a
+    /// `const fn`: place them in a `static` of their own and use a [BorrowedRouter].
+    #[inline(always)]
+    pub fn init_in_place(
+        slot: &mut MaybeUninit<Self>,
+        config: RouterConfig,
+        clock: C,
+        outbound: S,
+        hooks: H,
+        tables: T,
+    ) -> &mut Self {
+        slot.write(Self::new_with_tables(
+            config, clock, outbound, hooks, tables,
+        ))
+    }
+
+    /// Update the stack with the current time of the clock
     ///
-    /// Returns an interval value in milliseconds in which the next call to `update()` should be
-    /// issued.
+    /// Returns an interval value in milliseconds in which the next call to `poll()` should be
+    /// issued: the time until the earliest deadline of the stack, outstanding requests,
+    /// retained messages, reassemblies and liveness probes.
+    /// With a [WorkBudget] set, work left over is carried to the next call and 0 is returned.
     ///
     /// Note:
     /// It is the obligation of the implementer to wake up expired receive calls. However,
     /// this may be changed in future versions.
-    pub fn update(&mut self, now_millis: u64) -> Result<u64> {
-        self.stack.update(now_millis).map(|x| x.0)
+    pub fn poll(&mut self) -> Result<u64> {
+        self.poll_expired().map(|(timeout, _)| timeout)
     }
 
-    /// Provide an incoming packet to the router.
-    ///
-    /// This expects a single MCTP packet, without a transport binding header.
-    ///
-    /// Returns `Ok(Some(AppCookie))` for a associated listener or request,
-    /// or `Ok(None)` if the message was discarded.
-    pub fn inbound(&mut self, pkt: &[u8]) -> Result<Option<AppCookie>> {
-        let own_eid = self.stack.eid();
-        let Some(mut msg) = self.stack.receive(pkt)? else {
-            return Ok(None);
+    /// Update the stack, also reporting whether anything timed out
+    fn poll_expired(&mut self) -> Result<(u64, bool)> {
+        let now_millis = self.clock.now_millis();
+        let (timeout, mut expired) = self.stack.update(now_millis)?;
+        let mut next = NextDeadline::new(now_millis);
+        next.after(Some(timeout));
+        let mut timers = Allowance::new(self.work_budget.timers);
+        if expired {
+            debug!("flows or reassemblies timed out at {} ms", now_millis);
+            lifecycle!("reassembly expired", now_millis = now_millis);
+        }
+        for i in 0..self.tables.requests().len() {
+            let cookie = self.tables.request_cookie(i)?;
+            let Some(req) = self
+                .tables
+                .requests_mut()
+                .get_mut(i)
+                .and_then(|s| s.entry.as_mut())
+            else {
+                continue;
+            };
+            if let Some((tag, deadline)) = req.reclaim_at {
+                if now_millis < deadline {
+                    next.at(Some(deadline));
+                } else if !timers.take() {
+                    next.at(Some(now_millis));
+                    continue;
+                } else {
+                    debug!("tag {} of request to {} reclaimed", tag.tag().0, req.eid.0);
+                    self.stack.cancel_flow(req.eid, tag.tag());
+                    req.reclaim_at = None;
+                    if req.last_tag.take().is_some() {
+                        req.attempt = 0;
+                        req.error = Some(HandleError::Expired(ExpiryReason::NoResponse));
+                        expired = true;
+                        self.wakers.wake(RequestHandle(cookie).into());
+                        self.hooks.message_expired(
+                            RequestHandle(cookie).into(),
+                            ExpiryReason::NoResponse,
+                        );
+                        self.events.push(RouterEvent::Expired(
+                            RequestHandle(cookie).into(),
+                            ExpiryReason::NoResponse,
+                        ));
+                    }
+                    continue;
+                }
+            }
+            if let Some(started) = req.tx_started
+                && let Some(timeout) = self.tx_timeout_millis
+            {
+                let deadline = started.saturating_add(timeout);
+                if now_millis < deadline {
+                    next.at(Some(deadline));
+                    continue;
+                }
+                if !timers.take() {
+                    next.at(Some(now_millis));
+                    continue;
+                }
+                warn!("sending to {} stalled, releasing the request", req.eid.0);
+                if let Some(tag) = req.last_tag.take() {
+                    self.stack.cancel_flow(req.eid, tag.tag());
+                }
+                req.reclaim_at = None;
+                req.tx_started = None;
+                req.tx_stalled = true;
+                req.attempt = 0;
+                req.error = Some(HandleError::Expired(ExpiryReason::TxStalled));
+                expired = true;
+                self.wakers.wake(RequestHandle(cookie).into());
+                self.hooks
+                    .message_expired(RequestHandle(cookie).into(), ExpiryReason::TxStalled);
+                self.events.push(RouterEvent::Expired(
+                    RequestHandle(cookie).into(),
+                    ExpiryReason::TxStalled,
+                ));
+                continue;
+            }
+            let Some(tag) = req.last_tag else {
+                continue;
+            };
+            let policy = req
+                .retry
+                .or_else(|| req.typ.and_then(|t| self.retry_policies.get(t)));
+            let seed = cookie.0 as u32 ^ u32::from(req.eid.0) << 16;
+            let deadline = match policy {
+                Some(p) => retry::deadline(p, req.attempt, seed, req.sent_millis),
+                None => match self.request_timeout_millis {
+                    Some(timeout) => req.sent_millis.saturating_add(timeout),
+                    None => continue,
+                },
+            };
+            if now_millis < deadline {
+                next.at(Some(deadline));
+                continue;
+            }
+            if !timers.take() {
+                next.at(Some(now_millis));
+                continue;
+            }
+            debug!(
+                "request to {} with tag {} timed out",
+                req.eid.0,
+                tag.tag().0
+            );
+            lifecycle!("request expired", eid = req.eid.0, tag = tag.tag().0);
+            self.stack.cancel_flow(req.eid, tag.tag());
+            req.last_tag = None;
+            req.reclaim_at = None;
+            expired = true;
+            req.attempt = req.attempt.saturating_add(1);
+            let reason = if policy.is_some_and(|p| req.attempt < p.max_attempts()) {
+                ExpiryReason::RetryDue {
+                    attempts: req.attempt,
+                }
+            } else {
+                req.attempt = 0;
+                req.error = Some(HandleError::Expired(ExpiryReason::NoResponse));
+                ExpiryReason::NoResponse
+            };
+            self.wakers.wake(RequestHandle(cookie).into());
+            self.hooks
+                .message_expired(RequestHandle(cookie).into(), reason);
+            self.events
+                .push(RouterEvent::Expired(RequestHandle(cookie).into(), reason));
+        }
+        while timers.take()
+            && let Some(cookie) = self.retained.take_expired(now_millis)
+        {
+            let handle = match self.tables.listener_mut(cookie) {
+                Some(l) => {
+                    l.queue.queued = l.queue.queued.saturating_sub(1);
+                    Handle::from(ListenerHandle(cookie))
+                }
+                None => {
+                    let Some(r) = self.tables.request_mut(cookie) else {
+                        continue;
+                    };
+                    r.queue.queued = r.queue.queued.saturating_sub(1);
+                    Handle::from(RequestHandle(cookie))
+                }
+            };
+            debug!("message retained for {} expired unread", cookie.0);
+            Self::latch_error(
+                &mut self.tables,
+                handle,
+                HandleError::Expired(ExpiryReason::Unread),
+            );
+            self.wakers.wake(handle);
+            self.hooks.message_expired(handle, ExpiryReason::Unread);
+            self.events
+                .push(RouterEvent::Expired(handle, ExpiryReason::Unread));
+            expired = true;
+        }
+        next.at(self.retained.next_deadline());
+        self.consumers.expire(now_millis);
+        self.reservations.expire(now_millis);
+        self.size_tracker.expire(now_millis);
+        next.at(self.reservations.next_deadline());
+        if self.reassembly_buffers.expire(now_millis) {
+            debug!("reassembly into listener buffer timed out");
+            lifecycle!("reassembly expired", now_millis = now_millis);
+            expired = true;
+        }
+        next.at(self.reassembly_buffers.next_deadline());
+        next.after(self.probe_peers(now_millis));
+        self.release_held(now_millis);
+        next.at(self.throttles.next_deadline());
+        if self.notify_pending || self.notify.due(now_millis) {
+            match self.send_discovery_notify(now_millis) {
+                Ok(()) => self.notify_pending = false,
+                Err(_) => warn!("failed to send discovery notify, retrying"),
+            }
+        }
+        next.at(self.notify.next_deadline());
+        Ok((next.interval().unwrap_or(timeout), expired))
+    }
+
+    /// Send Discovery Notify to the bus owner, the null EID if it is not known yet
+    fn send_discovery_notify(&mut self, now_millis: u64) -> Result<()> {
+        let eid = self.bus_owner.unwrap_or(Eid(0));
+        let instance_id = self.instance_ids.alloc(eid, now_millis)?;
+        let header = control::ControlHeader {
+            request: true,
+            datagram: false,
+            instance_id,
+            command: control::CMD_DISCOVERY_NOTIFY,
+        }
+        .to_bytes();
+        let frag = self.start_message(
+            eid,
+            control::MSG_TYPE_CONTROL,
+            None,
+            MsgIC(false),
+            Some(INTERNAL_COOKIE),
+            None,
+            Some(header.len()),
+        );
+        let sent =
+            frag.and_then(|frag| self.transmit(eid, frag, &[&header], now_millis, None, None));
+        if let Err(e) = sent {
+            self.instance_ids.release(eid, instance_id);
+            return Err(e);
+        }
+        debug!("sent discovery notify to {}", eid.0);
+        let first = self.notify_pending;
+        if let Some((eid, instance_id)) = self.notify.sent(eid, instance_id, now_millis, first) {
+            self.instance_ids.release(eid, instance_id);
+        }
+        Ok(())
+    }
+
+    /// Consume a response to Discovery Notify, ending its retransmission
+    fn notify_answered(&mut self, source: Eid, instance_id: u8) -> Disposition {
+        let Some((eid, instance_id)) = self.notify.answered(source, instance_id) else {
+            debug!(
+                "dropped unexpected discovery notify response from {}",
+                source.0
+            );
+            return Disposition::DroppedNoRequest;
         };
+        self.instance_ids.release(eid, instance_id);
+        debug!("discovery notify answered by {}", source.0);
+        Disposition::NotifyAnswered
+    }
 
-        if msg.dest != own_eid && msg.dest != Eid(0) {
-            // Drop messages if eid does not match (for now).
-            // EID 0 messages are used for physical addressing
-            // and will thus be processed.
-            return Ok(None);
+    /// Stop retransmitting Discovery Notify
+    fn cancel_notify(&mut self) {
+        if let Some((eid, instance_id)) = self.notify.cancel() {
+            self.instance_ids.release(eid, instance_id);
         }
+    }
 
-        match msg.tag {
-            Tag::Unowned(_) => {
-                // check for matching requests
-                if let Some(cookie) = msg.cookie()
-                    && Self::requests_index_from_cookie(cookie)
-                        .is_some_and(|i| self.requests.get(i).is_some_and(|r| r.is_some()))
-                {
-                    msg.retain();
-                    return Ok(Some(cookie));
+    /// Time out and send liveness probes
+    ///
+    /// Returns the time until a monitored peer needs attention again.
+    fn probe_peers(&mut self, now_millis: u64) -> Option<u64> {
+        let keep_alive = self.monitor.keep_alive?;
+        let mut timeout = None;
+        for i in 0..LIVENESS_TABLE_SIZE {
+            let Some(mut peer) = self.monitor.peers.get(i).copied().flatten() else {
+                continue;
+            };
+            let max_failures = self
+                .monitor
+                .policy
+                .map_or(keep_alive.max_failures, |p| p.max_attempts().max(1));
+            if let Some(probe) = peer.probe
+                && probe.deadline_millis <= now_millis
+            {
+                debug!("liveness probe to {} timed out", peer.eid.0);
+                self.stack.cancel_flow(peer.eid, probe.tag);
+                self.instance_ids.release(peer.eid, probe.instance_id);
+                if peer.failed(max_failures) {
+                    self.hooks.peer_state_changed(peer.eid, PeerState::Down);
+                    self.events
+                        .push(RouterEvent::PeerStateChanged(peer.eid, PeerState::Down));
+                }
+                if self.monitor.policy.is_some() && peer.state != PeerState::Down {
+                    // Retried at once rather than at the next interval
+                    peer.next_probe_millis = now_millis;
                 }
-                // In this case an unowned message not associated with a request was received.
-                // This might happen if this endpoint was intended to route the packet to a different
-                // bus it is connected to (bridge configuration).
-                // Support for this is missing right now.
             }
-            Tag::Owned(_) => {
-                // check for matching listeners and retain with cookie
-                for i in 0..self.listeners.len() {
-                    if self.listeners.get(i).ok_or(Error::InternalError)? == &Some(msg.typ) {
-                        let cookie = Some(Self::listener_cookie_from_index(i));
-                        msg.set_cookie(cookie);
-                        msg.retain();
-                        return Ok(cookie);
+            if peer.probe.is_none() && peer.next_probe_millis <= now_millis && !self.quiesced {
+                peer.next_probe_millis = now_millis.saturating_add(keep_alive.interval_millis);
+                match self.send_probe(&peer, &keep_alive, now_millis) {
+                    Ok(probe) => peer.probe = Some(probe),
+                    // No instance ID free, try again at the next interval
+                    Err(Error::NoSpace) => (),
+                    Err(_) => {
+                        warn!("failed to send liveness probe to {}", peer.eid.0);
+                        if peer.failed(max_failures) {
+                            self.hooks.peer_state_changed(peer.eid, PeerState::Down);
+                            self.events
+                                .push(RouterEvent::PeerStateChanged(peer.eid, PeerState::Down));
+                        }
                     }
                 }
             }
+            let next = peer
+                .probe
+                .map_or(peer.next_probe_millis, |p| p.deadline_millis);
+            let next = next.saturating_sub(now_millis);
+            timeout = Some(timeout.map_or(next, |t: u64| t.min(next)));
+            if let Some(slot) = self.monitor.peers.get_mut(i) {
+                *slot = Some(peer);
+            }
         }
-
-        // Return Ok(()) even if a message has been discarded
-        Ok(None)
+        timeout
     }
 
-    /// Allocate a new request "_Handle_"
-    pub fn req(&mut self, eid: Eid) -> Result<AppCookie> {
-        for (index, handle) in self.requests.iter_mut().enumerate() {
-            if handle.is_none() {
-                let _ = handle.insert(ReqHandle::new(eid));
-                return Ok(Self::req_cookie_from_index(index));
+    /// Send the probe of `keep_alive` to `eid`
+    fn send_probe(
+        &mut self,
+        peer: &Peer,
+        keep_alive: &KeepAlive,
+        now_millis: u64,
+    ) -> Result<Probe> {
+        let eid = peer.eid;
+        let instance_id = self.instance_ids.alloc(eid, now_millis)?;
+        let header = control::ControlHeader {
+            request: true,
+            datagram: false,
+            instance_id,
+            command: keep_alive.command,
+        };
+        let header = header.to_bytes();
+        let frag = self.start_message(
+            eid,
+            control::MSG_TYPE_CONTROL,
+            None,
+            MsgIC(false),
+            Some(INTERNAL_COOKIE),
+            None,
+            Some(header.len()),
+        );
+        let sent =
+            frag.and_then(|frag| self.transmit(eid, frag, &[&header], now_millis, None, None));
+        match sent {
+            Ok(report) => Ok(Probe {
+                tag: report.tag.tag(),
+                instance_id,
+                deadline_millis: match self.monitor.policy {
+                    Some(p) => retry::deadline(p, peer.failures(), eid.0.into(), now_millis),
+                    None => now_millis.saturating_add(keep_alive.timeout_millis),
+                },
+            }),
+            Err(e) => {
+                self.instance_ids.release(eid, instance_id);
+                Err(e)
             }
         }
-        Err(mctp::Error::NoSpace)
     }
 
-    /// Allocate a new listener for [`typ`](MsgType)
-    ///
-    /// Returns an [AppCookie] when successful, [AddrInUse](mctp::Error::AddrInUse) when a listener
-    /// for `typ` already exists,
-    /// [NoSpace](mctp::Error::NoSpace) when all listener slots are occupied.
-    pub fn listener(&mut self, typ: MsgType) -> Result<AppCookie> {
-        if self.listeners.iter().any(|x| x == &Some(typ)) {
-            return Err(mctp::Error::AddrInUse);
+    /// Account for a response to a liveness probe from `source` with `tag`
+    fn probe_answered(
+        &mut self,
+        source: Eid,
+        tag: TagValue,
+        header: Option<control::ControlHeader>,
+        reported: Option<Eid>,
+    ) -> Disposition {
+        let command = self.monitor.keep_alive.map(|k| k.command);
+        let Some(peer) = self.monitor.probed(source, tag) else {
+            debug!("dropped response from {}, no probe outstanding", source.0);
+            return Disposition::DroppedNoRequest;
+        };
+        let Some(probe) = peer.probe else {
+            return Disposition::DroppedNoRequest;
+        };
+        let expected = command.map(|command| control::ControlHeader {
+            request: false,
+            datagram: false,
+            instance_id: probe.instance_id,
+            command,
+        });
+        if header.is_none() || header != expected {
+            debug!("dropped unexpected probe response from {}", source.0);
+            return Disposition::DroppedNoRequest;
         }
-        for (index, handle) in self.listeners.iter_mut().enumerate() {
-            if handle.is_none() {
-                let _ = handle.insert(typ);
-                return Ok(Self::listener_cookie_from_index(index));
-            }
+        let up = peer.answered();
+        self.instance_ids.release(source, probe.instance_id);
+        if up {
+            self.hooks.peer_state_changed(source, PeerState::Up);
+            self.events
+                .push(RouterEvent::PeerStateChanged(source, PeerState::Up));
+        }
+        let own_eid = self.stack.eid();
+        if reported == Some(own_eid) && own_eid != Eid(0) {
+            warn!("peer {} reports own eid {}", source.0, own_eid.0);
+            self.hooks
+                .eid_conflict(own_eid, EidConflict::ReportedByPeer(source));
         }
-        Err(mctp::Error::NoSpace)
+        Disposition::ProbeAnswered
     }
 
-    /// Get the currently configured _Eid_ for this endpoint
-    pub fn get_eid(&self) -> Eid {
-        self.stack.eid()
+    /// Get a reference to the clock
+    pub fn clock(&self) -> &C {
+        &self.clock
     }
 
-    /// Set the _Eid_ for this endpoint
-    pub fn set_eid(&mut self, eid: Eid) -> Result<()> {
-        self.stack.set_eid(eid.0)
+    /// Get the UUID of this endpoint, if configured
+    pub fn uuid(&self) -> Option<[u8; 16]> {
+        self.uuid
     }
 
-    /// Send a message
+    /// Stop sending, e.g. ahead of a low-power transition or transport reconfiguration
     ///
-    /// When responding to a request received by a listener, `eid` and `tag` have to be set.
-    /// A request usually won't set an ` eid `.
-    /// When no `tag` is supplied for a request, a new one will be allocated.
-    pub fn send(
-        &mut self,
-        eid: Option<Eid>,
-        typ: MsgType,
-        tag: Option<Tag>,
-        ic: MsgIC,
-        cookie: AppCookie,
-        buf: &[u8],
-    ) -> Result<Tag> {
-        self.send_vectored(eid, typ, tag, ic, cookie, &[buf])
+    /// While quiesced, sends fail with [TxFailure](Error::TxFailure) and packets for other
+    /// EIDs are dropped instead of forwarded, so the [Sender] is not used.
+    /// Messages are fragmented and sent within a single send call, so no fragmented message
+    /// is left in flight.
+    /// Inbound packets are still processed and responses to outstanding requests delivered.
+    ///
+    /// Returns whether the stack is idle already, see [is_idle()](Self::is_idle).
+    pub fn quiesce(&mut self) -> bool {
+        debug!("quiesced");
+        self.quiesced = true;
+        self.is_idle()
     }
 
-    /// Send a vectored message
+    /// Resume sending after [quiesce()](Self::quiesce)
     ///
-    /// When responding to a request received by a listener, `eid` and `tag` have to be set.
-    /// A request usually won't set an `eid`.
-    /// When no `tag` is supplied for a request, a new one will be allocated.
-    ///
-    /// The `bufs` will be copied to a new buffer with `MAX_PAYLOAD` size.
-    pub fn send_vectored(
-        &mut self,
-        eid: Option<Eid>,
-        typ: MsgType,
-        tag: Option<Tag>,
-        ic: MsgIC,
-        cookie: AppCookie,
-        bufs: &[&[u8]],
-    ) -> Result<Tag> {
-        let Some(eid) = eid.or(self.lookup_request(cookie).map(|r| r.eid)) else {
-            return Err(Error::InvalidInput);
-        };
-        let frag = self.stack.start_send(
-            eid,
-            typ,
-            tag,
-            true,
-            ic,
-            Some(self.sender.get_mtu()),
-            Some(cookie),
-        )?;
+    /// A router that was [shut down](Self::shutdown) stays quiesced.
+    pub fn resume(&mut self) {
+        if self.shut_down {
+            return;
+        }
+        debug!("resumed");
+        self.quiesced = false;
+    }
 
-        self.sender.send_vectored(eid, frag, bufs)
+    /// Check if the router is quiesced
+    pub fn is_quiesced(&self) -> bool {
+        self.quiesced
     }
 
-    /// Receive a message associated with a [`AppCookie`]
+    /// Tear the router down, e.g. to rebuild the stack after a bus fault
     ///
-    /// Returns `None` when no message is available for the listener/request.
-    ///
-    /// The message can be retained and received at a later point again (see [MctpMessage::retain()]).
-    pub fn recv(&mut self, cookie: AppCookie) -> Option<mctp_estack::MctpMessage<'_>> {
-        self.stack.get_deferred_bycookie(&[cookie])
+    /// Packets held back by a [Throttle] are sent or dropped according to `policy`, then all
+    /// listeners and requests are unbound, cancelling their flows and waking their tasks.
+    /// The router stays [quiesced](Self::quiesce) for good: binding new handles, sending,
+    /// receiving into a sink and unbinding fail with [Other](Error::Other) from now on,
+    /// rather than the [BadArgument](Error::BadArgument) of a stale handle, so tasks sharing
+    /// the router can tell that it went away. Get the [Sender] back with
+    /// [into_sender()](Self::into_sender).
+    pub fn shutdown(&mut self, policy: ShutdownPolicy) -> ShutdownReport {
+        let now_millis = self.clock.now_millis();
+        let mut report = ShutdownReport::default();
+        if policy == ShutdownPolicy::Flush && !self.quiesced {
+            let (sender, hooks) = (&mut self.sender, &mut self.hooks);
+            let (routes, failover, links) = (&self.routes, &self.failover, &self.links);
+            let port_padding = &self.padding;
+            let mut failed = 0;
+            report.flushed = self.throttles.flush(|eid, pkt| {
+                hooks.capture(Direction::Outbound, now_millis, pkt);
+                let route =
+                    routes::select(routes, failover, links, eid, now_millis).map(|h| h.route);
+                let padding = port_padding.for_route(route.as_ref());
+                let sent = send_packet(sender, eid, route.as_ref(), padding, pkt).is_ok();
+                if !sent {
+                    failed += 1;
+                }
+                sent
+            });
+            report.dropped = failed;
+        }
+        report.dropped += self.throttles.discard(|_| true);
+
+        for i in 0..self.tables.listeners().len() {
+            let bound = self
+                .tables
+                .listeners()
+                .get(i)
+                .is_some_and(|s| s.entry.is_some());
+            if bound && let Ok(cookie) = self.tables.listener_cookie(i) {
+                let _ = self.unbind_inner(ListenerHandle(cookie).into());
+                report.unbound += 1;
+            }
+        }
+        for i in 0..self.tables.requests().len() {
+            let Some(waiting) = self
+                .tables
+                .requests()
+                .get(i)
+                .and_then(|s| s.entry.as_ref())
+                .map(|r| r.last_tag.is_some())
+            else {
+                continue;
+            };
+            if let Ok(cookie) = self.tables.request_cookie(i) {
+                let _ = self.unbind_inner(RequestHandle(cookie).into());
+                report.unbound += 1;
+                report.cancelled += usize::from(waiting);
+            }
+        }
+        debug!("shut down, {} handles unbound", report.unbound);
+        self.quiesced = true;
+        self.shut_down = true;
+        report
     }
 
-    /// Unbind a listener/request
+    /// Check if the router was [shut down](Self::shutdown)
+    pub fn is_shut_down(&self) -> bool {
+        self.shut_down
+    }
+
+    /// Consume the router, returning the [Sender]
     ///
-    /// This has to be called to free the request/listener slot.
-    /// Returns [BadArgument](Error::BadArgument) for cookies that are malformed or non-existent.
-    pub fn unbind(&mut self, cookie: AppCookie) -> Result<()> {
-        if Self::cookie_is_listener(&cookie) {
-            self.listeners
-                .get_mut(Self::listeners_index_from_cookie(cookie).ok_or(Error::BadArgument)?)
-                .ok_or(Error::InternalError)?
+    /// Typically called after [shutdown()](Self::shutdown) to pass the transport on to the
+    /// rebuilt stack.
+    pub fn into_sender(self) -> S {
+        self.sender
+    }
+
+    /// Check if no request is waiting for a response
+    ///
+    /// Requests become idle when their response is received, when they time out
+    /// (see [RouterConfig::request_timeout_millis()]) or when they are unbound.
+    pub fn is_idle(&self) -> bool {
+        self.tables
+            .requests()
+            .iter()
+            .filter_map(|s| s.entry.as_ref())
+            .all(|r| r.last_tag.is_none())
+    }
+
+    /// Take a snapshot of the state to keep across a warm reboot
+    ///
+    /// Restore it with [RouterConfig::restore()], see [RouterSnapshot].
+    pub fn snapshot(&self) -> RouterSnapshot {
+        RouterSnapshot {
+            own_eid: self.stack.eid(),
+            static_eid: self.static_eid,
+            uuid: self.uuid,
+            network_id: self.network_id,
+            mtus: self
+                .mtu_overrides
+                .map(|x| x.map(|e| (e.eid, e.mtu, e.learned))),
+            routes: self.routes,
+        }
+    }
+
+    /// Get the ID of the MCTP network this endpoint is part of, if configured
+    pub fn network_id(&self) -> Option<[u8; 16]> {
+        self.network_id
+    }
+
+    /// Set or remove the network ID
+    ///
+    /// The network ID is a UUID distinguishing MCTP networks in topologies with more than one.
+    /// It is reported by the Get Network ID control command, see [control_response()](Self::control_response).
+    pub fn set_network_id(&mut self, network_id: Option<[u8; 16]>) {
+        self.network_id = network_id;
+    }
+
+    /// Get how the EID of this endpoint is assigned, see [RouterConfig::eid_type()]
+    pub fn eid_type(&self) -> control::EidType {
+        self.eid_type
+    }
+
+    /// Get the role of this endpoint, see [RouterConfig::endpoint_type()]
+    pub fn endpoint_type(&self) -> control::EndpointType {
+        self.endpoint_type
+    }
+
+    /// Declare a message type implemented by the application
+    ///
+    /// The type is reported by Get Message Type Support, its versions by Get MCTP Version
+    /// Support and its vendor command sets by Get Vendor Defined Message Support, see
+    /// [control_response()](Self::control_response). Declaring a type again replaces its
+    /// versions and adds further command sets. Nothing is registered unless the whole
+    /// capability fits.
+    ///
+    /// Returns [BadArgument](Error::BadArgument) for the control message type and for command
+    /// sets of a vendor not matching the type, [NoSpace](Error::NoSpace) if
+    /// [MESSAGE_TYPE_TABLE_SIZE] types, [VERSION_TABLE_SIZE] versioned types or
+    /// [VENDOR_TABLE_SIZE] command sets are registered.
+    pub fn declare_capability(&mut self, capability: &Capability<'_>) -> Result<()> {
+        self.capabilities.declare(capability)
+    }
+
+    /// Withdraw the declaration of `typ` together with its versions and vendor command sets
+    ///
+    /// Returns whether `typ` was declared. Types of bound listeners are still reported by
+    /// Get Message Type Support.
+    pub fn withdraw_capability(&mut self, typ: MsgType) -> bool {
+        self.capabilities.withdraw(typ)
+    }
+
+    /// Check whether `typ` was declared or has versions registered
+    pub fn is_declared(&self, typ: MsgType) -> bool {
+        self.capabilities.is_declared(typ)
+    }
+
+    /// Set whether listeners can only be bound to declared message types
+    ///
+    /// When set, [listener()](Self::listener) and [rebind()](Self::rebind) fail with
+    /// [BadArgument](Error::BadArgument) for types neither declared with
+    /// [declare_capability()](Self::declare_capability) nor registered otherwise, so each
+    /// protocol served is also reported to peers. Listeners bound already are kept.
+    pub fn set_declared_types_only(&mut self, declared_only: bool) {
+        self.capabilities.declared_only = declared_only;
+    }
+
+    /// Describe this endpoint like the peers returned by [topology()](Self::topology)
+    ///
+    /// Reports the EID, the UUID and the [message_types()](Self::message_types) of the
+    /// router, for exporting the topology including the local endpoint.
+    pub fn local_endpoint(&self) -> TopologyEntry {
+        TopologyEntry {
+            eid: self.stack.eid(),
+            assigned: false,
+            uuid: self.uuid,
+            message_types: Some(self.message_types()),
+            route: None,
+            state: None,
+        }
+    }
+
+    /// Register the versions of the protocol carried by message type `typ`
+    ///
+    /// Each version is encoded as major, minor, update and alpha byte, e.g.
+    /// `[0xf1, 0xf2, 0xff, 0x00]` for 1.2. They are reported by Get MCTP Version Support,
+    /// see [control_response()](Self::control_response). The versions of the base
+    /// specification ([VERSION_BASE_SPEC]) and of control messages are registered as
+    /// [BASE_SPEC_VERSION] and can be replaced. An empty `versions` removes `typ`.
+    ///
+    /// Returns [NoSpace](Error::NoSpace) for more than
+    /// [MAX_VERSIONS](control::codec::MAX_VERSIONS) versions or more than
+    /// [VERSION_TABLE_SIZE] message types.
+    pub fn register_versions(&mut self, typ: MsgType, versions: &[[u8; 4]]) -> Result<()> {
+        self.capabilities.versions.set(typ.0, versions)
+    }
+
+    /// Register a vendor command set implemented by the application
+    ///
+    /// Command sets are reported by Get Vendor Defined Message Support in the order they were
+    /// registered, see [control_response()](Self::control_response).
+    /// Registering a command set again has no effect.
+    /// Returns [NoSpace](Error::NoSpace) if [VENDOR_TABLE_SIZE] sets are registered.
+    pub fn add_vendor_support(&mut self, support: control::VendorSupport) -> Result<()> {
+        self.capabilities.vendors.add(support)
+    }
+
+    /// Remove the command sets of `vendor`
+    ///
+    /// Returns whether any was registered.
+    pub fn remove_vendor_support(&mut self, vendor: control::VendorId) -> bool {
+        self.capabilities.vendors.remove(vendor)
+    }
+
+    /// Report support for message type `typ` without a listener for it
+    ///
+    /// Message types of bound listeners are reported by Get Message Type Support
+    /// automatically, see [control_response()](Self::control_response). Types handled
+    /// elsewhere, e.g. through the catch-all listener, have to be registered.
+    /// Registering a type again has no effect.
+    /// Returns [NoSpace](Error::NoSpace) if [MESSAGE_TYPE_TABLE_SIZE] types are registered.
+    pub fn add_message_type(&mut self, typ: MsgType) -> Result<()> {
+        self.capabilities.types.add(typ)
+    }
+
+    /// Remove a message type registered with [add_message_type()](Self::add_message_type)
+    ///
+    /// Returns whether it was registered. Types of bound listeners are still reported.
+    pub fn remove_message_type(&mut self, typ: MsgType) -> bool {
+        self.capabilities.types.remove(typ)
+    }
+
+    /// Get the message types reported by Get Message Type Support
+    ///
+    /// The types of the bound listeners and the registered types in ascending order,
+    /// without the control message type.
+    pub fn message_types(&self) -> control::MessageTypes {
+        let listeners = self
+            .tables
+            .listeners()
+            .iter()
+            .filter_map(|s| s.entry.as_ref())
+            .flat_map(|l| l.types());
+        let types = listeners
+            .chain(self.capabilities.types.iter())
+            .filter(|t| t.0 != control::MSG_TYPE_CONTROL.0);
+        let mut out = [MsgType(0); control::MAX_MESSAGE_TYPES];
+        let len = message_types::collect(types, &mut out);
+        control::MessageTypes::new(out.get(..len).unwrap_or_default()).unwrap_or_default()
+    }
+
+    /// Get the requester of the last accepted Set Endpoint ID, taken as the bus owner
+    pub fn bus_owner(&self) -> Option<Eid> {
+        self.bus_owner
+    }
+
+    /// Allocate an instance ID for a control request to `peer`
+    ///
+    /// The ID is not handed out again for `peer` until it is released with
+    /// [release_instance_id()](Self::release_instance_id) or
+    /// [INSTANCE_ID_EXPIRY_MILLIS] have passed, so concurrent control transactions toward the
+    /// same peer don't clash.
+    /// Returns [NoSpace](Error::NoSpace) if [INSTANCE_ID_TABLE_SIZE] IDs are in use.
+    pub fn alloc_instance_id(&mut self, peer: Eid) -> Result<u8> {
+        self.instance_ids.alloc(peer, self.clock.now_millis())
+    }
+
+    /// Release an instance ID allocated with [alloc_instance_id()](Self::alloc_instance_id)
+    ///
+    /// Returns whether the ID was in use.
+    pub fn release_instance_id(&mut self, peer: Eid, instance_id: u8) -> bool {
+        self.instance_ids.release(peer, instance_id)
+    }
+
+    /// Build the response to a control `request` from `source` answered from router state
+    ///
+    /// `request` is the control message as received, starting with the [ControlHeader](control::ControlHeader).
+    /// Handles
+    /// - Get Endpoint ID, reporting the [EndpointType](control::EndpointType) and
+    ///   [EidType](control::EidType) of [RouterConfig].
+    /// - Get MCTP Version Support, reporting the versions registered with
+    ///   [register_versions()](Self::register_versions). Unregistered message types fail
+    ///   with [CC_UNSUPPORTED_MSG_TYPE](control::CC_UNSUPPORTED_MSG_TYPE).
+    /// - Get Message Type Support, reporting the [message_types()](Self::message_types).
+    /// - Get Vendor Defined Message Support, reporting the command sets registered with
+    ///   [add_vendor_support()](Self::add_vendor_support). Selectors past the last set fail
+    ///   with [CC_ERROR_INVALID_DATA](control::CC_ERROR_INVALID_DATA).
+    /// - Get Network ID, which fails with
+    ///   [CC_ERROR_UNSUPPORTED_CMD](control::CC_ERROR_UNSUPPORTED_CMD) when no network ID is set.
+    /// - Set Endpoint ID, if [Hooks::set_eid_request()] doesn't ignore it.
+    ///   Accepted assignments change the EID with [set_eid()](Self::set_eid), a reset restores
+    ///   the EID the router was created with. Assignments to endpoints with a
+    ///   [Static](control::EidType::Static) EID are rejected.
+    ///
+    /// Other commands fail with [CC_ERROR_UNSUPPORTED_CMD](control::CC_ERROR_UNSUPPORTED_CMD),
+    /// unless [set_manual_control()](Self::set_manual_control) leaves them to the application.
+    ///
+    /// Returns the length of the response written to `response`, `Ok(None)` for datagrams,
+    /// responses and commands left to the application.
+    /// Returns [NoSpace](Error::NoSpace) if `response` is too small.
+    pub fn control_response(
+        &mut self,
+        source: Eid,
+        request: &[u8],
+        response: &mut [u8],
+    ) -> Result<Option<usize>> {
+        let Some(header) = control::ControlHeader::parse(request) else {
+            return Ok(None);
+        };
+        if !header.request || header.datagram {
+            return Ok(None);
+        }
+        let body = request
+            .get(control::CONTROL_HEADER_LEN..)
+            .unwrap_or_default();
+        let mut data = [0; 1 + 4 * control::codec::MAX_VERSIONS];
+        let (cc, data_len) = match header.command {
+            control::CMD_GET_ENDPOINT_ID => {
+                let eid = self.stack.eid();
+                let eid_type =
+                    self.endpoint_type.to_bits() | self.eid_type.to_bits(eid == self.static_eid);
+                if let Some((out, _)) = data.split_first_chunk_mut() {
+                    *out = [eid.0, eid_type, 0];
+                }
+                (control::CC_SUCCESS, 3)
+            }
+            control::CMD_GET_VERSION_SUPPORT => match body.first() {
+                Some(&typ) => match self.capabilities.versions.get(typ) {
+                    Some(versions) => (
+                        control::CC_SUCCESS,
+                        control::codec::Payload::encode(versions, &mut data)?,
+                    ),
+                    None => (control::CC_UNSUPPORTED_MSG_TYPE, 0),
+                },
+                None => (control::CC_ERROR_INVALID_LENGTH, 0),
+            },
+            control::CMD_GET_MESSAGE_TYPE_SUPPORT => {
+                let response = control::codec::GetMessageTypeSupportResponse {
+                    types: self.message_types(),
+                };
+                (
+                    control::CC_SUCCESS,
+                    control::codec::Payload::encode(&response, &mut data)?,
+                )
+            }
+            control::CMD_GET_VENDOR_MESSAGE_SUPPORT => match body.first() {
+                Some(&selector) => match self.capabilities.vendors.get(selector) {
+                    Some((support, next_selector)) => {
+                        let response = control::codec::GetVendorMessageSupportResponse {
+                            next_selector,
+                            support,
+                        };
+                        (
+                            control::CC_SUCCESS,
+                            control::codec::Payload::encode(&response, &mut data)?,
+                        )
+                    }
+                    None => (control::CC_ERROR_INVALID_DATA, 0),
+                },
+                None => (control::CC_ERROR_INVALID_LENGTH, 0),
+            },
+            control::CMD_GET_NETWORK_ID => match self.network_id {
+                Some(id) => {
+                    if let Some((out, _)) = data.split_first_chunk_mut() {
+                        *out = id;
+                    }
+                    (control::CC_SUCCESS, id.len())
+                }
+                None => (control::CC_ERROR_UNSUPPORTED_CMD, 0),
+            },
+            control::CMD_SET_ENDPOINT_ID => match self.set_endpoint_id(source, body) {
+                Some((cc, Some(status))) => {
+                    if let Some((out, _)) = data.split_first_chunk_mut() {
+                        *out = status;
+                    }
+                    (cc, status.len())
+                }
+                Some((cc, None)) => (cc, 0),
+                None => return Ok(None),
+            },
+            _ if self.manual_control => return Ok(None),
+            _ => (control::CC_ERROR_UNSUPPORTED_CMD, 0),
+        };
+        let data = data.get(..data_len).ok_or(Error::InternalError)?;
+        let len = control::CONTROL_HEADER_LEN + 1 + data.len();
+        let out = response.get_mut(..len).ok_or(Error::NoSpace)?;
+        let (hdr, rest) = out.split_at_mut(control::CONTROL_HEADER_LEN);
+        hdr.copy_from_slice(&header.response().to_bytes());
+        if let Some((out_cc, out_data)) = rest.split_first_mut() {
+            *out_cc = cc;
+            out_data.copy_from_slice(data);
+        }
+        Ok(Some(len))
+    }
+
+    /// Handle a Set Endpoint ID request with body `body`
+    ///
+    /// Returns the completion code and response data, `None` if the request is ignored.
+    fn set_endpoint_id(&mut self, source: Eid, body: &[u8]) -> Option<(u8, Option<[u8; 3]>)> {
+        let &[operation, eid, ..] = body else {
+            return Some((control::CC_ERROR_INVALID_LENGTH, None));
+        };
+        let current = self.stack.eid();
+        let request = control::SetEidRequest {
+            source,
+            operation: control::SetEidOperation::from_bits(operation),
+            eid: Eid(eid),
+            current,
+            bus_owner: self.bus_owner,
+        };
+        let mut decision = self.hooks.set_eid_request(&request);
+        if self.eid_type == control::EidType::Static
+            && decision == control::SetEidDecision::Accept
+            && matches!(
+                request.operation,
+                control::SetEidOperation::Set | control::SetEidOperation::Force
+            )
+        {
+            decision = control::SetEidDecision::Reject;
+        }
+        let eid = match decision {
+            control::SetEidDecision::Ignore => return None,
+            control::SetEidDecision::Reject => {
+                debug!("rejected eid {} assigned by {}", eid, source.0);
+                return Some((
+                    control::CC_SUCCESS,
+                    Some([control::SET_EID_REJECTED, current.0, 0]),
+                ));
+            }
+            control::SetEidDecision::Accept => match request.operation {
+                control::SetEidOperation::Set | control::SetEidOperation::Force => {
+                    // Null, broadcast and reserved EIDs can't be assigned
+                    if eid == 0 || eid == 0xff || (1..=7).contains(&eid) {
+                        return Some((control::CC_ERROR_INVALID_DATA, None));
+                    }
+                    self.bus_owner = Some(source);
+                    Eid(eid)
+                }
+                control::SetEidOperation::Reset => self.static_eid,
+                control::SetEidOperation::SetDiscovered => current,
+            },
+        };
+        if self.set_eid(eid).is_err() {
+            return Some((control::CC_ERROR, None));
+        }
+        // The endpoint is discovered even if its EID didn't change
+        self.cancel_notify();
+        Some((
+            control::CC_SUCCESS,
+            Some([control::SET_EID_ACCEPTED, eid.0, 0]),
+        ))
+    }
+
+    /// Set or remove the per-source inbound [RateLimit]
+    ///
+    /// Resets the state of all sources and the drop counter.
+    pub fn set_rate_limit(&mut self, limit: Option<RateLimit>) {
+        self.rate_limiter = limit.map(RateLimiter::new);
+    }
+
+    /// Set or remove the outbound [Throttle] for `eid`
+    ///
+    /// Packets to `eid` exceeding the limit are held in the buffer set with
+    /// [set_transmit_buffer()](Self::set_transmit_buffer) and passed to the sender by
+    /// [poll()](Self::poll) once the limit allows, see [SendReport::held].
+    /// Without a buffer or room in it, sending fails with [NoSpace](Error::NoSpace)
+    /// (counted in [throttle_overflows()](Self::throttle_overflows)), possibly after
+    /// the first packets of the message were sent.
+    ///
+    /// Returns [NoSpace](Error::NoSpace) if [THROTTLE_TABLE_SIZE] destinations are throttled
+    /// already.
+    pub fn set_throttle(&mut self, eid: Eid, throttle: Option<Throttle>) -> Result<()> {
+        self.throttles.set(eid, throttle, self.clock.now_millis())
+    }
+
+    /// Get the outbound [Throttle] for `eid`
+    pub fn throttle(&self, eid: Eid) -> Option<Throttle> {
+        self.throttles.get(eid)
+    }
+
+    /// Hold packets exceeding a [Throttle] in `buf`
+    ///
+    /// Each packet takes its length plus 3 bytes. Returns the previous buffer; packets held
+    /// in it are discarded.
+    pub fn set_transmit_buffer(&mut self, buf: &'static mut [u8]) -> Option<&'static mut [u8]> {
+        self.throttles.set_buffer(Some(buf))
+    }
+
+    /// Remove the buffer set with [set_transmit_buffer()](Self::set_transmit_buffer),
+    /// discarding the packets held in it
+    pub fn take_transmit_buffer(&mut self) -> Option<&'static mut [u8]> {
+        self.throttles.set_buffer(None)
+    }
+
+    /// Get the number of packets held back by [Throttle]s
+    pub fn throttled(&self) -> usize {
+        self.throttles.held()
+    }
+
+    /// Get the number of packets that could not be held back, failing their message
+    pub fn throttle_overflows(&self) -> usize {
+        self.throttles.overflows
+    }
+
+    /// Add a static route, directing packets for `eids` to `physical_addr` on `port`
+    ///
+    /// Used for locally originated messages and, with
+    /// [set_forwarding()](Self::set_forwarding), forwarded packets. Packets for a covered EID
+    /// are passed to [Sender::send_routed()], the narrowest range wins if routes overlap.
+    /// A route for the same range is replaced.
+    /// Returns [BadArgument](Error::BadArgument) for an empty range and
+    /// [NoSpace](Error::NoSpace) if [ROUTE_TABLE_SIZE] routes are set.
+    pub fn add_route(
+        &mut self,
+        eids: core::ops::RangeInclusive<u8>,
+        port: u8,
+        physical_addr: u64,
+    ) -> Result<()> {
+        let slot = routes::add(&mut self.routes, Route::new(eids, port, physical_addr)?)?;
+        self.forward_stats.reset_route(slot);
+        self.failover.reset(slot);
+        Ok(())
+    }
+
+    /// Set or remove the backup next hop of the static route for exactly `eids`
+    ///
+    /// Packets along the route are passed to [Sender::send_routed()] with the port and
+    /// physical address of `backup` while the link of the primary port is down (see
+    /// [link_down()](Self::link_down)) or after [ROUTE_FAILOVER_THRESHOLD] consecutive send
+    /// failures. The primary is used again when its link comes up, or tried again
+    /// [ROUTE_FAILBACK_MILLIS] after the last failure.
+    ///
+    /// Returns whether there is a route for `eids`.
+    pub fn set_route_backup(
+        &mut self,
+        eids: core::ops::RangeInclusive<u8>,
+        backup: Option<NextHop>,
+    ) -> bool {
+        let Some(route) = self
+            .routes
+            .iter_mut()
+            .flatten()
+            .find(|r| (r.first.0, r.last.0) == (*eids.start(), *eids.end()))
+        else {
+            return false;
+        };
+        route.backup = backup;
+        true
+    }
+
+    /// Get the route packets for `eid` currently take
+    ///
+    /// Like [route()](Self::route), with the port and physical address of the backup next
+    /// hop while the route is failed over, see [set_route_backup()](Self::set_route_backup).
+    pub fn next_hop(&self, eid: Eid) -> Option<Route> {
+        self.hop(eid).map(|hop| hop.route)
+    }
+
+    /// Choose the next hop for `eid`
+    fn hop(&self, eid: Eid) -> Option<Hop> {
+        let now_millis = self.clock.now_millis();
+        routes::select(&self.routes, &self.failover, &self.links, eid, now_millis)
+    }
+
+    /// Remove the static route for exactly `eids`
+    ///
+    /// Returns whether there was one.
+    pub fn remove_route(&mut self, eids: core::ops::RangeInclusive<u8>) -> bool {
+        routes::remove(&mut self.routes, eids).is_some()
+    }
+
+    /// Get the static route used for `eid`, if any
+    pub fn route(&self, eid: Eid) -> Option<Route> {
+        routes::lookup(&self.routes, eid)
+    }
+
+    /// Iterate the static routes with the number of packets forwarded along each
+    ///
+    /// Counts start when a route is added and are reset with
+    /// [reset_forward_stats()](Self::reset_forward_stats).
+    pub fn route_hits(&self) -> impl Iterator<Item = (Route, usize)> + '_ {
+        self.routes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, r)| r.map(|r| (r, self.forward_stats.route_hits(i))))
+    }
+
+    /// Inform the router that the link of `port` went down
+    ///
+    /// Routes to the port become unreachable: sends to their EIDs fail with
+    /// [TxFailure](Error::TxFailure) and packets to be forwarded along them are dropped with
+    /// [DroppedLinkDown](Disposition::DroppedLinkDown). Packets held back by a [Throttle]
+    /// for these EIDs are discarded, requests whose last message was not sent completely
+    /// release their tag and expire with [LinkDown](ExpiryReason::LinkDown).
+    /// EIDs without a route are not affected.
+    pub fn link_down(&mut self, port: u8) {
+        if !self.links.set(port, false) {
+            return;
+        }
+        debug!("link of port {} down", port);
+        self.events.push(RouterEvent::LinkDown(port));
+        let (routes, failover, links) = (&self.routes, &self.failover, &self.links);
+        let now_millis = self.clock.now_millis();
+        let on_port = |eid: Eid| {
+            // EIDs with a backup on a port that is up keep going
+            routes::select(routes, failover, links, eid, now_millis)
+                .is_some_and(|hop| hop.route.port == port)
+        };
+        let discarded = self.throttles.discard(on_port);
+        if discarded > 0 {
+            debug!("discarded {} held packets for port {}", discarded, port);
+        }
+        for i in 0..self.tables.requests().len() {
+            let Ok(cookie) = self.tables.request_cookie(i) else {
+                continue;
+            };
+            let Some(req) = self
+                .tables
+                .requests_mut()
+                .get_mut(i)
+                .and_then(|s| s.entry.as_mut())
+            else {
+                continue;
+            };
+            if req.tx_started.is_none() || !on_port(req.eid) {
+                continue;
+            }
+            if let Some(tag) = req.last_tag.take() {
+                self.stack.cancel_flow(req.eid, tag.tag());
+            }
+            req.reclaim_at = None;
+            req.tx_started = None;
+            req.attempt = 0;
+            req.error = Some(HandleError::Expired(ExpiryReason::LinkDown));
+            self.wakers.wake(RequestHandle(cookie).into());
+            self.hooks
+                .message_expired(RequestHandle(cookie).into(), ExpiryReason::LinkDown);
+            self.events.push(RouterEvent::Expired(
+                RequestHandle(cookie).into(),
+                ExpiryReason::LinkDown,
+            ));
+        }
+    }
+
+    /// Inform the router that the link of `port` came up
+    ///
+    /// Routes to the port are reachable again. With
+    /// [set_discovery_notify()](Self::set_discovery_notify) enabled, a Discovery Notify is
+    /// sent to the bus owner in the next [update()](Self::update), so it can assign an EID.
+    pub fn link_up(&mut self, port: u8) {
+        if !self.links.set(port, true) {
+            return;
+        }
+        debug!("link of port {} up", port);
+        self.events.push(RouterEvent::LinkUp(port));
+        for (slot, route) in self.routes.iter().enumerate() {
+            if route.is_some_and(|r| r.port == port) {
+                self.failover.reset(slot);
+            }
+        }
+        self.notify_pending |= self.discovery_notify;
+    }
+
+    /// Check whether the link of `port` is up
+    ///
+    /// Links are up until [link_down()](Self::link_down) is called for them.
+    pub fn is_link_up(&self, port: u8) -> bool {
+        self.links.is_up(port)
+    }
+
+    /// Enable or disable sending Discovery Notify when a link comes up
+    ///
+    /// See [link_up()](Self::link_up). Disabled by default.
+    pub fn set_discovery_notify(&mut self, enable: bool) {
+        self.discovery_notify = enable;
+        self.notify_pending &= enable;
+        if !enable {
+            self.cancel_notify();
+        }
+    }
+
+    /// Set how Discovery Notify is sent again until the endpoint is discovered
+    ///
+    /// With a policy, each Discovery Notify sent after [link_up()](Self::link_up) is sent
+    /// again from [update()](Self::update) until the bus owner answers it, an EID is
+    /// assigned with [set_eid()](Self::set_eid) or the retransmissions run out.
+    /// `None` (the default) sends it once. Takes effect with the next link up.
+    pub fn set_notify_retransmit(&mut self, policy: Option<NotifyRetransmit>) {
+        self.notify.policy = policy;
+    }
+
+    /// Bound the work done per call of [poll()](Self::poll), see [WorkBudget]
+    pub fn set_work_budget(&mut self, budget: WorkBudget) {
+        self.work_budget = budget;
+    }
+
+    /// Enable or disable the event queue, see [next_event()](Self::next_event)
+    ///
+    /// Disabling discards queued events.
+    pub fn set_event_queue(&mut self, enable: bool) {
+        self.events.set_enabled(enable);
+    }
+
+    /// Take the oldest queued [RouterEvent]
+    ///
+    /// Events are only queued while the queue is enabled, see
+    /// [RouterConfig::event_queue()]. At most [EVENT_QUEUE_SIZE] events are queued, further
+    /// ones are dropped and counted in [lost_events()](Self::lost_events). Hooks are called
+    /// regardless of the queue.
+    pub fn next_event(&mut self) -> Option<RouterEvent> {
+        self.events.pop()
+    }
+
+    /// Get the number of events dropped because the event queue was full
+    pub fn lost_events(&self) -> usize {
+        self.events.lost()
+    }
+
+    /// Enable or disable the dispatch trace, see [dispatch_trace()](Self::dispatch_trace)
+    ///
+    /// Disabling discards the recorded entries.
+    pub fn set_dispatch_trace(&mut self, enable: bool) {
+        self.dispatch_trace.set_enabled(enable);
+    }
+
+    /// Iterate over the most recent routing decisions, oldest first
+    ///
+    /// Each inbound packet is recorded with its [Disposition] while the trace is enabled, see
+    /// [RouterConfig::dispatch_trace()]. Only the last [DISPATCH_TRACE_SIZE] packets are kept.
+    /// Meant to be dumped from a fault handler, [TraceEntry] implements `Display`.
+    pub fn dispatch_trace(&self) -> impl Iterator<Item = TraceEntry> + '_ {
+        self.dispatch_trace.entries()
+    }
+
+    /// Discard the entries of the dispatch trace
+    pub fn clear_dispatch_trace(&mut self) {
+        self.dispatch_trace.clear();
+    }
+
+    /// Get the counters of the forwarding path, see [ForwardStats]
+    pub fn forward_stats(&self) -> ForwardStats {
+        self.forward_stats
+    }
+
+    /// Reset the counters of the forwarding path, including the route hits
+    pub fn reset_forward_stats(&mut self) {
+        self.forward_stats = ForwardStats::default();
+    }
+
+    /// Set or remove the liveness monitoring of peers
+    ///
+    /// Peers added with [monitor_peer()](Self::monitor_peer) are probed from
+    /// [update()](Self::update) and [poll()](Self::poll) as configured by [KeepAlive].
+    /// Outstanding probes are abandoned.
+    pub fn set_keep_alive(&mut self, keep_alive: Option<KeepAlive>) {
+        self.monitor.keep_alive = keep_alive;
+        for peer in self.monitor.peers.iter_mut().flatten() {
+            if let Some(probe) = peer.probe.take() {
+                self.stack.cancel_flow(peer.eid, probe.tag);
+                self.instance_ids.release(peer.eid, probe.instance_id);
+            }
+        }
+    }
+
+    /// Set or remove the [RetryPolicy] of the liveness probes
+    ///
+    /// The timeout of a probe is taken from the policy, counting the failed probes in a row as
+    /// attempts. Failed probes are repeated at once, the peer is reported down after
+    /// [max_attempts()](RetryPolicy::max_attempts) failures. Without a policy, the settings of
+    /// the [KeepAlive] apply.
+    pub fn set_keep_alive_policy(&mut self, policy: Option<&'static dyn RetryPolicy>) {
+        self.monitor.policy = policy;
+    }
+
+    /// Set or remove the [RetryPolicy] of the request `handle`
+    ///
+    /// Takes precedence over the policy of the message type, see
+    /// [set_type_retry_policy()](Self::set_type_retry_policy).
+    /// Returns [BadArgument](Error::BadArgument) if the handle is not bound.
+    pub fn set_retry_policy(
+        &mut self,
+        handle: RequestHandle,
+        policy: Option<&'static dyn RetryPolicy>,
+    ) -> RouterResult<()> {
+        let req = self
+            .lookup_request_mut(handle)
+            .ok_or_else(|| RouterError::from(Error::BadArgument).with_handle(handle.into()))?;
+        req.retry = policy;
+        Ok(())
+    }
+
+    /// Set the tag reclaim time of the request `handle`
+    ///
+    /// Overrides [RouterConfig::tag_reclaim_millis()] for messages sent afterwards, `None`
+    /// applies the reclaim time of the router.
+    /// Returns [BadArgument](Error::BadArgument) if the handle is not bound.
+    pub fn set_tag_reclaim_millis(
+        &mut self,
+        handle: RequestHandle,
+        reclaim: Option<u64>,
+    ) -> RouterResult<()> {
+        let req = self
+            .lookup_request_mut(handle)
+            .ok_or_else(|| RouterError::from(Error::BadArgument).with_handle(handle.into()))?;
+        req.tag_reclaim_millis = reclaim;
+        Ok(())
+    }
+
+    /// Set how the owned tags of requests are allocated, see [TagAllocation]
+    pub fn set_tag_allocation(&mut self, allocation: TagAllocation) {
+        self.tag_allocation = allocation;
+    }
+
+    /// Reserve the tag values in `range` for the request `handle`
+    ///
+    /// Only applies with [TagAllocation::Reserved]: the request uses the lowest value of its
+    /// range that it doesn't hold already, other requests to the same EID avoid the range.
+    /// `None` removes the reservation.
+    /// Returns [BadArgument](Error::BadArgument) if the handle is not bound or the range is
+    /// empty or exceeds the tag values 0 to 7.
+    pub fn set_tag_range(
+        &mut self,
+        handle: RequestHandle,
+        range: Option<core::ops::RangeInclusive<u8>>,
+    ) -> RouterResult<()> {
+        let context = |e: Error| RouterError::from(e).with_handle(handle.into());
+        let mask = range
+            .map_or(Ok(0), tag_alloc::range_mask)
+            .map_err(context)?;
+        let req = self
+            .lookup_request_mut(handle)
+            .ok_or_else(|| context(Error::BadArgument))?;
+        req.tag_range = mask;
+        Ok(())
+    }
+
+    /// Choose the owned tag of a message of the request `handle` to `eid`
+    ///
+    /// Returns `None` to leave the allocation to the stack,
+    /// [NoSpace](Error::NoSpace) if no tag value is available.
+    fn request_tag(&self, handle: RequestHandle, eid: Eid) -> Result<Option<Tag>> {
+        if self.tag_allocation == TagAllocation::RoundRobin {
+            return Ok(None);
+        }
+        let (mut held, mut reserved) = (0u8, 0u8);
+        for req in self
+            .tables
+            .requests()
+            .iter()
+            .filter_map(|s| s.entry.as_ref())
+        {
+            if req.eid != eid {
+                continue;
+            }
+            for tag in [req.last_tag, req.reclaim_at.map(|r| r.0)]
+                .into_iter()
+                .flatten()
+            {
+                held |= 1 << (tag.tag().0 & 0x07);
+            }
+            reserved |= req.tag_range;
+        }
+        let own = self.lookup_request(handle).map_or(0, |r| r.tag_range);
+        let candidates = match self.tag_allocation {
+            TagAllocation::Reserved if own != 0 => own,
+            TagAllocation::Reserved => !reserved,
+            _ => !0,
+        };
+        tag_alloc::lowest(candidates & !held)
+            .map(|tv| Some(Tag::Owned(tv)))
+            .ok_or(Error::NoSpace)
+    }
+
+    /// Set or remove the [RetryPolicy] of requests sending messages of type `typ`
+    ///
+    /// A request waits for the response to each attempt as long as the policy says,
+    /// instead of the [request timeout](RouterConfig::request_timeout_millis). Attempts that
+    /// time out are reported to [Hooks::message_expired()] with
+    /// [RetryDue](ExpiryReason::RetryDue) and sent again by the application, the last one with
+    /// [NoResponse](ExpiryReason::NoResponse). The router keeps no copy of sent messages.
+    /// Returns [NoSpace](Error::NoSpace) if [RETRY_POLICY_TABLE_SIZE] types have a policy.
+    pub fn set_type_retry_policy(
+        &mut self,
+        typ: MsgType,
+        policy: Option<&'static dyn RetryPolicy>,
+    ) -> Result<()> {
+        self.retry_policies.set(typ, policy)
+    }
+
+    /// Set or remove the [MessageIntegrity] handler of messages of type `typ`
+    ///
+    /// The handler appends its MIC to the messages of `typ` sent by the router and verifies
+    /// it on the messages received, dropping those that fail with
+    /// [DroppedIntegrityError](Disposition::DroppedIntegrityError). Messages with a MIC can
+    /// be made up of at most [MAX_IC_BUFS] buffers while their IC bit is clear, and of one
+    /// buffer less when it is set, as the integrity check follows the MIC. More fail with
+    /// [BadArgument](Error::BadArgument).
+    /// Messages received into a [reassembly buffer](Self::set_reassembly_buffer) are not verified.
+    /// Returns [BadArgument](Error::BadArgument) if the MIC is longer than [MAX_MIC_LEN],
+    /// [NoSpace](Error::NoSpace) if [MIC_TABLE_SIZE] types have a handler.
+    pub fn set_message_integrity(
+        &mut self,
+        typ: MsgType,
+        handler: Option<&'static dyn MessageIntegrity>,
+    ) -> Result<()> {
+        self.mics.set(typ, handler)
+    }
+
+    /// Set or remove the minimum body length of messages of type `typ`
+    ///
+    /// Shorter messages of the type, including empty ones, are handled according to the
+    /// policy of `min` instead of the [empty policy](Self::set_empty_policy).
+    /// A minimum of 0 exempts the type from the empty policy.
+    /// Returns [NoSpace](Error::NoSpace) if [MIN_SIZE_TABLE_SIZE] types have a minimum.
+    pub fn set_min_size(&mut self, typ: MsgType, min: Option<MinSize>) -> Result<()> {
+        self.min_sizes.set(typ, min)
+    }
+
+    /// Set how empty messages of types without a [minimum size](Self::set_min_size) are
+    /// handled
+    ///
+    /// The default, [Deliver](Undersized::Deliver), passes them on like any other message.
+    pub fn set_empty_policy(&mut self, policy: Undersized) {
+        self.min_sizes.set_empty(policy);
+    }
+
+    /// Get the number of undersized messages received, whatever their policy
+    pub fn undersized(&self) -> usize {
+        self.min_sizes.count()
+    }
+
+    /// Start monitoring the liveness of `peer`
+    ///
+    /// The first probe is sent on the next [update()](Self::update), state changes are
+    /// reported through [Hooks::peer_state_changed()].
+    /// Returns [NoSpace](Error::NoSpace) if [LIVENESS_TABLE_SIZE] peers are monitored.
+    pub fn monitor_peer(&mut self, peer: Eid) -> Result<()> {
+        self.monitor.add(peer, self.clock.now_millis())
+    }
+
+    /// Stop monitoring the liveness of `peer`
+    ///
+    /// Returns whether `peer` was monitored.
+    pub fn unmonitor_peer(&mut self, peer: Eid) -> bool {
+        let Some(removed) = self.monitor.remove(peer) else {
+            return false;
+        };
+        if let Some(probe) = removed.probe {
+            self.stack.cancel_flow(peer, probe.tag);
+            self.instance_ids.release(peer, probe.instance_id);
+        }
+        true
+    }
+
+    /// Get the liveness of `peer`, `None` if it is not monitored
+    pub fn peer_state(&self, peer: Eid) -> Option<PeerState> {
+        self.monitor.peer(peer).map(|p| p.state)
+    }
+
+    /// Record what the control `response` of `peer` tells about the topology
+    ///
+    /// Called by [ControlRequester](control::ControlRequester) for each response it receives.
+    /// Endpoints are added when they report their EID or accept an assignment, which
+    /// replaces `peer` (e.g. the null EID) by the assigned EID. UUIDs and message types are
+    /// stored for `peer`.
+    /// Returns [NoSpace](Error::NoSpace) if [TOPOLOGY_TABLE_SIZE] endpoints are known.
+    pub fn learn_control_response(
+        &mut self,
+        peer: Eid,
+        response: &control::ControlResponse,
+    ) -> Result<()> {
+        self.topology.learn(peer, response)
+    }
+
+    /// Remove `eid` from the topology, e.g. after it was hot-unplugged
+    ///
+    /// Returns whether it was known.
+    pub fn forget_endpoint(&mut self, eid: Eid) -> bool {
+        self.topology.forget(eid)
+    }
+
+    /// Iterate the endpoints learned by control requests
+    ///
+    /// Entries combine the learned EIDs, UUIDs and message types with the
+    /// [static route](Self::route) and [liveness](Self::peer_state) of each endpoint, so
+    /// management firmware of a bus owner or bridge can present an inventory without
+    /// querying the bus again.
+    pub fn topology(&self) -> impl Iterator<Item = TopologyEntry> + '_ {
+        self.topology
+            .entries(|eid| self.route(eid), |eid| self.peer_state(eid))
+    }
+
+    /// Get the number of packets dropped by the [RateLimit]
+    pub fn rate_limited(&self) -> usize {
+        self.rate_limiter.as_ref().map_or(0, |l| l.dropped)
+    }
+
+    /// Get a reference to the sender
+    pub fn sender(&self) -> &S {
+        &self.sender
+    }
+
+    /// Get a mutable reference to the sender, e.g. to reconfigure the port
+    pub fn sender_mut(&mut self) -> &mut S {
+        &mut self.sender
+    }
+
+    /// Replace the sender, returning the previous one
+    ///
+    /// Allows switching to a new transport at runtime (e.g. after a USB transport re-enumerated
+    /// or an I2C controller was reset) while keeping all listeners and requests bound.
+    /// Messages are fragmented and passed to the sender within [send()](Self::send), so only
+    /// packets held back by a [Throttle] are pending in the router, they go to the new sender.
+    /// Packets still queued by the previous sender are returned with it.
+    ///
+    /// MTUs learned by discovery were measured on the previous link and are forgotten,
+    /// static MTU entries are kept.
+    pub fn replace_sender(&mut self, sender: S) -> S {
+        for slot in self.mtu_overrides.iter_mut() {
+            if slot.is_some_and(|e| e.learned) {
+                *slot = None;
+            }
+        }
+        core::mem::replace(&mut self.sender, sender)
+    }
+
+    /// Get a reference to the application hooks
+    pub fn hooks(&self) -> &H {
+        &self.hooks
+    }
+
+    /// Get a mutable reference to the application hooks
+    pub fn hooks_mut(&mut self) -> &mut H {
+        &mut self.hooks
+    }
+
+    /// Provide an incoming packet to the router.
+    ///
+    /// This expects a single MCTP packet, without a transport binding header.
+    ///
+    /// Returns `Ok(Some(Handle))` for a associated listener or request,
+    /// or `Ok(None)` if the message was discarded.
+    /// Use [inbound_disposition()](Self::inbound_disposition) to find out why a packet
+    /// did not result in a delivery.
+    /// Messages for a full receive queue with the [Reject](OverflowPolicy::Reject) policy
+    /// fail with [NoSpace](Error::NoSpace).
+    ///
+    /// Single packet control requests answered by the router itself skip the reassembly
+    /// context of the stack. All other messages, including single packet ones for a listener
+    /// or request, take a reassembly context: the stack holds them until received, as
+    /// [recv()](Self::recv) hands out its messages.
+    pub fn inbound(&mut self, pkt: &[u8]) -> Result<Option<Handle>> {
+        match self.dispatch(pkt)? {
+            Disposition::RejectedQueueFull => Err(Error::NoSpace),
+            d => Ok(d.handle()),
+        }
+    }
+
+    /// Provide an incoming frame that still carries the transport binding header
+    ///
+    /// The header is validated and stripped by [Sender::strip_header()], the packet is then
+    /// processed like by [inbound()](Self::inbound). This lets receive interrupts forward raw
+    /// bus frames without parsing them.
+    ///
+    /// Fails with the error of [Sender::strip_header()] for frames rejected by the binding.
+    pub fn inbound_frame(&mut self, frame: &[u8]) -> Result<Option<Handle>> {
+        let pkt = self.sender.strip_header(frame).inspect_err(|_| {
+            debug!(
+                "dropped frame rejected by the binding, {} bytes",
+                frame.len()
+            );
+        })?;
+        self.inbound(pkt)
+    }
+
+    /// Provide an incoming packet split across `parts` to the router
+    ///
+    /// Behaves like [inbound()](Self::inbound) for the concatenation of `parts`, e.g. a packet
+    /// in a DMA descriptor chain or wrapping around the end of a ring buffer.
+    /// A packet in a single non-empty part is processed in place. The reassembly takes
+    /// contiguous packets, so others are gathered into a [Sender::Packet] buffer on the stack.
+    ///
+    /// Returns [InvalidInput](Error::InvalidInput) for packets longer than that buffer.
+    pub fn inbound_vectored(&mut self, parts: &[&[u8]]) -> Result<Option<Handle>> {
+        let mut nonempty = parts.iter().filter(|p| !p.is_empty());
+        if let (Some(pkt), None) = (nonempty.next(), nonempty.next()) {
+            return self.inbound(pkt);
+        }
+        let mut buf = S::Packet::zeroed();
+        let mut len = 0;
+        for part in parts {
+            buf.as_mut()
+                .get_mut(len..len + part.len())
+                .ok_or(Error::InvalidInput)?
+                .copy_from_slice(part);
+            len += part.len();
+        }
+        self.inbound(buf.as_ref().get(..len).ok_or(Error::InternalError)?)
+    }
+
+    /// Add `buf` to the pool of receive buffers lent to the transport
+    ///
+    /// See [lend_rx_buffer()](Self::lend_rx_buffer). Buffers should hold a packet of the
+    /// MTU of the transport. Returns [NoSpace](Error::NoSpace) if the pool holds
+    /// [RX_BUFFER_POOL_SIZE] buffers already.
+    pub fn add_rx_buffer(&mut self, buf: &'static mut [u8]) -> Result<()> {
+        self.rx_pool.add(buf).map_err(|_| Error::NoSpace)
+    }
+
+    /// Lend a buffer of the pool to the transport, `None` if all buffers are lent
+    ///
+    /// DMA can deposit the next packet straight into [buf_mut()](RxBuffer::buf_mut).
+    /// The buffer is returned by [inbound_owned()](Self::inbound_owned), or unused with
+    /// [return_rx_buffer()](Self::return_rx_buffer).
+    pub fn lend_rx_buffer(&mut self) -> Option<RxBuffer> {
+        self.rx_pool.lend()
+    }
+
+    /// Return a lent buffer to the pool without processing it
+    pub fn return_rx_buffer(&mut self, buf: RxBuffer) {
+        self.rx_pool.give_back(buf);
+    }
+
+    /// Get the number of receive buffers that can be lent
+    pub fn rx_buffers_available(&self) -> usize {
+        self.rx_pool.available()
+    }
+
+    /// Provide an incoming packet received into a lent buffer to the router
+    ///
+    /// Processes the [packet()](RxBuffer::packet) of `buf` in place like
+    /// [inbound()](Self::inbound), saving the copy out of a transport buffer, and returns
+    /// `buf` to the pool in all cases.
+    pub fn inbound_owned(&mut self, buf: RxBuffer) -> Result<Option<Handle>> {
+        let result = self.inbound(buf.packet());
+        self.rx_pool.give_back(buf);
+        result
+    }
+
+    /// Pad the packets sent on `port` with `padding`, `None` sends them unpadded
+    ///
+    /// Packets without a route are sent on port 0. Padded packets are passed to
+    /// [Sender::send_padded()]; bindings with fixed-size frames should use an MTU that is a
+    /// multiple of the [alignment](Padding::align).
+    /// Returns [BadArgument](Error::BadArgument) if the padding can exceed the
+    /// [Sender::Packet] buffer, [NoSpace](Error::NoSpace) if [PADDING_TABLE_SIZE] ports are
+    /// padded already.
+    pub fn set_port_padding(&mut self, port: u8, padding: Option<Padding>) -> Result<()> {
+        self.padding.set(port, padding, S::Packet::LEN)
+    }
+
+    /// Get the padding of the packets sent on `port`
+    pub fn port_padding(&self, port: u8) -> Option<Padding> {
+        self.padding.get(port)
+    }
+
+    /// Provide an incoming packet ending with `pad_len` bytes of padding to the router
+    ///
+    /// For bindings that report the padding of a packet in their header, e.g. the Pad Length
+    /// of PCIe VDM. The padding is stripped before the packet is processed like
+    /// [inbound()](Self::inbound), so it isn't reassembled into the message.
+    /// Returns [InvalidInput](Error::InvalidInput) if the padding overlaps the MCTP header.
+    pub fn inbound_padded(&mut self, pkt: &[u8], pad_len: usize) -> Result<Option<Handle>> {
+        let pkt = pkt
+            .len()
+            .checked_sub(pad_len)
+            .filter(|&len| len >= header::HEADER_LEN)
+            .and_then(|len| pkt.get(..len))
+            .ok_or(Error::InvalidInput)?;
+        self.inbound(pkt)
+    }
+
+    /// Provide an incoming packet to the router and classify what happened to it
+    ///
+    /// Behaves like [inbound()](Self::inbound), but reports the [Disposition] of the packet
+    /// instead of only the cookie, so callers can log and count dropped traffic.
+    pub fn inbound_disposition(&mut self, pkt: &[u8]) -> Disposition {
+        self.dispatch(pkt)
+            .unwrap_or_else(|rejected| rejected.disposition)
+    }
+
+    /// Provide a burst of incoming packets to the router
+    ///
+    /// Processes each packet like [inbound_disposition()](Self::inbound_disposition) and
+    /// returns the aggregate result. Rather than waking the receivers of a handle per
+    /// delivered message, the application wakes the [handles](BatchReport::handles) of the
+    /// report once after the batch.
+    pub fn inbound_batch<'p>(&mut self, pkts: impl IntoIterator<Item = &'p [u8]>) -> BatchReport {
+        let mut report = BatchReport::default();
+        for pkt in pkts {
+            report.add(self.inbound_disposition(pkt));
+        }
+        report
+    }
+
+    /// Deliver a message to the local listeners and requests as if it was received from `source`
+    ///
+    /// The message is split into packets of the MTU of the [Sender] and passed through the
+    /// receive path like packets given to [inbound_disposition()](Self::inbound_disposition),
+    /// without involving the transport. This lets self-tests, test doubles and local bridges
+    /// between protocol layers exercise listeners. The message is addressed to the own EID,
+    /// requests carry an owned `tag`, responses are matched to requests sent to `source`.
+    /// Responses to injected requests are sent to `source` through the [Sender] as usual.
+    ///
+    /// With `ic` set, a CRC-32C integrity check is appended to the message.
+    /// Returns the disposition of the last packet, packets following one that is not
+    /// [Incomplete](Disposition::Incomplete) are not passed.
+    /// Returns [BadArgument](Error::BadArgument) if `source` is a local EID.
+    pub fn inject(
+        &mut self,
+        source: Eid,
+        typ: MsgType,
+        tag: Tag,
+        ic: MsgIC,
+        bufs: &[&[u8]],
+    ) -> Result<Disposition> {
+        if self.is_local_eid(source) {
+            return Err(Error::BadArgument);
+        }
+        let first = [typ.0 | if ic.0 { 0x80 } else { 0 }];
+        let crc = ic.0.then(|| integrity_check(typ, bufs));
+        let mut remaining =
+            first.len() + bufs.iter().map(|b| b.len()).sum::<usize>() + crc.map_or(0, |c| c.len());
+        let mut parts = core::iter::once(&first[..])
+            .chain(bufs.iter().copied())
+            .chain(crc.as_ref().map(|c| &c[..]));
+        let mtu = self
+            .sender
+            .get_mtu()
+            .min(S::Packet::LEN)
+            .max(header::HEADER_LEN + 1);
+        let mut hdr = header::Header {
+            dest: self.stack.eid(),
+            source,
+            som: true,
+            eom: false,
+            seq: 0,
+            tag,
+        };
+        let mut buf = S::Packet::zeroed();
+        let pkt = buf.as_mut();
+        let mut part: &[u8] = &[];
+        loop {
+            let mut len = header::HEADER_LEN;
+            while len < mtu {
+                if part.is_empty() {
+                    match parts.next() {
+                        Some(next) => part = next,
+                        None => break,
+                    }
+                    continue;
+                }
+                let (chunk, rest) = part.split_at((mtu - len).min(part.len()));
+                pkt.get_mut(len..len + chunk.len())
+                    .ok_or(Error::InternalError)?
+                    .copy_from_slice(chunk);
+                len += chunk.len();
+                remaining -= chunk.len();
+                part = rest;
+            }
+            hdr.eom = remaining == 0;
+            pkt.get_mut(..header::HEADER_LEN)
+                .ok_or(Error::InternalError)?
+                .copy_from_slice(&hdr.to_bytes());
+            let disposition = self.dispatch(pkt.get(..len).ok_or(Error::InternalError)?)?;
+            if hdr.eom || disposition != Disposition::Incomplete {
+                return Ok(disposition);
+            }
+            hdr.som = false;
+            hdr.seq = hdr.seq.wrapping_add(1) & 0x03;
+        }
+    }
+
+    /// Send a pattern message to the router itself and check that it arrives intact
+    ///
+    /// The message of `len` bytes, protected by an integrity check, is fragmented by the stack
+    /// with the [MTU](Self::mtu) of the own EID and its packets are passed straight back into
+    /// the reassembly. This exercises fragmentation, the reassembly buffers and the CRC-32C
+    /// without involving the [Sender], listeners or requests, e.g. for manufacturing and
+    /// boot-time checks. Run it while the router is idle, the message takes a tag and a
+    /// reassembly context of the stack while it is in flight.
+    /// Returns [BadArgument](Error::BadArgument) if `len` exceeds [SELF_TEST_MAX_LEN],
+    /// the error of the stack if it can't send or reassemble the message, and
+    /// [InternalError](Error::InternalError) if the reassembled message differs.
+    pub fn self_test(&mut self, len: usize) -> Result<SelfTestReport> {
+        let payload = self_test::PATTERN.get(..len).ok_or(Error::BadArgument)?;
+        let own = self.stack.eid();
+        let crc = integrity_check(SELF_TEST_TYPE, &[payload]);
+        let bufs = [payload, &crc[..]];
+        let mtu = self.mtu(own).max(header::HEADER_LEN + 1);
+        let frag = self.stack.start_send(
+            own,
+            SELF_TEST_TYPE,
+            None,
+            true,
+            MsgIC(true),
+            Some(mtu),
+            None,
+        )?;
+        let tag = frag.tag();
+        let mut report = SelfTestReport {
+            len,
+            packets: 0,
+            mtu: 0,
+        };
+        let mut intact = false;
+        let mut buf = S::Packet::zeroed();
+        let stack = &mut self.stack;
+        let looped = fragment_each(Packets::Fragments(frag), &bufs, buf.as_mut(), |pkt| {
+            report.packets += 1;
+            report.mtu = report.mtu.max(pkt.len());
+            if let Some(msg) = stack.receive(pkt)? {
+                intact = msg.typ == SELF_TEST_TYPE
+                    && msg.ic.0
+                    && check_integrity(&msg)
+                    && message_body(&msg) == payload;
+            }
+            Ok(())
+        });
+        self.stack.cancel_flow(own, tag.tag());
+        looped?;
+        if !intact {
+            warn!("self-test message of {} bytes corrupted", len);
+            return Err(Error::InternalError);
+        }
+        Ok(report)
+    }
+
+    /// Pass a packet for another EID on to the sender, unmodified unless it is refragmented
+    fn forward(&mut self, hdr: &header::Header, pkt: &[u8]) -> Result<Disposition> {
+        let hop = self.hop(hdr.dest);
+        let route = hop.map(|hop| (hop.slot, hop.route));
+        if self.quiesced {
+            debug!("dropped packet for {}, quiesced", hdr.dest.0);
+            self.forward_stats.count(route, false);
+            return Ok(Disposition::DroppedQuiesced);
+        }
+        if self.links.blocks(route.map(|r| r.1).as_ref()) {
+            debug!("dropped packet for {}, link down", hdr.dest.0);
+            self.forward_stats.count(route, false);
+            return Ok(Disposition::DroppedLinkDown);
+        }
+        let next = route.map(|r| r.1);
+        let padding = self.padding.for_route(next.as_ref());
+        if self.refragmenter.is_enabled() {
+            let mtu = self.mtu(hdr.dest);
+            let now_millis = self.clock.now_millis();
+            let (sender, hooks) = (&mut self.sender, &mut self.hooks);
+            let refragmented = self.refragmenter.push(hdr, pkt, mtu, now_millis, |out| {
+                hooks.capture(Direction::Outbound, now_millis, out);
+                send_packet(sender, hdr.dest, next.as_ref(), padding, out)
+            });
+            match refragmented {
+                Ok(Refragment::Unmodified) => (),
+                Ok(Refragment::MtuTooSmall) => {
+                    debug!("dropped packet for {}, mtu too small", hdr.dest.0);
+                    self.forward_stats.count(route, false);
+                    return Ok(Disposition::DroppedTooLarge);
+                }
+                Ok(Refragment::OutOfSequence) => {
+                    debug!("dropped packet for {}, out of sequence", hdr.dest.0);
+                    self.forward_stats.count(route, false);
+                    return Ok(Disposition::DroppedReassemblyError);
+                }
+                Ok(Refragment::Forwarded) | Err(_) => {
+                    return self.forwarded(hdr, hop, refragmented.map(|_| ()));
+                }
+            }
+        }
+        if pkt.len() > self.sender.get_mtu().min(S::Packet::LEN) {
+            debug!("dropped packet for {}, too large to forward", hdr.dest.0);
+            self.forward_stats.count(route, false);
+            return Ok(Disposition::DroppedTooLarge);
+        }
+        self.hooks
+            .capture(Direction::Outbound, self.clock.now_millis(), pkt);
+        let sent = send_packet(&mut self.sender, hdr.dest, next.as_ref(), padding, pkt);
+        self.forwarded(hdr, hop, sent)
+    }
+
+    /// Account for a packet forwarded to `hop`, `sent` is the result of sending it
+    fn forwarded(
+        &mut self,
+        hdr: &header::Header,
+        hop: Option<Hop>,
+        sent: Result<()>,
+    ) -> Result<Disposition> {
+        self.forward_stats
+            .count(hop.map(|hop| (hop.slot, hop.route)), sent.is_ok());
+        if let Some(hop) = hop.filter(|hop| !hop.backup) {
+            self.failover
+                .report(hop.slot, sent.is_ok(), self.clock.now_millis());
+        }
+        sent?;
+        trace!(
+            "forwarded packet from {} to {} with tag {}",
+            hdr.source.0,
+            hdr.dest.0,
+            hdr.tag.tag().0
+        );
+        Ok(Disposition::Forwarded)
+    }
+
+    /// Process an incoming packet, waking the tasks waiting on the handle it completes
+    ///
+    /// Errors are returned for packets rejected by the reassembly of the stack and for
+    /// failures processing them, e.g. forwarding them.
+    fn dispatch(&mut self, pkt: &[u8]) -> core::result::Result<Disposition, Rejected> {
+        let result = self.dispatch_packet(pkt);
+        let now_millis = self.clock.now_millis();
+        let traced = match &result {
+            Ok(disposition) => *disposition,
+            Err(rejected) => rejected.disposition,
+        };
+        self.dispatch_trace.record(now_millis, pkt, traced);
+        if header::Header::is_valid(pkt)
+            && let Some(hdr) = header::Header::parse(pkt)
+        {
+            self.peer_stats
+                .received(hdr.source, pkt.len(), traced, now_millis);
+        }
+        let disposition = result?;
+        if let Some(handle) = disposition.handle() {
+            self.wakers.wake(handle);
+            self.events.push(RouterEvent::MessageReady(handle));
+        } else if disposition.is_dropped() {
+            self.events.push(RouterEvent::Dropped(disposition));
+        }
+        Ok(disposition)
+    }
+
+    fn dispatch_packet(&mut self, pkt: &[u8]) -> core::result::Result<Disposition, Rejected> {
+        let _span = lifecycle_span!("mctp_inbound", len = pkt.len());
+        self.hooks
+            .capture(Direction::Inbound, self.clock.now_millis(), pkt);
+        trace!("inbound packet, {} bytes", pkt.len());
+        if self.promiscuous
+            && header::Header::is_valid(pkt)
+            && let Some(hdr) = header::Header::parse(pkt)
+            && let Some((header, payload)) = pkt.split_first_chunk()
+        {
+            self.hooks.snoop(&SnoopedPacket {
+                source: hdr.source,
+                dest: hdr.dest,
+                som: hdr.som,
+                eom: hdr.eom,
+                seq: hdr.seq,
+                tag: hdr.tag,
+                header: *header,
+                payload,
+            });
+        }
+        self.headers.inspect(pkt);
+        let mut rejected = None;
+        let mut reserved_bits = false;
+        validation::check(pkt, |v| {
+            self.violations.count(v);
+            if self.validation.rejects(v) {
+                rejected.get_or_insert(v);
+            }
+            reserved_bits |= v == Violation::ReservedHeaderBits;
+        });
+        if let Some(v) = rejected {
+            debug!("dropped invalid packet, violation {}", v as u8);
+            return Ok(Disposition::DroppedInvalid(v));
+        }
+        // Endpoints without an EID assigned share the null EID.
+        if self.stack.eid() != Eid(0)
+            && let Some(hdr) = header::Header::parse(pkt)
+            && self.is_local_eid(hdr.source)
+        {
+            warn!("dropped packet sourced from own eid {}", hdr.source.0);
+            self.eid_conflicts = self.eid_conflicts.wrapping_add(1);
+            self.hooks
+                .eid_conflict(hdr.source, EidConflict::SourcedFromOwnEid);
+            return Ok(Disposition::DroppedEidConflict);
+        }
+        if reserved_bits {
+            let mut buf = S::Packet::zeroed();
+            if let Some(pkt) = validation::clear_reserved(pkt, buf.as_mut()) {
+                return self.process(pkt);
+            }
+        }
+        self.process(pkt)
+    }
+
+    /// Process a validated packet
+    fn process(&mut self, pkt: &[u8]) -> core::result::Result<Disposition, Rejected> {
+        if let Some(limiter) = self.rate_limiter.as_mut()
+            && let Some(hdr) = header::Header::parse(pkt)
+            && !limiter.admit(hdr.source, self.clock.now_millis())
+        {
+            debug!("dropped packet from {}, rate limited", hdr.source.0);
+            return Ok(Disposition::DroppedRateLimited);
+        }
+        if self.forwarding
+            && let Some(hdr) = header::Header::parse(pkt)
+            && !self.is_local_eid(hdr.dest)
+            && hdr.dest != Eid(0)
+            && hdr.dest != Eid(0xff)
+            && !self.is_local_eid(hdr.source)
+        {
+            return Ok(self.forward(&hdr, pkt)?);
+        }
+        let Some(hdr) = header::Header::parse(pkt) else {
+            return self.reassemble(pkt);
+        };
+        if self.reorder.admit(&hdr, pkt) == Order::Held {
+            debug!(
+                "held back early packet from {} with tag {}",
+                hdr.source.0,
+                hdr.tag.tag().0
+            );
+            return Ok(Disposition::Incomplete);
+        }
+        let mut disposition = self
+            .reassemble(pkt)
+            .inspect_err(|_| self.reorder.forget(&hdr));
+        if disposition.is_ok() && self.reorder.held() > 0 {
+            let mut buf = S::Packet::zeroed();
+            while disposition.is_ok()
+                && let Some(len) = self.reorder.take_next(&hdr, buf.as_mut())
+            {
+                let held = buf.as_ref().get(..len).ok_or(Error::InternalError)?;
+                disposition = self
+                    .reassemble(held)
+                    .inspect_err(|_| self.reorder.forget(&hdr));
+            }
+        }
+        disposition
+    }
+
+    /// Pass an in-order packet to the reassembly and deliver completed messages
+    fn reassemble(&mut self, pkt: &[u8]) -> core::result::Result<Disposition, Rejected> {
+        let (own_eid, local_eids) = (self.stack.eid(), self.local_eids);
+        let is_local =
+            |eid: Eid| eid == own_eid || eid == Eid(0) || local_eids.contains(&Some(eid));
+        if let Some(hdr) = header::Header::parse(pkt)
+            && hdr.som
+            && let Some((header, rest)) = pkt.split_first_chunk()
+            && let Some((&typ, payload)) = rest.split_first()
+        {
+            let fragment = FirstFragment {
+                source: hdr.source,
+                dest: hdr.dest,
+                tag: hdr.tag,
+                typ: MsgType(typ & 0x7f),
+                ic: MsgIC(typ & 0x80 != 0),
+                header: *header,
+                payload,
+            };
+            if !self.hooks.first_fragment(&fragment) {
+                debug!("dropped message from {}, rejected by hook", hdr.source.0);
+                return Ok(Disposition::DroppedByHook);
+            }
+        }
+        if let Some(hdr) = header::Header::parse(pkt)
+            && is_local(hdr.dest)
+            && let Some(admit) = self
+                .reassembly_buffers
+                .admit(&hdr, pkt, self.clock.now_millis())
+        {
+            return Ok(self.admitted(&hdr, admit));
+        }
+        if let Some(hdr) = header::Header::parse(pkt)
+            && hdr.tag.is_owner()
+        {
+            let listener = match self.check_size(&hdr, pkt) {
+                SizeCheck::Accept => None,
+                SizeCheck::Reject { listener } => {
+                    self.reservations.forget(&hdr);
+                    Some(listener)
+                }
+                // The stack holds the partial message until its reassembly times out,
+                // so the context stays accounted for.
+                SizeCheck::Abort { listener } => Some(listener),
+                SizeCheck::Discard => {
+                    debug!(
+                        "dropped packet from {} with tag {}, message too large",
+                        hdr.source.0,
+                        hdr.tag.tag().0
+                    );
+                    return Ok(Disposition::DroppedTooLarge);
+                }
+            };
+            if let Some(listener) = listener {
+                Self::latch_error(
+                    &mut self.tables,
+                    ListenerHandle(listener).into(),
+                    HandleError::Dropped(Disposition::DroppedTooLarge),
+                );
+                debug!(
+                    "dropped request from {} with tag {}, too large",
+                    hdr.source.0,
+                    hdr.tag.tag().0
+                );
+                return Ok(Disposition::DroppedTooLarge);
+            }
+        }
+        if self.mtu_discovery
+            && let Some(hdr) = header::Header::parse(pkt)
+            && hdr.som
+            && !hdr.eom
+            && is_local(hdr.dest)
+            && let Some(&typ) = pkt.get(header::HEADER_LEN)
+            && self.accepts_mtu_from(&hdr, MsgType(typ & 0x7f))
+        {
+            // All packets but the last one of a message carry the senders transmission unit.
+            self.learn_mtu(hdr.source, pkt.len());
+        }
+        let hdr = header::Header::parse(pkt);
+        if let Some(hdr) = &hdr
+            && hdr.som
+            && hdr.eom
+            && hdr.tag.is_owner()
+            && is_local(hdr.dest)
+            && let Some((&typ, request)) =
+                pkt.get(header::HEADER_LEN..).and_then(|b| b.split_first())
+            && typ == control::MSG_TYPE_CONTROL.0
+            && !self.manual_control
+            && listener_index(self.tables.listeners(), control::MSG_TYPE_CONTROL).is_none()
+        {
+            // Single packet control requests are answered without a reassembly context.
+            // Messages for the application are held by the stack, so they take the regular path.
+            self.reservations.forget(hdr);
+            let request = request.get(..CONTROL_REQUEST_LEN).unwrap_or(request);
+            return Ok(self.answer_control(hdr.source, hdr.tag.tag(), request)?);
+        }
+        if let Some(hdr) = &hdr {
+            let now_millis = self.clock.now_millis();
+            if !hdr.som {
+                self.reservations.next(hdr, pkt.len(), now_millis);
+            } else if let Some(&typ) = pkt.get(header::HEADER_LEN) {
+                let typ = MsgType(typ & 0x7f);
+                self.reservations.forget(hdr);
+                if self.reassemblies_in_use() >= self.reservations.limit {
+                    debug!(
+                        "dropped message from {} with type {}, no reassembly context",
+                        hdr.source.0, typ.0
+                    );
+                    self.reservations.exhausted = self.reservations.exhausted.wrapping_add(1);
+                    return Ok(Disposition::DroppedNoContext);
+                }
+                if !self.context_available(typ) {
+                    debug!(
+                        "dropped message from {} with type {}, contexts reserved",
+                        hdr.source.0, typ.0
+                    );
+                    return Ok(Disposition::DroppedContextReserved);
+                }
+                self.reservations.start(hdr, typ, pkt.len(), now_millis);
+            }
+        }
+        let Some(mut msg) = self
+            .stack
+            .receive(pkt)
+            .inspect_err(|_| {
+                debug!("packet rejected by reassembly");
+                if let Some(hdr) = &hdr {
+                    self.reservations.forget(hdr);
+                }
+            })
+            .map_err(|err| Rejected {
+                err,
+                disposition: Disposition::DroppedReassemblyError,
+            })?
+        else {
+            return Ok(Disposition::Incomplete);
+        };
+
+        if !is_local(msg.dest) {
+            // Drop messages if eid does not match (for now).
+            // EID 0 messages are used for physical addressing
+            // and will thus be processed.
+            debug!(
+                "dropped message from {} for foreign eid {}",
+                msg.source.0, msg.dest.0
+            );
+            return Ok(Disposition::DroppedWrongEid);
+        }
+
+        if msg.ic.0 && !check_integrity(&msg) {
+            debug!(
+                "dropped message from {} with tag {}, integrity check failed",
+                msg.source.0,
+                msg.tag.tag().0
+            );
+            return Ok(Disposition::DroppedIntegrityError);
+        }
+
+        if let Some(handler) = self.mics.get(msg.typ, msg.ic)
+            && !mic::verify(handler, msg.typ, message_body(&msg))
+        {
+            debug!(
+                "dropped message from {} with tag {}, MIC check failed",
+                msg.source.0,
+                msg.tag.tag().0
+            );
+            return Ok(Disposition::DroppedIntegrityError);
+        }
+
+        if msg.typ == secured::MSG_TYPE_SECURED
+            && !secured::split(msg.payload)
+                .is_some_and(|(id, _)| self.sessions.contains(msg.source, id))
+        {
+            debug!("dropped secured message from {}, no session", msg.source.0);
+            return Ok(Disposition::DroppedNoSession);
+        }
+
+        match self.min_sizes.check(msg.typ, message_body(&msg).len()) {
+            None | Some(Undersized::Deliver) => (),
+            Some(Undersized::Drop) => {
+                debug!(
+                    "dropped undersized message from {} with tag {}",
+                    msg.source.0,
+                    msg.tag.tag().0
+                );
+                return Ok(Disposition::DroppedTooSmall);
+            }
+            Some(Undersized::Respond(responder)) => {
+                debug!(
+                    "dropped undersized message from {} with tag {}",
+                    msg.source.0,
+                    msg.tag.tag().0
+                );
+                let (source, typ, tag) = (msg.source, msg.typ, msg.tag);
+                let mut response = [0; MAX_ERROR_RESPONSE_LEN];
+                let len = match tag {
+                    Tag::Owned(_) => responder(typ, message_body(&msg), &mut response),
+                    Tag::Unowned(_) => None,
+                };
+                drop(msg);
+                if let Some(response) = len.and_then(|len| response.get(..len)) {
+                    self.answer_undersized(source, typ, tag.tag(), response)?;
+                }
+                return Ok(Disposition::DroppedTooSmall);
+            }
+        }
+
+        let (handle, admission) = match msg.tag {
+            Tag::Unowned(_) => {
+                // check for matching requests
+                let Some(cookie) = msg.cookie() else {
+                    return Ok(Self::drop_response(&mut self.hooks, &self.clock, &msg));
+                };
+                if cookie == INTERNAL_COOKIE {
+                    let (source, tag) = (msg.source, msg.tag.tag());
+                    let header = (msg.typ == control::MSG_TYPE_CONTROL)
+                        .then(|| control::ControlHeader::parse(msg.payload))
+                        .flatten();
+                    let body = msg.payload.get(control::CONTROL_HEADER_LEN..);
+                    let reported = match (header, body) {
+                        (
+                            Some(control::ControlHeader {
+                                command: control::CMD_GET_ENDPOINT_ID,
+                                ..
+                            }),
+                            Some(&[control::CC_SUCCESS, eid, ..]),
+                        ) => Some(Eid(eid)),
+                        _ => None,
+                    };
+                    drop(msg);
+                    if let Some(header) = header
+                        && header.command == control::CMD_DISCOVERY_NOTIFY
+                        && !header.request
+                    {
+                        return Ok(self.notify_answered(source, header.instance_id));
+                    }
+                    return Ok(self.probe_answered(source, tag, header, reported));
+                }
+                let Some(req) = self.tables.request_mut(cookie) else {
+                    return Ok(Self::drop_response(&mut self.hooks, &self.clock, &msg));
+                };
+                if req.typ.is_some_and(|typ| typ != msg.typ) {
+                    debug!(
+                        "dropped response from {} with tag {}, type {} does not match request",
+                        msg.source.0,
+                        msg.tag.tag().0,
+                        msg.typ.0
+                    );
+                    req.error = Some(HandleError::Dropped(Disposition::DroppedTypeMismatch));
+                    return Ok(Disposition::DroppedTypeMismatch);
+                }
+                let admission = req.queue.admit();
+                if !matches!(admission, Admission::Full(_)) {
+                    req.last_tag = None;
+                    req.attempt = 0;
+                }
+                lifecycle!(
+                    "response matched",
+                    eid = msg.source.0,
+                    tag = msg.tag.tag().0,
+                    handle = cookie.0,
+                );
+                (Handle::from(RequestHandle(cookie)), admission)
+            }
+            Tag::Owned(_) => {
+                // check for matching listeners and retain with cookie
+                let listeners = self.tables.listeners_mut();
+                let Some(i) = listener_index(listeners, msg.typ) else {
+                    if msg.typ == control::MSG_TYPE_CONTROL && !self.manual_control {
+                        let mut request = [0; CONTROL_REQUEST_LEN];
+                        let len = msg.payload.len().min(request.len());
+                        let request = request.get_mut(..len).ok_or(Error::InternalError)?;
+                        request.copy_from_slice(msg.payload.get(..len).unwrap_or_default());
+                        let (source, tag) = (msg.source, msg.tag.tag());
+                        drop(msg);
+                        return Ok(self.answer_control(source, tag, request)?);
+                    }
+                    debug!(
+                        "dropped request from {}, no listener for type {}",
+                        msg.source.0, msg.typ.0
+                    );
+                    return Ok(Disposition::DroppedNoListener);
+                };
+                let listener = listeners
+                    .get_mut(i)
+                    .and_then(|s| s.entry.as_mut())
+                    .ok_or(Error::InternalError)?;
+                if listener.acl.is_some_and(|acl| !acl.permits(msg.source)) {
+                    listener.denied = listener.denied.wrapping_add(1);
+                    listener.error = Some(HandleError::Dropped(Disposition::DroppedAccessDenied));
+                    debug!(
+                        "dropped request from {} for type {}, denied by acl",
+                        msg.source.0, msg.typ.0
+                    );
+                    return Ok(Disposition::DroppedAccessDenied);
+                }
+                let admission = listener.queue.admit();
+                let cookie = self.tables.listener_cookie(i)?;
+                msg.set_cookie(Some(cookie));
+                (Handle::from(ListenerHandle(cookie)), admission)
+            }
+        };
+
+        let evict = match admission {
+            Admission::Queued => false,
+            Admission::Evict => true,
+            Admission::Full(policy) => {
+                debug!(
+                    "dropped message from {}, receive queue of {} full",
+                    msg.source.0,
+                    handle.cookie().0
+                );
+                let disposition = if policy == OverflowPolicy::Reject {
+                    Disposition::RejectedQueueFull
+                } else {
+                    Disposition::DroppedQueueFull
+                };
+                Self::latch_error(&mut self.tables, handle, HandleError::Dropped(disposition));
+                return Ok(disposition);
+            }
+        };
+        msg.retain();
+        debug!(
+            "message from {} retained for {}",
+            msg.source.0,
+            handle.cookie().0
+        );
+        let (source, tag) = (msg.source, msg.tag);
+        drop(msg);
+        let now_millis = self.clock.now_millis();
+        if evict {
+            // The stack hands out the oldest retained message first.
+            let evicted = self
+                .stack
+                .get_deferred_bycookie(&[handle.cookie()])
+                .map(|m| (m.source, m.tag));
+            if let (Handle::Listener(listener), Some((source, tag))) = (handle, evicted) {
+                self.consumers.dropped(listener, source, tag);
+            }
+            self.retained.pop(handle.cookie());
+            debug!("dropped oldest message queued for {}", handle.cookie().0);
+        }
+        if let Handle::Listener(listener) = handle {
+            self.consumers.delivered(listener, source, tag, now_millis);
+        }
+        self.retained.push(handle.cookie(), now_millis);
+        Ok(Disposition::Delivered(handle))
+    }
+
+    /// Check if a message of type `typ` leaves enough free contexts for the reservations
+    fn context_available(&self, typ: MsgType) -> bool {
+        let shortfall: usize = self
+            .reservations
+            .iter()
+            .filter(|(t, _)| *t != typ)
+            .map(|(t, n)| {
+                n.saturating_sub(self.reservations.active(Some(t)) + self.retained(Some(t)))
+            })
+            .sum();
+        if shortfall == 0 {
+            return true;
+        }
+        self.reservations
+            .limit
+            .saturating_sub(self.reassemblies_in_use())
+            > shortfall
+    }
+
+    /// Number of messages of `typ` (or all types) retained until they are received
+    fn retained(&self, typ: Option<MsgType>) -> usize {
+        let matches = |t: Option<MsgType>| typ.is_none() || t == typ;
+        let listeners: usize = self
+            .tables
+            .listeners()
+            .iter()
+            .filter_map(|s| s.entry.as_ref())
+            .filter(|l| typ.is_none() || l.accepts(typ))
+            .map(|l| l.queue.queued)
+            .sum();
+        let requests: usize = self
+            .tables
+            .requests()
+            .iter()
+            .filter_map(|s| s.entry.as_ref())
+            .filter(|r| matches(r.typ))
+            .map(|r| r.queue.queued)
+            .sum();
+        listeners + requests
+    }
+
+    /// Map the outcome of a packet reassembled into a listener buffer to its disposition
+    fn admitted(&mut self, hdr: &header::Header, admit: Admit) -> Disposition {
+        match admit {
+            Admit::Incomplete => Disposition::Incomplete,
+            Admit::Complete(handle) | Admit::Chunk(handle) => {
+                let Some(listener) = self.tables.listener_mut(handle.0) else {
+                    self.reassembly_buffers.discard(handle);
+                    return Disposition::DroppedNoListener;
+                };
+                if listener.acl.is_some_and(|acl| !acl.permits(hdr.source)) {
+                    listener.denied = listener.denied.wrapping_add(1);
+                    listener.error = Some(HandleError::Dropped(Disposition::DroppedAccessDenied));
+                    self.reassembly_buffers.discard(handle);
+                    debug!(
+                        "dropped request from {} for {}, denied by acl",
+                        hdr.source.0, handle.0.0
+                    );
+                    return Disposition::DroppedAccessDenied;
+                }
+                if let Admit::Chunk(_) = admit {
+                    self.wakers.wake(handle.into());
+                    return Disposition::Incomplete;
+                }
+                debug!(
+                    "message from {} reassembled for {}",
+                    hdr.source.0, handle.0.0
+                );
+                Disposition::Delivered(handle.into())
+            }
+            Admit::Busy => {
+                debug!(
+                    "dropped request from {}, reassembly buffer in use",
+                    hdr.source.0
+                );
+                Disposition::DroppedQueueFull
+            }
+            Admit::TooLarge => {
+                debug!(
+                    "dropped request from {}, exceeds reassembly buffer",
+                    hdr.source.0
+                );
+                Disposition::DroppedTooLarge
+            }
+            Admit::OutOfSequence => {
+                debug!("dropped request from {}, packet lost", hdr.source.0);
+                Disposition::DroppedReassemblyError
+            }
+            Admit::IntegrityError => {
+                debug!(
+                    "dropped request from {}, integrity check failed",
+                    hdr.source.0
+                );
+                Disposition::DroppedIntegrityError
+            }
+        }
+    }
+
+    /// Answer a control `request` from `source` that has no listener
+    fn answer_control(
+        &mut self,
+        source: Eid,
+        tag: TagValue,
+        request: &[u8],
+    ) -> Result<Disposition> {
+        let mut response = [0; control::CONTROL_HEADER_LEN + 2 + 4 * control::codec::MAX_VERSIONS];
+        let Some(len) = self.control_response(source, request, &mut response)? else {
+            debug!("dropped control request from {}, no listener", source.0);
+            return Ok(Disposition::DroppedNoListener);
+        };
+        if self.quiesced {
+            debug!("dropped control request from {}, quiesced", source.0);
+            return Ok(Disposition::DroppedNoListener);
+        }
+        let now_millis = self.clock.now_millis();
+        let response = response.get(..len).ok_or(Error::InternalError)?;
+        let frag = self.start_message(
+            source,
+            control::MSG_TYPE_CONTROL,
+            Some(Tag::Unowned(tag)),
+            MsgIC(false),
+            None,
+            None,
+            Some(response.len()),
+        )?;
+        self.transmit(source, frag, &[response], now_millis, None, None)?;
+        Ok(Disposition::ControlAnswered)
+    }
+
+    /// Send the error `response` to an undersized request, see [Undersized::Respond]
+    fn answer_undersized(
+        &mut self,
+        source: Eid,
+        typ: MsgType,
+        tag: TagValue,
+        response: &[u8],
+    ) -> Result<()> {
+        if self.quiesced {
+            debug!(
+                "no response to undersized request from {}, quiesced",
+                source.0
+            );
+            return Ok(());
+        }
+        let now_millis = self.clock.now_millis();
+        let frag = self.start_message(
+            source,
+            typ,
+            Some(Tag::Unowned(tag)),
+            MsgIC(false),
+            None,
+            None,
+            Some(response.len()),
+        )?;
+        self.transmit(source, frag, &[response], now_millis, None, None)?;
+        Ok(())
+    }
+
+    /// Log a response that is not associated with an active request
+    fn drop_response(hooks: &mut H, clock: &C, msg: &MctpMessage<'_>) -> Disposition {
+        // In this case an unowned message not associated with a request was received.
+        // This might happen if this endpoint was intended to route the packet to a different
+        // bus it is connected to (bridge configuration).
+        // Support for this is missing right now.
+        debug!(
+            "dropped response from {} with tag {}, no matching request",
+            msg.source.0,
+            msg.tag.tag().0
+        );
+        hooks.orphan_response(&OrphanResponse {
+            source: msg.source,
+            dest: msg.dest,
+            tag: msg.tag,
+            typ: msg.typ,
+            ic: msg.ic,
+            now_millis: clock.now_millis(),
+            payload: msg.payload,
+        });
+        Disposition::DroppedNoRequest
+    }
+
+    /// Check a request packet against the maximum message size of its listener
+    fn check_size(&mut self, hdr: &header::Header, pkt: &[u8]) -> SizeCheck {
+        let now_millis = self.clock.now_millis();
+        if hdr.som {
+            let listeners = self.tables.listeners();
+            let index = pkt
+                .get(header::HEADER_LEN)
+                .and_then(|t| listener_index(listeners, MsgType(t & 0x7f)));
+            let limit = index
+                .and_then(|i| listeners.get(i))
+                .and_then(|s| s.entry.as_ref())
+                .and_then(|l| l.max_size)
+                .zip(index.and_then(|i| self.tables.listener_cookie(i).ok()));
+            self.size_tracker.start(hdr, pkt, limit, now_millis)
+        } else {
+            self.size_tracker.next(hdr, pkt, now_millis)
+        }
+    }
+
+    /// Allocate a new request "_Handle_"
+    ///
+    /// Responses are only delivered if their message type matches the last request sent,
+    /// others are dropped with [DroppedTypeMismatch](Disposition::DroppedTypeMismatch).
+    pub fn req(&mut self, eid: Eid) -> Result<RequestHandle> {
+        if self.shut_down {
+            return Err(Error::Other);
+        }
+        let now_millis = self.clock.now_millis();
+        let index = self
+            .tables
+            .requests()
+            .iter()
+            .position(|x| x.entry.is_none())
+            .ok_or(mctp::Error::NoSpace)?;
+        let cookie = self.tables.request_cookie(index)?;
+        if let Some(slot) = self.tables.requests_mut().get_mut(index) {
+            slot.entry = Some(ReqHandle::new(eid, now_millis));
+        }
+        Ok(RequestHandle(cookie))
+    }
+
+    /// Allocate a new listener for [`typ`](MsgType)
+    ///
+    /// Returns a [ListenerHandle] when successful, [AddrInUse](mctp::Error::AddrInUse) when a
+    /// listener for `typ` already exists,
+    /// [NoSpace](mctp::Error::NoSpace) when all listener slots are occupied,
+    /// [BadArgument](mctp::Error::BadArgument) when `typ` is not declared although
+    /// [declared types only](Self::set_declared_types_only) are allowed.
+    pub fn listener(&mut self, typ: MsgType) -> Result<ListenerHandle> {
+        if !self.capabilities.allows_listener(Some(typ)) {
+            return Err(Error::BadArgument);
+        }
+        self.bind_listener(Some(typ))
+    }
+
+    /// Allocate the catch-all listener
+    ///
+    /// It receives requests of all message types without a listener of their own, so
+    /// the application can answer them with a protocol-level error or log unexpected traffic
+    /// instead of having them dropped with [DroppedNoListener](Disposition::DroppedNoListener).
+    /// The type of each request is reported in its [MessageInfo].
+    /// The catch-all listener can't have a [reassembly buffer](Self::set_reassembly_buffer)
+    /// and is not reported by [listeners()](Self::listeners).
+    ///
+    /// Returns [AddrInUse](mctp::Error::AddrInUse) when the catch-all listener exists
+    /// already, [NoSpace](mctp::Error::NoSpace) when all listener slots are occupied.
+    pub fn catch_all_listener(&mut self) -> Result<ListenerHandle> {
+        self.bind_listener(None)
+    }
+
+    /// Allocate a listener receiving requests of all message types in `types`
+    ///
+    /// The types share the cookie and receive queue of a single listener, e.g. PLDM and its
+    /// vendor extension handled by one task, saving handles and wakeups. The type of each
+    /// request is reported in its [MessageInfo]. ACLs, size limits and other listener
+    /// settings apply to all types, a [reassembly buffer](Self::set_reassembly_buffer) only
+    /// to the first one.
+    ///
+    /// Returns [BadArgument](mctp::Error::BadArgument) when `types` is empty, has duplicates,
+    /// more than [LISTENER_GROUP_SIZE] entries or types that are not declared although
+    /// [declared types only](Self::set_declared_types_only) are allowed,
+    /// [AddrInUse](mctp::Error::AddrInUse) when a listener for one of the types exists,
+    /// [NoSpace](mctp::Error::NoSpace) when all listener slots are occupied.
+    pub fn listener_group(&mut self, types: &[MsgType]) -> Result<ListenerHandle> {
+        let (&first, rest) = types.split_first().ok_or(Error::BadArgument)?;
+        if types.len() > LISTENER_GROUP_SIZE
+            || types
+                .iter()
+                .enumerate()
+                .any(|(i, t)| types.get(..i).is_some_and(|prev| prev.contains(t)))
+            || !types
+                .iter()
+                .all(|&t| self.capabilities.allows_listener(Some(t)))
+        {
+            return Err(Error::BadArgument);
+        }
+        let bound = |t: &MsgType| {
+            self.tables
+                .listeners()
+                .iter()
+                .any(|x| x.entry.as_ref().is_some_and(|l| l.accepts(Some(*t))))
+        };
+        if rest.iter().any(bound) {
+            return Err(Error::AddrInUse);
+        }
+        let handle = self.bind_listener(Some(first))?;
+        if let Some(listener) = self.tables.listener_mut(handle.0) {
+            for (slot, &typ) in listener.group.iter_mut().zip(rest) {
+                *slot = Some(typ);
+            }
+        }
+        Ok(handle)
+    }
+
+    /// Add `typ` to the message types the listener `handle` receives
+    ///
+    /// Turns a listener into a [listener group](Self::listener_group), requests of `typ` are
+    /// queued for `handle` from now on.
+    ///
+    /// Returns [AddrInUse](Error::AddrInUse) if a listener receives `typ` already,
+    /// [NoSpace](Error::NoSpace) if the listener has [LISTENER_GROUP_SIZE] types,
+    /// [BadArgument](Error::BadArgument) if `handle` is not bound or is the catch-all listener,
+    /// or if `typ` is not declared although [declared types only](Self::set_declared_types_only)
+    /// are allowed.
+    pub fn add_listener_type(&mut self, handle: ListenerHandle, typ: MsgType) -> RouterResult<()> {
+        let context = |e: Error| RouterError::new(e).with_handle(handle.into());
+        if self
+            .tables
+            .listener(handle.0)
+            .is_none_or(|l| l.typ.is_none())
+            || !self.capabilities.allows_listener(Some(typ))
+        {
+            return Err(context(Error::BadArgument));
+        }
+        if self
+            .tables
+            .listeners()
+            .iter()
+            .any(|x| x.entry.as_ref().is_some_and(|l| l.accepts(Some(typ))))
+        {
+            return Err(context(Error::AddrInUse));
+        }
+        let slot = self
+            .tables
+            .listener_mut(handle.0)
+            .and_then(|l| l.group.iter_mut().find(|t| t.is_none()))
+            .ok_or_else(|| context(Error::NoSpace))?;
+        *slot = Some(typ);
+        debug!("added type {} to listener {}", typ.0, handle.0.0);
+        Ok(())
+    }
+
+    /// Receive requests of type `typ` on the listener `handle` instead of its current type
+    ///
+    /// Requests queued for the listener stay queued and its cookie stays valid, so copies of
+    /// the handle held elsewhere keep working, e.g. for a protocol handler switching between
+    /// a vendor-defined and a standard type. Requests of the previous type are handled as if
+    /// the listener was unbound from now on, including those still being reassembled.
+    ///
+    /// Returns [AddrInUse](Error::AddrInUse) if another listener is bound to `typ`,
+    /// [BadArgument](Error::BadArgument) if `handle` is not bound or is the catch-all listener,
+    /// or if `typ` is not declared although
+    /// [declared types only](Self::set_declared_types_only) are allowed.
+    pub fn rebind(&mut self, handle: ListenerHandle, typ: MsgType) -> RouterResult<()> {
+        let context = |e: Error| RouterError::new(e).with_handle(handle.into());
+        let previous = self
+            .tables
+            .listener(handle.0)
+            .ok_or_else(|| context(Error::BadArgument))?
+            .typ
+            .ok_or_else(|| context(Error::BadArgument))?;
+        if previous == typ {
+            return Ok(());
+        }
+        if !self.capabilities.allows_listener(Some(typ)) {
+            return Err(context(Error::BadArgument));
+        }
+        if self
+            .tables
+            .listeners()
+            .iter()
+            .any(|x| x.entry.as_ref().is_some_and(|l| l.accepts(Some(typ))))
+        {
+            return Err(context(Error::AddrInUse));
+        }
+        if let Some(listener) = self.tables.listener_mut(handle.0) {
+            listener.typ = Some(typ);
+        }
+        self.reassembly_buffers.retype(handle, typ);
+        debug!("rebound listener from type {} to {}", previous.0, typ.0);
+        Ok(())
+    }
+
+    /// Allocate a listener for `typ`, the catch-all listener for `None`
+    fn bind_listener(&mut self, typ: Option<MsgType>) -> Result<ListenerHandle> {
+        if self.shut_down {
+            return Err(Error::Other);
+        }
+        if self
+            .tables
+            .listeners()
+            .iter()
+            .any(|x| x.entry.as_ref().is_some_and(|l| l.accepts(typ)))
+        {
+            return Err(mctp::Error::AddrInUse);
+        }
+        let index = self
+            .tables
+            .listeners()
+            .iter()
+            .position(|x| x.entry.is_none())
+            .ok_or(mctp::Error::NoSpace)?;
+        let cookie = self.tables.listener_cookie(index)?;
+        if let Some(slot) = self.tables.listeners_mut().get_mut(index) {
+            slot.entry = Some(ListenerEntry::new(typ));
+        }
+        Ok(ListenerHandle(cookie))
+    }
+
+    /// Restrict the source EIDs `handle` accepts requests from
+    ///
+    /// Requests rejected by `acl` are dropped with
+    /// [DroppedAccessDenied](Disposition::DroppedAccessDenied) and counted,
+    /// see [listener_denied()](Self::listener_denied). `None` accepts all sources.
+    pub fn set_listener_acl(
+        &mut self,
+        handle: ListenerHandle,
+        acl: Option<EidAcl>,
+    ) -> RouterResult<()> {
+        let listener = self
+            .tables
+            .listener_mut(handle.0)
+            .ok_or_else(|| RouterError::new(Error::BadArgument).with_handle(handle.into()))?;
+        listener.acl = acl;
+        Ok(())
+    }
+
+    /// Limit the size of requests `handle` accepts to `max_size` bytes
+    ///
+    /// The size excludes the message type byte. Requests are dropped with
+    /// [DroppedTooLarge](Disposition::DroppedTooLarge) as soon as a packet exceeds the limit,
+    /// and so are their remaining packets. The stack can't cancel a reassembly, the partial
+    /// message holds its reassembly context until it times out. `None` removes the limit.
+    pub fn set_listener_max_size(
+        &mut self,
+        handle: ListenerHandle,
+        max_size: Option<usize>,
+    ) -> RouterResult<()> {
+        let listener = self
+            .tables
+            .listener_mut(handle.0)
+            .ok_or_else(|| RouterError::new(Error::BadArgument).with_handle(handle.into()))?;
+        listener.max_size = max_size;
+        Ok(())
+    }
+
+    /// Reassemble requests for `handle` into `buf` instead of the buffers of the stack
+    ///
+    /// Lifts the message size limit of the stack for the listener to the size of `buf`,
+    /// or provides dedicated storage for it on targets with small stack buffers. `buf` holds
+    /// one message at a time, which has to be received with [recv_into()](Self::recv_into)
+    /// before the next one is accepted; requests arriving meanwhile are dropped with
+    /// [DroppedQueueFull](Disposition::DroppedQueueFull).
+    /// [recv()](Self::recv) and the other receive functions don't see these messages.
+    ///
+    /// Messages larger than `buf` can be received in chunks, see
+    /// [set_streaming()](Self::set_streaming).
+    ///
+    /// Returns the previous buffer of the listener. Unbinding the listener drops its buffer,
+    /// take it back with [take_reassembly_buffer()](Self::take_reassembly_buffer) first.
+    /// Returns [NoSpace](Error::NoSpace) if [REASSEMBLY_BUFFER_TABLE_SIZE] listeners have a
+    /// buffer already.
+    pub fn set_reassembly_buffer(
+        &mut self,
+        handle: ListenerHandle,
+        buf: &'static mut [u8],
+    ) -> RouterResult<Option<&'static mut [u8]>> {
+        let context = |e: Error| RouterError::new(e).with_handle(handle.into());
+        let typ = self
+            .tables
+            .listener(handle.0)
+            .ok_or_else(|| context(Error::BadArgument))?
+            .typ
+            .ok_or_else(|| context(Error::BadArgument))?;
+        self.reassembly_buffers
+            .set(handle, typ, buf)
+            .map_err(|_| context(Error::NoSpace))
+    }
+
+    /// Set whether messages in the reassembly buffer of `handle` are received in chunks
+    ///
+    /// In streaming mode the application drains the buffer with
+    /// [recv_chunk()](Self::recv_chunk) while the message is still being received, so its
+    /// size is no longer limited by the buffer, e.g. for firmware images larger than the
+    /// available RAM. The listener is woken whenever the buffer is at least half full.
+    /// A packet not fitting into the buffer because it wasn't drained in time aborts the
+    /// message with [DroppedTooLarge](Disposition::DroppedTooLarge).
+    /// Messages not drained before they are complete can still be received with
+    /// [recv_into()](Self::recv_into).
+    ///
+    /// Discards the message in the buffer.
+    /// Returns [BadArgument](Error::BadArgument) if `handle` has no reassembly buffer,
+    /// see [set_reassembly_buffer()](Self::set_reassembly_buffer).
+    pub fn set_streaming(&mut self, handle: ListenerHandle, streaming: bool) -> RouterResult<()> {
+        if !self.reassembly_buffers.set_streaming(handle, streaming) {
+            return Err(RouterError::new(Error::BadArgument).with_handle(handle.into()));
+        }
+        Ok(())
+    }
+
+    /// Receive the next chunk of a message for the streaming listener `handle` into `buf`
+    ///
+    /// Copies as much of the reassembled data as fits to the start of `buf`, freeing the
+    /// space in the reassembly buffer. Chunks are returned in order; the last one of a
+    /// message has [last](MessageChunk::last) set and may be empty. With the integrity check
+    /// flag set, the last chunk is only returned once the message passed the check.
+    /// A message that is aborted (lost packets, timeouts, failed integrity checks) never
+    /// gets its last chunk, the next message starts at [offset](MessageChunk::offset) 0.
+    ///
+    /// Returns `Ok(None)` when no data is available,
+    /// [BadArgument](Error::BadArgument) when the handle is no longer bound.
+    /// See [set_streaming()](Self::set_streaming).
+    pub fn recv_chunk(
+        &mut self,
+        handle: ListenerHandle,
+        buf: &mut [u8],
+    ) -> RouterResult<Option<MessageChunk>> {
+        if self.tables.listener(handle.0).is_none() {
+            return Err(RouterError::new(Error::BadArgument).with_handle(handle.into()));
+        }
+        Ok(self.reassembly_buffers.drain(handle, buf))
+    }
+
+    /// Remove the reassembly buffer of `handle`, discarding the message in it
+    ///
+    /// Requests for the listener are reassembled by the stack again.
+    pub fn take_reassembly_buffer(&mut self, handle: ListenerHandle) -> Option<&'static mut [u8]> {
+        self.reassembly_buffers.take(handle)
+    }
+
+    /// Reserve `count` reassembly contexts of the stack for messages of type `typ`
+    ///
+    /// Contexts are in use while a message is reassembled and until it is received.
+    /// A message of another type is dropped with
+    /// [DroppedContextReserved](Disposition::DroppedContextReserved) when it would leave
+    /// fewer free contexts than are reserved and not in use by their types, e.g. to keep
+    /// control messages flowing during bulk transfers. `0` removes the reservation.
+    ///
+    /// Returns [BadArgument](Error::BadArgument) if all reservations together exceed
+    /// [max_reassemblies()](Self::max_reassemblies), [NoSpace](Error::NoSpace) if [RESERVATION_TABLE_SIZE] types
+    /// have a reservation already.
+    pub fn reserve_contexts(&mut self, typ: MsgType, count: usize) -> Result<()> {
+        self.reservations.reserve(typ, count)
+    }
+
+    /// Get the number of reassembly contexts reserved for `typ`
+    pub fn reserved_contexts(&self, typ: MsgType) -> usize {
+        self.reservations.reserved(typ)
+    }
+
+    /// Limit the number of reassembly contexts of the stack the router uses to `max`
+    ///
+    /// Messages are dropped with [DroppedNoContext](Disposition::DroppedNoContext) while
+    /// `max` contexts are in use, counted in [reassemblies_exhausted()](Self::reassemblies_exhausted).
+    /// The contexts themselves are allocated by the stack, see [MAX_REASSEMBLIES].
+    ///
+    /// Returns [BadArgument](Error::BadArgument) for 0, more than [MAX_REASSEMBLIES] or less
+    /// than the contexts reserved with [reserve_contexts()](Self::reserve_contexts).
+    pub fn set_max_reassemblies(&mut self, max: usize) -> Result<()> {
+        let reserved: usize = self.reservations.iter().map(|(_, n)| n).sum();
+        if max == 0 || max > MAX_REASSEMBLIES || max < reserved {
+            return Err(Error::BadArgument);
+        }
+        self.reservations.limit = max;
+        Ok(())
+    }
+
+    /// Get the number of reassembly contexts the router uses
+    pub fn max_reassemblies(&self) -> usize {
+        self.reservations.limit
+    }
+
+    /// Get the number of reassembly contexts currently in use
+    ///
+    /// Counts messages being reassembled and messages waiting to be received.
+    pub fn reassemblies_in_use(&self) -> usize {
+        self.reservations.active(None) + self.retained(None)
+    }
+
+    /// Get the number of messages dropped because all reassembly contexts were in use
+    pub fn reassemblies_exhausted(&self) -> usize {
+        self.reservations.exhausted
+    }
+
+    /// Bound the number of messages queued for `handle`
+    ///
+    /// Messages beyond `limit.depth` are handled according to `limit.policy`.
+    /// `None` (the default) leaves the bound to the reassembly buffers of the stack.
+    pub fn set_queue_limit(
+        &mut self,
+        handle: impl Into<Handle>,
+        limit: Option<QueueLimit>,
+    ) -> RouterResult<()> {
+        let handle = handle.into();
+        let queue = match handle {
+            Handle::Listener(h) => self.tables.listener_mut(h.0).map(|l| &mut l.queue),
+            Handle::Request(h) => self.tables.request_mut(h.0).map(|r| &mut r.queue),
+        }
+        .ok_or_else(|| RouterError::new(Error::BadArgument).with_handle(handle))?;
+        queue.limit = limit;
+        Ok(())
+    }
+
+    /// Attach an application value to `handle`
+    ///
+    /// The value is kept until the handle is unbound, new handles start with 0.
+    /// Lets a dispatcher map deliveries (e.g. [Disposition::Delivered]) and received messages
+    /// back to its own state, see [handle_context()](Self::handle_context).
+    /// Returns [BadArgument](Error::BadArgument) if the handle is not bound.
+    pub fn set_handle_context(
+        &mut self,
+        handle: impl Into<Handle>,
+        context: usize,
+    ) -> RouterResult<()> {
+        let handle = handle.into();
+        let slot = match handle {
+            Handle::Listener(h) => self.tables.listener_mut(h.0).map(|l| &mut l.context),
+            Handle::Request(h) => self.tables.request_mut(h.0).map(|r| &mut r.context),
+        }
+        .ok_or_else(|| RouterError::new(Error::BadArgument).with_handle(handle))?;
+        *slot = context;
+        Ok(())
+    }
+
+    /// Get the application value attached to `handle`
+    ///
+    /// Returns `None` if the handle is not bound.
+    pub fn handle_context(&self, handle: impl Into<Handle>) -> Option<usize> {
+        match handle.into() {
+            Handle::Listener(h) => self.tables.listener(h.0).map(|l| l.context),
+            Handle::Request(h) => self.tables.request(h.0).map(|r| r.context),
+        }
+    }
+
+    /// Get the number of messages queued for `handle`
+    ///
+    /// Includes a message completed in the reassembly buffer of a listener, see
+    /// [set_reassembly_buffer()](Self::set_reassembly_buffer).
+    /// Returns `None` if the handle is not bound.
+    pub fn queued(&self, handle: impl Into<Handle>) -> Option<usize> {
+        match handle.into() {
+            Handle::Listener(h) => self.tables.listener(h.0).map(|l| {
+                l.queue.queued + usize::from(self.reassembly_buffers.complete(h).is_some())
+            }),
+            Handle::Request(h) => self.tables.request(h.0).map(|r| r.queue.queued),
+        }
+    }
+
+    /// Take the last error encountered on behalf of `handle`, clearing it
+    ///
+    /// The router latches why traffic of a listener or request was lost: failed sends,
+    /// expired messages and messages for the handle dropped before delivery, see
+    /// [HandleError]. Only the latest error is kept, so applications that only drive a
+    /// handle through receive and send loops find out why its traffic stopped.
+    /// Returns `None` if nothing failed since the last call, or the handle is not bound.
+    pub fn take_error(&mut self, handle: impl Into<Handle>) -> Option<HandleError> {
+        match handle.into() {
+            Handle::Listener(h) => self.tables.listener_mut(h.0)?.error.take(),
+            Handle::Request(h) => self.tables.request_mut(h.0)?.error.take(),
+        }
+    }
+
+    /// Iterate over the listeners and requests with messages waiting to be received
+    ///
+    /// Lets an event loop dispatch to the tasks owning these handles instead of calling
+    /// [recv()](Self::recv) speculatively on every handle. Each handle is reported at most
+    /// once, listeners first; with [RoundRobin](Scheduling::RoundRobin) scheduling the
+    /// handles received from least recently come first.
+    pub fn poll_ready(&self) -> impl Iterator<Item = Handle> + '_ {
+        let fair = self.scheduler.is_fair();
+        let stamp = move |queue: &RecvQueue| if fair { queue.served } else { 0 };
+        let listeners = self
+            .tables
+            .listeners()
+            .iter()
+            .enumerate()
+            .filter_map(|(i, slot)| Some((i, slot.entry.as_ref()?)))
+            .filter_map(move |(i, l)| {
+                let handle = ListenerHandle(self.tables.listener_cookie(i).ok()?);
+                let buffered = self.reassembly_buffers.complete(handle).is_some();
+                (l.queue.queued > 0 || buffered).then(|| (stamp(&l.queue), handle.into()))
+            });
+        let requests = self
+            .tables
+            .requests()
+            .iter()
+            .enumerate()
+            .filter_map(|(i, slot)| Some((i, slot.entry.as_ref()?)))
+            .filter(|(_, r)| r.queue.queued > 0)
+            .filter_map(move |(i, r)| {
+                let cookie = self.tables.request_cookie(i).ok()?;
+                Some((stamp(&r.queue), RequestHandle(cookie).into()))
+            });
+        scheduling::least_recent(listeners.chain(requests))
+    }
+
+    /// Set how receive processing and bus time are shared among handles, see [Scheduling]
+    ///
+    /// With [RoundRobin](Scheduling::RoundRobin), [poll_ready()](Self::poll_ready) reports
+    /// the handles received from least recently first, and packets held back by
+    /// [throttles](Self::set_throttle) are released one per handle in turn, so packets of
+    /// different handles to the same destination may interleave. Packets of each handle stay
+    /// in order.
+    pub fn set_scheduling(&mut self, scheduling: Scheduling) {
+        self.scheduler.policy = scheduling;
+    }
+
+    /// Get the number of requests for `handle` dropped by its [EidAcl]
+    ///
+    /// Returns `None` if the handle is not bound.
+    pub fn listener_denied(&self, handle: ListenerHandle) -> Option<usize> {
+        self.tables.listener(handle.0).map(|l| l.denied)
+    }
+
+    /// Iterate over the bound listeners and their message types
+    ///
+    /// A [listener group](Self::listener_group) is reported once per message type.
+    pub fn listeners(&self) -> impl Iterator<Item = (ListenerHandle, MsgType)> + '_ {
+        self.tables
+            .listeners()
+            .iter()
+            .enumerate()
+            .filter_map(|(i, slot)| {
+                let cookie = self.tables.listener_cookie(i).ok()?;
+                Some((ListenerHandle(cookie), slot.entry.as_ref()?))
+            })
+            .flat_map(|(handle, l)| l.types().map(move |typ| (handle, typ)))
+    }
+
+    /// Iterate over the messages being reassembled
+    ///
+    /// Includes messages reassembled by the stack and into the buffers of listeners, see
+    /// [set_reassembly_buffer()](Self::set_reassembly_buffer). Ages are relative to the
+    /// current time of the [Clock]. Messages that completed and wait to be received are not
+    /// included.
+    pub fn reassemblies(&self) -> impl Iterator<Item = ReassemblyInfo> + '_ {
+        self.reassembly_flows().map(|(_, info)| info)
+    }
+
+    /// Iterate over the messages being reassembled for `handle`
+    ///
+    /// For a listener these are requests of its message type (or requests no other listener
+    /// takes, for the catch-all listener), for a request the response to the last request
+    /// sent. Nothing is returned for unbound handles.
+    /// The messages are not guaranteed to be delivered to `handle` when complete, they may
+    /// still be dropped, e.g. by an [EidAcl].
+    pub fn reassemblies_for(
+        &self,
+        handle: impl Into<Handle>,
+    ) -> impl Iterator<Item = ReassemblyInfo> + '_ {
+        let handle = handle.into();
+        self.reassembly_flows()
+            .filter(move |(buffer, info)| match handle {
+                Handle::Listener(h) => match buffer {
+                    Some(l) => *l == h,
+                    None => {
+                        info.tag.is_owner()
+                            && self.tables.listener_index(h.0).is_some_and(|i| {
+                                listener_index(self.tables.listeners(), info.typ) == Some(i)
+                            })
+                    }
+                },
+                Handle::Request(h) => {
+                    buffer.is_none()
+                        && !info.tag.is_owner()
+                        && self.tables.request(h.0).is_some_and(|r| {
+                            r.eid == info.source
+                                && r.last_tag.is_some_and(|t| t.tag() == info.tag.tag())
+                        })
+                }
+            })
+            .map(|(_, info)| info)
+    }
+
+    /// Iterate over the messages being reassembled, with the listener of the buffer they are
+    /// reassembled in
+    fn reassembly_flows(
+        &self,
+    ) -> impl Iterator<Item = (Option<ListenerHandle>, ReassemblyInfo)> + '_ {
+        let now_millis = self.clock.now_millis();
+        let info = move |f: &reservations::Flow| ReassemblyInfo {
+            source: f.source,
+            tag: f.tag,
+            typ: f.typ,
+            bytes: f.bytes,
+            age_millis: now_millis.saturating_sub(f.started_millis),
+            idle_millis: now_millis.saturating_sub(f.last_millis),
+        };
+        self.reservations
+            .flows()
+            .map(move |f| (None, info(f)))
+            .chain(
+                self.reassembly_buffers
+                    .flows()
+                    .map(move |(l, f)| (Some(l), info(&f))),
+            )
+    }
+
+    /// Iterate over the active requests
+    ///
+    /// The age of a request is relative to the current time of the [Clock].
+    pub fn requests(&self) -> impl Iterator<Item = RequestInfo> + '_ {
+        let now_millis = self.clock.now_millis();
+        self.tables
+            .requests()
+            .iter()
+            .enumerate()
+            .filter_map(move |(i, slot)| {
+                let cookie = self.tables.request_cookie(i).ok()?;
+                slot.entry.as_ref().map(|req| RequestInfo {
+                    handle: RequestHandle(cookie),
+                    eid: req.eid,
+                    tag: req.last_tag,
+                    age_millis: now_millis.saturating_sub(req.created_millis),
+                    context: req.context,
+                })
+            })
+    }
+
+    /// Get the currently configured _Eid_ for this endpoint
+    pub fn get_eid(&self) -> Eid {
+        self.stack.eid()
+    }
+
+    /// Add a secondary local EID
+    ///
+    /// Messages to any local EID are accepted, listeners receive them regardless of the EID
+    /// they were sent to. Responses sent with [SendArgs::response_to()] come from the EID the
+    /// request was sent to; requests are sent from a secondary EID with
+    /// [SendArgs::source()] or [set_source_eid()](Self::set_source_eid).
+    /// MCTP control requests are always answered from the own EID.
+    ///
+    /// Returns [BadArgument](Error::BadArgument) for the null, broadcast and reserved EIDs,
+    /// [NoSpace](Error::NoSpace) when all [LOCAL_EID_TABLE_SIZE] entries are used.
+    pub fn add_local_eid(&mut self, eid: Eid) -> Result<()> {
+        let eid = Eid::new_normal(eid.0).map_err(|_| Error::BadArgument)?;
+        if self.is_local_eid(eid) {
+            return Ok(());
+        }
+        let slot = self
+            .local_eids
+            .iter_mut()
+            .find(|e| e.is_none())
+            .ok_or(Error::NoSpace)?;
+        *slot = Some(eid);
+        Ok(())
+    }
+
+    /// Remove a secondary local EID added with [add_local_eid()](Self::add_local_eid)
+    ///
+    /// Returns whether `eid` was a secondary local EID.
+    pub fn remove_local_eid(&mut self, eid: Eid) -> bool {
+        match self.local_eids.iter_mut().find(|e| **e == Some(eid)) {
+            Some(slot) => {
+                *slot = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Get all local EIDs, the own EID first
+    pub fn local_eids(&self) -> impl Iterator<Item = Eid> + '_ {
+        core::iter::once(self.stack.eid()).chain(self.local_eids.iter().flatten().copied())
+    }
+
+    /// Set the local EID messages sent with `handle` come from
+    ///
+    /// `None` sends from the own EID. A source set with [SendArgs::source()] takes precedence.
+    ///
+    /// Returns [BadArgument](Error::BadArgument) if `handle` is not bound or `eid` is not
+    /// a local EID.
+    pub fn set_source_eid(
+        &mut self,
+        handle: impl Into<Handle>,
+        eid: Option<Eid>,
+    ) -> RouterResult<()> {
+        let handle = handle.into();
+        if let Some(eid) = eid
+            && !self.is_local_eid(eid)
+        {
+            return Err(RouterError::new(Error::BadArgument)
+                .with_handle(handle)
+                .with_eid(eid));
+        }
+        let slot = match handle {
+            Handle::Listener(h) => self.tables.listener_mut(h.0).map(|l| &mut l.source_eid),
+            Handle::Request(h) => self.tables.request_mut(h.0).map(|r| &mut r.source_eid),
+        }
+        .ok_or_else(|| RouterError::new(Error::BadArgument).with_handle(handle))?;
+        *slot = eid;
+        Ok(())
+    }
+
+    /// Check whether `eid` is the own or a secondary local EID
+    fn is_local_eid(&self, eid: Eid) -> bool {
+        eid == self.stack.eid() || self.local_eids.contains(&Some(eid))
+    }
+
+    /// Map a requested source EID to the secondary EID packets are sent from
+    ///
+    /// The own EID and unset EIDs (null, broadcast) send from the own EID.
+    fn secondary_eid(&self, source: Option<Eid>) -> Result<Option<Eid>> {
+        match source {
+            None => Ok(None),
+            Some(eid) if eid == self.stack.eid() || eid.0 == 0 || eid.0 == 0xff => Ok(None),
+            Some(eid) if self.local_eids.contains(&Some(eid)) => Ok(Some(eid)),
+            Some(_) => Err(Error::BadArgument),
+        }
+    }
+
+    /// Set the _Eid_ for this endpoint
+    ///
+    /// A change is reported to [Hooks::eid_changed()].
+    pub fn set_eid(&mut self, eid: Eid) -> Result<()> {
+        let old = self.stack.eid();
+        self.stack.set_eid(eid.0)?;
+        if old != eid {
+            debug!("eid changed from {} to {}", old.0, eid.0);
+            self.cancel_notify();
+            self.hooks.eid_changed(old, eid);
+            self.events.push(RouterEvent::EidChanged { old, new: eid });
+        }
+        Ok(())
+    }
+
+    /// Set the MTU used for messages sent to `eid`
+    ///
+    /// Overrides the port MTU reported by [Sender::get_mtu()] for this destination.
+    /// The effective MTU is capped at the port MTU.
+    /// An existing entry for `eid` is replaced, including a learned one.
+    /// When the MTU table is full, the oldest learned entry makes room.
+    ///
+    /// Returns [BadArgument](Error::BadArgument) if `mtu` can't hold an MCTP header and payload,
+    /// [NoSpace](Error::NoSpace) when the MTU table is full of static entries.
+    pub fn set_mtu(&mut self, eid: Eid, mtu: usize) -> Result<()> {
+        if mtu <= header::HEADER_LEN {
+            return Err(Error::BadArgument);
+        }
+        let entry = MtuEntry {
+            eid,
+            mtu,
+            learned: false,
+            learned_millis: 0,
+        };
+        if let Some(existing) = self
+            .mtu_overrides
+            .iter_mut()
+            .flatten()
+            .find(|x| x.eid == eid)
+        {
+            *existing = entry;
+            return Ok(());
+        }
+        let slot = match self.mtu_overrides.iter().position(|x| x.is_none()) {
+            Some(free) => self.mtu_overrides.get_mut(free),
+            None => Self::oldest_learned_mtu(&mut self.mtu_overrides),
+        };
+        *slot.ok_or(Error::NoSpace)? = Some(entry);
+        Ok(())
+    }
+
+    /// Get the slot of the learned entry in `table` that was updated least recently
+    fn oldest_learned_mtu(table: &mut [Option<MtuEntry>]) -> Option<&mut Option<MtuEntry>> {
+        table
+            .iter_mut()
+            .filter(|x| x.is_some_and(|e| e.learned))
+            .min_by_key(|x| x.map(|e| e.learned_millis))
+    }
+
+    /// Remove the MTU override for `eid`
+    ///
+    /// Messages to `eid` will use the port MTU again.
+    /// Returns the previously configured or learned MTU, if any.
+    pub fn clear_mtu(&mut self, eid: Eid) -> Option<usize> {
+        self.mtu_overrides
+            .iter_mut()
+            .find(|x| x.is_some_and(|x| x.eid == eid))
+            .and_then(|x| x.take())
+            .map(|x| x.mtu)
+    }
+
+    /// Get the MTU used for messages sent to `eid`
+    ///
+    /// In order of precedence, this is
+    /// - the statically configured override (see [set_mtu()](Self::set_mtu)),
+    /// - the peer MTU reported by the binding (see [Sender::peer_mtu()]),
+    /// - the MTU learned by MTU discovery (see [set_mtu_discovery()](Self::set_mtu_discovery)),
+    /// - the port MTU.
+    ///
+    /// The result is always capped at the port MTU and the size of the [Sender::Packet] buffer.
+    pub fn mtu(&self, eid: Eid) -> usize {
+        let port_mtu = self.sender.get_mtu();
+        let entry = self.mtu_overrides.iter().flatten().find(|x| x.eid == eid);
+        let mtu = match entry {
+            Some(e) if !e.learned => Some(e.mtu),
+            _ => self.sender.peer_mtu(eid).or(entry.map(|e| e.mtu)),
+        };
+        mtu.map_or(port_mtu, |mtu| mtu.min(port_mtu))
+            .min(S::Packet::LEN)
+    }
+
+    /// Enable or disable passive MTU learning
+    ///
+    /// MCTP control messages don't carry a packet size, so when enabled the MTU of a peer is
+    /// learned from the size of the non-final packets it sends, which by specification equals its
+    /// transmission unit. Only packets addressed to a local EID count, once the hooks accepted
+    /// them, and only for messages a listener (including its [EidAcl]) or a request accepts.
+    /// No control messages are exchanged to find the MTU; bindings that learn it during their
+    /// own discovery report it through [Sender::peer_mtu()], which takes precedence.
+    /// Learned MTUs populate the MTU table (see [mtu()](Self::mtu)) but never replace static
+    /// entries. Once the table is down to its last free slot, which is left for
+    /// [set_mtu()](Self::set_mtu), the oldest learned entry is replaced.
+    /// Disabled by default.
+    pub fn set_mtu_discovery(&mut self, enable: bool) {
+        self.mtu_discovery = enable;
+    }
+
+    /// Enable or disable bridge forwarding
+    ///
+    /// When enabled, packets addressed to another EID are passed unmodified to
+    /// [Sender::send_packet()] with their destination EID, instead of being dropped.
+    /// Forwarded packets bypass reassembly and keep their tag and tag owner bit, so bridged
+    /// flows never enter the tag allocation of the local stack.
+    /// Packets with the EID of this endpoint as source are not forwarded, they would collide
+    /// with locally originated flows.
+    /// Disabled by default.
+    pub fn set_forwarding(&mut self, enable: bool) {
+        self.forwarding = enable;
+    }
+
+    /// Enable or disable refragmentation of forwarded messages
+    ///
+    /// Without refragmentation, forwarded packets larger than the MTU of the [Sender] are
+    /// dropped with [DroppedTooLarge](Disposition::DroppedTooLarge). When enabled, messages
+    /// whose first packet exceeds the [MTU toward the destination](Self::mtu) are re-cut into
+    /// packets of that MTU while they stream through, so bridges between ports with different
+    /// MTUs pass them on. Only the payload not filling an outbound packet yet is held back.
+    /// At most [REFRAGMENT_FLOWS] messages are refragmented at a time, the one idle for the
+    /// longest time is abandoned for a new one. Messages losing a packet are abandoned with
+    /// [DroppedReassemblyError](Disposition::DroppedReassemblyError).
+    /// Disabled by default, changing the setting abandons the messages in progress.
+    pub fn set_refragmentation(&mut self, enable: bool) {
+        self.refragmenter.set_enabled(enable);
+    }
+
+    /// Set the number of sequence numbers a continuation packet may arrive early
+    ///
+    /// The stack aborts the reassembly of a message on the first packet out of sequence.
+    /// With a reorder window, continuation packets up to `window` sequence numbers ahead
+    /// are held back and passed on once the packets before them have arrived,
+    /// tolerating transports or bridges that reorder packets slightly.
+    /// At most [MAX_REORDER_WINDOW] packets are held across all messages;
+    /// packets beyond that are passed on as usual.
+    /// Packets held for the message of a source and tag are discarded when a new message on
+    /// it starts. Changing the window discards all held packets.
+    /// 0 (the default) disables reordering.
+    ///
+    /// Returns [BadArgument](Error::BadArgument) if `window` exceeds [MAX_REORDER_WINDOW].
+    pub fn set_reorder_window(&mut self, window: u8) -> Result<()> {
+        if window > MAX_REORDER_WINDOW {
+            return Err(Error::BadArgument);
+        }
+        self.reorder = ReorderBuffer::new(window);
+        Ok(())
+    }
+
+    /// Set how out-of-spec inbound packets are treated, see [Validation]
+    ///
+    /// The violation counters are kept.
+    pub fn set_validation(&mut self, validation: Validation) {
+        self.validation = validation;
+    }
+
+    /// Get the number of inbound packets with `violation`, rejected or not
+    pub fn violations(&self, violation: Violation) -> usize {
+        self.violations.get(violation)
+    }
+
+    /// Get the traffic counters of all peers with statistics, see [PeerStats]
+    ///
+    /// At most [PEER_STATS_TABLE_SIZE] peers are tracked, the peer without traffic for the
+    /// longest time is evicted for a new one.
+    pub fn peer_stats(&self) -> impl Iterator<Item = PeerStats> + '_ {
+        self.peer_stats.iter()
+    }
+
+    /// Get the traffic counters of `eid`, `None` if it is not tracked
+    pub fn peer_stats_for(&self, eid: Eid) -> Option<PeerStats> {
+        self.peer_stats.get(eid)
+    }
+
+    /// Get the number of peers evicted from the [PeerStats] table to make room for others
+    pub fn peer_stats_evicted(&self) -> usize {
+        self.peer_stats.evicted
+    }
+
+    /// Clear the [PeerStats] of all peers
+    pub fn reset_peer_stats(&mut self) {
+        self.peer_stats.clear();
+    }
+
+    /// Get the counters of inbound header problems, see [HeaderStats]
+    pub fn header_stats(&self) -> HeaderStats {
+        self.headers.stats()
+    }
+
+    /// Get the source EID of the last inbound packet counted in the [HeaderStats]
+    ///
+    /// Points at the peer (or the binding in front of it) that misbehaves during bus bring-up.
+    pub fn header_offender(&self) -> Option<Eid> {
+        self.headers.offender()
+    }
+
+    /// Get the number of inbound packets sourced from the own EID, see [Hooks::eid_conflict()]
+    pub fn eid_conflicts(&self) -> usize {
+        self.eid_conflicts
+    }
+
+    /// Get the counters of the send path
+    pub fn tx_stats(&self) -> TxStats {
+        self.tx_stats
+    }
+
+    /// Write a human-readable summary of the router state to `out`
+    ///
+    /// Lists handles, routes, neighbors (MTU table entries and monitored peers), messages
+    /// being reassembled, requests waiting for a response and the counters, one item per
+    /// line. Nothing is allocated, so it can be called from debug consoles and crash handlers.
+    /// The format is meant for humans and may change.
+    pub fn dump_state(&self, out: &mut impl core::fmt::Write) -> core::fmt::Result {
+        let now_millis = self.clock.now_millis();
+        writeln!(
+            out,
+            "eid {} static {} now {}ms{}",
+            self.stack.eid().0,
+            self.static_eid.0,
+            now_millis,
+            if self.shut_down {
+                " shut down"
+            } else if self.quiesced {
+                " quiesced"
+            } else {
+                ""
+            }
+        )?;
+        for (i, slot) in self.tables.listeners().iter().enumerate() {
+            let Some(l) = slot.entry.as_ref() else {
+                continue;
+            };
+            match l.typ {
+                Some(typ) => write!(out, "listener {} type {}", i, typ.0)?,
+                None => write!(out, "listener {} catch-all", i)?,
+            }
+            for typ in l.group.iter().flatten() {
+                write!(out, "+{}", typ.0)?;
+            }
+            writeln!(out, " queued {} denied {}", l.queue.queued, l.denied)?;
+        }
+        for req in self.requests() {
+            write!(out, "request {:?} eid {}", req.handle, req.eid.0)?;
+            match req.tag {
+                Some(tag) => write!(out, " pending tag {}", tag.tag().0)?,
+                None => write!(out, " idle")?,
+            }
+            let queued = self.queued(req.handle).unwrap_or(0);
+            writeln!(out, " age {}ms queued {}", req.age_millis, queued)?;
+        }
+        for (route, hits) in self.route_hits() {
+            writeln!(
+                out,
+                "route {}-{} port {} addr {:#x} hits {}",
+                route.first.0, route.last.0, route.port, route.physical_addr, hits
+            )?;
+        }
+        for entry in self.mtu_overrides.iter().flatten() {
+            let learned = if entry.learned { " learned" } else { "" };
+            writeln!(out, "mtu {} {}{}", entry.eid.0, entry.mtu, learned)?;
+        }
+        for peer in self.monitor.peers.iter().flatten() {
+            writeln!(
+                out,
+                "peer {} {:?} failures {}",
+                peer.eid.0,
+                peer.state,
+                peer.failures()
+            )?;
+        }
+        for r in self.reassemblies() {
+            writeln!(
+                out,
+                "reassembly {} tag {} type {} bytes {} idle {}ms",
+                r.source.0,
+                r.tag.tag().0,
+                r.typ.0,
+                r.bytes,
+                r.idle_millis
+            )?;
+        }
+        let tx = self.tx_stats;
+        writeln!(
+            out,
+            "tx messages {} packets {} bytes {} busy retries {}",
+            tx.messages, tx.packets, tx.copied_bytes, tx.busy_retries
+        )?;
+        let forward = self.forward_stats;
+        writeln!(
+            out,
+            "forward unrouted {} dropped {} route misses {}",
+            forward.unrouted.forwarded,
+            forward.unrouted.dropped,
+            forward.route_misses()
+        )?;
+        for port in 0..FORWARD_STATS_PORTS as u8 {
+            if let Some(stats) = forward.port(port).filter(|s| *s != PortStats::default()) {
+                writeln!(
+                    out,
+                    "port {} forwarded {} dropped {}",
+                    port, stats.forwarded, stats.dropped
+                )?;
+            }
+        }
+        let headers = self.header_stats();
+        writeln!(
+            out,
+            "headers bad version {} bad som/eom {} unexpected seq {} unknown source {}",
+            headers.bad_version,
+            headers.bad_som_eom,
+            headers.unexpected_seq,
+            headers.unknown_source
+        )?;
+        write!(out, "violations")?;
+        for violation in Violation::ALL {
+            write!(out, " {:?} {}", violation, self.violations(violation))?;
+        }
+        writeln!(
+            out,
+            "\ndropped rate limited {} eid conflicts {} no context {}",
+            self.rate_limited(),
+            self.eid_conflicts,
+            self.reservations.exhausted
+        )
+    }
+
+    /// Enable or disable promiscuous mode
+    ///
+    /// When enabled, every inbound packet with a valid transport header is passed to
+    /// [Hooks::snoop()] before it is filtered, e.g. for bus analyzers or test fixtures.
+    /// Processing of the packet continues as usual afterwards.
+    /// Disabled by default.
+    pub fn set_promiscuous(&mut self, enable: bool) {
+        self.promiscuous = enable;
+    }
+
+    /// Leave control requests the router can't answer to the application
+    ///
+    /// By default, [control_response()](Self::control_response) answers unknown commands
+    /// with [CC_ERROR_UNSUPPORTED_CMD](control::CC_ERROR_UNSUPPORTED_CMD), and control
+    /// requests arriving while no listener is bound for
+    /// [MSG_TYPE_CONTROL](control::MSG_TYPE_CONTROL) are answered by the router itself with
+    /// [ControlAnswered](Disposition::ControlAnswered), so requesters don't have to wait for
+    /// a timeout. When enabled, unknown commands are left to the application and control
+    /// requests without a listener are dropped.
+    pub fn set_manual_control(&mut self, enable: bool) {
+        self.manual_control = enable;
+    }
+
+    /// Check whether the first packet `hdr` of a message of type `typ` may teach an MTU
+    ///
+    /// Only messages a handle would accept count: requests permitted by the [EidAcl] of the
+    /// listener for `typ`, and responses from the peer of a bound request.
+    fn accepts_mtu_from(&self, hdr: &header::Header, typ: MsgType) -> bool {
+        if hdr.tag.is_owner() {
+            let listeners = self.tables.listeners();
+            listener_index(listeners, typ)
+                .and_then(|i| listeners.get(i))
+                .and_then(|s| s.entry.as_ref())
+                .is_some_and(|l| l.acl.is_none_or(|acl| acl.permits(hdr.source)))
+        } else {
+            self.tables
+                .requests()
+                .iter()
+                .filter_map(|s| s.entry.as_ref())
+                .any(|r| r.eid == hdr.source)
+        }
+    }
+
+    /// Record a learned MTU for `eid`
+    ///
+    /// Static entries are kept. The last free slot is left for static entries, the oldest
+    /// learned entry is replaced instead, see [set_mtu_discovery()](Self::set_mtu_discovery).
+    fn learn_mtu(&mut self, eid: Eid, mtu: usize) {
+        let entry = MtuEntry {
+            eid,
+            mtu,
+            learned: true,
+            learned_millis: self.clock.now_millis(),
+        };
+        if let Some(existing) = self
+            .mtu_overrides
+            .iter_mut()
+            .flatten()
+            .find(|x| x.eid == eid)
+        {
+            if existing.learned {
+                *existing = entry;
+            }
+        } else {
+            let free = self.mtu_overrides.iter().filter(|x| x.is_none()).count();
+            let slot = if free > 1 {
+                self.mtu_overrides.iter_mut().find(|x| x.is_none())
+            } else {
+                Self::oldest_learned_mtu(&mut self.mtu_overrides)
+            };
+            if let Some(slot) = slot {
+                *slot = Some(entry);
+            }
+        }
+    }
+
+    /// Send a message
+    ///
+    /// When responding to a request received by a listener, `eid` and `tag` have to be set.
+    /// A request usually won't set an ` eid `.
+    /// When no `tag` is supplied for a request, a new one will be allocated.
+    pub fn send(
+        &mut self,
+        eid: Option<Eid>,
+        typ: MsgType,
+        tag: Option<Tag>,
+        ic: MsgIC,
+        handle: impl Into<Handle>,
+        buf: &[u8],
+    ) -> RouterResult<SendReport> {
+        self.send_vectored(eid, typ, tag, ic, handle, &[buf])
+    }
+
+    /// Send a message described by `args` with `handle`
+    ///
+    /// Requests are sent with a [RequestHandle], responses with the [ListenerHandle] the
+    /// request arrived on, see [SendArgs].
+    pub fn send_msg<K: SendKind>(
+        &mut self,
+        handle: K::Handle,
+        args: SendArgs<'_, K>,
+    ) -> RouterResult<SendReport> {
+        let bufs = match &args.payload {
+            Payload::Single(buf) => core::slice::from_ref(buf),
+            Payload::Vectored(bufs) => bufs,
+        };
+        let handle = handle.into();
+        if !self.is_bound(handle) {
+            return Err(RouterError::from(self.unbound_error())
+                .with_handle(handle)
+                .with_tag(args.tag));
+        }
+        let source = args
+            .source
+            .or(args.request_dest.filter(|eid| self.is_local_eid(*eid)));
+        self.send_bound(
+            source,
+            args.eid,
+            args.typ,
+            args.tag,
+            args.ic,
+            handle,
+            args.deadline,
+            bufs,
+        )
+    }
+
+    /// Respond to `request`, received by `listener`, with the payload `bufs`
+    ///
+    /// The response goes to the source of the request with its tag, message type and
+    /// integrity check flag. `request` is the metadata returned when receiving, or taken from
+    /// the message itself with `MessageInfo::from(&msg)`.
+    ///
+    /// Returns [BadArgument](Error::BadArgument) if `request` is a response itself.
+    pub fn respond(
+        &mut self,
+        listener: ListenerHandle,
+        request: &MessageInfo,
+        bufs: &[&[u8]],
+    ) -> RouterResult<SendReport> {
+        if !request.tag.is_owner() {
+            return Err(RouterError::from(Error::BadArgument)
+                .with_handle(listener.into())
+                .with_eid(request.source)
+                .with_tag(Some(request.tag)));
+        }
+        self.send_msg(
+            listener,
+            SendArgs::response_to(request).payload_vectored(bufs),
+        )
+    }
+
+    /// Respond to the request of `context` with the payload `bufs`, see [ReplyContext]
+    ///
+    /// Consumes the context, so a single response is sent per request. Fails like
+    /// [respond()](Self::respond), e.g. with [BadArgument](Error::BadArgument) if the
+    /// listener was unbound since.
+    pub fn reply(&mut self, context: ReplyContext, bufs: &[&[u8]]) -> RouterResult<SendReport> {
+        self.respond(context.listener(), context.request(), bufs)
+    }
+
+    /// Send a vectored message
+    ///
+    /// When responding to a request received by a listener, `eid` and `tag` have to be set.
+    /// A request usually won't set an `eid`.
+    /// When no `tag` is supplied for a request, a new one will be allocated.
+    ///
+    /// The `bufs` are read directly while fragmenting, packets are built one at a time
+    /// and passed to the [Sender].
+    /// No intermediate buffer holding the whole message is used (see [for_each_fragment()]).
+    ///
+    /// With `ic` set, a CRC-32C integrity check is appended to the message.
+    /// Such messages, and those of types with a [MessageIntegrity] handler, can be made up of
+    /// at most [MAX_IC_BUFS] buffers, more fail with [BadArgument](Error::BadArgument).
+    /// See [set_message_integrity()](Self::set_message_integrity) for messages with both.
+    ///
+    /// Returns the tag and the packets and bytes passed to the [Sender].
+    /// Errors carry the `handle`, destination EID and tag as context.
+    pub fn send_vectored(
+        &mut self,
+        eid: Option<Eid>,
+        typ: MsgType,
+        tag: Option<Tag>,
+        ic: MsgIC,
+        handle: impl Into<Handle>,
+        bufs: &[&[u8]],
+    ) -> RouterResult<SendReport> {
+        let handle = handle.into();
+        if !self.is_bound(handle) {
+            return Err(RouterError::from(self.unbound_error())
+                .with_handle(handle)
+                .with_tag(tag));
+        }
+        self.send_bound(None, eid, typ, tag, ic, handle, None, bufs)
+    }
+
+    /// Send a vectored message for the bound `handle`, see [send_vectored()](Self::send_vectored)
+    ///
+    /// The message is sent from the local EID `source`, or the one set for the handle.
+    /// It is dropped once `deadline` passed, see [SendArgs::deadline()].
+    #[allow(clippy::too_many_arguments)]
+    fn send_bound(
+        &mut self,
+        source: Option<Eid>,
+        eid: Option<Eid>,
+        typ: MsgType,
+        tag: Option<Tag>,
+        ic: MsgIC,
+        handle: Handle,
+        deadline: Option<u64>,
+        bufs: &[&[u8]],
+    ) -> RouterResult<SendReport> {
+        let cookie = handle.cookie();
+        let context = |e: Error| RouterError::from(e).with_handle(handle).with_tag(tag);
+        let req_eid = match handle {
+            Handle::Request(req) => self.lookup_request(req).map(|r| r.eid),
+            Handle::Listener(_) => None,
+        };
+        if self.quiesced {
+            return Err(context(Error::TxFailure));
+        }
+        if deadline.is_some_and(|d| self.clock.now_millis() >= d) {
+            debug!("dropped message for {}, deadline passed", cookie.0);
+            self.deadline_passed(handle);
+            return Err(context(Error::TimedOut));
+        }
+        let Some(eid) = eid.or(req_eid) else {
+            return Err(context(Error::InvalidInput));
+        };
+        let source = source.or(match handle {
+            Handle::Listener(h) => self.tables.listener(h.0).and_then(|l| l.source_eid),
+            Handle::Request(h) => self.tables.request(h.0).and_then(|r| r.source_eid),
+        });
+        let source = self
+            .secondary_eid(source)
+            .map_err(|e| context(e).with_eid(eid))?;
+        let mut mic_parts: [&[u8]; MAX_IC_BUFS + 1] = [&[]; MAX_IC_BUFS + 1];
+        let mut mic = [0; MAX_MIC_LEN];
+        let bufs = match self.mics.get(typ, ic) {
+            Some(handler) => mic::append(handler, typ, bufs, &mut mic, &mut mic_parts)
+                .map_err(|e| context(e).with_eid(eid))?,
+            None => bufs,
+        };
+        let mut parts: [&[u8]; MAX_IC_BUFS + 1] = [&[]; MAX_IC_BUFS + 1];
+        let crc;
+        let bufs = if ic.0 && !self.ic_offloaded(eid, ic) {
+            crc = integrity_check(typ, bufs);
+            append_check(bufs, &crc, &mut parts).map_err(|e| context(e).with_eid(eid))?
+        } else {
+            bufs
+        };
+        let payload_len = bufs.iter().map(|b| b.len()).sum();
+        let tag = match (tag, handle) {
+            (None, Handle::Request(req)) => self
+                .request_tag(req, eid)
+                .map_err(|e| context(e).with_eid(eid))?,
+            _ => tag,
+        };
+        let frag = self
+            .start_message(eid, typ, tag, ic, Some(cookie), source, Some(payload_len))
+            .map_err(|e| {
+                warn!("failed to start message to {}", eid.0);
+                context(e).with_eid(eid)
+            })?;
+        let frag_tag = frag.tag();
+        let now_millis = self.clock.now_millis();
+        let _span = lifecycle_span!(
+            "mctp_send",
+            eid = eid.0,
+            typ = typ.0,
+            tag = frag_tag.tag().0,
+            owner = frag_tag.is_owner(),
+        );
+        let reclaim = self.tag_reclaim(Some(cookie));
+        let mut released = None;
+        if let Handle::Request(req) = handle
+            && frag_tag.is_owner()
+            && let Some(req) = self.lookup_request_mut(req)
+        {
+            // Remembered to cancel the flow when the request is unbound or times out
+            req.last_tag = Some(frag_tag);
+            if let Some(reclaim) = reclaim {
+                // A tag still held from the previous message is released early
+                released = req
+                    .reclaim_at
+                    .replace((frag_tag, now_millis.saturating_add(reclaim)))
+                    .map(|(tag, _)| tag)
+                    .filter(|tag| *tag != frag_tag);
+            }
+            req.typ = Some(typ);
+            req.sent_millis = now_millis;
+            req.tx_started = Some(now_millis);
+            req.tx_stalled = false;
+            lifecycle!(
+                "request tag allocated",
+                eid = eid.0,
+                tag = frag_tag.tag().0,
+                handle = cookie.0,
+            );
+        }
+        if let Some(tag) = released {
+            self.stack.cancel_flow(eid, tag.tag());
+        }
+        trace!(
+            "sending type {} to {} with tag {}",
+            typ.0,
+            eid.0,
+            frag_tag.tag().0
+        );
+
+        let report = self
+            .transmit(
+                eid,
+                frag,
+                bufs,
+                now_millis,
+                source,
+                Some((cookie, deadline)),
+            )
+            .map_err(|e| {
+                if matches!(e, Error::TimedOut)
+                    && deadline.is_some_and(|d| self.clock.now_millis() >= d)
+                {
+                    self.deadline_passed(handle);
+                } else {
+                    Self::latch_error(&mut self.tables, handle, HandleError::SendFailed);
+                }
+                context(e).with_eid(eid).with_tag(Some(frag_tag))
+            })?;
+        if let Handle::Request(req) = handle
+            && let Some(req) = self.lookup_request_mut(req)
+        {
+            req.tx_started = None;
+        }
+        Ok(report)
+    }
+
+    /// Prepare sending a message with a payload of `len` bytes to `eid`
+    ///
+    /// Responses fitting into a single packet are built directly, without setting up a
+    /// fragmenter in the stack. Other messages, and those of a length not known yet, are
+    /// fragmented by the stack. This includes single packet requests such as heartbeats:
+    /// the stack allocates their tag and resolves the response to the request by it, so
+    /// they can't bypass it.
+    #[allow(clippy::too_many_arguments)]
+    fn start_message(
+        &mut self,
+        eid: Eid,
+        typ: MsgType,
+        tag: Option<Tag>,
+        ic: MsgIC,
+        cookie: Option<AppCookie>,
+        source: Option<Eid>,
+        len: Option<usize>,
+    ) -> Result<Packets> {
+        // Room for the integrity check appended by the binding
+        let reserved = if self.ic_offloaded(eid, ic) {
+            IC_LEN
+        } else {
+            0
+        };
+        let mtu = self.mtu(eid).saturating_sub(reserved);
+        if let Some(tag @ Tag::Unowned(_)) = tag
+            && len.is_some_and(|len| header::HEADER_LEN + 1 + len <= mtu)
+        {
+            let hdr = header::Header {
+                dest: eid,
+                source: source.unwrap_or(self.stack.eid()),
+                som: true,
+                eom: true,
+                seq: 0,
+                tag,
+            };
+            return Ok(Packets::Single(hdr, typ, ic));
+        }
+        let tag_expires = self.tag_reclaim(cookie).is_none();
+        self.stack
+            .start_send(eid, typ, tag, tag_expires, ic, Some(mtu), cookie)
+            .map(Packets::Fragments)
+    }
+
+    /// Check whether the sender computes the integrity check of a message to `eid`
+    fn ic_offloaded(&self, eid: Eid, ic: MsgIC) -> bool {
+        ic.0 && self
+            .sender
+            .offloads_integrity_check(eid, self.hop(eid).map(|h| h.route).as_ref())
+    }
+
+    /// Get the tag reclaim time of the request with `cookie`
+    ///
+    /// Returns `None` when the tag expiry is left to the stack.
+    fn tag_reclaim(&self, cookie: Option<AppCookie>) -> Option<u64> {
+        let req = self.tables.request(cookie?)?;
+        req.tag_reclaim_millis.or(self.tag_reclaim_millis)
+    }
+
+    /// Fragment `bufs` with `frag` and pass the packets to the sender, see [Transmitter]
+    fn transmit(
+        &mut self,
+        eid: Eid,
+        frag: Packets,
+        bufs: &[&[u8]],
+        now_millis: u64,
+        source: Option<Eid>,
+        owner: Option<(AppCookie, Option<u64>)>,
+    ) -> Result<SendReport> {
+        let (_, mut tx) = self.split_transmitter();
+        tx.transmit(eid, frag, bufs, now_millis, source, owner)
+    }
+
+    /// Borrow the stack apart from the parts of the router passing packets to the sender
+    ///
+    /// Lets messages be sent while a received message is borrowed from the stack.
+    fn split_transmitter(&mut self) -> (&mut Stack, Transmitter<'_, S, H, C>) {
+        let tx = Transmitter {
+            sender: &mut self.sender,
+            hooks: &mut self.hooks,
+            clock: &self.clock,
+            routes: &self.routes,
+            failover: &mut self.failover,
+            links: &self.links,
+            padding: &self.padding,
+            throttles: &mut self.throttles,
+            tx_stats: &mut self.tx_stats,
+            peer_stats: &mut self.peer_stats,
+            busy_retry: self.busy_retry,
+            tx_timeout_millis: self.tx_timeout_millis,
+        };
+        (&mut self.stack, tx)
+    }
+
+    /// Report the message of `handle` dropped as its deadline passed
+    fn deadline_passed(&mut self, handle: Handle) {
+        self.tx_stats.expired = self.tx_stats.expired.wrapping_add(1);
+        Self::latch_error(
+            &mut self.tables,
+            handle,
+            HandleError::Expired(ExpiryReason::DeadlinePassed),
+        );
+        self.wakers.wake(handle);
+        self.hooks
+            .message_expired(handle, ExpiryReason::DeadlinePassed);
+        self.events
+            .push(RouterEvent::Expired(handle, ExpiryReason::DeadlinePassed));
+    }
+
+    /// Pass packets held back by throttles to the sender as their limits allow
+    ///
+    /// Held packets past the deadline of their message are dropped first.
+    fn release_held(&mut self, now_millis: u64) {
+        while let Some(cookie) = self.throttles.expire(now_millis) {
+            let handle = match self.tables.listener(cookie) {
+                Some(_) => Handle::from(ListenerHandle(cookie)),
+                None => Handle::from(RequestHandle(cookie)),
+            };
+            debug!("dropped held packets of {}, deadline passed", cookie.0);
+            self.deadline_passed(handle);
+        }
+        if self.quiesced {
+            return;
+        }
+        let (sender, hooks) = (&mut self.sender, &mut self.hooks);
+        let (routes, failover, links) = (&self.routes, &self.failover, &self.links);
+        let port_padding = &self.padding;
+        let max = self.work_budget.packets.unwrap_or(usize::MAX);
+        let fair = self.scheduler.is_fair();
+        let released = self.throttles.release(now_millis, max, fair, |eid, pkt| {
+            hooks.capture(Direction::Outbound, now_millis, pkt);
+            let hop = routes::select(routes, failover, links, eid, now_millis);
+            let route = hop.map(|h| h.route);
+            let padding = port_padding.for_route(route.as_ref());
+            send_packet(sender, eid, route.as_ref(), padding, pkt).map_err(|e| {
+                let busy = sender.is_receiver_busy(&e);
+                if !busy {
+                    warn!("dropped held packet to {}, send failed", eid.0);
+                }
+                busy
+            })
+        });
+        if released > 0 {
+            trace!("released {} held packets", released);
+        }
+    }
+
+    /// Get the time the final fragment of the next message for `handle` arrived
+    ///
+    /// The message [recv()](Self::recv) and the other receive functions return next, for
+    /// latency measurements and staleness checks. Messages received into a
+    /// [MessageInfo] carry the time in [received_millis](MessageInfo::received_millis).
+    /// Returns `None` when no message is available.
+    pub fn received_millis(&self, handle: impl Into<Handle>) -> Option<u64> {
+        let handle = handle.into();
+        if !self.is_bound(handle) {
+            return None;
+        }
+        if let Handle::Listener(listener) = handle
+            && let Some(info) = self.reassembly_buffers.complete(listener)
+        {
+            return info.received_millis;
+        }
+        self.retained.oldest(handle.cookie())
+    }
+
+    /// Receive a message for a listener or request [`Handle`]
+    ///
+    /// Returns `None` when no message is available for the listener/request,
+    /// or the handle is no longer bound.
+    ///
+    /// The message can be retained and received at a later point again (see [MctpMessage::retain()]).
+    /// Use [message_body()] to strip the integrity check of messages with the IC bit set.
+    pub fn recv(&mut self, handle: impl Into<Handle>) -> Option<mctp_estack::MctpMessage<'_>> {
+        let handle = handle.into();
+        if !self.is_bound(handle) {
+            return None;
+        }
+        self.take_deferred(handle)
+    }
+
+    /// Register a further consumer of the messages delivered to `listener`
+    ///
+    /// Each message delivered to the listener from now on is also handed out by
+    /// [recv_shared()](Self::recv_shared) to every consumer, e.g. a logger next to the
+    /// protocol handler owning the listener. The readers borrow the same reassembly buffer of
+    /// the stack, which is released once the listener and all consumers received the
+    /// message, so a slow consumer holds buffers other messages could use.
+    /// Messages of listeners with a [reassembly buffer](Self::set_reassembly_buffer) are not
+    /// shared.
+    ///
+    /// Returns [BadArgument](Error::BadArgument) if `listener` is not bound,
+    /// [NoSpace](Error::NoSpace) if [CONSUMER_TABLE_SIZE] consumers are registered.
+    pub fn add_consumer(&mut self, listener: ListenerHandle) -> RouterResult<ConsumerHandle> {
+        let context = |e: Error| RouterError::new(e).with_handle(listener.into());
+        if self.tables.listener(listener.0).is_none() {
+            return Err(context(Error::BadArgument));
+        }
+        self.consumers
+            .add(listener)
+            .ok_or_else(|| context(Error::NoSpace))
+    }
+
+    /// Remove `consumer`, releasing the messages held only for it
+    ///
+    /// Consumers of a listener are removed when it is unbound.
+    pub fn remove_consumer(&mut self, consumer: ConsumerHandle) -> Result<()> {
+        if !self.consumers.is_registered(consumer) {
+            return Err(Error::BadArgument);
+        }
+        for (source, tag) in self.consumers.remove(consumer) {
+            let _ = self.stack.get_deferred(source, tag);
+        }
+        Ok(())
+    }
+
+    /// Receive the oldest message held for `consumer`
+    ///
+    /// Messages are handed out in the order they were delivered to the listener, whether or
+    /// not the listener received them already. Like [recv()](Self::recv), the message has to
+    /// be dropped before the router is used again.
+    /// Returns `None` when no message is held for the consumer or it is not registered.
+    pub fn recv_shared(&mut self, consumer: ConsumerHandle) -> Option<MctpMessage<'_>> {
+        if !self.consumers.is_registered(consumer) {
+            return None;
+        }
+        let (source, tag, held) = loop {
+            let (source, tag, held) = self.consumers.next(consumer)?;
+            // Check that the stack still has the message, without releasing it
+            if let Some(mut msg) = self.stack.get_deferred(source, tag) {
+                msg.retain();
+                break (source, tag, held);
+            }
+            self.consumers.missed = self.consumers.missed.wrapping_add(1);
+        };
+        let mut msg = self.stack.get_deferred(source, tag)?;
+        if held {
+            msg.retain();
+        }
+        Some(msg)
+    }
+
+    /// Get the number of messages consumers missed
+    ///
+    /// Messages are missed when more messages are shared than the stack can retain, the
+    /// listener's queue drops them, or they expire before all consumers received them.
+    pub fn consumers_missed(&self) -> usize {
+        self.consumers.missed
+    }
+
+    /// Poll for a message for a listener or request [`Handle`], receiving it into `buf`
+    ///
+    /// Like [recv_into()](Self::recv_into), the payload is stored in `buf[..info.len]`.
+    /// When no message is available, the waker of `cx` is stored and woken once a message is
+    /// delivered to the handle, a message or request of the handle expires (see
+    /// [Hooks::message_expired()]) or the handle is reset or unbound.
+    /// Works with any executor, `async` receivers are built on it with
+    /// [core::future::poll_fn()], see e.g. [SharedListener::recv()](shared::SharedListener::recv).
+    /// At most [WAKER_TABLE_SIZE] tasks are remembered, further tasks are woken right away.
+    ///
+    /// A message is only taken from the handle when it is returned, so a future that stops
+    /// polling (e.g. the losing branch of a `select!` or a timeout) never loses one.
+    /// A message larger than `buf` stays queued and is reported as
+    /// [NoSpace](Error::NoSpace). Such futures should release the stored waker with
+    /// [cancel_poll_recv()](Self::cancel_poll_recv) when dropped, the receivers of the
+    /// [shared] module do.
+    pub fn poll_recv(
+        &mut self,
+        handle: impl Into<Handle>,
+        buf: &mut [u8],
+        cx: &mut Context<'_>,
+    ) -> Poll<RouterResult<MessageInfo>> {
+        let handle = handle.into();
+        if !self.is_bound(handle) {
+            return Poll::Ready(Err(
+                RouterError::from(self.unbound_error()).with_handle(handle)
+            ));
+        }
+        match self.recv_fitting(handle, buf) {
+            Ok(Some(info)) => Poll::Ready(Ok(info)),
+            Ok(None) => {
+                self.wakers.register(handle, Interest::Recv, cx.waker());
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+
+    /// Forget the waker stored by [poll_recv()](Self::poll_recv) for `handle`
+    ///
+    /// Only removes `waker` itself, a waker another task stored for the handle since is kept.
+    pub fn cancel_poll_recv(&mut self, handle: impl Into<Handle>, waker: &Waker) {
+        self.wakers.cancel(handle.into(), Interest::Recv, waker);
+    }
+
+    /// Forget the waker stored by [poll_send_complete()](Self::poll_send_complete) for
+    /// `handle`
+    ///
+    /// Only removes `waker` itself, a waker another task stored for the handle since is kept.
+    pub fn cancel_poll_send_complete(&mut self, handle: RequestHandle, waker: &Waker) {
+        self.wakers
+            .cancel(handle.into(), Interest::SendComplete, waker);
+    }
+
+    /// Receive a message for the bound `handle` into `buf` if it fits
+    ///
+    /// A message larger than `buf` is put back and reported as [NoSpace](Error::NoSpace).
+    fn recv_fitting(
+        &mut self,
+        handle: Handle,
+        buf: &mut [u8],
+    ) -> RouterResult<Option<MessageInfo>> {
+        let too_large = |info: &MessageInfo| {
+            RouterError::from(Error::NoSpace)
+                .with_handle(handle)
+                .with_eid(info.source)
+                .with_tag(Some(info.tag))
+        };
+        if let Handle::Listener(listener) = handle
+            && let Some(info) = self.reassembly_buffers.complete(listener)
+        {
+            if info.len > buf.len() {
+                return Err(too_large(&info));
+            }
+            let mut sink = buf;
+            return self.recv_bound_into(handle, &mut sink);
+        }
+        let since_millis = self.retained.oldest(handle.cookie());
+        let Some(mut msg) = Self::take_deferred_from(
+            &mut self.tables,
+            &mut self.stack,
+            &mut self.retained,
+            &mut self.consumers,
+            &mut self.scheduler,
+            handle,
+        ) else {
+            return Ok(None);
+        };
+        let info = MessageInfo {
+            received_millis: since_millis,
+            ..MessageInfo::from(&msg)
+        };
+        let body = message_body(&msg);
+        let Some(dst) = buf.get_mut(..body.len()) else {
+            msg.set_cookie(Some(handle.cookie()));
+            msg.retain();
+            if let Handle::Listener(listener) = handle {
+                self.consumers.requeued(listener, info.source, info.tag);
+            }
+            let queue = match handle {
+                Handle::Listener(h) => self.tables.listener_mut(h.0).map(|l| &mut l.queue),
+                Handle::Request(h) => self.tables.request_mut(h.0).map(|r| &mut r.queue),
+            };
+            if let Some(queue) = queue {
+                queue.requeue();
+            }
+            let now_millis = self.clock.now_millis();
+            self.retained
+                .push(handle.cookie(), since_millis.unwrap_or(now_millis));
+            return Err(too_large(&info));
+        };
+        dst.copy_from_slice(body);
+        Ok(Some(info))
+    }
+
+    /// Poll for the completion of the last message sent on the request `handle`
+    ///
+    /// Messages are passed to the [Sender] while sending, so this is ready right away unless
+    /// sending the last message failed part way. In that case the waker of `cx` is stored
+    /// until [update()](Self::update) abandons the message after the
+    /// [transmit timeout](RouterConfig::tx_timeout_millis), which completes with
+    /// [TimedOut](Error::TimedOut), or the request is reset or unbound.
+    /// Returns [BadArgument](Error::BadArgument) if `handle` is not bound.
+    pub fn poll_send_complete(
+        &mut self,
+        handle: RequestHandle,
+        cx: &mut Context<'_>,
+    ) -> Poll<RouterResult<()>> {
+        let context = |e: Error| RouterError::from(e).with_handle(handle.into());
+        match self.lookup_request(handle) {
+            None => Poll::Ready(Err(context(Error::BadArgument))),
+            Some(req) if req.tx_stalled => Poll::Ready(Err(context(Error::TimedOut))),
+            Some(req) if req.tx_started.is_some() => {
+                self.wakers
+                    .register(handle.into(), Interest::SendComplete, cx.waker());
+                Poll::Pending
+            }
+            Some(_) => Poll::Ready(Ok(())),
+        }
+    }
+
+    /// Receive a message for a listener or request [`Handle`] into `sink`
+    ///
+    /// The payload is written to `sink` straight from the reassembly buffer of the stack,
+    /// so it never has to be copied to a contiguous application buffer
+    /// (e.g. when streaming a firmware image to flash).
+    /// Also receives messages reassembled into the buffer of a listener, see
+    /// [set_reassembly_buffer()](Self::set_reassembly_buffer).
+    ///
+    /// Returns `Ok(None)` when no message is available for the listener/request,
+    /// [BadArgument](Error::BadArgument) when the handle is no longer bound.
+    /// The message is consumed even if writing to `sink` fails,
+    /// which is reported as [RxFailure](Error::RxFailure).
+    pub fn recv_into<W: embedded_io::Write>(
+        &mut self,
+        handle: impl Into<Handle>,
+        sink: &mut W,
+    ) -> RouterResult<Option<MessageInfo>> {
+        let handle = handle.into();
+        if !self.is_bound(handle) {
+            return Err(RouterError::from(self.unbound_error()).with_handle(handle));
+        }
+        self.recv_bound_into(handle, sink)
+    }
+
+    /// Receive a message for the bound `handle` into `sink`, see [recv_into()](Self::recv_into)
+    fn recv_bound_into<W: embedded_io::Write>(
+        &mut self,
+        handle: Handle,
+        sink: &mut W,
+    ) -> RouterResult<Option<MessageInfo>> {
+        if let Handle::Listener(listener) = handle
+            && let Some((info, written)) = self
+                .reassembly_buffers
+                .receive(listener, |info, body| (*info, sink.write_all(body)))
+        {
+            return written.map(|_| Some(info)).map_err(|_| {
+                RouterError::from(Error::RxFailure)
+                    .with_handle(handle)
+                    .with_eid(info.source)
+                    .with_tag(Some(info.tag))
+            });
+        }
+        let received_millis = self.retained.oldest(handle.cookie());
+        let Some(msg) = self.take_deferred(handle) else {
+            return Ok(None);
+        };
+        let info = MessageInfo {
+            received_millis,
+            ..MessageInfo::from(&msg)
+        };
+        sink.write_all(message_body(&msg)).map_err(|_| {
+            RouterError::from(Error::RxFailure)
+                .with_handle(handle)
+                .with_eid(info.source)
+                .with_tag(Some(info.tag))
+        })?;
+        Ok(Some(info))
+    }
+
+    /// Receive a message for a listener or request [`Handle`] into a buffer of `pool`
+    ///
+    /// The returned message owns its buffer, it doesn't borrow the router and can be passed
+    /// on to other code (e.g. another task) without copying.
+    /// Integrity checks of messages with the IC bit set are stripped.
+    ///
+    /// Returns `Ok(None)` when no message is available for the listener/request,
+    /// [BadArgument](Error::BadArgument) when the handle is no longer bound.
+    /// Returns [NoSpace](Error::NoSpace) without consuming a message when all buffers of
+    /// `pool` are in use. A message larger than the buffers is consumed and reported as
+    /// [NoSpace](Error::NoSpace).
+    pub fn recv_pooled<'p, const N: usize, const SIZE: usize>(
+        &mut self,
+        handle: impl Into<Handle>,
+        pool: &'p MessagePool<N, SIZE>,
+    ) -> RouterResult<Option<PooledMessage<'p, SIZE>>> {
+        let handle = handle.into();
+        if !self.is_bound(handle) {
+            return Err(RouterError::from(self.unbound_error()).with_handle(handle));
+        }
+        let mut buf = pool
+            .acquire()
+            .ok_or_else(|| RouterError::from(Error::NoSpace).with_handle(handle))?;
+        let received_millis = self.retained.oldest(handle.cookie());
+        let Some(msg) = self.take_deferred(handle) else {
+            return Ok(None);
+        };
+        let info = MessageInfo {
+            received_millis,
+            ..MessageInfo::from(&msg)
+        };
+        let body = message_body(&msg);
+        buf.get_mut(..body.len())
+            .ok_or_else(|| {
+                RouterError::from(Error::NoSpace)
+                    .with_handle(handle)
+                    .with_eid(info.source)
+                    .with_tag(Some(info.tag))
+            })?
+            .copy_from_slice(body);
+        Ok(Some(PooledMessage::new(info, buf)))
+    }
+
+    /// Receive all messages currently queued for a listener or request [`Handle`]
+    ///
+    /// Passes the messages to `f` in the order they were delivered, each message is consumed
+    /// after `f` returns. Useful after a processing stall, when several messages are waiting.
+    /// Use [message_body()] to strip the integrity check of messages with the IC bit set.
+    ///
+    /// Returns the number of messages received,
+    /// [BadArgument](Error::BadArgument) when the handle is no longer bound.
+    pub fn recv_all(
+        &mut self,
+        handle: impl Into<Handle>,
+        mut f: impl FnMut(&MctpMessage<'_>),
+    ) -> RouterResult<usize> {
+        let handle = handle.into();
+        if !self.is_bound(handle) {
+            return Err(RouterError::from(self.unbound_error()).with_handle(handle));
+        }
+        let mut count = 0;
+        while let Some(msg) = self.take_deferred(handle) {
+            f(&msg);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Receive a message for a listener or request [`Handle`] and pass it to `f`
+    ///
+    /// Along with the message, `f` gets a [Responder] to send the response while the message
+    /// is still borrowed, without copying it out first.
+    /// The message is consumed after `f` returns.
+    /// Use [message_body()] to strip the integrity check of messages with the IC bit set.
+    ///
+    /// Returns the result of `f`, `Ok(None)` when no message is available for the
+    /// listener/request, [BadArgument](Error::BadArgument) when the handle is no longer bound.
+    pub fn recv_with<R>(
+        &mut self,
+        handle: impl Into<Handle>,
+        f: impl FnOnce(&MctpMessage<'_>, &mut Responder<'_, S, H, C>) -> R,
+    ) -> RouterResult<Option<R>> {
+        let handle = handle.into();
+        let context = |e: Error| RouterError::from(e).with_handle(handle);
+        if !self.is_bound(handle) {
+            return Err(context(self.unbound_error()));
+        }
+        let Some(mut msg) = self.take_deferred(handle) else {
+            return Ok(None);
+        };
+        let (source, dest, tag, typ, ic) = (msg.source, msg.dest, msg.tag, msg.typ, msg.ic);
+        // Set the message aside while the stack prepares the response,
+        // it is handed out again first as the oldest retained message.
+        msg.set_cookie(Some(handle.cookie()));
+        msg.retain();
+        drop(msg);
+        let listener = match handle {
+            Handle::Listener(listener) => Some(listener),
+            Handle::Request(_) => None,
+        };
+        if let Some(listener) = listener {
+            self.consumers.requeued(listener, source, tag);
+        }
+        let response_tag = Tag::Unowned(tag.tag());
+        let prepared = if self.quiesced {
+            Err(Error::TxFailure)
+        } else if !tag.is_owner() {
+            // Responses are not answered
+            Err(Error::BadArgument)
+        } else {
+            // Answered from the local EID the request was addressed to, see respond()
+            let local = Some(dest).filter(|eid| self.is_local_eid(*eid));
+            self.secondary_eid(local).and_then(|local| {
+                // The length of the response is not known yet, so it is always fragmented
+                self.start_message(
+                    source,
+                    typ,
+                    Some(response_tag),
+                    ic,
+                    Some(handle.cookie()),
+                    local,
+                    None,
+                )
+                .map(|packets| (packets, local))
+            })
+        };
+        let shared = listener.is_some_and(|l| self.consumers.received(l, source, tag));
+        let ic_offloaded = self.ic_offloaded(source, ic);
+        let mic = self.mics.get(typ, ic);
+        let (stack, tx) = self.split_transmitter();
+        let mut msg = stack
+            .get_deferred_bycookie(&[handle.cookie()])
+            .ok_or_else(|| context(Error::InternalError))?;
+        if shared {
+            msg.retain();
+            msg.set_cookie(Some(SHARED_COOKIE));
+        }
+        let mut responder = Responder {
+            prepared,
+            handle,
+            dest: source,
+            tag: response_tag,
+            typ,
+            ic,
+            ic_offloaded,
+            mic,
+            failed: false,
+            tx,
+        };
+        let result = f(&msg, &mut responder);
+        let failed = responder.failed;
+        drop(msg);
+        if failed {
+            Self::latch_error(&mut self.tables, handle, HandleError::SendFailed);
+        }
+        Ok(Some(result))
+    }
+
+    /// Open the secured message session `session_id` with `eid`
+    ///
+    /// Called by the application once the SPDM session is established.
+    /// Returns [AddrInUse](Error::AddrInUse) if the session is already open,
+    /// [NoSpace](Error::NoSpace) if [SESSION_TABLE_SIZE](secured::SESSION_TABLE_SIZE)
+    /// sessions are open.
+    pub fn open_session(&mut self, eid: Eid, session_id: u32) -> Result<()> {
+        self.sessions.open(eid, session_id)
+    }
+
+    /// Close the secured message session `session_id` with `eid`
+    ///
+    /// Returns `false` if the session was not open.
+    pub fn close_session(&mut self, eid: Eid, session_id: u32) -> bool {
+        self.sessions.close(eid, session_id)
+    }
+
+    /// Send an application message over the secured session `session_id`
+    ///
+    /// The message is encrypted with [Hooks::encrypt()] into `scratch` and sent as a
+    /// [secured message](secured::MSG_TYPE_SECURED), the other arguments are the same
+    /// as for [send_vectored()](Self::send_vectored).
+    /// Fails with [BadArgument](Error::BadArgument) if the session is not open.
+    #[allow(clippy::too_many_arguments)]
+    pub fn send_secured(
+        &mut self,
+        eid: Option<Eid>,
+        session_id: u32,
+        typ: MsgType,
+        tag: Option<Tag>,
+        handle: impl Into<Handle>,
+        bufs: &[&[u8]],
+        scratch: &mut [u8],
+    ) -> RouterResult<SendReport> {
+        let handle = handle.into();
+        let context = |e: Error| RouterError::from(e).with_handle(handle).with_tag(tag);
+        let req_eid = match handle {
+            Handle::Request(req) => self.lookup_request(req).map(|r| r.eid),
+            Handle::Listener(_) => None,
+        };
+        let Some(eid) = eid.or(req_eid) else {
+            return Err(context(Error::InvalidInput));
+        };
+        if !self.sessions.contains(eid, session_id) {
+            return Err(context(Error::BadArgument).with_eid(eid));
+        }
+        let (id, out) = scratch
+            .split_first_chunk_mut::<{ secured::SESSION_ID_LEN }>()
+            .ok_or_else(|| context(Error::NoSpace).with_eid(eid))?;
+        *id = session_id.to_le_bytes();
+        let len = self
+            .hooks
+            .encrypt(eid, session_id, typ, bufs, out)
+            .map_err(|e| context(e).with_eid(eid))?;
+        let msg = scratch
+            .get(..secured::SESSION_ID_LEN + len)
+            .ok_or_else(|| context(Error::NoSpace).with_eid(eid))?;
+        self.send_vectored(
+            Some(eid),
+            secured::MSG_TYPE_SECURED,
+            tag,
+            MsgIC(false),
+            handle,
+            &[msg],
+        )
+    }
+
+    /// Receive a secured message for a listener or request [`Handle`] and decrypt it
+    ///
+    /// The application message is decrypted with [Hooks::decrypt()],
+    /// its payload is stored in `out[..info.len]`.
+    /// Returns `Ok(None)` when no message is available,
+    /// [BadArgument](Error::BadArgument) when the handle is no longer bound.
+    /// Messages that are not secured messages are consumed and fail with
+    /// [InvalidInput](Error::InvalidInput).
+    pub fn recv_secured(
+        &mut self,
+        handle: impl Into<Handle>,
+        out: &mut [u8],
+    ) -> RouterResult<Option<SecuredInfo>> {
+        let handle = handle.into();
+        if !self.is_bound(handle) {
+            return Err(RouterError::from(self.unbound_error()).with_handle(handle));
+        }
+        let Some(msg) = Self::take_deferred_from(
+            &mut self.tables,
+            &mut self.stack,
+            &mut self.retained,
+            &mut self.consumers,
+            &mut self.scheduler,
+            handle,
+        ) else {
+            return Ok(None);
+        };
+        let context = |e: Error| {
+            RouterError::from(e)
+                .with_handle(handle)
+                .with_eid(msg.source)
+                .with_tag(Some(msg.tag))
+        };
+        let Some((session_id, data)) =
+            secured::split(msg.payload).filter(|_| msg.typ == secured::MSG_TYPE_SECURED)
+        else {
+            return Err(context(Error::InvalidInput));
+        };
+        let (typ, len) = self
+            .hooks
+            .decrypt(msg.source, session_id, data, out)
+            .map_err(context)?;
+        Ok(Some(SecuredInfo {
+            source: msg.source,
+            session_id,
+            tag: msg.tag,
+            typ,
+            len,
+        }))
+    }
+
+    /// Unbind a listener/request
+    ///
+    /// This has to be called to free the request/listener slot.
+    /// Handles to the slot become stale, operations on them fail
+    /// even after the slot is reused by a new listener/request.
+    /// Returns [BadArgument](Error::BadArgument) for handles that are not bound and
+    /// split requests, whose halves are released instead, [Other](Error::Other) once the
+    /// router was [shut down](Self::shutdown).
+    pub fn unbind(&mut self, handle: impl Into<Handle>) -> RouterResult<()> {
+        let handle = handle.into();
+        if let Handle::Request(req) = handle
+            && self.lookup_request(req).is_some_and(|r| r.halves != 0)
+        {
+            return Err(RouterError::from(Error::BadArgument).with_handle(handle));
+        }
+        self.unbind_inner(handle).map_err(|e| {
+            let e = if matches!(e, Error::BadArgument) {
+                self.unbound_error()
+            } else {
+                e
+            };
+            RouterError::from(e).with_handle(handle)
+        })
+    }
+
+    /// Split the request `handle` into a sending and a receiving half
+    ///
+    /// The halves can be owned by different tasks, e.g. one producing commands and one
+    /// processing the responses. While split, `handle` itself can't be used to send,
+    /// receive or unbind. Once the [RecvHalf] is released, sending fails as nobody would
+    /// receive the responses. The request is unbound when both halves are released,
+    /// [join()](Self::join) turns them back into a plain request.
+    ///
+    /// Returns [BadArgument](Error::BadArgument) if `handle` is not bound or already split.
+    pub fn split(&mut self, handle: RequestHandle) -> RouterResult<(SendHalf, RecvHalf)> {
+        let req = self
+            .lookup_request_mut(handle)
+            .filter(|r| r.halves == 0)
+            .ok_or_else(|| RouterError::from(Error::BadArgument).with_handle(handle.into()))?;
+        req.halves = SEND_HALF | RECV_HALF;
+        Ok((SendHalf(handle), RecvHalf(handle)))
+    }
+
+    /// Send a request message through the sending half of a split request
+    ///
+    /// Like [send_vectored()](Self::send_vectored) for the request, allocating a new tag.
+    /// Returns [BadArgument](Error::BadArgument) once the [RecvHalf] was released.
+    pub fn send_half(
+        &mut self,
+        half: &SendHalf,
+        typ: MsgType,
+        ic: MsgIC,
+        bufs: &[&[u8]],
+    ) -> RouterResult<SendReport> {
+        let handle = half.0.into();
+        if !self.is_bound_as(handle, Access::Send) {
+            return Err(RouterError::from(self.unbound_error()).with_handle(handle));
+        }
+        self.send_bound(None, None, typ, None, ic, handle, None, bufs)
+    }
+
+    /// Receive a response through the receiving half of a split request
+    ///
+    /// See [recv_into()](Self::recv_into).
+    pub fn recv_half_into<W: embedded_io::Write>(
+        &mut self,
+        half: &RecvHalf,
+        sink: &mut W,
+    ) -> RouterResult<Option<MessageInfo>> {
+        let handle = half.0.into();
+        if !self.is_bound_as(handle, Access::Recv) {
+            return Err(RouterError::from(self.unbound_error()).with_handle(handle));
+        }
+        self.recv_bound_into(handle, sink)
+    }
+
+    /// Turn the halves of a split request back into its handle
+    ///
+    /// Returns [BadArgument](Error::BadArgument) if the halves belong to different requests.
+    pub fn join(&mut self, send: SendHalf, recv: RecvHalf) -> RouterResult<RequestHandle> {
+        let handle = send.0;
+        let req = self
+            .lookup_request_mut(handle)
+            .filter(|r| recv.0 == handle && r.halves == SEND_HALF | RECV_HALF)
+            .ok_or_else(|| RouterError::from(Error::BadArgument).with_handle(handle.into()))?;
+        req.halves = 0;
+        Ok(handle)
+    }
+
+    /// Release the sending half of a split request
+    ///
+    /// Responses to requests already sent can still be received through the [RecvHalf].
+    pub fn release_send_half(&mut self, half: SendHalf) -> RouterResult<()> {
+        self.release_half(half.0, SEND_HALF)
+    }
+
+    /// Release the receiving half of a split request
+    pub fn release_recv_half(&mut self, half: RecvHalf) -> RouterResult<()> {
+        self.release_half(half.0, RECV_HALF)
+    }
+
+    /// Release the `half` of the split request `handle`, unbinding it with the last half
+    fn release_half(&mut self, handle: RequestHandle, half: u8) -> RouterResult<()> {
+        let context = |e: Error| RouterError::from(e).with_handle(handle.into());
+        let req = self
+            .lookup_request_mut(handle)
+            .filter(|r| r.halves & half != 0)
+            .ok_or_else(|| context(Error::BadArgument))?;
+        req.halves &= !half;
+        if req.halves == 0 {
+            self.unbind_inner(handle.into()).map_err(context)?;
+        }
+        Ok(())
+    }
+
+    /// Clear the tag state of the request `handle`, keeping it bound
+    ///
+    /// Cancels the flow of the last message sent and discards retained responses,
+    /// late responses are dropped with [DroppedNoRequest](Disposition::DroppedNoRequest).
+    pub fn reset(&mut self, handle: RequestHandle) -> RouterResult<()> {
+        let context = |e: Error| RouterError::from(e).with_handle(handle.into());
+        let req = self
+            .lookup_request_mut(handle)
+            .ok_or_else(|| context(Error::BadArgument))?;
+        let eid = req.eid;
+        let tag = req.last_tag.take();
+        let held = req.reclaim_at.take();
+        req.typ = None;
+        req.attempt = 0;
+        req.tx_started = None;
+        req.tx_stalled = false;
+        if let Some(tag) = tag {
+            self.stack.cancel_flow(eid, tag.tag());
+        }
+        if let Some((held, _)) = held
+            && Some(held) != tag
+        {
+            self.stack.cancel_flow(eid, held.tag());
+        }
+        while self.take_deferred(handle.into()).is_some() {}
+        self.retained.forget(handle.cookie());
+        self.wakers.wake(handle.into());
+        Ok(())
+    }
+
+    /// Send further messages of the request `handle` to `eid`
+    ///
+    /// The request is [reset()](Self::reset) first. Its cookie stays valid, so copies of
+    /// the handle held elsewhere keep working.
+    pub fn retarget(&mut self, handle: RequestHandle, eid: Eid) -> RouterResult<()> {
+        self.reset(handle)?;
+        let req = self
+            .lookup_request_mut(handle)
+            .ok_or_else(|| RouterError::from(Error::InternalError).with_handle(handle.into()))?;
+        debug!("retargeted request from {} to {}", req.eid.0, eid.0);
+        req.eid = eid;
+        Ok(())
+    }
+
+    fn unbind_inner(&mut self, handle: Handle) -> Result<()> {
+        self.retained.forget(handle.cookie());
+        self.wakers.wake(handle);
+        match handle {
+            Handle::Listener(ListenerHandle(cookie)) => {
+                let index = self
+                    .tables
+                    .listener_index(cookie)
+                    .ok_or(Error::BadArgument)?;
+                Self::release(self.tables.listeners_mut(), index)?;
+                self.reassembly_buffers.take(ListenerHandle(cookie));
+                for (source, tag) in self.consumers.forget(ListenerHandle(cookie)) {
+                    let _ = self.stack.get_deferred(source, tag);
+                }
+                Ok(())
+            }
+            Handle::Request(RequestHandle(cookie)) => {
+                let index = self
+                    .tables
+                    .request_index(cookie)
+                    .ok_or(Error::BadArgument)?;
+                let req = Self::release(self.tables.requests_mut(), index)?;
+                if let ReqHandle {
+                    eid,
+                    last_tag: Some(tag),
+                    ..
+                } = req
+                {
+                    self.stack.cancel_flow(eid, tag.tag());
+                }
+                if let Some((held, _)) = req.reclaim_at
+                    && Some(held) != req.last_tag
+                {
+                    self.stack.cancel_flow(req.eid, held.tag());
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Free the slot at `index` and advance its generation
+    ///
+    /// Returns the previous entry, or [BadArgument](Error::BadArgument) if the slot is not bound.
+    fn release<E>(slots: &mut [Slot<E>], index: usize) -> Result<E> {
+        let slot = slots.get_mut(index).ok_or(Error::InternalError)?;
+        let entry = slot.entry.take().ok_or(Error::BadArgument)?;
+        slot.generation = slot.generation.wrapping_add(1);
+        Ok(entry)
+    }
+
+    /// Get the next retained message for `handle` from the stack
+    fn take_deferred(&mut self, handle: Handle) -> Option<MctpMessage<'_>> {
+        Self::take_deferred_from(
+            &mut self.tables,
+            &mut self.stack,
+            &mut self.retained,
+            &mut self.consumers,
+            &mut self.scheduler,
+            handle,
+        )
+    }
+
+    /// Like [take_deferred()](Self::take_deferred), borrowing only the tables and the stack
+    fn take_deferred_from<'s>(
+        tables: &mut T,
+        stack: &'s mut Stack,
+        retained: &mut RetainedMessages,
+        consumers: &mut Consumers,
+        scheduler: &mut Scheduler,
+        handle: Handle,
+    ) -> Option<MctpMessage<'s>> {
+        let queue = match handle {
+            Handle::Listener(h) => tables.listener_mut(h.0).map(|l| &mut l.queue),
+            Handle::Request(h) => tables.request_mut(h.0).map(|r| &mut r.queue),
+        };
+        let mut msg = stack.get_deferred_bycookie(&[handle.cookie()]);
+        if let Some(queue) = queue {
+            queue.pop(msg.is_some());
+            if msg.is_some() {
+                queue.served = scheduler.stamp();
+            }
+        }
+        if let (Handle::Listener(listener), Some(msg)) = (handle, msg.as_mut())
+            && consumers.received(listener, msg.source, msg.tag)
+        {
+            // Held for the consumers, out of reach of the listener
+            msg.retain();
+            msg.set_cookie(Some(SHARED_COOKIE));
+        }
+        if msg.is_some() {
+            retained.pop(handle.cookie());
+        } else {
+            // Expired in the stack, reported by the next update
+            retained.lost(handle.cookie());
+        }
+        msg
+    }
+
+    /// Latch `error` as the last error of `handle`, see [take_error()](Self::take_error)
+    fn latch_error(tables: &mut T, handle: Handle, error: HandleError) {
+        let latch = match handle {
+            Handle::Listener(h) => tables.listener_mut(h.0).map(|l| &mut l.error),
+            Handle::Request(h) => tables.request_mut(h.0).map(|r| &mut r.error),
+        };
+        if let Some(latch) = latch {
+            *latch = Some(error);
+        }
+    }
+
+    fn lookup_request(&self, handle: RequestHandle) -> Option<&ReqHandle> {
+        self.tables.request(handle.0)
+    }
+
+    fn lookup_request_mut(&mut self, handle: RequestHandle) -> Option<&mut ReqHandle> {
+        self.tables.request_mut(handle.0)
+    }
+
+    /// Get the error of operations on handles that are not bound
+    ///
+    /// [Other](Error::Other) once the router was [shut down](Self::shutdown),
+    /// [BadArgument](Error::BadArgument) for stale handles otherwise.
+    fn unbound_error(&self) -> Error {
+        if self.shut_down {
+            Error::Other
+        } else {
+            Error::BadArgument
+        }
+    }
+
+    /// Check if `handle` refers to a bound listener or request of the current slot generation
+    ///
+    /// Split requests are only bound for their halves.
+    fn is_bound(&self, handle: Handle) -> bool {
+        self.is_bound_as(handle, Access::Handle)
+    }
+
+    /// Check if `handle` is bound and can be accessed as `access`
+    fn is_bound_as(&self, handle: Handle, access: Access) -> bool {
+        match handle {
+            Handle::Listener(ListenerHandle(cookie)) => self.tables.listener(cookie).is_some(),
+            Handle::Request(handle) => self
+                .lookup_request(handle)
+                .is_some_and(|r| access.permits(r.halves)),
+        }
+    }
+}
+
+/// Get the payload of a received message without the integrity check
+///
+/// The payload of an [MctpMessage] with the IC bit set ends with the CRC-32C,
+/// which is verified by the [Router] before the message is delivered.
+pub fn message_body<'m>(msg: &'m MctpMessage<'_>) -> &'m [u8] {
+    if msg.ic.0 {
+        let len = msg.payload.len().saturating_sub(IC_LEN);
+        msg.payload.get(..len).unwrap_or_default()
+    } else {
+        msg.payload
+    }
+}
+
+/// Verify the CRC-32C at the end of a message with the IC bit set
+fn check_integrity(msg: &MctpMessage<'_>) -> bool {
+    let Some((body, check)) = msg
+        .payload
+        .split_last_chunk::<IC_LEN>()
+        .map(|(body, check)| (body, u32::from_le_bytes(*check)))
+    else {
+        return false;
+    };
+    let mut crc = Crc32c::new();
+    crc.update(&[msg.typ.0 | 0x80]);
+    crc.update(body);
+    crc.finish() == check
+}
+
+/// A buffer a [Router] builds a single MCTP packet in, see [Sender::Packet]
+///
+/// Implemented for byte arrays, the length of the array is the largest packet size.
+pub trait PacketBuffer: AsRef<[u8]> + AsMut<[u8]> + core::fmt::Debug {
+    /// Size of the buffer
+    const LEN: usize;
+    /// Create a buffer filled with zeros
+    fn zeroed() -> Self;
+}
+
+impl<const N: usize> PacketBuffer for [u8; N] {
+    const LEN: usize = N;
+
+    fn zeroed() -> Self {
+        [0; N]
+    }
+}
+
+/// A Sender used by a [Router] to send data
+///
+/// Implemented by a transport binding for sending packets.
+///
+/// Bindings implement [send_packet()](Sender::send_packet) and choose the [Packet](Sender::Packet)
+/// buffer. Earlier versions required `send_vectored()` to fragment and send whole messages,
+/// this is now done by the [Router]; [send_vectored()](Sender::send_vectored) remains as a
+/// provided method for callers.
+pub trait Sender {
+    /// Buffer for a single outbound packet, e.g. `[u8; 64]`
+    ///
+    /// Its size bounds the packets the [Router] builds and the memory it spends on each of
+    /// them: port and peer MTUs are capped at [PacketBuffer::LEN], and reordered or
+    /// refragmented packets are held in buffers of this type.
+    type Packet: PacketBuffer;
+
+    /// Send a single MCTP packet to `eid`
+    ///
+    /// `pkt` contains the MCTP transport header and payload,
+    /// the transport binding header has to be added by the implementation.
+    /// The packet is never larger than the MTU returned by [get_mtu()](Sender::get_mtu).
+    fn send_packet(&mut self, eid: Eid, pkt: &[u8]) -> Result<()>;
+    /// Get the MTU of a MCTP packet fragment (without transport headers)
+    fn get_mtu(&self) -> usize;
+    /// Get the MTU supported by the peer `eid`, if known to the binding
+    ///
+    /// Bindings that negotiate packet sizes through binding-specific means
+    /// (e.g. during endpoint discovery) can report them here.
+    /// Takes precedence over MTUs learned by the [Router].
+    /// The default implementation returns `None`.
+    fn peer_mtu(&self, _eid: Eid) -> Option<usize> {
+        None
+    }
+    /// Send a single MCTP packet to `eid` along a static [Route]
+    ///
+    /// Called instead of [send_packet()](Sender::send_packet) for destinations covered by a
+    /// route, see [GenericRouter::add_route()].
+    /// Bindings with more than one port, or that need the physical address of the next hop,
+    /// implement this. The default implementation ignores the route.
+    fn send_routed(&mut self, eid: Eid, route: &Route, pkt: &[u8]) -> Result<()> {
+        let _ = route;
+        self.send_packet(eid, pkt)
+    }
+    /// Send a single MCTP packet padded for its port to `eid`
+    ///
+    /// Called instead of [send_packet()](Sender::send_packet) and
+    /// [send_routed()](Sender::send_routed) for packets on a port with [Padding], see
+    /// [GenericRouter::set_port_padding()]. `pkt` ends with `pad_len` bytes of padding,
+    /// bindings that report the padding in their header (e.g. the Pad Length of PCIe VDM)
+    /// implement this. The default implementation passes the padded packet on along `route`.
+    fn send_padded(
+        &mut self,
+        eid: Eid,
+        route: Option<&Route>,
+        pkt: &[u8],
+        pad_len: usize,
+    ) -> Result<()> {
+        let _ = pad_len;
+        match route {
+            Some(route) => self.send_routed(eid, route, pkt),
+            None => self.send_packet(eid, pkt),
+        }
+    }
+
+    /// Check whether the failure `err` of sending a packet was only caused by a busy receiver
+    ///
+    /// Bindings with transient back pressure (e.g. an SMBus NACK or exhausted credits)
+    /// return `true` for such errors, the packet is then sent again as configured with
+    /// [RouterConfig::busy_retry()].
+    /// The default implementation treats all errors as hard failures.
+    fn is_receiver_busy(&self, err: &Error) -> bool {
+        let _ = err;
+        false
+    }
+    /// Validate the transport binding header of a received `frame` and strip it
+    ///
+    /// Returns the MCTP packet in `frame`, see [GenericRouter::inbound_frame()].
+    /// Bindings check what their header carries (e.g. an SMBus PEC or the physical
+    /// addresses) and return [InvalidInput](Error::InvalidInput) for frames failing the check.
+    /// The default implementation returns [Unsupported](Error::Unsupported).
+    fn strip_header<'f>(&self, frame: &'f [u8]) -> Result<&'f [u8]> {
+        let _ = frame;
+        Err(Error::Unsupported)
+    }
+
+    /// Check whether the binding computes the integrity check of messages to `eid` in hardware
+    ///
+    /// `route` is the [Route] the packets take, so bindings with offload on some ports only
+    /// decide per port. For messages with the IC bit set, the [Router] then leaves the
+    /// CRC-32C to the binding: it is not appended to the message, and the packets are
+    /// fragmented 4 bytes short of the MTU so the binding can append it to the packet with
+    /// the EOM flag set.
+    /// The default implementation returns `false`, the check is computed in software.
+    fn offloads_integrity_check(&self, eid: Eid, route: Option<&Route>) -> bool {
+        let _ = (eid, route);
+        false
+    }
+
+    /// Send the packets of a message fragmented by `fragmenter` with the payload `payload`
+    ///
+    /// Each packet is built in a [Packet](Sender::Packet) buffer and passed to
+    /// [send_packet()](Sender::send_packet), see [for_each_fragment()].
+    /// Returns the tag of the message.
+    fn send_vectored(
+        &mut self,
+        eid: Eid,
+        fragmenter: Fragmenter,
+        payload: &[&[u8]],
+    ) -> Result<Tag> {
+        let mut buf = Self::Packet::zeroed();
+        for_each_fragment(fragmenter, payload, buf.as_mut(), |pkt| {
+            self.send_packet(eid, pkt)
+        })
+    }
+}
+
+/// Forwards to the sender behind the reference, e.g. a `&mut dyn Sender`
+impl<S: Sender + ?Sized> Sender for &mut S {
+    type Packet = S::Packet;
+
+    fn send_packet(&mut self, eid: Eid, pkt: &[u8]) -> Result<()> {
+        (**self).send_packet(eid, pkt)
+    }
+
+    fn get_mtu(&self) -> usize {
+        (**self).get_mtu()
+    }
+
+    fn peer_mtu(&self, eid: Eid) -> Option<usize> {
+        (**self).peer_mtu(eid)
+    }
+
+    fn send_routed(&mut self, eid: Eid, route: &Route, pkt: &[u8]) -> Result<()> {
+        (**self).send_routed(eid, route, pkt)
+    }
+
+    fn send_padded(
+        &mut self,
+        eid: Eid,
+        route: Option<&Route>,
+        pkt: &[u8],
+        pad_len: usize,
+    ) -> Result<()> {
+        (**self).send_padded(eid, route, pkt, pad_len)
+    }
+
+    fn is_receiver_busy(&self, err: &Error) -> bool {
+        (**self).is_receiver_busy(err)
+    }
+
+    fn strip_header<'f>(&self, frame: &'f [u8]) -> Result<&'f [u8]> {
+        (**self).strip_header(frame)
+    }
+
+    fn offloads_integrity_check(&self, eid: Eid, route: Option<&Route>) -> bool {
+        (**self).offloads_integrity_check(eid, route)
+    }
+}
+
+/// Find the listener for requests of type `typ`, falling back to the catch-all listener
+fn listener_index(listeners: &[Slot<ListenerEntry>], typ: MsgType) -> Option<usize> {
+    let bound = |want: Option<MsgType>| {
+        listeners
+            .iter()
+            .position(|s| s.entry.as_ref().is_some_and(|l| l.accepts(want)))
+    };
+    bound(Some(typ)).or_else(|| bound(None))
+}
+
+/// Pass `pkt` for `eid` to `sender`, along `route` if there is one
+///
+/// Packets are padded with `padding` first.
+fn send_packet<S: Sender>(
+    sender: &mut S,
+    eid: Eid,
+    route: Option<&Route>,
+    padding: Option<Padding>,
+    pkt: &[u8],
+) -> Result<()> {
+    if let Some(padding) = padding {
+        let len = padding.padded_len(pkt.len()).min(S::Packet::LEN);
+        if len > pkt.len() {
+            let mut buf = S::Packet::zeroed();
+            let (head, tail) = buf
+                .as_mut()
+                .split_at_mut_checked(pkt.len())
+                .ok_or(Error::InternalError)?;
+            head.copy_from_slice(pkt);
+            tail.fill(padding.fill);
+            let padded = buf.as_ref().get(..len).ok_or(Error::InternalError)?;
+            return sender.send_padded(eid, route, padded, len - pkt.len());
+        }
+    }
+    match route {
+        Some(route) => sender.send_routed(eid, route, pkt),
+        None => sender.send_packet(eid, pkt),
+    }
+}
+
+/// Compute the CRC-32C of a message of type `typ` with the IC bit set and body `bufs`
+fn integrity_check(typ: MsgType, bufs: &[&[u8]]) -> [u8; IC_LEN] {
+    let mut c = Crc32c::new();
+    c.update(&[typ.0 | 0x80]);
+    for buf in bufs {
+        c.update(buf);
+    }
+    c.finish().to_le_bytes()
+}
+
+/// Collect `bufs` followed by their integrity check or MIC `crc` in `parts`
+///
+/// Returns [BadArgument](Error::BadArgument) if there are more than [MAX_IC_BUFS] buffers.
+fn append_check<'p, 'a>(
+    bufs: &[&'a [u8]],
+    crc: &'a [u8],
+    parts: &'p mut [&'a [u8]; MAX_IC_BUFS + 1],
+) -> Result<&'p [&'a [u8]]> {
+    if bufs.len() > MAX_IC_BUFS {
+        return Err(Error::BadArgument);
+    }
+    for (part, buf) in parts.iter_mut().zip(bufs) {
+        *part = buf;
+    }
+    let (check, _) = parts.split_at_mut(bufs.len() + 1);
+    if let Some(last) = check.last_mut() {
+        *last = crc;
+    }
+    Ok(check)
+}
+
+/// Fragment a message and pass each packet to `transmit`
+///
+/// Packets are built one at a time in `scratch`, reading directly from the borrowed `payload`
+/// slices, so no buffer for the whole message is needed.
+/// `scratch` has to hold at least one packet of the MTU `fragmenter` was created with.
+///
+/// Returns the tag of the message once all packets were transmitted,
+/// or the first error returned by the fragmenter or `transmit`.
+pub fn for_each_fragment(
+    fragmenter: Fragmenter,
+    payload: &[&[u8]],
+    scratch: &mut [u8],
+    mut transmit: impl FnMut(&[u8]) -> Result<()>,
+) -> Result<Tag> {
+    fragment_each(Packets::Fragments(fragmenter), payload, scratch, |pkt| {
+        transmit(pkt)
+    })
+}
+
+/// The packets of an outgoing message
+#[derive(Debug)]
+enum Packets {
+    /// Fragmented by the stack
+    Fragments(Fragmenter),
+    /// A message fitting into one packet with this header, built without a fragmenter
+    Single(header::Header, MsgType, MsgIC),
+}
+
+impl Packets {
+    fn tag(&self) -> Tag {
+        match self {
+            Packets::Fragments(frag) => frag.tag(),
+            Packets::Single(hdr, ..) => hdr.tag,
+        }
+    }
+}
+
+/// Like [for_each_fragment()], passing the packets mutably
+fn fragment_each(
+    packets: Packets,
+    payload: &[&[u8]],
+    scratch: &mut [u8],
+    mut transmit: impl FnMut(&mut [u8]) -> Result<()>,
+) -> Result<Tag> {
+    let mut fragmenter = match packets {
+        Packets::Fragments(fragmenter) => fragmenter,
+        Packets::Single(hdr, typ, ic) => {
+            let (head, body) = scratch
+                .split_first_chunk_mut::<{ header::HEADER_LEN }>()
+                .ok_or(Error::NoSpace)?;
+            *head = hdr.to_bytes();
+            let (first, mut rest) = body.split_first_mut().ok_or(Error::NoSpace)?;
+            *first = typ.0 | if ic.0 { 0x80 } else { 0 };
+            let mut len = header::HEADER_LEN + 1;
+            for buf in payload {
+                let (part, tail) = core::mem::take(&mut rest)
+                    .split_at_mut_checked(buf.len())
+                    .ok_or(Error::NoSpace)?;
+                part.copy_from_slice(buf);
+                rest = tail;
+                len += buf.len();
+            }
+            transmit(scratch.get_mut(..len).ok_or(Error::InternalError)?)?;
+            return Ok(hdr.tag);
+        }
+    };
+    loop {
+        match fragmenter.fragment_vectored(payload, scratch) {
+            fragment::SendOutput::Packet(pkt) => transmit(pkt)?,
+            fragment::SendOutput::Complete { tag, cookie: _ } => return Ok(tag),
+            fragment::SendOutput::Error { err, cookie: _ } => return Err(err),
+        }
+    }
+}
+
+/// The parts of a router passing the packets of a message to the sender
+///
+/// Borrowed apart from the stack, so a [Responder] can send while the request is borrowed.
+#[derive(Debug)]
+struct Transmitter<'r, S: Sender, H: Hooks, C: Clock> {
+    sender: &'r mut S,
+    hooks: &'r mut H,
+    clock: &'r C,
+    routes: &'r Routes,
+    failover: &'r mut Failover,
+    links: &'r LinkStates,
+    padding: &'r PortPadding,
+    throttles: &'r mut Throttles,
+    tx_stats: &'r mut TxStats,
+    peer_stats: &'r mut PeerStatsTable,
+    busy_retry: Option<BusyRetry>,
+    tx_timeout_millis: Option<u64>,
+}
+
+impl<S: Sender, H: Hooks, C: Clock> Transmitter<'_, S, H, C> {
+    /// Fragment `bufs` with `frag` and pass the packets to the sender
+    ///
+    /// Packets are sent from the local EID `source` instead of the own EID if set.
+    /// Aborts with [TimedOut](Error::TimedOut) when the transmit timeout or the deadline of
+    /// `owner`, the cookie of the handle sending the message and its deadline, passes while
+    /// sending.
+    fn transmit(
+        &mut self,
+        eid: Eid,
+        frag: Packets,
+        bufs: &[&[u8]],
+        now_millis: u64,
+        source: Option<Eid>,
+        owner: Option<(AppCookie, Option<u64>)>,
+    ) -> Result<SendReport> {
+        let mut buf = S::Packet::zeroed();
+        let hop = routes::select(
+            self.routes,
+            self.failover,
+            self.links,
+            eid,
+            self.clock.now_millis(),
+        );
+        let stats = &mut *self.tx_stats;
+        let route = hop.map(|hop| hop.route);
+        let padding = self.padding.for_route(route.as_ref());
+        let primary = hop.filter(|hop| !hop.backup).map(|hop| hop.slot);
+        let failover = &mut *self.failover;
+        if self.links.blocks(route.as_ref()) {
+            debug!("not sending to {}, link down", eid.0);
+            return Err(Error::TxFailure);
+        }
+        let (mut packets, mut bytes, mut held) = (0, 0, 0);
+        let (clock, tx_timeout) = (self.clock, self.tx_timeout_millis);
+        let busy_retry = self.busy_retry;
+        let throttles = &mut *self.throttles;
+        let tag = fragment_each(frag, bufs, buf.as_mut(), |pkt| {
+            if let Some(source) = source {
+                header::Header::set_source(pkt, source);
+            }
+            let pkt = &*pkt;
+            if !throttles.admit(eid, pkt.len(), clock.now_millis()) {
+                throttles.hold(eid, pkt, owner).inspect_err(|_| {
+                    warn!("no room to hold packet to {}", eid.0);
+                })?;
+                trace!("held packet to {}, throttled", eid.0);
+                packets += 1;
+                bytes += pkt.len();
+                held += 1;
+                stats.packets = stats.packets.wrapping_add(1);
+                stats.copied_bytes = stats.copied_bytes.wrapping_add(pkt.len());
+                return Ok(());
+            }
+            let mut retries = 0;
+            loop {
+                if let Some(timeout) = tx_timeout
+                    && clock.now_millis().saturating_sub(now_millis) >= timeout
+                {
+                    warn!("sending to {} timed out after {} packets", eid.0, packets);
+                    return Err(Error::TimedOut);
+                }
+                if owner
+                    .and_then(|o| o.1)
+                    .is_some_and(|d| clock.now_millis() >= d)
+                {
+                    warn!("sending to {} missed its deadline", eid.0);
+                    return Err(Error::TimedOut);
+                }
+                if retries == 0 {
+                    packets += 1;
+                    bytes += pkt.len();
+                    stats.packets = stats.packets.wrapping_add(1);
+                    stats.copied_bytes = stats.copied_bytes.wrapping_add(pkt.len());
+                    self.hooks.capture(Direction::Outbound, now_millis, pkt);
+                    lifecycle!("fragment sent", eid = eid.0, len = pkt.len());
+                }
+                let err = match send_packet(&mut *self.sender, eid, route.as_ref(), padding, pkt) {
+                    Err(e) if self.sender.is_receiver_busy(&e) => e,
+                    sent => {
+                        if let Some(slot) = primary {
+                            failover.report(slot, sent.is_ok(), clock.now_millis());
+                        }
+                        return sent;
+                    }
+                };
+                let Some(retry) = busy_retry.filter(|r| retries < r.max_retries) else {
+                    warn!("receiver {} still busy, giving up", eid.0);
+                    return Err(err);
+                };
+                retries += 1;
+                stats.busy_retries = stats.busy_retries.wrapping_add(1);
+                trace!("receiver {} busy, retry {}", eid.0, retries);
+                clock.delay_millis(retry.delay_millis);
+            }
+        })
+        .inspect_err(|_| self.peer_stats.sent(eid, None, now_millis))?;
+        self.tx_stats.messages = self.tx_stats.messages.wrapping_add(1);
+        self.peer_stats.sent(eid, Some(bytes), now_millis);
+        Ok(SendReport {
+            tag,
+            packets,
+            bytes,
+            queued: false,
+            held,
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::panic)]
+mod test {
+    use core::cell::RefCell;
+
+    use mctp::Eid;
+
+    use crate::test_util::{BufferSender, NullSender};
+    use crate::{Direction, HandleTables, Hooks, Router};
+
+    /// Test the creation of request and listener handles (`AppCookies`)
+    #[test]
+    fn test_handle_creation() {
+        const REQ_HANDLES: usize = 8;
+        const LISTENER_HANDLES: usize = 8;
+        let outbound = NullSender;
+        let mut router: Router<_, REQ_HANDLES, LISTENER_HANDLES> =
+            Router::new(Eid(42), 0, outbound);
+
+        // create a new listener and expect the cookie value to be 0 (raw index of the underlying table)
+        let listener = router.listener(mctp::MsgType(0));
+        assert!(listener.is_ok());
+        assert!(listener.as_ref().is_ok_and(|x| x.cookie().0 == 0));
+
+        // create a new request
+        // we expect the value to be MAX_LISTENER_HANDLES (request table index 0 + offset)
+        let req = router.req(Eid(112));
+        assert!(req.is_ok());
+        assert!(req.as_ref().is_ok_and(|x| x.cookie().0 == LISTENER_HANDLES));
+
+        router
+            .unbind(listener.unwrap())
+            .expect("failed to unbind listener handle");
+        router
+            .unbind(req.unwrap())
+            .expect("failed to unbind request handle");
+        for listener in router.tables.listeners() {
+            assert!(listener.entry.is_none());
+        }
+        for request in router.tables.requests() {
+            assert!(request.entry.is_none());
+        }
+    }
+
+    /// Handle tables can be sized at runtime
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn vec_tables() {
+        let config = crate::RouterConfig::new(Eid(42));
+        let mut router = crate::VecRouter::new_with_capacity(
+            config,
+            crate::ManualClock::new(0),
+            NullSender,
+            crate::NoHooks,
+            2,
+            3,
+        );
+        assert!(router.listener(mctp::MsgType(1)).is_ok());
+        assert!(router.listener(mctp::MsgType(2)).is_ok());
+        assert!(router.listener(mctp::MsgType(3)).is_err());
+        let reqs: Vec<_> = (0..3).map(|_| router.req(Eid(8)).unwrap()).collect();
+        assert!(router.req(Eid(8)).is_err());
+        // request cookies are enumerated after the listeners
+        assert_eq!(
+            reqs.iter().map(|r| r.cookie().0).collect::<Vec<_>>(),
+            vec![2, 3, 4]
+        );
+        assert_eq!(router.requests().count(), 3);
+    }
+
+    /// Slots beyond the cookie index range are refused instead of panicking
+    #[test]
+    fn cookie_range() {
+        use crate::tables::{ListenerEntry, ReqHandle, Slot};
+
+        struct HugeTables {
+            listeners: Vec<Slot<ListenerEntry>>,
+            requests: Vec<Slot<ReqHandle>>,
+        }
+
+        impl crate::tables::sealed::Sealed for HugeTables {}
+
+        impl HandleTables for HugeTables {
+            fn listeners(&self) -> &[Slot<ListenerEntry>] {
+                &self.listeners
+            }
+            fn listeners_mut(&mut self) -> &mut [Slot<ListenerEntry>] {
+                &mut self.listeners
+            }
+            fn requests(&self) -> &[Slot<ReqHandle>] {
+                &self.requests
+            }
+            fn requests_mut(&mut self) -> &mut [Slot<ReqHandle>] {
+                &mut self.requests
+            }
+        }
+
+        fn slot<T>() -> Slot<T> {
+            Slot {
+                entry: None,
+                generation: 0,
+            }
+        }
+
+        let tables = HugeTables {
+            listeners: (0..1 << 16).map(|_| slot()).collect(),
+            requests: vec![slot()],
+        };
+        let mut router = crate::GenericRouter::new_with_tables(
+            crate::RouterConfig::new(Eid(42)),
+            crate::ManualClock::new(0),
+            NullSender,
+            crate::NoHooks,
+            tables,
+        );
+        assert!(router.listener(mctp::MsgType(1)).is_ok());
+        assert!(matches!(router.req(Eid(8)), Err(mctp::Error::BadArgument)));
+        assert_eq!(router.requests().count(), 0);
+    }
+
+    /// Handles of a reused slot don't refer to the new listener/request
+    #[test]
+    fn stale_handles() {
+        let packets = RefCell::new(Vec::new());
+        let outbound: BufferSender<255> = BufferSender { packets: &packets };
+        let mut router_a: Router<_, 1, 1> = Router::new(Eid(42), 0, NullSender);
+        let mut router_b: Router<_, 1, 1> = Router::new(Eid(112), 0, outbound);
+
+        let stale_listener = router_a.listener(mctp::MsgType(1)).unwrap();
+        router_a.unbind(stale_listener).unwrap();
+        let listener = router_a.listener(mctp::MsgType(1)).unwrap();
+        assert_ne!(stale_listener, listener);
+        assert!(router_a.unbind(stale_listener).is_err());
+
+        let stale_req = router_b.req(Eid(42)).unwrap();
+        router_b.unbind(stale_req).unwrap();
+        let req = router_b.req(Eid(42)).unwrap();
+        assert_ne!(stale_req, req);
+        let send = |router: &mut Router<_, 1, 1>, handle| {
+            router.send(
+                None,
+                mctp::MsgType(1),
+                None,
+                mctp::MsgIC(false),
+                handle,
+                &[1, 2, 3],
+            )
+        };
+        assert!(matches!(
+            send(&mut router_b, stale_req).map_err(|e| e.into_inner()),
+            Err(mctp::Error::BadArgument)
+        ));
+        send(&mut router_b, req).unwrap();
+
+        for pkt in packets.borrow().iter() {
+            assert_eq!(
+                router_a.inbound(pkt).unwrap(),
+                Some(crate::Handle::Listener(listener))
+            );
+        }
+        assert!(router_a.recv(stale_listener).is_none());
+        assert!(router_a.recv(listener).is_some());
+    }
+
+    /// Bound handles can be enumerated
+    #[test]
+    fn introspection() {
+        let buf_out_a = RefCell::new(Vec::new());
+        let outbound_a: BufferSender<255> = BufferSender {
+            packets: &buf_out_a,
+        };
+        let buf_out_b = RefCell::new(Vec::new());
+        let outbound_b: BufferSender<255> = BufferSender {
+            packets: &buf_out_b,
+        };
+        let mut router_a: Router<_, 4, 4> = Router::new(Eid(42), 0, outbound_a);
+        let mut router_b: Router<_, 4, 4> = Router::new(Eid(112), 100, outbound_b);
+
+        let listener = router_a.listener(mctp::MsgType(1)).unwrap();
+        let other = router_a.listener(mctp::MsgType(2)).unwrap();
+        router_a.unbind(other).unwrap();
+        assert_eq!(
+            router_a.listeners().collect::<Vec<_>>(),
+            vec![(listener, mctp::MsgType(1))]
+        );
+        assert_eq!(router_a.requests().count(), 0);
+
+        let req = router_b.req(Eid(42)).unwrap();
+        let tag = router_b
+            .send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[1])
+            .unwrap()
+            .tag;
+        router_b.update(150).unwrap();
+        let info = router_b.requests().next().unwrap();
+        assert_eq!(info.handle, req);
+        assert_eq!(info.eid, Eid(42));
+        assert_eq!(info.tag, Some(tag));
+        assert_eq!(info.age_millis, 50);
+
+        // the response clears the outstanding tag
+        for pkt in buf_out_b.borrow().iter() {
+            router_a.inbound(pkt).unwrap();
+        }
+        let request = crate::MessageInfo::from(&router_a.recv(listener).unwrap());
+        router_a.respond(listener, &request, &[&[2]]).unwrap();
+        // responses are not answered
+        let response = crate::MessageInfo {
+            tag: mctp::Tag::Unowned(tag.tag()),
+            ..request
+        };
+        let err = router_a.respond(listener, &response, &[]).unwrap_err();
+        assert!(matches!(err.error(), mctp::Error::BadArgument));
+        for pkt in buf_out_a.borrow().iter() {
+            assert_eq!(router_b.inbound(pkt).unwrap(), Some(req.into()));
+        }
+        assert_eq!(router_b.requests().next().unwrap().tag, None);
+    }
+
+    /// Channels of a shared router can be used side by side
+    #[test]
+    fn shared_channels() {
+        let buf_out_a = RefCell::new(Vec::new());
+        let outbound_a: BufferSender<255> = BufferSender {
+            packets: &buf_out_a,
+        };
+        let buf_out_b = RefCell::new(Vec::new());
+        let outbound_b: BufferSender<255> = BufferSender {
+            packets: &buf_out_b,
+        };
+        let router_a: Router<_, 4, 4> = Router::new(Eid(42), 0, outbound_a);
+        let router_b: Router<_, 4, 4> = Router::new(Eid(112), 0, outbound_b);
+        let router_a = crate::shared::SharedRouter::new(router_a);
+        let router_b = crate::shared::SharedRouter::new(router_b);
+
+        let listener = router_a.listener(mctp::MsgType(1)).unwrap();
+        let req = router_b.req(Eid(42)).unwrap();
+        let req_copy = req;
+        req.send(mctp::MsgType(1), mctp::MsgIC(false), &[&[1, 2], &[3]])
+            .unwrap();
+        for pkt in buf_out_b.borrow().iter() {
+            router_a.inbound(pkt).unwrap();
+        }
+
+        let mut buf = [0; 16];
+        let info = listener.try_recv(&mut buf).unwrap().unwrap();
+        assert_eq!(buf.get(..info.len), Some([1, 2, 3].as_slice()));
+        listener
+            .respond(&info, mctp::MsgIC(false), &[&[4]])
+            .unwrap();
+        for pkt in buf_out_a.borrow().iter() {
+            router_b.inbound(pkt).unwrap();
+        }
+
+        let info = req_copy.try_recv(&mut buf).unwrap().unwrap();
+        assert_eq!(buf.get(..info.len), Some([4].as_slice()));
+        assert!(req.try_recv(&mut buf).unwrap().is_none());
+
+        // the core is only borrowed for the duration of an operation
+        let nested = router_a.with(|_| router_a.poll());
+        assert!(matches!(nested, Ok(Err(mctp::Error::InternalError))));
+        req.unbind().unwrap();
+        assert!(req_copy.unbind().is_err());
+    }
+
+    /// Options are applied from a `RouterConfig`
+    #[test]
+    fn config() {
+        let config = crate::RouterConfig::new(Eid(42))
+            .uuid([7; 16])
+            .network_id([9; 16])
+            .request_timeout_millis(Some(100))
+            .mtu(Eid(112), 68)
+            .unwrap();
+        assert!(config.clone().mtu(Eid(113), 4).is_err());
+        let mut router: Router<_, 4, 4> =
+            Router::new_with_config(config, 0, NullSender, crate::NoHooks);
+        assert_eq!(router.get_eid(), Eid(42));
+        assert_eq!(router.uuid(), Some([7; 16]));
+        assert_eq!(router.network_id(), Some([9; 16]));
+        assert_eq!(router.mtu(Eid(112)), 68);
+
+        let req = router.req(Eid(112)).unwrap();
+        router
+            .send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[1])
+            .unwrap();
+        assert!(router.update(60).unwrap() <= 40);
+        assert!(router.requests().next().unwrap().tag.is_some());
+        router.update(100).unwrap();
+        assert_eq!(router.requests().next().unwrap().tag, None);
+    }
+
+    /// A shared manual clock drives timestamps and timeouts
+    #[test]
+    fn clock() {
+        let clock = crate::ManualClock::new(100);
+        let config = crate::RouterConfig::new(Eid(42)).request_timeout_millis(Some(50));
+        let mut router: Router<_, 4, 4, _, _> =
+            Router::new_with_clock(config, &clock, NullSender, crate::NoHooks);
+
+        let req = router.req(Eid(112)).unwrap();
+        router
+            .send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[1])
+            .unwrap();
+        clock.advance(30);
+        assert_eq!(router.requests().next().unwrap().age_millis, 30);
+        router.poll().unwrap();
+        assert!(router.requests().next().unwrap().tag.is_some());
+        clock.advance(20);
+        router.poll().unwrap();
+        assert_eq!(router.requests().next().unwrap().tag, None);
+    }
+
+    /// Messages to a peer with an MTU override are fragmented at the overridden size
+    #[test]
+    fn per_destination_mtu() {
+        let packets = RefCell::new(Vec::new());
+        let outbound: BufferSender<255> = BufferSender { packets: &packets };
+        let mut router: Router<_, 8, 8> = Router::new(Eid(42), 0, outbound);
+
+        assert!(router.set_mtu(Eid(112), 4).is_err());
+        router.set_mtu(Eid(112), 68).unwrap();
+        assert_eq!(router.mtu(Eid(112)), 68);
+        assert_eq!(router.mtu(Eid(113)), 255);
+
+        let req = router.req(Eid(112)).unwrap();
+        let payload = [1; 300];
+        router
+            .send(
+                None,
+                mctp::MsgType(0),
+                None,
+                mctp::MsgIC(false),
+                req,
+                &payload,
+            )
+            .unwrap();
+        assert_eq!(packets.borrow().len(), 5);
+        assert!(packets.borrow().iter().all(|p| p.len() <= 68));
+
+        assert_eq!(router.clear_mtu(Eid(112)), Some(68));
+        assert_eq!(router.mtu(Eid(112)), 255);
+    }
+
+    /// MTU discovery learns the transmission unit of a peer from received fragments
+    #[test]
+    fn mtu_discovery() {
+        let packets = RefCell::new(Vec::new());
+        let outbound: BufferSender<255> = BufferSender { packets: &packets };
+        let mut router_a: Router<_, 8, 8> = Router::new(Eid(42), 0, NullSender);
+        let mut router_b: Router<_, 8, 8> = Router::new(Eid(112), 0, outbound);
+        router_a.set_mtu_discovery(true);
+        router_a.listener(mctp::MsgType(0)).unwrap();
+        router_b.set_mtu(Eid(42), 68).unwrap();
+
+        let req = router_b.req(Eid(42)).unwrap();
+        router_b
+            .send(
+                None,
+                mctp::MsgType(0),
+                None,
+                mctp::MsgIC(false),
+                req,
+                &[1; 100],
+            )
+            .unwrap();
+        for pkt in packets.borrow().iter() {
+            router_a.inbound(pkt).unwrap();
+        }
+        assert_eq!(router_a.mtu(Eid(112)), 68);
+
+        // static entries take precedence over learned ones
+        router_a.set_mtu(Eid(112), 100).unwrap();
+        router_a.learn_mtu(Eid(112), 68);
+        assert_eq!(router_a.mtu(Eid(112)), 100);
+
+        // Packets for other EIDs, types without a listener or denied sources teach nothing
+        let first = |dest, source, typ| {
+            let mut pkt = vec![1, dest, source, 0x88, typ];
+            pkt.resize(40, 0);
+            pkt
+        };
+        router_a.inbound(&first(50, 113, 0)).unwrap();
+        router_a.inbound(&first(42, 114, 1)).unwrap();
+        let listener = router_a.listener(mctp::MsgType(2)).unwrap();
+        router_a
+            .set_listener_acl(listener, Some(crate::EidAcl::allow(&[Eid(116)])))
+            .unwrap();
+        router_a.inbound(&first(42, 115, 2)).unwrap();
+        assert!(
+            [113, 114, 115]
+                .iter()
+                .all(|eid| router_a.mtu(Eid(*eid)) == 255)
+        );
+        router_a.inbound(&first(42, 116, 2)).unwrap();
+        assert_eq!(router_a.mtu(Eid(116)), 40);
+    }
+
+    /// The sender can be replaced without rebuilding the router
+    #[test]
+    fn replace_sender() {
+        let old_packets = RefCell::new(Vec::new());
+        let new_packets = RefCell::new(Vec::new());
+        let mut router: Router<_, 4, 4> =
+            Router::new(Eid(8), 0, BufferSender::<64>::new(&old_packets));
+        let req = router.req(Eid(20)).unwrap();
+        router.set_mtu(Eid(21), 40).unwrap();
+        router.learn_mtu(Eid(20), 32);
+        router
+            .send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[1])
+            .unwrap();
+
+        let old = router.replace_sender(BufferSender::new(&new_packets));
+        assert!(core::ptr::eq(old.packets, &old_packets));
+        assert_eq!(router.mtu(Eid(20)), 64);
+        assert_eq!(router.mtu(Eid(21)), 40);
+        router
+            .send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[2])
+            .unwrap();
+        assert_eq!(old_packets.borrow().len(), 1);
+        assert_eq!(new_packets.borrow().len(), 1);
+    }
+
+    /// Reserved reassembly contexts keep a message type receivable under bulk traffic
+    #[test]
+    fn reserved_contexts() {
+        use super::Disposition;
+        use mctp::MsgType;
+
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, NullSender);
+        let pldm = router.listener(MsgType(1)).unwrap();
+        let control = router.listener(MsgType(0)).unwrap();
+        router.reserve_contexts(MsgType(0), 1).unwrap();
+        assert!(matches!(
+            router.reserve_contexts(MsgType(1), 4),
+            Err(mctp::Error::BadArgument)
+        ));
+        assert_eq!(router.reserved_contexts(MsgType(0)), 1);
+
+        // Two messages being reassembled and one waiting to be received
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0x88, 1, 0]),
+            Disposition::Incomplete
+        );
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0x89, 1, 0]),
+            Disposition::Incomplete
+        );
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0xca, 1, 0]),
+            Disposition::Delivered(pldm.into())
+        );
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0xcb, 1, 0]),
+            Disposition::DroppedContextReserved
+        );
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 10, 0xc8, 0, 0x82, 0x02]),
+            Disposition::Delivered(control.into())
+        );
+
+        // Completing a message doesn't free its context until it is received
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0x58, 1]),
+            Disposition::Delivered(pldm.into())
+        );
+        assert!(router.recv(control).is_some());
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0xcb, 1, 0]),
+            Disposition::DroppedContextReserved
+        );
+        assert!(router.recv(pldm).is_some());
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0xcb, 1, 0]),
+            Disposition::Delivered(pldm.into())
+        );
+
+        router.reserve_contexts(MsgType(0), 0).unwrap();
+        assert_eq!(router.reserved_contexts(MsgType(0)), 0);
+    }
+
+    /// Inbound reassemblies are limited to the configured number of contexts
+    #[test]
+    fn max_reassemblies() {
+        use super::Disposition;
+        use crate::{MAX_REASSEMBLIES, NoHooks, RouterConfig};
+        use mctp::MsgType;
+
+        assert!(RouterConfig::new(Eid(8)).max_reassemblies(0).is_err());
+        assert!(
+            RouterConfig::new(Eid(8))
+                .max_reassemblies(MAX_REASSEMBLIES + 1)
+                .is_err()
+        );
+        let config = RouterConfig::new(Eid(8)).max_reassemblies(2).unwrap();
+        let mut router: Router<_, 4, 4> = Router::new_with_config(config, 0, NullSender, NoHooks);
+        let listener = router.listener(MsgType(1)).unwrap();
+        assert_eq!(router.max_reassemblies(), 2);
+        assert!(router.reserve_contexts(MsgType(0), 3).is_err());
+
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0x88, 1, 0]),
+            Disposition::Incomplete
+        );
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0xc9, 1, 0]),
+            Disposition::Delivered(listener.into())
+        );
+        assert_eq!(router.reassemblies_in_use(), 2);
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0xca, 1, 0]),
+            Disposition::DroppedNoContext
+        );
+        assert_eq!(router.reassemblies_exhausted(), 1);
+
+        router.set_max_reassemblies(3).unwrap();
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0xca, 1, 0]),
+            Disposition::Delivered(listener.into())
+        );
+        router.reserve_contexts(MsgType(0), 2).unwrap();
+        assert!(router.set_max_reassemblies(1).is_err());
+        assert!(router.set_max_reassemblies(0).is_err());
+    }
+
+    /// Requests are reassembled into a listener buffer beyond the limits of the stack
+    #[test]
+    fn reassembly_buffer() {
+        use crate::{EidAcl, REASSEMBLY_BUFFER_TABLE_SIZE};
+        use std::boxed::Box;
+        use std::vec;
+
+        let mut router: Router<_, 8, 4> = Router::new(Eid(8), 0, NullSender);
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+        let other = router.listener(mctp::MsgType(2)).unwrap();
+        let buf = Box::leak(vec![0; 2000].into_boxed_slice());
+        assert!(
+            router
+                .set_reassembly_buffer(listener, buf)
+                .unwrap()
+                .is_none()
+        );
+
+        // 1500 bytes exceed the reassembly buffers of the stack
+        let chunks = [(0x80, 0, 500), (0x00, 1, 500), (0x40, 2, 500)];
+        let mut dispositions = Vec::new();
+        for (i, (flags, seq, len)) in chunks.into_iter().enumerate() {
+            let mut pkt = vec![1, 8, 9, flags | (seq << 4) | 0x09];
+            if i == 0 {
+                pkt.push(1);
+            }
+            pkt.extend(core::iter::repeat_n(i as u8, len));
+            dispositions.push(router.inbound_disposition(&pkt));
+        }
+        assert_eq!(
+            dispositions,
+            [
+                super::Disposition::Incomplete,
+                super::Disposition::Incomplete,
+                super::Disposition::Delivered(listener.into())
+            ]
+        );
+        // The buffer is in use until the message is received
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 10, 0xc8, 1, 0xaa]),
+            super::Disposition::DroppedQueueFull
+        );
+        assert!(router.recv(listener).is_none());
+        let mut received = Vec::new();
+        let info = router.recv_into(listener, &mut received).unwrap().unwrap();
+        assert_eq!(
+            (info.source, info.len, received.len()),
+            (Eid(9), 1500, 1500)
+        );
+        assert_eq!(received.get(1000), Some(&2));
+
+        // Lost packets, oversized messages and denied sources are dropped
+        router.inbound(&[1, 8, 9, 0x89, 1, 0]).unwrap();
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0x29, 0]),
+            super::Disposition::DroppedReassemblyError
+        );
+        let small = router.take_reassembly_buffer(listener).unwrap();
+        let small = small.get_mut(..4).unwrap();
+        router.set_reassembly_buffer(listener, small).unwrap();
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0xc9, 1, 0, 1, 2, 3, 4]),
+            super::Disposition::DroppedTooLarge
+        );
+        router
+            .set_listener_acl(listener, Some(EidAcl::allow(&[Eid(20)])))
+            .unwrap();
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0xc9, 1, 0]),
+            super::Disposition::DroppedAccessDenied
+        );
+        assert!(router.recv_into(listener, &mut received).unwrap().is_none());
+
+        // Other listeners still use the stack
+        router.inbound(&[1, 8, 9, 0xca, 2, 7]).unwrap();
+        assert_eq!(router.recv(other).unwrap().payload, [7]);
+        for i in 1..REASSEMBLY_BUFFER_TABLE_SIZE {
+            let l = router.listener(mctp::MsgType(0x30 + i as u8)).unwrap();
+            router
+                .set_reassembly_buffer(l, Box::leak(Box::new([0; 4])))
+                .unwrap();
+        }
+        let l = router.listener(mctp::MsgType(0x40)).unwrap();
+        assert!(
+            router
+                .set_reassembly_buffer(l, Box::leak(Box::new([0; 4])))
+                .is_err()
+        );
+        router.unbind(listener).unwrap();
+        assert!(
+            router
+                .set_reassembly_buffer(l, Box::leak(Box::new([0; 4])))
+                .is_ok()
+        );
+    }
+
+    /// Pooled messages outlive the router borrow and return their buffer when dropped
+    #[test]
+    fn recv_pooled() {
+        use crate::MessagePool;
+
+        let pool: MessagePool<2, 4> = MessagePool::new();
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, NullSender);
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+        for tag in 0..3 {
+            router.inbound(&[1, 8, 9, 0xc8 | tag, 1, tag, 2]).unwrap();
+        }
+        router.inbound(&[1, 8, 9, 0xcc, 1, 1, 2, 3, 4, 5]).unwrap();
+
+        let first = router.recv_pooled(listener, &pool).unwrap().unwrap();
+        let second = router.recv_pooled(listener, &pool).unwrap().unwrap();
+        assert_eq!(pool.available(), 0);
+        assert!(matches!(
+            router
+                .recv_pooled(listener, &pool)
+                .map_err(|e| e.into_inner()),
+            Err(mctp::Error::NoSpace)
+        ));
+        assert_eq!((&*first, first.info().source), ([0, 2].as_slice(), Eid(9)));
+        assert_eq!(&*second, [1, 2].as_slice());
+        drop(first);
+        assert_eq!(pool.available(), 1);
+
+        // Nothing was consumed while the pool was exhausted
+        let third = router.recv_pooled(listener, &pool).unwrap().unwrap();
+        assert_eq!(&*third, [2, 2].as_slice());
+        drop((second, third));
+        assert!(router.recv_pooled(listener, &pool).is_err());
+        assert!(router.recv_pooled(listener, &pool).unwrap().is_none());
+        assert_eq!(pool.available(), 2);
+    }
+
+    /// A batch reports each handle with delivered messages once
+    #[test]
+    fn inbound_batch() {
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, NullSender);
+        let l1 = router.listener(mctp::MsgType(1)).unwrap();
+        let l2 = router.listener(mctp::MsgType(2)).unwrap();
+
+        let pkts: [&[u8]; 6] = [
+            &[1, 8, 9, 0xc8, 1, 5],
+            &[1, 8, 9, 0x89, 2, 6],
+            &[1, 8, 9, 0x59, 7],
+            &[1, 8, 9, 0xca, 1, 8],
+            &[1, 8, 9, 0xcb, 3, 9],
+            &[1, 8, 9, 0x5c, 0],
+        ];
+        let report = router.inbound_batch(pkts);
+        assert_eq!(report.packets, 6);
+        assert_eq!(report.delivered, 3);
+        assert_eq!(report.incomplete, 1);
+        assert_eq!(report.dropped, 2);
+        assert!(!report.handles_overflowed);
+        let handles: Vec<crate::Handle> = report.handles().collect();
+        assert_eq!(handles, [l1.into(), l2.into()]);
+        assert_eq!(router.recv(l1).unwrap().payload, [5]);
+        assert_eq!(router.recv(l2).unwrap().payload, [6, 7]);
+        assert_eq!(router.recv(l1).unwrap().payload, [8]);
+    }
+
+    /// Requests wait for each attempt as long as their retry policy says
+    #[test]
+    fn retry_policy() {
+        use crate::{Backoff, ExpiryReason, Handle, RetryPolicy};
+
+        static BACKOFF: Backoff = Backoff::new(10, 3);
+        static ONCE: Backoff = Backoff::new(40, 1);
+
+        #[derive(Default)]
+        struct Expiries(Vec<(Handle, ExpiryReason)>);
+
+        impl Hooks for Expiries {
+            fn message_expired(&mut self, handle: Handle, reason: ExpiryReason) {
+                self.0.push((handle, reason));
+            }
+        }
+
+        assert_eq!(
+            (0..4)
+                .map(|a| BACKOFF.timeout_millis(a))
+                .collect::<Vec<_>>(),
+            [10, 20, 40, 80]
+        );
+        assert_eq!(BACKOFF.max_timeout(30).timeout_millis(3), 30);
+        let jittered = BACKOFF.jitter(5);
+        assert!((0..8).all(|a| jittered.jitter_millis(a, 77) <= 5));
+
+        let mut router: Router<_, 4, 4, Expiries> = Router::new_with_config(
+            crate::RouterConfig::new(Eid(8)),
+            0,
+            NullSender,
+            Expiries::default(),
+        );
+        router
+            .set_type_retry_policy(mctp::MsgType(1), Some(&BACKOFF))
+            .unwrap();
+        let req = router.req(Eid(9)).unwrap();
+        let send = |r: &mut Router<_, 4, 4, Expiries>, req| {
+            r.send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[1])
+                .unwrap();
+        };
+
+        send(&mut router, req);
+        assert_eq!(router.update(0).unwrap(), 10);
+        router.update(10).unwrap();
+        send(&mut router, req);
+        assert_eq!(router.update(10).unwrap(), 20);
+        router.update(30).unwrap();
+        send(&mut router, req);
+        router.update(70).unwrap();
+        let reasons: Vec<_> = router.hooks().0.iter().map(|e| e.1).collect();
+        assert_eq!(
+            reasons,
+            [
+                ExpiryReason::RetryDue { attempts: 1 },
+                ExpiryReason::RetryDue { attempts: 2 },
+                ExpiryReason::NoResponse
+            ]
+        );
+
+        // the policy of the handle takes precedence, a new request starts over
+        router.set_retry_policy(req, Some(&ONCE)).unwrap();
+        send(&mut router, req);
+        assert_eq!(router.update(70).unwrap(), 40);
+        router.update(110).unwrap();
+        assert_eq!(
+            router.hooks().0.last(),
+            Some(&(req.into(), ExpiryReason::NoResponse))
+        );
+    }
+
+    /// The update interval is the time until the earliest deadline
+    #[test]
+    fn update_interval() {
+        use crate::{NoHooks, RouterConfig};
+
+        let config = RouterConfig::new(Eid(8)).request_timeout_millis(Some(50));
+        let mut router: Router<_, 4, 4> = Router::new_with_config(config, 0, NullSender, NoHooks);
+        let first = router.req(Eid(9)).unwrap();
+        let second = router.req(Eid(10)).unwrap();
+        assert_eq!(router.update(0).unwrap(), 100);
+
+        router
+            .send(
+                None,
+                mctp::MsgType(1),
+                None,
+                mctp::MsgIC(false),
+                first,
+                &[1],
+            )
+            .unwrap();
+        assert_eq!(router.update(10).unwrap(), 40);
+        router
+            .send(
+                None,
+                mctp::MsgType(1),
+                None,
+                mctp::MsgIC(false),
+                second,
+                &[1],
+            )
+            .unwrap();
+        assert_eq!(router.update(30).unwrap(), 20);
+        // the first request expired, the second one is due next
+        assert_eq!(router.update(50).unwrap(), 10);
+        assert_eq!(router.update(60).unwrap(), 100);
+    }
+
+    /// Messages that time out unread or unanswered are reported to the hooks
+    #[test]
+    fn message_expired() {
+        use crate::{ExpiryReason, Handle, RouterConfig};
+
+        #[derive(Default)]
+        struct Expiries(Vec<(Handle, ExpiryReason)>);
+
+        impl Hooks for Expiries {
+            fn message_expired(&mut self, handle: Handle, reason: ExpiryReason) {
+                self.0.push((handle, reason));
+            }
+        }
+
+        let config = RouterConfig::new(Eid(8)).request_timeout_millis(Some(1000));
+        let mut router: Router<_, 4, 4, Expiries> =
+            Router::new_with_config(config, 0, NullSender, Expiries::default());
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+        let req = router.req(Eid(9)).unwrap();
+
+        router.inbound(&[1, 8, 9, 0xc8, 1, 5]).unwrap();
+        router.update(1000).unwrap();
+        router.inbound(&[1, 8, 9, 0xc9, 1, 6]).unwrap();
+        router
+            .send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[1])
+            .unwrap();
+        assert!(router.hooks().0.is_empty());
+
+        // the first message expires unread, the request without response
+        router.update(6000).unwrap();
+        assert_eq!(router.queued(listener), Some(1));
+        assert_eq!(
+            router.hooks().0,
+            [
+                (req.into(), ExpiryReason::NoResponse),
+                (listener.into(), ExpiryReason::Unread)
+            ]
+        );
+        assert_eq!(router.recv(listener).unwrap().payload, [6]);
+        router.update(20000).unwrap();
+        assert_eq!(router.hooks().0.len(), 2);
+    }
+
+    /// Stalled sends are aborted and their request released in update()
+    #[test]
+    fn tx_watchdog() {
+        use crate::{ExpiryReason, Handle, ManualClock, RouterConfig, Sender};
+
+        /// Takes 30 ms to send each packet, fails while `.2` is set
+        struct SlowSender<'c>(&'c ManualClock, usize, bool);
+
+        impl Sender for SlowSender<'_> {
+            type Packet = [u8; 16];
+
+            fn send_packet(&mut self, _eid: Eid, _pkt: &[u8]) -> mctp::Result<()> {
+                if self.2 {
+                    return Err(mctp::Error::TxFailure);
+                }
+                self.0.advance(30);
+                self.1 += 1;
+                Ok(())
+            }
+
+            fn get_mtu(&self) -> usize {
+                16
+            }
+        }
+
+        #[derive(Default)]
+        struct Expiries(Vec<(Handle, ExpiryReason)>);
+
+        impl Hooks for Expiries {
+            fn message_expired(&mut self, handle: Handle, reason: ExpiryReason) {
+                self.0.push((handle, reason));
+            }
+        }
+
+        let clock = ManualClock::new(0);
+        let config = RouterConfig::new(Eid(8)).tx_timeout_millis(Some(50));
+        let mut router: Router<_, 4, 4, _, _> = Router::new_with_clock(
+            config,
+            &clock,
+            SlowSender(&clock, 0, true),
+            Expiries::default(),
+        );
+        let req = router.req(Eid(9)).unwrap();
+        let send = |router: &mut Router<_, 4, 4, _, _>, payload: &[u8]| {
+            router.send(
+                None,
+                mctp::MsgType(1),
+                None,
+                mctp::MsgIC(false),
+                req,
+                payload,
+            )
+        };
+
+        // the tag of a failed send is released once the timeout passed
+        assert!(send(&mut router, &[1]).is_err());
+        assert!(router.requests().next().unwrap().tag.is_some());
+        clock.set(40);
+        assert_eq!(router.poll().unwrap(), 10);
+        assert!(router.hooks().0.is_empty());
+        clock.set(50);
+        router.poll().unwrap();
+        assert_eq!(router.hooks().0, [(req.into(), ExpiryReason::TxStalled)]);
+        assert_eq!(router.requests().next().unwrap().tag, None);
+
+        // a send taking too long is aborted
+        router.sender_mut().2 = false;
+        let err = send(&mut router, &[0; 100]).unwrap_err();
+        assert!(matches!(err.error(), mctp::Error::TimedOut));
+        assert_eq!(router.sender().1, 2);
+        router.poll().unwrap();
+        assert_eq!(router.hooks().0.len(), 2);
+        assert_eq!(router.requests().next().unwrap().tag, None);
+
+        // completed sends are left alone
+        send(&mut router, &[1]).unwrap();
+        clock.advance(1000);
+        router.poll().unwrap();
+        assert_eq!(router.hooks().0.len(), 2);
+        assert!(router.requests().next().unwrap().tag.is_some());
+    }
+
+    /// Requests and responses built with SendArgs carry the right destination and tag
+    #[test]
+    fn send_msg() {
+        use crate::SendArgs;
+        use crate::test_util::BufferSender;
+
+        let packets = RefCell::new(Vec::new());
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, BufferSender::<64>::new(&packets));
+        let req = router.req(Eid(9)).unwrap();
+        let listener = router.listener(mctp::MsgType(2)).unwrap();
+
+        let report = router
+            .send_msg(req, SendArgs::request(mctp::MsgType(1)).payload(&[5]))
+            .unwrap();
+        let tag = report.tag.tag().0;
+        router
+            .send_msg(
+                listener,
+                SendArgs::response(Eid(10), mctp::MsgType(2), mctp::TagValue(3))
+                    .ic(mctp::MsgIC(true))
+                    .payload_vectored(&[&[6], &[7]]),
+            )
+            .unwrap();
+        let packets = packets.borrow();
+        assert_eq!(packets.first().unwrap(), &[1, 9, 8, 0xc8 | tag, 1, 5]);
+        let response = packets.get(1).unwrap();
+        assert_eq!(response.get(..7).unwrap(), [1, 10, 8, 0xc3, 0x82, 6, 7]);
+    }
+
+    /// Packets are sent again while the receiver is busy, a bounded number of times
+    #[test]
+    fn busy_retry() {
+        use crate::{BusyRetry, Clock, RouterConfig, Sender};
+
+        /// Busy for the next `.0` packets, counts packets accepted in `.1`
+        struct BusySender(usize, usize);
+
+        impl Sender for BusySender {
+            type Packet = [u8; 64];
+
+            fn send_packet(&mut self, _eid: Eid, _pkt: &[u8]) -> mctp::Result<()> {
+                if self.0 > 0 {
+                    self.0 -= 1;
+                    return Err(mctp::Error::TxFailure);
+                }
+                self.1 += 1;
+                Ok(())
+            }
+
+            fn get_mtu(&self) -> usize {
+                64
+            }
+
+            fn is_receiver_busy(&self, err: &mctp::Error) -> bool {
+                matches!(err, mctp::Error::TxFailure)
+            }
+        }
+
+        let config = RouterConfig::new(Eid(8)).busy_retry(Some(BusyRetry::new(5, 2)));
+        let mut router: Router<_, 4, 4> =
+            Router::new_with_config(config, 0, BusySender(2, 0), crate::NoHooks);
+        let req = router.req(Eid(9)).unwrap();
+        let send = |router: &mut Router<BusySender, 4, 4>| {
+            router.send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[1])
+        };
+
+        send(&mut router).unwrap();
+        assert_eq!(router.sender().1, 1);
+        assert_eq!(router.tx_stats().busy_retries, 2);
+        assert_eq!(router.clock().now_millis(), 10);
+
+        // the last retry fails with the error of the sender
+        router.sender_mut().0 = 3;
+        let err = send(&mut router).unwrap_err();
+        assert!(matches!(err.error(), mctp::Error::TxFailure));
+        assert_eq!(router.sender().1, 1);
+        assert_eq!(router.tx_stats().busy_retries, 4);
+
+        // without retries configured, busy receivers fail the send at once
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, BusySender(1, 0));
+        let req = router.req(Eid(9)).unwrap();
+        assert!(
+            router
+                .send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[1])
+                .is_err()
+        );
+        assert_eq!(router.tx_stats().busy_retries, 0);
+    }
+
+    /// The halves of a split request replace its handle until they are released or joined
+    #[test]
+    fn split_request() {
+        use crate::test_util::BufferSender;
+
+        let packets = RefCell::new(Vec::new());
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, BufferSender::<64>::new(&packets));
+        let req = router.req(Eid(9)).unwrap();
+        let (send, recv) = router.split(req).unwrap();
+        assert!(router.split(req).is_err());
+        assert!(
+            router
+                .send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[1])
+                .is_err()
+        );
+        assert!(router.unbind(req).is_err());
+
+        let tag = router
+            .send_half(&send, mctp::MsgType(1), mctp::MsgIC(false), &[&[1]])
+            .unwrap()
+            .tag
+            .tag()
+            .0;
+        router.inbound(&[1, 8, 9, 0xc0 | tag, 1, 2]).unwrap();
+        let mut buf = [0u8; 8];
+        let mut sink = &mut buf[..];
+        let info = router.recv_half_into(&recv, &mut sink).unwrap().unwrap();
+        assert_eq!(info.len, 1);
+        assert_eq!(buf.first(), Some(&2));
+
+        // joined halves give back the plain request
+        let req = router.join(send, recv).unwrap();
+        router
+            .send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[1])
+            .unwrap();
+
+        // without a receiving half, nobody would get the responses
+        let (send, recv) = router.split(req).unwrap();
+        router.release_recv_half(recv).unwrap();
+        assert!(
+            router
+                .send_half(&send, mctp::MsgType(1), mctp::MsgIC(false), &[&[1]])
+                .is_err()
+        );
+        router.release_send_half(send).unwrap();
+        assert!(router.unbind(req).is_err());
+        assert!(router.req(Eid(9)).is_ok());
+    }
+
+    /// Injected faults hit the selected packets only
+    #[test]
+    fn fault_injection() {
+        use crate::test_util::{Fault, FaultInjector, transfer};
+
+        let packets = RefCell::new(Vec::new());
+        let sender = FaultInjector::new(BufferSender::<64>::new(&packets))
+            .inject(1, Fault::Drop)
+            .inject(
+                3,
+                Fault::Corrupt {
+                    offset: 5,
+                    mask: 0xff,
+                },
+            )
+            .inject(4, Fault::Duplicate)
+            .inject(5, Fault::Delay { packets: 1 });
+        let mut requester: Router<_, 1, 1> = Router::new(Eid(8), 0, sender);
+        let mut responder: Router<_, 1, 1> = Router::new(Eid(9), 0, NullSender);
+        let listener = responder.listener(mctp::MsgType(1)).unwrap();
+        let req = requester.req(Eid(9)).unwrap();
+
+        // 100 bytes of payload make up two packets with an MTU of 64
+        let send = |r: &mut Router<_, 1, 1>| {
+            r.send(
+                None,
+                mctp::MsgType(1),
+                None,
+                mctp::MsgIC(false),
+                req,
+                &[7; 100],
+            )
+            .unwrap();
+        };
+        send(&mut requester);
+        assert_eq!(transfer(&packets, &mut responder).unwrap(), 1);
+        assert!(responder.recv(listener).is_none());
+
+        send(&mut requester);
+        transfer(&packets, &mut responder).unwrap();
+        let msg = responder.recv(listener).unwrap();
+        assert_eq!(msg.payload.iter().filter(|b| **b == 0xf8).count(), 1);
+        drop(msg);
+
+        // the first packet is duplicated, the second one delayed behind the next message
+        send(&mut requester);
+        assert_eq!(packets.borrow().len(), 2);
+        send(&mut requester);
+        assert_eq!(packets.borrow().len(), 5);
+        assert_eq!(requester.sender().injected(), 4);
+        assert_eq!(requester.sender().sent(), 8);
+    }
+
+    /// Packets split across several slices are processed like contiguous ones
+    #[test]
+    fn inbound_vectored() {
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, NullSender);
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+
+        let delivered = router.inbound_vectored(&[&[], &[1, 8, 9, 0xc8, 1, 5]]);
+        assert_eq!(delivered.unwrap(), Some(listener.into()));
+        let delivered = router.inbound_vectored(&[&[1, 8], &[], &[9, 0xc9, 1], &[6, 7]]);
+        assert_eq!(delivered.unwrap(), Some(listener.into()));
+        assert_eq!(router.recv(listener).unwrap().payload, [5]);
+        assert_eq!(router.recv(listener).unwrap().payload, [6, 7]);
+
+        let long = [0; crate::test_util::NULL_SENDER_MTU];
+        assert!(router.inbound_vectored(&[&[1, 8, 9, 0xca], &long]).is_err());
+    }
+
+    /// Receive a message into an `embedded_io::Write` sink
+    #[test]
+    fn recv_into_sink() {
+        let packets = RefCell::new(Vec::new());
+        let outbound: BufferSender<255> = BufferSender { packets: &packets };
+        let mut router_a: Router<_, 8, 8> = Router::new(Eid(42), 0, NullSender);
+        let mut router_b: Router<_, 8, 8> = Router::new(Eid(112), 0, outbound);
+
+        let listener = router_a.listener(mctp::MsgType(5)).unwrap();
+        let req = router_b.req(Eid(42)).unwrap();
+        let payload: Vec<u8> = (0..300).map(|x| x as u8).collect();
+        router_b
+            .send(
+                None,
+                mctp::MsgType(5),
+                None,
+                mctp::MsgIC(false),
+                req,
+                &payload,
+            )
+            .unwrap();
+        for pkt in packets.borrow().iter() {
+            router_a.inbound(pkt).unwrap();
+        }
+
+        let mut buf = [0u8; 512];
+        let mut sink = &mut buf[..];
+        let info = router_a.recv_into(listener, &mut sink).unwrap().unwrap();
+        assert_eq!(sink.len(), 512 - 300);
+        assert_eq!(info.len, 300);
+        assert_eq!(info.source, Eid(112));
+        assert_eq!(info.typ, mctp::MsgType(5));
+        assert_eq!(&buf[..300], payload.as_slice());
+
+        // the message was consumed
+        let mut sink = &mut buf[..];
+        assert!(router_a.recv_into(listener, &mut sink).unwrap().is_none());
+
+        // a sink that is too small fails
+        packets.borrow_mut().clear();
+        router_b
+            .send(
+                None,
+                mctp::MsgType(5),
+                None,
+                mctp::MsgIC(false),
+                req,
+                &payload,
+            )
+            .unwrap();
+        for pkt in packets.borrow().iter() {
+            router_a.inbound(pkt).unwrap();
+        }
+        let mut small = [0u8; 100];
+        let mut sink = &mut small[..];
+        assert!(router_a.recv_into(listener, &mut sink).is_err());
+    }
+
+    /// Packets are classified by `inbound_disposition()`
+    #[test]
+    fn inbound_dispositions() {
+        let packets = RefCell::new(Vec::new());
+        let outbound: BufferSender<255> = BufferSender { packets: &packets };
+        let mut router_a: Router<_, 8, 8> = Router::new(Eid(42), 0, NullSender);
+        let mut router_b: Router<_, 8, 8> = Router::new(Eid(112), 0, outbound);
+
+        let req = router_b.req(Eid(42)).unwrap();
+        router_b
+            .send(
+                None,
+                mctp::MsgType(1),
+                None,
+                mctp::MsgIC(false),
+                req,
+                &[0; 300],
+            )
+            .unwrap();
+        let pkts = packets.borrow().clone();
+        let [first, last] = pkts.as_slice() else {
+            panic!("expected two packets");
+        };
+        assert_eq!(
+            router_a.inbound_disposition(first),
+            super::Disposition::Incomplete
+        );
+        assert_eq!(
+            router_a.inbound_disposition(last),
+            super::Disposition::DroppedNoListener
+        );
+
+        let listener = router_a.listener(mctp::MsgType(1)).unwrap();
+        router_a.inbound_disposition(first);
+        assert_eq!(
+            router_a.inbound_disposition(last),
+            super::Disposition::Delivered(listener.into())
+        );
+
+        router_a.set_eid(Eid(43)).unwrap();
+        router_a.inbound_disposition(first);
+        let disposition = router_a.inbound_disposition(last);
+        assert_eq!(disposition, super::Disposition::DroppedWrongEid);
+        assert!(disposition.is_dropped());
+    }
+
+    #[derive(Default)]
+    struct CaptureHooks {
+        packets: Vec<(Direction, u64, Vec<u8>)>,
+    }
+
+    impl Hooks for CaptureHooks {
+        fn capture(&mut self, direction: Direction, now_millis: u64, pkt: &[u8]) {
+            self.packets.push((direction, now_millis, pkt.into()));
+        }
+    }
+
+    /// The capture hook sees inbound and outbound packets
+    #[test]
+    fn capture_hook() {
+        let packets = RefCell::new(Vec::new());
+        let outbound: BufferSender<255> = BufferSender { packets: &packets };
+        let mut router: Router<_, 8, 8, _> =
+            Router::new_with_hooks(Eid(42), 10, outbound, CaptureHooks::default());
+
+        let req = router.req(Eid(112)).unwrap();
+        router
+            .send(
+                None,
+                mctp::MsgType(1),
+                None,
+                mctp::MsgIC(false),
+                req,
+                &[0; 300],
+            )
+            .unwrap();
+        router.update(20).unwrap();
+        router.inbound(&[1, 42, 112, 0xc0, 1, 0]).unwrap();
+
+        let captured = &router.hooks().packets;
+        assert_eq!(captured.len(), 3);
+        assert!(captured.iter().take(2).zip(packets.borrow().iter()).all(
+            |((dir, now, pkt), sent)| *dir == Direction::Outbound && *now == 10 && pkt == sent
+        ));
+        assert_eq!(
+            captured.last(),
+            Some(&(Direction::Inbound, 20, vec![1, 42, 112, 0xc0, 1, 0]))
+        );
+    }
+
+    /// Listener ACLs drop requests from rejected sources
+    #[test]
+    fn listener_acl() {
+        let mut router: Router<_, 8, 8> = Router::new(Eid(42), 0, NullSender);
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+        router
+            .set_listener_acl(listener, Some(crate::EidAcl::allow(&[Eid(8)])))
+            .unwrap();
+
+        let request = |src| [1, 42, src, 0xc8, 1, 0];
+        assert_eq!(
+            router.inbound_disposition(&request(9)),
+            super::Disposition::DroppedAccessDenied
+        );
+        assert_eq!(
+            router.inbound_disposition(&request(8)),
+            super::Disposition::Delivered(listener.into())
+        );
+        assert_eq!(router.listener_denied(listener), Some(1));
+
+        let mut acl = crate::EidAcl::deny(&[Eid(8), Eid(200)]);
+        acl.remove(Eid(200));
+        assert!(!acl.permits(Eid(8)));
+        assert!(acl.permits(Eid(200)));
+        router.set_listener_acl(listener, Some(acl)).unwrap();
+        assert!(router.inbound_disposition(&request(8)).is_dropped());
+        assert_eq!(router.listener_denied(listener), Some(2));
+
+        router.unbind(listener).unwrap();
+        assert!(router.set_listener_acl(listener, None).is_err());
+        assert_eq!(router.listener_denied(listener), None);
+    }
+
+    /// The catch-all listener receives requests of types without a listener
+    #[test]
+    fn catch_all_listener() {
+        let mut router: Router<_, 8, 8> = Router::new(Eid(42), 0, NullSender);
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+        let catch_all = router.catch_all_listener().unwrap();
+        assert!(matches!(
+            router.catch_all_listener(),
+            Err(mctp::Error::AddrInUse)
+        ));
+        assert_eq!(
+            router.listeners().collect::<Vec<_>>(),
+            [(listener, mctp::MsgType(1))]
+        );
+
+        let request = |typ| [1, 42, 8, 0xc8, typ, 0];
+        assert_eq!(
+            router.inbound_disposition(&request(1)),
+            super::Disposition::Delivered(listener.into())
+        );
+        assert_eq!(
+            router.inbound_disposition(&request(0x7e)),
+            super::Disposition::Delivered(catch_all.into())
+        );
+        let mut buf = [0u8; 8];
+        let mut sink = &mut buf[..];
+        let info = router.recv_into(catch_all, &mut sink).unwrap().unwrap();
+        assert_eq!(info.typ, mctp::MsgType(0x7e));
+
+        // responses without a request are not caught
+        assert_eq!(
+            router.inbound_disposition(&[1, 42, 8, 0xc0, 0x7e, 0]),
+            super::Disposition::DroppedNoRequest
+        );
+        assert!(
+            router
+                .set_reassembly_buffer(catch_all, Vec::leak(vec![0; 16]))
+                .is_err()
+        );
+
+        router.unbind(catch_all).unwrap();
+        assert_eq!(
+            router.inbound_disposition(&request(0x7e)),
+            super::Disposition::DroppedNoListener
+        );
+    }
+
+    /// Requests larger than the listener accepts are aborted early
+    #[test]
+    fn listener_max_size() {
+        let packets = RefCell::new(Vec::new());
+        let outbound: BufferSender<64> = BufferSender { packets: &packets };
+        let mut router_a: Router<_, 8, 8> = Router::new(Eid(42), 0, NullSender);
+        let mut router_b: Router<_, 8, 8> = Router::new(Eid(112), 0, outbound);
+        let listener = router_a.listener(mctp::MsgType(1)).unwrap();
+        router_a.set_listener_max_size(listener, Some(100)).unwrap();
+
+        let mut send = |len| {
+            packets.borrow_mut().clear();
+            let req = router_b.req(Eid(42)).unwrap();
+            router_b
+                .send(
+                    None,
+                    mctp::MsgType(1),
+                    None,
+                    mctp::MsgIC(false),
+                    req,
+                    &vec![0; len],
+                )
+                .unwrap();
+            router_b.unbind(req).unwrap();
+            packets.borrow().clone()
+        };
+
+        // every oversized request is cut off at the packet exceeding the limit, the stack
+        // holds its reassembly context until it times out
+        for i in 1..=8 {
+            let dispositions: Vec<_> = send(200)
+                .iter()
+                .map(|pkt| router_a.inbound_disposition(pkt))
+                .collect();
+            assert_eq!(
+                dispositions,
+                [
+                    super::Disposition::Incomplete,
+                    super::Disposition::DroppedTooLarge,
+                    super::Disposition::DroppedTooLarge,
+                    super::Disposition::DroppedTooLarge,
+                ]
+            );
+            assert_eq!(router_a.reassemblies_in_use(), 1);
+            router_a
+                .update(i * super::REASSEMBLY_TIMEOUT_MILLIS)
+                .unwrap();
+            assert_eq!(router_a.reassemblies_in_use(), 0);
+        }
+
+        // reassembly contexts were released
+        let dispositions: Vec<_> = send(100)
+            .iter()
+            .map(|pkt| router_a.inbound_disposition(pkt))
+            .collect();
+        assert_eq!(
+            dispositions.last(),
+            Some(&super::Disposition::Delivered(listener.into()))
+        );
+    }
+
+    /// Receive queues are bounded according to their overflow policy
+    #[test]
+    fn queue_limit() {
+        use crate::{OverflowPolicy, QueueLimit};
+
+        let mut router: Router<_, 8, 8> = Router::new(Eid(42), 0, NullSender);
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+        let request = |tag: u8, payload| [1, 42, 8, 0xc8 | tag, 1, payload];
+        let limit = |policy| Some(QueueLimit { depth: 2, policy });
+
+        router
+            .set_queue_limit(listener, limit(OverflowPolicy::DropNewest))
+            .unwrap();
+        assert!(router.inbound(&request(0, 1)).unwrap().is_some());
+        assert!(router.inbound(&request(1, 2)).unwrap().is_some());
+        assert_eq!(
+            router.inbound_disposition(&request(2, 3)),
+            super::Disposition::DroppedQueueFull
+        );
+        assert_eq!(router.queued(listener), Some(2));
+
+        router
+            .set_queue_limit(listener, limit(OverflowPolicy::DropOldest))
+            .unwrap();
+        assert!(router.inbound(&request(3, 4)).unwrap().is_some());
+        let mut received = Vec::new();
+        let count = router
+            .recv_all(listener, |msg| received.extend_from_slice(msg.payload))
+            .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(received, vec![2, 4]);
+        assert_eq!(router.queued(listener), Some(0));
+
+        router
+            .set_queue_limit(listener, limit(OverflowPolicy::Reject))
+            .unwrap();
+        assert!(router.inbound(&request(4, 5)).is_ok());
+        assert!(router.inbound(&request(5, 6)).is_ok());
+        assert!(matches!(
+            router.inbound(&request(6, 7)),
+            Err(mctp::Error::NoSpace)
+        ));
+
+        router.set_queue_limit(listener, None).unwrap();
+        assert!(router.inbound(&request(7, 8)).unwrap().is_some());
+        assert_eq!(router.queued(listener), Some(3));
+
+        router.unbind(listener).unwrap();
+        assert!(router.recv_all(listener, |_| ()).is_err());
+    }
+
+    /// Messages with the IC bit set carry a CRC-32C that is checked on receive
+    #[test]
+    fn integrity_check() {
+        let packets = RefCell::new(Vec::new());
+        let outbound: BufferSender<64> = BufferSender { packets: &packets };
+        let mut router_a: Router<_, 8, 8> = Router::new(Eid(42), 0, NullSender);
+        let mut router_b: Router<_, 8, 8> = Router::new(Eid(112), 0, outbound);
+        let listener = router_a.listener(mctp::MsgType(1)).unwrap();
+        let req = router_b.req(Eid(42)).unwrap();
+
+        let payload: Vec<u8> = (0..100).collect();
+        let (head, tail) = payload.split_at(30);
+        router_b
+            .send_vectored(
+                None,
+                mctp::MsgType(1),
+                None,
+                mctp::MsgIC(true),
+                req,
+                &[head, tail],
+            )
+            .unwrap();
+        let mut pkts = packets.borrow().clone();
+        for pkt in pkts.iter() {
+            router_a.inbound(pkt).unwrap();
+        }
+        let mut received = Vec::new();
+        let info = router_a
+            .recv_into(listener, &mut received)
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.len, 100);
+        assert_eq!(received, payload);
+
+        // a corrupted message is dropped
+        if let Some(byte) = pkts.first_mut().and_then(|p| p.last_mut()) {
+            *byte ^= 1;
+        }
+        let dispositions: Vec<_> = pkts
+            .iter()
+            .map(|pkt| router_a.inbound_disposition(pkt))
+            .collect();
+        assert_eq!(
+            dispositions.last(),
+            Some(&super::Disposition::DroppedIntegrityError)
+        );
+
+        // too many buffers to append the check
+        let bufs = [&[0u8][..]; crate::MAX_IC_BUFS + 1];
+        let err = router_b
+            .send_vectored(None, mctp::MsgType(1), None, mctp::MsgIC(true), req, &bufs)
+            .unwrap_err();
+        assert!(matches!(err.error(), mctp::Error::BadArgument));
+    }
+
+    /// "Encrypts" secured messages by inverting the bytes
+    struct InvertCrypto;
+
+    impl Hooks for InvertCrypto {
+        fn encrypt(
+            &mut self,
+            _eid: Eid,
+            _session_id: u32,
+            typ: mctp::MsgType,
+            bufs: &[&[u8]],
+            out: &mut [u8],
+        ) -> mctp::Result<usize> {
+            let plain = core::iter::once(&typ.0).chain(bufs.iter().copied().flatten());
+            let mut len = 0;
+            for (o, p) in out.iter_mut().zip(plain) {
+                *o = !p;
+                len += 1;
+            }
+            Ok(len)
+        }
+
+        fn decrypt(
+            &mut self,
+            _eid: Eid,
+            _session_id: u32,
+            secured: &[u8],
+            out: &mut [u8],
+        ) -> mctp::Result<(mctp::MsgType, usize)> {
+            let Some((typ, data)) = secured.split_first() else {
+                return Err(mctp::Error::InvalidInput);
+            };
+            for (o, s) in out.iter_mut().zip(data) {
+                *o = !s;
+            }
+            Ok((mctp::MsgType(!typ), data.len()))
+        }
+    }
+
+    /// Secured messages are wrapped with the session ID and decrypted by the hooks
+    #[test]
+    fn secured_messages() {
+        let packets = RefCell::new(Vec::new());
+        let outbound: BufferSender<255> = BufferSender { packets: &packets };
+        let mut router_a: Router<_, 8, 8, InvertCrypto> =
+            Router::new_with_hooks(Eid(42), 0, NullSender, InvertCrypto);
+        let mut router_b: Router<_, 8, 8, InvertCrypto> =
+            Router::new_with_hooks(Eid(112), 0, outbound, InvertCrypto);
+        let listener = router_a.listener(crate::secured::MSG_TYPE_SECURED).unwrap();
+        let req = router_b.req(Eid(42)).unwrap();
+        let mut scratch = [0; 64];
+
+        // sessions have to be open on both sides
+        assert!(
+            router_b
+                .send_secured(
+                    None,
+                    7,
+                    mctp::MsgType(5),
+                    None,
+                    req,
+                    &[&[1, 2, 3]],
+                    &mut scratch
+                )
+                .is_err()
+        );
+        router_b.open_session(Eid(42), 7).unwrap();
+        let mut send = |router_b: &mut Router<_, 8, 8, InvertCrypto>| {
+            packets.borrow_mut().clear();
+            router_b
+                .send_secured(
+                    None,
+                    7,
+                    mctp::MsgType(5),
+                    None,
+                    req,
+                    &[&[1, 2], &[3]],
+                    &mut scratch,
+                )
+                .unwrap();
+            packets.borrow().clone()
+        };
+        for pkt in send(&mut router_b).iter() {
+            assert_eq!(
+                router_a.inbound_disposition(pkt),
+                super::Disposition::DroppedNoSession
+            );
+        }
+
+        router_a.open_session(Eid(112), 7).unwrap();
+        assert!(matches!(
+            router_a.open_session(Eid(112), 7),
+            Err(mctp::Error::AddrInUse)
+        ));
+        for pkt in send(&mut router_b).iter() {
+            router_a.inbound(pkt).unwrap();
+        }
+        let mut out = [0; 16];
+        let info = router_a.recv_secured(listener, &mut out).unwrap().unwrap();
+        assert_eq!(info.session_id, 7);
+        assert_eq!(info.typ, mctp::MsgType(5));
+        assert_eq!(out.get(..info.len), Some(&[1, 2, 3][..]));
+
+        assert!(router_a.close_session(Eid(112), 7));
+        assert!(!router_a.close_session(Eid(112), 7));
+    }
+
+    /// SPDM transport messages are exchanged through the device IO adapters
+    #[test]
+    fn spdm_transport() {
+        use crate::spdm::{MSG_TYPE_SPDM, SpdmRequester, SpdmResponder, decap, encap};
+
+        let buf_out_a = RefCell::new(Vec::new());
+        let outbound_a: BufferSender<255> = BufferSender {
+            packets: &buf_out_a,
+        };
+        let buf_out_b = RefCell::new(Vec::new());
+        let outbound_b: BufferSender<255> = BufferSender {
+            packets: &buf_out_b,
+        };
+        let router_a: Router<_, 4, 4> = Router::new(Eid(42), 0, outbound_a);
+        let router_b: Router<_, 4, 4> = Router::new(Eid(112), 0, outbound_b);
+        let router_a = crate::shared::SharedRouter::new(router_a);
+        let router_b = crate::shared::SharedRouter::new(router_b);
+
+        let mut responder = SpdmResponder::new(router_a.listener(MSG_TYPE_SPDM).unwrap(), None);
+        let mut requester = SpdmRequester::new(router_b.req(Eid(42)).unwrap());
+        let mut transport = [0; 64];
+        let mut spdm = [0; 64];
+
+        // GET_VERSION
+        let len = encap(&[0x10, 0x84, 0, 0], &mut transport, false).unwrap();
+        requester.send(transport.get(..len).unwrap()).unwrap();
+        for pkt in buf_out_b.borrow().iter() {
+            router_a.inbound(pkt).unwrap();
+        }
+        assert!(matches!(
+            responder.send(transport.get(..len).unwrap()),
+            Err(mctp::Error::BadArgument)
+        ));
+
+        let len = responder.receive(&mut transport).unwrap().unwrap();
+        let (len, secured) = decap(transport.get(..len).unwrap(), &mut spdm).unwrap();
+        assert!(!secured);
+        assert_eq!(spdm.get(..len), Some([0x10, 0x84, 0, 0].as_slice()));
+
+        // VERSION
+        let len = encap(&[0x10, 0x04, 0, 0], &mut transport, false).unwrap();
+        responder.send(transport.get(..len).unwrap()).unwrap();
+        for pkt in buf_out_a.borrow().iter() {
+            router_b.inbound(pkt).unwrap();
+        }
+        let len = requester.receive(&mut transport).unwrap().unwrap();
+        assert_eq!(
+            transport.get(..len),
+            Some([MSG_TYPE_SPDM.0, 0x10, 0x04, 0, 0].as_slice())
+        );
+        assert!(requester.receive(&mut transport).unwrap().is_none());
+        assert!(decap(&[0x01, 0], &mut spdm).is_err());
+    }
+
+    /// PLDM responses are correlated with their request by instance ID
+    #[test]
+    fn pldm_transport() {
+        use crate::pldm::{MSG_TYPE_PLDM, PldmRequester, PldmResponder};
+
+        let buf_out_a = RefCell::new(Vec::new());
+        let outbound_a: BufferSender<255> = BufferSender {
+            packets: &buf_out_a,
+        };
+        let buf_out_b = RefCell::new(Vec::new());
+        let outbound_b: BufferSender<255> = BufferSender {
+            packets: &buf_out_b,
+        };
+        let router_a: Router<_, 4, 4> = Router::new(Eid(42), 0, outbound_a);
+        let router_b: Router<_, 4, 4> = Router::new(Eid(112), 0, outbound_b);
+        let router_a = crate::shared::SharedRouter::new(router_a);
+        let router_b = crate::shared::SharedRouter::new(router_b);
+        let transfer = |from: &RefCell<Vec<Vec<u8>>>,
+                        to: &crate::shared::SharedRouter<_, _, _, _>| {
+            for pkt in from.borrow_mut().drain(..) {
+                to.inbound(&pkt).unwrap();
+            }
+        };
+
+        let mut responder = PldmResponder::new(router_a.listener(MSG_TYPE_PLDM).unwrap());
+        let mut requester = PldmRequester::new(router_b.req(Eid(42)).unwrap());
+        let mut buf = [0; 64];
+
+        // GetTID, the first request is abandoned
+        let first = requester.send(0, 0x02, &[]).unwrap();
+        let second = requester.send(0, 0x02, &[]).unwrap();
+        assert_eq!((first.instance_id, second.instance_id), (0, 1));
+        transfer(&buf_out_b, &router_a);
+
+        for _ in 0..2 {
+            let request = responder.receive(&mut buf).unwrap().unwrap();
+            assert!(request.header.request);
+            responder.respond(&request, &[0x00, 0x08]).unwrap();
+        }
+        assert!(responder.receive(&mut buf).unwrap().is_none());
+        transfer(&buf_out_a, &router_b);
+
+        let (header, len) = requester.receive(&mut buf).unwrap().unwrap();
+        assert_eq!(header, second.response());
+        assert_eq!(
+            buf.get(..len),
+            Some([0x01, 0x00, 0x02, 0x00, 0x08].as_slice())
+        );
+        assert!(matches!(
+            requester.receive(&mut buf),
+            Err(mctp::Error::BadArgument)
+        ));
+    }
+
+    /// Out-of-spec packets are counted and dropped or accepted per class
+    #[test]
+    fn validation() {
+        use crate::{Validation, Violation};
+
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, NullSender);
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+        let reserved_bits = [0x31, 8, 20, 0xc8, 1, 0xaa];
+        let reserved_source = [1, 8, 3, 0xc8, 1, 0xbb];
+
+        // Lenient by default, reserved bits are ignored
+        assert_eq!(
+            router.inbound_disposition(&reserved_bits),
+            super::Disposition::Delivered(listener.into())
+        );
+        assert_eq!(
+            router.inbound_disposition(&reserved_source),
+            super::Disposition::Delivered(listener.into())
+        );
+
+        router.set_validation(Validation::strict().reject(Violation::InvalidSourceEid, false));
+        assert_eq!(
+            router.inbound_disposition(&reserved_bits),
+            super::Disposition::DroppedInvalid(Violation::ReservedHeaderBits)
+        );
+        assert_eq!(
+            router.inbound_disposition(&reserved_source),
+            super::Disposition::Delivered(listener.into())
+        );
+        assert_eq!(
+            router.inbound_disposition(&[1, 5, 20, 0xc8, 1, 0xcc]),
+            super::Disposition::DroppedInvalid(Violation::ReservedDestEid)
+        );
+
+        assert_eq!(router.violations(Violation::ReservedHeaderBits), 2);
+        assert_eq!(router.violations(Violation::InvalidSourceEid), 2);
+        assert_eq!(router.violations(Violation::ReservedDestEid), 1);
+    }
+
+    /// Configurations load from JSON, snapshots round trip
+    /// Message lifecycles are reported as `tracing` events
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn tracing_events() {
+        use std::string::{String, ToString};
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        use crate::{NoHooks, RouterConfig};
+
+        #[derive(Clone, Default)]
+        struct Collector(Arc<Mutex<Vec<String>>>);
+
+        struct Message<'a>(&'a mut String);
+
+        impl Visit for Message<'_> {
+            fn record_debug(&mut self, field: &Field, value: &dyn core::fmt::Debug) {
+                if field.name() == "message" {
+                    *self.0 = format!("{value:?}");
+                }
+            }
+        }
+
+        impl Subscriber for Collector {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, _: &Attributes<'_>) -> Id {
+                Id::from_u64(1)
+            }
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, event: &Event<'_>) {
+                let mut message = String::new();
+                event.record(&mut Message(&mut message));
+                self.0.lock().unwrap().push(message);
+            }
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let collector = Collector::default();
+        tracing::subscriber::with_default(collector.clone(), || {
+            let mut router: Router<_, 4, 4> = Router::new_with_config(
+                RouterConfig::new(Eid(8)).request_timeout_millis(Some(1000)),
+                0,
+                NullSender,
+                NoHooks,
+            );
+            let req = router.req(Eid(9)).unwrap();
+            let report = router
+                .send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[1])
+                .unwrap();
+            let tag = report.tag.tag().0;
+            router.inbound(&[1, 8, 9, 0xc0 | tag, 1, 2]).unwrap();
+            router
+                .send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[1])
+                .unwrap();
+            router.update(2000).unwrap();
+        });
+        let events = collector.0.lock().unwrap().clone();
+        let expected = [
+            "request tag allocated",
+            "fragment sent",
+            "response matched",
+            "request tag allocated",
+            "fragment sent",
+            "request expired",
+        ];
+        assert_eq!(
+            events,
+            expected.iter().map(|e| e.to_string()).collect::<Vec<_>>()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        use crate::{RateLimit, RouterConfig, RouterSnapshot, Violation};
+
+        let config: RouterConfig = serde_json::from_str(
+            r#"{
+                "own_eid": 42,
+                "mtus": [{ "eid": 112, "mtu": 68 }],
+                "rate_limit": { "burst": 8, "packets_per_sec": 100 },
+                "validation": { "reserved_dest_eid": true }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.rate_limit, Some(RateLimit::new(8, 100)));
+        assert!(config.validation.rejects(Violation::ReservedDestEid));
+        assert!(!config.validation.rejects(Violation::ReservedHeaderBits));
+        let router: Router<_, 4, 4> =
+            Router::new_with_config(config, 0, NullSender, crate::NoHooks);
+        assert_eq!(router.get_eid(), Eid(42));
+        assert_eq!(router.mtu(Eid(112)), 68);
+
+        let snapshot = router.snapshot();
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let restored: RouterSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, snapshot);
+
+        assert!(
+            serde_json::from_str::<RouterConfig>(
+                r#"{ "own_eid": 8, "mtus": [{ "eid": 9, "mtu": 4 }] }"#
+            )
+            .is_err()
+        );
+        assert!(
+            serde_json::from_str::<RouterConfig>(r#"{ "own_eid": 8, "reorder_window": 3 }"#)
+                .is_err()
+        );
+        assert!(serde_json::from_str::<RouterConfig>(r#"{ "mtu_discovery": true }"#).is_err());
+    }
+
+    /// Handles with waiting messages are reported as ready
+    #[test]
+    fn poll_ready() {
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, NullSender);
+        let listener_a = router.listener(mctp::MsgType(1)).unwrap();
+        let listener_b = router.listener(mctp::MsgType(2)).unwrap();
+        let req = router.req(Eid(20)).unwrap();
+        let tag = router
+            .send(None, mctp::MsgType(3), None, mctp::MsgIC(false), req, &[1])
+            .unwrap()
+            .tag;
+        assert_eq!(router.poll_ready().count(), 0);
+
+        router.inbound(&[1, 8, 20, 0xc8, 2, 0xaa]).unwrap();
+        router
+            .inbound(&[1, 8, 20, 0xc0 | tag.tag().0, 3, 0xbb])
+            .unwrap();
+        router.inbound(&[1, 8, 21, 0xc9, 2, 0xcc]).unwrap();
+        let ready: Vec<crate::Handle> = router.poll_ready().collect();
+        assert_eq!(ready, [listener_b.into(), req.into()]);
+
+        assert!(router.recv(req).is_some());
+        assert!(router.recv(listener_b).is_some());
+        let ready: Vec<crate::Handle> = router.poll_ready().collect();
+        assert_eq!(ready, [listener_b.into()]);
+        assert!(router.recv(listener_b).is_some());
+        assert_eq!(router.poll_ready().count(), 0);
+        assert!(router.recv(listener_a).is_none());
+    }
+
+    /// Messages are fragmented without staging the whole payload
+    #[test]
+    fn tx_stats() {
+        let packets = RefCell::new(Vec::new());
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, BufferSender::<64>::new(&packets));
+        let req = router.req(Eid(9)).unwrap();
+        let report = router
+            .send_vectored(
+                None,
+                mctp::MsgType(1),
+                None,
+                mctp::MsgIC(false),
+                req,
+                &[&[1; 100], &[2; 100]],
+            )
+            .unwrap();
+        assert_eq!((report.packets, report.bytes), (4, 4 * 4 + 1 + 200));
+        assert!(!report.queued);
+        let stats = router.tx_stats();
+        assert_eq!(stats.messages, 1);
+        assert_eq!(stats.packets, 4);
+        // Every byte is copied once: 4 headers, the message type and the payload
+        assert_eq!(stats.copied_bytes, 4 * 4 + 1 + 200);
+        assert_eq!(
+            stats.copied_bytes,
+            packets.borrow().iter().map(|p| p.len()).sum::<usize>()
+        );
+    }
+
+    /// Continuation packets arriving slightly early are held back until they are due
+    #[test]
+    fn reorder_window() {
+        let packets = RefCell::new(Vec::new());
+        let mut sender: Router<_, 4, 4> =
+            Router::new(Eid(20), 0, BufferSender::<64>::new(&packets));
+        let req = sender.req(Eid(8)).unwrap();
+        sender
+            .send(
+                None,
+                mctp::MsgType(1),
+                None,
+                mctp::MsgIC(false),
+                req,
+                &[7; 200],
+            )
+            .unwrap();
+        let pkts = packets.take();
+        let [p0, p1, p2, p3] = pkts.as_slice() else {
+            panic!("expected 4 packets, got {}", pkts.len());
+        };
+
+        // Without a window, an early packet aborts the message
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, NullSender);
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+        assert_eq!(router.inbound(p0).unwrap(), None);
+        assert!(router.inbound(p2).is_err());
+
+        assert!(
+            router
+                .set_reorder_window(crate::MAX_REORDER_WINDOW + 1)
+                .is_err()
+        );
+        router.set_reorder_window(1).unwrap();
+        assert_eq!(
+            router.inbound_disposition(p0),
+            super::Disposition::Incomplete
+        );
+        assert_eq!(
+            router.inbound_disposition(p2),
+            super::Disposition::Incomplete
+        );
+        assert_eq!(
+            router.inbound_disposition(p1),
+            super::Disposition::Incomplete
+        );
+        assert_eq!(
+            router.inbound_disposition(p3),
+            super::Disposition::Delivered(listener.into())
+        );
+        assert_eq!(router.recv(listener).unwrap().payload, [7; 200]);
+
+        // The last packet is passed on together with the ones before it
+        let config = crate::RouterConfig::new(Eid(8)).reorder_window(2).unwrap();
+        let mut router: Router<_, 4, 4> =
+            Router::new_with_config(config, 0, NullSender, crate::NoHooks);
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+        for pkt in [p0, p3, p2] {
+            assert_eq!(
+                router.inbound_disposition(pkt),
+                super::Disposition::Incomplete
+            );
+        }
+        assert_eq!(
+            router.inbound_disposition(p1),
+            super::Disposition::Delivered(listener.into())
+        );
+        assert!(crate::RouterConfig::new(Eid(8)).reorder_window(3).is_err());
+    }
+
+    /// A quiesced router doesn't send, but completes outstanding requests
+    #[test]
+    fn quiesce() {
+        let packets = RefCell::new(Vec::new());
+        let outbound: BufferSender<255> = BufferSender { packets: &packets };
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, outbound);
+        router.set_forwarding(true);
+        let req = router.req(Eid(20)).unwrap();
+        assert!(router.is_idle());
+        let tag = router
+            .send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[1])
+            .unwrap()
+            .tag;
+
+        assert!(!router.quiesce());
+        assert!(router.is_quiesced());
+        assert!(
+            router
+                .send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[2])
+                .is_err()
+        );
+        assert_eq!(
+            router.inbound_disposition(&[1, 30, 20, 0xc8, 1, 0xaa]),
+            super::Disposition::DroppedQuiesced
+        );
+        assert_eq!(packets.borrow().len(), 1);
+
+        router
+            .inbound(&[1, 8, 20, 0xc0 | tag.tag().0, 1, 0xbb])
+            .unwrap();
+        assert!(router.is_idle());
+
+        router.resume();
+        assert!(
+            router
+                .send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[3])
+                .is_ok()
+        );
+        assert_eq!(packets.borrow().len(), 2);
+    }
+
+    /// Router state survives a snapshot round trip
+    #[test]
+    fn snapshot_restore() {
+        use crate::control::{CMD_SET_ENDPOINT_ID, SetEidDecision, SetEidRequest};
+        use crate::{NextHop, Route, RouterConfig, RouterSnapshot, SNAPSHOT_MAX_LEN};
+
+        struct AcceptEid;
+
+        impl Hooks for AcceptEid {
+            fn set_eid_request(&mut self, _request: &SetEidRequest) -> SetEidDecision {
+                SetEidDecision::Accept
+            }
+        }
+
+        let config = RouterConfig::new(Eid(8))
+            .uuid([1; 16])
+            .mtu(Eid(20), 68)
+            .unwrap()
+            .mtu_discovery(true);
+        let mut router: Router<_, 4, 4> =
+            Router::new_with_config(config, 0, NullSender, crate::NoHooks);
+        router.set_eid(Eid(9)).unwrap();
+        router.set_network_id(Some([2; 16]));
+        router.add_route(20..=29, 1, 0x1d).unwrap();
+        let backup = NextHop {
+            port: 2,
+            physical_addr: 0x1234_5678_9abc,
+        };
+        assert!(router.set_route_backup(20..=29, Some(backup)));
+        router.add_route(40..=40, 0, 0x2e).unwrap();
+        router.listener(mctp::MsgType(1)).unwrap();
+        // A non-final packet from 30 teaches its MTU
+        let mut pkt = vec![0; 100];
+        pkt.splice(..5, [1, 9, 30, 0x88, 1]);
+        router.inbound(&pkt).unwrap();
+
+        let mut buf = [0; SNAPSHOT_MAX_LEN];
+        let len = router.snapshot().to_bytes(&mut buf).unwrap();
+        let snapshot = RouterSnapshot::from_bytes(buf.get(..len).unwrap()).unwrap();
+        assert_eq!(snapshot, router.snapshot());
+        assert_eq!(
+            (snapshot.own_eid(), snapshot.static_eid()),
+            (Eid(9), Eid(8))
+        );
+        assert_eq!(
+            snapshot.routes().collect::<Vec<_>>(),
+            [
+                Route::new(20..=29, 1, 0x1d)
+                    .unwrap()
+                    .with_backup(Some(backup)),
+                Route::new(40..=40, 0, 0x2e).unwrap(),
+            ]
+        );
+        assert!(RouterSnapshot::from_bytes(buf.get(..len - 1).unwrap()).is_err());
+        assert!(router.snapshot().to_bytes(&mut [0; 8]).is_err());
+
+        let mut restored: Router<_, 4, 4, _> =
+            Router::new_with_config(RouterConfig::restore(&snapshot), 0, NullSender, AcceptEid);
+        assert_eq!(restored.get_eid(), Eid(9));
+        assert_eq!(restored.uuid(), Some([1; 16]));
+        assert_eq!(restored.network_id(), Some([2; 16]));
+        assert_eq!(restored.mtu(Eid(20)), 68);
+        assert_eq!(restored.mtu(Eid(30)), 100);
+        assert_eq!(restored.snapshot(), snapshot);
+
+        // A Set Endpoint ID reset returns to the static EID, not the assigned one
+        let mut response = [0; 16];
+        restored
+            .control_response(
+                Eid(10),
+                &[0x80 | 1, CMD_SET_ENDPOINT_ID, 2, 0],
+                &mut response,
+            )
+            .unwrap();
+        assert_eq!(restored.get_eid(), Eid(8));
+
+        // Snapshots of the first format version restore the EID as the static one
+        let v1 = [1, 9, 0, 1, 20, 68, 0, 0];
+        let snapshot = RouterSnapshot::from_bytes(&v1).unwrap();
+        assert_eq!(
+            (snapshot.own_eid(), snapshot.static_eid()),
+            (Eid(9), Eid(9))
+        );
+        assert_eq!(snapshot.routes().count(), 0);
+        assert!(RouterSnapshot::from_bytes(&[3, 9, 9, 0, 0, 0]).is_err());
+    }
+
+    /// Get Network ID is answered from the configured network ID
+    #[test]
+    fn get_network_id() {
+        use crate::control::{CC_ERROR_UNSUPPORTED_CMD, CC_SUCCESS, CMD_GET_NETWORK_ID};
+
+        let mut router: Router<_, 4, 4> = Router::new(Eid(42), 0, NullSender);
+        let mut response = [0; 32];
+        let request = [0x80 | 3, CMD_GET_NETWORK_ID];
+
+        assert_eq!(
+            router
+                .control_response(Eid(8), &request, &mut response)
+                .unwrap(),
+            Some(3)
+        );
+        assert_eq!(
+            response.get(..3),
+            Some([3, CMD_GET_NETWORK_ID, CC_ERROR_UNSUPPORTED_CMD].as_slice())
+        );
+
+        router.set_network_id(Some([0x5a; 16]));
+        assert_eq!(
+            router
+                .control_response(Eid(8), &request, &mut response)
+                .unwrap(),
+            Some(19)
+        );
+        assert_eq!(
+            response.get(..3),
+            Some([3, CMD_GET_NETWORK_ID, CC_SUCCESS].as_slice())
+        );
+        assert_eq!(response.get(3..19), Some([0x5a; 16].as_slice()));
+        assert!(
+            router
+                .control_response(Eid(8), &request, &mut [0; 18])
+                .is_err()
+        );
+
+        // Datagrams, responses and other commands are left to the application
+        assert_eq!(
+            router
+                .control_response(Eid(8), &[0xc0, CMD_GET_NETWORK_ID], &mut response)
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            router
+                .control_response(Eid(8), &[0x00, CMD_GET_NETWORK_ID], &mut response)
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            router
+                .control_response(Eid(8), &[0x80, 0x0a], &mut response)
+                .unwrap(),
+            Some(3)
+        );
+        assert_eq!(
+            response.get(..3),
+            Some(&[0x00, 0x0a, CC_ERROR_UNSUPPORTED_CMD][..])
+        );
+
+        router.set_manual_control(true);
+        assert_eq!(
+            router
+                .control_response(Eid(8), &[0x80, 0x0a], &mut response)
+                .unwrap(),
+            None
+        );
+    }
+
+    /// Control requests without a listener are answered by the router
+    #[test]
+    fn answer_control_without_listener() {
+        use crate::control::{
+            CC_ERROR_UNSUPPORTED_CMD, CC_SUCCESS, CMD_GET_ENDPOINT_ID, MSG_TYPE_CONTROL,
+        };
+
+        let packets = RefCell::new(Vec::new());
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, BufferSender::<64>::new(&packets));
+
+        // Unsupported command, owned tag 3 from EID 9
+        let request = [1, 8, 9, 0xc8 | 3, MSG_TYPE_CONTROL.0, 0x81, 0x7f, 0xaa];
+        assert_eq!(
+            router.inbound_disposition(&request),
+            super::Disposition::ControlAnswered
+        );
+        let expected = [
+            1,
+            9,
+            8,
+            0xc0 | 3,
+            MSG_TYPE_CONTROL.0,
+            0x01,
+            0x7f,
+            CC_ERROR_UNSUPPORTED_CMD,
+        ];
+        assert_eq!(packets.take(), [expected.to_vec()]);
+
+        // Supported commands are answered from router state
+        let request = [
+            1,
+            8,
+            9,
+            0xc8 | 4,
+            MSG_TYPE_CONTROL.0,
+            0x82,
+            CMD_GET_ENDPOINT_ID,
+        ];
+        assert_eq!(
+            router.inbound_disposition(&request),
+            super::Disposition::ControlAnswered
+        );
+        let expected = [
+            1,
+            9,
+            8,
+            0xc0 | 4,
+            MSG_TYPE_CONTROL.0,
+            0x02,
+            CMD_GET_ENDPOINT_ID,
+            CC_SUCCESS,
+            8,
+            0,
+            0,
+        ];
+        assert_eq!(packets.take(), [expected.to_vec()]);
+
+        // Opted out, the request is dropped
+        router.set_manual_control(true);
+        let request = [1, 8, 9, 0xc8 | 5, MSG_TYPE_CONTROL.0, 0x83, 0x7f];
+        assert_eq!(
+            router.inbound_disposition(&request),
+            super::Disposition::DroppedNoListener
+        );
+        assert!(packets.borrow().is_empty());
+
+        // With a listener bound, requests are left to the application
+        router.set_manual_control(false);
+        let listener = router.listener(MSG_TYPE_CONTROL).unwrap();
+        let request = [1, 8, 9, 0xc8 | 6, MSG_TYPE_CONTROL.0, 0x84, 0x7f];
+        assert_eq!(
+            router.inbound_disposition(&request),
+            super::Disposition::Delivered(listener.into())
+        );
+        assert!(packets.borrow().is_empty());
+    }
+
+    /// Get Endpoint ID reports the configured endpoint and EID types
+    #[test]
+    fn get_endpoint_id() {
+        use crate::RouterConfig;
+        use crate::control::{
+            CC_SUCCESS, CMD_GET_ENDPOINT_ID, CMD_SET_ENDPOINT_ID, EidType, EndpointType,
+            SetEidDecision, SetEidRequest,
+        };
+
+        struct AcceptAll;
+
+        impl Hooks for AcceptAll {
+            fn set_eid_request(&mut self, _request: &SetEidRequest) -> SetEidDecision {
+                SetEidDecision::Accept
+            }
+        }
+
+        let get_eid = |router: &mut Router<_, 4, 4, _>| {
+            let mut response = [0; 8];
+            let len = router
+                .control_response(Eid(10), &[0x82, CMD_GET_ENDPOINT_ID], &mut response)
+                .unwrap()
+                .unwrap();
+            response.get(..len).unwrap().to_vec()
+        };
+        let set_eid = |router: &mut Router<_, 4, 4, _>, eid| {
+            let mut response = [0; 8];
+            router
+                .control_response(Eid(10), &[0x81, CMD_SET_ENDPOINT_ID, 0, eid], &mut response)
+                .unwrap();
+            response.get(3).copied()
+        };
+
+        let mut router: Router<_, 4, 4, _> =
+            Router::new_with_hooks(Eid(8), 0, NullSender, AcceptAll);
+        assert_eq!(
+            get_eid(&mut router),
+            [0x02, CMD_GET_ENDPOINT_ID, CC_SUCCESS, 8, 0, 0]
+        );
+
+        let config = RouterConfig::new(Eid(8))
+            .eid_type(EidType::StaticWithFallback)
+            .endpoint_type(EndpointType::BusOwnerOrBridge);
+        let mut router: Router<_, 4, 4, _> =
+            Router::new_with_config(config, 0, NullSender, AcceptAll);
+        assert_eq!(get_eid(&mut router).get(3..), Some([8, 0x12, 0].as_slice()));
+        assert_eq!(set_eid(&mut router, 20), Some(0x00));
+        assert_eq!(
+            get_eid(&mut router).get(3..),
+            Some([20, 0x13, 0].as_slice())
+        );
+        let bits = get_eid(&mut router).get(4).copied().unwrap();
+        assert_eq!(EidType::from_bits(bits), EidType::StaticWithFallback);
+        assert_eq!(
+            EndpointType::from_bits(bits),
+            EndpointType::BusOwnerOrBridge
+        );
+
+        // a static EID can't be reassigned
+        let config = RouterConfig::new(Eid(8)).eid_type(EidType::Static);
+        let mut router: Router<_, 4, 4, _> =
+            Router::new_with_config(config, 0, NullSender, AcceptAll);
+        assert_eq!(set_eid(&mut router, 20), Some(0x10));
+        assert_eq!(get_eid(&mut router).get(3..), Some([8, 0x01, 0].as_slice()));
+    }
+
+    /// Get MCTP Version Support reports the versions registered per message type
+    #[test]
+    fn get_version_support() {
+        use crate::control::{
+            CC_ERROR_INVALID_LENGTH, CC_SUCCESS, CC_UNSUPPORTED_MSG_TYPE, CMD_GET_VERSION_SUPPORT,
+        };
+        use crate::{BASE_SPEC_VERSION, VERSION_BASE_SPEC, VERSION_TABLE_SIZE};
+        use mctp::MsgType;
+
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, NullSender);
+        let get_versions = |router: &mut Router<_, 4, 4>, request: &[u8]| {
+            let mut response = [0; 40];
+            let len = router
+                .control_response(Eid(10), request, &mut response)
+                .unwrap()
+                .unwrap();
+            response.get(2..len).unwrap().to_vec()
+        };
+        let query = |typ| [0x80, CMD_GET_VERSION_SUPPORT, typ];
+
+        let mut base = vec![CC_SUCCESS, 1];
+        base.extend(BASE_SPEC_VERSION);
+        assert_eq!(get_versions(&mut router, &query(VERSION_BASE_SPEC)), base);
+        assert_eq!(get_versions(&mut router, &query(0)), base);
+        assert_eq!(
+            get_versions(&mut router, &query(5)),
+            [CC_UNSUPPORTED_MSG_TYPE]
+        );
+        assert_eq!(
+            get_versions(&mut router, &[0x80, CMD_GET_VERSION_SUPPORT]),
+            [CC_ERROR_INVALID_LENGTH]
+        );
+
+        router
+            .register_versions(MsgType(5), &[[0xf1, 0xf0, 0xff, 0], [0xf1, 0xf1, 0xff, 0]])
+            .unwrap();
+        assert_eq!(
+            get_versions(&mut router, &query(5)),
+            [CC_SUCCESS, 2, 0xf1, 0xf0, 0xff, 0, 0xf1, 0xf1, 0xff, 0]
+        );
+        router.register_versions(MsgType(5), &[]).unwrap();
+        assert_eq!(
+            get_versions(&mut router, &query(5)),
+            [CC_UNSUPPORTED_MSG_TYPE]
+        );
+
+        assert!(router.register_versions(MsgType(6), &[[0; 4]; 9]).is_err());
+        for typ in 1..VERSION_TABLE_SIZE as u8 - 1 {
+            router.register_versions(MsgType(typ), &[[0; 4]]).unwrap();
+        }
+        assert!(router.register_versions(MsgType(0x7e), &[[0; 4]]).is_err());
+    }
+
+    /// Get Vendor Defined Message Support walks the registered command sets
+    #[test]
+    fn get_vendor_support() {
+        use crate::VENDOR_TABLE_SIZE;
+        use crate::control::codec::{self, GetVendorMessageSupportResponse};
+        use crate::control::{
+            CC_ERROR_INVALID_DATA, CMD_GET_VENDOR_MESSAGE_SUPPORT, ControlResponse, VendorId,
+            VendorSupport,
+        };
+
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, NullSender);
+        let query = |router: &mut Router<_, 4, 4>, selector| {
+            let mut response = [0; 16];
+            let len = router
+                .control_response(
+                    Eid(10),
+                    &[0x80, CMD_GET_VENDOR_MESSAGE_SUPPORT, selector],
+                    &mut response,
+                )
+                .unwrap()
+                .unwrap();
+            response.get(..len).unwrap().to_vec()
+        };
+        let pci = VendorSupport {
+            vendor: VendorId::Pci(0x1af4),
+            command_set: 1,
+        };
+        let iana = VendorSupport {
+            vendor: VendorId::Iana(0x0000_a015),
+            command_set: 0x0102,
+        };
+
+        assert_eq!(query(&mut router, 0).get(2), Some(&CC_ERROR_INVALID_DATA));
+        router.add_vendor_support(pci).unwrap();
+        router.add_vendor_support(iana).unwrap();
+        router.add_vendor_support(pci).unwrap();
+        assert_eq!(
+            query(&mut router, 0).get(2..),
+            Some([0, 1, 0x00, 0x1a, 0xf4, 0, 1].as_slice())
+        );
+        let response = query(&mut router, 1);
+        assert_eq!(
+            response.get(2..),
+            Some([0, 0xff, 0x01, 0, 0, 0xa0, 0x15, 1, 2].as_slice())
+        );
+        let (_, decoded) =
+            codec::decode_response::<GetVendorMessageSupportResponse>(&response).unwrap();
+        assert_eq!(
+            decoded,
+            Ok(GetVendorMessageSupportResponse {
+                next_selector: None,
+                support: iana,
+            })
+        );
+        assert_eq!(
+            ControlResponse::decode(CMD_GET_VENDOR_MESSAGE_SUPPORT, response.get(2..).unwrap())
+                .unwrap(),
+            ControlResponse::VendorSupport {
+                next_selector: None,
+                support: iana,
+            }
+        );
+        assert_eq!(query(&mut router, 2).get(2), Some(&CC_ERROR_INVALID_DATA));
+
+        // removing a vendor moves the later sets up
+        assert!(router.remove_vendor_support(VendorId::Pci(0x1af4)));
+        assert!(!router.remove_vendor_support(VendorId::Pci(0x1af4)));
+        assert_eq!(
+            query(&mut router, 0).get(3..5),
+            Some([0xff, 0x01].as_slice())
+        );
+        for command_set in 2..VENDOR_TABLE_SIZE as u16 + 1 {
+            router
+                .add_vendor_support(VendorSupport { command_set, ..pci })
+                .unwrap();
+        }
+        assert!(router.add_vendor_support(pci).is_err());
+    }
+
+    /// Set Endpoint ID requests are applied according to the hooks
+    #[test]
+    fn set_endpoint_id() {
+        use crate::control::{
+            CC_ERROR_INVALID_DATA, CC_ERROR_INVALID_LENGTH, CC_SUCCESS, CMD_SET_ENDPOINT_ID,
+            SetEidDecision, SetEidOperation, SetEidRequest,
+        };
+
+        #[derive(Default)]
+        struct BusOwnerOnly {
+            ignore: bool,
+            requests: Vec<SetEidRequest>,
+        }
+
+        impl Hooks for BusOwnerOnly {
+            fn set_eid_request(&mut self, request: &SetEidRequest) -> SetEidDecision {
+                self.requests.push(*request);
+                if self.ignore {
+                    SetEidDecision::Ignore
+                } else if request.from_bus_owner() {
+                    SetEidDecision::Accept
+                } else {
+                    SetEidDecision::Reject
+                }
+            }
+        }
+
+        let mut router: Router<_, 4, 4, _> =
+            Router::new_with_hooks(Eid(8), 0, NullSender, BusOwnerOnly::default());
+        let set_eid = |router: &mut Router<_, 4, 4, _>, source, op, eid| {
+            let mut response = [0; 16];
+            let len = router
+                .control_response(
+                    Eid(source),
+                    &[0x80 | 1, CMD_SET_ENDPOINT_ID, op, eid],
+                    &mut response,
+                )
+                .unwrap();
+            len.and_then(|len| response.get(2..len)).map(|r| r.to_vec())
+        };
+
+        // The first assignment makes the requester the bus owner
+        assert_eq!(
+            set_eid(&mut router, 10, 0, 20),
+            Some(vec![CC_SUCCESS, 0x00, 20, 0])
+        );
+        assert_eq!(router.get_eid(), Eid(20));
+        assert_eq!(router.bus_owner(), Some(Eid(10)));
+        assert_eq!(
+            set_eid(&mut router, 11, 1, 30),
+            Some(vec![CC_SUCCESS, 0x10, 20, 0])
+        );
+        assert_eq!(router.get_eid(), Eid(20));
+        assert_eq!(
+            set_eid(&mut router, 10, 0, 0xff),
+            Some(vec![CC_ERROR_INVALID_DATA])
+        );
+        assert_eq!(
+            set_eid(&mut router, 10, 2, 0),
+            Some(vec![CC_SUCCESS, 0x00, 8, 0])
+        );
+        assert_eq!(router.get_eid(), Eid(8));
+        let last = router.hooks().requests.last().copied().unwrap();
+        assert_eq!(last.operation, SetEidOperation::Reset);
+        assert_eq!(last.current, Eid(20));
+
+        let mut response = [0; 16];
+        assert_eq!(
+            router
+                .control_response(Eid(10), &[0x81, CMD_SET_ENDPOINT_ID, 0], &mut response)
+                .unwrap(),
+            Some(3)
+        );
+        assert_eq!(response.get(2), Some(&CC_ERROR_INVALID_LENGTH));
+
+        router.hooks_mut().ignore = true;
+        assert_eq!(set_eid(&mut router, 10, 0, 40), None);
+        assert_eq!(router.get_eid(), Eid(8));
+    }
+
+    /// Instance IDs in use are skipped per destination and expire
+    #[test]
+    fn instance_ids() {
+        use crate::{INSTANCE_ID_EXPIRY_MILLIS, INSTANCE_ID_TABLE_SIZE};
+
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, NullSender);
+        assert_eq!(router.alloc_instance_id(Eid(9)).unwrap(), 0);
+        for i in 1..32 {
+            assert_eq!(router.alloc_instance_id(Eid(9)).unwrap(), i);
+            assert!(router.release_instance_id(Eid(9), i));
+        }
+        // 0 is still in use for EID 9, but not for EID 10
+        assert_eq!(router.alloc_instance_id(Eid(9)).unwrap(), 1);
+        assert!(!router.release_instance_id(Eid(10), 0));
+        assert!(router.release_instance_id(Eid(9), 1));
+
+        for _ in 1..INSTANCE_ID_TABLE_SIZE {
+            router.alloc_instance_id(Eid(10)).unwrap();
+        }
+        assert!(matches!(
+            router.alloc_instance_id(Eid(11)),
+            Err(mctp::Error::NoSpace)
+        ));
+        router.update(INSTANCE_ID_EXPIRY_MILLIS).unwrap();
+        assert!(router.alloc_instance_id(Eid(11)).is_ok());
+        assert!(!router.release_instance_id(Eid(9), 0));
+    }
+
+    /// Control payloads roundtrip through the codec, failures carry the completion code
+    #[test]
+    fn control_codec() {
+        use crate::control::codec::{
+            self, CompletionCode, GetMessageTypeSupportResponse, GetVersionSupportResponse,
+            SetEndpointIdRequest, SetEndpointIdResponse,
+        };
+        use crate::control::{
+            CMD_GET_MESSAGE_TYPE_SUPPORT, CMD_GET_VERSION_SUPPORT, ControlHeader, MessageTypes,
+            SetEidOperation,
+        };
+        use mctp::MsgType;
+
+        let mut buf = [0; 64];
+        let request = SetEndpointIdRequest {
+            operation: SetEidOperation::Set,
+            eid: Eid(20),
+        };
+        let len = codec::encode_request(5, &request, &mut buf).unwrap();
+        assert_eq!(buf.get(..len), Some([0x85, 0x01, 0x00, 20].as_slice()));
+        let (header, decoded) = codec::decode_request(buf.get(..len).unwrap()).unwrap();
+        assert_eq!((header.instance_id, decoded), (5, request));
+        assert!(codec::decode_response::<SetEndpointIdResponse>(buf.get(..len).unwrap()).is_err());
+        assert!(codec::encode_request(5, &request, &mut [0; 3]).is_err());
+
+        let response = SetEndpointIdResponse {
+            accepted: false,
+            eid: Eid(8),
+            pool_size: 0,
+        };
+        let len = codec::encode_response(header, &response, &mut buf).unwrap();
+        assert_eq!(
+            buf.get(..len),
+            Some([0x05, 0x01, 0x00, 0x10, 8, 0].as_slice())
+        );
+        let (_, decoded) = codec::decode_response(buf.get(..len).unwrap()).unwrap();
+        assert_eq!(decoded, Ok(response));
+
+        let len = codec::encode_error(header, CompletionCode::NotReady, &mut buf).unwrap();
+        let (_, decoded) =
+            codec::decode_response::<SetEndpointIdResponse>(buf.get(..len).unwrap()).unwrap();
+        assert_eq!(decoded, Err(CompletionCode::NotReady));
+        assert_eq!(u8::from(CompletionCode::from(0x80)), 0x80);
+
+        let versions = GetVersionSupportResponse::new(&[[0xf1, 0xf3, 0xf1, 0]]).unwrap();
+        let len = codec::encode_response(header, &versions, &mut buf).unwrap();
+        assert!(
+            codec::decode_response::<GetVersionSupportResponse>(buf.get(..len).unwrap()).is_err()
+        );
+        let header = ControlHeader {
+            command: CMD_GET_VERSION_SUPPORT,
+            ..header
+        };
+        let len = codec::encode_response(header, &versions, &mut buf).unwrap();
+        let (_, decoded) = codec::decode_response(buf.get(..len).unwrap()).unwrap();
+        assert_eq!(decoded, Ok(versions));
+        assert!(GetVersionSupportResponse::new(&[[0; 4]; 9]).is_err());
+
+        let types = GetMessageTypeSupportResponse {
+            types: MessageTypes::new(&[MsgType(1), MsgType(5)]).unwrap(),
+        };
+        let header = ControlHeader {
+            command: CMD_GET_MESSAGE_TYPE_SUPPORT,
+            ..header
+        };
+        let len = codec::encode_response(header, &types, &mut buf).unwrap();
+        assert_eq!(buf.get(2..len), Some([0, 2, 1, 5].as_slice()));
+        let (_, decoded) = codec::decode_response(buf.get(..len).unwrap()).unwrap();
+        assert_eq!(decoded, Ok(types));
+        assert!(
+            codec::decode_response::<GetMessageTypeSupportResponse>(buf.get(..len - 1).unwrap())
+                .is_err()
+        );
+    }
+
+    /// Typed channels enforce their message type and frame messages with the type byte
+    #[test]
+    fn typed_channels() {
+        use crate::shared::SharedRouter;
+        use crate::spdm::MSG_TYPE_SPDM;
+        use crate::typed::{self, Pldm, Spdm, TypedListener, TypedRequester};
+        use mctp::{MsgIC, Tag};
+
+        let buf_out_a = RefCell::new(Vec::new());
+        let buf_out_b = RefCell::new(Vec::new());
+        let router_a: Router<_, 4, 4> =
+            Router::new(Eid(42), 0, BufferSender::<255>::new(&buf_out_a));
+        let router_b: Router<_, 4, 4> =
+            Router::new(Eid(112), 0, BufferSender::<255>::new(&buf_out_b));
+        let router_a = SharedRouter::new(router_a);
+        let router_b = SharedRouter::new(router_b);
+        let transfer = |from: &RefCell<Vec<Vec<u8>>>, to: &SharedRouter<_, _, _, _>| {
+            for pkt in from.borrow_mut().drain(..) {
+                to.inbound(&pkt).unwrap();
+            }
+        };
+
+        assert_eq!(typed::name(MSG_TYPE_SPDM), Some("SPDM"));
+        assert_eq!(typed::name(mctp::MsgType(0x30)), None);
+
+        let listener = TypedListener::<Pldm, _, _, _, _>::bind(&router_a).unwrap();
+        assert!(TypedListener::<Pldm, _, _, _, _>::bind(&router_a).is_err());
+        let requester = TypedRequester::<Pldm, _, _, _, _>::new(router_b.req(Eid(42)).unwrap());
+        let spdm = TypedRequester::<Spdm, _, _, _, _>::new(router_b.req(Eid(42)).unwrap());
+        assert!(requester.send_framed(&[0x05, 0x10, 0x84]).is_err());
+        assert!(requester.send_framed(&[]).is_err());
+        requester.send_framed(&[0x81, 0x80, 0x00, 0x01]).unwrap();
+        spdm.send(MsgIC(false), &[&[0x10, 0x84]]).unwrap();
+        transfer(&buf_out_b, &router_a);
+
+        // Only the PLDM request reaches the PLDM listener
+        let mut buf = [0; 16];
+        let (info, len) = listener.try_recv_framed(&mut buf).unwrap().unwrap();
+        assert_eq!(buf.get(..len), Some([0x81, 0x80, 0x00, 0x01].as_slice()));
+        assert!(listener.try_recv(&mut buf).unwrap().is_none());
+
+        // A response of the wrong type is discarded by the requester
+        router_a
+            .with(|r| {
+                r.send(
+                    Some(info.source),
+                    MSG_TYPE_SPDM,
+                    Some(Tag::Unowned(info.tag.tag())),
+                    MsgIC(false),
+                    listener.handle(),
+                    &[0x11],
+                )
+            })
+            .unwrap()
+            .unwrap();
+        assert!(listener.respond_framed(&info, &[0x05, 0x00]).is_err());
+        listener
+            .respond_framed(&info, &[0x01, 0x00, 0x00, 0x01, 0x00])
+            .unwrap();
+        transfer(&buf_out_a, &router_b);
+        let (info, len) = requester.try_recv_framed(&mut buf).unwrap().unwrap();
+        assert_eq!(info.typ, crate::pldm::MSG_TYPE_PLDM);
+        assert_eq!(
+            buf.get(..len),
+            Some([0x01, 0x00, 0x00, 0x01, 0x00].as_slice())
+        );
+        assert!(requester.try_recv(&mut buf).unwrap().is_none());
+    }
+
+    /// Control requests are encoded and the matching responses decoded
+    #[test]
+    fn control_requester() {
+        use crate::control::{
+            CC_ERROR_INVALID_DATA, CC_ERROR_UNSUPPORTED_CMD, CMD_GET_ENDPOINT_UUID,
+            CMD_GET_MESSAGE_TYPE_SUPPORT, CMD_GET_VERSION_SUPPORT, CompletionFailure, ControlError,
+            ControlHeader, ControlRequester, ControlResponse, ControlStats, MSG_TYPE_CONTROL,
+            SetEidDecision, SetEidOperation, SetEidRequest,
+        };
+        use crate::shared::SharedRouter;
+        use mctp::{MsgIC, MsgType};
+
+        struct Reject;
+
+        impl Hooks for Reject {
+            fn set_eid_request(&mut self, _request: &SetEidRequest) -> SetEidDecision {
+                SetEidDecision::Reject
+            }
+        }
+
+        let buf_out_a = RefCell::new(Vec::new());
+        let buf_out_b = RefCell::new(Vec::new());
+        let router_a: Router<_, 4, 4, _> =
+            Router::new_with_hooks(Eid(42), 0, BufferSender::<255>::new(&buf_out_a), Reject);
+        let router_b: Router<_, 4, 4> =
+            Router::new(Eid(112), 0, BufferSender::<255>::new(&buf_out_b));
+        let router_a = SharedRouter::new(router_a);
+        let router_b = SharedRouter::new(router_b);
+        let transfer = |from: &RefCell<Vec<Vec<u8>>>, to: &dyn Fn(&[u8])| {
+            for pkt in from.borrow_mut().drain(..) {
+                to(&pkt);
+            }
+        };
+        let to_a = |pkt: &[u8]| {
+            router_a.inbound(pkt).unwrap();
+        };
+        let to_b = |pkt: &[u8]| {
+            router_b.inbound(pkt).unwrap();
+        };
+
+        let listener = router_a.listener(MSG_TYPE_CONTROL).unwrap();
+        let mut requester = ControlRequester::new(&router_b);
+        let mut buf = [0; 64];
+        assert!(matches!(
+            requester.receive(),
+            Err(ControlError::Mctp(mctp::Error::BadArgument))
+        ));
+
+        // Set Endpoint ID, answered by the router
+        let header = requester
+            .set_eid(Eid(42), SetEidOperation::Set, Eid(50))
+            .unwrap();
+        assert_eq!(header.instance_id, 0);
+        transfer(&buf_out_b, &to_a);
+        let info = listener.try_recv(&mut buf).unwrap().unwrap();
+        let mut response = [0; 16];
+        let len = router_a
+            .with(|r| r.control_response(info.source, buf.get(..info.len).unwrap(), &mut response))
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        listener
+            .respond(&info, MsgIC(false), &[response.get(..len).unwrap()])
+            .unwrap();
+        transfer(&buf_out_a, &to_b);
+        assert_eq!(
+            requester.receive().unwrap(),
+            Some((
+                Eid(42),
+                ControlResponse::SetEndpointId {
+                    accepted: false,
+                    eid: Eid(42),
+                    pool_size: 0
+                }
+            ))
+        );
+
+        // Get Message Type Support to a new EID, a stale response is discarded
+        router_a.with(|r| r.set_eid(Eid(50))).unwrap().unwrap();
+        let header = requester.get_message_types(Eid(50)).unwrap();
+        assert_eq!(header.instance_id, 1);
+        transfer(&buf_out_b, &to_a);
+        let info = listener.try_recv(&mut buf).unwrap().unwrap();
+        let stale = ControlHeader {
+            instance_id: 0,
+            ..header.response()
+        };
+        let types = [0x01, 0x7e];
+        listener
+            .respond(&info, MsgIC(false), &[&stale.to_bytes(), &[0, 0]])
+            .unwrap();
+        listener
+            .respond(
+                &info,
+                MsgIC(false),
+                &[&header.response().to_bytes(), &[0, 2], &types],
+            )
+            .unwrap();
+        transfer(&buf_out_a, &to_b);
+        let Some((peer, ControlResponse::MessageTypes(supported))) = requester.receive().unwrap()
+        else {
+            panic!("no message types");
+        };
+        assert_eq!(peer, Eid(50));
+        assert!(
+            !router_b
+                .with(|r| r.release_instance_id(Eid(50), header.instance_id))
+                .unwrap()
+        );
+        assert_eq!(
+            supported.as_slice(),
+            [MsgType(0x01), MsgType(0x7e)].as_slice()
+        );
+        let learned = router_b.with(|r| r.topology().last()).unwrap().unwrap();
+        assert_eq!(
+            (learned.eid, learned.message_types),
+            (Eid(50), Some(supported))
+        );
+        assert!(matches!(
+            requester.receive(),
+            Err(ControlError::Mctp(mctp::Error::BadArgument))
+        ));
+
+        // Failed commands report the completion code
+        let header = requester.get_uuid(Eid(50)).unwrap();
+        transfer(&buf_out_b, &to_a);
+        let info = listener.try_recv(&mut buf).unwrap().unwrap();
+        listener
+            .respond(
+                &info,
+                MsgIC(false),
+                &[&header.response().to_bytes(), &[CC_ERROR_UNSUPPORTED_CMD]],
+            )
+            .unwrap();
+        transfer(&buf_out_a, &to_b);
+        let unsupported = CompletionFailure {
+            command: CMD_GET_ENDPOINT_UUID,
+            code: CC_ERROR_UNSUPPORTED_CMD,
+        };
+        assert!(matches!(
+            requester.receive(),
+            Err(ControlError::Failed(f)) if f == unsupported
+        ));
+        assert!(matches!(
+            requester.receive(),
+            Err(ControlError::Mctp(mctp::Error::BadArgument))
+        ));
+
+        // Callers can branch on the completion code
+        let header = requester.get_versions(Eid(50), 0xff).unwrap();
+        transfer(&buf_out_b, &to_a);
+        let info = listener.try_recv(&mut buf).unwrap().unwrap();
+        listener
+            .respond(
+                &info,
+                MsgIC(false),
+                &[&header.response().to_bytes(), &[CC_ERROR_INVALID_DATA]],
+            )
+            .unwrap();
+        transfer(&buf_out_a, &to_b);
+        let err = requester.receive().unwrap_err();
+        assert_eq!(err.completion_code(), Some(CC_ERROR_INVALID_DATA));
+        assert!(matches!(mctp::Error::from(err), mctp::Error::RxFailure));
+        assert_eq!(
+            requester.stats(),
+            ControlStats {
+                responses: 4,
+                failures: 2,
+                invalid_data: 1,
+                unsupported: 1,
+                last_failure: Some(CompletionFailure {
+                    command: CMD_GET_VERSION_SUPPORT,
+                    code: CC_ERROR_INVALID_DATA,
+                }),
+            }
+        );
+        assert!(ControlResponse::decode(CMD_GET_MESSAGE_TYPE_SUPPORT, &[0, 3, 1]).is_err());
+        requester.unbind().unwrap();
+    }
+
+    /// The topology combines learned endpoints with their routes and liveness
+    #[test]
+    fn topology() {
+        use crate::control::{ControlResponse, MessageTypes};
+        use crate::{KeepAlive, PeerState, TOPOLOGY_TABLE_SIZE};
+
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, NullSender);
+        assert_eq!(router.topology().count(), 0);
+
+        // an endpoint without EID is assigned 20
+        let assigned = ControlResponse::SetEndpointId {
+            accepted: true,
+            eid: Eid(20),
+            pool_size: 0,
+        };
+        router.learn_control_response(Eid(0), &assigned).unwrap();
+        router
+            .learn_control_response(Eid(20), &ControlResponse::Uuid([3; 16]))
+            .unwrap();
+        let types = MessageTypes::new(&[mctp::MsgType(5)]).unwrap();
+        router
+            .learn_control_response(Eid(20), &ControlResponse::MessageTypes(types))
+            .unwrap();
+        router.add_route(20..=29, 1, 0x1d).unwrap();
+        router.set_keep_alive(Some(KeepAlive::new(100, 50, 2)));
+        router.monitor_peer(Eid(20)).unwrap();
+
+        let endpoint = ControlResponse::EndpointId {
+            eid: Eid(9),
+            eid_type: 0,
+            medium_specific: 0,
+        };
+        router.learn_control_response(Eid(9), &endpoint).unwrap();
+        // failures and other responses don't add endpoints
+        router
+            .learn_control_response(Eid(10), &ControlResponse::Failed(1))
+            .unwrap();
+
+        let entries: Vec<_> = router.topology().collect();
+        assert_eq!(entries.len(), 2);
+        let first = entries.first().unwrap();
+        assert_eq!(first.eid, Eid(20));
+        assert!(first.assigned);
+        assert_eq!(first.uuid, Some([3; 16]));
+        assert_eq!(first.message_types, Some(types));
+        assert_eq!(
+            first.route.map(|r| (r.port, r.physical_addr)),
+            Some((1, 0x1d))
+        );
+        assert_eq!(first.state, Some(PeerState::Unknown));
+        let second = entries.get(1).unwrap();
+        assert_eq!((second.eid, second.assigned), (Eid(9), false));
+        assert_eq!((second.route, second.state), (None, None));
+
+        assert!(router.forget_endpoint(Eid(9)));
+        assert!(!router.forget_endpoint(Eid(9)));
+        for eid in 0..TOPOLOGY_TABLE_SIZE as u8 - 1 {
+            let endpoint = ControlResponse::EndpointId {
+                eid: Eid(30 + eid),
+                eid_type: 0,
+                medium_specific: 0,
+            };
+            router
+                .learn_control_response(Eid(30 + eid), &endpoint)
+                .unwrap();
+        }
+        assert!(router.learn_control_response(Eid(9), &endpoint).is_err());
+    }
+
+    /// Routers exchange messages through a simulated, impaired link
+    #[cfg(feature = "std")]
+    #[test]
+    fn simulation() {
+        use crate::RouterConfig;
+        use crate::sim::{LinkConfig, SimPort, SimRouter, Simulation};
+
+        type Tables = crate::ArrayTables<2, 2>;
+        let node = |eid, forwarding| {
+            move |port: SimPort| -> SimRouter<Tables, crate::NoHooks> {
+                let config = RouterConfig::new(Eid(eid))
+                    .forwarding(forwarding)
+                    .request_timeout_millis(Some(50));
+                SimRouter::new_with_tables(
+                    config,
+                    crate::ManualClock::new(0),
+                    port,
+                    crate::NoHooks,
+                    Tables::default(),
+                )
+            }
+        };
+
+        // Requester and responder on either side of a bridge
+        let link = LinkConfig {
+            latency_millis: 2,
+            ..Default::default()
+        };
+        let mut sim = Simulation::new(link, 1);
+        let a = sim.add(node(8, false));
+        let bridge = sim.add(node(9, true));
+        let c = sim.add(node(10, false));
+        sim.route(a, Eid(10), bridge);
+        sim.route(c, Eid(8), bridge);
+
+        let listener = sim
+            .router_mut(c)
+            .unwrap()
+            .listener(mctp::MsgType(1))
+            .unwrap();
+        let router_a = sim.router_mut(a).unwrap();
+        let req = router_a.req(Eid(10)).unwrap();
+        router_a
+            .send(
+                None,
+                mctp::MsgType(1),
+                None,
+                mctp::MsgIC(false),
+                req,
+                &[0; 100],
+            )
+            .unwrap();
+        assert!(sim.run_until_idle(1, 10));
+        assert!(
+            sim.take_dispositions()
+                .contains(&(bridge, super::Disposition::Forwarded))
+        );
+
+        let router_c = sim.router_mut(c).unwrap();
+        let (source, tag) = router_c
+            .recv(listener)
+            .map(|msg| (msg.source, msg.tag))
+            .unwrap();
+        router_c
+            .send(
+                Some(source),
+                mctp::MsgType(1),
+                Some(mctp::Tag::Unowned(tag.tag())),
+                mctp::MsgIC(false),
+                listener,
+                &[1],
+            )
+            .unwrap();
+        assert!(sim.run_until_idle(1, 10));
+        assert!(sim.router_mut(a).unwrap().recv(req).is_some());
+        assert_eq!(sim.stats().lost, 0);
+
+        // Identical seeds give identical runs on a lossy link
+        let run = |seed| {
+            let link = LinkConfig {
+                loss_percent: 30,
+                duplicate_percent: 10,
+                reorder_percent: 10,
+                latency_millis: 1,
+                ..Default::default()
+            };
+            let mut sim = Simulation::new(link, seed);
+            let a = sim.add(node(8, false));
+            sim.add(node(10, false));
+            let router_a = sim.router_mut(a).unwrap();
+            let req = router_a.req(Eid(10)).unwrap();
+            for _ in 0..10 {
+                let router_a = sim.router_mut(a).unwrap();
+                let _ = router_a.send(
+                    None,
+                    mctp::MsgType(1),
+                    None,
+                    mctp::MsgIC(false),
+                    req,
+                    &[0; 200],
+                );
+                sim.run_until_idle(10, 10);
+            }
+            (sim.stats(), sim.take_dispositions())
+        };
+        let (stats, dispositions) = run(7);
+        assert!(stats.lost > 0);
+        assert_eq!(run(7), (stats, dispositions));
+    }
+
+    /// EID changes are reported to the hooks
+    #[test]
+    fn eid_changed() {
+        #[derive(Default)]
+        struct EidChanges {
+            changes: Vec<(Eid, Eid)>,
+        }
+
+        impl Hooks for EidChanges {
+            fn eid_changed(&mut self, old: Eid, new: Eid) {
+                self.changes.push((old, new));
+            }
+        }
+
+        let mut router: Router<_, 4, 4, EidChanges> =
+            Router::new_with_hooks(Eid(8), 0, NullSender, EidChanges::default());
+        router.set_eid(Eid(9)).unwrap();
+        router.set_eid(Eid(9)).unwrap();
+        router.set_eid(Eid(10)).unwrap();
+        assert_eq!(
+            router.hooks().changes,
+            [(Eid(8), Eid(9)), (Eid(9), Eid(10))]
+        );
+    }
+
+    /// Monitored peers are probed and reported up and down
+    #[test]
+    fn liveness() {
+        use crate::control::{CC_SUCCESS, ControlHeader, MSG_TYPE_CONTROL};
+        use crate::{KeepAlive, PeerState, RouterConfig};
+        use mctp::{MsgIC, Tag};
+
+        #[derive(Default)]
+        struct StateChanges {
+            changes: Vec<(Eid, PeerState)>,
+        }
+
+        impl Hooks for StateChanges {
+            fn peer_state_changed(&mut self, peer: Eid, state: PeerState) {
+                self.changes.push((peer, state));
+            }
+        }
+
+        let packets_a = RefCell::new(Vec::new());
+        let packets_b = RefCell::new(Vec::new());
+        let config = RouterConfig::new(Eid(8)).keep_alive(Some(KeepAlive::new(1000, 100, 2)));
+        let mut router: Router<_, 4, 4, StateChanges> = Router::new_with_config(
+            config,
+            0,
+            BufferSender::<64>::new(&packets_a),
+            StateChanges::default(),
+        );
+        let mut peer: Router<_, 4, 4> = Router::new(Eid(9), 0, BufferSender::<64>::new(&packets_b));
+        let listener = peer.listener(MSG_TYPE_CONTROL).unwrap();
+
+        router.monitor_peer(Eid(9)).unwrap();
+        assert_eq!(router.peer_state(Eid(9)), Some(PeerState::Unknown));
+        assert!(router.update(0).unwrap() <= 100);
+        assert_eq!(
+            crate::test_util::transfer(&packets_a, &mut peer).unwrap(),
+            1
+        );
+
+        // Get Endpoint ID is answered by the peer
+        let msg = peer.recv(listener).unwrap();
+        let (source, tag) = (msg.source, msg.tag);
+        let request = ControlHeader::parse(msg.payload).unwrap();
+        drop(msg);
+        let response = [
+            request.response().to_bytes().as_slice(),
+            &[CC_SUCCESS, 9, 0, 0],
+        ]
+        .concat();
+        peer.send(
+            Some(source),
+            MSG_TYPE_CONTROL,
+            Some(Tag::Unowned(tag.tag())),
+            MsgIC(false),
+            listener,
+            &response,
+        )
+        .unwrap();
+        let pkt = packets_b.borrow_mut().pop().unwrap();
+        assert_eq!(
+            router.inbound_disposition(&pkt),
+            super::Disposition::ProbeAnswered
+        );
+        assert_eq!(router.peer_state(Eid(9)), Some(PeerState::Up));
+        assert_eq!(
+            router.inbound_disposition(&pkt),
+            super::Disposition::DroppedNoRequest
+        );
+
+        // Two unanswered probes take the peer down
+        router.update(1000).unwrap();
+        router.update(1100).unwrap();
+        assert_eq!(router.peer_state(Eid(9)), Some(PeerState::Up));
+        router.update(2000).unwrap();
+        router.update(2100).unwrap();
+        assert_eq!(router.peer_state(Eid(9)), Some(PeerState::Down));
+        assert_eq!(packets_a.borrow().len(), 2);
+        assert_eq!(
+            router.hooks().changes,
+            [(Eid(9), PeerState::Up), (Eid(9), PeerState::Down)]
+        );
+
+        assert!(router.unmonitor_peer(Eid(9)));
+        assert!(!router.unmonitor_peer(Eid(9)));
+        assert_eq!(router.peer_state(Eid(9)), None);
+    }
+
+    /// Packets sourced from the own EID are reported as a conflict
+    #[test]
+    fn eid_conflict() {
+        use crate::EidConflict;
+
+        #[derive(Default)]
+        struct Conflicts {
+            conflicts: Vec<(Eid, EidConflict)>,
+        }
+
+        impl Hooks for Conflicts {
+            fn eid_conflict(&mut self, eid: Eid, conflict: EidConflict) {
+                self.conflicts.push((eid, conflict));
+            }
+        }
+
+        let mut router: Router<_, 4, 4, Conflicts> =
+            Router::new_with_hooks(Eid(0), 0, NullSender, Conflicts::default());
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+
+        // Endpoints without an EID share the null EID
+        let unassigned = [1, 0, 0, 0xc8, 1, 0xaa];
+        assert_eq!(
+            router.inbound_disposition(&unassigned),
+            super::Disposition::Delivered(listener.into())
+        );
+
+        router.set_eid(Eid(8)).unwrap();
+        let spoofed = [1, 8, 8, 0xc8, 1, 0xaa];
+        assert_eq!(
+            router.inbound_disposition(&spoofed),
+            super::Disposition::DroppedEidConflict
+        );
+        assert_eq!(router.eid_conflicts(), 1);
+        assert_eq!(
+            router.hooks().conflicts,
+            [(Eid(8), EidConflict::SourcedFromOwnEid)]
+        );
+    }
+
+    /// Responses matching no request are passed to the orphan hook before being dropped
+    #[test]
+    fn orphan_response() {
+        use crate::OrphanResponse;
+        use mctp::{MsgIC, MsgType, Tag, TagValue};
+
+        #[derive(Default)]
+        struct Orphans {
+            orphans: Vec<(Eid, Tag, MsgType, u64, Vec<u8>)>,
+        }
+
+        impl Hooks for Orphans {
+            fn orphan_response(&mut self, response: &OrphanResponse<'_>) {
+                self.orphans.push((
+                    response.source,
+                    response.tag,
+                    response.typ,
+                    response.now_millis,
+                    response.payload.to_vec(),
+                ));
+            }
+        }
+
+        let mut router: Router<_, 4, 4, Orphans> =
+            Router::new_with_hooks(Eid(8), 0, NullSender, Orphans::default());
+        router.update(42).unwrap();
+        let req = router.req(Eid(9)).unwrap();
+        let tag = router
+            .send(None, MsgType(1), None, MsgIC(false), req, &[0x80])
+            .unwrap()
+            .tag
+            .tag()
+            .0;
+
+        // A response with a tag no request is waiting for
+        let stray = (tag + 1) % 8;
+        let response = [1, 8, 9, 0xc0 | stray, 1, 0xaa, 0xbb];
+        assert_eq!(
+            router.inbound_disposition(&response),
+            super::Disposition::DroppedNoRequest
+        );
+        assert_eq!(
+            router.hooks().orphans,
+            [(
+                Eid(9),
+                Tag::Unowned(TagValue(stray)),
+                MsgType(1),
+                42,
+                vec![0xaa, 0xbb]
+            )]
+        );
+
+        // The matching response still reaches the request
+        let response = [1, 8, 9, 0xc0 | tag, 1, 0xcc];
+        assert!(matches!(
+            router.inbound_disposition(&response),
+            super::Disposition::Delivered(_)
+        ));
+        assert_eq!(router.hooks().orphans.len(), 1);
+    }
+
+    /// Retargeting a request keeps its handle and forgets the previous peer
+    #[test]
+    fn retarget_request() {
+        use mctp::{MsgIC, MsgType};
+
+        let packets = RefCell::new(Vec::new());
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, BufferSender::<64>::new(&packets));
+        let req = router.req(Eid(9)).unwrap();
+        let tag = router
+            .send(None, MsgType(1), None, MsgIC(false), req, &[0x80])
+            .unwrap()
+            .tag
+            .tag()
+            .0;
+        let response = [1, 8, 9, 0xc0 | tag, 1, 0x00];
+        assert_eq!(
+            router.inbound_disposition(&response),
+            super::Disposition::Delivered(req.into())
+        );
+
+        let tag = router
+            .send(None, MsgType(1), None, MsgIC(false), req, &[0x80])
+            .unwrap()
+            .tag
+            .tag()
+            .0;
+
+        // The retained response is discarded along with the tag state
+        router.retarget(req, Eid(10)).unwrap();
+        assert!(router.recv(req).is_none());
+        let late = [1, 8, 9, 0xc0 | tag, 1, 0x00];
+        assert_eq!(
+            router.inbound_disposition(&late),
+            super::Disposition::DroppedNoRequest
+        );
+        packets.borrow_mut().clear();
+        router
+            .send(None, MsgType(1), None, MsgIC(false), req, &[0x80])
+            .unwrap();
+        assert_eq!(packets.borrow().first().and_then(|p| p.get(1)), Some(&10));
+
+        router.reset(req).unwrap();
+        router.unbind(req).unwrap();
+        assert!(router.reset(req).is_err());
+        assert!(router.retarget(req, Eid(9)).is_err());
+    }
+
+    /// Responses of a different message type don't satisfy a request
+    #[test]
+    fn response_type_mismatch() {
+        use mctp::{MsgIC, MsgType};
+
+        let packets = RefCell::new(Vec::new());
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, BufferSender::<64>::new(&packets));
+        let req = router.req(Eid(9)).unwrap();
+        let tag = router
+            .send(None, MsgType(5), None, MsgIC(false), req, &[0x10, 0x84])
+            .unwrap()
+            .tag
+            .tag()
+            .0;
+
+        let pldm = [1, 8, 9, 0xc0 | tag, 1, 0x00, 0x00, 0x01];
+        assert_eq!(
+            router.inbound_disposition(&pldm),
+            super::Disposition::DroppedTypeMismatch
+        );
+        assert!(super::Disposition::DroppedTypeMismatch.is_dropped());
+        assert!(router.recv(req).is_none());
+
+        let spdm = [1, 8, 9, 0xc0 | tag, 5, 0x10, 0x04];
+        assert_eq!(
+            router.inbound_disposition(&spdm),
+            super::Disposition::Delivered(req.into())
+        );
+        let msg = router.recv(req).unwrap();
+        assert_eq!(
+            (msg.typ, msg.payload),
+            (MsgType(5), [0x10, 0x04].as_slice())
+        );
+    }
+
+    /// Promiscuous mode surfaces packets that are dropped otherwise
+    #[test]
+    fn promiscuous() {
+        #[derive(Default)]
+        struct Snoop {
+            packets: Vec<(Eid, Eid, bool, Vec<u8>)>,
+        }
+
+        impl Hooks for Snoop {
+            fn snoop(&mut self, pkt: &crate::SnoopedPacket<'_>) {
+                self.packets
+                    .push((pkt.source, pkt.dest, pkt.som, pkt.payload.to_vec()));
+            }
+        }
+
+        let config = crate::RouterConfig::new(Eid(8)).promiscuous(true);
+        let mut router: Router<_, 4, 4, Snoop> =
+            Router::new_with_config(config, 0, NullSender, Snoop::default());
+
+        // Foreign EID, no listener, unsupported version, truncated
+        router.inbound_disposition(&[1, 30, 20, 0xc8, 1, 0xaa]);
+        router.inbound_disposition(&[1, 8, 20, 0xc8, 1, 0xbb]);
+        router.inbound_disposition(&[2, 8, 20, 0xc8, 1, 0xcc]);
+        router.inbound_disposition(&[1, 8, 20]);
+        assert_eq!(
+            router.hooks().packets,
+            [
+                (Eid(20), Eid(30), true, vec![1, 0xaa]),
+                (Eid(20), Eid(8), true, vec![1, 0xbb]),
+            ]
+        );
+
+        router.set_promiscuous(false);
+        router.inbound_disposition(&[1, 8, 20, 0xc8, 1, 0xdd]);
+        assert_eq!(router.hooks().packets.len(), 2);
+    }
+
+    /// Bridged packets are passed through unmodified, apart from local flows
+    #[test]
+    fn forwarding() {
+        let packets = RefCell::new(Vec::new());
+        let outbound: BufferSender<255> = BufferSender { packets: &packets };
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, outbound);
+        let req = router.req(Eid(20)).unwrap();
+        let tag = router
+            .send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[1])
+            .unwrap()
+            .tag;
+        packets.borrow_mut().clear();
+
+        // A response from 20 with the same tag, but addressed to 30
+        let bridged = [1, 30, 20, 0xc0 | tag.tag().0, 1, 0xaa];
+        assert_eq!(
+            router.inbound_disposition(&bridged),
+            super::Disposition::DroppedWrongEid
+        );
+        router.set_forwarding(true);
+        assert_eq!(
+            router.inbound_disposition(&bridged),
+            super::Disposition::Forwarded
+        );
+        assert_eq!(packets.borrow().as_slice(), [bridged.to_vec()]);
+        assert!(router.recv(req).is_none());
+
+        // Packets claiming the local EID as source are not bridged
+        let spoofed = [1, 30, 8, 0xc8, 1, 0xaa];
+        assert_eq!(
+            router.inbound_disposition(&spoofed),
+            super::Disposition::DroppedEidConflict
+        );
+        assert_eq!(packets.borrow().len(), 1);
+
+        let response = [1, 8, 20, 0xc0 | tag.tag().0, 1, 0xbb];
+        assert_eq!(
+            router.inbound_disposition(&response),
+            super::Disposition::Delivered(req.into())
+        );
+    }
+
+    /// Frames are passed on without the binding header, rejected frames are dropped
+    #[test]
+    fn inbound_frame() {
+        use crate::Sender;
+        use mctp::{Error, Result};
+
+        /// A binding with a one byte header, 0xf0 followed by the length of the packet
+        struct FramedSender;
+
+        impl Sender for FramedSender {
+            type Packet = [u8; 64];
+
+            fn send_packet(&mut self, _eid: Eid, _pkt: &[u8]) -> Result<()> {
+                Ok(())
+            }
+
+            fn get_mtu(&self) -> usize {
+                64
+            }
+
+            fn strip_header<'f>(&self, frame: &'f [u8]) -> Result<&'f [u8]> {
+                match frame {
+                    [0xf0, len, pkt @ ..] if usize::from(*len) == pkt.len() => Ok(pkt),
+                    _ => Err(Error::InvalidInput),
+                }
+            }
+        }
+
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, FramedSender);
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+        let delivered = router.inbound_frame(&[0xf0, 6, 1, 8, 9, 0xc8, 1, 5]);
+        assert_eq!(delivered.unwrap(), Some(listener.into()));
+        let rejected = router.inbound_frame(&[0xf0, 7, 1, 8, 9, 0xc9, 1, 6]);
+        assert!(matches!(rejected, Err(Error::InvalidInput)));
+        assert_eq!(router.recv(listener).unwrap().payload, [5]);
+        assert!(router.recv(listener).is_none());
+
+        let mut plain: Router<_, 4, 4> = Router::new(Eid(8), 0, NullSender);
+        let unsupported = plain.inbound_frame(&[0xf0, 6, 1, 8, 9, 0xc8, 1, 5]);
+        assert!(matches!(unsupported, Err(Error::Unsupported)));
+    }
+
+    /// Static routes direct local and forwarded packets, the narrowest range wins
+    #[test]
+    fn static_routes() {
+        use crate::{NoHooks, Route, RouterConfig, Sender};
+        use mctp::Result;
+
+        /// Destination and port and physical address of the route taken
+        type Sent = RefCell<Vec<(Eid, Option<(u8, u64)>)>>;
+
+        struct RoutedSender<'a> {
+            sent: &'a Sent,
+        }
+
+        impl Sender for RoutedSender<'_> {
+            type Packet = [u8; 64];
+
+            fn send_packet(&mut self, eid: Eid, _pkt: &[u8]) -> Result<()> {
+                self.sent.borrow_mut().push((eid, None));
+                Ok(())
+            }
+
+            fn get_mtu(&self) -> usize {
+                64
+            }
+
+            fn send_routed(&mut self, eid: Eid, route: &Route, _pkt: &[u8]) -> Result<()> {
+                self.sent
+                    .borrow_mut()
+                    .push((eid, Some((route.port, route.physical_addr))));
+                Ok(())
+            }
+        }
+
+        let sent = RefCell::new(Vec::new());
+        let config = RouterConfig::new(Eid(8))
+            .forwarding(true)
+            .route(20..=29, 1, 0x1d)
+            .unwrap();
+        let mut router: Router<_, 4, 4> =
+            Router::new_with_config(config, 0, RoutedSender { sent: &sent }, NoHooks);
+        router.add_route(24..=24, 2, 0x30).unwrap();
+        let (first, last) = (30, 29);
+        assert!(router.add_route(first..=last, 1, 0).is_err());
+        assert_eq!(router.route(Eid(24)).map(|r| r.port), Some(2));
+        assert_eq!(router.route(Eid(40)), None);
+
+        let req = router.req(Eid(25)).unwrap();
+        router
+            .send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[1])
+            .unwrap();
+        router.inbound(&[1, 24, 9, 0xc8, 1, 0xaa]).unwrap();
+        router.inbound(&[1, 40, 9, 0xc8, 1, 0xaa]).unwrap();
+        assert!(router.remove_route(24..=24));
+        assert!(!router.remove_route(24..=24));
+        router.inbound(&[1, 24, 9, 0xc8, 1, 0xaa]).unwrap();
+        assert_eq!(
+            sent.borrow().as_slice(),
+            [
+                (Eid(25), Some((1, 0x1d))),
+                (Eid(24), Some((2, 0x30))),
+                (Eid(40), None),
+                (Eid(24), Some((1, 0x1d))),
+            ]
+        );
+    }
+
+    /// Forwarded packets are counted per port and per route
+    #[test]
+    fn forward_stats() {
+        use crate::{NoHooks, PortStats, Route, RouterConfig, Sender};
+        use mctp::Result;
+
+        /// Fails packets routed to port 3
+        struct PortSender;
+
+        impl Sender for PortSender {
+            type Packet = [u8; 64];
+
+            fn send_packet(&mut self, _eid: Eid, _pkt: &[u8]) -> Result<()> {
+                Ok(())
+            }
+
+            fn get_mtu(&self) -> usize {
+                64
+            }
+
+            fn send_routed(&mut self, _eid: Eid, route: &Route, _pkt: &[u8]) -> Result<()> {
+                if route.port == 3 {
+                    return Err(mctp::Error::TxFailure);
+                }
+                Ok(())
+            }
+        }
+
+        let config = RouterConfig::new(Eid(8))
+            .forwarding(true)
+            .route(20..=29, 1, 0x1d)
+            .unwrap()
+            .route(30..=39, 3, 0x1e)
+            .unwrap();
+        let mut router: Router<_, 4, 4> = Router::new_with_config(config, 0, PortSender, NoHooks);
+        let forward = |router: &mut Router<_, 4, 4>, dest| router.inbound(&[1, dest, 9, 0xc8, 1]);
+        forward(&mut router, 20).unwrap();
+        forward(&mut router, 21).unwrap();
+        assert!(forward(&mut router, 30).is_err());
+        forward(&mut router, 40).unwrap();
+        // local traffic isn't counted
+        let req = router.req(Eid(25)).unwrap();
+        router
+            .send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[1])
+            .unwrap();
+
+        let stats = router.forward_stats();
+        let port = |forwarded, dropped| Some(PortStats { forwarded, dropped });
+        assert_eq!(stats.port(1), port(2, 0));
+        assert_eq!(stats.port(3), port(0, 1));
+        assert_eq!(stats.port(0), port(0, 0));
+        assert_eq!(stats.port(8), None);
+        assert_eq!(
+            stats.unrouted,
+            PortStats {
+                forwarded: 1,
+                dropped: 0
+            }
+        );
+        assert_eq!(stats.route_misses(), 1);
+        let hits: Vec<_> = router
+            .route_hits()
+            .map(|(r, hits)| (r.port, hits))
+            .collect();
+        assert_eq!(hits, [(1, 2), (3, 1)]);
+
+        // a new route starts counting from 0
+        router.remove_route(20..=29);
+        router.add_route(40..=49, 2, 0x1f).unwrap();
+        forward(&mut router, 40).unwrap();
+        let hits: Vec<_> = router
+            .route_hits()
+            .map(|(r, hits)| (r.port, hits))
+            .collect();
+        assert_eq!(hits, [(2, 1), (3, 1)]);
+        router.reset_forward_stats();
+        assert_eq!(router.forward_stats().route_misses(), 0);
+        assert!(router.route_hits().all(|(_, hits)| hits == 0));
+    }
+
+    /// Networks hosted side by side don't share any routing state
+    #[test]
+    fn multiple_networks() {
+        use crate::networks::Networks;
+
+        let buf_out_a = RefCell::new(Vec::new());
+        let buf_out_b = RefCell::new(Vec::new());
+        let config = |id| crate::RouterConfig::new(Eid(8)).network_id([id; 16]);
+        let mut networks = Networks::new([
+            Router::<_, 4, 4>::new_with_config(
+                config(1),
+                0,
+                BufferSender::<255> {
+                    packets: &buf_out_a,
+                },
+                crate::NoHooks,
+            ),
+            Router::<_, 4, 4>::new_with_config(
+                config(2),
+                0,
+                BufferSender::<255> {
+                    packets: &buf_out_b,
+                },
+                crate::NoHooks,
+            ),
+        ]);
+        assert_eq!(networks.find([2; 16]), Some(1));
+        assert_eq!(networks.find([3; 16]), None);
+
+        let listener = networks
+            .network_mut(0)
+            .unwrap()
+            .listener(mctp::MsgType(1))
+            .unwrap();
+
+        // The same EID in the other network has no listener
+        let pkt = [1, 8, 20, 0xc8, 1, 0xaa];
+        assert_eq!(networks.inbound(1, &pkt).unwrap(), None);
+        assert_eq!(
+            networks.inbound(0, &pkt).unwrap(),
+            Some(crate::Handle::Listener(listener))
+        );
+        assert!(networks.inbound(2, &pkt).is_err());
+
+        // Responses leave through the port of the network the request came from
+        let net = networks.network_mut(0).unwrap();
+        let info = net.recv(listener).map(|msg| (msg.source, msg.tag)).unwrap();
+        net.send(
+            Some(info.0),
+            mctp::MsgType(1),
+            Some(mctp::Tag::Unowned(info.1.tag())),
+            mctp::MsgIC(false),
+            listener,
+            &[0xbb],
+        )
+        .unwrap();
+        assert_eq!(buf_out_a.borrow().len(), 1);
+        assert!(buf_out_b.borrow().is_empty());
+        assert!(networks.poll().is_ok());
+    }
+
+    /// CCI responses are correlated with their request by message tag and opcode
+    #[test]
+    fn cci_channel() {
+        use crate::cci::{CciHeader, CciRequester, CciResponder, MSG_TYPE_CXL_CCI};
+
+        let buf_out_a = RefCell::new(Vec::new());
+        let outbound_a: BufferSender<255> = BufferSender {
+            packets: &buf_out_a,
+        };
+        let buf_out_b = RefCell::new(Vec::new());
+        let outbound_b: BufferSender<255> = BufferSender {
+            packets: &buf_out_b,
+        };
+        let router_a: Router<_, 4, 4> = Router::new(Eid(42), 0, outbound_a);
+        let router_b: Router<_, 4, 4> = Router::new(Eid(112), 0, outbound_b);
+        let router_a = crate::shared::SharedRouter::new(router_a);
+        let router_b = crate::shared::SharedRouter::new(router_b);
+
+        let mut responder = CciResponder::new(router_a.listener(MSG_TYPE_CXL_CCI).unwrap());
+        let mut requester = CciRequester::new(router_b.req(Eid(42)).unwrap(), MSG_TYPE_CXL_CCI);
+        let mut buf = [0; 64];
+
+        // Identify
+        let sent = requester.send(0x0001, &[]).unwrap();
+        for pkt in buf_out_b.borrow().iter() {
+            router_a.inbound(pkt).unwrap();
+        }
+        let request = responder.receive(&mut buf).unwrap().unwrap();
+        assert_eq!(request.header, sent);
+        responder.respond(&request, 0, &[1, 2, 3, 4]).unwrap();
+        for pkt in buf_out_a.borrow().iter() {
+            router_b.inbound(pkt).unwrap();
+        }
+
+        let (header, len) = requester.receive(&mut buf).unwrap().unwrap();
+        assert!(!header.request);
+        assert_eq!(
+            (header.tag, header.opcode, header.payload_len),
+            (0, 0x0001, 4)
+        );
+        assert_eq!(buf.get(12..len), Some([1, 2, 3, 4].as_slice()));
+
+        let header = CciHeader {
+            payload_len: 0x1f_ffff,
+            background: true,
+            ..header
+        };
+        assert_eq!(CciHeader::parse(&header.to_bytes().unwrap()), Some(header));
+        let header = CciHeader {
+            payload_len: 0x20_0000,
+            ..header
+        };
+        assert!(header.to_bytes().is_err());
+    }
+
+    /// Sources exceeding the rate limit are dropped before reassembly
+    #[test]
+    fn rate_limit() {
+        let config =
+            crate::RouterConfig::new(Eid(42)).rate_limit(Some(crate::RateLimit::new(2, 10)));
+        let mut router: Router<_, 8, 8> =
+            Router::new_with_config(config, 0, NullSender, crate::NoHooks);
+        router.listener(mctp::MsgType(1)).unwrap();
+
+        let request = |src| [1, 42, src, 0xc8, 1, 0];
+        assert!(!router.inbound_disposition(&request(8)).is_dropped());
+        assert!(!router.inbound_disposition(&request(8)).is_dropped());
+        assert_eq!(
+            router.inbound_disposition(&request(8)),
+            super::Disposition::DroppedRateLimited
+        );
+        // other sources have their own bucket
+        assert!(!router.inbound_disposition(&request(9)).is_dropped());
+        assert_eq!(router.rate_limited(), 1);
+
+        // one token per 100 ms
+        router.update(100).unwrap();
+        assert!(!router.inbound_disposition(&request(8)).is_dropped());
+        assert!(router.inbound_disposition(&request(8)).is_dropped());
+        assert_eq!(router.rate_limited(), 2);
+
+        router.set_rate_limit(None);
+        assert_ne!(
+            router.inbound_disposition(&request(8)),
+            super::Disposition::DroppedRateLimited
+        );
+        assert_eq!(router.rate_limited(), 0);
+    }
+
+    #[derive(Default)]
+    struct TypeFilter {
+        seen: Vec<(Eid, mctp::MsgType, usize)>,
+    }
+
+    impl Hooks for TypeFilter {
+        fn first_fragment(&mut self, fragment: &crate::FirstFragment<'_>) -> bool {
+            self.seen
+                .push((fragment.source, fragment.typ, fragment.payload.len()));
+            fragment.typ != mctp::MsgType(2)
+        }
+    }
+
+    /// The first fragment hook rejects messages before reassembly
+    #[test]
+    fn first_fragment_hook() {
+        let mut router: Router<_, 8, 8, TypeFilter> =
+            Router::new_with_hooks(Eid(42), 0, NullSender, TypeFilter::default());
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+        router.listener(mctp::MsgType(2)).unwrap();
+
+        assert_eq!(
+            router.inbound_disposition(&[1, 42, 8, 0xc8, 2, 0, 0]),
+            super::Disposition::DroppedByHook
+        );
+        // continuation packets of the rejected message find no reassembly
+        assert_eq!(
+            router.inbound_disposition(&[1, 42, 8, 0x58, 0]),
+            super::Disposition::DroppedReassemblyError
+        );
+        assert_eq!(
+            router.inbound_disposition(&[1, 42, 8, 0xc8, 1, 0]),
+            super::Disposition::Delivered(listener.into())
+        );
+        assert_eq!(
+            router.hooks().seen,
+            vec![(Eid(8), mctp::MsgType(2), 2), (Eid(8), mctp::MsgType(1), 1)]
+        );
+    }
+
+    /// Messages queued from interrupt context are sent when the queue is flushed
+    #[test]
+    fn send_queue() {
+        let packets = RefCell::new(Vec::new());
+        let outbound: BufferSender<64> = BufferSender { packets: &packets };
+        let mut router: Router<_, 8, 8> = Router::new(Eid(42), 0, outbound);
+        let req = router.req(Eid(112)).unwrap();
+
+        let mut queue: crate::queue::SendQueue<2, 111> = crate::queue::SendQueue::new();
+        let (mut producer, mut consumer) = queue.split();
+        let send = |producer: &mut crate::queue::SendProducer<'_, 2, 111>, payload: &[u8]| {
+            producer.try_send(
+                None,
+                mctp::MsgType(1),
+                None,
+                mctp::MsgIC(false),
+                req,
+                payload,
+            )
+        };
+        assert!(send(&mut producer, &[7; 100]).is_ok());
+        assert!(send(&mut producer, &[7]).is_ok());
+        assert!(matches!(
+            send(&mut producer, &[7]),
+            Err(mctp::Error::NoSpace)
+        ));
+        assert!(packets.borrow().is_empty());
+
+        assert!(matches!(
+            consumer.send_next(&mut router),
+            Some(Ok(super::SendReport {
+                tag: mctp::Tag::Owned(_),
+                packets: 2,
+                queued: true,
+                ..
+            }))
+        ));
+        assert_eq!(packets.borrow().len(), 2);
+        assert_eq!(consumer.flush(&mut router), 1);
+        assert_eq!(packets.borrow().len(), 3);
+        assert!(consumer.send_next(&mut router).is_none());
+
+        // stale handles fail when the queue is flushed
+        router.unbind(req).unwrap();
+        assert!(send(&mut producer, &[7]).is_ok());
+        let err = consumer.send_next(&mut router).unwrap().unwrap_err();
+        assert!(matches!(err.error(), mctp::Error::BadArgument));
+        assert_eq!(queue.dropped(), 1);
+    }
+
+    /// Errors carry the handle, peer and tag they occurred on
+    #[test]
+    fn error_context() {
+        let mut router: Router<_, 8, 8> = Router::new(Eid(42), 0, NullSender);
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+
+        // responses need a destination
+        let err = router
+            .send(
+                None,
+                mctp::MsgType(1),
+                Some(mctp::Tag::Unowned(mctp::TagValue(3))),
+                mctp::MsgIC(false),
+                listener,
+                &[],
+            )
+            .unwrap_err();
+        assert!(matches!(err.error(), mctp::Error::InvalidInput));
+        assert_eq!(err.handle(), Some(listener.into()));
+        assert_eq!(err.eid(), None);
+        assert_eq!(err.tag(), Some(mctp::Tag::Unowned(mctp::TagValue(3))));
+
+        router.unbind(listener).unwrap();
+        let err = router.unbind(listener).unwrap_err();
+        assert_eq!(err.handle(), Some(listener.into()));
+        assert!(matches!(err.into_inner(), mctp::Error::BadArgument));
+    }
+
+    /// Create two routers, send a request from B to A and receive the echo response
+    #[test]
+    fn roundtrip() {
+        const REQ_HANDLES: usize = 8;
+        const LISTENER_HANDLES: usize = 8;
+        let buf_out_a = RefCell::new(Vec::new());
+        let outbound_a: BufferSender<255> = BufferSender {
+            packets: &buf_out_a,
+        };
+        let mut router_a: Router<_, LISTENER_HANDLES, REQ_HANDLES> =
+            Router::new(Eid(42), 0, outbound_a);
+
+        let buf_out_b = RefCell::new(Vec::new());
+        let outbound_b: BufferSender<255> = BufferSender {
+            packets: &buf_out_b,
+        };
+        let mut router_b: Router<_, LISTENER_HANDLES, REQ_HANDLES> =
+            Router::new(Eid(112), 0, outbound_b);
+
+        // create a new listener and expect the cookie value to be 0 (raw index of the underlying table)
+        let listener = router_a.listener(mctp::MsgType(0)).unwrap();
+
+        let requester = router_b.req(Eid(42)).unwrap();
+
+        let payload = [1; 300]; // 300 byte payload to exceed 255 byte MTU
+        router_b
+            .send(
+                None,
+                mctp::MsgType(0),
+                None,
+                mctp::MsgIC(false),
+                requester,
+                &payload,
+            )
+            .unwrap();
+
+        let packets = buf_out_b.borrow();
+        for pkt in packets.as_slice() {
+            router_a.inbound(pkt).unwrap();
+        }
+
+        let message = router_a.recv(listener).unwrap();
+        let (msg_source, msg_typ, msg_tag, msg_ic) =
+            (message.source, message.typ, message.tag, message.ic);
+        let msg_payload: Vec<_> = message.payload.into();
+        drop(message);
+
+        assert_eq!(
+            &msg_payload, &payload,
+            "Received payload does not match send payload"
+        );
+        assert!(
+            msg_tag.is_owner(),
+            "Received message is not a request (tag is unowned)"
+        );
+
+        router_a
+            .send(
+                Some(msg_source),
+                msg_typ,
+                Some(super::Tag::Unowned(msg_tag.tag())),
+                msg_ic,
+                listener,
+                &msg_payload,
+            )
+            .unwrap();
+
+        let packets = buf_out_a.borrow();
+        for pkt in packets.as_slice() {
+            router_b.inbound(pkt).unwrap();
+        }
+
+        let message = router_b.recv(requester).unwrap();
+        assert_eq!(
+            message.payload, &payload,
+            "Received payload does not match send payload"
+        );
+        assert!(
+            !message.tag.is_owner(),
+            "Received message is not a response (tag is unowned)"
+        );
+    }
+
+    /// The state dump lists handles, routes, neighbors and reassemblies
+    #[test]
+    fn dump_state() {
+        use mctp::{MsgIC, MsgType};
+
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, NullSender);
+        router.listener(MsgType(1)).unwrap();
+        let req = router.req(Eid(9)).unwrap();
+        router
+            .send(None, MsgType(1), None, MsgIC(false), req, &[0x80])
+            .unwrap();
+        router.add_route(20..=29, 1, 0x1d).unwrap();
+        router.set_mtu(Eid(9), 68).unwrap();
+        router.update(10).unwrap();
+        // First of two packets of a request from EID 10
+        router.inbound(&[1, 8, 10, 0x88, 1, 0xaa]).unwrap();
+        router.update(15).unwrap();
+
+        let mut dump = String::new();
+        router.dump_state(&mut dump).unwrap();
+        for line in [
+            "eid 8 static 8 now 15ms",
+            "listener 0 type 1 queued 0 denied 0",
+            "route 20-29 port 1 addr 0x1d hits 0",
+            "mtu 9 68",
+            "reassembly 10 tag 0 type 1 bytes 1 idle 5ms",
+            "tx messages 1 packets 1 bytes 6 busy retries 0",
+        ] {
+            assert!(
+                dump.lines().any(|l| l == line),
+                "{line:?} missing in {dump}"
+            );
+        }
+        assert!(
+            dump.lines()
+                .any(|l| l.starts_with("request ") && l.contains(" eid 9 pending tag ")),
+            "{dump}"
+        );
+    }
+
+    /// Polling receivers are woken by deliveries and unbinding
+    #[test]
+    fn poll_recv() {
+        use core::task::{Context, Poll};
+        use mctp::{MsgIC, MsgType};
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::task::Wake;
+
+        struct CountWakes(AtomicUsize);
+
+        impl Wake for CountWakes {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let wakes = Arc::new(CountWakes(AtomicUsize::new(0)));
+        let waker = wakes.clone().into();
+        let mut cx = Context::from_waker(&waker);
+        let woken = || wakes.0.load(Ordering::Relaxed);
+
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, NullSender);
+        let listener = router.listener(MsgType(1)).unwrap();
+        let mut buf = [0; 8];
+        assert!(router.poll_recv(listener, &mut buf, &mut cx).is_pending());
+        // Polling again replaces the waker
+        assert!(router.poll_recv(listener, &mut buf, &mut cx).is_pending());
+
+        router.inbound(&[1, 8, 9, 0xc8, 1, 0xaa]).unwrap();
+        assert_eq!(woken(), 1);
+        let Poll::Ready(Ok(info)) = router.poll_recv(listener, &mut buf, &mut cx) else {
+            panic!("message not ready");
+        };
+        assert_eq!(buf.get(..info.len), Some(&[0xaa][..]));
+
+        let req = router.req(Eid(9)).unwrap();
+        router
+            .send(None, MsgType(1), None, MsgIC(false), req, &[0x80])
+            .unwrap();
+        assert!(matches!(
+            router.poll_send_complete(req, &mut cx),
+            Poll::Ready(Ok(()))
+        ));
+        assert!(router.poll_recv(req, &mut buf, &mut cx).is_pending());
+        router.unbind(req).unwrap();
+        assert_eq!(woken(), 2);
+        assert!(matches!(
+            router.poll_recv(req, &mut buf, &mut cx),
+            Poll::Ready(Err(_))
+        ));
+    }
+
+    /// Threads exchange a request and response through two synchronized routers
+    #[cfg(feature = "std")]
+    #[test]
+    fn sync_router() {
+        use crate::sync::{SyncListener, SyncRequest, SyncRouter};
+        use crate::{ArrayTables, ManualClock, NoHooks, RouterConfig, Sender};
+        use mctp::{Listener, MsgType, ReqChannel, RespChannel};
+        use std::sync::mpsc;
+        use std::time::Duration;
+
+        struct ChannelSender(mpsc::Sender<Vec<u8>>);
+
+        impl Sender for ChannelSender {
+            type Packet = [u8; 64];
+
+            fn send_packet(&mut self, _eid: Eid, pkt: &[u8]) -> mctp::Result<()> {
+                self.0
+                    .send(pkt.to_vec())
+                    .map_err(|_| mctp::Error::TxFailure)
+            }
+
+            fn get_mtu(&self) -> usize {
+                64
+            }
+        }
+
+        type Tables = ArrayTables<4, 4>;
+        type Node = SyncRouter<ChannelSender, Tables, NoHooks, ManualClock>;
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Node>();
+        assert_send_sync::<SyncRequest<ChannelSender, Tables, NoHooks, ManualClock>>();
+        assert_send_sync::<SyncListener<ChannelSender, Tables, NoHooks, ManualClock>>();
+
+        let (to_b, from_a) = mpsc::channel();
+        let (to_a, from_b) = mpsc::channel();
+        let new = |eid, sender| -> Node {
+            SyncRouter::new(Router::new_with_config(
+                RouterConfig::new(Eid(eid)),
+                0,
+                sender,
+                NoHooks,
+            ))
+        };
+        let a = new(8, ChannelSender(to_b));
+        let b = new(9, ChannelSender(to_a));
+        let pump = |router: Node, packets: mpsc::Receiver<Vec<u8>>| {
+            std::thread::spawn(move || {
+                for pkt in packets {
+                    router.inbound(&pkt).unwrap();
+                }
+            })
+        };
+        // The pumps hold the routers and with them each other's senders, they end with the test
+        pump(a.clone(), from_b);
+        pump(b.clone(), from_a);
+
+        let mut listener = b
+            .listener(MsgType(1), Some(Duration::from_secs(5)))
+            .unwrap();
+        let responder = std::thread::spawn(move || {
+            let mut buf = [0; 16];
+            let (typ, _, payload, mut response) = listener.recv(&mut buf).unwrap();
+            assert_eq!((typ, &*payload), (MsgType(1), &[0x80, 0x01][..]));
+            response.send(&[0x00, 0x02]).unwrap();
+        });
+
+        let mut req = a.req(Eid(9), Some(Duration::from_secs(5))).unwrap();
+        req.send(MsgType(1), &[0x80, 0x01]).unwrap();
+        let mut buf = [0; 16];
+        let (typ, _, payload) = req.recv(&mut buf).unwrap();
+        assert_eq!((typ, &*payload), (MsgType(1), &[0x00, 0x02][..]));
+        responder.join().unwrap();
+        assert_eq!(req.remote_eid(), Eid(9));
+
+        // Dropping a channel releases its handle
+        drop(req);
+        assert_eq!(a.with(|r| r.requests().count()).unwrap(), 0);
+
+        // Messages delivered within with() wake receivers, also those waiting forever
+        let mut listener = a.listener(MsgType(2), Some(Duration::MAX)).unwrap();
+        let receiver = std::thread::spawn(move || {
+            let mut buf = [0; 4];
+            listener.recv(&mut buf).map(|(typ, ..)| typ)
+        });
+        a.with(|r| r.inbound(&[1, 8, 9, 0xc8, 2, 0x80]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(receiver.join().unwrap().unwrap(), MsgType(2));
+    }
+
+    /// Type-erased channels work on any router, including one sending through `dyn Sender`
+    #[test]
+    fn erased_router() {
+        use crate::erased::{DynListener, DynRequest};
+        use crate::shared::SharedRouter;
+        use crate::test_util::transfer;
+        use crate::{NoHooks, RouterConfig, Sender};
+        use mctp::{MsgIC, MsgType};
+
+        let packets = RefCell::new(Vec::new());
+        let mut sender = BufferSender::<64>::new(&packets);
+        let sender: &mut dyn Sender<Packet = [u8; 64]> = &mut sender;
+        let requester: Router<_, 4, 4> =
+            Router::new_with_config(RouterConfig::new(Eid(8)), 0, sender, NoHooks);
+        let requester = SharedRouter::new(requester);
+        let responder = RefCell::new(Router::<_, 4, 4>::new(
+            Eid(9),
+            0,
+            BufferSender::<64>::new(&packets),
+        ));
+
+        let listener = DynListener::new(&responder, MsgType(1)).unwrap();
+        let req = DynRequest::new(requester.as_dyn(), Eid(9)).unwrap();
+        req.send(MsgType(1), MsgIC(false), &[&[0x80, 0x01]])
+            .unwrap();
+        transfer(&packets, &mut responder.borrow_mut()).unwrap();
+
+        let mut buf = [0; 8];
+        let request = listener.try_recv(&mut buf).unwrap().unwrap();
+        assert_eq!(buf.get(..request.len), Some(&[0x80, 0x01][..]));
+        listener
+            .respond(&request, MsgIC(false), &[&[0x00]])
+            .unwrap();
+        requester.with(|r| transfer(&packets, r)).unwrap().unwrap();
+        let response = req.try_recv(&mut buf).unwrap().unwrap();
+        assert_eq!(buf.get(..response.len), Some(&[0x00][..]));
+
+        req.unbind().unwrap();
+        listener.unbind().unwrap();
+        assert_eq!(requester.with(|r| r.requests().count()).unwrap(), 0);
+    }
+
+    #[test]
+    fn borrowed_router() {
+        use crate::test_util::{BufferSender, transfer};
+        use crate::{ArrayTables, BorrowedRouter, ManualClock, NoHooks, RouterConfig, Sender};
+        use mctp::{MsgIC, MsgType};
+
+        // Independent of the table sizes
+        fn exchange<S: Sender>(
+            packets: &RefCell<std::vec::Vec<std::vec::Vec<u8>>>,
+            requester: &mut BorrowedRouter<'_, S>,
+            responder: &mut BorrowedRouter<'_, S>,
+        ) -> bool {
+            let listener = responder.listener(MsgType(1)).unwrap();
+            let req = requester.req(Eid(9)).unwrap();
+            requester
+                .send(None, MsgType(1), None, MsgIC(false), req, &[0x80])
+                .unwrap();
+            transfer(packets, responder).unwrap();
+            responder.recv(listener).is_some()
+        }
+
+        let packets = RefCell::new(std::vec::Vec::new());
+        let mut small = ArrayTables::<1, 1>::new();
+        let mut large = ArrayTables::<8, 4>::new();
+        let mut requester = BorrowedRouter::new_with_tables(
+            RouterConfig::new(Eid(8)),
+            ManualClock::new(0),
+            BufferSender::<64>::new(&packets),
+            NoHooks,
+            &mut small,
+        );
+        let mut responder = BorrowedRouter::new_with_tables(
+            RouterConfig::new(Eid(9)),
+            ManualClock::new(0),
+            BufferSender::<64>::new(&packets),
+            NoHooks,
+            &mut large,
+        );
+        assert!(exchange(&packets, &mut requester, &mut responder));
+        assert!(requester.req(Eid(9)).is_err());
+        assert_eq!(responder.listeners().count(), 1);
+    }
+
+    #[test]
+    fn init_in_place() {
+        use crate::test_util::NullSender;
+        use crate::{ArrayTables, BorrowedRouter, ManualClock, NoHooks, RouterConfig};
+        use core::mem::MaybeUninit;
+        use mctp::MsgType;
+
+        let mut tables = ArrayTables::<2, 2>::new();
+        let mut slot = MaybeUninit::uninit();
+        let router = BorrowedRouter::init_in_place(
+            &mut slot,
+            RouterConfig::new(Eid(8)),
+            ManualClock::new(0),
+            NullSender,
+            NoHooks,
+            &mut tables,
+        );
+        assert_eq!(router.get_eid(), Eid(8));
+        router.listener(MsgType(1)).unwrap();
+        assert_eq!(router.listeners().count(), 1);
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn heapless_queue() {
+        use crate::heapless_queue::{QueueSender, QueuedPacket, inbound_from};
+        use crate::test_util::NullSender;
+        use heapless::spsc::Queue;
+        use mctp::{MsgIC, MsgType};
+
+        let mut outbound: Queue<QueuedPacket<64>, 3> = Queue::new();
+        let (producer, mut pending) = outbound.split();
+        let mut requester: Router<_, 2, 2> = Router::new(Eid(8), 0, QueueSender::new(producer));
+        let mut responder: Router<_, 2, 2> = Router::new(Eid(9), 0, NullSender);
+        let listener = responder.listener(MsgType(1)).unwrap();
+
+        // Three packets don't fit
+        let req = requester.req(Eid(9)).unwrap();
+        let err = requester
+            .send(None, MsgType(1), None, MsgIC(false), req, &[0; 150])
+            .unwrap_err();
+        assert!(matches!(err.error(), mctp::Error::NoSpace));
+        assert_eq!(requester.sender().rejected(), 1);
+        while pending.dequeue().is_some() {}
+
+        requester
+            .send(None, MsgType(1), None, MsgIC(false), req, &[0; 100])
+            .unwrap();
+        let mut inbound: Queue<heapless::Vec<u8, 64>, 4> = Queue::new();
+        let (mut rx, mut consumer) = inbound.split();
+        while let Some((eid, pkt)) = pending.dequeue() {
+            assert_eq!(eid, Eid(9));
+            rx.enqueue(pkt).unwrap();
+        }
+        assert_eq!(inbound_from(&mut consumer, &mut responder), 2);
+        assert!(responder.recv(listener).is_some());
+    }
+
+    #[test]
+    fn reassembly_progress() {
+        use crate::test_util::{BufferSender, transfer};
+        use mctp::{MsgIC, MsgType};
+
+        let packets = RefCell::new(std::vec::Vec::new());
+        let mut requester: Router<_, 2, 2> =
+            Router::new(Eid(8), 0, BufferSender::<64>::new(&packets));
+        let mut responder: Router<_, 2, 2> =
+            Router::new(Eid(9), 0, BufferSender::<64>::new(&packets));
+        let listener = responder.listener(MsgType(1)).unwrap();
+        let other = responder.listener(MsgType(2)).unwrap();
+
+        let req = requester.req(Eid(9)).unwrap();
+        requester
+            .send(None, MsgType(1), None, MsgIC(false), req, &[0; 150])
+            .unwrap();
+        let last = packets.borrow_mut().pop().unwrap();
+        assert_eq!(transfer(&packets, &mut responder).unwrap(), 2);
+        responder.update(100).unwrap();
+
+        let progress: std::vec::Vec<_> = responder.reassemblies().collect();
+        assert_eq!(progress.len(), 1);
+        let info = progress.first().unwrap();
+        assert_eq!((info.source, info.typ), (Eid(8), MsgType(1)));
+        assert!(info.tag.is_owner());
+        assert_eq!(info.bytes, 2 * 60 - 1);
+        assert_eq!((info.age_millis, info.idle_millis), (100, 100));
+        assert_eq!(responder.reassemblies_for(listener).count(), 1);
+        assert_eq!(responder.reassemblies_for(other).count(), 0);
+
+        responder.inbound(&last).unwrap();
+        assert_eq!(responder.reassemblies().count(), 0);
+
+        // The response is tracked for the request
+        let request = crate::MessageInfo::from(&responder.recv(listener).unwrap());
+        responder.respond(listener, &request, &[&[0; 100]]).unwrap();
+        let last = packets.borrow_mut().pop().unwrap();
+        transfer(&packets, &mut requester).unwrap();
+        assert_eq!(requester.reassemblies_for(req).count(), 1);
+        requester.inbound(&last).unwrap();
+        assert_eq!(requester.reassemblies_for(req).count(), 0);
+    }
+
+    /// Dropped receive futures lose neither messages nor waker slots
+    #[test]
+    fn cancel_safe_recv() {
+        use crate::WAKER_TABLE_SIZE;
+        use crate::shared::SharedRouter;
+        use crate::test_util::{BufferSender, transfer};
+        use core::future::Future;
+        use core::task::{Context, Poll};
+        use mctp::{MsgIC, MsgType};
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::task::Wake;
+
+        struct CountWakes(AtomicUsize);
+
+        impl Wake for CountWakes {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let wakes = Arc::new(CountWakes(AtomicUsize::new(0)));
+        let waker = wakes.clone().into();
+        let mut cx = Context::from_waker(&waker);
+        let packets = RefCell::new(std::vec::Vec::new());
+        let mut requester: Router<_, 1, 1> =
+            Router::new(Eid(8), 0, BufferSender::<64>::new(&packets));
+        let router: Router<_, WAKER_TABLE_SIZE, 1> =
+            Router::new(Eid(9), 0, BufferSender::<64>::new(&packets));
+        let router = SharedRouter::new(router);
+
+        // Fill the waker table with futures that are dropped
+        let listeners: std::vec::Vec<_> = (1..=WAKER_TABLE_SIZE as u8)
+            .map(|t| router.listener(MsgType(t)).unwrap())
+            .collect();
+        for listener in listeners.iter() {
+            let mut buf = [0; 8];
+            let fut = core::pin::pin!(listener.recv(&mut buf));
+            assert!(fut.poll(&mut cx).is_pending());
+        }
+        let last = router.with(|r| r.req(Eid(8))).unwrap().unwrap();
+        let mut buf = [0; 8];
+        let poll = router
+            .with(|r| r.poll_recv(last, &mut buf, &mut cx))
+            .unwrap();
+        assert!(poll.is_pending());
+        assert_eq!(wakes.0.load(Ordering::Relaxed), 0);
+
+        // A message larger than the buffer stays queued
+        let listener = listeners.first().unwrap();
+        let req = requester.req(Eid(9)).unwrap();
+        requester
+            .send(None, MsgType(1), None, MsgIC(false), req, &[7; 100])
+            .unwrap();
+        router.with(|r| transfer(&packets, r)).unwrap().unwrap();
+        assert_eq!(wakes.0.load(Ordering::Relaxed), 0);
+        let mut small = [0; 8];
+        let mut fut = core::pin::pin!(listener.recv(&mut small));
+        match fut.as_mut().poll(&mut cx) {
+            Poll::Ready(Err(e)) => assert!(matches!(e.error(), mctp::Error::NoSpace)),
+            _ => panic!("expected NoSpace"),
+        }
+        assert_eq!(
+            router.with(|r| r.queued(listener.handle())).unwrap(),
+            Some(1)
+        );
+        let mut large = [0; 128];
+        let info = listener.try_recv(&mut large).unwrap().unwrap();
+        assert_eq!(info.len, 100);
+        assert_eq!(
+            router.with(|r| r.queued(listener.handle())).unwrap(),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn handle_context() {
+        use crate::Disposition;
+        use crate::test_util::{BufferSender, NullSender, transfer};
+        use mctp::{MsgIC, MsgType};
+
+        let packets = RefCell::new(std::vec::Vec::new());
+        let mut requester: Router<_, 1, 1> =
+            Router::new(Eid(8), 0, BufferSender::<64>::new(&packets));
+        let mut responder: Router<_, 2, 1> = Router::new(Eid(9), 0, NullSender);
+        let listener = responder.listener(MsgType(1)).unwrap();
+        let other = responder.listener(MsgType(2)).unwrap();
+        assert_eq!(responder.handle_context(listener), Some(0));
+        responder.set_handle_context(listener, 0x1234).unwrap();
+        responder.set_handle_context(other, 7).unwrap();
+
+        let req = requester.req(Eid(9)).unwrap();
+        requester.set_handle_context(req, 42).unwrap();
+        assert_eq!(requester.requests().next().unwrap().context, 42);
+        requester
+            .send(None, MsgType(1), None, MsgIC(false), req, &[1])
+            .unwrap();
+        let pkt = packets.borrow_mut().pop().unwrap();
+        let Disposition::Delivered(handle) = responder.inbound_disposition(&pkt) else {
+            panic!("not delivered");
+        };
+        assert_eq!(responder.handle_context(handle), Some(0x1234));
+
+        // Rebinding starts over
+        responder.unbind(listener).unwrap();
+        assert_eq!(responder.handle_context(listener), None);
+        assert!(responder.set_handle_context(listener, 1).is_err());
+        let listener = responder.listener(MsgType(1)).unwrap();
+        assert_eq!(responder.handle_context(listener), Some(0));
+        assert_eq!(transfer(&packets, &mut responder).unwrap(), 0);
+    }
+
+    #[test]
+    fn local_eids() {
+        use crate::test_util::{BufferSender, transfer};
+        use crate::{MessageInfo, SendArgs};
+        use mctp::{MsgIC, MsgType};
+
+        let to_responder = RefCell::new(std::vec::Vec::new());
+        let to_requester = RefCell::new(std::vec::Vec::new());
+        let mut requester: Router<_, 2, 1> =
+            Router::new(Eid(8), 0, BufferSender::<64>::new(&to_responder));
+        let mut responder: Router<_, 1, 1> =
+            Router::new(Eid(9), 0, BufferSender::<64>::new(&to_requester));
+        let listener = responder.listener(MsgType(1)).unwrap();
+
+        assert!(matches!(
+            responder.add_local_eid(Eid(0xff)),
+            Err(mctp::Error::BadArgument)
+        ));
+        responder.add_local_eid(Eid(20)).unwrap();
+        responder.add_local_eid(Eid(20)).unwrap();
+        assert!(responder.local_eids().eq([Eid(9), Eid(20)]));
+
+        // Requests to either EID are delivered, the response comes from the addressed one
+        for dest in [Eid(9), Eid(20)] {
+            let req = requester.req(dest).unwrap();
+            requester
+                .send(None, MsgType(1), None, MsgIC(false), req, &[1])
+                .unwrap();
+            assert_eq!(transfer(&to_responder, &mut responder).unwrap(), 1);
+            let info = MessageInfo::from(&responder.recv(listener).unwrap());
+            assert_eq!(info.dest, dest);
+            responder.respond(listener, &info, &[&[2]]).unwrap();
+            assert_eq!(to_requester.borrow().first().unwrap().get(2), Some(&dest.0));
+            assert_eq!(transfer(&to_requester, &mut requester).unwrap(), 1);
+            assert!(requester.recv(req).is_some());
+            requester.unbind(req).unwrap();
+        }
+
+        // Requests from a secondary EID, per handle or per message
+        let req = responder.req(Eid(8)).unwrap();
+        assert!(responder.set_source_eid(req, Some(Eid(21))).is_err());
+        responder.set_source_eid(req, Some(Eid(20))).unwrap();
+        responder
+            .send(None, MsgType(2), None, MsgIC(false), req, &[3])
+            .unwrap();
+        assert_eq!(to_requester.borrow_mut().pop().unwrap().get(2), Some(&20));
+        let args = SendArgs::request(MsgType(2)).source(Eid(9)).payload(&[4]);
+        responder.send_msg(req, args).unwrap();
+        assert_eq!(to_requester.borrow_mut().pop().unwrap().get(2), Some(&9));
+        let args = SendArgs::request(MsgType(2)).source(Eid(21)).payload(&[5]);
+        let err = responder.send_msg(req, args).unwrap_err();
+        assert!(matches!(err.error(), mctp::Error::BadArgument));
+
+        // Requests to a removed EID are answered from the own EID
+        let req = requester.req(Eid(20)).unwrap();
+        requester
+            .send(None, MsgType(1), None, MsgIC(false), req, &[1])
+            .unwrap();
+        transfer(&to_responder, &mut responder).unwrap();
+        let info = MessageInfo::from(&responder.recv(listener).unwrap());
+        assert!(responder.remove_local_eid(Eid(20)));
+        responder.respond(listener, &info, &[&[2]]).unwrap();
+        assert_eq!(to_requester.borrow_mut().pop().unwrap().get(2), Some(&9));
+        requester.unbind(req).unwrap();
+
+        // Removed EIDs are foreign again
+        assert!(!responder.remove_local_eid(Eid(20)));
+        let req = requester.req(Eid(20)).unwrap();
+        requester
+            .send(None, MsgType(1), None, MsgIC(false), req, &[1])
+            .unwrap();
+        transfer(&to_responder, &mut responder).unwrap();
+        assert!(responder.recv(listener).is_none());
+    }
+
+    /// Single packet responses and router answered control requests bypass the stack
+    #[test]
+    fn single_packet_responses() {
+        use crate::control::{CMD_GET_ENDPOINT_ID, MSG_TYPE_CONTROL};
+        use crate::{Disposition, MessageInfo};
+        use mctp::MsgType;
+
+        let packets = RefCell::new(std::vec::Vec::new());
+        let mut router: Router<_, 1, 1> = Router::new(Eid(8), 0, BufferSender::<64>::new(&packets));
+        let listener = router.listener(MsgType(1)).unwrap();
+        router.set_max_reassemblies(1).unwrap();
+
+        // A partial message occupies the only reassembly context
+        let mut first = [0; 64];
+        first[..5].copy_from_slice(&[1, 8, 10, 0x88, 1]);
+        assert_eq!(router.inbound_disposition(&first), Disposition::Incomplete);
+        let other = [1, 8, 11, 0x89, 1, 0];
+        assert_eq!(
+            router.inbound_disposition(&other),
+            Disposition::DroppedNoContext
+        );
+
+        // Single packet control requests don't need one
+        let request = [
+            1,
+            8,
+            9,
+            0xc8 | 3,
+            MSG_TYPE_CONTROL.0,
+            0x80,
+            CMD_GET_ENDPOINT_ID,
+        ];
+        assert_eq!(
+            router.inbound_disposition(&request),
+            Disposition::ControlAnswered
+        );
+        let response = packets.borrow_mut().pop().unwrap();
+        assert_eq!(response.get(..5), Some([1, 9, 8, 0xc3, 0].as_slice()));
+
+        // Complete the message, then respond in one and in two packets
+        let last = [1, 8, 10, 0x58, 2];
+        assert!(matches!(
+            router.inbound_disposition(&last),
+            Disposition::Delivered(_)
+        ));
+        let info = MessageInfo::from(&router.recv(listener).unwrap());
+        router.respond(listener, &info, &[&[1, 2]]).unwrap();
+        assert_eq!(
+            packets.borrow_mut().pop(),
+            Some(std::vec![1, 10, 8, 0xc0, 1, 1, 2])
+        );
+        let report = router.respond(listener, &info, &[&[0; 100]]).unwrap();
+        assert_eq!(report.packets, 2);
+        let sent = packets.borrow();
+        assert_eq!(sent.first().and_then(|p| p.get(3)), Some(&0x80));
+        assert_eq!(sent.get(1).and_then(|p| p.get(3)), Some(&0x50));
+    }
+
+    #[test]
+    fn throttle() {
+        use crate::Throttle;
+        use mctp::{MsgIC, MsgType};
+
+        let packets = RefCell::new(std::vec::Vec::new());
+        let mut router: Router<_, 1, 2> = Router::new(Eid(8), 0, BufferSender::<64>::new(&packets));
+        let slow = router.req(Eid(9)).unwrap();
+        let fast = router.req(Eid(10)).unwrap();
+        router
+            .set_throttle(Eid(9), Some(Throttle::new(64, 1000)))
+            .unwrap();
+        assert_eq!(router.throttle(Eid(9)), Some(Throttle::new(64, 1000)));
+        router.set_transmit_buffer(std::boxed::Box::leak(std::vec![0; 256].into_boxed_slice()));
+
+        // The second packet exceeds the burst and is held
+        let report = router
+            .send(None, MsgType(1), None, MsgIC(false), slow, &[0; 100])
+            .unwrap();
+        assert_eq!((report.packets, report.held), (2, 1));
+        assert_eq!(packets.borrow().len(), 1);
+        assert_eq!(router.throttled(), 1);
+
+        // Other destinations are not held up
+        router
+            .send(None, MsgType(1), None, MsgIC(false), fast, &[1])
+            .unwrap();
+        assert_eq!(packets.borrow().len(), 2);
+
+        // Released once 45 bytes worth of tokens refilled
+        assert_eq!(router.update(10).unwrap(), 35);
+        assert_eq!(packets.borrow().len(), 2);
+        router.update(45).unwrap();
+        assert_eq!(router.throttled(), 0);
+        assert_eq!(packets.borrow().last().map(|p| p.len()), Some(45));
+
+        // Without a buffer, throttled messages fail
+        assert!(router.take_transmit_buffer().is_some());
+        router
+            .send(None, MsgType(1), None, MsgIC(false), slow, &[0; 100])
+            .unwrap_err();
+        assert_eq!(router.throttle_overflows(), 1);
+        router.set_throttle(Eid(9), None).unwrap();
+        assert!(router.throttle(Eid(9)).is_none());
+    }
+
+    #[cfg(feature = "fuzz")]
+    #[test]
+    fn fuzz_inbound() {
+        use crate::fuzz::{FragmentSequence, control_message, fragment_sequence};
+        use arbitrary::{Arbitrary, Unstructured};
+        use mctp::MsgType;
+        use proptest::prelude::*;
+
+        // Sequences to the router itself, with and without faults
+        proptest!(|(mut seq in fragment_sequence(), control in control_message())| {
+            let mut router: Router<_, 2, 2> = Router::new(Eid(8), 0, NullSender);
+            seq.typ = 0x40 | seq.typ & 0x3f;
+            let listener = router.listener(MsgType(seq.typ)).unwrap();
+            seq.header.dest = 8;
+            seq.header.version = 1;
+            let faulty = !seq.faults.is_empty();
+            for pkt in seq.packets() {
+                let _ = router.inbound(&pkt);
+            }
+            let received = router.recv(listener).map(|m| m.payload.len());
+            if !faulty && seq.header.owner && (10..=254).contains(&seq.header.source) {
+                prop_assert_eq!(received, Some(seq.payload.len()));
+            }
+            let mut pkt = std::vec![1, 8, 9, 0xc8];
+            pkt.extend(control.to_bytes());
+            let _ = router.inbound(&pkt);
+        });
+
+        // Raw fuzzer input
+        let data: std::vec::Vec<u8> = (0..4096u32).map(|i| (i * 7919 % 251) as u8).collect();
+        let mut u = Unstructured::new(&data);
+        let mut router: Router<_, 2, 2> = Router::new(Eid(8), 0, NullSender);
+        while !u.is_empty()
+            && let Ok(seq) = FragmentSequence::arbitrary(&mut u)
+        {
+            for pkt in seq.packets() {
+                let _ = router.inbound(&pkt);
+            }
+        }
+    }
+
+    /// Messages larger than the reassembly buffer are received in chunks
+    #[test]
+    fn streaming_reassembly() {
+        use crate::crc32c::Crc32c;
+        use std::vec;
+
+        let mut router: Router<_, 8, 4> = Router::new(Eid(8), 0, NullSender);
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+        assert!(router.set_streaming(listener, true).is_err());
+        router
+            .set_reassembly_buffer(listener, Vec::leak(vec![0; 16]))
+            .unwrap();
+        router.set_streaming(listener, true).unwrap();
+
+        // 50 bytes and the integrity check through a 16 byte buffer
+        let payload: Vec<u8> = (0..50).collect();
+        let mut crc = Crc32c::new();
+        crc.update(&[0x81]);
+        crc.update(&payload);
+        let mut msg = vec![0x81];
+        msg.extend(&payload);
+        msg.extend(crc.finish().to_le_bytes());
+        let packets: Vec<&[u8]> = msg.chunks(10).collect();
+        let mut received: Vec<u8> = Vec::new();
+        let mut chunks = Vec::new();
+        for (seq, part) in packets.iter().enumerate() {
+            let som = if seq == 0 { 0x80 } else { 0 };
+            let eom = if seq == packets.len() - 1 { 0x40 } else { 0 };
+            let mut pkt = vec![1, 8, 9, som | eom | ((seq as u8 & 3) << 4) | 0x09];
+            pkt.extend(*part);
+            let disposition = router.inbound_disposition(&pkt);
+            assert!(matches!(
+                disposition,
+                super::Disposition::Incomplete | super::Disposition::Delivered(_)
+            ));
+            let mut buf = [0; 8];
+            while let Some(chunk) = router.recv_chunk(listener, &mut buf).unwrap() {
+                assert_eq!(chunk.offset, received.len());
+                received.extend(buf.get(..chunk.len).unwrap());
+                chunks.push(chunk);
+            }
+        }
+        assert_eq!(received, payload);
+        let last = chunks.last().unwrap();
+        assert!(last.last && chunks.iter().filter(|c| c.last).count() == 1);
+        assert_eq!((last.source, last.typ), (Eid(9), mctp::MsgType(1)));
+        assert!(router.recv_chunk(listener, &mut [0; 8]).unwrap().is_none());
+
+        // Without draining the buffer overflows
+        for seq in 0..2 {
+            let som = if seq == 0 { 0x80 } else { 0 };
+            let mut pkt = vec![1, 8, 9, som | (seq << 4) | 0x09];
+            if seq == 0 {
+                pkt.push(1);
+            }
+            pkt.extend([0; 10]);
+            let disposition = router.inbound_disposition(&pkt);
+            if seq == 1 {
+                assert_eq!(disposition, super::Disposition::DroppedTooLarge);
+            }
+        }
+        assert!(router.recv_chunk(listener, &mut [0; 8]).unwrap().is_none());
+
+        // Messages fitting into the buffer are still received whole
+        router.inbound(&[1, 8, 9, 0xc9, 1, 1, 2, 3]).unwrap();
+        let mut whole = Vec::new();
+        let info = router.recv_into(listener, &mut whole).unwrap().unwrap();
+        assert_eq!((info.len, whole), (3, vec![1, 2, 3]));
+    }
+
+    /// Tags are held for the configured reclaim time instead of the expiry of the stack
+    #[test]
+    fn tag_reclaim() {
+        use crate::{NoHooks, RouterConfig};
+
+        let config = RouterConfig::new(Eid(42)).tag_reclaim_millis(Some(10_000));
+        let mut router: Router<_, 4, 4> = Router::new_with_config(config, 0, NullSender, NoHooks);
+        let req = router.req(Eid(112)).unwrap();
+        let send = |router: &mut Router<_, 4, 4>| {
+            router
+                .send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[1])
+                .unwrap();
+            router.requests().next().unwrap().tag.unwrap().tag().0
+        };
+
+        // A response after the expiry of the stack still matches
+        let tag = send(&mut router);
+        router.update(8000).unwrap();
+        assert_eq!(
+            router.inbound_disposition(&[1, 42, 112, 0xc0 | tag, 1, 0]),
+            super::Disposition::Delivered(req.into())
+        );
+        assert!(router.recv(req).is_some());
+
+        // Per request override, the unanswered request expires with its tag
+        router.set_tag_reclaim_millis(req, Some(100)).unwrap();
+        let tag = send(&mut router);
+        router.update(8050).unwrap();
+        assert!(router.requests().next().unwrap().tag.is_some());
+        router.update(8100).unwrap();
+        assert_eq!(router.requests().next().unwrap().tag, None);
+        assert_ne!(
+            router.inbound_disposition(&[1, 42, 112, 0xc0 | tag, 1, 0]),
+            super::Disposition::Delivered(req.into())
+        );
+    }
+
+    /// Get Message Type Support reports the bound listeners and registered types
+    #[test]
+    fn message_type_support() {
+        use crate::control::CC_SUCCESS;
+        use mctp::MsgType;
+
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, NullSender);
+        let spdm = router.listener(MsgType(5)).unwrap();
+        router.listener(MsgType(1)).unwrap();
+        router.listener(MsgType(0)).unwrap();
+        router.catch_all_listener().unwrap();
+        router.add_message_type(MsgType(0x7e)).unwrap();
+        router.add_message_type(MsgType(1)).unwrap();
+
+        let mut response = [0; 16];
+        let len = router
+            .control_response(Eid(9), &[0x80, 0x05], &mut response)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            response.get(..len),
+            Some([0x00, 0x05, CC_SUCCESS, 3, 1, 5, 0x7e].as_slice())
+        );
+
+        // The answer follows the listeners
+        router.unbind(spdm).unwrap();
+        assert!(router.remove_message_type(MsgType(0x7e)));
+        assert!(!router.remove_message_type(MsgType(0x7e)));
+        assert_eq!(router.message_types().as_slice(), [MsgType(1)]);
+    }
+
+    /// Routes to a port whose link is down are unreachable, link up sends Discovery Notify
+    #[test]
+    fn link_state() {
+        use crate::RouterConfig;
+        use crate::control::CMD_DISCOVERY_NOTIFY;
+
+        let packets = RefCell::new(Vec::new());
+        let config = RouterConfig::new(Eid(8))
+            .forwarding(true)
+            .route(20..=29, 1, 0x1d)
+            .unwrap()
+            .discovery_notify(true);
+        let mut router: Router<_, 4, 4> =
+            Router::new_with_config(config, 0, BufferSender::<64>::new(&packets), crate::NoHooks);
+        let req = router.req(Eid(20)).unwrap();
+        let other = router.req(Eid(30)).unwrap();
+
+        router.link_down(1);
+        assert!(!router.is_link_up(1) && router.is_link_up(0));
+        let send = |router: &mut Router<_, 4, 4>, req| {
+            router.send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[1])
+        };
+        assert!(send(&mut router, req).is_err());
+        assert!(send(&mut router, other).is_ok());
+        assert_eq!(
+            router.inbound_disposition(&[1, 21, 9, 0xc8, 1]),
+            super::Disposition::DroppedLinkDown
+        );
+        router.update(0).unwrap();
+        assert_eq!(packets.take().len(), 1);
+
+        // Link up notifies the bus owner, the null EID before an EID was assigned
+        router.link_up(1);
+        router.link_up(1);
+        router.update(10).unwrap();
+        router.update(20).unwrap();
+        let sent = packets.take();
+        assert_eq!(sent.len(), 1);
+        let notify = sent.first().unwrap();
+        assert_eq!((notify.get(1), notify.get(2)), (Some(&0), Some(&8)));
+        assert_eq!(notify.get(6), Some(&CMD_DISCOVERY_NOTIFY));
+        assert!(send(&mut router, req).is_ok());
+    }
+
+    /// Routes fail over to their backup next hop and back
+    #[test]
+    fn route_failover() {
+        use crate::{NextHop, ROUTE_FAILBACK_MILLIS, ROUTE_FAILOVER_THRESHOLD, Route, Sender};
+        use mctp::Result;
+
+        struct FlakySender<'a> {
+            ports: &'a RefCell<Vec<u8>>,
+            failing: &'a core::cell::Cell<bool>,
+        }
+
+        impl Sender for FlakySender<'_> {
+            type Packet = [u8; 64];
+
+            fn send_packet(&mut self, _eid: Eid, _pkt: &[u8]) -> Result<()> {
+                Ok(())
+            }
+
+            fn get_mtu(&self) -> usize {
+                64
+            }
+
+            fn send_routed(&mut self, _eid: Eid, route: &Route, _pkt: &[u8]) -> Result<()> {
+                self.ports.borrow_mut().push(route.port);
+                if route.port == 1 && self.failing.get() {
+                    return Err(mctp::Error::TxFailure);
+                }
+                Ok(())
+            }
+        }
+
+        let ports = RefCell::new(Vec::new());
+        let failing = core::cell::Cell::new(false);
+        let sender = FlakySender {
+            ports: &ports,
+            failing: &failing,
+        };
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, sender);
+        router.add_route(20..=29, 1, 0x1d).unwrap();
+        let backup = NextHop {
+            port: 2,
+            physical_addr: 0x2d,
+        };
+        assert!(router.set_route_backup(20..=29, Some(backup)));
+        assert!(!router.set_route_backup(20..=28, Some(backup)));
+        let req = router.req(Eid(20)).unwrap();
+        let send = |router: &mut Router<_, 4, 4>| {
+            let sent = router.send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[1]);
+            router.reset(req).unwrap();
+            (sent.is_ok(), ports.borrow_mut().pop())
+        };
+
+        // Link down
+        router.link_down(1);
+        assert_eq!(router.next_hop(Eid(20)).unwrap().physical_addr, 0x2d);
+        assert_eq!(send(&mut router), (true, Some(2)));
+        router.link_up(1);
+        assert_eq!(send(&mut router), (true, Some(1)));
+
+        // Repeated failures, then failback after a while
+        failing.set(true);
+        for _ in 0..ROUTE_FAILOVER_THRESHOLD {
+            assert_eq!(send(&mut router), (false, Some(1)));
+        }
+        assert_eq!(send(&mut router), (true, Some(2)));
+        assert_eq!(router.route(Eid(20)).unwrap().port, 1);
+        router.update(ROUTE_FAILBACK_MILLIS).unwrap();
+        assert_eq!(send(&mut router), (false, Some(1)));
+        assert_eq!(send(&mut router), (true, Some(2)));
+        failing.set(false);
+        router.link_up(1);
+        router.link_down(1);
+        router.link_up(1);
+        assert_eq!(send(&mut router), (true, Some(1)));
+    }
+
+    /// Work beyond the budget of an update is carried to the next one
+    #[test]
+    fn work_budget() {
+        use crate::{NoHooks, RouterConfig, Throttle, WorkBudget};
+        use mctp::{MsgIC, MsgType};
+
+        let packets = RefCell::new(std::vec::Vec::new());
+        let budget = WorkBudget {
+            timers: Some(1),
+            packets: Some(1),
+        };
+        let config = RouterConfig::new(Eid(8))
+            .request_timeout_millis(Some(50))
+            .work_budget(budget);
+        let mut router: Router<_, 1, 3> =
+            Router::new_with_config(config, 0, BufferSender::<64>::new(&packets), NoHooks);
+        let first = router.req(Eid(10)).unwrap();
+        let second = router.req(Eid(11)).unwrap();
+        for req in [first, second] {
+            router
+                .send(None, MsgType(1), None, MsgIC(false), req, &[1])
+                .unwrap();
+        }
+
+        // One request times out per update
+        assert_eq!(router.update(50).unwrap(), 0);
+        assert_eq!(router.requests().filter(|r| r.tag.is_none()).count(), 1);
+        router.update(50).unwrap();
+        assert!(router.requests().all(|r| r.tag.is_none()));
+
+        // One held packet is released per update
+        let slow = router.req(Eid(9)).unwrap();
+        router
+            .set_throttle(Eid(9), Some(Throttle::new(200, 1000)))
+            .unwrap();
+        router.set_transmit_buffer(std::boxed::Box::leak(std::vec![0; 512].into_boxed_slice()));
+        let report = router
+            .send(None, MsgType(1), None, MsgIC(false), slow, &[0; 400])
+            .unwrap();
+        assert_eq!(report.held, 4);
+        assert_eq!(router.update(1050).unwrap(), 0);
+        assert_eq!(router.throttled(), 3);
+        router.update(1050).unwrap();
+        assert_eq!(router.throttled(), 2);
+
+        // Without a budget, everything due is done at once
+        router.set_work_budget(WorkBudget::default());
+        router.update(1050).unwrap();
+        assert_eq!(router.throttled(), 1);
+    }
+
+    /// A rebound listener keeps its queued requests and receives the new type
+    #[test]
+    fn rebind_listener() {
+        use mctp::{MsgIC, MsgType};
+
+        let packets = RefCell::new(Vec::new());
+        let mut requester: Router<_, 1, 1> =
+            Router::new(Eid(8), 0, BufferSender::<64>::new(&packets));
+        let mut responder: Router<_, 3, 1> = Router::new(Eid(9), 0, NullSender);
+        let listener = responder.listener(MsgType(0x7e)).unwrap();
+        let other = responder.listener(MsgType(2)).unwrap();
+        let catch_all = responder.catch_all_listener().unwrap();
+        let req = requester.req(Eid(9)).unwrap();
+        requester
+            .send(None, MsgType(0x7e), None, MsgIC(false), req, &[1])
+            .unwrap();
+        for pkt in packets.take() {
+            responder.inbound(&pkt).unwrap();
+        }
+
+        assert!(matches!(
+            responder.rebind(listener, MsgType(2)),
+            Err(e) if matches!(e.error(), mctp::Error::AddrInUse)
+        ));
+        assert!(responder.rebind(catch_all, MsgType(3)).is_err());
+        responder.rebind(listener, MsgType(1)).unwrap();
+        assert!(responder.listeners().any(|l| l == (listener, MsgType(1))));
+        assert!(!responder.listeners().any(|l| l.1 == MsgType(0x7e)));
+
+        // The queued request is still received
+        let msg = responder.recv(listener).unwrap();
+        assert_eq!((msg.typ, msg.payload), (MsgType(0x7e), &[1][..]));
+        drop(msg);
+
+        for typ in [MsgType(1), MsgType(0x7e)] {
+            requester.reset(req).unwrap();
+            requester
+                .send(None, typ, None, MsgIC(false), req, &[2])
+                .unwrap();
+            for pkt in packets.take() {
+                responder.inbound(&pkt).unwrap();
+            }
+        }
+        assert_eq!(responder.recv(listener).map(|m| m.typ), Some(MsgType(1)));
+        assert_eq!(
+            responder.recv(catch_all).map(|m| m.typ),
+            Some(MsgType(0x7e))
+        );
+        assert!(responder.recv(other).is_none());
+    }
+
+    /// Received messages carry the time their final fragment arrived
+    #[test]
+    fn receive_timestamp() {
+        let mut router: Router<_, 1, 1> = Router::new(Eid(8), 0, NullSender);
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+        assert_eq!(router.received_millis(listener), None);
+
+        router.update(25).unwrap();
+        router.inbound(&[1, 8, 10, 0x88, 1, 0xaa]).unwrap();
+        router.update(40).unwrap();
+        router.inbound(&[1, 8, 10, 0x58, 0xbb]).unwrap();
+        router.inbound(&[1, 8, 10, 0xc9, 1, 0xcc]).unwrap();
+        assert_eq!(router.received_millis(listener), Some(40));
+
+        let mut received = Vec::new();
+        let info = router.recv_into(listener, &mut received).unwrap().unwrap();
+        assert_eq!((info.len, info.received_millis), (2, Some(40)));
+        let msg = router.recv(listener).unwrap();
+        assert_eq!(crate::MessageInfo::from(&msg).received_millis, None);
+    }
+
+    /// The integrity check is left to bindings offloading it, per port
+    #[test]
+    fn integrity_check_offload() {
+        use crate::{NoHooks, Route, RouterConfig, Sender};
+        use mctp::{MsgIC, MsgType, Result};
+
+        struct OffloadSender<'a> {
+            packets: &'a RefCell<Vec<Vec<u8>>>,
+        }
+
+        impl Sender for OffloadSender<'_> {
+            type Packet = [u8; 64];
+
+            fn send_packet(&mut self, _eid: Eid, pkt: &[u8]) -> Result<()> {
+                self.packets.borrow_mut().push(pkt.into());
+                Ok(())
+            }
+
+            fn get_mtu(&self) -> usize {
+                64
+            }
+
+            fn send_routed(&mut self, eid: Eid, _route: &Route, pkt: &[u8]) -> Result<()> {
+                self.send_packet(eid, pkt)
+            }
+
+            fn offloads_integrity_check(&self, _eid: Eid, route: Option<&Route>) -> bool {
+                route.is_some_and(|r| r.port == 1)
+            }
+        }
+
+        let packets = RefCell::new(Vec::new());
+        let config = RouterConfig::new(Eid(8)).route(20..=29, 1, 0).unwrap();
+        let mut router: Router<_, 1, 2> =
+            Router::new_with_config(config, 0, OffloadSender { packets: &packets }, NoHooks);
+        let software = router.req(Eid(9)).unwrap();
+        let offloaded = router.req(Eid(20)).unwrap();
+
+        // 4 header bytes, type and payload, plus the check computed in software
+        router
+            .send(None, MsgType(1), None, MsgIC(true), software, &[0; 55])
+            .unwrap();
+        assert_eq!(
+            packets.take().iter().map(Vec::len).collect::<Vec<_>>(),
+            [64]
+        );
+
+        // The last packet leaves room for the check appended by the binding
+        router
+            .send(None, MsgType(1), None, MsgIC(true), offloaded, &[0; 56])
+            .unwrap();
+        let sent = packets.take();
+        assert_eq!(sent.iter().map(Vec::len).collect::<Vec<_>>(), [60, 5]);
+        assert_eq!(sent.first().and_then(|p| p.get(4)), Some(&0x81));
+    }
+
+    /// Events are queued for a superloop in the order they happened
+    #[test]
+    fn event_queue() {
+        use crate::{Disposition, EVENT_QUEUE_SIZE, NoHooks, RouterConfig, RouterEvent};
+
+        let config = RouterConfig::new(Eid(8)).event_queue(true);
+        let mut router: Router<_, 1, 1> = Router::new_with_config(config, 0, NullSender, NoHooks);
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+        assert_eq!(router.next_event(), None);
+
+        router.inbound(&[1, 8, 10, 0xc8, 1, 0xaa]).unwrap();
+        router.inbound(&[1, 8, 10, 0xc9, 2, 0xbb]).unwrap();
+        router.inbound(&[1, 8, 10, 0x88, 1, 0xcc]).unwrap();
+        router.link_down(1);
+        router.link_up(1);
+        router.set_eid(Eid(9)).unwrap();
+        assert_eq!(
+            core::iter::from_fn(|| router.next_event()).collect::<Vec<_>>(),
+            [
+                RouterEvent::MessageReady(listener.into()),
+                RouterEvent::Dropped(Disposition::DroppedNoListener),
+                RouterEvent::LinkDown(1),
+                RouterEvent::LinkUp(1),
+                RouterEvent::EidChanged {
+                    old: Eid(8),
+                    new: Eid(9)
+                },
+            ]
+        );
+
+        // A full queue keeps the oldest events
+        for port in 0..EVENT_QUEUE_SIZE as u8 + 2 {
+            router.link_down(port);
+        }
+        assert_eq!(router.lost_events(), 2);
+        assert_eq!(router.next_event(), Some(RouterEvent::LinkDown(0)));
+
+        router.set_event_queue(false);
+        router.link_up(0);
+        assert_eq!(router.next_event(), None);
+    }
+
+    /// Header problems are counted by kind, with the peer causing the last one
+    #[test]
+    fn header_stats() {
+        let mut router: Router<_, 1, 1> = Router::new(Eid(8), 0, NullSender);
+        router.listener(mctp::MsgType(1)).unwrap();
+
+        // A complete two packet message
+        router.inbound(&[1, 8, 10, 0x88, 1, 0xaa]).unwrap();
+        router.inbound(&[1, 8, 10, 0x58, 0xbb]).unwrap();
+        assert_eq!(router.header_stats(), crate::HeaderStats::default());
+        assert_eq!(router.header_offender(), None);
+
+        let _ = router.inbound(&[2, 8, 11, 0xc8, 1, 0xaa]);
+        // Sequence number 2 follows 0
+        router.inbound(&[1, 8, 12, 0x88, 1, 0xaa]).unwrap();
+        let _ = router.inbound(&[1, 8, 12, 0x28, 0xbb]);
+        // The message from 13 restarts before its end
+        router.inbound(&[1, 8, 13, 0x88, 1, 0xaa]).unwrap();
+        router.inbound(&[1, 8, 13, 0xc8, 1, 0xaa]).unwrap();
+        let _ = router.inbound(&[1, 8, 14, 0x58, 0xbb]);
+
+        let stats = router.header_stats();
+        assert_eq!(
+            (
+                stats.bad_version,
+                stats.unexpected_seq,
+                stats.bad_som_eom,
+                stats.unknown_source
+            ),
+            (1, 1, 1, 1)
+        );
+        assert_eq!(router.header_offender(), Some(Eid(14)));
+    }
+
+    /// A reply context outlives the request and is redeemed for one response
+    #[test]
+    fn reply_context() {
+        use crate::ReplyContext;
+        use mctp::{MsgIC, MsgType};
+
+        let packets = RefCell::new(Vec::new());
+        let mut requester: Router<_, 1, 1> =
+            Router::new(Eid(8), 0, BufferSender::<64>::new(&packets));
+        let mut responder: Router<_, 1, 1> =
+            Router::new(Eid(9), 0, BufferSender::<64>::new(&packets));
+        let listener = responder.listener(MsgType(1)).unwrap();
+        let req = requester.req(Eid(9)).unwrap();
+        requester
+            .send(None, MsgType(1), None, MsgIC(false), req, &[1])
+            .unwrap();
+        for pkt in packets.take() {
+            responder.inbound(&pkt).unwrap();
+        }
+
+        let context = {
+            let msg = responder.recv(listener).unwrap();
+            ReplyContext::new(listener, &crate::MessageInfo::from(&msg)).unwrap()
+        };
+        assert_eq!((context.source(), context.typ()), (Eid(8), MsgType(1)));
+        assert!(context.tag().is_owner());
+        responder.reply(context, &[&[2]]).unwrap();
+        for pkt in packets.take() {
+            requester.inbound(&pkt).unwrap();
+        }
+        let response = requester.recv(req).unwrap();
+        assert_eq!(response.payload, &[2]);
+
+        let info = crate::MessageInfo::from(&response);
+        assert!(matches!(
+            ReplyContext::new(listener, &info),
+            Err(mctp::Error::BadArgument)
+        ));
+    }
+
+    /// Injected messages reach listeners without a transport, responses go out as usual
+    #[test]
+    fn inject_message() {
+        use mctp::{MsgIC, MsgType, Tag, TagValue};
+
+        let packets = RefCell::new(Vec::new());
+        let mut router: Router<_, 1, 1> = Router::new(Eid(8), 0, BufferSender::<64>::new(&packets));
+        let listener = router.listener(MsgType(1)).unwrap();
+        let payload = [0x5a; 150];
+        let tag = Tag::Owned(TagValue(3));
+
+        let disposition = router
+            .inject(
+                Eid(20),
+                MsgType(1),
+                tag,
+                MsgIC(true),
+                &[&payload[..100], &payload[100..]],
+            )
+            .unwrap();
+        assert_eq!(disposition, super::Disposition::Delivered(listener.into()));
+        assert!(packets.borrow().is_empty());
+        let mut received = Vec::new();
+        let request = router.recv_into(listener, &mut received).unwrap().unwrap();
+        assert_eq!((request.source, request.tag), (Eid(20), tag));
+        assert_eq!(received, payload);
+
+        router.respond(listener, &request, &[&[1]]).unwrap();
+        assert_eq!(
+            packets.take().first().and_then(|p| p.get(1..3)),
+            Some(&[20, 8][..])
+        );
+        assert!(matches!(
+            router.inject(Eid(8), MsgType(1), tag, MsgIC(false), &[&[1]]),
+            Err(mctp::Error::BadArgument)
+        ));
+    }
+
+    /// Queued messages can be inspected and cancelled before they are flushed
+    #[test]
+    fn send_queue_cancel() {
+        use crate::queue::{QueuedSend, SendQueue};
+        use core::sync::atomic::{AtomicU64, Ordering};
+
+        struct AtomicClock(AtomicU64);
+
+        impl crate::Clock for AtomicClock {
+            fn now_millis(&self) -> u64 {
+                self.0.load(Ordering::Relaxed)
+            }
+        }
+
+        let packets = RefCell::new(Vec::new());
+        let mut router: Router<_, 2, 2> =
+            Router::new(Eid(42), 0, BufferSender::<64>::new(&packets));
+        let req = router.req(Eid(112)).unwrap();
+        let gone = router.req(Eid(113)).unwrap();
+
+        let clock = AtomicClock(AtomicU64::new(100));
+        let mut queue: SendQueue<4, 32> = SendQueue::new();
+        let (mut producer, mut consumer) = queue.split_with_clock(&clock);
+        let mut send = |handle, payload: &[u8]| {
+            producer.try_send(
+                None,
+                mctp::MsgType(1),
+                None,
+                mctp::MsgIC(false),
+                handle,
+                payload,
+            )
+        };
+        send(req, &[1]).unwrap();
+        let stale = send(gone, &[2, 2]).unwrap();
+        clock.0.store(130, Ordering::Relaxed);
+        send(req, &[3]).unwrap();
+
+        let pending: Vec<QueuedSend> = consumer.pending().collect();
+        assert_eq!(
+            pending
+                .iter()
+                .map(|q| (q.handle, q.len, q.age_millis))
+                .collect::<Vec<_>>(),
+            [
+                (req.into(), 1, Some(30)),
+                (gone.into(), 2, Some(30)),
+                (req.into(), 1, Some(0)),
+            ]
+        );
+        assert_eq!(pending.get(1).map(|q| q.ticket), Some(stale));
+
+        assert!(consumer.cancel(stale));
+        assert!(!consumer.cancel(stale));
+        assert_eq!(consumer.pending().count(), 2);
+        assert_eq!(consumer.flush(&mut router), 2);
+        assert_eq!(
+            packets
                 .take()
-                .ok_or(Error::BadArgument)?;
-            Ok(())
-        } else {
-            let req = self
-                .requests
-                .get_mut(Self::requests_index_from_cookie(cookie).ok_or(Error::BadArgument)?)
-                .ok_or(Error::InternalError)?
+                .iter()
+                .map(|p| p.get(5).copied())
+                .collect::<Vec<_>>(),
+            [Some(1), Some(3)]
+        );
+        assert!(!consumer.cancel(pending.first().unwrap().ticket));
+    }
+
+    /// Traffic is counted per peer, the least recently active peer is evicted
+    #[test]
+    fn peer_stats() {
+        use crate::PEER_STATS_TABLE_SIZE;
+        use mctp::{MsgIC, MsgType};
+
+        let packets = RefCell::new(Vec::new());
+        let mut router: Router<_, 1, 1> = Router::new(Eid(8), 0, BufferSender::<64>::new(&packets));
+        router.listener(MsgType(1)).unwrap();
+        let req = router.req(Eid(9)).unwrap();
+
+        router.update(10).unwrap();
+        router.inbound(&[1, 8, 10, 0x88, 1, 0xaa]).unwrap();
+        router.inbound(&[1, 8, 10, 0x58, 0xbb]).unwrap();
+        router.inbound(&[1, 8, 10, 0xc9, 2, 0xcc]).unwrap();
+        router.update(15).unwrap();
+        router
+            .send(None, MsgType(1), None, MsgIC(false), req, &[0; 70])
+            .unwrap();
+
+        let peer = router.peer_stats_for(Eid(10)).unwrap();
+        assert_eq!(
+            (peer.messages_in, peer.bytes_in, peer.errors_in),
+            (1, 17, 1)
+        );
+        assert_eq!(peer.last_seen_millis, Some(10));
+        let peer = router.peer_stats_for(Eid(9)).unwrap();
+        assert_eq!((peer.messages_out, peer.bytes_out), (1, 79));
+        assert_eq!(peer.last_seen_millis, None);
+
+        for eid in 0..PEER_STATS_TABLE_SIZE as u8 - 1 {
+            router.update(20 + u64::from(eid)).unwrap();
+            router.inbound(&[1, 8, 20 + eid, 0xc9, 2, 0]).unwrap();
+        }
+        assert_eq!(router.peer_stats().count(), PEER_STATS_TABLE_SIZE);
+        assert_eq!(router.peer_stats_evicted(), 1);
+        assert!(router.peer_stats_for(Eid(10)).is_none());
+        assert!(router.peer_stats_for(Eid(9)).is_some());
+
+        router.reset_peer_stats();
+        assert_eq!(router.peer_stats().count(), 0);
+    }
+
+    /// The router picks the lowest free tag, honoring ranges reserved per request
+    #[test]
+    fn tag_allocation() {
+        use crate::TagAllocation;
+        use mctp::{MsgIC, MsgType, Tag, TagValue};
+
+        let packets = RefCell::new(Vec::new());
+        let mut router: Router<_, 1, 4> = Router::new(Eid(8), 0, BufferSender::<64>::new(&packets));
+        let reqs = [(); 3].map(|_| router.req(Eid(9)).unwrap());
+        let send = |router: &mut Router<_, 1, 4>, req| {
+            router
+                .send(None, MsgType(1), None, MsgIC(false), req, &[0])
+                .map(|r| r.tag)
+        };
+        let respond = |router: &mut Router<_, 1, 4>, tag: Tag| {
+            router
+                .inbound(&[1, 8, 9, 0xc0 | tag.tag().0, 1, 0])
+                .unwrap();
+        };
+
+        router.set_tag_allocation(TagAllocation::LowestFree);
+        let [a, b, c] = reqs;
+        let first = send(&mut router, a).unwrap();
+        assert_eq!(first, Tag::Owned(TagValue(0)));
+        assert_eq!(send(&mut router, b).unwrap(), Tag::Owned(TagValue(1)));
+        // A response frees the tag, it is reused at once
+        respond(&mut router, first);
+        assert!(router.recv(a).is_some());
+        assert_eq!(send(&mut router, c).unwrap(), Tag::Owned(TagValue(0)));
+        let tag = send(&mut router, a).unwrap();
+        assert_eq!(tag, Tag::Owned(TagValue(2)));
+        respond(&mut router, tag);
+        assert!(router.recv(a).is_some());
+
+        router.set_tag_allocation(TagAllocation::Reserved);
+        router.set_tag_range(a, Some(5..=6)).unwrap();
+        assert!(router.set_tag_range(a, Some(6..=8)).is_err());
+        // The tag held by the previous message of the request is avoided
+        let tags = [(); 3].map(|_| send(&mut router, a).map(|t| t.tag().0).ok());
+        assert_eq!(tags, [Some(5), Some(6), Some(5)]);
+        router.reset(b).unwrap();
+        router.reset(c).unwrap();
+        // Requests without a range use the values not reserved
+        assert_eq!(send(&mut router, c).unwrap(), Tag::Owned(TagValue(0)));
+        router.set_tag_range(b, Some(7..=7)).unwrap();
+        assert_eq!(send(&mut router, b).unwrap(), Tag::Owned(TagValue(7)));
+        assert!(matches!(
+            send(&mut router, b),
+            Err(e) if matches!(e.error(), mctp::Error::NoSpace)
+        ));
+    }
+
+    /// Packets are received in place into buffers lent by the router
+    #[test]
+    fn rx_buffers() {
+        use crate::RX_BUFFER_POOL_SIZE;
+
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, NullSender);
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+        assert!(router.lend_rx_buffer().is_none());
+        for _ in 0..RX_BUFFER_POOL_SIZE {
+            router.add_rx_buffer(Vec::leak(vec![0; 64])).unwrap();
+        }
+        assert!(matches!(
+            router.add_rx_buffer(Vec::leak(vec![0; 64])),
+            Err(mctp::Error::NoSpace)
+        ));
+
+        let mut rx = router.lend_rx_buffer().unwrap();
+        assert_eq!(router.rx_buffers_available(), RX_BUFFER_POOL_SIZE - 1);
+        assert!(rx.is_empty());
+        let pkt = [1, 8, 9, 0xc8, 1, 5, 6];
+        rx.buf_mut()
+            .get_mut(..pkt.len())
+            .unwrap()
+            .copy_from_slice(&pkt);
+        assert!(rx.set_len(rx.capacity() + 1).is_err());
+        rx.set_len(pkt.len()).unwrap();
+        assert_eq!(rx.packet(), pkt);
+        assert_eq!(router.inbound_owned(rx).unwrap(), Some(listener.into()));
+        assert_eq!(router.rx_buffers_available(), RX_BUFFER_POOL_SIZE);
+        assert_eq!(router.recv(listener).unwrap().payload, [5, 6]);
+
+        // Buffers come back on errors and unused, or leave the pool for good
+        let mut rx = router.lend_rx_buffer().unwrap();
+        rx.set_len(2).unwrap();
+        assert!(router.inbound_owned(rx).is_err());
+        let rx = router.lend_rx_buffer().unwrap();
+        router.return_rx_buffer(rx);
+        assert_eq!(router.rx_buffers_available(), RX_BUFFER_POOL_SIZE);
+        assert_eq!(router.lend_rx_buffer().unwrap().into_inner().len(), 64);
+        assert_eq!(router.rx_buffers_available(), RX_BUFFER_POOL_SIZE - 1);
+    }
+
+    /// Discovery Notify is sent again until an EID is assigned or the bus owner answers
+    #[test]
+    fn notify_retransmit() {
+        use crate::control::CMD_DISCOVERY_NOTIFY;
+        use crate::{Disposition, NotifyRetransmit, RouterConfig};
+
+        let packets = RefCell::new(Vec::new());
+        let config = RouterConfig::new(Eid(8))
+            .discovery_notify(true)
+            .notify_retransmit(Some(NotifyRetransmit::new(100, 2)));
+        let mut router: Router<_, 4, 4> =
+            Router::new_with_config(config, 0, BufferSender::<64>::new(&packets), crate::NoHooks);
+        let notifies = |packets: &RefCell<Vec<Vec<u8>>>| {
+            packets
                 .take()
-                .ok_or(Error::BadArgument)?;
-            if let ReqHandle {
-                eid,
-                last_tag: Some(tag),
-            } = req
-            {
-                self.stack.cancel_flow(eid, tag.tag());
+                .iter()
+                .filter(|p| p.get(6) == Some(&CMD_DISCOVERY_NOTIFY))
+                .count()
+        };
+
+        // Retransmitted up to the cap
+        router.link_down(0);
+        router.link_up(0);
+        router.update(0).unwrap();
+        assert_eq!(notifies(&packets), 1);
+        router.update(50).unwrap();
+        assert_eq!(notifies(&packets), 0);
+        router.update(100).unwrap();
+        router.update(200).unwrap();
+        router.update(300).unwrap();
+        router.update(400).unwrap();
+        assert_eq!(notifies(&packets), 2);
+
+        // An EID assignment stops the retransmission
+        router.link_down(0);
+        router.link_up(0);
+        router.update(500).unwrap();
+        assert_eq!(notifies(&packets), 1);
+        router.set_eid(Eid(9)).unwrap();
+        router.update(600).unwrap();
+        assert_eq!(notifies(&packets), 0);
+
+        // So does a response of the bus owner
+        router.link_down(0);
+        router.link_up(0);
+        router.update(700).unwrap();
+        let sent = packets.take();
+        let notify = sent.first().unwrap();
+        let (tag, instance_id) = (notify.get(3).unwrap() & 0x07, notify.get(5).unwrap() & 0x1f);
+        let response = [1, 9, 0, 0xc0 | tag, 0, instance_id, CMD_DISCOVERY_NOTIFY, 0];
+        assert_eq!(
+            router.inbound_disposition(&response),
+            Disposition::NotifyAnswered
+        );
+        router.update(800).unwrap();
+        assert_eq!(notifies(&packets), 0);
+    }
+
+    /// Messages are shared with further consumers of a listener without copying
+    #[test]
+    fn consumers() {
+        use crate::CONSUMER_TABLE_SIZE;
+        use mctp_estack::config::NUM_RECEIVE;
+
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, NullSender);
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+        let logger = router.add_consumer(listener).unwrap();
+        assert_eq!(logger.listener(), listener);
+
+        router.inbound(&[1, 8, 9, 0xc8, 1, 5]).unwrap();
+        router.inbound(&[1, 8, 9, 0xc9, 1, 6]).unwrap();
+        assert_eq!(router.recv_shared(logger).unwrap().payload, [5]);
+        assert_eq!(router.recv(listener).unwrap().payload, [5]);
+        // Received by the listener first, still held for the consumer
+        assert_eq!(router.recv(listener).unwrap().payload, [6]);
+        assert!(router.recv(listener).is_none());
+        assert_eq!(router.recv_shared(logger).unwrap().payload, [6]);
+        assert!(router.recv_shared(logger).is_none());
+
+        // Buffers are released by the last reader
+        for i in 0..NUM_RECEIVE + 2 {
+            router.inbound(&[1, 8, 9, 0xc8, 1, i as u8]).unwrap();
+            assert_eq!(router.recv(listener).unwrap().payload, [i as u8]);
+            assert_eq!(router.recv_shared(logger).unwrap().payload, [i as u8]);
+        }
+        router.inbound(&[1, 8, 9, 0xc8, 1, 8]).unwrap();
+        router.recv(listener).unwrap();
+        router.remove_consumer(logger).unwrap();
+        assert!(router.recv_shared(logger).is_none());
+        assert!(router.remove_consumer(logger).is_err());
+        for _ in 0..NUM_RECEIVE + 2 {
+            router.inbound(&[1, 8, 9, 0xc8, 1, 9]).unwrap();
+            assert_eq!(router.recv(listener).unwrap().payload, [9]);
+        }
+        assert_eq!(router.consumers_missed(), 0);
+
+        for _ in 0..CONSUMER_TABLE_SIZE {
+            router.add_consumer(listener).unwrap();
+        }
+        assert!(router.add_consumer(listener).is_err());
+        router.unbind(listener).unwrap();
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+        assert!(router.add_consumer(listener).is_ok());
+    }
+
+    /// Packets sent on a padded port are padded, inbound padding is stripped
+    #[test]
+    fn port_padding() {
+        use crate::Padding;
+
+        let packets = RefCell::new(Vec::new());
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, BufferSender::<64>::new(&packets));
+        assert!(matches!(
+            router.set_port_padding(0, Some(Padding::new().min_len(65))),
+            Err(mctp::Error::BadArgument)
+        ));
+        router
+            .set_port_padding(0, Some(Padding::new().min_len(12).align(8).fill(0xff)))
+            .unwrap();
+        assert_eq!(router.port_padding(0).unwrap().padded_len(13), 16);
+        assert!(router.port_padding(1).is_none());
+
+        let req = router.req(Eid(9)).unwrap();
+        router
+            .send(
+                None,
+                mctp::MsgType(1),
+                None,
+                mctp::MsgIC(false),
+                req,
+                &[5, 6],
+            )
+            .unwrap();
+        let pkt = packets.borrow_mut().pop().unwrap();
+        assert_eq!(
+            pkt,
+            [
+                1, 9, 8, 0xc8, 1, 5, 6, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff
+            ]
+        );
+
+        // Packets filling the alignment are sent as they are
+        router
+            .send(
+                None,
+                mctp::MsgType(1),
+                None,
+                mctp::MsgIC(false),
+                req,
+                &[0; 11],
+            )
+            .unwrap();
+        assert_eq!(packets.borrow_mut().pop().unwrap().len(), 16);
+        router.set_port_padding(0, None).unwrap();
+        router
+            .send(
+                None,
+                mctp::MsgType(1),
+                None,
+                mctp::MsgIC(false),
+                req,
+                &[5, 6],
+            )
+            .unwrap();
+        assert_eq!(packets.borrow_mut().pop().unwrap().len(), 7);
+
+        let mut peer: Router<_, 4, 4> = Router::new(Eid(9), 0, NullSender);
+        let listener = peer.listener(mctp::MsgType(1)).unwrap();
+        assert!(matches!(
+            peer.inbound_padded(&pkt, 13),
+            Err(mctp::Error::InvalidInput)
+        ));
+        assert_eq!(peer.inbound_padded(&pkt, 9).unwrap(), Some(listener.into()));
+        assert_eq!(peer.recv(listener).unwrap().payload, [5, 6]);
+    }
+
+    /// Declared capabilities answer the control queries and gate listeners
+    #[test]
+    fn capabilities() {
+        use crate::Capability;
+        use crate::control::{
+            CC_SUCCESS, CC_UNSUPPORTED_MSG_TYPE, CMD_GET_MESSAGE_TYPE_SUPPORT,
+            CMD_GET_VENDOR_MESSAGE_SUPPORT, CMD_GET_VERSION_SUPPORT, MSG_TYPE_VENDOR_PCI, VendorId,
+            VendorSupport,
+        };
+
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, NullSender);
+        let query = |router: &mut Router<_, 4, 4>, request: &[u8]| {
+            let mut response = [0; 40];
+            let len = router
+                .control_response(Eid(10), request, &mut response)
+                .unwrap()
+                .unwrap();
+            response.get(2..len).unwrap().to_vec()
+        };
+        let pci = VendorSupport {
+            vendor: VendorId::Pci(0x1af4),
+            command_set: 1,
+        };
+        let iana = VendorSupport {
+            vendor: VendorId::Iana(0x0000_a015),
+            command_set: 2,
+        };
+        let versions = [[0xf1, 0xf0, 0xff, 0]];
+        assert!(matches!(
+            router.declare_capability(&Capability::new(MSG_TYPE_VENDOR_PCI).vendors(&[iana])),
+            Err(mctp::Error::BadArgument)
+        ));
+        assert!(matches!(
+            router.declare_capability(&Capability::new(mctp::MsgType(0))),
+            Err(mctp::Error::BadArgument)
+        ));
+        assert!(!router.is_declared(MSG_TYPE_VENDOR_PCI));
+        router
+            .declare_capability(
+                &Capability::new(MSG_TYPE_VENDOR_PCI)
+                    .versions(&versions)
+                    .vendors(&[pci]),
+            )
+            .unwrap();
+        router
+            .declare_capability(&Capability::new(mctp::MsgType(5)))
+            .unwrap();
+
+        assert_eq!(
+            query(&mut router, &[0x80, CMD_GET_MESSAGE_TYPE_SUPPORT]),
+            [CC_SUCCESS, 2, 5, 0x7e]
+        );
+        assert_eq!(
+            query(&mut router, &[0x80, CMD_GET_VERSION_SUPPORT, 0x7e]),
+            [CC_SUCCESS, 1, 0xf1, 0xf0, 0xff, 0]
+        );
+        assert_eq!(
+            query(&mut router, &[0x80, CMD_GET_VENDOR_MESSAGE_SUPPORT, 0]),
+            [CC_SUCCESS, 0xff, 0x00, 0x1a, 0xf4, 0, 1]
+        );
+        assert_eq!(
+            router.local_endpoint().message_types.unwrap().as_slice(),
+            [mctp::MsgType(5), MSG_TYPE_VENDOR_PCI]
+        );
+
+        // Only declared types get listeners
+        router.set_declared_types_only(true);
+        assert!(matches!(
+            router.listener(mctp::MsgType(6)),
+            Err(mctp::Error::BadArgument)
+        ));
+        let listener = router.listener(mctp::MsgType(5)).unwrap();
+        assert!(router.rebind(listener, mctp::MsgType(6)).is_err());
+        router.rebind(listener, MSG_TYPE_VENDOR_PCI).unwrap();
+
+        assert!(router.withdraw_capability(MSG_TYPE_VENDOR_PCI));
+        assert!(!router.withdraw_capability(MSG_TYPE_VENDOR_PCI));
+        assert_eq!(
+            query(&mut router, &[0x80, CMD_GET_VERSION_SUPPORT, 0x7e]),
+            [CC_UNSUPPORTED_MSG_TYPE]
+        );
+        assert_eq!(
+            query(&mut router, &[0x80, CMD_GET_MESSAGE_TYPE_SUPPORT]),
+            [CC_SUCCESS, 2, 5, 0x7e]
+        );
+        assert!(router.listener(MSG_TYPE_VENDOR_PCI).is_err());
+    }
+
+    /// Messages held back past their deadline are dropped and reported
+    #[test]
+    fn send_deadline() {
+        use crate::{ExpiryReason, Handle, SendArgs, Throttle};
+
+        #[derive(Default)]
+        struct Expiries(Vec<(Handle, ExpiryReason)>);
+
+        impl Hooks for Expiries {
+            fn message_expired(&mut self, handle: Handle, reason: ExpiryReason) {
+                self.0.push((handle, reason));
+            }
+        }
+
+        let packets = RefCell::new(Vec::new());
+        let mut router: Router<_, 1, 2, Expiries> = Router::new_with_config(
+            crate::RouterConfig::new(Eid(8)),
+            0,
+            BufferSender::<64>::new(&packets),
+            Expiries::default(),
+        );
+        let req = router.req(Eid(9)).unwrap();
+        router
+            .set_throttle(Eid(9), Some(Throttle::new(64, 1000)))
+            .unwrap();
+        router.set_transmit_buffer(Vec::leak(vec![0; 256]));
+        router.update(10).unwrap();
+
+        let args = SendArgs::request(mctp::MsgType(1))
+            .payload(&[0; 100])
+            .deadline(20);
+        assert_eq!(router.send_msg(req, args).unwrap().held, 1);
+        assert_eq!(router.update(15).unwrap(), 5);
+        assert_eq!(router.throttled(), 1);
+        assert!(router.hooks().0.is_empty());
+
+        // The held packet is dropped instead of being sent late
+        router.update(20).unwrap();
+        assert_eq!(router.throttled(), 0);
+        assert_eq!(packets.borrow().len(), 1);
+        assert_eq!(
+            router.hooks().0,
+            [(req.into(), ExpiryReason::DeadlinePassed)]
+        );
+
+        // Messages already late are not sent at all
+        let args = SendArgs::request(mctp::MsgType(1))
+            .payload(&[1])
+            .deadline(20);
+        assert!(matches!(
+            router.send_msg(req, args).map_err(|e| e.into_inner()),
+            Err(mctp::Error::TimedOut)
+        ));
+        assert_eq!(packets.borrow().len(), 1);
+        assert_eq!(router.tx_stats().expired, 2);
+        assert_eq!(router.hooks().0.len(), 2);
+    }
+
+    /// Routers for a single role work without the tables of the other one
+    #[test]
+    fn single_role() {
+        use crate::{Disposition, RequesterRouter, ResponderRouter};
+
+        let packets = RefCell::new(Vec::new());
+        let mut requester: RequesterRouter<_, 2> =
+            Router::new(Eid(8), 0, BufferSender::<64>::new(&packets));
+        let mut responder: ResponderRouter<_, 2> = Router::new(Eid(9), 0, NullSender);
+        assert!(matches!(
+            requester.listener(mctp::MsgType(1)),
+            Err(mctp::Error::NoSpace)
+        ));
+        assert!(matches!(responder.req(Eid(8)), Err(mctp::Error::NoSpace)));
+        assert_eq!(
+            requester.inbound_disposition(&[1, 8, 9, 0xc8, 1, 5]),
+            Disposition::DroppedNoListener
+        );
+        assert_eq!(
+            responder.inbound_disposition(&[1, 9, 8, 0xc0, 1, 5]),
+            Disposition::DroppedNoRequest
+        );
+
+        let listener = responder.listener(mctp::MsgType(1)).unwrap();
+        let req = requester.req(Eid(9)).unwrap();
+        requester
+            .send(None, mctp::MsgType(1), None, mctp::MsgIC(false), req, &[5])
+            .unwrap();
+        assert_eq!(
+            crate::test_util::transfer(&packets, &mut responder).unwrap(),
+            1
+        );
+        assert_eq!(responder.recv(listener).unwrap().payload, [5]);
+    }
+
+    /// Generated trains are conformant until bent, the router output passes the checker
+    #[test]
+    fn conformance() {
+        use crate::conformance::{Finding, Nonconformance, PacketTrain, check};
+        use mctp::{Tag, TagValue};
+
+        let tag = Tag::Owned(TagValue(1));
+        let train = PacketTrain::message(Eid(9), Eid(8), tag, &[1; 150], 64);
+        assert_eq!(train.packets().len(), 3);
+        assert!(check(train.packets(), 64).is_empty());
+
+        let mut router: Router<_, 2, 2> = Router::new(Eid(8), 0, NullSender);
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+        for pkt in train.packets() {
+            router.inbound(pkt).unwrap();
+        }
+        assert_eq!(router.recv(listener).unwrap().payload.len(), 149);
+
+        let finding = |index, issue| Finding { index, issue };
+        assert_eq!(
+            check(train.clone().remove(1).packets(), 64),
+            [finding(
+                1,
+                Nonconformance::SequenceError {
+                    expected: 1,
+                    found: 2
+                }
+            )]
+        );
+        assert_eq!(
+            check(train.clone().set_eom(2, false).packets(), 64),
+            [
+                finding(
+                    2,
+                    Nonconformance::UnevenFragment {
+                        len: 22,
+                        expected: 64
+                    }
+                ),
+                finding(3, Nonconformance::Unterminated),
+            ]
+        );
+        assert_eq!(
+            check(train.clone().set_payload_len(1, 10).packets(), 48),
+            [
+                finding(0, Nonconformance::TooLarge { len: 64, mtu: 48 }),
+                finding(
+                    1,
+                    Nonconformance::UnevenFragment {
+                        len: 10,
+                        expected: 64
+                    }
+                ),
+            ]
+        );
+        assert_eq!(
+            check(
+                train
+                    .clone()
+                    .set_som(0, false)
+                    .set_version(2, 0x11)
+                    .packets(),
+                64
+            ),
+            [
+                finding(0, Nonconformance::MissingSom),
+                finding(1, Nonconformance::MissingSom),
+                finding(
+                    2,
+                    Nonconformance::Violation(crate::Violation::ReservedHeaderBits)
+                ),
+                finding(2, Nonconformance::MissingSom),
+            ]
+        );
+        let interrupted = train.clone().duplicate(0);
+        assert_eq!(
+            check(interrupted.packets(), 64),
+            [finding(1, Nonconformance::Interrupted)]
+        );
+
+        // What the router sends conforms
+        let packets = RefCell::new(Vec::new());
+        let mut sender: Router<_, 2, 2> = Router::new(Eid(9), 0, BufferSender::<32>::new(&packets));
+        let req = sender.req(Eid(8)).unwrap();
+        sender
+            .send(
+                None,
+                mctp::MsgType(1),
+                None,
+                mctp::MsgIC(true),
+                req,
+                &[2; 100],
+            )
+            .unwrap();
+        assert!(packets.borrow().len() > 1);
+        assert!(check(packets.borrow().iter(), 32).is_empty());
+    }
+
+    #[test]
+    fn dispatch_trace() {
+        use crate::{DISPATCH_TRACE_SIZE, Disposition, NoHooks, RouterConfig};
+
+        let config = RouterConfig::new(Eid(8)).dispatch_trace(true);
+        let mut router: Router<_, 1, 1> = Router::new_with_config(config, 0, NullSender, NoHooks);
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+
+        router.inbound(&[1, 8, 10, 0xc8, 1, 0xaa]).unwrap();
+        router.update(5).unwrap();
+        router.inbound(&[1, 8, 10, 0xc9, 2, 0xbb]).unwrap();
+        let _ = router.inbound(&[1, 8]);
+        let trace: Vec<_> = router.dispatch_trace().collect();
+        let dispositions: Vec<_> = trace.iter().map(|e| e.disposition).collect();
+        assert_eq!(
+            dispositions,
+            [
+                Disposition::Delivered(listener.into()),
+                Disposition::DroppedNoListener,
+                Disposition::DroppedReassemblyError,
+            ]
+        );
+        let first = trace.first().unwrap();
+        assert_eq!((first.source, first.dest, first.len), (Eid(10), Eid(8), 6));
+        assert_eq!(first.typ, Some(mctp::MsgType(1)));
+        assert_eq!(
+            trace.get(1).map(|e| e.to_string()).unwrap(),
+            "5 ms: 10 -> 8, 6 bytes, tag 1 TO, seq 0, SOM, EOM, type 2: DroppedNoListener"
+        );
+        assert_eq!(trace.get(2).map(|e| e.tag), Some(None));
+
+        // The ring keeps the most recent decisions
+        for _ in 0..DISPATCH_TRACE_SIZE {
+            router.inbound(&[1, 8, 10, 0xc8, 3, 0xcc]).unwrap();
+        }
+        assert_eq!(router.dispatch_trace().count(), DISPATCH_TRACE_SIZE);
+        assert!(
+            router
+                .dispatch_trace()
+                .all(|e| e.disposition == Disposition::DroppedNoListener)
+        );
+
+        router.clear_dispatch_trace();
+        assert_eq!(router.dispatch_trace().count(), 0);
+        router.set_dispatch_trace(false);
+        router.inbound(&[1, 8, 10, 0xc8, 3, 0xcc]).unwrap();
+        assert_eq!(router.dispatch_trace().count(), 0);
+    }
+
+    #[test]
+    fn shutdown() {
+        use crate::{ShutdownPolicy, ShutdownReport, Throttle};
+
+        let packets = RefCell::new(Vec::new());
+        let mut router: Router<_, 2, 2> = Router::new(Eid(8), 0, BufferSender::<64>::new(&packets));
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+        let req = router.req(Eid(9)).unwrap();
+        let idle = router.req(Eid(10)).unwrap();
+        router
+            .set_throttle(Eid(9), Some(Throttle::new(64, 1000)))
+            .unwrap();
+        router.set_transmit_buffer(Vec::leak(vec![0; 256]));
+        let report = router
+            .send(
+                None,
+                mctp::MsgType(1),
+                None,
+                mctp::MsgIC(false),
+                req,
+                &[0; 100],
+            )
+            .unwrap();
+        assert_eq!(report.held, 1);
+
+        // Held packets are flushed despite the throttle, all handles are unbound
+        assert_eq!(
+            router.shutdown(ShutdownPolicy::Flush),
+            ShutdownReport {
+                cancelled: 1,
+                unbound: 3,
+                flushed: 1,
+                dropped: 0,
+            }
+        );
+        assert_eq!(packets.borrow().len(), 2);
+        assert_eq!(router.throttled(), 0);
+        assert!(router.is_shut_down() && router.is_quiesced() && router.is_idle());
+
+        // Handles fail with a distinct error, and so does binding new ones
+        assert!(matches!(
+            router
+                .send(None, mctp::MsgType(1), None, mctp::MsgIC(false), idle, &[1])
+                .map_err(|e| e.into_inner()),
+            Err(mctp::Error::Other)
+        ));
+        assert!(matches!(
+            router.unbind(listener).map_err(|e| e.into_inner()),
+            Err(mctp::Error::Other)
+        ));
+        assert!(router.recv(req).is_none());
+        assert!(matches!(router.req(Eid(9)), Err(mctp::Error::Other)));
+        assert!(matches!(
+            router.listener(mctp::MsgType(2)),
+            Err(mctp::Error::Other)
+        ));
+        router.resume();
+        assert!(router.is_quiesced());
+
+        let sender = router.into_sender();
+        assert!(core::ptr::eq(sender.packets, &packets));
+    }
+
+    #[test]
+    fn listener_group() {
+        use crate::LISTENER_GROUP_SIZE;
+
+        let mut router: Router<_, 4, 1> = Router::new(Eid(8), 0, NullSender);
+        let group = router
+            .listener_group(&[mctp::MsgType(1), mctp::MsgType(0x7e)])
+            .unwrap();
+        assert!(matches!(
+            router.listener(mctp::MsgType(0x7e)),
+            Err(mctp::Error::AddrInUse)
+        ));
+        assert!(matches!(
+            router.listener_group(&[mctp::MsgType(2), mctp::MsgType(2)]),
+            Err(mctp::Error::BadArgument)
+        ));
+        assert!(matches!(
+            router.listener_group(&[mctp::MsgType(2), mctp::MsgType(1)]),
+            Err(mctp::Error::AddrInUse)
+        ));
+
+        // Requests of both types share the queue of the group
+        assert_eq!(
+            router.inbound(&[1, 8, 10, 0xc8, 0x7e, 0xaa]).unwrap(),
+            Some(group.into())
+        );
+        assert_eq!(
+            router.inbound(&[1, 8, 10, 0xc9, 1, 0xbb]).unwrap(),
+            Some(group.into())
+        );
+        assert_eq!(router.queued(group), Some(2));
+        for expect in [(0x7e, 0xaa), (1, 0xbb)] {
+            let msg = router.recv(group).unwrap();
+            assert_eq!((msg.typ.0, msg.payload), (expect.0, &[expect.1][..]));
+        }
+
+        // Plain listeners can be extended up to the group size
+        let listener = router.listener(mctp::MsgType(5)).unwrap();
+        for typ in 6..5 + LISTENER_GROUP_SIZE as u8 {
+            router
+                .add_listener_type(listener, mctp::MsgType(typ))
+                .unwrap();
+        }
+        assert!(matches!(
+            router
+                .add_listener_type(listener, mctp::MsgType(20))
+                .map_err(|e| e.into_inner()),
+            Err(mctp::Error::NoSpace)
+        ));
+        assert!(matches!(
+            router
+                .add_listener_type(listener, mctp::MsgType(1))
+                .map_err(|e| e.into_inner()),
+            Err(mctp::Error::AddrInUse)
+        ));
+        assert_eq!(
+            router.listeners().filter(|l| l.0 == listener).count(),
+            LISTENER_GROUP_SIZE
+        );
+        assert!(
+            router
+                .message_types()
+                .as_slice()
+                .contains(&mctp::MsgType(0x7e))
+        );
+
+        // Unbinding frees all types of the group
+        router.unbind(group).unwrap();
+        router.listener(mctp::MsgType(0x7e)).unwrap();
+    }
+
+    #[test]
+    fn config_validation() {
+        use crate::{
+            ArrayTables, BusyRetry, Clock, ConfigError, GenericRouter, NoHooks, RateLimit,
+            RouterConfig, Timing,
+        };
+
+        /// A clock ticking every 10 ms
+        struct Coarse;
+
+        impl Clock for Coarse {
+            fn now_millis(&self) -> u64 {
+                0
+            }
+
+            fn resolution_millis(&self) -> u64 {
+                10
+            }
+        }
+
+        let config = RouterConfig::new(Eid(8));
+        assert!(
+            Router::<_, 2, 2>::try_new_with_config(config.clone(), 0, NullSender, NoHooks).is_ok()
+        );
+
+        let packets = RefCell::new(Vec::new());
+        let tiny = Router::<_, 2, 2>::try_new_with_config(
+            config.clone(),
+            0,
+            BufferSender::<4>::new(&packets),
+            NoHooks,
+        );
+        assert_eq!(
+            tiny.err(),
+            Some(ConfigError::MtuTooSmall { eid: None, mtu: 4 })
+        );
+
+        let config = config.request_timeout_millis(Some(5));
+        assert!(
+            Router::<_, 2, 2>::try_new_with_config(config.clone(), 0, NullSender, NoHooks).is_ok()
+        );
+        let coarse = GenericRouter::try_new_with_tables(
+            config.clone(),
+            Coarse,
+            NullSender,
+            NoHooks,
+            ArrayTables::<2, 2>::new(),
+        );
+        assert_eq!(
+            coarse.err(),
+            Some(ConfigError::BelowResolution {
+                timing: Timing::RequestTimeout,
+                millis: 5,
+                resolution_millis: 10,
+            })
+        );
+
+        let busy = config.clone().busy_retry(Some(BusyRetry::new(0, 3)));
+        assert_eq!(
+            Router::<_, 2, 2>::try_new_with_config(busy, 0, NullSender, NoHooks).err(),
+            Some(ConfigError::BelowResolution {
+                timing: Timing::BusyRetryDelay,
+                millis: 0,
+                resolution_millis: 1,
+            })
+        );
+
+        let stalled = config.rate_limit(Some(RateLimit::new(0, 100)));
+        assert_eq!(
+            Router::<_, 2, 2>::try_new_with_config(stalled, 0, NullSender, NoHooks).err(),
+            Some(ConfigError::RateLimitStalled)
+        );
+    }
+
+    #[test]
+    fn message_integrity() {
+        use crate::{Disposition, MessageIntegrity, message_body};
+
+        /// Sum of the type and body bytes
+        #[derive(Debug)]
+        struct Sum;
+
+        impl MessageIntegrity for Sum {
+            fn mic_len(&self) -> usize {
+                1
+            }
+
+            fn compute(&self, typ: mctp::MsgType, bufs: &[&[u8]], mic: &mut [u8]) {
+                let sum = bufs
+                    .iter()
+                    .flat_map(|b| b.iter())
+                    .fold(typ.0, |s, b| s.wrapping_add(*b));
+                if let Some(m) = mic.first_mut() {
+                    *m = sum;
+                }
             }
-            Ok(())
         }
-    }
 
-    fn lookup_request(&self, cookie: AppCookie) -> Option<&ReqHandle> {
-        Self::requests_index_from_cookie(cookie)
-            .and_then(|i| self.requests.get(i).and_then(|r| r.as_ref()))
-    }
+        let packets = RefCell::new(Vec::new());
+        let mut requester: Router<_, 1, 1> =
+            Router::new(Eid(9), 0, BufferSender::<64>::new(&packets));
+        let mut responder: Router<_, 1, 1> = Router::new(Eid(8), 0, NullSender);
+        requester
+            .set_message_integrity(mctp::MsgType(4), Some(&Sum))
+            .unwrap();
+        responder
+            .set_message_integrity(mctp::MsgType(4), Some(&Sum))
+            .unwrap();
+        let listener = responder.listener(mctp::MsgType(4)).unwrap();
 
-    /// Function to create a router unique AppCookie for listeners
-    ///
-    /// Currently, the listeners are just the index ranging from 0 to LISTENER_HANDLES-1.
-    /// Requests are enumerated from LISTENER_HANDLES to LISTENER_HANDLES+REQUEST_HANDLES-1
-    fn listener_cookie_from_index(i: usize) -> AppCookie {
-        debug_assert!(
-            i < MAX_LISTENER_HANDLES,
-            "tried to create out of range listener AppCookie!"
+        // The MIC is appended ahead of the MCTP integrity check
+        let req = requester.req(Eid(8)).unwrap();
+        requester
+            .send(
+                None,
+                mctp::MsgType(4),
+                None,
+                mctp::MsgIC(true),
+                req,
+                &[1, 2],
+            )
+            .unwrap();
+        assert_eq!(
+            packets.borrow().first().and_then(|p| p.get(4..8)),
+            Some(&[0x84, 1, 2, 7][..])
         );
-        AppCookie(i)
-    }
+        assert_eq!(
+            crate::test_util::transfer(&packets, &mut responder).unwrap(),
+            1
+        );
+        let msg = responder.recv(listener).unwrap();
+        assert_eq!(message_body(&msg), [1, 2, 7]);
+        drop(msg);
 
-    /// Function to create a router unique [AppCookie] for requests
-    ///
-    /// Currently, the listeners are just the index ranging from 0 to `LISTENER_HANDLES-1`.
-    /// Requests are enumerated from `LISTENER_HANDLES` to `LISTENER_HANDLES+REQUEST_HANDLES-1`.
-    fn req_cookie_from_index(i: usize) -> AppCookie {
-        debug_assert!(
-            i < MAX_REQ_HANDLES,
-            "tried to create out of range request AppCookie!"
+        // A wrong MIC is dropped
+        assert_eq!(
+            responder.inbound_disposition(&[1, 8, 9, 0xc9, 4, 1, 2, 8]),
+            Disposition::DroppedIntegrityError
+        );
+        assert_eq!(
+            responder.inbound_disposition(&[1, 8, 9, 0xca, 4, 1, 2, 7]),
+            Disposition::Delivered(listener.into())
         );
-        AppCookie(i + MAX_LISTENER_HANDLES)
-    }
 
-    /// Get the listener array index from an [AppCookie]
-    ///
-    /// Returns `None` for invalid cookies.
-    fn listeners_index_from_cookie(cookie: AppCookie) -> Option<usize> {
-        if cookie.0 < MAX_LISTENER_HANDLES {
-            Some(cookie.0)
-        } else {
-            None
+        // Other types are left alone
+        requester
+            .send(
+                None,
+                mctp::MsgType(5),
+                None,
+                mctp::MsgIC(false),
+                req,
+                &[1, 2],
+            )
+            .unwrap();
+        assert_eq!(packets.borrow().first().map(|p| p.len()), Some(7));
+
+        // The MIC takes a buffer, and the integrity check one more
+        let bufs = [&[0u8][..]; crate::MAX_IC_BUFS + 1];
+        for (ic, max) in [(false, crate::MAX_IC_BUFS), (true, crate::MAX_IC_BUFS - 1)] {
+            let send = |requester: &mut Router<_, 1, 1>, n: usize| {
+                requester.send_vectored(
+                    None,
+                    mctp::MsgType(4),
+                    None,
+                    mctp::MsgIC(ic),
+                    req,
+                    bufs.get(..n).unwrap_or_default(),
+                )
+            };
+            assert!(send(&mut requester, max).is_ok());
+            let err = send(&mut requester, max + 1).unwrap_err();
+            assert!(matches!(err.error(), mctp::Error::BadArgument));
         }
     }
 
-    /// Get the requester array index from a [AppCookie]
-    ///
-    /// Returns `None` for invalid cookies.
-    fn requests_index_from_cookie(cookie: AppCookie) -> Option<usize> {
-        if cookie.0 >= MAX_LISTENER_HANDLES && cookie.0 < (MAX_LISTENER_HANDLES + MAX_REQ_HANDLES) {
-            Some(cookie.0 - MAX_LISTENER_HANDLES)
-        } else {
-            None
+    #[test]
+    fn undersized_messages() {
+        use super::{Disposition, MinSize, Undersized};
+
+        fn too_short(_typ: mctp::MsgType, request: &[u8], response: &mut [u8]) -> Option<usize> {
+            let out = response.get_mut(..2)?;
+            out.copy_from_slice(&[0xee, request.len() as u8]);
+            Some(2)
         }
-    }
 
-    /// Check if a cookie is a corresponding to a listener
-    ///
-    /// Checks based on the contained id.
-    /// Returns false for request cookies.
-    fn cookie_is_listener(cookie: &AppCookie) -> bool {
-        cookie.0 < MAX_LISTENER_HANDLES
+        let packets = RefCell::new(Vec::new());
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, BufferSender::<64>::new(&packets));
+        let one = router.listener(mctp::MsgType(1)).unwrap();
+        let two = router.listener(mctp::MsgType(2)).unwrap();
+        let three = router.listener(mctp::MsgType(3)).unwrap();
+
+        // Empty messages are delivered by default
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0xc8, 1]),
+            Disposition::Delivered(one.into())
+        );
+        assert!(router.recv(one).is_some());
+        assert_eq!(router.undersized(), 1);
+
+        router.set_empty_policy(Undersized::Drop);
+        router
+            .set_min_size(
+                mctp::MsgType(2),
+                Some(MinSize::new(3, Undersized::Respond(too_short))),
+            )
+            .unwrap();
+        router
+            .set_min_size(mctp::MsgType(3), Some(MinSize::new(0, Undersized::Drop)))
+            .unwrap();
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0xc8, 1]),
+            Disposition::DroppedTooSmall
+        );
+        assert!(
+            router
+                .inbound_disposition(&[1, 8, 9, 0xc8, 1, 0])
+                .handle()
+                .is_some()
+        );
+
+        // Undersized requests of type 2 are answered, responses only dropped
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0xc8 | 1, 2, 0, 0]),
+            Disposition::DroppedTooSmall
+        );
+        assert_eq!(packets.take(), [vec![1, 9, 8, 0xc0 | 1, 2, 0xee, 2]]);
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0xc0, 2]),
+            Disposition::DroppedTooSmall
+        );
+        assert!(packets.borrow().is_empty());
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0xc8 | 2, 2, 0, 0, 0]),
+            Disposition::Delivered(two.into())
+        );
+
+        // A minimum of 0 exempts the type from the empty policy
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0xc8 | 3, 3]),
+            Disposition::Delivered(three.into())
+        );
+        assert_eq!(router.undersized(), 4);
     }
-}
 
-/// A Sender used by a [Router] to send data
-///
-/// Implemented by a transport binding for sending packets.
-pub trait Sender {
-    /// Send a packet fragmented by `fragmenter` with the payload `payload`
-    fn send_vectored(&mut self, eid: Eid, fragmenter: Fragmenter, payload: &[&[u8]])
-    -> Result<Tag>;
-    /// Get the MTU of a MCTP packet fragment (without transport headers)
-    fn get_mtu(&self) -> usize;
-}
+    #[test]
+    fn refragmentation() {
+        use super::Disposition;
 
-#[cfg(test)]
-#[allow(clippy::panic)]
-mod test {
-    use core::cell::RefCell;
+        let packets = RefCell::new(Vec::new());
+        let mut router: Router<_, 4, 4> =
+            Router::new(Eid(8), 0, BufferSender::<255>::new(&packets));
+        router.set_forwarding(true);
+        router.set_refragmentation(true);
+        router.set_mtu(Eid(30), 8).unwrap();
 
-    use mctp::Eid;
+        // Owned tag 0 from 20 to 30, payload of 9 bytes in two packets
+        let first = [1, 30, 20, 0x88, 1, 0xa0, 0xa1, 0xa2, 0xa3, 0xa4, 0xa5];
+        let last = [1, 30, 20, 0x58, 0xa6, 0xa7];
+        assert_eq!(router.inbound_disposition(&first), Disposition::Forwarded);
+        assert_eq!(packets.take(), [vec![1, 30, 20, 0x88, 1, 0xa0, 0xa1, 0xa2]]);
+        assert_eq!(router.inbound_disposition(&last), Disposition::Forwarded);
+        assert_eq!(
+            packets.take(),
+            [
+                vec![1, 30, 20, 0x18, 0xa3, 0xa4, 0xa5, 0xa6],
+                vec![1, 30, 20, 0x68, 0xa7],
+            ]
+        );
+
+        // Messages fitting the MTU pass unmodified
+        let small = [1, 30, 20, 0xc9, 1, 0xb0];
+        assert_eq!(router.inbound_disposition(&small), Disposition::Forwarded);
+        assert_eq!(packets.take(), [small.to_vec()]);
+
+        // A lost packet abandons the message
+        assert_eq!(router.inbound_disposition(&first), Disposition::Forwarded);
+        let skipped = [1, 30, 20, 0x68, 0xa6, 0xa7];
+        assert_eq!(
+            router.inbound_disposition(&skipped),
+            Disposition::DroppedReassemblyError
+        );
+        assert_eq!(packets.take().len(), 1);
 
-    use crate::{Router, Sender};
+        // Without refragmentation, packets are only limited by the port MTU
+        router.set_refragmentation(false);
+        assert_eq!(router.inbound_disposition(&first), Disposition::Forwarded);
+        assert_eq!(packets.take(), [first.to_vec()]);
+    }
 
-    struct DoNothingSender;
+    #[test]
+    fn round_robin_scheduling() {
+        use crate::{NoHooks, RouterConfig, Scheduling, Throttle, WorkBudget};
+        use mctp::{MsgIC, MsgType};
 
-    impl Sender for DoNothingSender {
-        fn send_vectored(
-            &mut self,
-            _eid: Eid,
-            fragmenter: mctp_estack::fragment::Fragmenter,
-            payload: &[&[u8]],
-        ) -> core::result::Result<mctp::Tag, mctp::Error> {
-            let _ = payload;
-            Ok(fragmenter.tag())
+        let mut router: Router<_, 2, 2> = Router::new(Eid(8), 0, NullSender);
+        let one = router.listener(MsgType(1)).unwrap();
+        let two = router.listener(MsgType(2)).unwrap();
+        for pkt in [[1, 8, 9, 0xc8, 1], [1, 8, 9, 0xc9, 1], [1, 8, 9, 0xca, 2]] {
+            router.inbound(&pkt).unwrap();
         }
 
-        fn get_mtu(&self) -> usize {
-            255
+        // In order, the first listener stays in front
+        assert!(router.recv(one).is_some());
+        assert_eq!(router.poll_ready().next(), Some(one.into()));
+
+        // Round robin puts the listener received from last at the end
+        router.set_scheduling(Scheduling::RoundRobin);
+        assert_eq!(
+            router.poll_ready().collect::<Vec<_>>(),
+            [two.into(), one.into()]
+        );
+        assert!(router.recv(two).is_some());
+        assert_eq!(router.poll_ready().collect::<Vec<_>>(), [one.into()]);
+
+        // Held packets are released one per request in turn
+        let packets = RefCell::new(Vec::new());
+        let config = RouterConfig::new(Eid(8))
+            .scheduling(Scheduling::RoundRobin)
+            .work_budget(WorkBudget {
+                timers: None,
+                packets: Some(2),
+            });
+        let mut router: Router<_, 1, 2> =
+            Router::new_with_config(config, 0, BufferSender::<64>::new(&packets), NoHooks);
+        router.set_transmit_buffer(std::boxed::Box::leak(std::vec![0; 1024].into_boxed_slice()));
+        router
+            .set_throttle(Eid(9), Some(Throttle::new(64, 1000)))
+            .unwrap();
+        let first = router.req(Eid(9)).unwrap();
+        let second = router.req(Eid(9)).unwrap();
+        for req in [first, second] {
+            router
+                .send(None, MsgType(1), None, MsgIC(false), req, &[0; 150])
+                .unwrap();
         }
-    }
+        let sent = packets.take();
+        assert_eq!(sent.len(), 1);
+        let tag_of = |pkt: &Vec<u8>| pkt.get(3).map(|flags| flags & 0x07);
+        let first_tag = sent.first().and_then(tag_of);
 
-    struct BufferSender<'a, const MTU: usize> {
-        packets: &'a RefCell<Vec<Vec<u8>>>,
+        router
+            .set_throttle(Eid(9), Some(Throttle::new(1000, 1000)))
+            .unwrap();
+        router.update(0).unwrap();
+        let released = packets.take();
+        assert_eq!(released.len(), 2);
+        assert_eq!(released.first().and_then(tag_of), first_tag);
+        assert_ne!(released.get(1).and_then(tag_of), first_tag);
+        assert_eq!(router.throttled(), 3);
     }
 
-    impl<const MTU: usize> Sender for BufferSender<'_, MTU> {
-        fn send_vectored(
-            &mut self,
-            _eid: Eid,
-            mut fragmenter: mctp_estack::fragment::Fragmenter,
-            payload: &[&[u8]],
-        ) -> core::result::Result<mctp::Tag, mctp::Error> {
-            loop {
-                let mut buf = [0; MTU];
-                match fragmenter.fragment_vectored(payload, &mut buf) {
-                    mctp_estack::fragment::SendOutput::Packet(items) => {
-                        self.packets.borrow_mut().push(items.into())
-                    }
-                    mctp_estack::fragment::SendOutput::Complete { tag, cookie: _ } => {
-                        return Ok(tag);
-                    }
-                    mctp_estack::fragment::SendOutput::Error { err, cookie: _ } => return Err(err),
-                }
-            }
-        }
+    #[test]
+    fn self_test() {
+        let mut router: Router<_, 1, 1> = Router::new(Eid(8), 0, NullSender);
+        let report = router.self_test(1000).unwrap();
+        assert_eq!(report.len, 1000);
+        assert_eq!(report.packets, 5);
+        assert_eq!(report.mtu, crate::test_util::NULL_SENDER_MTU);
 
-        fn get_mtu(&self) -> usize {
-            MTU
+        // Smaller MTUs take more packets, the tag is released after each run
+        router.set_mtu(Eid(8), 64).unwrap();
+        for _ in 0..10 {
+            assert_eq!(router.self_test(100).unwrap().packets, 2);
         }
+        assert!(router.self_test(0).is_ok());
+        assert!(matches!(
+            router.self_test(super::SELF_TEST_MAX_LEN + 1),
+            Err(mctp::Error::BadArgument)
+        ));
     }
 
-    /// Test the creation of request and listener handles (`AppCookies`)
     #[test]
-    fn test_handle_creation() {
-        const REQ_HANDLES: usize = 8;
-        const LISTENER_HANDLES: usize = 8;
-        let outbound = DoNothingSender;
-        let mut router: Router<_, REQ_HANDLES, LISTENER_HANDLES> =
-            Router::new(Eid(42), 0, outbound);
-
-        // create a new listener and expect the cookie value to be 0 (raw index of the underlying table)
-        let listener = router.listener(mctp::MsgType(0));
-        assert!(listener.is_ok());
-        assert!(listener.as_ref().is_ok_and(|x| x.0 == 0));
+    fn take_error() {
+        use crate::hooks::ExpiryReason;
+        use crate::{Disposition, HandleError, NoHooks, RouterConfig};
+        use mctp::{MsgIC, MsgType};
 
-        // create a new request
-        // we expect the value to be MAX_LISTENER_HANDLES (request table index 0 + offset)
-        let req = router.req(Eid(112));
-        assert!(req.is_ok());
-        assert!(req.as_ref().is_ok_and(|x| x.0 == LISTENER_HANDLES));
+        let config = RouterConfig::new(Eid(8)).request_timeout_millis(Some(50));
+        let mut router: Router<_, 1, 1> = Router::new_with_config(config, 0, NullSender, NoHooks);
+        let listener = router.listener(MsgType(1)).unwrap();
+        let req = router.req(Eid(9)).unwrap();
+        assert_eq!(router.take_error(req), None);
 
+        // A response timeout is latched once
         router
-            .unbind(listener.unwrap())
-            .expect("failed to unbind listener handle");
-        router
-            .unbind(req.unwrap())
-            .expect("failed to unbind request handle");
-        for listener in router.listeners {
-            assert!(listener.is_none());
+            .send(None, MsgType(1), None, MsgIC(false), req, &[1])
+            .unwrap();
+        router.update(50).unwrap();
+        assert_eq!(
+            router.take_error(req),
+            Some(HandleError::Expired(ExpiryReason::NoResponse))
+        );
+        assert_eq!(router.take_error(req), None);
+
+        // Requests growing beyond the maximum size abort their reassembly
+        router.set_listener_max_size(listener, Some(2)).unwrap();
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0xc8, 1, 0, 0, 0]),
+            Disposition::DroppedTooLarge
+        );
+        assert_eq!(
+            router.take_error(listener),
+            Some(HandleError::Dropped(Disposition::DroppedTooLarge))
+        );
+        assert_eq!(router.take_error(listener), None);
+    }
+
+    /// Responses sent with recv_with() take the regular transmit path
+    #[test]
+    fn recv_with() {
+        use crate::HandleError;
+        use crate::test_util::{BufferSender, transfer};
+        use mctp::{MsgIC, MsgType};
+
+        let to_responder = RefCell::new(std::vec::Vec::new());
+        let to_requester = RefCell::new(std::vec::Vec::new());
+        let mut requester: Router<_, 1, 1> =
+            Router::new(Eid(8), 0, BufferSender::<64>::new(&to_responder));
+        let mut responder: Router<_, 1, 1> =
+            Router::new(Eid(9), 0, BufferSender::<64>::new(&to_requester));
+        let listener = responder.listener(MsgType(1)).unwrap();
+        responder.add_local_eid(Eid(20)).unwrap();
+
+        // A request to a secondary EID is echoed from that EID, while it is borrowed
+        let payload = [7; 100];
+        let req = requester.req(Eid(20)).unwrap();
+        requester
+            .send(None, MsgType(1), None, MsgIC(false), req, &payload)
+            .unwrap();
+        transfer(&to_responder, &mut responder).unwrap();
+        responder
+            .recv_with(listener, |message, responder| {
+                assert_eq!(message.payload, &payload);
+                responder.respond(&[message.payload]).unwrap();
+                // Only a single response can be sent
+                assert!(responder.respond(&[message.payload]).is_err());
+            })
+            .unwrap()
+            .unwrap();
+        assert!(responder.recv_with(listener, |_, _| ()).unwrap().is_none());
+        assert!(to_requester.borrow().len() > 1);
+        assert!(to_requester.borrow().iter().all(|p| p.get(2) == Some(&20)));
+        transfer(&to_requester, &mut requester).unwrap();
+        assert_eq!(requester.recv(req).unwrap().payload, &payload);
+
+        // Responses are blocked while the link of the route is down, and latched as failed
+        responder.add_route(8..=8, 1, 0x10).unwrap();
+        responder.link_down(1);
+        requester
+            .send(None, MsgType(1), None, MsgIC(false), req, &[1])
+            .unwrap();
+        transfer(&to_responder, &mut responder).unwrap();
+        responder
+            .recv_with(listener, |message, responder| {
+                assert!(responder.respond(&[message.payload]).is_err());
+            })
+            .unwrap()
+            .unwrap();
+        assert!(to_requester.borrow().is_empty());
+        assert_eq!(
+            responder.take_error(listener),
+            Some(HandleError::SendFailed)
+        );
+    }
+
+    /// Learned MTUs are evicted oldest first and never keep static entries out
+    #[test]
+    fn mtu_table_eviction() {
+        use crate::MTU_TABLE_SIZE;
+
+        let mut router: Router<_, 1, 1> = Router::new(Eid(8), 0, NullSender);
+        for (eid, now) in (10..).zip(0..MTU_TABLE_SIZE as u64) {
+            router.update(now).unwrap();
+            router.learn_mtu(Eid(eid), 64);
         }
-        for request in router.requests {
-            assert!(request.is_none());
+        // The last free slot is kept, the oldest learned entry is replaced instead
+        let last = 10 + MTU_TABLE_SIZE as u8 - 1;
+        assert_eq!(router.mtu(Eid(10)), 255);
+        assert_eq!(router.mtu(Eid(last)), 64);
+
+        // Static entries take the free slot, then evict learned ones
+        for eid in 100..110 {
+            router.set_mtu(Eid(eid), 100).unwrap();
         }
+        assert_eq!(router.mtu(Eid(11)), 255);
+        assert_eq!(router.mtu(Eid(last)), 64);
+        assert!(router.mtu_overrides.iter().all(|x| x.is_some()));
+
+        // Refreshed entries are kept longest
+        router.update(100).unwrap();
+        router.learn_mtu(Eid(20), 80);
+        router.set_mtu(Eid(110), 100).unwrap();
+        assert_eq!(router.mtu(Eid(20)), 80);
+        assert_eq!(router.mtu(Eid(21)), 255);
     }
 
-    /// Create two routers, send a request from B to A and receive the echo response
+    /// Packets are bounded by the packet buffer of the sender, not only its MTU
     #[test]
-    fn roundtrip() {
-        const REQ_HANDLES: usize = 8;
-        const LISTENER_HANDLES: usize = 8;
-        let buf_out_a = RefCell::new(Vec::new());
-        let outbound_a: BufferSender<255> = BufferSender {
-            packets: &buf_out_a,
-        };
-        let mut router_a: Router<_, LISTENER_HANDLES, REQ_HANDLES> =
-            Router::new(Eid(42), 0, outbound_a);
+    fn sender_packet_buffer() {
+        use crate::Sender;
 
-        let buf_out_b = RefCell::new(Vec::new());
-        let outbound_b: BufferSender<255> = BufferSender {
-            packets: &buf_out_b,
-        };
-        let mut router_b: Router<_, LISTENER_HANDLES, REQ_HANDLES> =
-            Router::new(Eid(112), 0, outbound_b);
+        /// Reports a large MTU but builds packets in 32 byte buffers
+        struct SmallBufferSender<'a>(&'a RefCell<Vec<Vec<u8>>>);
 
-        // create a new listener and expect the cookie value to be 0 (raw index of the underlying table)
-        let listener = router_a.listener(mctp::MsgType(0)).unwrap();
+        impl Sender for SmallBufferSender<'_> {
+            type Packet = [u8; 32];
 
-        let requester = router_b.req(Eid(42)).unwrap();
+            fn send_packet(&mut self, _eid: Eid, pkt: &[u8]) -> mctp::Result<()> {
+                self.0.borrow_mut().push(pkt.into());
+                Ok(())
+            }
 
-        let payload = [1; 300]; // 300 byte payload to exceed 255 byte MTU
-        router_b
+            fn get_mtu(&self) -> usize {
+                255
+            }
+        }
+
+        let packets = RefCell::new(Vec::new());
+        let mut router: Router<_, 4, 4> = Router::new(Eid(8), 0, SmallBufferSender(&packets));
+        assert_eq!(router.mtu(Eid(9)), 32);
+        let req = router.req(Eid(9)).unwrap();
+        router
             .send(
                 None,
-                mctp::MsgType(0),
+                mctp::MsgType(1),
                 None,
                 mctp::MsgIC(false),
-                requester,
-                &payload,
+                req,
+                &[0; 100],
             )
             .unwrap();
+        let packets = packets.borrow();
+        assert_eq!(packets.len(), 4);
+        assert!(packets.iter().all(|p| p.len() <= 32));
+    }
 
-        let packets = buf_out_b.borrow();
-        for pkt in packets.as_slice() {
-            router_a.inbound(pkt).unwrap();
+    /// Failures outside the reassembly are not reported as reassembly errors
+    #[test]
+    fn disposition_failed() {
+        use crate::{Disposition, NoHooks, RouterConfig, Sender};
+
+        /// Fails to send every packet
+        struct FailingSender;
+
+        impl Sender for FailingSender {
+            type Packet = [u8; 64];
+
+            fn send_packet(&mut self, _eid: Eid, _pkt: &[u8]) -> mctp::Result<()> {
+                Err(mctp::Error::TxFailure)
+            }
+
+            fn get_mtu(&self) -> usize {
+                64
+            }
         }
 
-        let message = router_a.recv(listener).unwrap();
-        let (msg_source, msg_typ, msg_tag, msg_ic) =
-            (message.source, message.typ, message.tag, message.ic);
-        let msg_payload: Vec<_> = message.payload.into();
-        drop(message);
+        let config = RouterConfig::new(Eid(8)).dispatch_trace(true);
+        let mut router: Router<_, 1, 1> =
+            Router::new_with_config(config, 0, FailingSender, NoHooks);
+        router.set_forwarding(true);
 
+        let err = router.inbound(&[1, 20, 10, 0xc8, 1, 0xaa]).unwrap_err();
+        assert!(matches!(err, mctp::Error::TxFailure));
         assert_eq!(
-            &msg_payload, &payload,
-            "Received payload does not match send payload"
+            router.inbound_disposition(&[1, 20, 10, 0xc8, 1, 0xaa]),
+            Disposition::Failed
         );
-        assert!(
-            msg_tag.is_owner(),
-            "Received message is not a request (tag is unowned)"
+        assert_eq!(
+            router.inbound_disposition(&[1, 8]),
+            Disposition::DroppedReassemblyError
         );
+        let traced: Vec<_> = router.dispatch_trace().map(|e| e.disposition).collect();
+        assert_eq!(
+            traced,
+            [
+                Disposition::Failed,
+                Disposition::Failed,
+                Disposition::DroppedReassemblyError
+            ]
+        );
+        assert!(Disposition::Failed.is_dropped());
+        assert_eq!(router.peer_stats_for(Eid(10)).unwrap().errors_in, 2);
+    }
 
-        router_a
-            .send(
-                Some(msg_source),
-                msg_typ,
-                Some(super::Tag::Unowned(msg_tag.tag())),
-                msg_ic,
-                listener,
-                &msg_payload,
-            )
-            .unwrap();
+    /// `embassy::call()` releases its request on success, failure and timeout
+    #[cfg(feature = "embassy")]
+    #[test]
+    fn embassy_call() {
+        use crate::clock::EmbassyClock;
+        use crate::embassy::call;
+        use crate::test_util::transfer;
+        use crate::{NoHooks, RouterConfig, Sender};
+        use embassy_futures::{block_on, join::join};
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embassy_sync::mutex::Mutex;
+        use embassy_sync::watch::Watch;
+        use embassy_time::Timer;
+        use mctp::{MsgIC, MsgType};
 
-        let packets = buf_out_a.borrow();
-        for pkt in packets.as_slice() {
-            router_b.inbound(pkt).unwrap();
+        /// Fails to send every packet
+        struct FailingSender;
+
+        impl Sender for FailingSender {
+            type Packet = [u8; 64];
+
+            fn send_packet(&mut self, _eid: Eid, _pkt: &[u8]) -> mctp::Result<()> {
+                Err(mctp::Error::TxFailure)
+            }
+
+            fn get_mtu(&self) -> usize {
+                64
+            }
         }
 
-        let message = router_b.recv(requester).unwrap();
-        assert_eq!(
-            message.payload, &payload,
-            "Received payload does not match send payload"
+        let packets_a = RefCell::new(Vec::new());
+        let packets_b = RefCell::new(Vec::new());
+        let requester: Router<_, 1, 1, NoHooks, EmbassyClock> = Router::new_with_clock(
+            RouterConfig::new(Eid(8)),
+            EmbassyClock,
+            BufferSender::<64>::new(&packets_a),
+            NoHooks,
         );
-        assert!(
-            !message.tag.is_owner(),
-            "Received message is not a response (tag is unowned)"
+        let requester = Mutex::<NoopRawMutex, _>::new(requester);
+        let mut responder: Router<_, 1, 1> =
+            Router::new(Eid(9), 0, BufferSender::<64>::new(&packets_b));
+        let listener = responder.listener(MsgType(1)).unwrap();
+        let wake: Watch<NoopRawMutex, u64, 2> = Watch::new();
+
+        // a response that fits, then one larger than the buffer
+        for buf_len in [16, 4] {
+            let mut buf = [0; 16];
+            let respond = async {
+                while packets_a.borrow().is_empty() {
+                    Timer::after_millis(1).await;
+                }
+                transfer(&packets_a, &mut responder).unwrap();
+                let mut request = [0; 16];
+                let info = responder
+                    .recv_into(listener, &mut request.as_mut_slice())
+                    .unwrap()
+                    .unwrap();
+                responder.respond(listener, &info, &[&[4; 10]]).unwrap();
+                transfer(&packets_b, &mut *requester.lock().await).unwrap();
+                wake.sender().send(0);
+            };
+            let exchange = call(
+                &requester,
+                &wake,
+                Eid(9),
+                MsgType(1),
+                MsgIC(false),
+                &[1, 2, 3],
+                1000,
+                buf.get_mut(..buf_len).unwrap(),
+            );
+            let (result, ()) = block_on(join(exchange, respond));
+            if buf_len == 16 {
+                let info = result.unwrap();
+                assert_eq!(buf.get(..info.len), Some([4; 10].as_slice()));
+            } else {
+                assert!(matches!(
+                    result.unwrap_err().error(),
+                    mctp::Error::RxFailure
+                ));
+            }
+            let mut router = block_on(requester.lock());
+            let req = router.req(Eid(9)).unwrap();
+            router.unbind(req).unwrap();
+        }
+
+        // no response
+        let mut buf = [0; 16];
+        let result = block_on(call(
+            &requester,
+            &wake,
+            Eid(9),
+            MsgType(1),
+            MsgIC(false),
+            &[1],
+            10,
+            &mut buf,
+        ));
+        assert!(matches!(result.unwrap_err().error(), mctp::Error::TimedOut));
+        let mut router = block_on(requester.lock());
+        let req = router.req(Eid(9)).unwrap();
+        router.unbind(req).unwrap();
+        drop(router);
+
+        // failing to send the request
+        let failing: Router<_, 1, 1, NoHooks, EmbassyClock> = Router::new_with_clock(
+            RouterConfig::new(Eid(8)),
+            EmbassyClock,
+            FailingSender,
+            NoHooks,
+        );
+        let failing = Mutex::<NoopRawMutex, _>::new(failing);
+        let result = block_on(call(
+            &failing,
+            &wake,
+            Eid(9),
+            MsgType(1),
+            MsgIC(false),
+            &[1],
+            10,
+            &mut buf,
+        ));
+        assert!(matches!(
+            result.unwrap_err().error(),
+            mctp::Error::TxFailure
+        ));
+        assert!(block_on(failing.lock()).req(Eid(9)).is_ok());
+    }
+
+    /// Messages completed in a reassembly buffer are reported as ready
+    #[test]
+    fn reassembly_buffer_ready() {
+        use crate::Disposition;
+        use std::vec;
+
+        let mut router: Router<_, 2, 2> = Router::new(Eid(8), 0, NullSender);
+        let listener = router.listener(mctp::MsgType(1)).unwrap();
+        let other = router.listener(mctp::MsgType(2)).unwrap();
+        router
+            .set_reassembly_buffer(listener, Vec::leak(vec![0; 16]))
+            .unwrap();
+        assert_eq!(
+            router.inbound_disposition(&[1, 8, 9, 0xc8, 1, 0xaa]),
+            Disposition::Delivered(listener.into())
         );
+        assert_eq!(router.queued(listener), Some(1));
+        assert_eq!(router.queued(other), Some(0));
+        assert_eq!(router.poll_ready().collect::<Vec<_>>(), [listener.into()]);
+
+        #[cfg(feature = "embassy")]
+        {
+            use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+            use embassy_sync::mutex::Mutex;
+            use embassy_sync::watch::Watch;
+
+            let shared = Mutex::<NoopRawMutex, _>::new(router);
+            let wake: Watch<NoopRawMutex, u64, 1> = Watch::new();
+            let handles = [other.into(), listener.into()];
+            let ready = embassy_futures::block_on(crate::embassy::select(&shared, &wake, &handles));
+            assert_eq!(ready.unwrap(), listener.into());
+            router = shared.into_inner();
+        }
+
+        let mut buf = [0; 16];
+        let info = router
+            .recv_into(listener, &mut buf.as_mut_slice())
+            .unwrap()
+            .unwrap();
+        assert_eq!(buf.get(..info.len), Some([0xaa].as_slice()));
+        assert_eq!(router.queued(listener), Some(0));
+        assert_eq!(router.poll_ready().count(), 0);
     }
 }
@@ -0,0 +1,194 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Liveness monitoring of peer endpoints
+//!
+//! Monitored peers are probed with a control request every `interval_millis` from
+//! [update()](crate::GenericRouter::update) and [poll()](crate::GenericRouter::poll).
+//! A peer is reported down after `max_failures` probes in a row went unanswered for
+//! `timeout_millis`, and up on the first answered probe, see
+//! [Hooks::peer_state_changed()](crate::Hooks::peer_state_changed).
+
+use mctp::{Eid, Error, Result, TagValue};
+
+use crate::control::CMD_GET_ENDPOINT_ID;
+use crate::retry::RetryPolicy;
+
+/// Number of peers a [Router](crate::Router) can monitor
+pub const LIVENESS_TABLE_SIZE: usize = 8;
+
+/// Settings of the liveness monitoring, see
+/// [set_keep_alive()](crate::GenericRouter::set_keep_alive)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct KeepAlive {
+    pub(crate) interval_millis: u64,
+    pub(crate) timeout_millis: u64,
+    pub(crate) max_failures: u8,
+    #[cfg_attr(feature = "serde", serde(default = "default_command"))]
+    pub(crate) command: u8,
+}
+
+impl KeepAlive {
+    /// Probe every `interval_millis` with Get Endpoint ID
+    ///
+    /// A probe fails without a response within `timeout_millis`, peers are reported down
+    /// after `max_failures` failed probes in a row.
+    pub fn new(interval_millis: u64, timeout_millis: u64, max_failures: u8) -> Self {
+        KeepAlive {
+            interval_millis,
+            timeout_millis,
+            max_failures: max_failures.max(1),
+            command: CMD_GET_ENDPOINT_ID,
+        }
+    }
+
+    /// Probe with the control `command` instead, sent without request data
+    ///
+    /// Any response to the command counts as an answer, including failed completion codes.
+    pub fn probe(mut self, command: u8) -> Self {
+        self.command = command;
+        self
+    }
+}
+
+#[cfg(feature = "serde")]
+fn default_command() -> u8 {
+    CMD_GET_ENDPOINT_ID
+}
+
+/// Liveness of a monitored peer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PeerState {
+    /// No probe was answered or failed often enough yet
+    Unknown,
+    /// The last probe was answered
+    Up,
+    /// The last `max_failures` probes failed
+    Down,
+}
+
+/// A probe waiting for its response
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Probe {
+    pub(crate) tag: TagValue,
+    pub(crate) instance_id: u8,
+    pub(crate) deadline_millis: u64,
+}
+
+/// A monitored peer
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Peer {
+    pub(crate) eid: Eid,
+    pub(crate) state: PeerState,
+    failures: u8,
+    pub(crate) next_probe_millis: u64,
+    pub(crate) probe: Option<Probe>,
+}
+
+impl Peer {
+    /// Get the number of probes that failed in a row
+    pub(crate) fn failures(&self) -> u8 {
+        self.failures
+    }
+
+    /// Account for a failed probe
+    ///
+    /// Returns `true` if the peer went down.
+    pub(crate) fn failed(&mut self, max_failures: u8) -> bool {
+        self.probe = None;
+        self.failures = self.failures.saturating_add(1);
+        if self.failures >= max_failures && self.state != PeerState::Down {
+            self.state = PeerState::Down;
+            return true;
+        }
+        false
+    }
+
+    /// Account for an answered probe
+    ///
+    /// Returns `true` if the peer came up.
+    pub(crate) fn answered(&mut self) -> bool {
+        self.probe = None;
+        self.failures = 0;
+        if self.state != PeerState::Up {
+            self.state = PeerState::Up;
+            return true;
+        }
+        false
+    }
+}
+
+/// Monitored peers and the settings to probe them with
+#[derive(Debug)]
+pub(crate) struct Monitor {
+    pub(crate) keep_alive: Option<KeepAlive>,
+    /// Timeouts and failures of the probes, replacing the ones of `keep_alive`
+    pub(crate) policy: Option<&'static dyn RetryPolicy>,
+    pub(crate) peers: [Option<Peer>; LIVENESS_TABLE_SIZE],
+}
+
+impl Monitor {
+    pub(crate) const fn new(keep_alive: Option<KeepAlive>) -> Self {
+        Monitor {
+            keep_alive,
+            policy: None,
+            peers: [None; LIVENESS_TABLE_SIZE],
+        }
+    }
+
+    /// Start monitoring `eid`, probing it at `now_millis` first
+    ///
+    /// Returns [NoSpace](Error::NoSpace) if [LIVENESS_TABLE_SIZE] peers are monitored.
+    pub(crate) fn add(&mut self, eid: Eid, now_millis: u64) -> Result<()> {
+        if self.peer(eid).is_some() {
+            return Ok(());
+        }
+        let slot = self
+            .peers
+            .iter_mut()
+            .find(|p| p.is_none())
+            .ok_or(Error::NoSpace)?;
+        *slot = Some(Peer {
+            eid,
+            state: PeerState::Unknown,
+            failures: 0,
+            next_probe_millis: now_millis,
+            probe: None,
+        });
+        Ok(())
+    }
+
+    /// Stop monitoring `eid`, returning its last state
+    pub(crate) fn remove(&mut self, eid: Eid) -> Option<Peer> {
+        self.peers
+            .iter_mut()
+            .find(|p| p.is_some_and(|p| p.eid == eid))
+            .and_then(Option::take)
+    }
+
+    pub(crate) fn peer(&self, eid: Eid) -> Option<&Peer> {
+        self.peers.iter().flatten().find(|p| p.eid == eid)
+    }
+
+    /// Get the peer whose probe has been sent to `eid` with `tag`
+    pub(crate) fn probed(&mut self, eid: Eid, tag: TagValue) -> Option<&mut Peer> {
+        self.peers
+            .iter_mut()
+            .flatten()
+            .find(|p| p.eid == eid && p.probe.is_some_and(|probe| probe.tag == tag))
+    }
+}
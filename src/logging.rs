@@ -0,0 +1,86 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Logging macros forwarding to `log` or `defmt`, depending on the enabled features
+//!
+//! Without either feature, the arguments are evaluated but nothing is logged.
+//! Format strings have to be compatible with both backends, so stick to `{}` with
+//! primitive arguments.
+//!
+//! [lifecycle!] and [lifecycle_span!] report the lifecycle of messages (tags allocated,
+//! fragments sent, responses matched, expiries) to the `tracing` crate with structured
+//! fields, for hosted services feeding observability pipelines.
+//!
+//! Declared with `#[macro_use]` first in the crate, so the macros are available in all modules.
+
+macro_rules! trace {
+    ($s:literal $(, $x:expr)* $(,)?) => {{
+        #[cfg(feature = "log")]
+        ::log::trace!($s $(, $x)*);
+        #[cfg(feature = "defmt")]
+        ::defmt::trace!($s $(, $x)*);
+        #[cfg(not(any(feature = "log", feature = "defmt")))]
+        let _ = ($(&$x),*);
+    }};
+}
+
+macro_rules! debug {
+    ($s:literal $(, $x:expr)* $(,)?) => {{
+        #[cfg(feature = "log")]
+        ::log::debug!($s $(, $x)*);
+        #[cfg(feature = "defmt")]
+        ::defmt::debug!($s $(, $x)*);
+        #[cfg(not(any(feature = "log", feature = "defmt")))]
+        let _ = ($(&$x),*);
+    }};
+}
+
+macro_rules! warn {
+    ($s:literal $(, $x:expr)* $(,)?) => {{
+        #[cfg(feature = "log")]
+        ::log::warn!($s $(, $x)*);
+        #[cfg(feature = "defmt")]
+        ::defmt::warn!($s $(, $x)*);
+        #[cfg(not(any(feature = "log", feature = "defmt")))]
+        let _ = ($(&$x),*);
+    }};
+}
+
+/// Emit a message lifecycle event with `tracing`
+macro_rules! lifecycle {
+    ($name:literal $(, $field:ident = $x:expr)* $(,)?) => {{
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!(target: "mctp_lib", $($field = $x,)* $name);
+        #[cfg(not(feature = "tracing"))]
+        let _ = ($(&$x),*);
+    }};
+}
+
+/// Enter a `tracing` span, returning the guard that exits it when dropped
+macro_rules! lifecycle_span {
+    ($name:literal $(, $field:ident = $x:expr)* $(,)?) => {{
+        #[cfg(feature = "tracing")]
+        let span = ::tracing::debug_span!(target: "mctp_lib", $name $(, $field = $x)*).entered();
+        #[cfg(not(feature = "tracing"))]
+        let span = {
+            let _ = ($(&$x),*);
+            $crate::logging::NoSpan
+        };
+        span
+    }};
+}
+
+/// Stand-in for the span guard of [lifecycle_span!] without the `tracing` feature
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct NoSpan;
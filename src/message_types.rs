@@ -0,0 +1,107 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Message types reported by Get Message Type Support
+
+use mctp::{Error, MsgType, Result};
+
+use crate::control::MAX_MESSAGE_TYPES;
+
+/// Number of message types a [Router](crate::Router) reports in addition to its listeners
+pub const MESSAGE_TYPE_TABLE_SIZE: usize = 8;
+
+/// Message types registered explicitly, e.g. for types handled outside of listeners
+#[derive(Debug)]
+pub(crate) struct MessageTypeSet {
+    types: [Option<MsgType>; MESSAGE_TYPE_TABLE_SIZE],
+}
+
+impl MessageTypeSet {
+    pub(crate) const fn new() -> Self {
+        MessageTypeSet {
+            types: [None; MESSAGE_TYPE_TABLE_SIZE],
+        }
+    }
+
+    /// Register `typ`, unless it is registered already
+    ///
+    /// Returns [NoSpace](Error::NoSpace) if the table is full.
+    pub(crate) fn add(&mut self, typ: MsgType) -> Result<()> {
+        if self.types.iter().flatten().any(|t| t.0 == typ.0) {
+            return Ok(());
+        }
+        let slot = self
+            .types
+            .iter_mut()
+            .find(|t| t.is_none())
+            .ok_or(Error::NoSpace)?;
+        *slot = Some(typ);
+        Ok(())
+    }
+
+    /// Remove `typ`, returning whether it was registered
+    pub(crate) fn remove(&mut self, typ: MsgType) -> bool {
+        let Some(slot) = self
+            .types
+            .iter_mut()
+            .find(|t| t.is_some_and(|t| t.0 == typ.0))
+        else {
+            return false;
+        };
+        *slot = None;
+        true
+    }
+
+    /// Check whether `typ` is registered
+    pub(crate) fn contains(&self, typ: MsgType) -> bool {
+        self.types.iter().flatten().any(|t| t.0 == typ.0)
+    }
+
+    /// Check whether `typ` can be added, or is registered already
+    pub(crate) fn has_room(&self, typ: MsgType) -> bool {
+        self.contains(typ) || self.types.iter().any(|t| t.is_none())
+    }
+
+    /// Iterate over the registered types
+    pub(crate) fn iter(&self) -> impl Iterator<Item = MsgType> + '_ {
+        self.types.iter().flatten().copied()
+    }
+}
+
+/// Collect `types` in ascending order without duplicates into `out`
+///
+/// Returns the number of types, types beyond [MAX_MESSAGE_TYPES] are dropped.
+pub(crate) fn collect(
+    types: impl Iterator<Item = MsgType>,
+    out: &mut [MsgType; MAX_MESSAGE_TYPES],
+) -> usize {
+    let mut len = 0;
+    for typ in types {
+        let sorted = out.get(..len).unwrap_or_default();
+        let Err(pos) = sorted.binary_search_by_key(&typ.0, |t| t.0) else {
+            continue;
+        };
+        if len == MAX_MESSAGE_TYPES {
+            continue;
+        }
+        if let Some(tail) = out.get_mut(pos..=len) {
+            tail.rotate_right(1);
+        }
+        if let Some(slot) = out.get_mut(pos) {
+            *slot = typ;
+        }
+        len += 1;
+    }
+    len
+}
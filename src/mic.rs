@@ -0,0 +1,150 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Message integrity checks defined by protocols on top of MCTP
+//!
+//! Some protocols protect their messages with a message integrity check (MIC) of their own,
+//! e.g. NVMe-MI. A [MessageIntegrity] handler set for a message type with
+//! [set_message_integrity()](crate::GenericRouter::set_message_integrity) appends the MIC to
+//! every message of the type the router sends, and drops received messages whose MIC does
+//! not verify with [DroppedIntegrityError](crate::Disposition::DroppedIntegrityError).
+//!
+//! The MIC is part of the message body: it is covered by the MCTP integrity check, and
+//! received messages are delivered with the MIC at the end of their
+//! [body](crate::message_body).
+
+use core::fmt::Debug;
+
+use mctp::{Error, MsgIC, MsgType, Result};
+
+use crate::MAX_IC_BUFS;
+
+/// Number of message types with a [MessageIntegrity] handler a [Router](crate::Router) can hold
+pub const MIC_TABLE_SIZE: usize = 4;
+
+/// Longest MIC a [MessageIntegrity] handler can produce
+pub const MAX_MIC_LEN: usize = 16;
+
+/// Computes and verifies the MIC of the messages of a protocol
+///
+/// Handlers are shared by reference, `Sync` keeps routers holding them `Send`.
+pub trait MessageIntegrity: Debug + Sync {
+    /// Get the length of the MIC, at most [MAX_MIC_LEN]
+    fn mic_len(&self) -> usize;
+
+    /// Check if messages with the integrity check flag `ic` carry a MIC
+    ///
+    /// The default applies the MIC to all messages.
+    fn applies(&self, ic: MsgIC) -> bool {
+        let _ = ic;
+        true
+    }
+
+    /// Compute the MIC of a message of type `typ` with the body `bufs` into `mic`
+    ///
+    /// `mic` is [mic_len()](Self::mic_len) bytes long.
+    fn compute(&self, typ: MsgType, bufs: &[&[u8]], mic: &mut [u8]);
+
+    /// Verify the `mic` received at the end of a message of type `typ` with the body `body`
+    ///
+    /// The default compares `mic` with the one [compute()](Self::compute) produces.
+    fn verify(&self, typ: MsgType, body: &[u8], mic: &[u8]) -> bool {
+        let mut expected = [0; MAX_MIC_LEN];
+        let Some(expected) = expected.get_mut(..mic.len()) else {
+            return false;
+        };
+        self.compute(typ, &[body], expected);
+        expected == mic
+    }
+}
+
+/// [MessageIntegrity] handlers of message types
+#[derive(Debug)]
+pub(crate) struct MicHandlers {
+    entries: [Option<(MsgType, &'static dyn MessageIntegrity)>; MIC_TABLE_SIZE],
+}
+
+impl MicHandlers {
+    pub(crate) const fn new() -> Self {
+        MicHandlers {
+            entries: [None; MIC_TABLE_SIZE],
+        }
+    }
+
+    /// Set or remove the handler of `typ`
+    ///
+    /// Returns [BadArgument](Error::BadArgument) if the MIC of `handler` is longer than
+    /// [MAX_MIC_LEN], [NoSpace](Error::NoSpace) if the table is full.
+    pub(crate) fn set(
+        &mut self,
+        typ: MsgType,
+        handler: Option<&'static dyn MessageIntegrity>,
+    ) -> Result<()> {
+        let existing = self
+            .entries
+            .iter()
+            .position(|e| e.is_some_and(|(t, _)| t == typ));
+        let Some(handler) = handler else {
+            if let Some(slot) = existing.and_then(|i| self.entries.get_mut(i)) {
+                *slot = None;
+            }
+            return Ok(());
+        };
+        if handler.mic_len() > MAX_MIC_LEN {
+            return Err(Error::BadArgument);
+        }
+        let slot = existing
+            .or_else(|| self.entries.iter().position(|e| e.is_none()))
+            .and_then(|i| self.entries.get_mut(i))
+            .ok_or(Error::NoSpace)?;
+        *slot = Some((typ, handler));
+        Ok(())
+    }
+
+    /// Get the handler of `typ` if it applies to messages with the flag `ic`
+    pub(crate) fn get(&self, typ: MsgType, ic: MsgIC) -> Option<&'static dyn MessageIntegrity> {
+        self.entries
+            .iter()
+            .flatten()
+            .find(|(t, _)| *t == typ)
+            .map(|(_, h)| *h)
+            .filter(|h| h.applies(ic))
+    }
+}
+
+/// Collect `bufs` followed by their MIC, computed into `mic` by `handler`, in `parts`
+///
+/// Returns [BadArgument](Error::BadArgument) if there are more than [MAX_IC_BUFS] buffers,
+/// regardless of the IC bit of the message.
+pub(crate) fn append<'p, 'a>(
+    handler: &dyn MessageIntegrity,
+    typ: MsgType,
+    bufs: &[&'a [u8]],
+    mic: &'a mut [u8; MAX_MIC_LEN],
+    parts: &'p mut [&'a [u8]; MAX_IC_BUFS + 1],
+) -> Result<&'p [&'a [u8]]> {
+    let mic = mic
+        .get_mut(..handler.mic_len())
+        .ok_or(Error::InternalError)?;
+    handler.compute(typ, bufs, mic);
+    crate::append_check(bufs, mic, parts)
+}
+
+/// Verify the MIC at the end of `body`, a message of type `typ`
+pub(crate) fn verify(handler: &dyn MessageIntegrity, typ: MsgType, body: &[u8]) -> bool {
+    body.len()
+        .checked_sub(handler.mic_len())
+        .and_then(|len| body.split_at_checked(len))
+        .is_some_and(|(body, mic)| handler.verify(typ, body, mic))
+}
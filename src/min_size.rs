@@ -0,0 +1,129 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Handling of empty and undersized inbound messages
+//!
+//! Protocols define a minimum length for their messages, e.g. a header the body has to
+//! start with. Messages shorter than the minimum set for their type with
+//! [set_min_size()](crate::GenericRouter::set_min_size), and empty messages of other types
+//! (see [set_empty_policy()](crate::GenericRouter::set_empty_policy)), are handled by an
+//! [Undersized] policy before they reach a listener or request.
+//!
+//! The length is that of the message [body](crate::message_body), excluding the message
+//! type and the CRC-32C of the integrity check.
+
+use mctp::{Error, MsgType, Result};
+
+/// Number of message types with a [MinSize] a [Router](crate::Router) can hold
+pub const MIN_SIZE_TABLE_SIZE: usize = 4;
+
+/// Longest error response an [ErrorResponder] can produce
+pub const MAX_ERROR_RESPONSE_LEN: usize = 16;
+
+/// Build the error response to an undersized request
+///
+/// Gets the message type and body of the request, writes the body of the response to
+/// `response` and returns its length, or `None` to send no response.
+pub type ErrorResponder = fn(typ: MsgType, request: &[u8], response: &mut [u8]) -> Option<usize>;
+
+/// How a [Router](crate::Router) handles an undersized message
+#[derive(Debug, Clone, Copy)]
+pub enum Undersized {
+    /// Deliver the message like any other
+    Deliver,
+    /// Drop the message with [DroppedTooSmall](crate::Disposition::DroppedTooSmall)
+    Drop,
+    /// Drop the message, and answer requests with the response the function builds
+    ///
+    /// The response is sent with the message type and tag of the request, without integrity
+    /// check. Undersized responses are dropped.
+    Respond(ErrorResponder),
+}
+
+/// Minimum body length of the messages of a type, and the policy for shorter ones
+#[derive(Debug, Clone, Copy)]
+pub struct MinSize {
+    /// Minimum length of the message body
+    pub len: usize,
+    /// Handling of messages with a shorter body
+    pub policy: Undersized,
+}
+
+impl MinSize {
+    /// Handle messages with a body shorter than `len` according to `policy`
+    pub const fn new(len: usize, policy: Undersized) -> Self {
+        MinSize { len, policy }
+    }
+}
+
+/// Minimum sizes of message types, and the policy for empty messages of other types
+#[derive(Debug)]
+pub(crate) struct MinSizes {
+    entries: [Option<(MsgType, MinSize)>; MIN_SIZE_TABLE_SIZE],
+    empty: Undersized,
+    /// Undersized messages seen so far
+    count: usize,
+}
+
+impl MinSizes {
+    pub(crate) const fn new() -> Self {
+        MinSizes {
+            entries: [None; MIN_SIZE_TABLE_SIZE],
+            empty: Undersized::Deliver,
+            count: 0,
+        }
+    }
+
+    /// Set or remove the minimum size of `typ`
+    ///
+    /// Returns [NoSpace](Error::NoSpace) if the table is full.
+    pub(crate) fn set(&mut self, typ: MsgType, min: Option<MinSize>) -> Result<()> {
+        let existing = self
+            .entries
+            .iter()
+            .position(|e| e.is_some_and(|(t, _)| t == typ));
+        let Some(min) = min else {
+            if let Some(slot) = existing.and_then(|i| self.entries.get_mut(i)) {
+                *slot = None;
+            }
+            return Ok(());
+        };
+        let slot = existing
+            .or_else(|| self.entries.iter().position(|e| e.is_none()))
+            .and_then(|i| self.entries.get_mut(i))
+            .ok_or(Error::NoSpace)?;
+        *slot = Some((typ, min));
+        Ok(())
+    }
+
+    pub(crate) fn set_empty(&mut self, policy: Undersized) {
+        self.empty = policy;
+    }
+
+    /// Check a message of type `typ` with a body of `len` bytes
+    ///
+    /// Returns the policy to apply if the message is undersized, counting it.
+    pub(crate) fn check(&mut self, typ: MsgType, len: usize) -> Option<Undersized> {
+        let policy = match self.entries.iter().flatten().find(|(t, _)| *t == typ) {
+            Some((_, min)) => (len < min.len).then_some(min.policy),
+            None => (len == 0).then_some(self.empty),
+        }?;
+        self.count = self.count.wrapping_add(1);
+        Some(policy)
+    }
+
+    pub(crate) fn count(&self) -> usize {
+        self.count
+    }
+}
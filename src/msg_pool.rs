@@ -0,0 +1,91 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pools of owned message buffers
+
+use core::cell::{RefCell, RefMut};
+use core::ops::{Deref, DerefMut};
+
+use crate::MessageInfo;
+
+/// A pool of `N` buffers of `SIZE` bytes for [recv_pooled()](crate::GenericRouter::recv_pooled)
+///
+/// Usually placed in a `static` or on the stack of the task driving the router, so received
+/// messages can outlive the borrow of the router.
+#[derive(Debug)]
+pub struct MessagePool<const N: usize, const SIZE: usize> {
+    buffers: [RefCell<[u8; SIZE]>; N],
+}
+
+impl<const N: usize, const SIZE: usize> MessagePool<N, SIZE> {
+    /// Create a pool with all buffers free
+    pub const fn new() -> Self {
+        MessagePool {
+            buffers: [const { RefCell::new([0; SIZE]) }; N],
+        }
+    }
+
+    /// Number of buffers currently free
+    pub fn available(&self) -> usize {
+        self.buffers
+            .iter()
+            .filter(|b| b.try_borrow_mut().is_ok())
+            .count()
+    }
+
+    /// Take a free buffer
+    pub(crate) fn acquire(&self) -> Option<RefMut<'_, [u8; SIZE]>> {
+        self.buffers.iter().find_map(|b| b.try_borrow_mut().ok())
+    }
+}
+
+impl<const N: usize, const SIZE: usize> Default for MessagePool<N, SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A complete message in a buffer of a [MessagePool]
+///
+/// Dereferences to the payload. The buffer returns to the pool when the message is dropped.
+#[derive(Debug)]
+pub struct PooledMessage<'p, const SIZE: usize> {
+    info: MessageInfo,
+    buf: RefMut<'p, [u8; SIZE]>,
+}
+
+impl<'p, const SIZE: usize> PooledMessage<'p, SIZE> {
+    pub(crate) fn new(info: MessageInfo, buf: RefMut<'p, [u8; SIZE]>) -> Self {
+        PooledMessage { info, buf }
+    }
+
+    /// Get the metadata of the message
+    pub fn info(&self) -> &MessageInfo {
+        &self.info
+    }
+}
+
+impl<const SIZE: usize> Deref for PooledMessage<'_, SIZE> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.buf.get(..self.info.len).unwrap_or_default()
+    }
+}
+
+impl<const SIZE: usize> DerefMut for PooledMessage<'_, SIZE> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        self.buf.get_mut(..self.info.len).unwrap_or_default()
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Endpoints in more than one MCTP network
+//!
+//! A bridge device straddling networks has an endpoint in each of them. [Networks] holds one
+//! [GenericRouter] per network, each with its own EID, handle tables, MTUs, reassembly state
+//! and port. Nothing is shared between them, so EIDs may repeat across networks and traffic
+//! never crosses from one network into another.
+//!
+//! Networks are addressed by their index, or looked up by the network ID set with
+//! [RouterConfig::network_id()](crate::RouterConfig::network_id).
+//! Handles belong to the router of the network they were allocated on.
+
+use mctp::{Error, Result};
+
+use crate::{Clock, GenericRouter, Handle, HandleTables, Hooks, Sender};
+
+/// The routers of `N` MCTP networks
+#[derive(Debug)]
+pub struct Networks<S: Sender, T: HandleTables, H: Hooks, C: Clock, const N: usize> {
+    routers: [GenericRouter<S, T, H, C>; N],
+}
+
+impl<S: Sender, T: HandleTables, H: Hooks, C: Clock, const N: usize> Networks<S, T, H, C, N> {
+    /// Host an endpoint in each network through `routers`, indexed by position
+    pub fn new(routers: [GenericRouter<S, T, H, C>; N]) -> Self {
+        Networks { routers }
+    }
+
+    /// Get the routers back
+    pub fn into_inner(self) -> [GenericRouter<S, T, H, C>; N] {
+        self.routers
+    }
+
+    /// Get the router of network `net`
+    pub fn network(&self, net: usize) -> Option<&GenericRouter<S, T, H, C>> {
+        self.routers.get(net)
+    }
+
+    /// Get the router of network `net` mutably
+    pub fn network_mut(&mut self, net: usize) -> Option<&mut GenericRouter<S, T, H, C>> {
+        self.routers.get_mut(net)
+    }
+
+    /// Find the index of the network with `network_id`
+    pub fn find(&self, network_id: [u8; 16]) -> Option<usize> {
+        self.routers
+            .iter()
+            .position(|r| r.network_id() == Some(network_id))
+    }
+
+    /// Iterate over the network indices and their routers
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &GenericRouter<S, T, H, C>)> + '_ {
+        self.routers.iter().enumerate()
+    }
+
+    /// Provide a packet received on the port of network `net`, see [GenericRouter::inbound()]
+    ///
+    /// Returns [BadArgument](Error::BadArgument) for an unknown network.
+    pub fn inbound(&mut self, net: usize, pkt: &[u8]) -> Result<Option<Handle>> {
+        self.routers
+            .get_mut(net)
+            .ok_or(Error::BadArgument)?
+            .inbound(pkt)
+    }
+
+    /// Update the stacks of all networks, see [GenericRouter::poll()]
+    ///
+    /// Returns the shortest interval in which the next call should be issued.
+    pub fn poll(&mut self) -> Result<u64> {
+        let mut timeout = u64::MAX;
+        for router in self.routers.iter_mut() {
+            timeout = timeout.min(router.poll()?);
+        }
+        Ok(timeout)
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Padding of outbound packets to the frame sizes a port requires
+//!
+//! Some transports only carry frames of fixed sizes, e.g. PCIe VDM pads packets to a
+//! multiple of 4 bytes and some mailbox hardware only moves whole mailbox slots.
+//! The router pads the packets it sends on such ports, see
+//! [set_port_padding()](crate::GenericRouter::set_port_padding), and strips the padding of
+//! received packets reported by the binding, see
+//! [inbound_padded()](crate::GenericRouter::inbound_padded).
+
+use mctp::{Error, Result};
+
+use crate::Route;
+
+/// Number of ports a [Router](crate::Router) can pad packets for
+pub const PADDING_TABLE_SIZE: usize = 4;
+
+/// How the packets sent on a port are padded
+///
+/// Padding is appended after the payload of the packet, the padded packet is passed to
+/// [Sender::send_padded()](crate::Sender::send_padded) with the number of padding bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Padding {
+    pub(crate) min_len: usize,
+    pub(crate) align: usize,
+    pub(crate) fill: u8,
+}
+
+impl Padding {
+    /// Padding that leaves packets as they are, to be extended with the methods below
+    pub fn new() -> Self {
+        Padding {
+            min_len: 0,
+            align: 1,
+            fill: 0,
+        }
+    }
+
+    /// Pad packets shorter than `len` bytes to `len`, e.g. the MTU for fixed-size frames
+    pub fn min_len(mut self, len: usize) -> Self {
+        self.min_len = len;
+        self
+    }
+
+    /// Pad packets to a multiple of `align` bytes
+    pub fn align(mut self, align: usize) -> Self {
+        self.align = align.max(1);
+        self
+    }
+
+    /// Pad with `fill` instead of zeros
+    pub fn fill(mut self, fill: u8) -> Self {
+        self.fill = fill;
+        self
+    }
+
+    /// Get the length of a packet of `len` bytes after padding
+    pub fn padded_len(&self, len: usize) -> usize {
+        len.max(self.min_len).next_multiple_of(self.align)
+    }
+}
+
+impl Default for Padding {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The padding of each port
+#[derive(Debug, Default)]
+pub(crate) struct PortPadding {
+    ports: [Option<(u8, Padding)>; PADDING_TABLE_SIZE],
+}
+
+impl PortPadding {
+    pub(crate) const fn new() -> Self {
+        PortPadding {
+            ports: [None; PADDING_TABLE_SIZE],
+        }
+    }
+
+    /// Set or remove the padding of `port`
+    ///
+    /// Returns [BadArgument](Error::BadArgument) if the minimum length or alignment exceed
+    /// `max_len`, the largest packet, [NoSpace](Error::NoSpace) if the table is full.
+    pub(crate) fn set(&mut self, port: u8, padding: Option<Padding>, max_len: usize) -> Result<()> {
+        if padding.is_some_and(|p| p.min_len.max(p.align) > max_len) {
+            return Err(Error::BadArgument);
+        }
+        let existing = self
+            .ports
+            .iter_mut()
+            .find(|p| p.is_some_and(|p| p.0 == port));
+        match (existing, padding) {
+            (Some(slot), padding) => *slot = padding.map(|p| (port, p)),
+            (None, Some(padding)) => {
+                *self
+                    .ports
+                    .iter_mut()
+                    .find(|p| p.is_none())
+                    .ok_or(Error::NoSpace)? = Some((port, padding));
+            }
+            (None, None) => (),
+        }
+        Ok(())
+    }
+
+    pub(crate) fn get(&self, port: u8) -> Option<Padding> {
+        self.ports
+            .iter()
+            .flatten()
+            .find(|p| p.0 == port)
+            .map(|p| p.1)
+    }
+
+    /// Get the padding of packets along `route`, packets without a route go out on port 0
+    pub(crate) fn for_route(&self, route: Option<&Route>) -> Option<Padding> {
+        self.get(route.map_or(0, |r| r.port))
+    }
+}
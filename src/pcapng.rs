@@ -0,0 +1,172 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Packet capture in the [pcapng](https://www.ietf.org/archive/id/draft-ietf-opsawg-pcapng-03.html)
+//! format
+//!
+//! Packets are recorded with the `LINKTYPE_MCTP` link type, so captures can be inspected with
+//! Wireshark.
+
+use std::io::{self, Write};
+
+use crate::hooks::{Direction, Hooks};
+
+/// Link type for MCTP packets, starting with the MCTP transport header
+pub const LINKTYPE_MCTP: u16 = 291;
+
+const BLOCK_SHB: u32 = 0x0A0D_0D0A;
+const BLOCK_IDB: u32 = 0x0000_0001;
+const BLOCK_EPB: u32 = 0x0000_0006;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const OPT_END: u16 = 0;
+const OPT_EPB_FLAGS: u16 = 2;
+const EPB_FLAGS_INBOUND: u32 = 0b01;
+const EPB_FLAGS_OUTBOUND: u32 = 0b10;
+
+/// Writes captured packets to a pcapng stream
+///
+/// Implements [Hooks], so it can be passed to a [Router](crate::Router) directly.
+/// Errors while capturing can't be propagated through the router,
+/// the first one is kept and can be retrieved with [take_error()](Self::take_error).
+#[derive(Debug)]
+pub struct PcapngWriter<W: Write> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> PcapngWriter<W> {
+    /// Create a new writer and write the section and interface headers to `writer`
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        // Section Header Block without options, section length unspecified
+        let mut shb = Vec::with_capacity(28);
+        shb.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+        shb.extend_from_slice(&1u16.to_le_bytes());
+        shb.extend_from_slice(&0u16.to_le_bytes());
+        shb.extend_from_slice(&(-1i64).to_le_bytes());
+        write_block(&mut writer, BLOCK_SHB, &shb)?;
+
+        // Interface Description Block, default timestamp resolution (microseconds)
+        let mut idb = Vec::with_capacity(8);
+        idb.extend_from_slice(&LINKTYPE_MCTP.to_le_bytes());
+        idb.extend_from_slice(&0u16.to_le_bytes());
+        idb.extend_from_slice(&0u32.to_le_bytes());
+        write_block(&mut writer, BLOCK_IDB, &idb)?;
+
+        Ok(PcapngWriter {
+            writer,
+            error: None,
+        })
+    }
+
+    /// Write a single packet
+    ///
+    /// `now_millis` is recorded as the packet timestamp.
+    pub fn write_packet(
+        &mut self,
+        direction: Direction,
+        now_millis: u64,
+        pkt: &[u8],
+    ) -> io::Result<()> {
+        let len = u32::try_from(pkt.len()).map_err(|_| io::ErrorKind::InvalidInput)?;
+        let micros = now_millis.saturating_mul(1000);
+        let flags = match direction {
+            Direction::Inbound => EPB_FLAGS_INBOUND,
+            Direction::Outbound => EPB_FLAGS_OUTBOUND,
+        };
+
+        let mut epb = Vec::with_capacity(pkt.len() + 32);
+        epb.extend_from_slice(&0u32.to_le_bytes());
+        epb.extend_from_slice(&((micros >> 32) as u32).to_le_bytes());
+        epb.extend_from_slice(&(micros as u32).to_le_bytes());
+        epb.extend_from_slice(&len.to_le_bytes());
+        epb.extend_from_slice(&len.to_le_bytes());
+        epb.extend_from_slice(pkt);
+        pad(&mut epb);
+        epb.extend_from_slice(&OPT_EPB_FLAGS.to_le_bytes());
+        epb.extend_from_slice(&4u16.to_le_bytes());
+        epb.extend_from_slice(&flags.to_le_bytes());
+        epb.extend_from_slice(&OPT_END.to_le_bytes());
+        epb.extend_from_slice(&0u16.to_le_bytes());
+        write_block(&mut self.writer, BLOCK_EPB, &epb)
+    }
+
+    /// Take the first error that occurred while capturing through [Hooks]
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    /// Flush the underlying writer
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Consume the capture and return the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> Hooks for PcapngWriter<W> {
+    fn capture(&mut self, direction: Direction, now_millis: u64, pkt: &[u8]) {
+        if let Err(e) = self.write_packet(direction, now_millis, pkt) {
+            self.error.get_or_insert(e);
+        }
+    }
+}
+
+/// Pad `buf` with zeros to a multiple of 32 bit
+fn pad(buf: &mut Vec<u8>) {
+    while !buf.len().is_multiple_of(4) {
+        buf.push(0);
+    }
+}
+
+/// Write a block with type `typ` and `body` (already padded)
+fn write_block<W: Write>(writer: &mut W, typ: u32, body: &[u8]) -> io::Result<()> {
+    let len = body
+        .len()
+        .checked_add(12)
+        .and_then(|l| u32::try_from(l).ok())
+        .ok_or(io::ErrorKind::InvalidInput)?;
+    writer.write_all(&typ.to_le_bytes())?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(body)?;
+    writer.write_all(&len.to_le_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn block_layout() {
+        let mut capture = PcapngWriter::new(Vec::new()).unwrap();
+        capture.capture(Direction::Inbound, 1, &[1, 8, 9, 0xc8, 0]);
+        let out = capture.into_inner();
+
+        // SHB (28 bytes) + IDB (20 bytes)
+        assert_eq!(out.get(..4), Some(&BLOCK_SHB.to_le_bytes()[..]));
+        assert_eq!(out.get(28..32), Some(&BLOCK_IDB.to_le_bytes()[..]));
+        assert_eq!(out.get(36..38), Some(&LINKTYPE_MCTP.to_le_bytes()[..]));
+
+        // EPB: 12 header/trailer + 20 fixed + 8 padded data + 12 options
+        let epb = out.get(48..).unwrap();
+        assert_eq!(epb.len(), 52);
+        assert_eq!(epb.get(..4), Some(&BLOCK_EPB.to_le_bytes()[..]));
+        assert_eq!(epb.get(4..8), Some(&52u32.to_le_bytes()[..]));
+        assert_eq!(epb.get(16..20), Some(&1000u32.to_le_bytes()[..]));
+        assert_eq!(epb.get(28..33), Some(&[1, 8, 9, 0xc8, 0][..]));
+        assert_eq!(epb.get(48..52), Some(&52u32.to_le_bytes()[..]));
+    }
+}
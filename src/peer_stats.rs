@@ -0,0 +1,185 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Traffic counters per peer EID
+//!
+//! A [Router](crate::Router) counts the messages and bytes exchanged with each peer in a table
+//! of [PEER_STATS_TABLE_SIZE] entries. When the table is full, the peer without traffic for
+//! the longest time is evicted to make room for a new one.
+
+use mctp::Eid;
+
+use crate::Disposition;
+
+/// Number of peers a [Router](crate::Router) keeps statistics for
+pub const PEER_STATS_TABLE_SIZE: usize = 16;
+
+/// Counters of the traffic with a peer, see
+/// [peer_stats()](crate::GenericRouter::peer_stats)
+///
+/// Byte counts include the MCTP headers of all packets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerStats {
+    /// EID of the peer
+    pub eid: Eid,
+    /// Messages received from the peer and delivered to a listener or request
+    pub messages_in: usize,
+    /// Bytes of all packets received from the peer
+    pub bytes_in: usize,
+    /// Messages sent to the peer
+    pub messages_out: usize,
+    /// Bytes of the packets sent to the peer, including held ones
+    pub bytes_out: usize,
+    /// Packets received from the peer that were dropped
+    pub errors_in: usize,
+    /// Messages to the peer that failed to send
+    pub errors_out: usize,
+    /// Time the last packet from the peer arrived, `None` if nothing was received
+    pub last_seen_millis: Option<u64>,
+    /// Time of the last traffic in either direction, for eviction
+    last_active_millis: u64,
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for PeerStats {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "PeerStats {{ eid: {=u8}, messages_in: {=usize}, bytes_in: {=usize}, messages_out: {=usize}, bytes_out: {=usize}, errors_in: {=usize}, errors_out: {=usize}, last_seen_millis: {} }}",
+            self.eid.0,
+            self.messages_in,
+            self.bytes_in,
+            self.messages_out,
+            self.bytes_out,
+            self.errors_in,
+            self.errors_out,
+            self.last_seen_millis
+        );
+    }
+}
+
+impl PeerStats {
+    fn new(eid: Eid, now_millis: u64) -> Self {
+        PeerStats {
+            eid,
+            messages_in: 0,
+            bytes_in: 0,
+            messages_out: 0,
+            bytes_out: 0,
+            errors_in: 0,
+            errors_out: 0,
+            last_seen_millis: None,
+            last_active_millis: now_millis,
+        }
+    }
+}
+
+/// The peers with statistics
+#[derive(Debug)]
+pub(crate) struct PeerStatsTable {
+    peers: [Option<PeerStats>; PEER_STATS_TABLE_SIZE],
+    /// Peers evicted to make room for others
+    pub(crate) evicted: usize,
+}
+
+impl PeerStatsTable {
+    pub(crate) fn new() -> Self {
+        PeerStatsTable {
+            peers: [None; PEER_STATS_TABLE_SIZE],
+            evicted: 0,
+        }
+    }
+
+    /// Count a packet of `len` bytes received from `source`
+    ///
+    /// Dropped packets, including those that failed processing, count as errors.
+    pub(crate) fn received(
+        &mut self,
+        source: Eid,
+        len: usize,
+        disposition: Disposition,
+        now_millis: u64,
+    ) {
+        let Some(peer) = self.entry(source, now_millis) else {
+            return;
+        };
+        peer.bytes_in = peer.bytes_in.wrapping_add(len);
+        peer.last_seen_millis = Some(now_millis);
+        match disposition {
+            Disposition::Delivered(_) => {
+                peer.messages_in = peer.messages_in.wrapping_add(1);
+            }
+            d if d.is_dropped() => peer.errors_in = peer.errors_in.wrapping_add(1),
+            _ => (),
+        }
+    }
+
+    /// Count a message of `bytes` sent to `dest`, `None` if sending failed
+    pub(crate) fn sent(&mut self, dest: Eid, bytes: Option<usize>, now_millis: u64) {
+        let Some(peer) = self.entry(dest, now_millis) else {
+            return;
+        };
+        match bytes {
+            Some(bytes) => {
+                peer.messages_out = peer.messages_out.wrapping_add(1);
+                peer.bytes_out = peer.bytes_out.wrapping_add(bytes);
+            }
+            None => peer.errors_out = peer.errors_out.wrapping_add(1),
+        }
+    }
+
+    pub(crate) fn get(&self, eid: Eid) -> Option<PeerStats> {
+        self.iter().find(|p| p.eid == eid)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = PeerStats> + '_ {
+        self.peers.iter().flatten().copied()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.peers = [None; PEER_STATS_TABLE_SIZE];
+        self.evicted = 0;
+    }
+
+    /// Get the entry of `eid`, creating it if needed, and mark it active
+    fn entry(&mut self, eid: Eid, now_millis: u64) -> Option<&mut PeerStats> {
+        let index = match self
+            .peers
+            .iter()
+            .position(|p| p.is_some_and(|p| p.eid == eid))
+        {
+            Some(i) => i,
+            None => {
+                let i = match self.peers.iter().position(Option::is_none) {
+                    Some(i) => i,
+                    None => {
+                        self.evicted = self.evicted.wrapping_add(1);
+                        self.peers
+                            .iter()
+                            .enumerate()
+                            .min_by_key(|(_, p)| p.map(|p| p.last_active_millis))
+                            .map_or(0, |(i, _)| i)
+                    }
+                };
+                if let Some(slot) = self.peers.get_mut(i) {
+                    *slot = Some(PeerStats::new(eid, now_millis));
+                }
+                i
+            }
+        };
+        let peer = self.peers.get_mut(index)?.as_mut()?;
+        peer.last_active_millis = now_millis;
+        Some(peer)
+    }
+}
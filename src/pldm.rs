@@ -0,0 +1,215 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! PLDM over MCTP (DSP0241) transport
+//!
+//! PLDM messages are sent with MCTP message type [MSG_TYPE_PLDM]. Each one starts with a
+//! [PldmHeader] carrying the request bit, the instance ID correlating a response with its
+//! request, the PLDM type and the command code.
+//!
+//! [PldmRequester] and [PldmResponder] work on the channels of a
+//! [SharedRouter](crate::shared::SharedRouter). The payloads passed to and returned from
+//! them are complete PLDM messages, header included, so PLDM crates can parse them directly.
+
+use mctp::{Error, MsgIC, MsgType, Result};
+
+use crate::shared::{SharedListener, SharedRequest};
+use crate::{Clock, HandleTables, Hooks, MessageInfo, Sender};
+
+/// MCTP message type of PLDM messages
+pub const MSG_TYPE_PLDM: MsgType = MsgType(0x01);
+
+/// Length of the PLDM message header
+pub const PLDM_HEADER_LEN: usize = 3;
+
+/// Number of distinct instance IDs
+const INSTANCE_IDS: u8 = 32;
+
+const FLAG_REQUEST: u8 = 0x80;
+const FLAG_DATAGRAM: u8 = 0x40;
+const INSTANCE_MASK: u8 = 0x1f;
+const TYPE_MASK: u8 = 0x3f;
+
+/// The header of a PLDM message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PldmHeader {
+    /// Request (or datagram) rather than response
+    pub request: bool,
+    /// Unacknowledged request
+    pub datagram: bool,
+    /// Instance ID, 0 to 31
+    pub instance_id: u8,
+    /// PLDM type
+    pub pldm_type: u8,
+    /// Command code
+    pub command: u8,
+}
+
+impl PldmHeader {
+    /// Parse the header at the start of `msg`
+    ///
+    /// Returns `None` if `msg` is too short or uses an unsupported header version.
+    pub fn parse(msg: &[u8]) -> Option<Self> {
+        let &[flags, typ, command, ..] = msg else {
+            return None;
+        };
+        // Header version 0 is the only one defined
+        if typ & !TYPE_MASK != 0 {
+            return None;
+        }
+        Some(PldmHeader {
+            request: flags & FLAG_REQUEST != 0,
+            datagram: flags & FLAG_DATAGRAM != 0,
+            instance_id: flags & INSTANCE_MASK,
+            pldm_type: typ & TYPE_MASK,
+            command,
+        })
+    }
+
+    /// Encode the header
+    pub fn to_bytes(self) -> [u8; PLDM_HEADER_LEN] {
+        let mut flags = self.instance_id & INSTANCE_MASK;
+        if self.request {
+            flags |= FLAG_REQUEST;
+        }
+        if self.datagram {
+            flags |= FLAG_DATAGRAM;
+        }
+        [flags, self.pldm_type & TYPE_MASK, self.command]
+    }
+
+    /// Get the header of the response to this request
+    pub fn response(&self) -> Self {
+        PldmHeader {
+            request: false,
+            datagram: false,
+            ..*self
+        }
+    }
+}
+
+/// A PLDM requester talking to a single terminus
+///
+/// Only one request is outstanding at a time, responses with a different instance ID
+/// (e.g. late responses to an abandoned request) are discarded.
+#[derive(Debug)]
+pub struct PldmRequester<'r, S: Sender, T: HandleTables, H: Hooks, C: Clock> {
+    channel: SharedRequest<'r, S, T, H, C>,
+    next_instance: u8,
+    outstanding: Option<PldmHeader>,
+}
+
+impl<'r, S: Sender, T: HandleTables, H: Hooks, C: Clock> PldmRequester<'r, S, T, H, C> {
+    /// Use `channel` to talk to the terminus
+    pub fn new(channel: SharedRequest<'r, S, T, H, C>) -> Self {
+        PldmRequester {
+            channel,
+            next_instance: 0,
+            outstanding: None,
+        }
+    }
+
+    /// Send a request for `command` of `pldm_type`, with `payload` following the header
+    ///
+    /// Allocates the next instance ID, a previously outstanding request is abandoned.
+    /// Returns the header that was sent.
+    pub fn send(&mut self, pldm_type: u8, command: u8, payload: &[u8]) -> Result<PldmHeader> {
+        let header = PldmHeader {
+            request: true,
+            datagram: false,
+            instance_id: self.next_instance,
+            pldm_type,
+            command,
+        };
+        self.channel
+            .send(MSG_TYPE_PLDM, MsgIC(false), &[&header.to_bytes(), payload])?;
+        self.next_instance = (self.next_instance + 1) % INSTANCE_IDS;
+        self.outstanding = Some(header);
+        Ok(header)
+    }
+
+    /// Receive the response to the outstanding request into `buf` without blocking
+    ///
+    /// The PLDM message, header included, is stored in `buf[..len]`.
+    /// Returns the header and `len`, `Ok(None)` when no matching response is available.
+    pub fn receive(&mut self, buf: &mut [u8]) -> Result<Option<(PldmHeader, usize)>> {
+        let Some(outstanding) = self.outstanding else {
+            return Err(Error::BadArgument);
+        };
+        while let Some(info) = self.channel.try_recv(buf)? {
+            let header = buf.get(..info.len).and_then(PldmHeader::parse);
+            match header {
+                Some(header) if info.typ == MSG_TYPE_PLDM && header == outstanding.response() => {
+                    self.outstanding = None;
+                    return Ok(Some((header, info.len)));
+                }
+                _ => debug!("discarded unexpected pldm response from {}", info.source.0),
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A request received by a [PldmResponder]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PldmRequest {
+    /// Metadata of the MCTP message
+    pub info: MessageInfo,
+    /// Header of the request
+    pub header: PldmHeader,
+}
+
+/// A PLDM responder
+#[derive(Debug)]
+pub struct PldmResponder<'r, S: Sender, T: HandleTables, H: Hooks, C: Clock> {
+    listener: SharedListener<'r, S, T, H, C>,
+}
+
+impl<'r, S: Sender, T: HandleTables, H: Hooks, C: Clock> PldmResponder<'r, S, T, H, C> {
+    /// Receive requests on `listener`, bound for [MSG_TYPE_PLDM]
+    pub fn new(listener: SharedListener<'r, S, T, H, C>) -> Self {
+        PldmResponder { listener }
+    }
+
+    /// Receive a request into `buf` without blocking
+    ///
+    /// The PLDM message, header included, is stored in `buf[..request.info.len]`.
+    /// Messages that are not PLDM requests are discarded.
+    /// Returns `Ok(None)` when no request is available.
+    pub fn receive(&mut self, buf: &mut [u8]) -> Result<Option<PldmRequest>> {
+        while let Some(info) = self.listener.try_recv(buf)? {
+            match buf.get(..info.len).and_then(PldmHeader::parse) {
+                Some(header) if header.request => {
+                    return Ok(Some(PldmRequest { info, header }));
+                }
+                _ => debug!("discarded invalid pldm request from {}", info.source.0),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Respond to `request`, with `payload` (starting with the completion code) following the
+    /// header
+    ///
+    /// Returns [BadArgument](Error::BadArgument) for datagrams, which are not responded to.
+    pub fn respond(&mut self, request: &PldmRequest, payload: &[u8]) -> Result<()> {
+        if request.header.datagram {
+            return Err(Error::BadArgument);
+        }
+        let header = request.header.response().to_bytes();
+        self.listener
+            .respond(&request.info, MsgIC(false), &[&header, payload])?;
+        Ok(())
+    }
+}
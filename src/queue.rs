@@ -0,0 +1,572 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lock-free inbound packet queue
+//!
+//! A single producer (usually an interrupt handler of the transport) pushes raw packets into a
+//! [PacketQueue] without touching the [Router](crate::Router).
+//! A single consumer in task context drains them into
+//! [inbound()](crate::GenericRouter::inbound).
+//!
+//! A [SendQueue] works the same way for outbound messages: the producer only records the
+//! intent to send, fragmentation and transmission happen when the consumer flushes the queue.
+//! Until then the consumer can inspect the queued messages and cancel stale ones.
+//!
+//! Only atomic loads and stores are used, so the queues also work on targets without
+//! compare-and-swap instructions.
+
+use core::sync::atomic::{AtomicU8, AtomicU32, AtomicUsize, Ordering};
+
+use mctp::{Eid, Error, MsgIC, MsgType, Result, Tag, TagValue};
+use mctp_estack::AppCookie;
+
+use crate::{
+    Clock, GenericRouter, Handle, HandleTables, Hooks, ListenerHandle, RequestHandle, RouterResult,
+    SendReport, Sender,
+};
+
+/// Length of a slot cancelled by the consumer, skipped when popping
+const CANCELLED: usize = usize::MAX;
+
+/// A packet buffer of the queue
+#[derive(Debug)]
+struct QueueSlot<const MTU: usize> {
+    data: [AtomicU8; MTU],
+    len: AtomicUsize,
+    /// Time the packet was pushed, the low 32 bits of the milliseconds of a [Clock]
+    stamp: AtomicU32,
+}
+
+/// A single producer, single consumer queue of up to `N` packets of up to `MTU` bytes
+///
+/// Packets pushed while the queue is full or that are larger than `MTU` are dropped and
+/// counted, see [dropped()](Self::dropped).
+#[derive(Debug)]
+pub struct PacketQueue<const N: usize, const MTU: usize> {
+    slots: [QueueSlot<MTU>; N],
+    /// Number of packets popped, only written by the consumer
+    head: AtomicUsize,
+    /// Number of packets pushed, only written by the producer
+    tail: AtomicUsize,
+    /// Number of dropped packets, only written by the producer
+    dropped: AtomicUsize,
+}
+
+impl<const N: usize, const MTU: usize> PacketQueue<N, MTU> {
+    /// Create an empty queue
+    pub const fn new() -> Self {
+        PacketQueue {
+            slots: [const {
+                QueueSlot {
+                    data: [const { AtomicU8::new(0) }; MTU],
+                    len: AtomicUsize::new(0),
+                    stamp: AtomicU32::new(0),
+                }
+            }; N],
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            dropped: AtomicUsize::new(0),
+        }
+    }
+
+    /// Split the queue into its producer and consumer side
+    pub fn split(&mut self) -> (Producer<'_, N, MTU>, Consumer<'_, N, MTU>) {
+        (Producer { queue: self }, Consumer { queue: self })
+    }
+
+    /// Number of packets dropped by the producer since the queue was created
+    ///
+    /// Wraps around on overflow.
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Number of packets currently queued
+    pub fn len(&self) -> usize {
+        self.tail
+            .load(Ordering::Acquire)
+            .wrapping_sub(self.head.load(Ordering::Acquire))
+    }
+
+    /// Check if no packets are queued
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<const N: usize, const MTU: usize> Default for PacketQueue<N, MTU> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The producer side of a [PacketQueue]
+#[derive(Debug)]
+pub struct Producer<'q, const N: usize, const MTU: usize> {
+    queue: &'q PacketQueue<N, MTU>,
+}
+
+impl<const N: usize, const MTU: usize> Producer<'_, N, MTU> {
+    /// Push a packet
+    ///
+    /// Returns `false` if the packet was dropped because the queue is full
+    /// or the packet is larger than `MTU`.
+    pub fn push(&mut self, pkt: &[u8]) -> bool {
+        self.push_vectored(&[pkt])
+    }
+
+    /// Push a packet made up of the concatenated `parts`
+    ///
+    /// See [push()](Self::push).
+    pub fn push_vectored(&mut self, parts: &[&[u8]]) -> bool {
+        self.push_stamped(parts, 0).is_some()
+    }
+
+    /// Push a packet with the time `stamp`
+    ///
+    /// Returns the sequence number of the packet, or `None` if it was dropped.
+    fn push_stamped(&mut self, parts: &[&[u8]], stamp: u32) -> Option<usize> {
+        let q = self.queue;
+        let tail = q.tail.load(Ordering::Relaxed);
+        let head = q.head.load(Ordering::Acquire);
+        let slot = q.slots.get(tail % N.max(1));
+        let len = parts.iter().map(|p| p.len()).sum();
+        match slot {
+            Some(slot) if tail.wrapping_sub(head) < N && len <= MTU => {
+                for (dst, src) in slot.data.iter().zip(parts.iter().copied().flatten()) {
+                    dst.store(*src, Ordering::Relaxed);
+                }
+                slot.len.store(len, Ordering::Relaxed);
+                slot.stamp.store(stamp, Ordering::Relaxed);
+                q.tail.store(tail.wrapping_add(1), Ordering::Release);
+                Some(tail)
+            }
+            _ => {
+                // Only the producer writes the counter, no read-modify-write needed.
+                let dropped = q.dropped.load(Ordering::Relaxed);
+                q.dropped.store(dropped.wrapping_add(1), Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Number of packets dropped since the queue was created
+    pub fn dropped(&self) -> usize {
+        self.queue.dropped()
+    }
+}
+
+/// The consumer side of a [PacketQueue]
+#[derive(Debug)]
+pub struct Consumer<'q, const N: usize, const MTU: usize> {
+    queue: &'q PacketQueue<N, MTU>,
+}
+
+impl<const N: usize, const MTU: usize> Consumer<'_, N, MTU> {
+    /// Pop the oldest packet into `buf`
+    ///
+    /// Returns the length of the packet, or `None` if the queue is empty.
+    /// The packet is truncated if `buf` is too small.
+    pub fn pop(&mut self, buf: &mut [u8]) -> Option<usize> {
+        let q = self.queue;
+        loop {
+            let head = q.head.load(Ordering::Relaxed);
+            if head == q.tail.load(Ordering::Acquire) {
+                return None;
+            }
+            let slot = q.slots.get(head % N.max(1))?;
+            let len = slot.len.load(Ordering::Relaxed);
+            let copied = len.min(buf.len());
+            for (dst, src) in buf.iter_mut().zip(&slot.data).take(copied) {
+                *dst = src.load(Ordering::Relaxed);
+            }
+            q.head.store(head.wrapping_add(1), Ordering::Release);
+            if len != CANCELLED {
+                return Some(copied);
+            }
+        }
+    }
+
+    /// Iterate over the queued packets, oldest first, with their sequence numbers
+    ///
+    /// Cancelled packets are skipped.
+    fn queued(&self) -> impl Iterator<Item = (usize, &QueueSlot<MTU>)> {
+        let q = self.queue;
+        let head = q.head.load(Ordering::Relaxed);
+        let count = q.tail.load(Ordering::Acquire).wrapping_sub(head);
+        (0..count)
+            .map(move |i| head.wrapping_add(i))
+            .filter_map(|seq| Some((seq, q.slots.get(seq % N.max(1))?)))
+            .filter(|(_, slot)| slot.len.load(Ordering::Relaxed) != CANCELLED)
+    }
+
+    /// Cancel the queued packet with sequence number `seq`
+    ///
+    /// Returns `false` if it is no longer queued.
+    fn cancel(&mut self, seq: usize) -> bool {
+        match self.queued().find(|(s, _)| *s == seq) {
+            Some((_, slot)) => {
+                // Slots between head and tail are only written by the consumer.
+                slot.len.store(CANCELLED, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Pop all queued packets and pass each to `f`
+    ///
+    /// E.g. `consumer.drain(|pkt| { router.inbound_disposition(pkt); })`.
+    /// Returns the number of packets drained.
+    pub fn drain(&mut self, mut f: impl FnMut(&[u8])) -> usize {
+        let mut buf = [0; MTU];
+        let mut count = 0;
+        while let Some(len) = self.pop(&mut buf) {
+            f(buf.get(..len).unwrap_or_default());
+            count += 1;
+        }
+        count
+    }
+
+    /// Number of packets dropped by the producer since the queue was created
+    pub fn dropped(&self) -> usize {
+        self.queue.dropped()
+    }
+}
+
+/// Length of the send parameters stored in front of the payload in a [SendQueue] slot
+pub const SEND_INTENT_HEADER_LEN: usize = 11;
+
+/// A single producer, single consumer queue of up to `N` messages to send
+///
+/// Each slot holds [SEND_INTENT_HEADER_LEN] bytes of send parameters and the payload,
+/// so messages can be up to `SLOT - SEND_INTENT_HEADER_LEN` bytes long.
+#[derive(Debug)]
+pub struct SendQueue<const N: usize, const SLOT: usize> {
+    queue: PacketQueue<N, SLOT>,
+}
+
+impl<const N: usize, const SLOT: usize> SendQueue<N, SLOT> {
+    /// Create an empty queue
+    pub const fn new() -> Self {
+        SendQueue {
+            queue: PacketQueue::new(),
+        }
+    }
+
+    /// Split the queue into its producer and consumer side
+    pub fn split(&mut self) -> (SendProducer<'_, N, SLOT>, SendConsumer<'_, N, SLOT>) {
+        let (producer, consumer) = self.queue.split();
+        (
+            SendProducer {
+                producer,
+                clock: None,
+            },
+            SendConsumer {
+                consumer,
+                clock: None,
+            },
+        )
+    }
+
+    /// Split the queue, recording the time messages are queued with `clock`
+    ///
+    /// The consumer reports the age of queued messages, see [SendConsumer::pending()].
+    /// The clock is read by the producer, so it has to be usable from its context.
+    pub fn split_with_clock<'q>(
+        &'q mut self,
+        clock: &'q (dyn Clock + Sync),
+    ) -> (SendProducer<'q, N, SLOT>, SendConsumer<'q, N, SLOT>) {
+        let (producer, consumer) = self.split();
+        (
+            SendProducer {
+                clock: Some(clock),
+                ..producer
+            },
+            SendConsumer {
+                clock: Some(clock),
+                ..consumer
+            },
+        )
+    }
+
+    /// Number of messages rejected by [SendProducer::try_send()]
+    pub fn dropped(&self) -> usize {
+        self.queue.dropped()
+    }
+}
+
+impl<const N: usize, const SLOT: usize> Default for SendQueue<N, SLOT> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Identifies a message queued with [SendProducer::try_send()], see [SendConsumer::cancel()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SendTicket(usize);
+
+/// A message waiting in a [SendQueue], see [SendConsumer::pending()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueuedSend {
+    /// Ticket returned when the message was queued
+    pub ticket: SendTicket,
+    /// Destination EID, `None` for the peer of the request handle
+    pub eid: Option<Eid>,
+    /// Message type
+    pub typ: MsgType,
+    /// Handle the message is sent for
+    pub handle: Handle,
+    /// Payload length in bytes
+    pub len: usize,
+    /// Time since the message was queued, `None` without a clock, see
+    /// [split_with_clock()](SendQueue::split_with_clock)
+    pub age_millis: Option<u64>,
+}
+
+/// Send parameters stored in front of the payload
+struct SendIntent {
+    eid: Option<Eid>,
+    typ: MsgType,
+    tag: Option<Tag>,
+    ic: MsgIC,
+    handle: Handle,
+}
+
+impl SendIntent {
+    fn decode(header: &[u8; SEND_INTENT_HEADER_LEN]) -> Self {
+        let [
+            has_eid,
+            eid,
+            typ,
+            ic,
+            tag_kind,
+            tag_value,
+            kind,
+            c0,
+            c1,
+            c2,
+            c3,
+        ] = *header;
+        let tag = match tag_kind {
+            1 => Some(Tag::Owned(TagValue(tag_value))),
+            2 => Some(Tag::Unowned(TagValue(tag_value))),
+            _ => None,
+        };
+        let cookie = AppCookie(u32::from_le_bytes([c0, c1, c2, c3]) as usize);
+        SendIntent {
+            eid: (has_eid != 0).then_some(Eid(eid)),
+            typ: MsgType(typ),
+            tag,
+            ic: MsgIC(ic != 0),
+            handle: if kind == 0 {
+                Handle::Listener(ListenerHandle(cookie))
+            } else {
+                Handle::Request(RequestHandle(cookie))
+            },
+        }
+    }
+}
+
+/// The producer side of a [SendQueue], usable from interrupt context
+pub struct SendProducer<'q, const N: usize, const SLOT: usize> {
+    producer: Producer<'q, N, SLOT>,
+    clock: Option<&'q (dyn Clock + Sync)>,
+}
+
+impl<const N: usize, const SLOT: usize> core::fmt::Debug for SendProducer<'_, N, SLOT> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SendProducer")
+            .field("producer", &self.producer)
+            .field("clock", &self.clock.is_some())
+            .finish()
+    }
+}
+
+impl<const N: usize, const SLOT: usize> SendProducer<'_, N, SLOT> {
+    /// Enqueue a message without blocking
+    ///
+    /// The parameters are the same as for [GenericRouter::send()],
+    /// the payload is copied into the queue.
+    /// Returns a ticket to [cancel](SendConsumer::cancel) the message while it is queued,
+    /// [NoSpace](Error::NoSpace) if the queue is full or the message too large.
+    pub fn try_send(
+        &mut self,
+        eid: Option<Eid>,
+        typ: MsgType,
+        tag: Option<Tag>,
+        ic: MsgIC,
+        handle: impl Into<Handle>,
+        buf: &[u8],
+    ) -> Result<SendTicket> {
+        let (kind, cookie) = match handle.into() {
+            Handle::Listener(h) => (0, h.cookie()),
+            Handle::Request(h) => (1, h.cookie()),
+        };
+        let cookie = u32::try_from(cookie.0).map_err(|_| Error::BadArgument)?;
+        let (tag_kind, tag_value) = match tag {
+            None => (0, 0),
+            Some(Tag::Owned(v)) => (1, v.0),
+            Some(Tag::Unowned(v)) => (2, v.0),
+        };
+        let [c0, c1, c2, c3] = cookie.to_le_bytes();
+        let header: [u8; SEND_INTENT_HEADER_LEN] = [
+            eid.is_some() as u8,
+            eid.map_or(0, |e| e.0),
+            typ.0,
+            ic.0 as u8,
+            tag_kind,
+            tag_value,
+            kind,
+            c0,
+            c1,
+            c2,
+            c3,
+        ];
+        // Ages are computed from the low bits, wrapping after 49 days
+        let stamp = self.clock.map_or(0, |c| c.now_millis() as u32);
+        self.producer
+            .push_stamped(&[&header, buf], stamp)
+            .map(SendTicket)
+            .ok_or(Error::NoSpace)
+    }
+}
+
+/// The consumer side of a [SendQueue], flushed from task context
+pub struct SendConsumer<'q, const N: usize, const SLOT: usize> {
+    consumer: Consumer<'q, N, SLOT>,
+    clock: Option<&'q (dyn Clock + Sync)>,
+}
+
+impl<const N: usize, const SLOT: usize> core::fmt::Debug for SendConsumer<'_, N, SLOT> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SendConsumer")
+            .field("consumer", &self.consumer)
+            .field("clock", &self.clock.is_some())
+            .finish()
+    }
+}
+
+impl<const N: usize, const SLOT: usize> SendConsumer<'_, N, SLOT> {
+    /// Send the oldest queued message through `router`
+    ///
+    /// Returns `None` if the queue is empty, otherwise the result of the send operation,
+    /// reported as [queued](SendReport::queued).
+    pub fn send_next<S: Sender, T: HandleTables, H: Hooks, C: Clock>(
+        &mut self,
+        router: &mut GenericRouter<S, T, H, C>,
+    ) -> Option<RouterResult<SendReport>> {
+        let mut buf = [0; SLOT];
+        let len = self.consumer.pop(&mut buf)?;
+        let Some((header, payload)) = buf
+            .get(..len)
+            .and_then(|b| b.split_first_chunk::<SEND_INTENT_HEADER_LEN>())
+        else {
+            return Some(Err(Error::InternalError.into()));
+        };
+        let intent = SendIntent::decode(header);
+        let sent = router.send(
+            intent.eid,
+            intent.typ,
+            intent.tag,
+            intent.ic,
+            intent.handle,
+            payload,
+        );
+        Some(sent.map(|report| SendReport {
+            queued: true,
+            ..report
+        }))
+    }
+
+    /// Send all queued messages through `router`
+    ///
+    /// Messages that fail to send are dropped.
+    /// Returns the number of messages sent successfully.
+    pub fn flush<S: Sender, T: HandleTables, H: Hooks, C: Clock>(
+        &mut self,
+        router: &mut GenericRouter<S, T, H, C>,
+    ) -> usize {
+        let mut sent = 0;
+        while let Some(result) = self.send_next(router) {
+            match result {
+                Ok(_) => sent += 1,
+                Err(_) => warn!("failed to send queued message"),
+            }
+        }
+        sent
+    }
+
+    /// Iterate over the queued messages, oldest first
+    ///
+    /// Messages queued by the producer while iterating may be included.
+    pub fn pending(&self) -> impl Iterator<Item = QueuedSend> + '_ {
+        let now = self.clock.map(|c| c.now_millis() as u32);
+        self.consumer.queued().map(move |(seq, slot)| {
+            let mut header = [0; SEND_INTENT_HEADER_LEN];
+            for (dst, src) in header.iter_mut().zip(&slot.data) {
+                *dst = src.load(Ordering::Relaxed);
+            }
+            let intent = SendIntent::decode(&header);
+            let stamp = slot.stamp.load(Ordering::Relaxed);
+            QueuedSend {
+                ticket: SendTicket(seq),
+                eid: intent.eid,
+                typ: intent.typ,
+                handle: intent.handle,
+                len: slot
+                    .len
+                    .load(Ordering::Relaxed)
+                    .saturating_sub(SEND_INTENT_HEADER_LEN),
+                age_millis: now.map(|now| u64::from(now.wrapping_sub(stamp))),
+            }
+        })
+    }
+
+    /// Cancel the message queued with `ticket` before it is sent
+    ///
+    /// E.g. to shed messages for a peer that went away, see [pending()](Self::pending).
+    /// Returns `false` if the message is no longer queued.
+    pub fn cancel(&mut self, ticket: SendTicket) -> bool {
+        self.consumer.cancel(ticket.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PacketQueue;
+
+    /// Packets are drained in order, overflow is counted
+    #[test]
+    fn overflow() {
+        let mut queue: PacketQueue<2, 8> = PacketQueue::new();
+        let (mut producer, mut consumer) = queue.split();
+        assert!(producer.push(&[1]));
+        assert!(producer.push(&[2, 2]));
+        assert!(!producer.push(&[3]));
+        assert!(!producer.push(&[0; 9]));
+        assert_eq!(producer.dropped(), 2);
+
+        let mut drained = Vec::new();
+        assert_eq!(consumer.drain(|pkt| drained.push(pkt.to_vec())), 2);
+        assert_eq!(drained, vec![vec![1], vec![2, 2]]);
+
+        assert!(producer.push(&[4, 4, 4]));
+        let mut buf = [0; 8];
+        assert_eq!(consumer.pop(&mut buf), Some(3));
+        assert_eq!(consumer.pop(&mut buf), None);
+        assert_eq!(consumer.dropped(), 2);
+    }
+}
@@ -0,0 +1,132 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Per-source inbound rate limiting
+//!
+//! Every source EID gets a token bucket that holds up to `burst` packets and is refilled
+//! with `packets_per_sec`. Inbound packets without a token are dropped before reassembly.
+
+use mctp::Eid;
+
+/// Number of source EIDs tracked by the rate limiter of a [Router](crate::Router)
+///
+/// When all entries are in use, the bucket with the most tokens is reused for a new source.
+pub const RATE_LIMIT_TABLE_SIZE: usize = 16;
+
+/// Tokens are tracked in thousandths, so refills per millisecond stay integral
+const TOKEN_SCALE: u64 = 1000;
+
+/// Limit for inbound packets per source EID
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RateLimit {
+    burst: u32,
+    packets_per_sec: u32,
+}
+
+impl RateLimit {
+    /// Allow bursts of `burst` packets and `packets_per_sec` packets on average
+    pub fn new(burst: u32, packets_per_sec: u32) -> Self {
+        RateLimit {
+            burst,
+            packets_per_sec,
+        }
+    }
+
+    fn capacity(&self) -> u64 {
+        u64::from(self.burst) * TOKEN_SCALE
+    }
+
+    /// Check if the limit never admits a packet or never refills
+    pub(crate) fn is_stalled(&self) -> bool {
+        self.burst == 0 || self.packets_per_sec == 0
+    }
+}
+
+/// Token bucket of a single source
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    eid: Eid,
+    /// Available tokens, scaled by [TOKEN_SCALE]
+    tokens: u64,
+    /// Time of the last refill
+    refill_millis: u64,
+}
+
+impl Bucket {
+    fn refill(&mut self, limit: &RateLimit, now_millis: u64) {
+        let elapsed = now_millis.saturating_sub(self.refill_millis);
+        let added = elapsed.saturating_mul(u64::from(limit.packets_per_sec));
+        self.tokens = self.tokens.saturating_add(added).min(limit.capacity());
+        self.refill_millis = now_millis;
+    }
+}
+
+/// Token buckets for the tracked sources
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    limit: RateLimit,
+    buckets: [Option<Bucket>; RATE_LIMIT_TABLE_SIZE],
+    /// Number of packets dropped
+    pub(crate) dropped: usize,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(limit: RateLimit) -> Self {
+        RateLimiter {
+            limit,
+            buckets: [None; RATE_LIMIT_TABLE_SIZE],
+            dropped: 0,
+        }
+    }
+
+    /// Take a token for a packet from `eid`
+    ///
+    /// Returns `false` and counts the drop if the bucket of `eid` is empty.
+    pub(crate) fn admit(&mut self, eid: Eid, now_millis: u64) -> bool {
+        let limit = self.limit;
+        for bucket in self.buckets.iter_mut().flatten() {
+            bucket.refill(&limit, now_millis);
+        }
+        let index = self
+            .buckets
+            .iter()
+            .position(|b| b.is_some_and(|b| b.eid == eid))
+            .or_else(|| self.buckets.iter().position(|b| b.is_none()))
+            .or_else(|| {
+                // Reuse the bucket of the quietest source.
+                (0..RATE_LIMIT_TABLE_SIZE)
+                    .max_by_key(|&i| self.buckets.get(i).copied().flatten().map(|b| b.tokens))
+            });
+        let Some(slot) = index.and_then(|i| self.buckets.get_mut(i)) else {
+            return true;
+        };
+        let bucket = match slot {
+            Some(bucket) if bucket.eid == eid => bucket,
+            _ => slot.insert(Bucket {
+                eid,
+                tokens: limit.capacity(),
+                refill_millis: now_millis,
+            }),
+        };
+        if bucket.tokens >= TOKEN_SCALE {
+            bucket.tokens -= TOKEN_SCALE;
+            true
+        } else {
+            self.dropped = self.dropped.wrapping_add(1);
+            false
+        }
+    }
+}
@@ -0,0 +1,95 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bounds on the messages retained for a listener or request
+//!
+//! Completed messages stay in the reassembly buffers of the stack until they are received.
+//! Without a bound, a single busy handle can occupy all of them.
+
+/// What happens to a message delivered to a handle whose receive queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OverflowPolicy {
+    /// Drop the new message
+    DropNewest,
+    /// Drop the oldest queued message to make room for the new one
+    DropOldest,
+    /// Drop the new message and fail [inbound()](crate::Router::inbound) with
+    /// [NoSpace](mctp::Error::NoSpace), so the transport can push back
+    Reject,
+}
+
+/// Limit of the receive queue of a listener or request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct QueueLimit {
+    /// Maximum number of queued messages
+    pub depth: usize,
+    /// Handling of messages beyond `depth`
+    pub policy: OverflowPolicy,
+}
+
+/// Result of delivering a message to a [RecvQueue]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Admission {
+    /// The message is queued
+    Queued,
+    /// The message is queued, the oldest queued message has to be dropped
+    Evict,
+    /// The message has to be dropped
+    Full(OverflowPolicy),
+}
+
+/// Number of messages retained for a handle
+#[derive(Debug, Default)]
+pub(crate) struct RecvQueue {
+    pub(crate) limit: Option<QueueLimit>,
+    /// Messages retained in the stack and not received yet
+    pub(crate) queued: usize,
+    /// Stamp of the message received last, see [Scheduler](crate::scheduling::Scheduler)
+    pub(crate) served: u64,
+}
+
+impl RecvQueue {
+    /// Account for a newly delivered message
+    pub(crate) fn admit(&mut self) -> Admission {
+        match self.limit {
+            Some(limit) if self.queued >= limit.depth => match limit.policy {
+                OverflowPolicy::DropOldest if limit.depth > 0 => Admission::Evict,
+                policy => Admission::Full(policy),
+            },
+            _ => {
+                self.queued += 1;
+                Admission::Queued
+            }
+        }
+    }
+
+    /// Account for a message put back after it was taken
+    pub(crate) fn requeue(&mut self) {
+        self.queued += 1;
+    }
+
+    /// Account for a received message, `received` is `false` if none was available
+    pub(crate) fn pop(&mut self, received: bool) {
+        self.queued = if received {
+            self.queued.saturating_sub(1)
+        } else {
+            // Retained messages may have expired in the stack.
+            0
+        };
+    }
+}
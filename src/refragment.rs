@@ -0,0 +1,235 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Refragmentation of forwarded messages for destinations with a smaller MTU
+//!
+//! A bridge between ports with different MTUs can't pass the packets of the large-MTU side
+//! on unmodified. The payload of such messages is re-cut into packets of the MTU toward
+//! the destination while it streams through: every packet but the last one of a message is
+//! filled up to the MTU, as the base specification requires, and the sequence numbers are
+//! renumbered. Only the payload that doesn't fill an outbound packet yet is held, so a
+//! message never has to be reassembled completely.
+
+use mctp::{Eid, Error, Result, Tag};
+
+use crate::PacketBuffer;
+use crate::header::{HEADER_LEN, Header};
+
+/// Number of forwarded messages a [Router](crate::Router) can refragment at the same time
+pub const REFRAGMENT_FLOWS: usize = 4;
+
+/// Result of passing a packet to the [Refragmenter]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Refragment {
+    /// The packet is not part of a refragmented message, it is forwarded unmodified
+    Unmodified,
+    /// The payload of the packet was taken over, refragmented packets were sent
+    Forwarded,
+    /// The packet was dropped, the MTU toward the destination can't hold any payload
+    MtuTooSmall,
+    /// The packet was dropped and the message abandoned, a packet was lost on the way
+    OutOfSequence,
+}
+
+/// A forwarded message being refragmented
+#[derive(Debug)]
+struct Flow<B> {
+    source: Eid,
+    dest: Eid,
+    tag: Tag,
+    /// Sequence number of the next inbound packet
+    in_seq: u8,
+    /// Sequence number of the next outbound packet
+    out_seq: u8,
+    /// The next outbound packet starts the message
+    som: bool,
+    /// Payload per outbound packet
+    chunk: usize,
+    /// Outbound packet being filled, the header is written when it is sent
+    pkt: B,
+    /// Payload held in `pkt`
+    len: usize,
+    /// Time of the last inbound packet, to evict abandoned messages
+    last_millis: u64,
+}
+
+impl<B: PacketBuffer> Flow<B> {
+    fn matches(&self, hdr: &Header) -> bool {
+        self.source == hdr.source && self.dest == hdr.dest && self.tag == hdr.tag
+    }
+
+    /// Send the held payload as the next outbound packet
+    fn emit(&mut self, eom: bool, send: &mut impl FnMut(&[u8]) -> Result<()>) -> Result<()> {
+        let hdr = Header {
+            dest: self.dest,
+            source: self.source,
+            som: self.som,
+            eom,
+            seq: self.out_seq,
+            tag: self.tag,
+        };
+        let pkt = self
+            .pkt
+            .as_mut()
+            .get_mut(..HEADER_LEN + self.len)
+            .ok_or(Error::InternalError)?;
+        if let Some(header) = pkt.first_chunk_mut::<HEADER_LEN>() {
+            *header = hdr.to_bytes();
+        }
+        send(pkt)?;
+        self.som = false;
+        self.out_seq = self.out_seq.wrapping_add(1) & 0x03;
+        self.len = 0;
+        Ok(())
+    }
+}
+
+/// Forwarded messages being refragmented, each held in a packet buffer `B`
+#[derive(Debug)]
+pub(crate) struct Refragmenter<B> {
+    enabled: bool,
+    flows: [Option<Flow<B>>; REFRAGMENT_FLOWS],
+}
+
+impl<B: PacketBuffer> Refragmenter<B> {
+    pub(crate) const fn new(enabled: bool) -> Self {
+        Refragmenter {
+            enabled,
+            flows: [const { None }; REFRAGMENT_FLOWS],
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Enable or disable refragmentation, abandoning the messages in progress
+    pub(crate) fn set_enabled(&mut self, enable: bool) {
+        self.enabled = enable;
+        self.flows = [const { None }; REFRAGMENT_FLOWS];
+    }
+
+    /// Pass the forwarded packet `pkt` with the header `hdr`, to be sent with an MTU of `mtu`
+    ///
+    /// Messages whose first packet is larger than `mtu` are refragmented, `send` is called
+    /// for each outbound packet. When all flows are in use, the one idle for the longest time
+    /// is evicted for a new message.
+    pub(crate) fn push(
+        &mut self,
+        hdr: &Header,
+        pkt: &[u8],
+        mtu: usize,
+        now_millis: u64,
+        mut send: impl FnMut(&[u8]) -> Result<()>,
+    ) -> Result<Refragment> {
+        let existing = self
+            .flows
+            .iter()
+            .position(|f| f.as_ref().is_some_and(|f| f.matches(hdr)));
+        let flow = if hdr.som {
+            // A new message replaces a previous one on the same flow.
+            if let Some(slot) = existing.and_then(|i| self.flows.get_mut(i)) {
+                *slot = None;
+            }
+            if pkt.len() <= mtu {
+                return Ok(Refragment::Unmodified);
+            }
+            let chunk = mtu.min(B::LEN).saturating_sub(HEADER_LEN);
+            if chunk == 0 {
+                return Ok(Refragment::MtuTooSmall);
+            }
+            let i = self
+                .flows
+                .iter()
+                .position(|f| f.is_none())
+                .or_else(|| {
+                    self.flows
+                        .iter()
+                        .enumerate()
+                        .min_by_key(|(_, f)| f.as_ref().map_or(0, |f| f.last_millis))
+                        .map(|(i, _)| i)
+                })
+                .unwrap_or(0);
+            let slot = self.flows.get_mut(i).ok_or(Error::InternalError)?;
+            slot.insert(Flow {
+                source: hdr.source,
+                dest: hdr.dest,
+                tag: hdr.tag,
+                in_seq: hdr.seq,
+                out_seq: hdr.seq,
+                som: true,
+                chunk,
+                pkt: B::zeroed(),
+                len: 0,
+                last_millis: now_millis,
+            })
+        } else {
+            let Some(slot) = existing.and_then(|i| self.flows.get_mut(i)) else {
+                return Ok(Refragment::Unmodified);
+            };
+            let Some(flow) = slot.as_mut().filter(|f| f.in_seq == hdr.seq) else {
+                *slot = None;
+                return Ok(Refragment::OutOfSequence);
+            };
+            flow
+        };
+        flow.in_seq = hdr.seq.wrapping_add(1) & 0x03;
+        flow.last_millis = now_millis;
+
+        let result = Self::fill(
+            flow,
+            hdr.eom,
+            pkt.get(HEADER_LEN..).unwrap_or_default(),
+            &mut send,
+        );
+        if hdr.eom || result.is_err() {
+            for slot in self.flows.iter_mut() {
+                if slot.as_ref().is_some_and(|f| f.matches(hdr)) {
+                    *slot = None;
+                }
+            }
+        }
+        result.map(|_| Refragment::Forwarded)
+    }
+
+    /// Append `payload` to the held one, sending every packet that is filled up
+    fn fill(
+        flow: &mut Flow<B>,
+        eom: bool,
+        mut payload: &[u8],
+        send: &mut impl FnMut(&[u8]) -> Result<()>,
+    ) -> Result<()> {
+        loop {
+            let (chunk, rest) =
+                payload.split_at(flow.chunk.saturating_sub(flow.len).min(payload.len()));
+            let start = HEADER_LEN + flow.len;
+            flow.pkt
+                .as_mut()
+                .get_mut(start..start + chunk.len())
+                .ok_or(Error::InternalError)?
+                .copy_from_slice(chunk);
+            flow.len += chunk.len();
+            payload = rest;
+            // The last packet of the message is only sent with the end of message flag.
+            if payload.is_empty() {
+                break;
+            }
+            flow.emit(false, send)?;
+        }
+        if eom {
+            flow.emit(true, send)?;
+        }
+        Ok(())
+    }
+}
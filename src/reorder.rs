@@ -0,0 +1,208 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tolerance for slightly reordered continuation packets
+//!
+//! The reassembly in the stack aborts a message on the first packet with an unexpected
+//! sequence number. Packets arriving a little early are held back here and passed on once
+//! the packets before them have arrived.
+
+use mctp::{Eid, Tag};
+use mctp_estack::config::NUM_RECEIVE;
+
+use crate::PacketBuffer;
+use crate::header::Header;
+
+/// Largest supported reorder window
+///
+/// Sequence numbers are two bits wide, packets further ahead can't be told apart from
+/// duplicates of earlier ones.
+pub const MAX_REORDER_WINDOW: u8 = 2;
+
+const SEQ_MASK: u8 = 0x03;
+
+/// A message being reassembled, with the sequence number of its next packet
+#[derive(Debug, Clone, Copy)]
+struct Flow {
+    source: Eid,
+    tag: Tag,
+    next_seq: u8,
+}
+
+/// A packet held back until the packets before it arrived
+#[derive(Debug)]
+struct Held<B> {
+    source: Eid,
+    tag: Tag,
+    seq: u8,
+    len: usize,
+    data: B,
+}
+
+/// What to do with an inbound packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Order {
+    /// Pass the packet on to the reassembly
+    Pass,
+    /// The packet was held back
+    Held,
+}
+
+/// Reorder state for the messages being reassembled, holding packets in buffers `B`
+#[derive(Debug)]
+pub(crate) struct ReorderBuffer<B> {
+    window: u8,
+    flows: [Option<Flow>; NUM_RECEIVE],
+    held: [Option<Held<B>>; MAX_REORDER_WINDOW as usize],
+}
+
+impl<B: PacketBuffer> ReorderBuffer<B> {
+    /// `window` must not exceed [MAX_REORDER_WINDOW]
+    pub(crate) const fn new(window: u8) -> Self {
+        ReorderBuffer {
+            window,
+            flows: [None; NUM_RECEIVE],
+            held: [const { None }; MAX_REORDER_WINDOW as usize],
+        }
+    }
+
+    /// Classify the inbound packet `pkt`, holding it back if it arrived early
+    pub(crate) fn admit(&mut self, hdr: &Header, pkt: &[u8]) -> Order {
+        if self.window == 0 {
+            return Order::Pass;
+        }
+        if hdr.som {
+            // A new message replaces a previous one on the same flow.
+            self.remove(hdr.source, hdr.tag);
+            if !hdr.eom
+                && let Some(slot) = self.flows.iter_mut().find(|f| f.is_none())
+            {
+                *slot = Some(Flow {
+                    source: hdr.source,
+                    tag: hdr.tag,
+                    next_seq: next(hdr.seq),
+                });
+            }
+            return Order::Pass;
+        }
+        let Some(flow) = self.flow(hdr.source, hdr.tag) else {
+            return Order::Pass;
+        };
+        let ahead = hdr.seq.wrapping_sub(flow.next_seq) & SEQ_MASK;
+        if ahead == 0 {
+            self.advance(hdr);
+            return Order::Pass;
+        }
+        let duplicate = self
+            .held
+            .iter()
+            .flatten()
+            .any(|h| h.source == hdr.source && h.tag == hdr.tag && h.seq == hdr.seq);
+        if ahead <= self.window
+            && !duplicate
+            && pkt.len() <= B::LEN
+            && let Some(slot) = self.held.iter_mut().find(|h| h.is_none())
+        {
+            let mut held = Held {
+                source: hdr.source,
+                tag: hdr.tag,
+                seq: hdr.seq,
+                len: pkt.len(),
+                data: B::zeroed(),
+            };
+            if let Some(dst) = held.data.as_mut().get_mut(..pkt.len()) {
+                dst.copy_from_slice(pkt);
+            }
+            *slot = Some(held);
+            return Order::Held;
+        }
+        // Out of the window, the reassembly in the stack will abort the message.
+        self.remove(hdr.source, hdr.tag);
+        Order::Pass
+    }
+
+    /// Take the held packet of the flow of `hdr` that is due next, copying it to `buf`
+    ///
+    /// Returns the length of the packet.
+    pub(crate) fn take_next(&mut self, hdr: &Header, buf: &mut [u8]) -> Option<usize> {
+        let flow = self.flow(hdr.source, hdr.tag)?;
+        let slot = self.held.iter_mut().find(|h| {
+            h.as_ref().is_some_and(|h| {
+                h.source == flow.source && h.tag == flow.tag && h.seq == flow.next_seq
+            })
+        })?;
+        let held = slot.take()?;
+        let data = held.data.as_ref().get(..held.len)?;
+        buf.get_mut(..held.len)?.copy_from_slice(data);
+        if let Some(next_hdr) = Header::parse(data) {
+            self.advance(&next_hdr);
+        }
+        Some(held.len)
+    }
+
+    /// Number of packets currently held back
+    pub(crate) fn held(&self) -> usize {
+        self.held.iter().flatten().count()
+    }
+
+    fn flow(&self, source: Eid, tag: Tag) -> Option<Flow> {
+        self.flows
+            .iter()
+            .flatten()
+            .find(|f| f.source == source && f.tag == tag)
+            .copied()
+    }
+
+    /// Account for the in-order packet `hdr`
+    fn advance(&mut self, hdr: &Header) {
+        if hdr.eom {
+            self.remove(hdr.source, hdr.tag);
+            return;
+        }
+        if let Some(flow) = self
+            .flows
+            .iter_mut()
+            .flatten()
+            .find(|f| f.source == hdr.source && f.tag == hdr.tag)
+        {
+            flow.next_seq = next(hdr.seq);
+        }
+    }
+
+    /// Forget the flow of `hdr` and the packets held for it
+    pub(crate) fn forget(&mut self, hdr: &Header) {
+        self.remove(hdr.source, hdr.tag);
+    }
+
+    /// Forget a flow and the packets held for it
+    fn remove(&mut self, source: Eid, tag: Tag) {
+        for slot in self.flows.iter_mut() {
+            if slot.is_some_and(|f| f.source == source && f.tag == tag) {
+                *slot = None;
+            }
+        }
+        for slot in self.held.iter_mut() {
+            if slot
+                .as_ref()
+                .is_some_and(|h| h.source == source && h.tag == tag)
+            {
+                *slot = None;
+            }
+        }
+    }
+}
+
+fn next(seq: u8) -> u8 {
+    seq.wrapping_add(1) & SEQ_MASK
+}
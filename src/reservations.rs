@@ -0,0 +1,192 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Accounting of the reassembly contexts of the stack
+//!
+//! The stack has [MAX_REASSEMBLIES] reassembly contexts, used by messages being reassembled
+//! and by completed messages until they are received. The router can use fewer of them, and
+//! reserving contexts for a message type keeps bulk traffic of other types from occupying
+//! all of them.
+
+use mctp::{Eid, Error, MsgType, Result, Tag};
+use mctp_estack::config::NUM_RECEIVE;
+
+use crate::REASSEMBLY_TIMEOUT_MILLIS;
+use crate::header::{HEADER_LEN, Header};
+
+/// Maximum number of message types with reserved reassembly contexts
+pub const RESERVATION_TABLE_SIZE: usize = 4;
+
+/// Number of reassembly contexts of the stack
+///
+/// Set by the `NUM_RECEIVE` build configuration of `mctp-estack`.
+pub const MAX_REASSEMBLIES: usize = NUM_RECEIVE;
+
+/// A message being reassembled
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Flow {
+    pub(crate) source: Eid,
+    pub(crate) tag: Tag,
+    pub(crate) typ: MsgType,
+    /// Payload received so far, without the message type
+    pub(crate) bytes: usize,
+    /// Time of the first packet
+    pub(crate) started_millis: u64,
+    /// Time of the last packet
+    pub(crate) last_millis: u64,
+}
+
+/// Reservations and the messages currently being reassembled
+#[derive(Debug)]
+pub(crate) struct Reservations {
+    /// Number of contexts the router uses
+    pub(crate) limit: usize,
+    /// Messages dropped because all contexts were in use
+    pub(crate) exhausted: usize,
+    reserved: [Option<(MsgType, usize)>; RESERVATION_TABLE_SIZE],
+    flows: [Option<Flow>; NUM_RECEIVE],
+}
+
+impl Reservations {
+    /// Use `limit` contexts, capped at [MAX_REASSEMBLIES]
+    pub(crate) fn new(limit: usize) -> Self {
+        Reservations {
+            limit: limit.min(MAX_REASSEMBLIES),
+            exhausted: 0,
+            reserved: [None; RESERVATION_TABLE_SIZE],
+            flows: [None; NUM_RECEIVE],
+        }
+    }
+
+    /// Reserve `count` contexts for `typ`, 0 removes the reservation
+    pub(crate) fn reserve(&mut self, typ: MsgType, count: usize) -> Result<()> {
+        let others: usize = self
+            .reserved
+            .iter()
+            .flatten()
+            .filter(|(t, _)| *t != typ)
+            .map(|(_, n)| n)
+            .sum();
+        if others + count > self.limit {
+            return Err(Error::BadArgument);
+        }
+        let existing = self
+            .reserved
+            .iter_mut()
+            .find(|r| r.is_some_and(|(t, _)| t == typ));
+        let slot = match existing {
+            Some(slot) => slot,
+            None if count == 0 => return Ok(()),
+            None => self
+                .reserved
+                .iter_mut()
+                .find(|r| r.is_none())
+                .ok_or(Error::NoSpace)?,
+        };
+        *slot = (count > 0).then_some((typ, count));
+        Ok(())
+    }
+
+    /// Get the number of contexts reserved for `typ`
+    pub(crate) fn reserved(&self, typ: MsgType) -> usize {
+        self.reserved
+            .iter()
+            .flatten()
+            .find(|(t, _)| *t == typ)
+            .map_or(0, |(_, n)| *n)
+    }
+
+    /// Iterate over the reservations
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (MsgType, usize)> + '_ {
+        self.reserved.iter().flatten().copied()
+    }
+
+    /// Iterate over the messages being reassembled by the stack
+    pub(crate) fn flows(&self) -> impl Iterator<Item = &Flow> + '_ {
+        self.flows.iter().flatten()
+    }
+
+    /// Number of messages of `typ` (or all types) being reassembled
+    pub(crate) fn active(&self, typ: Option<MsgType>) -> usize {
+        self.flows
+            .iter()
+            .flatten()
+            .filter(|f| typ.is_none_or(|t| f.typ == t))
+            .count()
+    }
+
+    /// Track the message started by the first packet `hdr` of type `typ`
+    ///
+    /// `len` is the length of the packet.
+    pub(crate) fn start(&mut self, hdr: &Header, typ: MsgType, len: usize, now_millis: u64) {
+        // A new message replaces a previous one on the same flow.
+        self.forget(hdr);
+        if !hdr.eom
+            && let Some(slot) = self.flows.iter_mut().find(|f| f.is_none())
+        {
+            *slot = Some(Flow {
+                source: hdr.source,
+                tag: hdr.tag,
+                typ,
+                bytes: len.saturating_sub(HEADER_LEN + 1),
+                started_millis: now_millis,
+                last_millis: now_millis,
+            });
+        }
+    }
+
+    /// Account for the continuation packet `hdr` of length `len`
+    pub(crate) fn next(&mut self, hdr: &Header, len: usize, now_millis: u64) {
+        if hdr.eom {
+            self.forget(hdr);
+        } else if let Some(flow) = self
+            .flows
+            .iter_mut()
+            .flatten()
+            .find(|f| f.source == hdr.source && f.tag == hdr.tag)
+        {
+            flow.bytes += len.saturating_sub(HEADER_LEN);
+            flow.last_millis = now_millis;
+        }
+    }
+
+    /// Stop tracking the message of `hdr`
+    pub(crate) fn forget(&mut self, hdr: &Header) {
+        for slot in self.flows.iter_mut() {
+            if slot.is_some_and(|f| f.source == hdr.source && f.tag == hdr.tag) {
+                *slot = None;
+            }
+        }
+    }
+
+    /// Get the time at which the next tracked message is discarded by the stack
+    pub(crate) fn next_deadline(&self) -> Option<u64> {
+        self.flows
+            .iter()
+            .flatten()
+            .map(|f| f.last_millis.saturating_add(REASSEMBLY_TIMEOUT_MILLIS))
+            .min()
+    }
+
+    /// Stop tracking messages the stack discarded for lack of progress
+    pub(crate) fn expire(&mut self, now_millis: u64) {
+        for slot in self.flows.iter_mut() {
+            if slot.is_some_and(|f| {
+                now_millis.saturating_sub(f.last_millis) >= REASSEMBLY_TIMEOUT_MILLIS
+            }) {
+                *slot = None;
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Responding to a request while it is borrowed, or later from a [ReplyContext]
+
+use mctp::{Eid, Error, MsgIC, MsgType, Result, Tag};
+
+use crate::mic;
+use crate::{
+    Clock, Handle, Hooks, ListenerHandle, MAX_IC_BUFS, MAX_MIC_LEN, MessageInfo, MessageIntegrity,
+    Packets, RouterError, RouterResult, Sender, Transmitter, append_check, integrity_check,
+};
+
+/// What is needed to respond to a request received by a listener, after the request is gone
+///
+/// Unlike the [MessageInfo] it is created from, a reply context can't be copied: it is
+/// redeemed for exactly one response with [reply()](crate::GenericRouter::reply), which
+/// consumes it. It owns no data borrowed from the router, so it can be stored or passed to
+/// another task while the request is processed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ReplyContext {
+    listener: ListenerHandle,
+    request: MessageInfo,
+}
+
+impl ReplyContext {
+    /// Create the context for responding to `request`, received by `listener`
+    ///
+    /// Returns [BadArgument](Error::BadArgument) if `request` is a response itself.
+    pub fn new(listener: ListenerHandle, request: &MessageInfo) -> Result<Self> {
+        if !request.tag.is_owner() {
+            return Err(Error::BadArgument);
+        }
+        Ok(ReplyContext {
+            listener,
+            request: *request,
+        })
+    }
+
+    /// Get the listener that received the request
+    pub fn listener(&self) -> ListenerHandle {
+        self.listener
+    }
+
+    /// Get the source EID of the request, the destination of the response
+    pub fn source(&self) -> Eid {
+        self.request.source
+    }
+
+    /// Get the tag of the request
+    pub fn tag(&self) -> Tag {
+        self.request.tag
+    }
+
+    /// Get the message type of the request and the response
+    pub fn typ(&self) -> MsgType {
+        self.request.typ
+    }
+
+    /// Get the metadata of the request
+    pub fn request(&self) -> &MessageInfo {
+        &self.request
+    }
+}
+
+/// Sends the response to a request passed to [recv_with()](crate::GenericRouter::recv_with)
+///
+/// Only borrows the parts of the router needed to transmit,
+/// so it can be used while the request is borrowed.
+#[derive(Debug)]
+pub struct Responder<'r, S: Sender, H: Hooks, C: Clock> {
+    /// Prepared for the response along with the local EID it is sent from,
+    /// or the reason no response can be sent
+    pub(crate) prepared: Result<(Packets, Option<Eid>)>,
+    pub(crate) handle: Handle,
+    pub(crate) dest: Eid,
+    pub(crate) tag: Tag,
+    pub(crate) typ: MsgType,
+    pub(crate) ic: MsgIC,
+    /// The integrity check is computed by the sender
+    pub(crate) ic_offloaded: bool,
+    /// MIC handler of the message type
+    pub(crate) mic: Option<&'static dyn MessageIntegrity>,
+    /// Sending the response failed, latched for the handle once the request is consumed
+    pub(crate) failed: bool,
+    pub(crate) tx: Transmitter<'r, S, H, C>,
+}
+
+impl<S: Sender, H: Hooks, C: Clock> Responder<'_, S, H, C> {
+    /// Send the response with the payload `bufs`
+    ///
+    /// The response has the message type and integrity check flag of the request, and is
+    /// sent from the local EID the request was addressed to.
+    /// It takes the same path as [send_vectored()](crate::GenericRouter::send_vectored),
+    /// e.g. through throttles and to backup hops.
+    /// A single response can be sent.
+    ///
+    /// Returns [BadArgument](Error::BadArgument) if the message is a response itself or the
+    /// response was already sent, [TxFailure](Error::TxFailure) if the router is quiesced.
+    pub fn respond(&mut self, bufs: &[&[u8]]) -> RouterResult<()> {
+        let (dest, tag) = (self.dest, self.tag);
+        let context = |e: Error| {
+            RouterError::from(e)
+                .with_handle(self.handle)
+                .with_eid(dest)
+                .with_tag(Some(tag))
+        };
+        let mut mic_parts: [&[u8]; MAX_IC_BUFS + 1] = [&[]; MAX_IC_BUFS + 1];
+        let mut mic = [0; MAX_MIC_LEN];
+        let bufs = match self.mic {
+            Some(handler) => {
+                mic::append(handler, self.typ, bufs, &mut mic, &mut mic_parts).map_err(context)?
+            }
+            None => bufs,
+        };
+        let mut parts: [&[u8]; MAX_IC_BUFS + 1] = [&[]; MAX_IC_BUFS + 1];
+        let crc;
+        let bufs = if self.ic.0 && !self.ic_offloaded {
+            crc = integrity_check(self.typ, bufs);
+            append_check(bufs, &crc, &mut parts).map_err(context)?
+        } else {
+            bufs
+        };
+        let (packets, source) =
+            core::mem::replace(&mut self.prepared, Err(Error::BadArgument)).map_err(context)?;
+
+        let now_millis = self.tx.clock.now_millis();
+        let owner = Some((self.handle.cookie(), None));
+        self.tx
+            .transmit(dest, packets, bufs, now_millis, source, owner)
+            .map_err(|e| {
+                self.failed = true;
+                context(e)
+            })?;
+        Ok(())
+    }
+}
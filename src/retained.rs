@@ -0,0 +1,133 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Expiry of retained messages that were not received in time
+//!
+//! The stack drops retained messages once they are older than the reassembly timeout,
+//! without telling which ones. The router keeps the delivery time of every message it
+//! retained, so it can report the ones that vanished unread.
+
+use mctp_estack::AppCookie;
+use mctp_estack::config::NUM_RECEIVE;
+
+use crate::REASSEMBLY_TIMEOUT_MILLIS;
+
+/// A message retained in the stack
+#[derive(Debug, Clone, Copy)]
+struct Retained {
+    cookie: AppCookie,
+    since_millis: u64,
+    /// The stack no longer holds the message
+    lost: bool,
+}
+
+/// Delivery times of the messages retained in the stack, by cookie of their handle
+#[derive(Debug)]
+pub(crate) struct RetainedMessages {
+    entries: [Option<Retained>; NUM_RECEIVE],
+}
+
+impl RetainedMessages {
+    pub(crate) const fn new() -> Self {
+        RetainedMessages {
+            entries: [None; NUM_RECEIVE],
+        }
+    }
+
+    /// Account for a message retained for `cookie` at `now_millis`
+    ///
+    /// The stack holds at most [NUM_RECEIVE] messages, a full table means the oldest entry
+    /// is stale and gets replaced.
+    pub(crate) fn push(&mut self, cookie: AppCookie, now_millis: u64) {
+        let free = self.entries.iter().position(|e| e.is_none());
+        let slot = match free {
+            Some(i) => self.entries.get_mut(i),
+            None => self
+                .entries
+                .iter_mut()
+                .min_by_key(|e| e.map(|e| e.since_millis)),
+        };
+        if let Some(slot) = slot {
+            *slot = Some(Retained {
+                cookie,
+                since_millis: now_millis,
+                lost: false,
+            });
+        }
+    }
+
+    /// Get the delivery time of the oldest message of `cookie`
+    pub(crate) fn oldest(&self, cookie: AppCookie) -> Option<u64> {
+        self.entries
+            .iter()
+            .flatten()
+            .filter(|e| e.cookie == cookie)
+            .map(|e| e.since_millis)
+            .min()
+    }
+
+    /// Account for the oldest message of `cookie` being received or dropped
+    pub(crate) fn pop(&mut self, cookie: AppCookie) {
+        if let Some(slot) = self
+            .entries
+            .iter_mut()
+            .filter(|e| e.is_some_and(|e| e.cookie == cookie))
+            .min_by_key(|e| e.map(|e| e.since_millis))
+        {
+            *slot = None;
+        }
+    }
+
+    /// Mark all messages of `cookie` as expired, the stack no longer holds them
+    pub(crate) fn lost(&mut self, cookie: AppCookie) {
+        for e in self.entries.iter_mut().flatten() {
+            if e.cookie == cookie {
+                e.lost = true;
+            }
+        }
+    }
+
+    /// Forget the messages of `cookie` without reporting them
+    pub(crate) fn forget(&mut self, cookie: AppCookie) {
+        for slot in self.entries.iter_mut() {
+            if slot.is_some_and(|e| e.cookie == cookie) {
+                *slot = None;
+            }
+        }
+    }
+
+    /// Get the time at which the next message expires
+    ///
+    /// Lost messages are due at once.
+    pub(crate) fn next_deadline(&self) -> Option<u64> {
+        self.entries
+            .iter()
+            .flatten()
+            .map(|e| match e.lost {
+                true => 0,
+                false => e.since_millis.saturating_add(REASSEMBLY_TIMEOUT_MILLIS),
+            })
+            .min()
+    }
+
+    /// Take the cookie of a message that expired at `now_millis`
+    pub(crate) fn take_expired(&mut self, now_millis: u64) -> Option<AppCookie> {
+        let slot = self.entries.iter_mut().find(|e| {
+            e.is_some_and(|e| {
+                e.lost || now_millis.saturating_sub(e.since_millis) >= REASSEMBLY_TIMEOUT_MILLIS
+            })
+        })?;
+        slot.take().map(|e| e.cookie)
+    }
+}
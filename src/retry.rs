@@ -0,0 +1,179 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Retry policies for requests and liveness probes
+//!
+//! A [RetryPolicy] decides how long to wait for the response to each attempt and how many
+//! attempts are made. Protocols sharing a [Router](crate::Router) can use different
+//! policies, set per request handle with
+//! [set_retry_policy()](crate::GenericRouter::set_retry_policy), per message type with
+//! [set_type_retry_policy()](crate::GenericRouter::set_type_retry_policy) and for the
+//! liveness probes with [set_keep_alive_policy()](crate::GenericRouter::set_keep_alive_policy).
+//!
+//! The router does not keep copies of sent messages. A request whose attempt timed out is
+//! reported with [RetryDue](crate::ExpiryReason::RetryDue) and sent again by the
+//! application, after the last attempt with [NoResponse](crate::ExpiryReason::NoResponse).
+
+use core::fmt::Debug;
+
+use mctp::{Error, MsgType, Result};
+
+/// Number of message types with a [RetryPolicy] a [Router](crate::Router) can hold
+pub const RETRY_POLICY_TABLE_SIZE: usize = 4;
+
+/// Timeouts and number of attempts of a request
+///
+/// Policies are shared by reference, `Sync` keeps routers holding them `Send`.
+pub trait RetryPolicy: Debug + Sync {
+    /// Get the time to wait for a response to attempt `attempt`, counted from 0
+    fn timeout_millis(&self, attempt: u8) -> u64;
+
+    /// Get the number of attempts before giving up
+    fn max_attempts(&self) -> u8;
+
+    /// Get an additional random wait for attempt `attempt`
+    ///
+    /// `seed` differs between requests and peers, so their retries spread out.
+    /// The default implementation adds none.
+    fn jitter_millis(&self, attempt: u8, seed: u32) -> u64 {
+        let _ = (attempt, seed);
+        0
+    }
+}
+
+/// A [RetryPolicy] with exponential backoff
+///
+/// The timeout starts at `initial_millis` and is multiplied by the factor (2 by default)
+/// for each further attempt, up to the maximum timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Backoff {
+    initial_millis: u64,
+    max_millis: u64,
+    factor: u8,
+    max_attempts: u8,
+    jitter_millis: u64,
+}
+
+impl Backoff {
+    /// Wait `initial_millis` for the first of `max_attempts` attempts
+    pub const fn new(initial_millis: u64, max_attempts: u8) -> Self {
+        Backoff {
+            initial_millis,
+            max_millis: u64::MAX,
+            factor: 2,
+            max_attempts,
+            jitter_millis: 0,
+        }
+    }
+
+    /// Multiply the timeout by `factor` for each attempt, 1 keeps it constant
+    pub const fn factor(mut self, factor: u8) -> Self {
+        self.factor = factor;
+        self
+    }
+
+    /// Limit the timeout of an attempt to `max_millis`
+    pub const fn max_timeout(mut self, max_millis: u64) -> Self {
+        self.max_millis = max_millis;
+        self
+    }
+
+    /// Add a random wait of up to `jitter_millis` to each attempt
+    pub const fn jitter(mut self, jitter_millis: u64) -> Self {
+        self.jitter_millis = jitter_millis;
+        self
+    }
+}
+
+impl RetryPolicy for Backoff {
+    fn timeout_millis(&self, attempt: u8) -> u64 {
+        let factor = u64::from(self.factor).saturating_pow(attempt.into());
+        self.initial_millis
+            .saturating_mul(factor)
+            .min(self.max_millis)
+    }
+
+    fn max_attempts(&self) -> u8 {
+        self.max_attempts
+    }
+
+    fn jitter_millis(&self, attempt: u8, seed: u32) -> u64 {
+        if self.jitter_millis == 0 {
+            return 0;
+        }
+        // xorshift32 of the seed and attempt, random enough to spread retries
+        let mut x = seed ^ u32::from(attempt).rotate_left(16) | 1;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        u64::from(x) % (self.jitter_millis + 1)
+    }
+}
+
+/// Deadline of attempt `attempt` sent at `sent_millis` under `policy`
+pub(crate) fn deadline(policy: &dyn RetryPolicy, attempt: u8, seed: u32, sent_millis: u64) -> u64 {
+    sent_millis
+        .saturating_add(policy.timeout_millis(attempt))
+        .saturating_add(policy.jitter_millis(attempt, seed))
+}
+
+/// Retry policies of message types
+#[derive(Debug)]
+pub(crate) struct TypePolicies {
+    entries: [Option<(MsgType, &'static dyn RetryPolicy)>; RETRY_POLICY_TABLE_SIZE],
+}
+
+impl TypePolicies {
+    pub(crate) const fn new() -> Self {
+        TypePolicies {
+            entries: [None; RETRY_POLICY_TABLE_SIZE],
+        }
+    }
+
+    /// Set or remove the policy of `typ`
+    ///
+    /// Returns [NoSpace](Error::NoSpace) if the table is full.
+    pub(crate) fn set(
+        &mut self,
+        typ: MsgType,
+        policy: Option<&'static dyn RetryPolicy>,
+    ) -> Result<()> {
+        let existing = self
+            .entries
+            .iter()
+            .position(|e| e.is_some_and(|(t, _)| t == typ));
+        let Some(policy) = policy else {
+            if let Some(slot) = existing.and_then(|i| self.entries.get_mut(i)) {
+                *slot = None;
+            }
+            return Ok(());
+        };
+        let slot = existing
+            .or_else(|| self.entries.iter().position(|e| e.is_none()))
+            .and_then(|i| self.entries.get_mut(i))
+            .ok_or(Error::NoSpace)?;
+        *slot = Some((typ, policy));
+        Ok(())
+    }
+
+    pub(crate) fn get(&self, typ: MsgType) -> Option<&'static dyn RetryPolicy> {
+        self.entries
+            .iter()
+            .flatten()
+            .find(|(t, _)| *t == typ)
+            .map(|(_, p)| *p)
+    }
+}
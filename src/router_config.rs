@@ -0,0 +1,445 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Configuration of a [Router](crate::Router)
+
+use core::ops::RangeInclusive;
+
+use mctp::{Eid, Error, Result};
+
+use crate::control::{EidType, EndpointType};
+use crate::routes::{self, Routes};
+use crate::{
+    BusyRetry, KeepAlive, MAX_REASSEMBLIES, MAX_REORDER_WINDOW, MTU_TABLE_SIZE, NotifyRetransmit,
+    ROUTE_TABLE_SIZE, RateLimit, Route, RouterSnapshot, Scheduling, TagAllocation, Validation,
+    WorkBudget,
+};
+
+/// Configuration of a [Router](crate::Router)
+///
+/// Built with chained setters and passed to
+/// [Router::new_with_config()](crate::Router::new_with_config):
+///
+/// ```
+/// # fn main() -> mctp::Result<()> {
+/// use mctp::Eid;
+/// use mctp_lib::{RateLimit, RouterConfig};
+///
+/// let config = RouterConfig::new(Eid(8))
+///     .uuid([0x42; 16])
+///     .network_id([0x43; 16])
+///     .request_timeout_millis(Some(1000))
+///     .mtu(Eid(9), 68)?
+///     .mtu_discovery(true)
+///     .rate_limit(Some(RateLimit::new(8, 100)));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// With the `serde` feature, a configuration can also be loaded from e.g. TOML or JSON.
+/// Only `own_eid` is required, the fields are named like the setters:
+///
+/// ```toml
+/// own_eid = 8
+/// request_timeout_millis = 1000
+/// mtu_discovery = true
+/// mtus = [{ eid = 9, mtu = 68 }]
+/// rate_limit = { burst = 8, packets_per_sec = 100 }
+/// validation = { reserved_header_bits = true }
+/// routes = [{ first = 20, last = 29, port = 0, physical_addr = 0x1d }]
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RouterConfig {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_util::eid"))]
+    pub(crate) own_eid: Eid,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "crate::serde_util::option_eid")
+    )]
+    pub(crate) static_eid: Option<Eid>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) uuid: Option<[u8; 16]>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) network_id: Option<[u8; 16]>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) eid_type: EidType,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) endpoint_type: EndpointType,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) request_timeout_millis: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) tx_timeout_millis: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) tag_reclaim_millis: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) work_budget: WorkBudget,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) tag_allocation: TagAllocation,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) event_queue: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) dispatch_trace: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) busy_retry: Option<BusyRetry>,
+    /// Static and learned MTU table entries
+    #[cfg_attr(feature = "serde", serde(default, with = "crate::serde_util::mtus"))]
+    pub(crate) mtus: [Option<(Eid, usize, bool)>; MTU_TABLE_SIZE],
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) mtu_discovery: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) discovery_notify: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) notify_retransmit: Option<NotifyRetransmit>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) forwarding: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) refragment: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) scheduling: Scheduling,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) promiscuous: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) manual_control: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) declared_types_only: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) validation: Validation,
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default,
+            deserialize_with = "crate::serde_util::reorder_window::deserialize"
+        )
+    )]
+    pub(crate) reorder_window: u8,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) rate_limit: Option<RateLimit>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) keep_alive: Option<KeepAlive>,
+    #[cfg_attr(feature = "serde", serde(default, with = "crate::serde_util::routes"))]
+    pub(crate) routes: Routes,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) max_reassemblies: Option<usize>,
+}
+
+impl RouterConfig {
+    /// Create a configuration for an endpoint with `own_eid`
+    ///
+    /// All other options are unset or disabled.
+    pub fn new(own_eid: Eid) -> Self {
+        RouterConfig {
+            own_eid,
+            static_eid: None,
+            uuid: None,
+            network_id: None,
+            eid_type: EidType::Dynamic,
+            endpoint_type: EndpointType::Simple,
+            request_timeout_millis: None,
+            tx_timeout_millis: None,
+            tag_reclaim_millis: None,
+            work_budget: WorkBudget::default(),
+            tag_allocation: TagAllocation::default(),
+            event_queue: false,
+            dispatch_trace: false,
+            busy_retry: None,
+            mtus: [None; MTU_TABLE_SIZE],
+            mtu_discovery: false,
+            discovery_notify: false,
+            notify_retransmit: None,
+            forwarding: false,
+            refragment: false,
+            scheduling: Scheduling::InOrder,
+            promiscuous: false,
+            manual_control: false,
+            declared_types_only: false,
+            validation: Validation::lenient(),
+            reorder_window: 0,
+            rate_limit: None,
+            keep_alive: None,
+            routes: [None; ROUTE_TABLE_SIZE],
+            max_reassemblies: None,
+        }
+    }
+
+    /// Create a configuration restoring the state saved in `snapshot`
+    ///
+    /// Sets the EID, static EID, UUID, network ID, MTU table and routes of the endpoint,
+    /// all other options are unset or disabled and can be applied on top.
+    pub fn restore(snapshot: &RouterSnapshot) -> Self {
+        RouterConfig {
+            static_eid: Some(snapshot.static_eid),
+            uuid: snapshot.uuid,
+            network_id: snapshot.network_id,
+            mtus: snapshot.mtus,
+            routes: snapshot.routes,
+            ..RouterConfig::new(snapshot.own_eid)
+        }
+    }
+
+    /// Set the EID of the endpoint
+    pub fn own_eid(mut self, eid: Eid) -> Self {
+        self.own_eid = eid;
+        self
+    }
+
+    /// Set the static EID, restored by a Set Endpoint ID reset
+    ///
+    /// Defaults to the EID the endpoint starts with, see [eid_type()](Self::eid_type).
+    pub fn static_eid(mut self, eid: Eid) -> Self {
+        self.static_eid = Some(eid);
+        self
+    }
+
+    /// Set the UUID of the endpoint
+    pub fn uuid(mut self, uuid: [u8; 16]) -> Self {
+        self.uuid = Some(uuid);
+        self
+    }
+
+    /// Set the network ID, see [Router::set_network_id()](crate::Router::set_network_id)
+    pub fn network_id(mut self, network_id: [u8; 16]) -> Self {
+        self.network_id = Some(network_id);
+        self
+    }
+
+    /// Set how the endpoint gets its EID
+    ///
+    /// The EID passed to [new()](Self::new) is the static EID, unless one is set with
+    /// [static_eid()](Self::static_eid), which a Set Endpoint ID reset restores. With [Static](EidType::Static), assignments by the bus owner are rejected.
+    /// Reported by Get Endpoint ID, see
+    /// [Router::control_response()](crate::Router::control_response).
+    /// Defaults to [Dynamic](EidType::Dynamic).
+    pub fn eid_type(mut self, eid_type: EidType) -> Self {
+        self.eid_type = eid_type;
+        self
+    }
+
+    /// Set the role of the endpoint reported by Get Endpoint ID
+    ///
+    /// Defaults to [Simple](EndpointType::Simple). Forwarding is enabled separately with
+    /// [forwarding()](Self::forwarding).
+    pub fn endpoint_type(mut self, endpoint_type: EndpointType) -> Self {
+        self.endpoint_type = endpoint_type;
+        self
+    }
+
+    /// Set the time after which an unanswered request is abandoned
+    ///
+    /// When expired, the tag of the request is released in [Router::update()](crate::Router::update)
+    /// and late responses are dropped.
+    /// `None` (the default) leaves tag expiry to the stack.
+    pub fn request_timeout_millis(mut self, timeout: Option<u64>) -> Self {
+        self.request_timeout_millis = timeout;
+        self
+    }
+
+    /// Set the time after which sending a message is abandoned
+    ///
+    /// Fragments are not passed to the [Sender](crate::Sender) any more once sending took
+    /// longer, the send fails with [TimedOut](Error::TimedOut).
+    /// Requests whose last message could not be sent completely keep their tag until
+    /// [Router::update()](crate::Router::update) releases it after this time and reports
+    /// [TxStalled](crate::ExpiryReason::TxStalled).
+    /// `None` (the default) disables the watchdog.
+    pub fn tx_timeout_millis(mut self, timeout: Option<u64>) -> Self {
+        self.tx_timeout_millis = timeout;
+        self
+    }
+
+    /// Set the time after which the tag of a request is reclaimed
+    ///
+    /// The tag is held for this time after a request was sent, whether it is answered or
+    /// not, and released in [Router::update()](crate::Router::update). A request unanswered
+    /// by then expires with [NoResponse](crate::ExpiryReason::NoResponse) and late responses
+    /// are dropped. Slow buses and long-running commands need longer times than the fixed
+    /// expiry of the stack, which applies with `None` (the default).
+    /// Can be overridden per request, see
+    /// [Router::set_tag_reclaim_millis()](crate::Router::set_tag_reclaim_millis).
+    pub fn tag_reclaim_millis(mut self, reclaim: Option<u64>) -> Self {
+        self.tag_reclaim_millis = reclaim;
+        self
+    }
+
+    /// Set how the owned tags of requests are allocated, see [TagAllocation]
+    ///
+    /// [RoundRobin](TagAllocation::RoundRobin) by default, see also
+    /// [Router::set_tag_allocation()](crate::Router::set_tag_allocation).
+    pub fn tag_allocation(mut self, allocation: TagAllocation) -> Self {
+        self.tag_allocation = allocation;
+        self
+    }
+
+    /// Bound the work done per [Router::update()](crate::Router::update), see [WorkBudget]
+    ///
+    /// Unlimited by default.
+    pub fn work_budget(mut self, budget: WorkBudget) -> Self {
+        self.work_budget = budget;
+        self
+    }
+
+    /// Queue [RouterEvent](crate::RouterEvent)s for
+    /// [Router::next_event()](crate::Router::next_event)
+    ///
+    /// Disabled by default, see also [Router::set_event_queue()](crate::Router::set_event_queue).
+    pub fn event_queue(mut self, enable: bool) -> Self {
+        self.event_queue = enable;
+        self
+    }
+
+    /// Record recent routing decisions for
+    /// [Router::dispatch_trace()](crate::Router::dispatch_trace)
+    ///
+    /// Disabled by default, see also
+    /// [Router::set_dispatch_trace()](crate::Router::set_dispatch_trace).
+    pub fn dispatch_trace(mut self, enable: bool) -> Self {
+        self.dispatch_trace = enable;
+        self
+    }
+
+    /// Set how packets are retried when the receiver is busy, see [BusyRetry]
+    ///
+    /// `None` (the default) fails a send on the first busy receiver.
+    pub fn busy_retry(mut self, retry: Option<BusyRetry>) -> Self {
+        self.busy_retry = retry;
+        self
+    }
+
+    /// Add a static MTU override for `eid`, see [Router::set_mtu()](crate::Router::set_mtu)
+    ///
+    /// Returns [BadArgument](Error::BadArgument) if `mtu` can't hold an MCTP header and payload,
+    /// [NoSpace](Error::NoSpace) when the MTU table is full.
+    pub fn mtu(mut self, eid: Eid, mtu: usize) -> Result<Self> {
+        if mtu <= crate::header::HEADER_LEN {
+            return Err(Error::BadArgument);
+        }
+        let slot = match self.mtus.iter().position(|x| x.is_some_and(|x| x.0 == eid)) {
+            Some(i) => self.mtus.get_mut(i),
+            None => self.mtus.iter_mut().find(|x| x.is_none()),
+        }
+        .ok_or(Error::NoSpace)?;
+        *slot = Some((eid, mtu, false));
+        Ok(self)
+    }
+
+    /// Enable or disable MTU discovery, see [Router::set_mtu_discovery()](crate::Router::set_mtu_discovery)
+    pub fn mtu_discovery(mut self, enable: bool) -> Self {
+        self.mtu_discovery = enable;
+        self
+    }
+
+    /// Enable or disable Discovery Notify on link up, see
+    /// [Router::set_discovery_notify()](crate::Router::set_discovery_notify)
+    pub fn discovery_notify(mut self, enable: bool) -> Self {
+        self.discovery_notify = enable;
+        self
+    }
+
+    /// Set how Discovery Notify is sent again, see
+    /// [Router::set_notify_retransmit()](crate::Router::set_notify_retransmit)
+    pub fn notify_retransmit(mut self, policy: Option<NotifyRetransmit>) -> Self {
+        self.notify_retransmit = policy;
+        self
+    }
+
+    /// Enable or disable bridge forwarding, see [Router::set_forwarding()](crate::Router::set_forwarding)
+    pub fn forwarding(mut self, enable: bool) -> Self {
+        self.forwarding = enable;
+        self
+    }
+
+    /// Enable or disable refragmentation of forwarded messages, see
+    /// [Router::set_refragmentation()](crate::Router::set_refragmentation)
+    pub fn refragment(mut self, enable: bool) -> Self {
+        self.refragment = enable;
+        self
+    }
+
+    /// Set how handles share receive processing and bus time, see
+    /// [Router::set_scheduling()](crate::Router::set_scheduling)
+    pub fn scheduling(mut self, scheduling: Scheduling) -> Self {
+        self.scheduling = scheduling;
+        self
+    }
+
+    /// Enable or disable promiscuous mode, see [Router::set_promiscuous()](crate::Router::set_promiscuous)
+    pub fn promiscuous(mut self, enable: bool) -> Self {
+        self.promiscuous = enable;
+        self
+    }
+
+    /// Leave control requests the router can't answer to the application, see
+    /// [Router::set_manual_control()](crate::Router::set_manual_control)
+    pub fn manual_control(mut self, enable: bool) -> Self {
+        self.manual_control = enable;
+        self
+    }
+
+    /// Only bind listeners to declared message types, see
+    /// [Router::set_declared_types_only()](crate::Router::set_declared_types_only)
+    pub fn declared_types_only(mut self, enable: bool) -> Self {
+        self.declared_types_only = enable;
+        self
+    }
+
+    /// Set how out-of-spec packets are treated, see [Router::set_validation()](crate::Router::set_validation)
+    pub fn validation(mut self, validation: Validation) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    /// Set the reorder window, see [Router::set_reorder_window()](crate::Router::set_reorder_window)
+    ///
+    /// Returns [BadArgument](Error::BadArgument) if `window` exceeds [MAX_REORDER_WINDOW].
+    pub fn reorder_window(mut self, window: u8) -> Result<Self> {
+        if window > MAX_REORDER_WINDOW {
+            return Err(Error::BadArgument);
+        }
+        self.reorder_window = window;
+        Ok(self)
+    }
+
+    /// Limit inbound packets per source EID, see [Router::set_rate_limit()](crate::Router::set_rate_limit)
+    pub fn rate_limit(mut self, limit: Option<RateLimit>) -> Self {
+        self.rate_limit = limit;
+        self
+    }
+
+    /// Add a static route, see [Router::add_route()](crate::Router::add_route)
+    ///
+    /// Returns [BadArgument](Error::BadArgument) for an empty range and
+    /// [NoSpace](Error::NoSpace) if the table is full.
+    pub fn route(mut self, eids: RangeInclusive<u8>, port: u8, physical_addr: u64) -> Result<Self> {
+        routes::add(&mut self.routes, Route::new(eids, port, physical_addr)?)?;
+        Ok(self)
+    }
+
+    /// Limit concurrent inbound reassemblies, see
+    /// [Router::set_max_reassemblies()](crate::Router::set_max_reassemblies)
+    ///
+    /// Returns [BadArgument](Error::BadArgument) for 0 or more than [MAX_REASSEMBLIES].
+    pub fn max_reassemblies(mut self, max: usize) -> Result<Self> {
+        if max == 0 || max > MAX_REASSEMBLIES {
+            return Err(Error::BadArgument);
+        }
+        self.max_reassemblies = Some(max);
+        Ok(self)
+    }
+
+    /// Set the liveness monitoring, see [Router::set_keep_alive()](crate::Router::set_keep_alive)
+    pub fn keep_alive(mut self, keep_alive: Option<KeepAlive>) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+}
@@ -0,0 +1,364 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Static routes to EIDs behind bridges
+//!
+//! A route directs all packets for a range of EIDs to a port and physical address of the
+//! binding, e.g. a bridge with a root of trust behind it.
+//! Packets for EIDs covered by more than one route take the narrowest range.
+//!
+//! A route can have a backup next hop on another port, used while the link of the primary
+//! port is down or after [ROUTE_FAILOVER_THRESHOLD] consecutive send failures. The primary
+//! is tried again once its link comes back up or [ROUTE_FAILBACK_MILLIS] after the failover.
+
+use core::ops::RangeInclusive;
+
+use mctp::{Eid, Error, Result};
+
+/// Number of entries in the routing table of a [Router](crate::Router)
+pub const ROUTE_TABLE_SIZE: usize = 16;
+
+/// Consecutive send failures on the primary next hop of a route before its backup is used
+pub const ROUTE_FAILOVER_THRESHOLD: u8 = 3;
+
+/// Time after a failover at which the primary next hop of a route is tried again
+pub const ROUTE_FAILBACK_MILLIS: u64 = 10_000;
+
+/// A port and physical address packets are passed to, see [Route::backup]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NextHop {
+    /// Port of the binding the packets leave on
+    pub port: u8,
+    /// Binding-specific physical address of the next hop
+    pub physical_addr: u64,
+}
+
+/// A static route, see [add_route()](crate::GenericRouter::add_route)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Route {
+    /// First EID of the range
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_util::eid"))]
+    pub first: Eid,
+    /// Last EID of the range
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_util::eid"))]
+    pub last: Eid,
+    /// Port of the binding the packets leave on
+    pub port: u8,
+    /// Binding-specific physical address of the next hop (e.g. an SMBus address or PCIe BDF)
+    pub physical_addr: u64,
+    /// Next hop used while the primary one is unavailable
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub backup: Option<NextHop>,
+}
+
+impl Route {
+    /// Route `eids` to `physical_addr` on `port`
+    ///
+    /// Returns [BadArgument](Error::BadArgument) for an empty range.
+    pub fn new(eids: RangeInclusive<u8>, port: u8, physical_addr: u64) -> Result<Self> {
+        if eids.is_empty() {
+            return Err(Error::BadArgument);
+        }
+        Ok(Route {
+            first: Eid(*eids.start()),
+            last: Eid(*eids.end()),
+            port,
+            physical_addr,
+            backup: None,
+        })
+    }
+
+    /// Set the backup next hop of the route
+    pub fn with_backup(mut self, backup: Option<NextHop>) -> Self {
+        self.backup = backup;
+        self
+    }
+
+    /// Get the route along its backup next hop, without a backup itself
+    fn via_backup(&self) -> Option<Route> {
+        let backup = self.backup?;
+        Some(Route {
+            port: backup.port,
+            physical_addr: backup.physical_addr,
+            backup: None,
+            ..*self
+        })
+    }
+
+    /// Check if `eid` is covered by the route
+    pub fn contains(&self, eid: Eid) -> bool {
+        (self.first.0..=self.last.0).contains(&eid.0)
+    }
+
+    fn len(&self) -> u8 {
+        self.last.0 - self.first.0
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Route {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "Route {{ first: {=u8}, last: {=u8}, port: {=u8}, physical_addr: {=u64:#x}, backup: {} }}",
+            self.first.0,
+            self.last.0,
+            self.port,
+            self.physical_addr,
+            self.backup
+        );
+    }
+}
+
+/// The static routes of a router
+pub(crate) type Routes = [Option<Route>; ROUTE_TABLE_SIZE];
+
+/// Number of ports with their own [ForwardStats] counters
+pub const FORWARD_STATS_PORTS: usize = 8;
+
+/// Forwarding counters of a port, see [ForwardStats]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PortStats {
+    /// Packets forwarded
+    pub forwarded: usize,
+    /// Packets dropped instead of forwarded, e.g. too large or failed by the [Sender](crate::Sender)
+    pub dropped: usize,
+}
+
+/// Counters of the forwarding path of a bridge
+///
+/// Packets are counted on the port of the [Route] they are forwarded along. Packets for EIDs
+/// without a route are route misses, passed to [Sender::send_packet()](crate::Sender::send_packet)
+/// and counted as [unrouted](Self::unrouted).
+/// Ports from [FORWARD_STATS_PORTS] on are not counted per port.
+/// See [forward_stats()](crate::GenericRouter::forward_stats).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ForwardStats {
+    ports: [PortStats; FORWARD_STATS_PORTS],
+    /// Packets for EIDs without a route
+    pub unrouted: PortStats,
+    /// Packets forwarded along each slot of the routing table
+    route_hits: [usize; ROUTE_TABLE_SIZE],
+}
+
+impl ForwardStats {
+    /// Get the counters of `port`, `None` from [FORWARD_STATS_PORTS] on
+    pub fn port(&self, port: u8) -> Option<PortStats> {
+        self.ports.get(usize::from(port)).copied()
+    }
+
+    /// Get the number of packets for EIDs without a route
+    pub fn route_misses(&self) -> usize {
+        self.unrouted.forwarded.wrapping_add(self.unrouted.dropped)
+    }
+
+    /// Count a packet forwarded (or dropped) along the route in `slot`, `None` if unrouted
+    pub(crate) fn count(&mut self, route: Option<(usize, Route)>, forwarded: bool) {
+        let stats = match route {
+            Some((slot, route)) => {
+                if let Some(hits) = self.route_hits.get_mut(slot) {
+                    *hits = hits.wrapping_add(1);
+                }
+                self.ports.get_mut(usize::from(route.port))
+            }
+            None => Some(&mut self.unrouted),
+        };
+        if let Some(stats) = stats {
+            let counter = if forwarded {
+                &mut stats.forwarded
+            } else {
+                &mut stats.dropped
+            };
+            *counter = counter.wrapping_add(1);
+        }
+    }
+
+    /// Get the packets forwarded along the route in `slot`
+    pub(crate) fn route_hits(&self, slot: usize) -> usize {
+        self.route_hits.get(slot).copied().unwrap_or(0)
+    }
+
+    /// Reset the hit count of `slot` for a new route
+    pub(crate) fn reset_route(&mut self, slot: usize) {
+        if let Some(hits) = self.route_hits.get_mut(slot) {
+            *hits = 0;
+        }
+    }
+}
+
+/// Ports whose link is down, see [link_down()](crate::GenericRouter::link_down)
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct LinkStates {
+    down: [u32; 8],
+}
+
+impl LinkStates {
+    /// Mark the link of `port` up or down
+    ///
+    /// Returns whether the state changed.
+    pub(crate) fn set(&mut self, port: u8, up: bool) -> bool {
+        let was_up = self.is_up(port);
+        if let Some(word) = self.down.get_mut(usize::from(port / 32)) {
+            let bit = 1 << (port % 32);
+            if up {
+                *word &= !bit;
+            } else {
+                *word |= bit;
+            }
+        }
+        was_up != up
+    }
+
+    /// Check whether the link of `port` is up
+    pub(crate) fn is_up(&self, port: u8) -> bool {
+        self.down
+            .get(usize::from(port / 32))
+            .is_none_or(|word| word & 1 << (port % 32) == 0)
+    }
+
+    /// Check whether `route` leads to a port whose link is down
+    pub(crate) fn blocks(&self, route: Option<&Route>) -> bool {
+        route.is_some_and(|r| !self.is_up(r.port))
+    }
+}
+
+/// Health of the primary next hops of the routes, indexed by slot
+#[derive(Debug, Default)]
+pub(crate) struct Failover {
+    /// Consecutive send failures
+    failures: [u8; ROUTE_TABLE_SIZE],
+    /// Time of the last failure beyond the threshold
+    failed_millis: [Option<u64>; ROUTE_TABLE_SIZE],
+}
+
+impl Failover {
+    /// Account for a packet sent along the primary next hop of the route in `slot`
+    pub(crate) fn report(&mut self, slot: usize, ok: bool, now_millis: u64) {
+        let (Some(failures), Some(failed)) = (
+            self.failures.get_mut(slot),
+            self.failed_millis.get_mut(slot),
+        ) else {
+            return;
+        };
+        if ok {
+            (*failures, *failed) = (0, None);
+            return;
+        }
+        *failures = failures.saturating_add(1);
+        if *failures >= ROUTE_FAILOVER_THRESHOLD {
+            // Refreshed on each failure, so a failed retry of the primary fails over again
+            *failed = Some(now_millis);
+        }
+    }
+
+    /// Forget the health of `slot`, e.g. for a new route or when its link came up
+    pub(crate) fn reset(&mut self, slot: usize) {
+        self.report(slot, true, 0);
+    }
+
+    /// Check whether the route in `slot` is failed over to its backup
+    pub(crate) fn is_failed_over(&self, slot: usize, now_millis: u64) -> bool {
+        self.failed_millis
+            .get(slot)
+            .copied()
+            .flatten()
+            .is_some_and(|t| now_millis.saturating_sub(t) < ROUTE_FAILBACK_MILLIS)
+    }
+}
+
+/// The next hop chosen for a destination
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Hop {
+    /// Slot of the route in the routing table
+    pub(crate) slot: usize,
+    /// The route with the next hop in use
+    pub(crate) route: Route,
+    /// Whether the backup next hop is used
+    pub(crate) backup: bool,
+}
+
+/// Find the route for `eid` and choose its next hop
+///
+/// The backup is used while the primary port is down or failed over, unless its own port is
+/// down as well.
+pub(crate) fn select(
+    routes: &Routes,
+    failover: &Failover,
+    links: &LinkStates,
+    eid: Eid,
+    now_millis: u64,
+) -> Option<Hop> {
+    let (slot, route) = lookup_slot(routes, eid)?;
+    let primary_ok = links.is_up(route.port) && !failover.is_failed_over(slot, now_millis);
+    match route.via_backup() {
+        Some(backup) if !primary_ok && links.is_up(backup.port) => Some(Hop {
+            slot,
+            route: backup,
+            backup: true,
+        }),
+        _ => Some(Hop {
+            slot,
+            route,
+            backup: false,
+        }),
+    }
+}
+
+/// Add `route`, replacing a route for the same range
+///
+/// Returns the slot of the route, [NoSpace](Error::NoSpace) if the table is full.
+pub(crate) fn add(routes: &mut Routes, route: Route) -> Result<usize> {
+    let i = match routes
+        .iter()
+        .position(|r| r.is_some_and(|r| (r.first, r.last) == (route.first, route.last)))
+    {
+        Some(i) => i,
+        None => routes
+            .iter()
+            .position(|r| r.is_none())
+            .ok_or(Error::NoSpace)?,
+    };
+    *routes.get_mut(i).ok_or(Error::NoSpace)? = Some(route);
+    Ok(i)
+}
+
+/// Remove the route for exactly `eids`
+///
+/// Returns the slot of the route if there was one.
+pub(crate) fn remove(routes: &mut Routes, eids: RangeInclusive<u8>) -> Option<usize> {
+    let i = routes
+        .iter()
+        .position(|r| r.is_some_and(|r| (r.first.0, r.last.0) == (*eids.start(), *eids.end())))?;
+    routes.get_mut(i)?.take().map(|_| i)
+}
+
+/// Find the narrowest route covering `eid`
+pub(crate) fn lookup(routes: &Routes, eid: Eid) -> Option<Route> {
+    lookup_slot(routes, eid).map(|(_, route)| route)
+}
+
+/// Find the narrowest route covering `eid`, with its slot
+pub(crate) fn lookup_slot(routes: &Routes, eid: Eid) -> Option<(usize, Route)> {
+    routes
+        .iter()
+        .enumerate()
+        .filter_map(|(i, r)| r.filter(|r| r.contains(eid)).map(|r| (i, r)))
+        .min_by_key(|(_, r)| r.len())
+}
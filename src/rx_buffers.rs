@@ -0,0 +1,121 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Receive buffers lent to the transport
+//!
+//! The application gives packet buffers to the router with
+//! [add_rx_buffer()](crate::GenericRouter::add_rx_buffer). The transport borrows one with
+//! [lend_rx_buffer()](crate::GenericRouter::lend_rx_buffer), lets DMA deposit the next
+//! packet into it and passes it to [inbound_owned()](crate::GenericRouter::inbound_owned),
+//! which processes the packet in place and takes the buffer back.
+
+use mctp::{Error, Result};
+
+/// Number of buffers in the receive buffer pool of a [Router](crate::Router)
+pub const RX_BUFFER_POOL_SIZE: usize = 4;
+
+/// A packet buffer lent by a [Router](crate::Router)
+///
+/// The buffer has a fixed address until it is returned, so it can be handed to DMA.
+#[derive(Debug)]
+pub struct RxBuffer {
+    data: &'static mut [u8],
+    len: usize,
+}
+
+impl RxBuffer {
+    /// Get the whole buffer to receive a packet into
+    pub fn buf_mut(&mut self) -> &mut [u8] {
+        self.data
+    }
+
+    /// Get the size of the buffer
+    pub fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Set the length of the packet received into the buffer
+    ///
+    /// Returns [InvalidInput](Error::InvalidInput) if `len` exceeds the
+    /// [capacity](Self::capacity).
+    pub fn set_len(&mut self, len: usize) -> Result<()> {
+        if len > self.data.len() {
+            return Err(Error::InvalidInput);
+        }
+        self.len = len;
+        Ok(())
+    }
+
+    /// Get the length of the received packet
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if no packet was received into the buffer
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the received packet
+    pub fn packet(&self) -> &[u8] {
+        self.data.get(..self.len).unwrap_or_default()
+    }
+
+    /// Remove the buffer from the pool of the router
+    pub fn into_inner(self) -> &'static mut [u8] {
+        self.data
+    }
+}
+
+/// The buffers not lent at the moment
+#[derive(Debug)]
+pub(crate) struct RxPool {
+    slots: [Option<&'static mut [u8]>; RX_BUFFER_POOL_SIZE],
+}
+
+impl RxPool {
+    pub(crate) const fn new() -> Self {
+        RxPool {
+            slots: [const { None }; RX_BUFFER_POOL_SIZE],
+        }
+    }
+
+    /// Add `data` to the pool, `Err(data)` if the pool is full
+    pub(crate) fn add(
+        &mut self,
+        data: &'static mut [u8],
+    ) -> core::result::Result<(), &'static mut [u8]> {
+        match self.slots.iter_mut().find(|s| s.is_none()) {
+            Some(slot) => {
+                *slot = Some(data);
+                Ok(())
+            }
+            None => Err(data),
+        }
+    }
+
+    pub(crate) fn lend(&mut self) -> Option<RxBuffer> {
+        let data = self.slots.iter_mut().find_map(Option::take)?;
+        Some(RxBuffer { data, len: 0 })
+    }
+
+    pub(crate) fn give_back(&mut self, buf: RxBuffer) {
+        // The pool has room for every buffer that was lent from it
+        let _ = self.add(buf.into_inner());
+    }
+
+    pub(crate) fn available(&self) -> usize {
+        self.slots.iter().filter(|s| s.is_some()).count()
+    }
+}
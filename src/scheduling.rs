@@ -0,0 +1,88 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fair scheduling of receive processing and bus time among handles
+//!
+//! By default the router serves handles in table order: [poll_ready()] reports listeners
+//! before requests, and packets held back by [throttles](crate::Throttle) are released in the
+//! order they were sent. One chatty protocol can then starve the others, e.g. an event loop
+//! that only serves the first ready handle, or a [WorkBudget](crate::WorkBudget) used up by
+//! the held packets of one message. [RoundRobin](Scheduling::RoundRobin) rotates among the
+//! handles instead.
+//!
+//! [poll_ready()]: crate::GenericRouter::poll_ready
+
+use crate::Handle;
+
+/// How a [Router](crate::Router) shares receive processing and bus time among handles
+///
+/// See [set_scheduling()](crate::GenericRouter::set_scheduling).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Scheduling {
+    /// Handles are served in table order, held packets in the order they were sent
+    #[default]
+    InOrder,
+    /// Ready handles are reported least recently received from first, held packets are
+    /// released one per handle in turn
+    RoundRobin,
+}
+
+/// Scheduling policy and the receive order of handles
+#[derive(Debug)]
+pub(crate) struct Scheduler {
+    pub(crate) policy: Scheduling,
+    /// Number of messages received so far, stamps the handle each message is taken from
+    received: u64,
+}
+
+impl Scheduler {
+    pub(crate) const fn new(policy: Scheduling) -> Self {
+        Scheduler {
+            policy,
+            received: 0,
+        }
+    }
+
+    pub(crate) fn is_fair(&self) -> bool {
+        self.policy == Scheduling::RoundRobin
+    }
+
+    /// Get the stamp of a message received now, later ones get higher stamps
+    pub(crate) fn stamp(&mut self) -> u64 {
+        self.received = self.received.wrapping_add(1);
+        self.received
+    }
+}
+
+/// Order `ready` handles by the stamp of the message last received from them, oldest first
+///
+/// Handles with the same stamp (e.g. never received from) keep the order of `ready`.
+pub(crate) fn least_recent<I>(ready: I) -> impl Iterator<Item = Handle>
+where
+    I: Iterator<Item = (u64, Handle)> + Clone,
+{
+    let mut last: Option<(u64, usize)> = None;
+    core::iter::from_fn(move || {
+        let (key, handle) = ready
+            .clone()
+            .enumerate()
+            .map(|(i, (stamp, handle))| ((stamp, i), handle))
+            .filter(|(key, _)| last.is_none_or(|last| *key > last))
+            .min_by_key(|(key, _)| *key)?;
+        last = Some(key);
+        Some(handle)
+    })
+}
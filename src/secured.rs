@@ -0,0 +1,104 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Secured Messages using SPDM (DSP0276) over MCTP
+//!
+//! A secured message is sent with message type [MSG_TYPE_SECURED]. Its body starts with the
+//! little endian session ID, followed by the encrypted application message.
+//!
+//! The router keeps the table of open sessions and does the framing.
+//! Encryption and decryption are left to the application, which owns the SPDM session keys,
+//! through [Hooks::encrypt()](crate::Hooks::encrypt) and
+//! [Hooks::decrypt()](crate::Hooks::decrypt).
+//! Secured messages from sessions that are not open are dropped on receive.
+
+use mctp::{Eid, Error, MsgType, Result, Tag};
+
+/// MCTP message type of secured messages
+pub const MSG_TYPE_SECURED: MsgType = MsgType(0x06);
+
+/// Length of the session ID in front of a secured message
+pub const SESSION_ID_LEN: usize = 4;
+
+/// Number of sessions a [Router](crate::Router) can have open at once
+pub const SESSION_TABLE_SIZE: usize = 8;
+
+/// Metadata of a received and decrypted secured message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SecuredInfo {
+    /// Source EID
+    pub source: Eid,
+    /// Session the message was received on
+    pub session_id: u32,
+    /// Message tag
+    pub tag: Tag,
+    /// Message type of the decrypted application message
+    pub typ: MsgType,
+    /// Length of the decrypted application message payload
+    pub len: usize,
+}
+
+/// Open sessions, keyed by peer EID and session ID
+#[derive(Debug)]
+pub(crate) struct Sessions {
+    entries: [Option<(Eid, u32)>; SESSION_TABLE_SIZE],
+}
+
+impl Sessions {
+    pub(crate) const fn new() -> Self {
+        Sessions {
+            entries: [None; SESSION_TABLE_SIZE],
+        }
+    }
+
+    /// Returns [AddrInUse](Error::AddrInUse) if the session is already open,
+    /// [NoSpace](Error::NoSpace) if the table is full.
+    pub(crate) fn open(&mut self, eid: Eid, session_id: u32) -> Result<()> {
+        if self.contains(eid, session_id) {
+            return Err(Error::AddrInUse);
+        }
+        let slot = self
+            .entries
+            .iter_mut()
+            .find(|e| e.is_none())
+            .ok_or(Error::NoSpace)?;
+        *slot = Some((eid, session_id));
+        Ok(())
+    }
+
+    /// Returns `false` if the session was not open
+    pub(crate) fn close(&mut self, eid: Eid, session_id: u32) -> bool {
+        let slot = self
+            .entries
+            .iter_mut()
+            .find(|e| **e == Some((eid, session_id)));
+        match slot {
+            Some(slot) => {
+                *slot = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn contains(&self, eid: Eid, session_id: u32) -> bool {
+        self.entries.contains(&Some((eid, session_id)))
+    }
+}
+
+/// Split a secured message body into session ID and encrypted data
+pub(crate) fn split(body: &[u8]) -> Option<(u32, &[u8])> {
+    body.split_first_chunk::<SESSION_ID_LEN>()
+        .map(|(id, data)| (u32::from_le_bytes(*id), data))
+}
@@ -0,0 +1,56 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Loopback self-test of the send and receive paths
+//!
+//! [self_test()](crate::GenericRouter::self_test) fragments a message with a known pattern,
+//! feeds the packets back into the reassembly of the stack and compares the result, without
+//! involving the transport. Manufacturing and boot-time checks use it to validate the stack
+//! and its configured buffers.
+
+use mctp::MsgType;
+
+/// Longest message [self_test()](crate::GenericRouter::self_test) sends
+pub const SELF_TEST_MAX_LEN: usize = 1024;
+
+/// Message type of the self-test message, from the vendor defined range
+pub(crate) const SELF_TEST_TYPE: MsgType = MsgType(0x7e);
+
+/// Payload of the self-test message, `len` bytes of it are sent
+pub(crate) static PATTERN: [u8; SELF_TEST_MAX_LEN] = pattern();
+
+const fn pattern() -> [u8; SELF_TEST_MAX_LEN] {
+    let mut pattern = [0; SELF_TEST_MAX_LEN];
+    let mut rest: &mut [u8] = &mut pattern;
+    let mut i: usize = 0;
+    while let [byte, tail @ ..] = rest {
+        // Differs between neighbouring bytes and between blocks of 256 bytes
+        *byte = (i as u8) ^ ((i >> 8) as u8).wrapping_mul(0x5b) ^ 0xa5;
+        rest = tail;
+        i += 1;
+    }
+    pattern
+}
+
+/// Result of a successful [self_test()](crate::GenericRouter::self_test)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SelfTestReport {
+    /// Length of the message body
+    pub len: usize,
+    /// Packets the message was fragmented into
+    pub packets: usize,
+    /// Largest packet, including the MCTP header
+    pub mtu: usize,
+}
@@ -0,0 +1,201 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Arguments of a message to send, built step by step
+//!
+//! [SendArgs] replaces the positional parameters of [send()](crate::GenericRouter::send)
+//! for [send_msg()](crate::GenericRouter::send_msg). Whether a message is a request or a
+//! response is part of its type, so a response can't be sent without destination and tag
+//! and a request only goes through a [RequestHandle]:
+//!
+//! ```
+//! use mctp::{Eid, MsgIC, MsgType, TagValue};
+//! use mctp_lib::{Router, SendArgs};
+//! use mctp_lib::test_util::NullSender;
+//!
+//! let mut router: Router<_, 1, 1> = Router::new(Eid(8), 0, NullSender);
+//! let req = router.req(Eid(9)).unwrap();
+//! router.send_msg(req, SendArgs::request(MsgType(1)).payload(&[1, 2])).unwrap();
+//!
+//! let listener = router.listener(MsgType(1)).unwrap();
+//! let args = SendArgs::response(Eid(9), MsgType(1), TagValue(3))
+//!     .ic(MsgIC(true))
+//!     .payload_vectored(&[&[1], &[2]]);
+//! router.send_msg(listener, args).unwrap();
+//! ```
+
+use core::marker::PhantomData;
+
+use mctp::{Eid, MsgIC, MsgType, Tag, TagValue};
+
+use crate::{Handle, ListenerHandle, MessageInfo, RequestHandle};
+
+/// Whether [SendArgs] describe a [Request] or a [Response]
+pub trait SendKind {
+    /// The handle messages of this kind are sent with
+    type Handle: Into<Handle>;
+}
+
+/// Marker of [SendArgs] for a request, sent with a [RequestHandle]
+#[derive(Debug, Clone, Copy)]
+pub struct Request;
+
+impl SendKind for Request {
+    type Handle = RequestHandle;
+}
+
+/// Marker of [SendArgs] for a response, sent with a [ListenerHandle]
+#[derive(Debug, Clone, Copy)]
+pub struct Response;
+
+impl SendKind for Response {
+    type Handle = ListenerHandle;
+}
+
+/// The payload of a message, in one or more buffers
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Payload<'a> {
+    Single(&'a [u8]),
+    Vectored(&'a [&'a [u8]]),
+}
+
+/// Arguments of a message sent with [send_msg()](crate::GenericRouter::send_msg)
+///
+/// Created with [request()](SendArgs::request) or [response()](SendArgs::response),
+/// the integrity check flag and payload default to unset and empty.
+#[derive(Debug, Clone, Copy)]
+pub struct SendArgs<'a, K: SendKind> {
+    pub(crate) eid: Option<Eid>,
+    pub(crate) typ: MsgType,
+    pub(crate) tag: Option<Tag>,
+    pub(crate) ic: MsgIC,
+    pub(crate) source: Option<Eid>,
+    /// Destination of the request answered, sent from if still a local EID
+    pub(crate) request_dest: Option<Eid>,
+    pub(crate) deadline: Option<u64>,
+    pub(crate) payload: Payload<'a>,
+    kind: PhantomData<K>,
+}
+
+impl SendArgs<'_, Request> {
+    /// A request of type `typ` to the peer of the request handle
+    ///
+    /// A new tag is allocated when the message is sent.
+    pub fn request(typ: MsgType) -> Self {
+        SendArgs {
+            eid: None,
+            typ,
+            tag: None,
+            ic: MsgIC(false),
+            source: None,
+            request_dest: None,
+            deadline: None,
+            payload: Payload::Single(&[]),
+            kind: PhantomData,
+        }
+    }
+
+    /// Send to `eid` instead of the peer of the request handle
+    pub fn eid(mut self, eid: Eid) -> Self {
+        self.eid = Some(eid);
+        self
+    }
+
+    /// Send with the owned tag `tag` instead of allocating one
+    pub fn tag(mut self, tag: TagValue) -> Self {
+        self.tag = Some(Tag::Owned(tag));
+        self
+    }
+}
+
+impl SendArgs<'_, Response> {
+    /// A response of type `typ` to `eid`, for the request that arrived with tag `tag`
+    pub fn response(eid: Eid, typ: MsgType, tag: TagValue) -> Self {
+        SendArgs {
+            eid: Some(eid),
+            typ,
+            tag: Some(Tag::Unowned(tag)),
+            ic: MsgIC(false),
+            source: None,
+            request_dest: None,
+            deadline: None,
+            payload: Payload::Single(&[]),
+            kind: PhantomData,
+        }
+    }
+
+    /// The response to `request`, with its message type and integrity check flag
+    ///
+    /// Sent from the local EID the request was addressed to, or the own EID if it is no
+    /// longer local.
+    pub fn response_to(request: &MessageInfo) -> Self {
+        let mut args = SendArgs::response(request.source, request.typ, request.tag.tag());
+        args.request_dest = Some(request.dest);
+        args.ic(request.ic)
+    }
+}
+
+impl<'a, K: SendKind> SendArgs<'a, K> {
+    /// Set the integrity check flag, see [send_vectored()](crate::GenericRouter::send_vectored)
+    pub fn ic(mut self, ic: MsgIC) -> Self {
+        self.ic = ic;
+        self
+    }
+
+    /// Send from the local EID `source`
+    ///
+    /// Overrides the source EID of the handle, see
+    /// [set_source_eid()](crate::GenericRouter::set_source_eid).
+    pub fn source(mut self, source: Eid) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Drop the message unless it is sent completely before `deadline_millis`
+    ///
+    /// The deadline is a time of the [Clock](crate::Clock) of the router. Messages found
+    /// late before they are started, while retrying a busy receiver or while packets are
+    /// held back by a [Throttle](crate::Throttle) are dropped instead of sending stale data,
+    /// and reported to [message_expired()](crate::Hooks::message_expired) with
+    /// [DeadlinePassed](crate::ExpiryReason::DeadlinePassed), e.g. for periodic sensor
+    /// readings or heartbeats superseded by the next one.
+    pub fn deadline(mut self, deadline_millis: u64) -> Self {
+        self.deadline = Some(deadline_millis);
+        self
+    }
+
+    /// Send `buf` as payload
+    pub fn payload<'b>(self, buf: &'b [u8]) -> SendArgs<'b, K> {
+        self.with_payload(Payload::Single(buf))
+    }
+
+    /// Send the concatenation of `bufs` as payload
+    pub fn payload_vectored<'b>(self, bufs: &'b [&'b [u8]]) -> SendArgs<'b, K> {
+        self.with_payload(Payload::Vectored(bufs))
+    }
+
+    fn with_payload<'b>(self, payload: Payload<'b>) -> SendArgs<'b, K> {
+        SendArgs {
+            eid: self.eid,
+            typ: self.typ,
+            tag: self.tag,
+            ic: self.ic,
+            source: self.source,
+            request_dest: self.request_dest,
+            deadline: self.deadline,
+            payload,
+            kind: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `serde` representations of types the derives can't handle directly
+
+/// An [Eid](mctp::Eid) as its plain number
+pub(crate) mod eid {
+    use mctp::Eid;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(eid: &Eid, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(eid.0)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Eid, D::Error> {
+        u8::deserialize(deserializer).map(Eid)
+    }
+}
+
+/// An optional [Eid](mctp::Eid) as its plain number
+pub(crate) mod option_eid {
+    use mctp::Eid;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(
+        eid: &Option<Eid>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match eid {
+            Some(eid) => serializer.serialize_some(&eid.0),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Eid>, D::Error> {
+        Option::<u8>::deserialize(deserializer).map(|eid| eid.map(Eid))
+    }
+}
+
+/// A reorder window, checked against [MAX_REORDER_WINDOW](crate::MAX_REORDER_WINDOW)
+pub(crate) mod reorder_window {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer};
+
+    use crate::MAX_REORDER_WINDOW;
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+        let window = u8::deserialize(deserializer)?;
+        if window > MAX_REORDER_WINDOW {
+            return Err(D::Error::custom("reorder window too large"));
+        }
+        Ok(window)
+    }
+}
+
+/// An MTU table as a sequence of its entries, e.g. `[{ eid = 9, mtu = 68 }]` in TOML
+pub(crate) mod mtus {
+    use core::fmt;
+
+    use mctp::Eid;
+    use serde::de::{Error, SeqAccess, Visitor};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::MTU_TABLE_SIZE;
+
+    type Table = [Option<(Eid, usize, bool)>; MTU_TABLE_SIZE];
+
+    #[derive(Serialize, Deserialize)]
+    struct Entry {
+        #[serde(with = "super::eid")]
+        eid: Eid,
+        mtu: usize,
+        /// Learned by MTU discovery rather than configured
+        #[serde(default)]
+        learned: bool,
+    }
+
+    pub(crate) fn serialize<S: Serializer>(
+        table: &Table,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(table.iter().flatten().map(|&(eid, mtu, learned)| Entry {
+            eid,
+            mtu,
+            learned,
+        }))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Table, D::Error> {
+        deserializer.deserialize_seq(TableVisitor)
+    }
+
+    struct TableVisitor;
+
+    impl<'de> Visitor<'de> for TableVisitor {
+        type Value = Table;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "at most {MTU_TABLE_SIZE} MTU entries")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Table, A::Error> {
+            let mut table = [None; MTU_TABLE_SIZE];
+            let mut len = 0;
+            while let Some(entry) = seq.next_element::<Entry>()? {
+                if entry.mtu <= crate::header::HEADER_LEN {
+                    return Err(A::Error::custom(
+                        "MTU can't hold an MCTP header and payload",
+                    ));
+                }
+                let slot = table
+                    .get_mut(len)
+                    .ok_or_else(|| A::Error::invalid_length(len + 1, &self))?;
+                *slot = Some((entry.eid, entry.mtu, entry.learned));
+                len += 1;
+            }
+            Ok(table)
+        }
+    }
+}
+
+/// A routing table as a sequence of its routes, e.g.
+/// `[{ first = 20, last = 29, port = 0, physical_addr = 0x1d }]` in TOML
+pub(crate) mod routes {
+    use core::fmt;
+
+    use serde::de::{Error, SeqAccess, Visitor};
+    use serde::{Deserializer, Serializer};
+
+    use crate::routes::Routes;
+    use crate::{ROUTE_TABLE_SIZE, Route};
+
+    pub(crate) fn serialize<S: Serializer>(
+        routes: &Routes,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(routes.iter().flatten())
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Routes, D::Error> {
+        deserializer.deserialize_seq(RoutesVisitor)
+    }
+
+    struct RoutesVisitor;
+
+    impl<'de> Visitor<'de> for RoutesVisitor {
+        type Value = Routes;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "at most {ROUTE_TABLE_SIZE} routes")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Routes, A::Error> {
+            let mut routes = [None; ROUTE_TABLE_SIZE];
+            let mut len = 0;
+            while let Some(route) = seq.next_element::<Route>()? {
+                if route.first.0 > route.last.0 {
+                    return Err(A::Error::custom("route with an empty EID range"));
+                }
+                let slot = routes
+                    .get_mut(len)
+                    .ok_or_else(|| A::Error::invalid_length(len + 1, &self))?;
+                *slot = Some(route);
+                len += 1;
+            }
+            Ok(routes)
+        }
+    }
+}
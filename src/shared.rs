@@ -0,0 +1,472 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A shared router core with per-handle channels
+//!
+//! A [SharedRouter] wraps a [GenericRouter] in a [RefCell], so the transport driver and
+//! any number of [SharedListener]s and [SharedRequest]s can use it through shared references.
+//! Each operation borrows the core only for its duration.
+//!
+//! The core is not [Sync]; all users have to run in the same thread or on the same executor
+//! (e.g. different tasks of a single-core async executor).
+//! Operations that find the core already borrowed (e.g. when called from a [Hooks] callback)
+//! fail with [InternalError](Error::InternalError).
+//!
+//! The `async` receive methods wait on
+//! [GenericRouter::poll_recv()], so they work with any executor: the task is woken when
+//! the transport passes the message to [inbound()](SharedRouter::inbound).
+//! They are cancellation safe: a message is only taken when the future completes with it,
+//! and a dropped future releases its waker, so `select!` and timeouts lose nothing.
+
+use core::cell::{RefCell, RefMut};
+use core::future::poll_fn;
+use core::task::{Context, Poll, Waker};
+
+use mctp::{Eid, Error, MsgIC, MsgType, Result};
+
+use crate::erased::DynCore;
+use crate::{
+    BatchReport, Clock, GenericRouter, Handle, HandleTables, Hooks, ListenerHandle, MessageInfo,
+    MessagePool, PooledMessage, RecvHalf, RequestHandle, RouterError, RouterResult, SendHalf,
+    SendReport, Sender,
+};
+
+/// A [GenericRouter] that can be shared by the transport and per-handle channels
+#[derive(Debug)]
+pub struct SharedRouter<S: Sender, T: HandleTables, H: Hooks, C: Clock> {
+    core: RefCell<GenericRouter<S, T, H, C>>,
+}
+
+impl<S: Sender, T: HandleTables, H: Hooks, C: Clock> SharedRouter<S, T, H, C> {
+    /// Share `router`
+    pub fn new(router: GenericRouter<S, T, H, C>) -> Self {
+        SharedRouter {
+            core: RefCell::new(router),
+        }
+    }
+
+    /// Get the wrapped router back
+    pub fn into_inner(self) -> GenericRouter<S, T, H, C> {
+        self.core.into_inner()
+    }
+
+    /// Get the router for [type-erased channels](crate::erased)
+    pub fn as_dyn(&self) -> &DynCore<'_> {
+        &self.core
+    }
+
+    /// Run `f` with exclusive access to the router
+    ///
+    /// Returns [InternalError](Error::InternalError) if the router is already borrowed.
+    pub fn with<R>(&self, f: impl FnOnce(&mut GenericRouter<S, T, H, C>) -> R) -> Result<R> {
+        Ok(f(&mut *self.lock()?))
+    }
+
+    fn lock(&self) -> Result<RefMut<'_, GenericRouter<S, T, H, C>>> {
+        self.core.try_borrow_mut().map_err(|_| Error::InternalError)
+    }
+
+    /// Provide an incoming packet, see [GenericRouter::inbound()]
+    pub fn inbound(&self, pkt: &[u8]) -> Result<Option<Handle>> {
+        self.lock()?.inbound(pkt)
+    }
+
+    /// Provide an incoming frame with binding header, see [GenericRouter::inbound_frame()]
+    pub fn inbound_frame(&self, frame: &[u8]) -> Result<Option<Handle>> {
+        self.lock()?.inbound_frame(frame)
+    }
+
+    /// Provide a burst of incoming packets, see [GenericRouter::inbound_batch()]
+    pub fn inbound_batch<'p>(
+        &self,
+        pkts: impl IntoIterator<Item = &'p [u8]>,
+    ) -> Result<BatchReport> {
+        Ok(self.lock()?.inbound_batch(pkts))
+    }
+
+    /// Provide an incoming packet split across `parts`, see [GenericRouter::inbound_vectored()]
+    pub fn inbound_vectored(&self, parts: &[&[u8]]) -> Result<Option<Handle>> {
+        self.lock()?.inbound_vectored(parts)
+    }
+
+    /// Update the stack, see [GenericRouter::poll()]
+    pub fn poll(&self) -> Result<u64> {
+        self.lock()?.poll()
+    }
+
+    /// Bind a listener for `typ` and get a channel for it
+    pub fn listener(&self, typ: MsgType) -> Result<SharedListener<'_, S, T, H, C>> {
+        let handle = self.lock()?.listener(typ)?;
+        Ok(SharedListener {
+            router: self,
+            handle,
+        })
+    }
+
+    /// Bind the catch-all listener and get a channel for it, see
+    /// [GenericRouter::catch_all_listener()]
+    pub fn catch_all_listener(&self) -> Result<SharedListener<'_, S, T, H, C>> {
+        let handle = self.lock()?.catch_all_listener()?;
+        Ok(SharedListener {
+            router: self,
+            handle,
+        })
+    }
+
+    /// Allocate a request to `eid` and get a channel for it
+    pub fn req(&self, eid: Eid) -> Result<SharedRequest<'_, S, T, H, C>> {
+        let handle = self.lock()?.req(eid)?;
+        Ok(SharedRequest {
+            router: self,
+            handle,
+        })
+    }
+
+    fn try_recv(&self, handle: Handle, buf: &mut [u8]) -> RouterResult<Option<MessageInfo>> {
+        let mut sink = buf;
+        self.lock()
+            .map_err(|e| RouterError::from(e).with_handle(handle))?
+            .recv_into(handle, &mut sink)
+    }
+
+    fn poll_recv(
+        &self,
+        handle: Handle,
+        buf: &mut [u8],
+        cx: &mut Context<'_>,
+    ) -> Poll<RouterResult<MessageInfo>> {
+        match self.lock() {
+            Ok(mut core) => core.poll_recv(handle, buf, cx),
+            Err(e) => Poll::Ready(Err(RouterError::from(e).with_handle(handle))),
+        }
+    }
+
+    /// Wait for a message for `handle` and receive it into `buf`
+    async fn recv(&self, handle: Handle, buf: &mut [u8]) -> RouterResult<MessageInfo> {
+        let mut guard = WakerGuard {
+            router: self,
+            handle,
+            send_complete: false,
+            waker: None,
+        };
+        poll_fn(|cx| {
+            let poll = self.poll_recv(handle, buf, cx);
+            guard.waker = poll.is_pending().then(|| cx.waker().clone());
+            poll
+        })
+        .await
+    }
+
+    /// Wait until the last message of the request `handle` left the router
+    async fn send_complete(&self, handle: RequestHandle) -> RouterResult<()> {
+        let mut guard = WakerGuard {
+            router: self,
+            handle: handle.into(),
+            send_complete: true,
+            waker: None,
+        };
+        poll_fn(|cx| {
+            let poll = match self.lock() {
+                Ok(mut core) => core.poll_send_complete(handle, cx),
+                Err(e) => Poll::Ready(Err(RouterError::from(e).with_handle(handle.into()))),
+            };
+            guard.waker = poll.is_pending().then(|| cx.waker().clone());
+            poll
+        })
+        .await
+    }
+
+    fn try_recv_pooled<'p, const N: usize, const SIZE: usize>(
+        &self,
+        handle: Handle,
+        pool: &'p MessagePool<N, SIZE>,
+    ) -> RouterResult<Option<PooledMessage<'p, SIZE>>> {
+        self.lock()
+            .map_err(|e| RouterError::from(e).with_handle(handle))?
+            .recv_pooled(handle, pool)
+    }
+}
+
+/// A channel for a listener bound on a [SharedRouter]
+///
+/// Copies refer to the same listener, it stays bound until [unbind()](Self::unbind) is called.
+#[derive(Debug)]
+pub struct SharedListener<'r, S: Sender, T: HandleTables, H: Hooks, C: Clock> {
+    router: &'r SharedRouter<S, T, H, C>,
+    handle: ListenerHandle,
+}
+
+impl<S: Sender, T: HandleTables, H: Hooks, C: Clock> Clone for SharedListener<'_, S, T, H, C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S: Sender, T: HandleTables, H: Hooks, C: Clock> Copy for SharedListener<'_, S, T, H, C> {}
+
+impl<S: Sender, T: HandleTables, H: Hooks, C: Clock> SharedListener<'_, S, T, H, C> {
+    /// Get the handle of the listener
+    pub fn handle(&self) -> ListenerHandle {
+        self.handle
+    }
+
+    /// Receive a request into `buf` without blocking
+    ///
+    /// Returns `Ok(None)` when no request is available.
+    /// The payload is stored in `buf[..info.len]`.
+    pub fn try_recv(&self, buf: &mut [u8]) -> RouterResult<Option<MessageInfo>> {
+        self.router.try_recv(self.handle.into(), buf)
+    }
+
+    /// Wait for a request and receive it into `buf`
+    ///
+    /// The payload is stored in `buf[..info.len]`. A request larger than `buf` stays queued
+    /// and is reported as [NoSpace](Error::NoSpace).
+    pub async fn recv(&self, buf: &mut [u8]) -> RouterResult<MessageInfo> {
+        self.router.recv(self.handle.into(), buf).await
+    }
+
+    /// Receive a request into a buffer of `pool` without blocking
+    ///
+    /// See [recv_pooled()](GenericRouter::recv_pooled).
+    pub fn try_recv_pooled<'p, const N: usize, const SIZE: usize>(
+        &self,
+        pool: &'p MessagePool<N, SIZE>,
+    ) -> RouterResult<Option<PooledMessage<'p, SIZE>>> {
+        self.router.try_recv_pooled(self.handle.into(), pool)
+    }
+
+    /// Respond to a request received with [try_recv()](Self::try_recv)
+    ///
+    /// Sends with the integrity check flag `ic`, see [GenericRouter::respond()].
+    pub fn respond(&self, request: &MessageInfo, ic: MsgIC, bufs: &[&[u8]]) -> RouterResult<()> {
+        let request = MessageInfo { ic, ..*request };
+        self.router
+            .lock()
+            .map_err(|e| RouterError::from(e).with_handle(self.handle.into()))?
+            .respond(self.handle, &request, bufs)
+            .map(|_| ())
+    }
+
+    /// Unbind the listener
+    pub fn unbind(self) -> RouterResult<()> {
+        self.router
+            .lock()
+            .map_err(|e| RouterError::from(e).with_handle(self.handle.into()))?
+            .unbind(self.handle)
+    }
+}
+
+/// A channel for a request allocated on a [SharedRouter]
+///
+/// Copies refer to the same request, it stays allocated until [unbind()](Self::unbind) is called.
+#[derive(Debug)]
+pub struct SharedRequest<'r, S: Sender, T: HandleTables, H: Hooks, C: Clock> {
+    router: &'r SharedRouter<S, T, H, C>,
+    handle: RequestHandle,
+}
+
+impl<S: Sender, T: HandleTables, H: Hooks, C: Clock> Clone for SharedRequest<'_, S, T, H, C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S: Sender, T: HandleTables, H: Hooks, C: Clock> Copy for SharedRequest<'_, S, T, H, C> {}
+
+impl<'r, S: Sender, T: HandleTables, H: Hooks, C: Clock> SharedRequest<'r, S, T, H, C> {
+    /// Get the handle of the request
+    pub fn handle(&self) -> RequestHandle {
+        self.handle
+    }
+
+    /// Send a request message, allocating a new tag
+    pub fn send(&self, typ: MsgType, ic: MsgIC, bufs: &[&[u8]]) -> RouterResult<SendReport> {
+        self.router
+            .lock()
+            .map_err(|e| RouterError::from(e).with_handle(self.handle.into()))?
+            .send_vectored(None, typ, None, ic, self.handle, bufs)
+    }
+
+    /// Receive a response into `buf` without blocking
+    ///
+    /// Returns `Ok(None)` when no response is available.
+    /// The payload is stored in `buf[..info.len]`.
+    pub fn try_recv(&self, buf: &mut [u8]) -> RouterResult<Option<MessageInfo>> {
+        self.router.try_recv(self.handle.into(), buf)
+    }
+
+    /// Wait for a response and receive it into `buf`
+    ///
+    /// The payload is stored in `buf[..info.len]`. A response larger than `buf` stays queued
+    /// and is reported as [NoSpace](Error::NoSpace). Waits forever if no response arrives,
+    /// combine it with a timer of the executor to bound the wait.
+    pub async fn recv(&self, buf: &mut [u8]) -> RouterResult<MessageInfo> {
+        self.router.recv(self.handle.into(), buf).await
+    }
+
+    /// Wait until the last request message left the router, see
+    /// [poll_send_complete()](GenericRouter::poll_send_complete)
+    pub async fn send_complete(&self) -> RouterResult<()> {
+        self.router.send_complete(self.handle).await
+    }
+
+    /// Receive a response into a buffer of `pool` without blocking
+    ///
+    /// See [recv_pooled()](GenericRouter::recv_pooled).
+    pub fn try_recv_pooled<'p, const N: usize, const SIZE: usize>(
+        &self,
+        pool: &'p MessagePool<N, SIZE>,
+    ) -> RouterResult<Option<PooledMessage<'p, SIZE>>> {
+        self.router.try_recv_pooled(self.handle.into(), pool)
+    }
+
+    /// Clear the tag state of the request, see [reset()](GenericRouter::reset)
+    pub fn reset(&self) -> RouterResult<()> {
+        self.router
+            .lock()
+            .map_err(|e| RouterError::from(e).with_handle(self.handle.into()))?
+            .reset(self.handle)
+    }
+
+    /// Send further requests to `eid`, see [retarget()](GenericRouter::retarget)
+    pub fn retarget(&self, eid: Eid) -> RouterResult<()> {
+        self.router
+            .lock()
+            .map_err(|e| RouterError::from(e).with_handle(self.handle.into()))?
+            .retarget(self.handle, eid)
+    }
+
+    /// Release the request
+    pub fn unbind(self) -> RouterResult<()> {
+        self.router
+            .lock()
+            .map_err(|e| RouterError::from(e).with_handle(self.handle.into()))?
+            .unbind(self.handle)
+    }
+
+    /// Split the request into a sending and a receiving channel
+    ///
+    /// See [split()](GenericRouter::split), copies of the request can't be used afterwards.
+    #[allow(clippy::type_complexity)]
+    pub fn split(
+        self,
+    ) -> RouterResult<(
+        SharedSendHalf<'r, S, T, H, C>,
+        SharedRecvHalf<'r, S, T, H, C>,
+    )> {
+        let (send, recv) = self
+            .router
+            .lock()
+            .map_err(|e| RouterError::from(e).with_handle(self.handle.into()))?
+            .split(self.handle)?;
+        Ok((
+            SharedSendHalf {
+                router: self.router,
+                half: send,
+            },
+            SharedRecvHalf {
+                router: self.router,
+                half: recv,
+            },
+        ))
+    }
+}
+
+/// The sending channel of a split [SharedRequest]
+#[derive(Debug)]
+pub struct SharedSendHalf<'r, S: Sender, T: HandleTables, H: Hooks, C: Clock> {
+    router: &'r SharedRouter<S, T, H, C>,
+    half: SendHalf,
+}
+
+impl<S: Sender, T: HandleTables, H: Hooks, C: Clock> SharedSendHalf<'_, S, T, H, C> {
+    /// Get the handle of the request
+    pub fn handle(&self) -> RequestHandle {
+        self.half.handle()
+    }
+
+    /// Send a request message, allocating a new tag
+    pub fn send(&self, typ: MsgType, ic: MsgIC, bufs: &[&[u8]]) -> RouterResult<SendReport> {
+        self.router
+            .lock()
+            .map_err(|e| RouterError::from(e).with_handle(self.handle().into()))?
+            .send_half(&self.half, typ, ic, bufs)
+    }
+
+    /// Release the sending half, see [release_send_half()](GenericRouter::release_send_half)
+    pub fn release(self) -> RouterResult<()> {
+        self.router
+            .lock()
+            .map_err(|e| RouterError::from(e).with_handle(self.handle().into()))?
+            .release_send_half(self.half)
+    }
+}
+
+/// The receiving channel of a split [SharedRequest]
+#[derive(Debug)]
+pub struct SharedRecvHalf<'r, S: Sender, T: HandleTables, H: Hooks, C: Clock> {
+    router: &'r SharedRouter<S, T, H, C>,
+    half: RecvHalf,
+}
+
+impl<S: Sender, T: HandleTables, H: Hooks, C: Clock> SharedRecvHalf<'_, S, T, H, C> {
+    /// Get the handle of the request
+    pub fn handle(&self) -> RequestHandle {
+        self.half.handle()
+    }
+
+    /// Receive a response into `buf` without blocking
+    ///
+    /// Returns `Ok(None)` when no response is available.
+    /// The payload is stored in `buf[..info.len]`.
+    pub fn try_recv(&self, buf: &mut [u8]) -> RouterResult<Option<MessageInfo>> {
+        let mut sink = buf;
+        self.router
+            .lock()
+            .map_err(|e| RouterError::from(e).with_handle(self.handle().into()))?
+            .recv_half_into(&self.half, &mut sink)
+    }
+
+    /// Release the receiving half, see [release_recv_half()](GenericRouter::release_recv_half)
+    pub fn release(self) -> RouterResult<()> {
+        self.router
+            .lock()
+            .map_err(|e| RouterError::from(e).with_handle(self.handle().into()))?
+            .release_recv_half(self.half)
+    }
+}
+
+/// Releases the waker stored by a pending future of a [SharedRouter] when it is dropped
+struct WakerGuard<'r, S: Sender, T: HandleTables, H: Hooks, C: Clock> {
+    router: &'r SharedRouter<S, T, H, C>,
+    handle: Handle,
+    /// Waiting for a send to complete rather than for a message
+    send_complete: bool,
+    /// Waker stored by the last poll, if it was pending
+    waker: Option<Waker>,
+}
+
+impl<S: Sender, T: HandleTables, H: Hooks, C: Clock> Drop for WakerGuard<'_, S, T, H, C> {
+    fn drop(&mut self) {
+        let Some(waker) = self.waker.take() else {
+            return;
+        };
+        // A borrowed core can't be helped, the waker is then released when it is woken.
+        let _ = self.router.with(|core| match self.handle {
+            Handle::Request(h) if self.send_complete => core.cancel_poll_send_complete(h, &waker),
+            handle => core.cancel_poll_recv(handle, &waker),
+        });
+    }
+}
@@ -0,0 +1,43 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tearing down a [Router](crate::Router), see
+//! [shutdown()](crate::GenericRouter::shutdown)
+
+/// What happens to the packets a [Throttle](crate::Throttle) holds back when the router
+/// shuts down
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ShutdownPolicy {
+    /// Pass the held packets to the [Sender](crate::Sender) regardless of the throttle,
+    /// packets it fails to send are dropped
+    Flush,
+    /// Drop the held packets, e.g. when the transport is known to be broken
+    #[default]
+    Drop,
+}
+
+/// Outcome of [shutdown()](crate::GenericRouter::shutdown)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ShutdownReport {
+    /// Requests unbound while waiting for a response
+    pub cancelled: usize,
+    /// Listeners and requests unbound
+    pub unbound: usize,
+    /// Held packets passed to the [Sender](crate::Sender)
+    pub flushed: usize,
+    /// Held packets dropped, including those the [Sender](crate::Sender) failed to send
+    pub dropped: usize,
+}
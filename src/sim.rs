@@ -0,0 +1,329 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! In-memory simulation of routers sharing a bus
+//!
+//! A [Simulation] wires any number of routers together through a simulated link with
+//! configurable loss, duplication, reordering and latency ([LinkConfig]).
+//! All randomness comes from a seeded generator and time only advances in
+//! [step()](Simulation::step), so runs are deterministic and suited for CI tests of timeouts,
+//! retries and bridging.
+//!
+//! Packets are delivered to the router whose EID matches the destination passed to
+//! [Sender::send_packet()], unless a route set with [route()](Simulation::route)
+//! directs them elsewhere (e.g. to a bridge).
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::vec::Vec;
+
+use mctp::{Eid, Error, Result};
+
+use crate::{Disposition, GenericRouter, HandleTables, Hooks, ManualClock, Sender};
+
+/// Largest MTU of a simulated link, see [LinkConfig::mtu]
+pub const MAX_MTU: usize = 512;
+
+/// Packets sent by a router, with the EID passed to [Sender::send_packet()]
+type Outbox = Rc<RefCell<VecDeque<(Eid, Vec<u8>)>>>;
+
+/// Index of a router in a [Simulation]
+pub type NodeId = usize;
+
+/// Impairments of the simulated link
+///
+/// Probabilities are given in percent and apply to each packet independently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct LinkConfig {
+    /// MTU reported to the routers, packets are capped at [MAX_MTU]
+    pub mtu: usize,
+    /// Delay of every packet in milliseconds
+    pub latency_millis: u64,
+    /// Probability of a packet being lost
+    pub loss_percent: u8,
+    /// Probability of a packet being delivered twice
+    pub duplicate_percent: u8,
+    /// Probability of a packet being delayed past packets sent after it
+    pub reorder_percent: u8,
+}
+
+impl Default for LinkConfig {
+    /// A perfect link with a 64 byte MTU and no latency
+    fn default() -> Self {
+        LinkConfig {
+            mtu: 64,
+            latency_millis: 0,
+            loss_percent: 0,
+            duplicate_percent: 0,
+            reorder_percent: 0,
+        }
+    }
+}
+
+/// Counters of a [Simulation]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SimStats {
+    /// Packets sent by the routers
+    pub sent: usize,
+    /// Packets passed to a router
+    pub delivered: usize,
+    /// Packets lost on the link
+    pub lost: usize,
+    /// Additional copies of duplicated packets
+    pub duplicated: usize,
+    /// Packets delayed for reordering
+    pub reordered: usize,
+    /// Packets without a router for their destination
+    pub unroutable: usize,
+}
+
+/// The [Sender] of a router attached to a [Simulation]
+#[derive(Debug, Clone)]
+pub struct SimPort {
+    outbox: Outbox,
+    mtu: usize,
+}
+
+impl Sender for SimPort {
+    type Packet = [u8; MAX_MTU];
+
+    fn send_packet(&mut self, eid: Eid, pkt: &[u8]) -> Result<()> {
+        self.outbox
+            .try_borrow_mut()
+            .map_err(|_| Error::InternalError)?
+            .push_back((eid, pkt.to_vec()));
+        Ok(())
+    }
+
+    fn get_mtu(&self) -> usize {
+        self.mtu
+    }
+}
+
+/// A router attached to a [Simulation]
+pub type SimRouter<T, H> = GenericRouter<SimPort, T, H, ManualClock>;
+
+#[derive(Debug)]
+struct InFlight {
+    deliver_millis: u64,
+    /// Send order, keeps delivery stable for equal times
+    seq: u64,
+    to: NodeId,
+    pkt: Vec<u8>,
+}
+
+/// Routers connected by a simulated link
+#[derive(Debug)]
+pub struct Simulation<T: HandleTables, H: Hooks> {
+    link: LinkConfig,
+    rng: u64,
+    now_millis: u64,
+    seq: u64,
+    nodes: Vec<(SimRouter<T, H>, Outbox)>,
+    routes: Vec<(NodeId, Eid, NodeId)>,
+    in_flight: Vec<InFlight>,
+    dispositions: Vec<(NodeId, Disposition)>,
+    stats: SimStats,
+}
+
+impl<T: HandleTables, H: Hooks> Simulation<T, H> {
+    /// Create an empty simulation of `link`, seeding the random generator with `seed`
+    pub fn new(link: LinkConfig, seed: u64) -> Self {
+        Simulation {
+            link,
+            // xorshift must not start at 0
+            rng: seed | 1,
+            now_millis: 0,
+            seq: 0,
+            nodes: Vec::new(),
+            routes: Vec::new(),
+            in_flight: Vec::new(),
+            dispositions: Vec::new(),
+            stats: SimStats::default(),
+        }
+    }
+
+    /// Attach a router built by `build` from the port it has to send through
+    ///
+    /// The router has to use a [ManualClock] starting at 0.
+    pub fn add(&mut self, build: impl FnOnce(SimPort) -> SimRouter<T, H>) -> NodeId {
+        let outbox = Rc::new(RefCell::new(VecDeque::new()));
+        let port = SimPort {
+            outbox: outbox.clone(),
+            mtu: self.link.mtu,
+        };
+        let mut router = build(port);
+        let _ = router.update(self.now_millis);
+        self.nodes.push((router, outbox));
+        self.nodes.len() - 1
+    }
+
+    /// Deliver packets sent by `from` to `eid` to the router `to` instead
+    pub fn route(&mut self, from: NodeId, eid: Eid, to: NodeId) {
+        self.routes.retain(|r| (r.0, r.1) != (from, eid));
+        self.routes.push((from, eid, to));
+    }
+
+    /// Get the router `node`
+    pub fn router(&self, node: NodeId) -> Option<&SimRouter<T, H>> {
+        self.nodes.get(node).map(|n| &n.0)
+    }
+
+    /// Get the router `node` mutably
+    pub fn router_mut(&mut self, node: NodeId) -> Option<&mut SimRouter<T, H>> {
+        self.nodes.get_mut(node).map(|n| &mut n.0)
+    }
+
+    /// Get the current simulated time
+    pub fn now_millis(&self) -> u64 {
+        self.now_millis
+    }
+
+    /// Get the counters of the simulation
+    pub fn stats(&self) -> SimStats {
+        self.stats
+    }
+
+    /// Take the dispositions of all packets delivered so far, with the receiving router
+    pub fn take_dispositions(&mut self) -> Vec<(NodeId, Disposition)> {
+        core::mem::take(&mut self.dispositions)
+    }
+
+    /// Check if no packet is waiting to be sent or delivered
+    pub fn is_idle(&self) -> bool {
+        self.in_flight.is_empty()
+            && self
+                .nodes
+                .iter()
+                .all(|n| n.1.try_borrow().is_ok_and(|o| o.is_empty()))
+    }
+
+    /// Advance the time by `millis`
+    ///
+    /// Puts packets sent by the routers onto the link, delivers all packets due
+    /// (including packets sent in response while delivering) and updates the routers.
+    pub fn step(&mut self, millis: u64) {
+        self.now_millis = self.now_millis.saturating_add(millis);
+        for (router, _) in self.nodes.iter_mut() {
+            let _ = router.update(self.now_millis);
+        }
+        loop {
+            self.collect();
+            let Some(i) = self
+                .in_flight
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| p.deliver_millis <= self.now_millis)
+                .min_by_key(|(_, p)| (p.deliver_millis, p.seq))
+                .map(|(i, _)| i)
+            else {
+                break;
+            };
+            let pkt = self.in_flight.swap_remove(i);
+            if let Some((router, _)) = self.nodes.get_mut(pkt.to) {
+                let disposition = router.inbound_disposition(&pkt.pkt);
+                self.dispositions.push((pkt.to, disposition));
+                self.stats.delivered += 1;
+            }
+        }
+    }
+
+    /// Step by `millis` until the simulation is idle, at most `max_steps` times
+    ///
+    /// Returns whether the simulation became idle.
+    pub fn run_until_idle(&mut self, millis: u64, max_steps: usize) -> bool {
+        for _ in 0..max_steps {
+            self.step(millis);
+            if self.is_idle() {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Move packets from the outboxes onto the link
+    fn collect(&mut self) {
+        for from in 0..self.nodes.len() {
+            loop {
+                let next = self
+                    .nodes
+                    .get(from)
+                    .and_then(|n| n.1.try_borrow_mut().ok()?.pop_front());
+                let Some((eid, pkt)) = next else {
+                    break;
+                };
+                self.stats.sent += 1;
+                self.transmit(from, eid, pkt);
+            }
+        }
+    }
+
+    fn transmit(&mut self, from: NodeId, eid: Eid, pkt: Vec<u8>) {
+        let to = self
+            .routes
+            .iter()
+            .find(|r| (r.0, r.1) == (from, eid))
+            .map(|r| r.2)
+            .or_else(|| self.nodes.iter().position(|n| n.0.get_eid() == eid));
+        let Some(to) = to else {
+            self.stats.unroutable += 1;
+            return;
+        };
+        if self.chance(self.link.loss_percent) {
+            self.stats.lost += 1;
+            return;
+        }
+        let copies = if self.chance(self.link.duplicate_percent) {
+            self.stats.duplicated += 1;
+            2
+        } else {
+            1
+        };
+        for _ in 0..copies {
+            let mut deliver_millis = self.now_millis.saturating_add(self.link.latency_millis);
+            if self.chance(self.link.reorder_percent) {
+                self.stats.reordered += 1;
+                let delay = self.next_random() % self.link.latency_millis.saturating_add(1);
+                deliver_millis = deliver_millis.saturating_add(delay).saturating_add(1);
+            }
+            self.seq += 1;
+            self.in_flight.push(InFlight {
+                deliver_millis,
+                seq: self.seq,
+                to,
+                pkt: pkt.clone(),
+            });
+        }
+    }
+
+    fn chance(&mut self, percent: u8) -> bool {
+        percent > 0 && self.next_random() % 100 < u64::from(percent)
+    }
+
+    /// xorshift64
+    fn next_random(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tracking of inbound messages for listeners with a maximum message size
+//!
+//! The size of a message is only known once it is reassembled. To reject oversized
+//! messages early, the payload of the packets is summed up while they arrive.
+
+use mctp::{Eid, Tag};
+use mctp_estack::AppCookie;
+use mctp_estack::config::NUM_RECEIVE;
+
+use crate::REASSEMBLY_TIMEOUT_MILLIS;
+use crate::header::{HEADER_LEN, Header};
+
+/// A message being reassembled for a size limited listener
+#[derive(Debug, Clone, Copy)]
+struct Flow {
+    source: Eid,
+    tag: Tag,
+    /// Payload received so far, excluding the message type
+    len: usize,
+    limit: usize,
+    /// Cookie of the listener the message is for
+    listener: AppCookie,
+    /// The message exceeded the limit, its remaining packets are dropped
+    aborted: bool,
+    /// Time of the last packet passed on to the stack
+    last_millis: u64,
+}
+
+/// Result of checking a packet against the size limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SizeCheck {
+    /// The packet does not exceed a limit
+    Accept,
+    /// The first packet already exceeds the limit of `listener`, nothing was reassembled yet
+    Reject {
+        /// Cookie of the listener the message is for
+        listener: AppCookie,
+    },
+    /// The message exceeds the limit
+    ///
+    /// The stack can't cancel a reassembly, so the remaining packets are dropped with
+    /// [Discard](SizeCheck::Discard) and the stack releases the context once the
+    /// reassembly times out.
+    Abort {
+        /// Cookie of the listener the message is for
+        listener: AppCookie,
+    },
+    /// The packet continues a message that was aborted
+    Discard,
+}
+
+/// Size limited messages currently being reassembled
+///
+/// Holds at most as many messages as the stack can reassemble.
+#[derive(Debug)]
+pub(crate) struct SizeTracker {
+    flows: [Option<Flow>; NUM_RECEIVE],
+}
+
+impl SizeTracker {
+    pub(crate) const fn new() -> Self {
+        SizeTracker {
+            flows: [None; NUM_RECEIVE],
+        }
+    }
+
+    /// Start tracking a message whose first packet is `pkt`
+    ///
+    /// `limit` is the maximum message size and the cookie of the listener for the message
+    /// type, messages without a limit are not tracked.
+    pub(crate) fn start(
+        &mut self,
+        hdr: &Header,
+        pkt: &[u8],
+        limit: Option<(usize, AppCookie)>,
+        now_millis: u64,
+    ) -> SizeCheck {
+        // A new message replaces a previous one on the same flow.
+        self.remove(hdr.source, hdr.tag);
+        let Some((limit, listener)) = limit else {
+            return SizeCheck::Accept;
+        };
+        // The first packet carries the message type in front of the payload.
+        let len = pkt.len().saturating_sub(HEADER_LEN + 1);
+        if len > limit {
+            return SizeCheck::Reject { listener };
+        }
+        if !hdr.eom
+            && let Some(slot) = self.flows.iter_mut().find(|f| f.is_none())
+        {
+            *slot = Some(Flow {
+                source: hdr.source,
+                tag: hdr.tag,
+                len,
+                limit,
+                listener,
+                aborted: false,
+                last_millis: now_millis,
+            });
+        }
+        SizeCheck::Accept
+    }
+
+    /// Account for a continuation packet `pkt`
+    pub(crate) fn next(&mut self, hdr: &Header, pkt: &[u8], now_millis: u64) -> SizeCheck {
+        let Some(slot) = self
+            .flows
+            .iter_mut()
+            .find(|f| f.is_some_and(|f| f.source == hdr.source && f.tag == hdr.tag))
+        else {
+            return SizeCheck::Accept;
+        };
+        let Some(flow) = slot.as_mut() else {
+            return SizeCheck::Accept;
+        };
+        let check = if flow.aborted {
+            SizeCheck::Discard
+        } else {
+            flow.len = flow
+                .len
+                .saturating_add(pkt.len().saturating_sub(HEADER_LEN));
+            if flow.len > flow.limit {
+                flow.aborted = true;
+                SizeCheck::Abort {
+                    listener: flow.listener,
+                }
+            } else {
+                flow.last_millis = now_millis;
+                SizeCheck::Accept
+            }
+        };
+        if hdr.eom {
+            *slot = None;
+        }
+        check
+    }
+
+    /// Stop tracking messages the stack discarded for lack of progress
+    pub(crate) fn expire(&mut self, now_millis: u64) {
+        for slot in self.flows.iter_mut() {
+            if slot.is_some_and(|f| {
+                now_millis.saturating_sub(f.last_millis) >= REASSEMBLY_TIMEOUT_MILLIS
+            }) {
+                *slot = None;
+            }
+        }
+    }
+
+    fn remove(&mut self, source: Eid, tag: Tag) {
+        for slot in self.flows.iter_mut() {
+            if slot.is_some_and(|f| f.source == source && f.tag == tag) {
+                *slot = None;
+            }
+        }
+    }
+}
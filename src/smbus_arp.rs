@@ -0,0 +1,371 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! SMBus Address Resolution Protocol for dynamically addressed SMBus endpoints
+//!
+//! MCTP over SMBus needs a slave address before the endpoint can take part in MCTP.
+//! Devices without a fixed address obtain one from the ARP master, identified by their
+//! unique device identifier ([Udid]).
+//!
+//! [ArpDevice] implements the device side. The SMBus binding passes it the transactions
+//! addressed to [ARP_ADDRESS]: writes to [write()](ArpDevice::write), reads to
+//! [read()](ArpDevice::read), and uses [address()](ArpDevice::address) as its slave address
+//! once assigned. All ARP transactions carry a PEC, which is checked and generated here
+//! unless the SMBus controller does it in hardware (see
+//! [set_pec_offload()](ArpDevice::set_pec_offload)).
+//! Bus arbitration during a general Get UDID is up to the binding: a device that loses
+//! arbitration stops sending and keeps waiting for its address.
+
+use mctp::{Error, Result};
+
+/// The SMBus Device Default Address the ARP commands are sent to (7 bit)
+pub const ARP_ADDRESS: u8 = 0x61;
+
+/// Length of a [Udid]
+pub const UDID_LEN: usize = 16;
+
+/// Length of a Get UDID response: byte count, UDID, device address and PEC
+pub const GET_UDID_RESPONSE_LEN: usize = UDID_LEN + 3;
+
+const CMD_PREPARE_TO_ARP: u8 = 0x01;
+const CMD_RESET_DEVICE: u8 = 0x02;
+const CMD_GET_UDID: u8 = 0x03;
+const CMD_ASSIGN_ADDRESS: u8 = 0x04;
+
+/// Byte count of the Get UDID and Assign Address blocks
+const UDID_BLOCK_LEN: u8 = UDID_LEN as u8 + 1;
+
+/// Device address byte reported while the device has no valid address
+const NO_ADDRESS: u8 = 0xff;
+
+const CAP_ADDRESS_TYPE_SHIFT: u8 = 6;
+const CAP_PEC: u8 = 0x01;
+
+/// How a device obtains its slave address, bits 7:6 of the device capabilities
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AddressType {
+    /// The address is fixed and reported, but never changed by ARP
+    Fixed,
+    /// The assigned address is kept across a Reset Device command
+    DynamicPersistent,
+    /// The assigned address is lost on a Reset Device command
+    DynamicVolatile,
+    /// Like [DynamicVolatile](Self::DynamicVolatile), with a random UDID
+    Random,
+}
+
+/// Unique device identifier of an SMBus device, in the order sent on the bus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Udid(pub [u8; UDID_LEN]);
+
+impl Udid {
+    /// Build a UDID with PEC support from its fields
+    pub fn new(
+        address_type: AddressType,
+        vendor_id: u16,
+        device_id: u16,
+        interface: u16,
+        vendor_specific_id: u32,
+    ) -> Self {
+        let mut udid = [0; UDID_LEN];
+        let caps = (address_type as u8) << CAP_ADDRESS_TYPE_SHIFT | CAP_PEC;
+        // Version 1 (SMBus 2.0), revision 0
+        let version = 0x08;
+        let fields = [
+            &[caps, version][..],
+            &vendor_id.to_be_bytes(),
+            &device_id.to_be_bytes(),
+            &interface.to_be_bytes(),
+            &[0; 4],
+            &vendor_specific_id.to_be_bytes(),
+        ];
+        let mut pos = 0;
+        for field in fields {
+            if let Some(dst) = udid.get_mut(pos..pos + field.len()) {
+                dst.copy_from_slice(field);
+            }
+            pos += field.len();
+        }
+        Udid(udid)
+    }
+
+    /// Get the address type from the device capabilities
+    pub fn address_type(&self) -> AddressType {
+        match self.0.first().map_or(0, |c| c >> CAP_ADDRESS_TYPE_SHIFT) {
+            0 => AddressType::Fixed,
+            1 => AddressType::DynamicPersistent,
+            2 => AddressType::DynamicVolatile,
+            _ => AddressType::Random,
+        }
+    }
+}
+
+/// A change of the ARP state caused by a write to [ArpDevice::write()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ArpEvent {
+    /// The ARP master starts resolving addresses, the device answers general Get UDID again
+    Prepared,
+    /// The device was reset, [address()](ArpDevice::address) tells whether it kept its
+    /// address
+    Reset,
+    /// The device was assigned this slave address (7 bit)
+    Assigned(u8),
+}
+
+/// Device side of the SMBus ARP
+#[derive(Debug, Clone)]
+pub struct ArpDevice {
+    udid: Udid,
+    address: Option<u8>,
+    /// Address Resolved flag, set once the ARP master assigned the address
+    resolved: bool,
+    /// The controller checks and generates the PEC
+    pec_offload: bool,
+}
+
+impl ArpDevice {
+    /// Create a device identified by `udid`
+    ///
+    /// `address` is the fixed address, or a persistent address assigned earlier. Devices
+    /// with an address start out resolved, ARP masters find them after a Prepare to ARP.
+    pub const fn new(udid: Udid, address: Option<u8>) -> Self {
+        ArpDevice {
+            udid,
+            address,
+            resolved: address.is_some(),
+            pec_offload: false,
+        }
+    }
+
+    /// Set whether the SMBus controller checks and generates the PEC in hardware
+    ///
+    /// With offload, transactions passed to [write()](Self::write) end before the PEC and
+    /// responses of [read()](Self::read) leave it out, one byte short of
+    /// [GET_UDID_RESPONSE_LEN]. Disabled by default.
+    pub fn set_pec_offload(&mut self, offload: bool) {
+        self.pec_offload = offload;
+    }
+
+    /// Get the UDID of the device
+    pub fn udid(&self) -> &Udid {
+        &self.udid
+    }
+
+    /// Get the slave address (7 bit), if the device has a valid one
+    pub fn address(&self) -> Option<u8> {
+        self.address
+    }
+
+    /// Check if the ARP master resolved the address of the device
+    pub fn is_resolved(&self) -> bool {
+        self.resolved
+    }
+
+    /// Handle a write transaction to [ARP_ADDRESS]
+    ///
+    /// `data` is everything following the address byte: command code, data and PEC (unless
+    /// [offloaded](Self::set_pec_offload)).
+    /// Returns the resulting event, or `None` for commands not addressed to the device.
+    /// Returns [InvalidInput](Error::InvalidInput) for malformed transactions or a PEC
+    /// mismatch, the binding should NACK them.
+    pub fn write(&mut self, data: &[u8]) -> Result<Option<ArpEvent>> {
+        if !self.pec_offload && pec(&[&[ARP_ADDRESS << 1], data]) != 0 {
+            return Err(Error::InvalidInput);
+        }
+        let (&command, rest) = data.split_first().ok_or(Error::InvalidInput)?;
+        let body = match self.pec_offload {
+            true => rest,
+            false => rest.split_last().map_or(rest, |(_, body)| body),
+        };
+        match (command, body) {
+            (CMD_PREPARE_TO_ARP, []) => {
+                self.resolved = false;
+                Ok(Some(ArpEvent::Prepared))
+            }
+            (CMD_RESET_DEVICE, []) => Ok(Some(self.reset())),
+            (CMD_ASSIGN_ADDRESS, [UDID_BLOCK_LEN, block @ ..]) => {
+                let (udid, [address]) = block
+                    .split_first_chunk::<UDID_LEN>()
+                    .ok_or(Error::InvalidInput)?
+                else {
+                    return Err(Error::InvalidInput);
+                };
+                if *udid != self.udid.0 {
+                    return Ok(None);
+                }
+                if self.udid.address_type() != AddressType::Fixed {
+                    self.address = Some(address >> 1);
+                }
+                self.resolved = true;
+                Ok(self.address.map(ArpEvent::Assigned))
+            }
+            (c, []) if c & 1 == 0 && Some(c >> 1) == self.address && c > CMD_ASSIGN_ADDRESS => {
+                Ok(Some(self.reset()))
+            }
+            (CMD_PREPARE_TO_ARP | CMD_RESET_DEVICE | CMD_ASSIGN_ADDRESS, _) => {
+                Err(Error::InvalidInput)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Handle a block read from [ARP_ADDRESS] with command code `command`
+    ///
+    /// Answers a general Get UDID while the address is not resolved and a directed Get UDID
+    /// for the address of the device. The response of [GET_UDID_RESPONSE_LEN] bytes is
+    /// written to `out`, returns its length or `None` if the device does not respond.
+    /// Returns [NoSpace](Error::NoSpace) if `out` is too small.
+    pub fn read(&mut self, command: u8, out: &mut [u8]) -> Result<Option<usize>> {
+        let respond = match command {
+            CMD_GET_UDID => !self.resolved,
+            c => c & 1 == 1 && c > CMD_ASSIGN_ADDRESS && Some(c >> 1) == self.address,
+        };
+        if !respond {
+            return Ok(None);
+        }
+        let out = out.get_mut(..GET_UDID_RESPONSE_LEN).ok_or(Error::NoSpace)?;
+        let Some((pec_byte, block)) = out.split_last_mut() else {
+            return Err(Error::NoSpace);
+        };
+        let Some(([count], rest)) = block.split_first_chunk_mut::<1>() else {
+            return Err(Error::NoSpace);
+        };
+        *count = UDID_BLOCK_LEN;
+        let Some((udid, [address])) = rest.split_first_chunk_mut::<UDID_LEN>() else {
+            return Err(Error::NoSpace);
+        };
+        *udid = self.udid.0;
+        *address = self.address.map_or(NO_ADDRESS, |a| a << 1 | 1);
+        if self.pec_offload {
+            return Ok(Some(GET_UDID_RESPONSE_LEN - 1));
+        }
+        *pec_byte = pec(&[&[ARP_ADDRESS << 1, command, ARP_ADDRESS << 1 | 1], block]);
+        Ok(Some(GET_UDID_RESPONSE_LEN))
+    }
+
+    fn reset(&mut self) -> ArpEvent {
+        self.resolved = false;
+        if matches!(
+            self.udid.address_type(),
+            AddressType::DynamicVolatile | AddressType::Random
+        ) {
+            self.address = None;
+        }
+        ArpEvent::Reset
+    }
+}
+
+/// Compute the SMBus Packet Error Code (CRC-8, polynomial 0x07) of `parts`
+///
+/// The PEC covers the whole transaction including the address bytes. The PEC of a
+/// transaction followed by its PEC is 0.
+pub fn pec(parts: &[&[u8]]) -> u8 {
+    let mut crc = 0u8;
+    for b in parts.iter().flat_map(|p| p.iter()) {
+        crc ^= b;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_value() {
+        assert_eq!(pec(&[b"1234", b"56789"]), 0xf4);
+    }
+
+    /// Write `command` and `body` to `device`, appending the PEC
+    fn write(device: &mut ArpDevice, command: u8, body: &[u8]) -> Result<Option<ArpEvent>> {
+        let mut data = [0; 2 + UDID_LEN + 2];
+        let len = body.len() + 2;
+        let (check, msg) = data.get_mut(..len).unwrap().split_last_mut().unwrap();
+        let (first, rest) = msg.split_first_mut().unwrap();
+        *first = command;
+        rest.copy_from_slice(body);
+        *check = pec(&[&[ARP_ADDRESS << 1], msg]);
+        device.write(data.get(..len).unwrap())
+    }
+
+    #[test]
+    fn assign_address() {
+        let udid = Udid::new(AddressType::DynamicVolatile, 0x1af4, 0x0001, 0x0004, 7);
+        let mut device = ArpDevice::new(udid, None);
+        assert_eq!(udid.address_type(), AddressType::DynamicVolatile);
+
+        let mut out = [0; GET_UDID_RESPONSE_LEN];
+        assert_eq!(
+            device.read(CMD_GET_UDID, &mut out).unwrap(),
+            Some(out.len())
+        );
+        assert_eq!(out.get(1..=UDID_LEN).unwrap(), udid.0);
+        assert_eq!(out.get(UDID_LEN + 1), Some(&NO_ADDRESS));
+        let mut transaction = [0; GET_UDID_RESPONSE_LEN + 3];
+        transaction[..3].copy_from_slice(&[0xc2, CMD_GET_UDID, 0xc3]);
+        transaction[3..].copy_from_slice(&out);
+        assert_eq!(pec(&[&transaction]), 0);
+
+        let mut other = udid.0;
+        other[15] = 8;
+        let mut block = [0; UDID_LEN + 2];
+        block[0] = UDID_BLOCK_LEN;
+        block[1..=UDID_LEN].copy_from_slice(&other);
+        block[UDID_LEN + 1] = 0x20 << 1;
+        assert_eq!(
+            write(&mut device, CMD_ASSIGN_ADDRESS, &block).unwrap(),
+            None
+        );
+        block[1..=UDID_LEN].copy_from_slice(&udid.0);
+        assert_eq!(
+            write(&mut device, CMD_ASSIGN_ADDRESS, &block).unwrap(),
+            Some(ArpEvent::Assigned(0x20))
+        );
+        assert_eq!(device.address(), Some(0x20));
+        assert!(device.is_resolved());
+        assert_eq!(device.read(CMD_GET_UDID, &mut out).unwrap(), None);
+        assert!(device.read(0x20 << 1 | 1, &mut out).unwrap().is_some());
+        assert_eq!(out.get(UDID_LEN + 1), Some(&(0x20 << 1 | 1)));
+
+        let mut corrupt = [CMD_PREPARE_TO_ARP, 0];
+        assert!(device.write(&corrupt).is_err());
+        corrupt[1] = pec(&[&[ARP_ADDRESS << 1, CMD_PREPARE_TO_ARP]]);
+        assert_eq!(device.write(&corrupt).unwrap(), Some(ArpEvent::Prepared));
+        assert!(!device.is_resolved());
+        assert_eq!(write(&mut device, 0x21 << 1, &[]).unwrap(), None);
+        assert_eq!(
+            write(&mut device, 0x20 << 1, &[]).unwrap(),
+            Some(ArpEvent::Reset)
+        );
+        assert_eq!(device.address(), None);
+
+        let fixed = Udid::new(AddressType::Fixed, 0x1af4, 2, 4, 0);
+        let mut device = ArpDevice::new(fixed, Some(0x30));
+        assert_eq!(
+            write(&mut device, CMD_RESET_DEVICE, &[]).unwrap(),
+            Some(ArpEvent::Reset)
+        );
+        assert_eq!(device.address(), Some(0x30));
+    }
+}
@@ -0,0 +1,255 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Snapshots of router state surviving a warm reboot
+
+use mctp::{Eid, Error, Result};
+
+use crate::routes::Routes;
+use crate::{MTU_TABLE_SIZE, NextHop, ROUTE_TABLE_SIZE, Route};
+
+/// Version of the serialized snapshot format
+///
+/// Version 1 lacked the static EID and the routes, it is still accepted by
+/// [from_bytes()](RouterSnapshot::from_bytes).
+const FORMAT_VERSION: u8 = 2;
+
+const FLAG_UUID: u8 = 0x01;
+const FLAG_NETWORK_ID: u8 = 0x02;
+const FLAG_LEARNED: u8 = 0x01;
+const FLAG_BACKUP: u8 = 0x01;
+
+/// Length of a serialized MTU table entry
+const MTU_ENTRY_LEN: usize = 4;
+
+/// Length of a serialized next hop
+const HOP_LEN: usize = 9;
+
+/// Maximum length of a serialized route, with a backup next hop
+const ROUTE_ENTRY_LEN: usize = 3 + HOP_LEN + HOP_LEN;
+
+/// Maximum length of a serialized [RouterSnapshot]
+pub const SNAPSHOT_MAX_LEN: usize =
+    4 + 16 + 16 + 1 + MTU_TABLE_SIZE * MTU_ENTRY_LEN + 1 + ROUTE_TABLE_SIZE * ROUTE_ENTRY_LEN;
+
+/// The state of a [Router](crate::Router) worth keeping across a warm reboot
+///
+/// Taken with [snapshot()](crate::GenericRouter::snapshot) and restored with
+/// [RouterConfig::restore()](crate::RouterConfig::restore): the EID assigned by the bus
+/// owner and the static EID, the UUID and network ID, the MTU table, including learned
+/// entries, and the static routes with their backup next hops.
+/// Transient state (handles, reassembly, tags) is not included, neither are the link states
+/// and failover health of the routes, which the binding reports again after the reboot.
+///
+/// [to_bytes()](Self::to_bytes) and [from_bytes()](Self::from_bytes) convert to and from a
+/// versioned binary format for storage in retained memory or flash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RouterSnapshot {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_util::eid"))]
+    pub(crate) own_eid: Eid,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_util::eid"))]
+    pub(crate) static_eid: Eid,
+    pub(crate) uuid: Option<[u8; 16]>,
+    pub(crate) network_id: Option<[u8; 16]>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_util::mtus"))]
+    pub(crate) mtus: [Option<(Eid, usize, bool)>; MTU_TABLE_SIZE],
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_util::routes"))]
+    pub(crate) routes: Routes,
+}
+
+impl RouterSnapshot {
+    /// Get the EID of the endpoint
+    pub fn own_eid(&self) -> Eid {
+        self.own_eid
+    }
+
+    /// Get the static EID, restored by a Set Endpoint ID reset
+    pub fn static_eid(&self) -> Eid {
+        self.static_eid
+    }
+
+    /// Get the static routes
+    pub fn routes(&self) -> impl Iterator<Item = Route> + '_ {
+        self.routes.iter().flatten().copied()
+    }
+
+    /// Get the UUID of the endpoint, if configured
+    pub fn uuid(&self) -> Option<[u8; 16]> {
+        self.uuid
+    }
+
+    /// Get the network ID, if configured
+    pub fn network_id(&self) -> Option<[u8; 16]> {
+        self.network_id
+    }
+
+    /// Serialize the snapshot to `out`
+    ///
+    /// Returns the number of bytes written, at most [SNAPSHOT_MAX_LEN].
+    /// Returns [NoSpace](Error::NoSpace) if `out` is too small.
+    pub fn to_bytes(&self, out: &mut [u8]) -> Result<usize> {
+        let mut flags = 0;
+        if self.uuid.is_some() {
+            flags |= FLAG_UUID;
+        }
+        if self.network_id.is_some() {
+            flags |= FLAG_NETWORK_ID;
+        }
+        let mut w = Writer { out, pos: 0 };
+        w.put(&[FORMAT_VERSION, self.own_eid.0, self.static_eid.0, flags])?;
+        if let Some(uuid) = &self.uuid {
+            w.put(uuid)?;
+        }
+        if let Some(network_id) = &self.network_id {
+            w.put(network_id)?;
+        }
+        let count = self.mtus.iter().flatten().count();
+        w.put(&[count as u8])?;
+        for &(eid, mtu, learned) in self.mtus.iter().flatten() {
+            let [mtu0, mtu1] = u16::try_from(mtu)
+                .map_err(|_| Error::BadArgument)?
+                .to_le_bytes();
+            let flags = if learned { FLAG_LEARNED } else { 0 };
+            w.put(&[eid.0, mtu0, mtu1, flags])?;
+        }
+        let count = self.routes.iter().flatten().count();
+        w.put(&[count as u8])?;
+        for route in self.routes.iter().flatten() {
+            let flags = if route.backup.is_some() {
+                FLAG_BACKUP
+            } else {
+                0
+            };
+            w.put(&[route.first.0, route.last.0, flags])?;
+            w.put_hop(route.port, route.physical_addr)?;
+            if let Some(backup) = &route.backup {
+                w.put_hop(backup.port, backup.physical_addr)?;
+            }
+        }
+        Ok(w.pos)
+    }
+
+    /// Parse a snapshot serialized by [to_bytes()](Self::to_bytes)
+    ///
+    /// Returns [InvalidInput](Error::InvalidInput) for truncated or malformed data and
+    /// unknown format versions. Snapshots of version 1 restore the current EID as the static
+    /// one and no routes.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let mut r = Reader { data };
+        let [version, eid] = r.take()?;
+        let static_eid = match version {
+            1 => eid,
+            FORMAT_VERSION => {
+                let [static_eid] = r.take()?;
+                static_eid
+            }
+            _ => return Err(Error::InvalidInput),
+        };
+        let [flags] = r.take()?;
+        let uuid = if flags & FLAG_UUID != 0 {
+            Some(r.take()?)
+        } else {
+            None
+        };
+        let network_id = if flags & FLAG_NETWORK_ID != 0 {
+            Some(r.take()?)
+        } else {
+            None
+        };
+        let [count] = r.take()?;
+        if usize::from(count) > MTU_TABLE_SIZE {
+            return Err(Error::InvalidInput);
+        }
+        let mut mtus = [None; MTU_TABLE_SIZE];
+        for slot in mtus.iter_mut().take(count.into()) {
+            let [eid, mtu0, mtu1, flags] = r.take()?;
+            let mtu = u16::from_le_bytes([mtu0, mtu1]).into();
+            *slot = Some((Eid(eid), mtu, flags & FLAG_LEARNED != 0));
+        }
+        let mut routes = [None; ROUTE_TABLE_SIZE];
+        if version == FORMAT_VERSION {
+            let [count] = r.take()?;
+            if usize::from(count) > ROUTE_TABLE_SIZE {
+                return Err(Error::InvalidInput);
+            }
+            for slot in routes.iter_mut().take(count.into()) {
+                let [first, last, flags] = r.take()?;
+                let (port, physical_addr) = r.take_hop()?;
+                let backup = if flags & FLAG_BACKUP != 0 {
+                    let (port, physical_addr) = r.take_hop()?;
+                    Some(NextHop {
+                        port,
+                        physical_addr,
+                    })
+                } else {
+                    None
+                };
+                let route = Route::new(first..=last, port, physical_addr)
+                    .map_err(|_| Error::InvalidInput)?;
+                *slot = Some(route.with_backup(backup));
+            }
+        }
+        Ok(RouterSnapshot {
+            own_eid: Eid(eid),
+            static_eid: Eid(static_eid),
+            uuid,
+            network_id,
+            mtus,
+            routes,
+        })
+    }
+}
+
+struct Writer<'a> {
+    out: &'a mut [u8],
+    pos: usize,
+}
+
+impl Writer<'_> {
+    fn put(&mut self, data: &[u8]) -> Result<()> {
+        let end = self.pos + data.len();
+        self.out
+            .get_mut(self.pos..end)
+            .ok_or(Error::NoSpace)?
+            .copy_from_slice(data);
+        self.pos = end;
+        Ok(())
+    }
+
+    fn put_hop(&mut self, port: u8, physical_addr: u64) -> Result<()> {
+        self.put(&[port])?;
+        self.put(&physical_addr.to_le_bytes())
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let (chunk, rest) = self
+            .data
+            .split_first_chunk::<N>()
+            .ok_or(Error::InvalidInput)?;
+        self.data = rest;
+        Ok(*chunk)
+    }
+
+    fn take_hop(&mut self) -> Result<(u8, u64)> {
+        let [port] = self.take()?;
+        Ok((port, u64::from_le_bytes(self.take()?)))
+    }
+}
@@ -0,0 +1,177 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! SPDM over MCTP (DSP0275) transport adapter
+//!
+//! SPDM stacks in the style of spdm-rs split the transport into two parts:
+//! - an encapsulation, adding the SPDM-over-MCTP header (the MCTP message type byte) to
+//!   SPDM messages, see [encap()] and [decap()]
+//! - a device IO, sending and receiving the encapsulated "transport messages",
+//!   see [SpdmRequester] and [SpdmResponder]
+//!
+//! Both device IO types work on the channels of a [SharedRouter](crate::shared::SharedRouter)
+//! and map the message type byte of a transport message onto the MCTP message type.
+
+use mctp::{Error, MsgIC, MsgType, Result};
+
+use crate::secured::MSG_TYPE_SECURED;
+use crate::shared::{SharedListener, SharedRequest};
+use crate::{Clock, HandleTables, Hooks, MessageInfo, Sender};
+
+/// MCTP message type of SPDM messages
+pub const MSG_TYPE_SPDM: MsgType = MsgType(0x05);
+
+/// Length of the SPDM-over-MCTP header
+pub const SPDM_HEADER_LEN: usize = 1;
+
+/// Add the SPDM-over-MCTP header to `spdm`
+///
+/// `secured` selects the message type of secured messages over the plain SPDM type.
+/// Returns the length of the transport message written to `transport`.
+pub fn encap(spdm: &[u8], transport: &mut [u8], secured: bool) -> Result<usize> {
+    let typ = if secured {
+        MSG_TYPE_SECURED
+    } else {
+        MSG_TYPE_SPDM
+    };
+    let (header, body) = transport.split_first_mut().ok_or(Error::NoSpace)?;
+    *header = typ.0;
+    body.get_mut(..spdm.len())
+        .ok_or(Error::NoSpace)?
+        .copy_from_slice(spdm);
+    Ok(SPDM_HEADER_LEN + spdm.len())
+}
+
+/// Strip the SPDM-over-MCTP header from `transport`
+///
+/// Returns the length of the message written to `spdm`
+/// and whether it is a secured message.
+pub fn decap(transport: &[u8], spdm: &mut [u8]) -> Result<(usize, bool)> {
+    let (typ, body) = transport.split_first().ok_or(Error::InvalidInput)?;
+    let secured = match MsgType(*typ & 0x7f) {
+        MSG_TYPE_SPDM => false,
+        MSG_TYPE_SECURED => true,
+        _ => return Err(Error::InvalidInput),
+    };
+    spdm.get_mut(..body.len())
+        .ok_or(Error::NoSpace)?
+        .copy_from_slice(body);
+    Ok((body.len(), secured))
+}
+
+/// Split a transport message into message type and payload
+fn split_transport(transport: &[u8]) -> Result<(MsgType, &[u8])> {
+    let (typ, body) = transport.split_first().ok_or(Error::InvalidInput)?;
+    match MsgType(*typ & 0x7f) {
+        typ @ (MSG_TYPE_SPDM | MSG_TYPE_SECURED) => Ok((typ, body)),
+        _ => Err(Error::InvalidInput),
+    }
+}
+
+/// Receive a message into a transport buffer, prepending the message type
+fn recv_transport(
+    buf: &mut [u8],
+    recv: impl FnOnce(&mut [u8]) -> Result<Option<MessageInfo>>,
+) -> Result<Option<(MessageInfo, usize)>> {
+    let (header, body) = buf.split_first_mut().ok_or(Error::NoSpace)?;
+    let Some(info) = recv(body)? else {
+        return Ok(None);
+    };
+    *header = info.typ.0;
+    Ok(Some((info, SPDM_HEADER_LEN + info.len)))
+}
+
+/// Device IO of an SPDM requester, sending requests to a single responder
+#[derive(Debug)]
+pub struct SpdmRequester<'r, S: Sender, T: HandleTables, H: Hooks, C: Clock> {
+    channel: SharedRequest<'r, S, T, H, C>,
+}
+
+impl<'r, S: Sender, T: HandleTables, H: Hooks, C: Clock> SpdmRequester<'r, S, T, H, C> {
+    /// Use `channel` to talk to the responder
+    pub fn new(channel: SharedRequest<'r, S, T, H, C>) -> Self {
+        SpdmRequester { channel }
+    }
+
+    /// Send a transport message produced by [encap()]
+    pub fn send(&mut self, transport: &[u8]) -> Result<()> {
+        let (typ, body) = split_transport(transport)?;
+        self.channel.send(typ, MsgIC(false), &[body])?;
+        Ok(())
+    }
+
+    /// Receive a response as transport message into `buf` without blocking
+    ///
+    /// Returns the length of the transport message, `Ok(None)` when no response is available.
+    pub fn receive(&mut self, buf: &mut [u8]) -> Result<Option<usize>> {
+        let received = recv_transport(buf, |body| Ok(self.channel.try_recv(body)?))?;
+        Ok(received.map(|(_, len)| len))
+    }
+}
+
+/// Device IO of an SPDM responder
+///
+/// Responses are sent to the source of the last received request.
+#[derive(Debug)]
+pub struct SpdmResponder<'r, S: Sender, T: HandleTables, H: Hooks, C: Clock> {
+    spdm: SharedListener<'r, S, T, H, C>,
+    secured: Option<SharedListener<'r, S, T, H, C>>,
+    request: Option<MessageInfo>,
+}
+
+impl<'r, S: Sender, T: HandleTables, H: Hooks, C: Clock> SpdmResponder<'r, S, T, H, C> {
+    /// Receive requests on the listeners for [MSG_TYPE_SPDM] and,
+    /// if given, [MSG_TYPE_SECURED]
+    pub fn new(
+        spdm: SharedListener<'r, S, T, H, C>,
+        secured: Option<SharedListener<'r, S, T, H, C>>,
+    ) -> Self {
+        SpdmResponder {
+            spdm,
+            secured,
+            request: None,
+        }
+    }
+
+    /// Receive a request as transport message into `buf` without blocking
+    ///
+    /// Returns the length of the transport message, `Ok(None)` when no request is available.
+    pub fn receive(&mut self, buf: &mut [u8]) -> Result<Option<usize>> {
+        let mut received = recv_transport(buf, |body| Ok(self.spdm.try_recv(body)?))?;
+        if received.is_none()
+            && let Some(secured) = self.secured
+        {
+            received = recv_transport(buf, |body| Ok(secured.try_recv(body)?))?;
+        }
+        Ok(received.map(|(info, len)| {
+            self.request = Some(info);
+            len
+        }))
+    }
+
+    /// Send a transport message produced by [encap()] as response to the last request
+    ///
+    /// Returns [BadArgument](Error::BadArgument) if no request was received.
+    pub fn send(&mut self, transport: &[u8]) -> Result<()> {
+        let request = self.request.ok_or(Error::BadArgument)?;
+        let (typ, body) = split_transport(transport)?;
+        let listener = match (typ, self.secured) {
+            (MSG_TYPE_SECURED, Some(secured)) => secured,
+            _ => self.spdm,
+        };
+        let response = MessageInfo { typ, ..request };
+        listener.respond(&response, MsgIC(false), &[body])?;
+        Ok(())
+    }
+}
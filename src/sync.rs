@@ -0,0 +1,309 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A thread-safe router for hosted environments
+//!
+//! A [SyncRouter] wraps a [GenericRouter] in a [Mutex], so the transport thread and any
+//! number of threads using [SyncListener]s and [SyncRequest]s can share it. Threads
+//! waiting for a message block on a [Condvar] that is notified whenever a message is
+//! delivered or the router is updated.
+//!
+//! The channels implement the blocking [mctp] traits ([Listener], [ReqChannel],
+//! [RespChannel]) and unbind their handle when dropped.
+//!
+//! ```
+//! use std::time::Duration;
+//! use mctp::{Eid, MsgType, ReqChannel};
+//! use mctp_lib::sync::SyncRouter;
+//! use mctp_lib::{Router, Sender};
+//! # struct NullSender;
+//! # impl Sender for NullSender {
+//! #     type Packet = [u8; 64];
+//! #     fn send_packet(&mut self, _eid: Eid, _pkt: &[u8]) -> mctp::Result<()> { Ok(()) }
+//! #     fn get_mtu(&self) -> usize { 64 }
+//! # }
+//!
+//! let router = SyncRouter::new(Router::<_, 4, 4>::new(Eid(8), 0, NullSender));
+//! let mut req = router.req(Eid(9), Some(Duration::from_millis(10)))?;
+//! req.send(MsgType(1), &[0x80])?;
+//! // Nobody answers
+//! assert!(req.recv(&mut [0; 8]).is_err());
+//! # Ok::<(), mctp::Error>(())
+//! ```
+
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use mctp::{Eid, Error, Listener, MsgIC, MsgType, ReqChannel, RespChannel, Result};
+
+use crate::{
+    Clock, GenericRouter, Handle, HandleTables, Hooks, ListenerHandle, MessageInfo, RequestHandle,
+    Sender,
+};
+
+#[derive(Debug)]
+struct Core<S: Sender, T: HandleTables, H: Hooks, C: Clock> {
+    router: Mutex<GenericRouter<S, T, H, C>>,
+    /// Notified when a message was delivered or the router was updated
+    changed: Condvar,
+}
+
+/// A [GenericRouter] shared by threads
+///
+/// Clones refer to the same router. The router is only locked for the duration of each
+/// operation, a poisoned lock fails with [InternalError](Error::InternalError).
+#[derive(Debug)]
+pub struct SyncRouter<S: Sender, T: HandleTables, H: Hooks, C: Clock> {
+    core: Arc<Core<S, T, H, C>>,
+}
+
+impl<S: Sender, T: HandleTables, H: Hooks, C: Clock> Clone for SyncRouter<S, T, H, C> {
+    fn clone(&self) -> Self {
+        SyncRouter {
+            core: Arc::clone(&self.core),
+        }
+    }
+}
+
+impl<S: Sender, T: HandleTables, H: Hooks, C: Clock> SyncRouter<S, T, H, C> {
+    /// Share `router`
+    pub fn new(router: GenericRouter<S, T, H, C>) -> Self {
+        SyncRouter {
+            core: Arc::new(Core {
+                router: Mutex::new(router),
+                changed: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Run `f` with exclusive access to the router
+    ///
+    /// Wakes the threads waiting for a message afterwards, as `f` may have delivered one.
+    pub fn with<R>(&self, f: impl FnOnce(&mut GenericRouter<S, T, H, C>) -> R) -> Result<R> {
+        let result = f(&mut *self.lock()?);
+        self.core.changed.notify_all();
+        Ok(result)
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, GenericRouter<S, T, H, C>>> {
+        self.core.router.lock().map_err(|_| Error::InternalError)
+    }
+
+    /// Provide an incoming packet, see [GenericRouter::inbound()]
+    ///
+    /// Wakes the threads waiting for a message when the packet completes one.
+    pub fn inbound(&self, pkt: &[u8]) -> Result<Option<Handle>> {
+        let handle = self.lock()?.inbound(pkt)?;
+        if handle.is_some() {
+            self.core.changed.notify_all();
+        }
+        Ok(handle)
+    }
+
+    /// Update the router, see [GenericRouter::poll()]
+    ///
+    /// Wakes the threads waiting for a message when anything timed out.
+    pub fn poll(&self) -> Result<u64> {
+        let (interval, expired) = self.lock()?.poll_expired()?;
+        if expired {
+            self.core.changed.notify_all();
+        }
+        Ok(interval)
+    }
+
+    /// Bind a listener for `typ`
+    ///
+    /// [Listener::recv()] waits at most `timeout` for a request, forever if `None`.
+    pub fn listener(
+        &self,
+        typ: MsgType,
+        timeout: Option<Duration>,
+    ) -> Result<SyncListener<S, T, H, C>> {
+        let handle = self.lock()?.listener(typ)?;
+        Ok(SyncListener {
+            router: self.clone(),
+            handle,
+            timeout,
+        })
+    }
+
+    /// Allocate a request to `eid`
+    ///
+    /// [ReqChannel::recv()] waits at most `timeout` for a response, forever if `None`.
+    pub fn req(&self, eid: Eid, timeout: Option<Duration>) -> Result<SyncRequest<S, T, H, C>> {
+        let handle = self.lock()?.req(eid)?;
+        Ok(SyncRequest {
+            router: self.clone(),
+            handle,
+            eid,
+            timeout,
+        })
+    }
+
+    /// Receive a message for `handle` into `buf`, waiting at most `timeout`
+    fn recv(
+        &self,
+        handle: Handle,
+        buf: &mut [u8],
+        timeout: Option<Duration>,
+    ) -> Result<MessageInfo> {
+        // A timeout too large to represent waits forever
+        let deadline = timeout.and_then(|t| Instant::now().checked_add(t));
+        let mut router = self.lock()?;
+        loop {
+            let mut sink = &mut *buf;
+            if let Some(info) = router.recv_into(handle, &mut sink)? {
+                return Ok(info);
+            }
+            router = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline
+                        .checked_duration_since(Instant::now())
+                        .ok_or(Error::TimedOut)?;
+                    self.core
+                        .changed
+                        .wait_timeout(router, remaining)
+                        .map_err(|_| Error::InternalError)?
+                        .0
+                }
+                None => self
+                    .core
+                    .changed
+                    .wait(router)
+                    .map_err(|_| Error::InternalError)?,
+            };
+        }
+    }
+}
+
+/// A listener bound on a [SyncRouter], unbound when dropped
+#[derive(Debug)]
+pub struct SyncListener<S: Sender, T: HandleTables, H: Hooks, C: Clock> {
+    router: SyncRouter<S, T, H, C>,
+    handle: ListenerHandle,
+    timeout: Option<Duration>,
+}
+
+impl<S: Sender, T: HandleTables, H: Hooks, C: Clock> SyncListener<S, T, H, C> {
+    /// Get the handle of the listener
+    pub fn handle(&self) -> ListenerHandle {
+        self.handle
+    }
+}
+
+impl<S: Sender, T: HandleTables, H: Hooks, C: Clock> Listener for SyncListener<S, T, H, C> {
+    type RespChannel<'a>
+        = SyncResponse<S, T, H, C>
+    where
+        Self: 'a;
+
+    fn recv<'f>(
+        &mut self,
+        buf: &'f mut [u8],
+    ) -> Result<(MsgType, MsgIC, &'f mut [u8], Self::RespChannel<'_>)> {
+        let info = self.router.recv(self.handle.into(), buf, self.timeout)?;
+        let payload = buf.get_mut(..info.len).ok_or(Error::NoSpace)?;
+        let response = SyncResponse {
+            router: self.router.clone(),
+            listener: self.handle,
+            request: info,
+        };
+        Ok((info.typ, info.ic, payload, response))
+    }
+}
+
+impl<S: Sender, T: HandleTables, H: Hooks, C: Clock> Drop for SyncListener<S, T, H, C> {
+    fn drop(&mut self) {
+        if let Ok(mut router) = self.router.lock() {
+            let _ = router.unbind(self.handle);
+        }
+    }
+}
+
+/// A request allocated on a [SyncRouter], released when dropped
+#[derive(Debug)]
+pub struct SyncRequest<S: Sender, T: HandleTables, H: Hooks, C: Clock> {
+    router: SyncRouter<S, T, H, C>,
+    handle: RequestHandle,
+    eid: Eid,
+    timeout: Option<Duration>,
+}
+
+impl<S: Sender, T: HandleTables, H: Hooks, C: Clock> SyncRequest<S, T, H, C> {
+    /// Get the handle of the request
+    pub fn handle(&self) -> RequestHandle {
+        self.handle
+    }
+}
+
+impl<S: Sender, T: HandleTables, H: Hooks, C: Clock> ReqChannel for SyncRequest<S, T, H, C> {
+    fn send_vectored(
+        &mut self,
+        typ: MsgType,
+        integrity_check: MsgIC,
+        bufs: &[&[u8]],
+    ) -> Result<()> {
+        self.router
+            .lock()?
+            .send_vectored(None, typ, None, integrity_check, self.handle, bufs)?;
+        Ok(())
+    }
+
+    fn recv<'f>(&mut self, buf: &'f mut [u8]) -> Result<(MsgType, MsgIC, &'f mut [u8])> {
+        let info = self.router.recv(self.handle.into(), buf, self.timeout)?;
+        let payload = buf.get_mut(..info.len).ok_or(Error::NoSpace)?;
+        Ok((info.typ, info.ic, payload))
+    }
+
+    fn remote_eid(&self) -> Eid {
+        self.eid
+    }
+}
+
+impl<S: Sender, T: HandleTables, H: Hooks, C: Clock> Drop for SyncRequest<S, T, H, C> {
+    fn drop(&mut self) {
+        if let Ok(mut router) = self.router.lock() {
+            let _ = router.unbind(self.handle);
+        }
+    }
+}
+
+/// The channel to respond to a request received by a [SyncListener]
+#[derive(Debug)]
+pub struct SyncResponse<S: Sender, T: HandleTables, H: Hooks, C: Clock> {
+    router: SyncRouter<S, T, H, C>,
+    listener: ListenerHandle,
+    request: MessageInfo,
+}
+
+impl<S: Sender, T: HandleTables, H: Hooks, C: Clock> RespChannel for SyncResponse<S, T, H, C> {
+    type ReqChannel = SyncRequest<S, T, H, C>;
+
+    fn send_vectored(&mut self, integrity_check: MsgIC, bufs: &[&[u8]]) -> Result<()> {
+        let request = MessageInfo {
+            ic: integrity_check,
+            ..self.request
+        };
+        self.router.lock()?.respond(self.listener, &request, bufs)?;
+        Ok(())
+    }
+
+    fn remote_eid(&self) -> Eid {
+        self.request.source
+    }
+
+    fn req_channel(&self) -> Result<Self::ReqChannel> {
+        self.router.req(self.request.source, None)
+    }
+}
@@ -0,0 +1,427 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Storage of the listener and request handle tables
+//!
+//! The tables are either sized at compile time ([ArrayTables])
+//! or at runtime ([VecTables], requires the `alloc` feature).
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use mctp::{Eid, Error, MsgType, Result, Tag};
+
+use crate::EidAcl;
+use crate::handle_error::HandleError;
+use crate::recv_queue::RecvQueue;
+use crate::retry::RetryPolicy;
+use mctp_estack::AppCookie;
+
+/// Number of low bits of an [AppCookie] that hold the handle slot
+///
+/// The bits above hold the generation of the slot.
+const COOKIE_INDEX_BITS: u32 = 16;
+
+/// Cookie of messages the router exchanges itself, never the cookie of a handle
+pub(crate) const INTERNAL_COOKIE: AppCookie = AppCookie((1 << COOKIE_INDEX_BITS) - 1);
+
+/// Number of listener and request slots cookies can address
+pub(crate) const MAX_HANDLES: usize = INTERNAL_COOKIE.0;
+
+/// Cookie of messages received by their listener and still held for its consumers
+pub(crate) const SHARED_COOKIE: AppCookie = AppCookie((2 << COOKIE_INDEX_BITS) - 1);
+
+/// Number of message types a listener group receives, including the type it was bound for
+///
+/// See [listener_group()](crate::GenericRouter::listener_group).
+pub const LISTENER_GROUP_SIZE: usize = 4;
+
+/// An entry in a handle table
+#[derive(Debug)]
+pub struct Slot<T> {
+    pub(crate) entry: Option<T>,
+    /// Generation of the slot, incremented when the slot is unbound
+    ///
+    /// Encoded into the AppCookie to detect stale handles.
+    pub(crate) generation: u16,
+}
+
+impl<T> Slot<T> {
+    const EMPTY: Self = Slot {
+        entry: None,
+        generation: 0,
+    };
+}
+
+/// State of a bound listener
+#[derive(Debug)]
+pub struct ListenerEntry {
+    /// Message type the listener is bound for, `None` for the catch-all listener
+    pub(crate) typ: Option<MsgType>,
+    /// Further message types received by the listener, see [LISTENER_GROUP_SIZE]
+    pub(crate) group: [Option<MsgType>; LISTENER_GROUP_SIZE - 1],
+    /// Source EIDs requests are accepted from, `None` accepts all
+    pub(crate) acl: Option<EidAcl>,
+    /// Number of requests dropped by the ACL
+    pub(crate) denied: usize,
+    /// Maximum size of accepted requests, excluding the message type
+    pub(crate) max_size: Option<usize>,
+    /// Requests retained for the listener
+    pub(crate) queue: RecvQueue,
+    /// Value set by the application
+    pub(crate) context: usize,
+    /// Local EID responses are sent from when the request doesn't tell
+    pub(crate) source_eid: Option<Eid>,
+    /// Last error, see [take_error()](crate::GenericRouter::take_error)
+    pub(crate) error: Option<HandleError>,
+}
+
+impl ListenerEntry {
+    pub(crate) fn new(typ: Option<MsgType>) -> ListenerEntry {
+        ListenerEntry {
+            typ,
+            group: [None; LISTENER_GROUP_SIZE - 1],
+            acl: None,
+            denied: 0,
+            max_size: None,
+            queue: RecvQueue::default(),
+            context: 0,
+            source_eid: None,
+            error: None,
+        }
+    }
+
+    /// Iterate over the message types of the listener, starting with the one it was bound for
+    pub(crate) fn types(&self) -> impl Iterator<Item = MsgType> + '_ {
+        self.typ
+            .into_iter()
+            .chain(self.group.iter().flatten().copied())
+    }
+
+    /// Check if the listener receives requests of type `typ`
+    ///
+    /// The catch-all listener only matches `None`.
+    pub(crate) fn accepts(&self, typ: Option<MsgType>) -> bool {
+        match typ {
+            Some(typ) => self.types().any(|t| t == typ),
+            None => self.typ.is_none(),
+        }
+    }
+}
+
+/// State of a bound request
+#[derive(Debug)]
+pub struct ReqHandle {
+    /// Destination EID
+    pub(crate) eid: Eid,
+    /// Tag from last send operation
+    ///
+    /// Has to be cleared upon receiving a response.
+    pub(crate) last_tag: Option<Tag>,
+    /// Message type of the last send operation, responses have to match it
+    pub(crate) typ: Option<MsgType>,
+    /// Time the handle was allocated at
+    pub(crate) created_millis: u64,
+    /// Time of the last send operation
+    pub(crate) sent_millis: u64,
+    /// Retry policy of the request, overriding the one of its message type
+    pub(crate) retry: Option<&'static dyn RetryPolicy>,
+    /// Attempts of the current request that timed out
+    pub(crate) attempt: u8,
+    /// Start of the last send operation if it did not complete
+    pub(crate) tx_started: Option<u64>,
+    /// The last send operation was abandoned after the transmit timeout
+    pub(crate) tx_stalled: bool,
+    /// Halves of a split request that were not released yet, 0 if not split
+    pub(crate) halves: u8,
+    /// Responses retained for the request
+    pub(crate) queue: RecvQueue,
+    /// Value set by the application
+    pub(crate) context: usize,
+    /// Local EID requests are sent from, `None` for the own EID
+    pub(crate) source_eid: Option<Eid>,
+    /// Tag reclaim time, overriding the one of the router
+    pub(crate) tag_reclaim_millis: Option<u64>,
+    /// Tag held by the router instead of the stack and the time it is reclaimed at
+    pub(crate) reclaim_at: Option<(Tag, u64)>,
+    /// Tag values reserved for the request as a bit mask, 0 if none
+    pub(crate) tag_range: u8,
+    /// Last error, see [take_error()](crate::GenericRouter::take_error)
+    pub(crate) error: Option<HandleError>,
+}
+impl ReqHandle {
+    pub(crate) fn new(eid: Eid, now_millis: u64) -> ReqHandle {
+        ReqHandle {
+            eid,
+            last_tag: None,
+            typ: None,
+            created_millis: now_millis,
+            sent_millis: now_millis,
+            retry: None,
+            attempt: 0,
+            tx_started: None,
+            tx_stalled: false,
+            halves: 0,
+            queue: RecvQueue::default(),
+            context: 0,
+            source_eid: None,
+            tag_reclaim_millis: None,
+            reclaim_at: None,
+            tag_range: 0,
+            error: None,
+        }
+    }
+}
+
+pub(crate) mod sealed {
+    /// Keeps [HandleTables](super::HandleTables) from being implemented outside of the crate
+    ///
+    /// Its slots hold entries only the router can fill in.
+    pub trait Sealed {}
+}
+
+/// Storage for the listener and request handles of a [GenericRouter](crate::GenericRouter)
+///
+/// Implemented by [ArrayTables] and [VecTables], and sealed against other implementations.
+pub trait HandleTables: sealed::Sealed {
+    /// Listener slots
+    ///
+    /// The index is used to construct the AppCookie.
+    fn listeners(&self) -> &[Slot<ListenerEntry>];
+    /// Mutable listener slots
+    fn listeners_mut(&mut self) -> &mut [Slot<ListenerEntry>];
+    /// Request slots
+    ///
+    /// The index is used to construct the AppCookie.
+    fn requests(&self) -> &[Slot<ReqHandle>];
+    /// Mutable request slots
+    fn requests_mut(&mut self) -> &mut [Slot<ReqHandle>];
+}
+
+/// Handle tables sized at compile time
+#[derive(Debug)]
+pub struct ArrayTables<const MAX_LISTENER_HANDLES: usize, const MAX_REQ_HANDLES: usize> {
+    listeners: [Slot<ListenerEntry>; MAX_LISTENER_HANDLES],
+    requests: [Slot<ReqHandle>; MAX_REQ_HANDLES],
+}
+
+impl<const MAX_LISTENER_HANDLES: usize, const MAX_REQ_HANDLES: usize>
+    ArrayTables<MAX_LISTENER_HANDLES, MAX_REQ_HANDLES>
+{
+    /// Create empty tables
+    ///
+    /// Fails to compile for more than 65535 handles in total, which cookies can't address.
+    pub const fn new() -> Self {
+        const {
+            assert!(
+                MAX_LISTENER_HANDLES + MAX_REQ_HANDLES <= MAX_HANDLES,
+                "too many handles for the cookie space"
+            )
+        };
+        ArrayTables {
+            listeners: [const { Slot::EMPTY }; MAX_LISTENER_HANDLES],
+            requests: [const { Slot::EMPTY }; MAX_REQ_HANDLES],
+        }
+    }
+}
+
+impl<const MAX_LISTENER_HANDLES: usize, const MAX_REQ_HANDLES: usize> Default
+    for ArrayTables<MAX_LISTENER_HANDLES, MAX_REQ_HANDLES>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const MAX_LISTENER_HANDLES: usize, const MAX_REQ_HANDLES: usize> sealed::Sealed
+    for ArrayTables<MAX_LISTENER_HANDLES, MAX_REQ_HANDLES>
+{
+}
+
+impl<const MAX_LISTENER_HANDLES: usize, const MAX_REQ_HANDLES: usize> HandleTables
+    for ArrayTables<MAX_LISTENER_HANDLES, MAX_REQ_HANDLES>
+{
+    fn listeners(&self) -> &[Slot<ListenerEntry>] {
+        &self.listeners
+    }
+    fn listeners_mut(&mut self) -> &mut [Slot<ListenerEntry>] {
+        &mut self.listeners
+    }
+    fn requests(&self) -> &[Slot<ReqHandle>] {
+        &self.requests
+    }
+    fn requests_mut(&mut self) -> &mut [Slot<ReqHandle>] {
+        &mut self.requests
+    }
+}
+
+impl<T: HandleTables + ?Sized> sealed::Sealed for &mut T {}
+
+/// Handle tables borrowed from storage owned elsewhere
+///
+/// Allows a router to use `&mut dyn HandleTables`, see [BorrowedRouter](crate::BorrowedRouter).
+impl<T: HandleTables + ?Sized> HandleTables for &mut T {
+    fn listeners(&self) -> &[Slot<ListenerEntry>] {
+        (**self).listeners()
+    }
+    fn listeners_mut(&mut self) -> &mut [Slot<ListenerEntry>] {
+        (**self).listeners_mut()
+    }
+    fn requests(&self) -> &[Slot<ReqHandle>] {
+        (**self).requests()
+    }
+    fn requests_mut(&mut self) -> &mut [Slot<ReqHandle>] {
+        (**self).requests_mut()
+    }
+}
+
+/// Handle tables sized at runtime
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct VecTables {
+    listeners: Vec<Slot<ListenerEntry>>,
+    requests: Vec<Slot<ReqHandle>>,
+}
+
+#[cfg(feature = "alloc")]
+impl VecTables {
+    /// Create empty tables with room for `listeners` listener and `requests` request handles
+    ///
+    /// The total number of handles is limited to 65535, larger tables are truncated.
+    pub fn new(listeners: usize, requests: usize) -> Self {
+        let max = MAX_HANDLES;
+        let listeners = listeners.min(max);
+        let requests = requests.min(max - listeners);
+        VecTables {
+            listeners: (0..listeners).map(|_| Slot::EMPTY).collect(),
+            requests: (0..requests).map(|_| Slot::EMPTY).collect(),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl sealed::Sealed for VecTables {}
+
+#[cfg(feature = "alloc")]
+impl HandleTables for VecTables {
+    fn listeners(&self) -> &[Slot<ListenerEntry>] {
+        &self.listeners
+    }
+    fn listeners_mut(&mut self) -> &mut [Slot<ListenerEntry>] {
+        &mut self.listeners
+    }
+    fn requests(&self) -> &[Slot<ReqHandle>] {
+        &self.requests
+    }
+    fn requests_mut(&mut self) -> &mut [Slot<ReqHandle>] {
+        &mut self.requests
+    }
+}
+
+/// Cookie handling on top of [HandleTables]
+///
+/// Listeners are enumerated from 0 to `listeners().len()-1`,
+/// requests from `listeners().len()` to `listeners().len()+requests().len()-1`.
+/// The generation of the slot is stored above [COOKIE_INDEX_BITS].
+pub(crate) trait Cookies: HandleTables {
+    /// Create the cookie of the listener in slot `i`
+    ///
+    /// Returns [BadArgument](Error::BadArgument) if there is no slot `i`
+    /// or its id does not fit into [COOKIE_INDEX_BITS].
+    fn listener_cookie(&self, i: usize) -> Result<AppCookie> {
+        let slot = self.listeners().get(i).ok_or(Error::BadArgument)?;
+        make_cookie(i, slot.generation)
+    }
+
+    /// Create the cookie of the request in slot `i`
+    ///
+    /// Returns [BadArgument](Error::BadArgument) if there is no slot `i`
+    /// or its id does not fit into [COOKIE_INDEX_BITS].
+    fn request_cookie(&self, i: usize) -> Result<AppCookie> {
+        let slot = self.requests().get(i).ok_or(Error::BadArgument)?;
+        let id = i
+            .checked_add(self.listeners().len())
+            .ok_or(Error::BadArgument)?;
+        make_cookie(id, slot.generation)
+    }
+
+    /// Get the listener slot index from an [AppCookie]
+    ///
+    /// Returns `None` for invalid cookies and cookies of a previous slot generation.
+    fn listener_index(&self, cookie: AppCookie) -> Option<usize> {
+        let (id, generation) = split_cookie(cookie);
+        self.listeners()
+            .get(id)
+            .is_some_and(|s| s.generation as usize == generation)
+            .then_some(id)
+    }
+
+    /// Get the request slot index from an [AppCookie]
+    ///
+    /// Returns `None` for invalid cookies and cookies of a previous slot generation.
+    fn request_index(&self, cookie: AppCookie) -> Option<usize> {
+        let (id, generation) = split_cookie(cookie);
+        let index = id.checked_sub(self.listeners().len())?;
+        self.requests()
+            .get(index)
+            .is_some_and(|s| s.generation as usize == generation)
+            .then_some(index)
+    }
+
+    /// Get the bound listener for a cookie
+    fn listener(&self, cookie: AppCookie) -> Option<&ListenerEntry> {
+        self.listener_index(cookie)
+            .and_then(|i| self.listeners().get(i))
+            .and_then(|s| s.entry.as_ref())
+    }
+
+    /// Get the bound listener for a cookie mutably
+    fn listener_mut(&mut self, cookie: AppCookie) -> Option<&mut ListenerEntry> {
+        self.listener_index(cookie)
+            .and_then(|i| self.listeners_mut().get_mut(i))
+            .and_then(|s| s.entry.as_mut())
+    }
+
+    /// Get the bound request for a cookie
+    fn request(&self, cookie: AppCookie) -> Option<&ReqHandle> {
+        self.request_index(cookie)
+            .and_then(|i| self.requests().get(i))
+            .and_then(|s| s.entry.as_ref())
+    }
+
+    /// Get the bound request for a cookie mutably
+    fn request_mut(&mut self, cookie: AppCookie) -> Option<&mut ReqHandle> {
+        self.request_index(cookie)
+            .and_then(|i| self.requests_mut().get_mut(i))
+            .and_then(|s| s.entry.as_mut())
+    }
+}
+
+impl<T: HandleTables + ?Sized> Cookies for T {}
+
+/// Combine slot id and generation into an [AppCookie]
+fn make_cookie(id: usize, generation: u16) -> Result<AppCookie> {
+    if id >= INTERNAL_COOKIE.0 {
+        return Err(Error::BadArgument);
+    }
+    Ok(AppCookie(id | usize::from(generation) << COOKIE_INDEX_BITS))
+}
+
+/// Split an [AppCookie] into slot id and generation
+fn split_cookie(cookie: AppCookie) -> (usize, usize) {
+    (
+        cookie.0 & ((1 << COOKIE_INDEX_BITS) - 1),
+        cookie.0 >> COOKIE_INDEX_BITS,
+    )
+}
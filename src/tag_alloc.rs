@@ -0,0 +1,62 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Allocation of the owned tags of requests
+//!
+//! By default the stack rotates through the tag values for each destination. Peers that
+//! mismatch responses when a tag value comes back soon, or applications running several
+//! requesters towards one EID, can have the router pick the tags instead.
+
+use core::ops::RangeInclusive;
+
+use mctp::{Error, Result, TagValue};
+
+/// Number of tag values
+const TAG_VALUES: u8 = 8;
+
+/// How the owned tags of requests are allocated, see
+/// [RouterConfig::tag_allocation()](crate::RouterConfig::tag_allocation)
+///
+/// Tags chosen by the router avoid the tags held by other requests to the same destination.
+/// Tags the stack allocates for the router itself (e.g. liveness probes) are not known to
+/// the router, so they are only avoided with [RoundRobin](Self::RoundRobin).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TagAllocation {
+    /// The stack rotates through the tag values per destination
+    #[default]
+    RoundRobin,
+    /// The lowest tag value not held by a request to the destination
+    LowestFree,
+    /// Like [LowestFree](Self::LowestFree), requests only use the tag values reserved for
+    /// them with [set_tag_range()](crate::GenericRouter::set_tag_range). Requests without
+    /// a range use the values not reserved by other requests to the destination.
+    Reserved,
+}
+
+/// Convert a range of tag values to a bit mask
+///
+/// Returns [BadArgument](Error::BadArgument) for empty ranges and values beyond 7.
+pub(crate) fn range_mask(range: RangeInclusive<u8>) -> Result<u8> {
+    if range.is_empty() || *range.end() >= TAG_VALUES {
+        return Err(Error::BadArgument);
+    }
+    Ok(range.fold(0, |mask, tv| mask | 1 << tv))
+}
+
+/// Pick the lowest tag value in the mask `candidates`
+pub(crate) fn lowest(candidates: u8) -> Option<TagValue> {
+    (candidates != 0).then(|| TagValue(candidates.trailing_zeros() as u8))
+}
@@ -0,0 +1,247 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Transports for unit tests of code built on a [Router](crate::Router)
+//!
+//! Requires the `test-util` feature.
+//!
+//! [FaultInjector] wraps any [Sender] to drop, corrupt, duplicate or delay selected packets,
+//! a deterministic adversary for testing timeout and retry behavior.
+//!
+//! ```
+//! use core::cell::RefCell;
+//! use mctp::{Eid, MsgIC, MsgType};
+//! use mctp_lib::Router;
+//! use mctp_lib::test_util::{BufferSender, NullSender, transfer};
+//!
+//! let packets = RefCell::new(Vec::new());
+//! let mut requester: Router<_, 1, 1> = Router::new(Eid(8), 0, BufferSender::<64>::new(&packets));
+//! let mut responder: Router<_, 1, 1> = Router::new(Eid(9), 0, NullSender);
+//! let listener = responder.listener(MsgType(1)).unwrap();
+//!
+//! let req = requester.req(Eid(9)).unwrap();
+//! requester.send(None, MsgType(1), None, MsgIC(false), req, &[0; 100]).unwrap();
+//! assert_eq!(transfer(&packets, &mut responder).unwrap(), 2);
+//! assert!(responder.recv(listener).is_some());
+//! ```
+
+use core::cell::RefCell;
+
+#[cfg(not(test))]
+use alloc::vec::Vec;
+
+use mctp::{Eid, Result};
+
+use crate::{Clock, GenericRouter, HandleTables, Hooks, Route, Sender};
+
+/// MTU of a [NullSender]
+pub const NULL_SENDER_MTU: usize = 255;
+
+/// A [Sender] discarding all packets
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NullSender;
+
+impl Sender for NullSender {
+    type Packet = [u8; NULL_SENDER_MTU];
+
+    fn send_packet(&mut self, _eid: Eid, _pkt: &[u8]) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_mtu(&self) -> usize {
+        NULL_SENDER_MTU
+    }
+}
+
+/// A [Sender] with an MTU of `MTU`, appending all packets to a shared buffer
+///
+/// The buffer is borrowed, so the test can inspect the packets while the router holds the
+/// sender, or pass them on with [transfer()].
+#[derive(Debug, Clone, Copy)]
+pub struct BufferSender<'a, const MTU: usize> {
+    /// Packets sent so far, without transport binding header
+    pub packets: &'a RefCell<Vec<Vec<u8>>>,
+}
+
+impl<'a, const MTU: usize> BufferSender<'a, MTU> {
+    /// Append packets to `packets`
+    pub fn new(packets: &'a RefCell<Vec<Vec<u8>>>) -> Self {
+        BufferSender { packets }
+    }
+}
+
+impl<const MTU: usize> Sender for BufferSender<'_, MTU> {
+    type Packet = [u8; MTU];
+
+    fn send_packet(&mut self, _eid: Eid, pkt: &[u8]) -> Result<()> {
+        self.packets.borrow_mut().push(pkt.into());
+        Ok(())
+    }
+
+    fn get_mtu(&self) -> usize {
+        MTU
+    }
+}
+
+/// A fault applied by a [FaultInjector] to a packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Discard the packet
+    Drop,
+    /// XOR the byte at `offset` with `mask`, packets shorter than `offset` are sent unmodified
+    Corrupt {
+        /// Offset in the packet, 0 is the first byte of the MCTP transport header
+        offset: usize,
+        /// Bits to flip
+        mask: u8,
+    },
+    /// Send the packet twice
+    Duplicate,
+    /// Hold the packet back until `packets` further packets were sent
+    Delay {
+        /// Number of packets overtaking the delayed one
+        packets: usize,
+    },
+}
+
+/// A [Sender] passing packets on to `inner`, applying [Fault]s to selected ones
+///
+/// Packets are numbered from 0 in the order they are sent, [inject()](Self::inject) selects
+/// the packet a fault is applied to. Faults on the same packet apply in the order they were
+/// added. Packets still delayed are passed on by [flush()](Self::flush).
+#[derive(Debug)]
+pub struct FaultInjector<S: Sender> {
+    inner: S,
+    faults: Vec<(usize, Fault)>,
+    /// Delayed packets, with the number of packets sent when they are released
+    delayed: Vec<(usize, Eid, Vec<u8>)>,
+    sent: usize,
+    injected: usize,
+}
+
+impl<S: Sender> FaultInjector<S> {
+    /// Wrap `inner` without any faults
+    pub fn new(inner: S) -> Self {
+        FaultInjector {
+            inner,
+            faults: Vec::new(),
+            delayed: Vec::new(),
+            sent: 0,
+            injected: 0,
+        }
+    }
+
+    /// Apply `fault` to packet number `nth`
+    pub fn inject(mut self, nth: usize, fault: Fault) -> Self {
+        self.faults.push((nth, fault));
+        self
+    }
+
+    /// Get the number of packets sent so far, including dropped ones
+    pub fn sent(&self) -> usize {
+        self.sent
+    }
+
+    /// Get the number of faults applied so far
+    pub fn injected(&self) -> usize {
+        self.injected
+    }
+
+    /// Pass all delayed packets on to the wrapped sender
+    pub fn flush(&mut self) -> Result<()> {
+        for (_, eid, pkt) in core::mem::take(&mut self.delayed) {
+            self.inner.send_packet(eid, &pkt)?;
+        }
+        Ok(())
+    }
+
+    /// Get the wrapped sender
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Get the wrapped sender mutably
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Pass delayed packets that are due on to the wrapped sender
+    fn release(&mut self) -> Result<()> {
+        while let Some(i) = self.delayed.iter().position(|d| d.0 <= self.sent) {
+            let (_, eid, pkt) = self.delayed.remove(i);
+            self.inner.send_packet(eid, &pkt)?;
+        }
+        Ok(())
+    }
+}
+
+impl<S: Sender> Sender for FaultInjector<S> {
+    type Packet = S::Packet;
+
+    fn send_packet(&mut self, eid: Eid, pkt: &[u8]) -> Result<()> {
+        let nth = self.sent;
+        self.sent += 1;
+        let mut pkt = Vec::from(pkt);
+        let (mut copies, mut delay) = (1, None);
+        for &(_, fault) in self.faults.iter().filter(|f| f.0 == nth) {
+            self.injected += 1;
+            match fault {
+                Fault::Drop => copies = 0,
+                Fault::Corrupt { offset, mask } => {
+                    if let Some(b) = pkt.get_mut(offset) {
+                        *b ^= mask;
+                    }
+                }
+                Fault::Duplicate => copies *= 2,
+                Fault::Delay { packets } => delay = Some(self.sent + packets),
+            }
+        }
+        for _ in 0..copies {
+            match delay {
+                Some(release) => self.delayed.push((release, eid, pkt.clone())),
+                None => self.inner.send_packet(eid, &pkt)?,
+            }
+        }
+        self.release()
+    }
+
+    fn get_mtu(&self) -> usize {
+        self.inner.get_mtu()
+    }
+
+    fn peer_mtu(&self, eid: Eid) -> Option<usize> {
+        self.inner.peer_mtu(eid)
+    }
+
+    fn offloads_integrity_check(&self, eid: Eid, route: Option<&Route>) -> bool {
+        self.inner.offloads_integrity_check(eid, route)
+    }
+}
+
+/// Pass all packets in `packets` to `router`, emptying the buffer
+///
+/// Loops two routers back to back when each one sends into the buffer the other one is
+/// fed from.
+/// Returns the number of packets passed, or the first error of
+/// [inbound()](GenericRouter::inbound).
+pub fn transfer<S: Sender, T: HandleTables, H: Hooks, C: Clock>(
+    packets: &RefCell<Vec<Vec<u8>>>,
+    router: &mut GenericRouter<S, T, H, C>,
+) -> Result<usize> {
+    let pending = core::mem::take(&mut *packets.borrow_mut());
+    for pkt in pending.iter() {
+        router.inbound(pkt)?;
+    }
+    Ok(pending.len())
+}
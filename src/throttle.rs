@@ -0,0 +1,458 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Outbound bandwidth throttling per destination
+//!
+//! Every throttled destination EID gets a token bucket that holds up to `burst_bytes` and is
+//! refilled with `bytes_per_sec`. Packets to a destination without enough tokens are held in
+//! a transmit buffer provided by the application and passed to the sender by
+//! [poll()](crate::GenericRouter::poll) once the bucket refilled.
+//! Packets to each destination stay in order, except that
+//! [RoundRobin](crate::Scheduling::RoundRobin) scheduling interleaves the packets of
+//! different handles. Packets of messages sent with a
+//! [deadline](crate::SendArgs::deadline) are dropped once it passed.
+
+use mctp::{Eid, Error, Result};
+use mctp_estack::AppCookie;
+
+/// Number of destination EIDs a [Router](crate::Router) can throttle
+pub const THROTTLE_TABLE_SIZE: usize = 8;
+
+/// Tokens are tracked in thousandths, so refills per millisecond stay integral
+const TOKEN_SCALE: u64 = 1000;
+
+/// Length of the record header in front of each held packet: EID, length, deadline and
+/// cookie of the message
+const RECORD_HEADER_LEN: usize = 19;
+
+/// Offset of the deadline in the record header, following EID and length
+const RECORD_DEADLINE_OFFSET: usize = 3;
+
+/// Deadline of held packets without one
+const NO_DEADLINE: u64 = u64::MAX;
+
+/// Cookie of held packets not sent by a handle
+const NO_COOKIE: u64 = u64::MAX;
+
+/// Number of handles served per round of a fair [release()](Throttles::release)
+const FAIR_ROUND: usize = 8;
+
+/// Outbound bandwidth limit for a destination
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Throttle {
+    burst_bytes: u32,
+    bytes_per_sec: u32,
+}
+
+impl Throttle {
+    /// Allow bursts of `burst_bytes` and `bytes_per_sec` on average
+    ///
+    /// Packets count with their MCTP header. A burst smaller than a packet still lets
+    /// single packets through once the bucket is full.
+    pub fn new(burst_bytes: u32, bytes_per_sec: u32) -> Self {
+        Throttle {
+            burst_bytes,
+            bytes_per_sec,
+        }
+    }
+
+    fn capacity(&self) -> u64 {
+        u64::from(self.burst_bytes) * TOKEN_SCALE
+    }
+}
+
+/// Token bucket of a single destination
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    eid: Eid,
+    limit: Throttle,
+    /// Available tokens, scaled by [TOKEN_SCALE]
+    tokens: u64,
+    /// Time of the last refill
+    refill_millis: u64,
+}
+
+impl Bucket {
+    fn refill(&mut self, now_millis: u64) {
+        let elapsed = now_millis.saturating_sub(self.refill_millis);
+        let added = elapsed.saturating_mul(u64::from(self.limit.bytes_per_sec));
+        self.tokens = self.tokens.saturating_add(added).min(self.limit.capacity());
+        self.refill_millis = now_millis;
+    }
+
+    /// Tokens needed for a packet of `len` bytes, capped at a full bucket
+    fn cost(&self, len: usize) -> u64 {
+        (len as u64)
+            .saturating_mul(TOKEN_SCALE)
+            .min(self.limit.capacity())
+    }
+
+    /// Take the tokens for a packet of `len` bytes if available
+    fn take(&mut self, len: usize, now_millis: u64) -> bool {
+        self.refill(now_millis);
+        let cost = self.cost(len);
+        if self.tokens < cost {
+            return false;
+        }
+        self.tokens -= cost;
+        true
+    }
+
+    /// Get the time at which a packet of `len` bytes can be sent
+    fn ready_millis(&self, len: usize) -> Option<u64> {
+        let missing = self.cost(len).saturating_sub(self.tokens);
+        let rate = u64::from(self.limit.bytes_per_sec);
+        if missing == 0 {
+            return Some(self.refill_millis);
+        }
+        (rate > 0).then(|| self.refill_millis.saturating_add(missing.div_ceil(rate)))
+    }
+}
+
+/// Throttled destinations and the packets held back for them
+#[derive(Debug, Default)]
+pub(crate) struct Throttles {
+    buckets: [Option<Bucket>; THROTTLE_TABLE_SIZE],
+    /// Held packets, each prefixed by a record header
+    buf: Option<&'static mut [u8]>,
+    /// Bytes of `buf` in use
+    used: usize,
+    /// Packets dropped because the transmit buffer was missing or full
+    pub(crate) overflows: usize,
+}
+
+impl Throttles {
+    /// Throttle packets to `eid` with `limit`, `None` removes the throttle
+    ///
+    /// Packets held for `eid` are passed on as if it was still throttled.
+    pub(crate) fn set(&mut self, eid: Eid, limit: Option<Throttle>, now_millis: u64) -> Result<()> {
+        let existing = self
+            .buckets
+            .iter_mut()
+            .find(|b| b.is_some_and(|b| b.eid == eid));
+        let slot = match existing {
+            Some(slot) => slot,
+            None if limit.is_none() => return Ok(()),
+            None => self
+                .buckets
+                .iter_mut()
+                .find(|b| b.is_none())
+                .ok_or(Error::NoSpace)?,
+        };
+        *slot = limit.map(|limit| Bucket {
+            eid,
+            limit,
+            tokens: limit.capacity(),
+            refill_millis: now_millis,
+        });
+        Ok(())
+    }
+
+    /// Get the throttle of `eid`
+    pub(crate) fn get(&self, eid: Eid) -> Option<Throttle> {
+        self.bucket(eid).map(|b| b.limit)
+    }
+
+    /// Replace the transmit buffer, discarding held packets
+    pub(crate) fn set_buffer(
+        &mut self,
+        buf: Option<&'static mut [u8]>,
+    ) -> Option<&'static mut [u8]> {
+        self.used = 0;
+        core::mem::replace(&mut self.buf, buf)
+    }
+
+    /// Check whether a packet of `len` bytes to `eid` can be sent now, taking its tokens
+    ///
+    /// Packets have to wait while earlier ones to the same destination are held.
+    pub(crate) fn admit(&mut self, eid: Eid, len: usize, now_millis: u64) -> bool {
+        if self.records().any(|(e, _)| e == eid) {
+            return false;
+        }
+        match self.bucket_mut(eid) {
+            Some(bucket) => bucket.take(len, now_millis),
+            None => true,
+        }
+    }
+
+    /// Hold `pkt` to `eid` until [release()](Self::release) passes it on
+    ///
+    /// `owner` is the cookie of the handle sending the message and its deadline, past the
+    /// deadline the packet is dropped instead.
+    /// Returns [NoSpace](Error::NoSpace) without a transmit buffer or room in it.
+    pub(crate) fn hold(
+        &mut self,
+        eid: Eid,
+        pkt: &[u8],
+        owner: Option<(AppCookie, Option<u64>)>,
+    ) -> Result<()> {
+        let stored = self.store(eid, pkt, owner);
+        if stored.is_err() {
+            self.overflows = self.overflows.wrapping_add(1);
+        }
+        stored
+    }
+
+    fn store(
+        &mut self,
+        eid: Eid,
+        pkt: &[u8],
+        owner: Option<(AppCookie, Option<u64>)>,
+    ) -> Result<()> {
+        let [len0, len1] = u16::try_from(pkt.len())
+            .map_err(|_| Error::NoSpace)?
+            .to_le_bytes();
+        let (cookie, deadline) = owner.map_or((NO_COOKIE, None), |(c, d)| (c.0 as u64, d));
+        let deadline = deadline.unwrap_or(NO_DEADLINE);
+        let end = self.used + RECORD_HEADER_LEN + pkt.len();
+        let record = self
+            .buf
+            .as_deref_mut()
+            .and_then(|b| b.get_mut(self.used..end))
+            .ok_or(Error::NoSpace)?;
+        let (header, body) = record
+            .split_first_chunk_mut::<RECORD_HEADER_LEN>()
+            .ok_or(Error::InternalError)?;
+        let (prefix, rest) = header.split_at_mut(RECORD_DEADLINE_OFFSET);
+        let (deadline_bytes, cookie_bytes) = rest.split_at_mut(8);
+        prefix.copy_from_slice(&[eid.0, len0, len1]);
+        deadline_bytes.copy_from_slice(&deadline.to_le_bytes());
+        cookie_bytes.copy_from_slice(&cookie.to_le_bytes());
+        body.copy_from_slice(pkt);
+        self.used = end;
+        Ok(())
+    }
+
+    /// Discard the held packets to destinations `matches` selects
+    ///
+    /// Returns the number of packets discarded.
+    pub(crate) fn discard(&mut self, mut matches: impl FnMut(Eid) -> bool) -> usize {
+        let mut discarded = 0;
+        let mut pos = 0;
+        while let Some((eid, len)) = self.record_at(pos) {
+            let record_len = RECORD_HEADER_LEN + len;
+            if matches(eid) {
+                self.remove(pos, record_len);
+                discarded += 1;
+            } else {
+                pos += record_len;
+            }
+        }
+        discarded
+    }
+
+    /// Pass all held packets to `send` regardless of the buckets, emptying the buffer
+    ///
+    /// Returns the number of packets `send` succeeded for, the others are dropped.
+    pub(crate) fn flush(&mut self, mut send: impl FnMut(Eid, &[u8]) -> bool) -> usize {
+        let mut flushed = 0;
+        while let Some((eid, len)) = self.record_at(0) {
+            let record_len = RECORD_HEADER_LEN + len;
+            let pkt = self
+                .buf
+                .as_deref()
+                .and_then(|b| b.get(RECORD_HEADER_LEN..record_len))
+                .unwrap_or_default();
+            if send(eid, pkt) {
+                flushed += 1;
+            }
+            self.remove(0, record_len);
+        }
+        flushed
+    }
+
+    /// Drop the held packets of a message whose deadline passed by `now_millis`
+    ///
+    /// Returns the cookie of the message, `None` if no deadline passed.
+    pub(crate) fn expire(&mut self, now_millis: u64) -> Option<AppCookie> {
+        let mut expired = None;
+        let mut pos = 0;
+        while let Some((_, len)) = self.record_at(pos) {
+            let record_len = RECORD_HEADER_LEN + len;
+            let expiry = self.expiry_at(pos);
+            if expiry.is_some_and(|e| e.1 <= now_millis && expired.is_none_or(|x| x == e)) {
+                expired = expiry;
+                self.remove(pos, record_len);
+            } else {
+                pos += record_len;
+            }
+        }
+        expired.map(|e| e.0)
+    }
+
+    /// Number of packets held
+    pub(crate) fn held(&self) -> usize {
+        self.records().count()
+    }
+
+    /// Pass up to `max` held packets whose destination has enough tokens to `send`
+    ///
+    /// Packets `send` fails with [busy](crate::Sender::is_receiver_busy) (as reported by
+    /// `busy`) stay held, other failures drop the packet.
+    /// With `fair` set, packets are released in rounds passing at most one packet per
+    /// sending handle, instead of in the order they were held.
+    /// Returns the number of packets passed to `send`.
+    pub(crate) fn release(
+        &mut self,
+        now_millis: u64,
+        max: usize,
+        fair: bool,
+        mut send: impl FnMut(Eid, &[u8]) -> core::result::Result<(), bool>,
+    ) -> usize {
+        let mut released = 0;
+        let mut blocked = [None; THROTTLE_TABLE_SIZE];
+        loop {
+            // Handles that had their turn in this round
+            let mut served = [None; FAIR_ROUND];
+            let mut progress = false;
+            let mut pos = 0;
+            while released < max
+                && let Some((eid, len)) = self.record_at(pos)
+            {
+                let record_len = RECORD_HEADER_LEN + len;
+                let owner = self.owner_at(pos);
+                let waiting = fair && served.contains(&Some(owner));
+                let ready = !waiting
+                    && !blocked.contains(&Some(eid))
+                    && self.bucket_mut(eid).is_none_or(|b| b.take(len, now_millis));
+                let sent = ready && {
+                    let pkt = self
+                        .buf
+                        .as_deref()
+                        .and_then(|b| b.get(pos + RECORD_HEADER_LEN..pos + record_len))
+                        .unwrap_or_default();
+                    match send(eid, pkt) {
+                        Ok(()) => {
+                            released += 1;
+                            true
+                        }
+                        Err(busy) => !busy,
+                    }
+                };
+                if sent {
+                    self.remove(pos, record_len);
+                    progress = true;
+                    if !fair {
+                        continue;
+                    }
+                    match served.iter_mut().find(|s| s.is_none()) {
+                        Some(slot) => *slot = Some(owner),
+                        None => break,
+                    }
+                } else {
+                    if !waiting && let Some(slot) = blocked.iter_mut().find(|b| b.is_none()) {
+                        *slot = Some(eid);
+                    }
+                    pos += record_len;
+                }
+            }
+            if !fair || !progress || released >= max {
+                return released;
+            }
+        }
+    }
+
+    /// Get the earliest time a held packet can be released or expires
+    pub(crate) fn next_deadline(&self) -> Option<u64> {
+        let mut seen = [None; THROTTLE_TABLE_SIZE];
+        let mut next: Option<u64> = None;
+        let mut pos = 0;
+        while let Some((_, len)) = self.record_at(pos) {
+            if let Some((_, deadline)) = self.expiry_at(pos) {
+                next = Some(next.map_or(deadline, |n| n.min(deadline)));
+            }
+            pos += RECORD_HEADER_LEN + len;
+        }
+        for (eid, len) in self.records() {
+            if seen.contains(&Some(eid)) {
+                continue;
+            }
+            if let Some(slot) = seen.iter_mut().find(|s| s.is_none()) {
+                *slot = Some(eid);
+            }
+            let ready = match self.bucket(eid) {
+                Some(bucket) => bucket.ready_millis(len),
+                None => Some(0),
+            };
+            if let Some(ready) = ready {
+                next = Some(next.map_or(ready, |n| n.min(ready)));
+            }
+        }
+        next
+    }
+
+    fn bucket(&self, eid: Eid) -> Option<&Bucket> {
+        self.buckets.iter().flatten().find(|b| b.eid == eid)
+    }
+
+    fn bucket_mut(&mut self, eid: Eid) -> Option<&mut Bucket> {
+        self.buckets.iter_mut().flatten().find(|b| b.eid == eid)
+    }
+
+    /// Get the destination and length of the record at `pos`
+    fn record_at(&self, pos: usize) -> Option<(Eid, usize)> {
+        if pos >= self.used {
+            return None;
+        }
+        let buf = self.buf.as_deref()?;
+        let &[eid, len0, len1] = buf.get(pos..pos + RECORD_DEADLINE_OFFSET)? else {
+            return None;
+        };
+        Some((Eid(eid), u16::from_le_bytes([len0, len1]).into()))
+    }
+
+    /// Get the cookie and deadline of the message of the record at `pos`, if it has one
+    fn expiry_at(&self, pos: usize) -> Option<(AppCookie, u64)> {
+        let header = self
+            .buf
+            .as_deref()?
+            .get(pos + RECORD_DEADLINE_OFFSET..pos + RECORD_HEADER_LEN)?;
+        let (deadline, cookie) = header.split_first_chunk::<8>()?;
+        let cookie = cookie.first_chunk::<8>()?;
+        let deadline = u64::from_le_bytes(*deadline);
+        (deadline != NO_DEADLINE)
+            .then(|| (AppCookie(u64::from_le_bytes(*cookie) as usize), deadline))
+    }
+
+    /// Get the raw cookie of the handle sending the record at `pos`
+    fn owner_at(&self, pos: usize) -> u64 {
+        self.buf
+            .as_deref()
+            .and_then(|b| b.get(pos + RECORD_DEADLINE_OFFSET + 8..pos + RECORD_HEADER_LEN))
+            .and_then(|c| c.first_chunk::<8>())
+            .map_or(NO_COOKIE, |c| u64::from_le_bytes(*c))
+    }
+
+    /// Iterate over the destination and length of the held packets
+    fn records(&self) -> impl Iterator<Item = (Eid, usize)> + '_ {
+        let mut pos = 0;
+        core::iter::from_fn(move || {
+            let (eid, len) = self.record_at(pos)?;
+            pos += RECORD_HEADER_LEN + len;
+            Some((eid, len))
+        })
+    }
+
+    /// Remove the record of `len` bytes at `pos`, moving the following ones forward
+    fn remove(&mut self, pos: usize, len: usize) {
+        if let Some(buf) = self.buf.as_deref_mut()
+            && pos + len <= self.used
+        {
+            buf.copy_within(pos + len..self.used, pos);
+            self.used -= len;
+        }
+    }
+}
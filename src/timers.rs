@@ -0,0 +1,97 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Earliest deadline of the timers kept by the router
+//!
+//! Every part of the router with timeouts (the stack, requests, retained messages,
+//! reassemblies into listener buffers, reservations and liveness probes) reports its next
+//! deadline, so [update()](crate::GenericRouter::update) returns the time until the earliest
+//! one rather than a fixed interval.
+//!
+//! A [WorkBudget] bounds the timers handled per call.
+
+/// The earliest of a set of deadlines
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct NextDeadline {
+    now_millis: u64,
+    next_millis: Option<u64>,
+}
+
+impl NextDeadline {
+    pub(crate) fn new(now_millis: u64) -> Self {
+        NextDeadline {
+            now_millis,
+            next_millis: None,
+        }
+    }
+
+    /// Account for a deadline at `deadline_millis`, deadlines in the past are due now
+    pub(crate) fn at(&mut self, deadline_millis: Option<u64>) {
+        if let Some(deadline) = deadline_millis {
+            let deadline = deadline.max(self.now_millis);
+            self.next_millis = Some(self.next_millis.map_or(deadline, |n| n.min(deadline)));
+        }
+    }
+
+    /// Account for a deadline `interval_millis` from now
+    pub(crate) fn after(&mut self, interval_millis: Option<u64>) {
+        self.at(interval_millis.map(|i| self.now_millis.saturating_add(i)))
+    }
+
+    /// Get the time until the earliest deadline
+    ///
+    /// Returns `None` without any deadline.
+    pub(crate) fn interval(&self) -> Option<u64> {
+        self.next_millis.map(|n| n - self.now_millis)
+    }
+}
+
+/// Limits of the work done by a single [update()](crate::GenericRouter::update) or
+/// [poll()](crate::GenericRouter::poll)
+///
+/// Real-time firmware can bound the time spent in the router per tick. Work beyond the
+/// budget is left for the next call, which is then due at once (`poll()` returns 0).
+/// `None` leaves a kind of work unlimited, as in the default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct WorkBudget {
+    /// Timers handled: requests timing out or reclaiming their tag, stalled sends and
+    /// retained messages expiring
+    pub timers: Option<usize>,
+    /// Packets held back by a [Throttle](crate::Throttle) passed to the sender, fragments of
+    /// local messages and forwarded packets alike
+    pub packets: Option<usize>,
+}
+
+/// What is left of a [WorkBudget] limit in the current call
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Allowance(usize);
+
+impl Allowance {
+    pub(crate) fn new(limit: Option<usize>) -> Self {
+        Allowance(limit.unwrap_or(usize::MAX))
+    }
+
+    /// Take one unit of work, returns `false` once the allowance is used up
+    pub(crate) fn take(&mut self) -> bool {
+        let left = self.0 > 0;
+        self.0 = self.0.saturating_sub(1);
+        left
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Endpoints learned from control responses, for bus owners and bridges
+//!
+//! A [ControlRequester](crate::control::ControlRequester) records what it learns about its
+//! peers: EIDs assigned with Set Endpoint ID, UUIDs and supported message types. Together with
+//! the routing table and the liveness monitor this forms the inventory returned by
+//! [topology()](crate::GenericRouter::topology), without querying the bus again.
+
+use mctp::{Eid, Error, Result};
+
+use crate::control::{ControlResponse, MessageTypes};
+use crate::{PeerState, Route};
+
+/// Number of endpoints a [Router](crate::Router) keeps in its topology
+pub const TOPOLOGY_TABLE_SIZE: usize = 16;
+
+/// An endpoint in the topology, see [topology()](crate::GenericRouter::topology)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TopologyEntry {
+    /// EID of the endpoint
+    pub eid: Eid,
+    /// The EID was assigned by this endpoint with Set Endpoint ID
+    pub assigned: bool,
+    /// UUID reported by Get Endpoint UUID
+    pub uuid: Option<[u8; 16]>,
+    /// Message types reported by Get Message Type Support
+    pub message_types: Option<MessageTypes>,
+    /// Static route to the endpoint, with its physical address
+    pub route: Option<Route>,
+    /// Liveness, if the endpoint is monitored
+    pub state: Option<PeerState>,
+}
+
+/// What was learned about an endpoint
+#[derive(Debug, Clone, Copy)]
+struct Learned {
+    eid: Eid,
+    assigned: bool,
+    uuid: Option<[u8; 16]>,
+    message_types: Option<MessageTypes>,
+}
+
+/// The endpoints learned so far
+#[derive(Debug)]
+pub(crate) struct Topology {
+    endpoints: [Option<Learned>; TOPOLOGY_TABLE_SIZE],
+}
+
+impl Topology {
+    pub(crate) const fn new() -> Self {
+        Topology {
+            endpoints: [None; TOPOLOGY_TABLE_SIZE],
+        }
+    }
+
+    /// Record `response` of `peer`
+    ///
+    /// Returns [NoSpace](Error::NoSpace) if the table is full.
+    pub(crate) fn learn(&mut self, peer: Eid, response: &ControlResponse) -> Result<()> {
+        match *response {
+            ControlResponse::EndpointId { eid, .. } => {
+                self.entry(eid)?;
+            }
+            ControlResponse::SetEndpointId {
+                accepted: true,
+                eid,
+                ..
+            } => {
+                if eid != peer {
+                    self.forget(peer);
+                }
+                self.entry(eid)?.assigned = true;
+            }
+            ControlResponse::Uuid(uuid) => self.entry(peer)?.uuid = Some(uuid),
+            ControlResponse::MessageTypes(types) => self.entry(peer)?.message_types = Some(types),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Remove `eid`, returning whether it was known
+    pub(crate) fn forget(&mut self, eid: Eid) -> bool {
+        let mut found = false;
+        for slot in self.endpoints.iter_mut() {
+            if slot.is_some_and(|e| e.eid == eid) {
+                *slot = None;
+                found = true;
+            }
+        }
+        found
+    }
+
+    /// Iterate the known endpoints, completing them with `route` and `state`
+    pub(crate) fn entries<'a>(
+        &'a self,
+        route: impl Fn(Eid) -> Option<Route> + 'a,
+        state: impl Fn(Eid) -> Option<PeerState> + 'a,
+    ) -> impl Iterator<Item = TopologyEntry> + 'a {
+        self.endpoints.iter().flatten().map(move |e| TopologyEntry {
+            eid: e.eid,
+            assigned: e.assigned,
+            uuid: e.uuid,
+            message_types: e.message_types,
+            route: route(e.eid),
+            state: state(e.eid),
+        })
+    }
+
+    /// Get the entry for `eid`, adding it if it is not known yet
+    fn entry(&mut self, eid: Eid) -> Result<&mut Learned> {
+        let i = match self
+            .endpoints
+            .iter()
+            .position(|e| e.is_some_and(|e| e.eid == eid))
+        {
+            Some(i) => i,
+            None => self
+                .endpoints
+                .iter()
+                .position(|e| e.is_none())
+                .ok_or(Error::NoSpace)?,
+        };
+        let slot = self.endpoints.get_mut(i).ok_or(Error::NoSpace)?;
+        Ok(slot.get_or_insert(Learned {
+            eid,
+            assigned: false,
+            uuid: None,
+            message_types: None,
+        }))
+    }
+}
@@ -0,0 +1,296 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Channels typed by a well-known MCTP message type
+//!
+//! Each message type assigned in DSP0239 has a marker type implementing [MessageKind].
+//! [TypedListener] and [TypedRequester] wrap the channels of a
+//! [SharedRouter](crate::shared::SharedRouter) and only send and receive messages of that
+//! type.
+//!
+//! Messages are passed either as payload, or "framed" with the message type byte (the
+//! message type and the integrity check flag in the top bit) in front, as many protocol
+//! crates expect:
+//!
+//! ```
+//! use mctp::{Eid, MsgIC};
+//! use mctp_lib::Router;
+//! use mctp_lib::shared::SharedRouter;
+//! use mctp_lib::test_util::NullSender;
+//! use mctp_lib::typed::{Pldm, TypedListener, TypedRequester};
+//!
+//! let router: Router<_, 1, 1> = Router::new(Eid(8), 0, NullSender);
+//! let router = SharedRouter::new(router);
+//! let listener = TypedListener::<Pldm, _, _, _, _>::bind(&router).unwrap();
+//! let requester = TypedRequester::<Pldm, _, _, _, _>::new(router.req(Eid(9)).unwrap());
+//! // A framed message with a different type is refused
+//! assert!(requester.send_framed(&[0x05, 0x10, 0x84]).is_err());
+//! requester.send_framed(&[0x01, 0x80, 0x00, 0x01]).unwrap();
+//! # let _ = listener;
+//! ```
+
+use core::marker::PhantomData;
+
+use mctp::{Error, MsgIC, MsgType};
+
+use crate::shared::{SharedListener, SharedRequest, SharedRouter};
+use crate::{
+    Clock, HandleTables, Hooks, ListenerHandle, MessageInfo, RequestHandle, RouterError,
+    RouterResult, SendReport, Sender,
+};
+
+/// MCTP message type of NC-SI messages
+pub const MSG_TYPE_NCSI: MsgType = MsgType(0x02);
+/// MCTP message type of Ethernet frames
+pub const MSG_TYPE_ETHERNET: MsgType = MsgType(0x03);
+/// MCTP message type of NVMe Management messages
+pub const MSG_TYPE_NVME_MI: MsgType = MsgType(0x04);
+/// MCTP message type of PCI vendor defined messages
+pub const MSG_TYPE_VENDOR_PCI: MsgType = MsgType(0x7e);
+/// MCTP message type of IANA vendor defined messages
+pub const MSG_TYPE_VENDOR_IANA: MsgType = MsgType(0x7f);
+
+const IC_FLAG: u8 = 0x80;
+
+/// A well-known MCTP message type
+pub trait MessageKind {
+    /// The message type
+    const TYPE: MsgType;
+    /// Name of the protocol, e.g. for logs
+    const NAME: &'static str;
+}
+
+macro_rules! message_kinds {
+    ($($(#[$doc:meta])* $name:ident = $typ:expr, $label:literal;)*) => {
+        $(
+            $(#[$doc])*
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            pub struct $name;
+
+            impl MessageKind for $name {
+                const TYPE: MsgType = $typ;
+                const NAME: &'static str = $label;
+            }
+        )*
+
+        /// Get the protocol name of the well-known message type `typ`
+        pub fn name(typ: MsgType) -> Option<&'static str> {
+            $(
+                if typ == $typ {
+                    return Some($label);
+                }
+            )*
+            None
+        }
+    };
+}
+
+message_kinds! {
+    /// MCTP control messages
+    Control = crate::control::MSG_TYPE_CONTROL, "MCTP Control";
+    /// PLDM
+    Pldm = crate::pldm::MSG_TYPE_PLDM, "PLDM";
+    /// NC-SI
+    Ncsi = MSG_TYPE_NCSI, "NC-SI";
+    /// Ethernet
+    Ethernet = MSG_TYPE_ETHERNET, "Ethernet";
+    /// NVMe Management Interface
+    NvmeMi = MSG_TYPE_NVME_MI, "NVMe-MI";
+    /// SPDM
+    Spdm = crate::spdm::MSG_TYPE_SPDM, "SPDM";
+    /// Secured messages
+    Secured = crate::secured::MSG_TYPE_SECURED, "Secured Messages";
+    /// CXL Fabric Manager API
+    CxlFmApi = crate::cci::MSG_TYPE_CXL_FM_API, "CXL FM API";
+    /// CXL Component Command Interface
+    CxlCci = crate::cci::MSG_TYPE_CXL_CCI, "CXL CCI";
+    /// PCI vendor defined messages
+    VendorPci = MSG_TYPE_VENDOR_PCI, "Vendor Defined - PCI";
+    /// IANA vendor defined messages
+    VendorIana = MSG_TYPE_VENDOR_IANA, "Vendor Defined - IANA";
+}
+
+/// Split a framed message of type `M` into integrity check flag and payload
+fn unframe<M: MessageKind>(msg: &[u8]) -> Result<(MsgIC, &[u8]), Error> {
+    let (&typ, payload) = msg.split_first().ok_or(Error::InvalidInput)?;
+    if MsgType(typ & !IC_FLAG) != M::TYPE {
+        return Err(Error::InvalidInput);
+    }
+    Ok((MsgIC(typ & IC_FLAG != 0), payload))
+}
+
+/// Receive a payload into `buf` behind the message type byte
+fn recv_framed(
+    buf: &mut [u8],
+    recv: impl FnOnce(&mut [u8]) -> RouterResult<Option<MessageInfo>>,
+) -> RouterResult<Option<(MessageInfo, usize)>> {
+    let (header, body) = buf.split_first_mut().ok_or(Error::NoSpace)?;
+    let Some(info) = recv(body)? else {
+        return Ok(None);
+    };
+    *header = info.typ.0 | if info.ic.0 { IC_FLAG } else { 0 };
+    Ok(Some((info, 1 + info.len)))
+}
+
+/// A listener for messages of type `M`
+#[derive(Debug)]
+pub struct TypedListener<'r, M: MessageKind, S: Sender, T: HandleTables, H: Hooks, C: Clock> {
+    listener: SharedListener<'r, S, T, H, C>,
+    kind: PhantomData<M>,
+}
+
+impl<M: MessageKind, S: Sender, T: HandleTables, H: Hooks, C: Clock> Clone
+    for TypedListener<'_, M, S, T, H, C>
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<M: MessageKind, S: Sender, T: HandleTables, H: Hooks, C: Clock> Copy
+    for TypedListener<'_, M, S, T, H, C>
+{
+}
+
+impl<'r, M: MessageKind, S: Sender, T: HandleTables, H: Hooks, C: Clock>
+    TypedListener<'r, M, S, T, H, C>
+{
+    /// Bind a listener for the type `M` on `router`
+    pub fn bind(router: &'r SharedRouter<S, T, H, C>) -> mctp::Result<Self> {
+        Ok(TypedListener {
+            listener: router.listener(M::TYPE)?,
+            kind: PhantomData,
+        })
+    }
+
+    /// Get the handle of the listener
+    pub fn handle(&self) -> ListenerHandle {
+        self.listener.handle()
+    }
+
+    /// Receive a request payload into `buf` without blocking
+    ///
+    /// Returns `Ok(None)` when no request is available.
+    pub fn try_recv(&self, buf: &mut [u8]) -> RouterResult<Option<MessageInfo>> {
+        self.listener.try_recv(buf)
+    }
+
+    /// Receive a framed request into `buf` without blocking
+    ///
+    /// Returns the length of the framed message, `Ok(None)` when no request is available.
+    pub fn try_recv_framed(&self, buf: &mut [u8]) -> RouterResult<Option<(MessageInfo, usize)>> {
+        recv_framed(buf, |body| self.listener.try_recv(body))
+    }
+
+    /// Respond to `request` with the payload `bufs`
+    pub fn respond(&self, request: &MessageInfo, ic: MsgIC, bufs: &[&[u8]]) -> RouterResult<()> {
+        let request = MessageInfo {
+            typ: M::TYPE,
+            ..*request
+        };
+        self.listener.respond(&request, ic, bufs)
+    }
+
+    /// Respond to `request` with the framed message `msg`
+    ///
+    /// Returns [InvalidInput](Error::InvalidInput) if `msg` is not of type `M`.
+    pub fn respond_framed(&self, request: &MessageInfo, msg: &[u8]) -> RouterResult<()> {
+        let (ic, payload) = unframe::<M>(msg)
+            .map_err(|e| RouterError::from(e).with_handle(self.handle().into()))?;
+        self.respond(request, ic, &[payload])
+    }
+
+    /// Unbind the listener
+    pub fn unbind(self) -> RouterResult<()> {
+        self.listener.unbind()
+    }
+}
+
+/// A requester for messages of type `M`
+///
+/// Responses of other types are discarded.
+#[derive(Debug)]
+pub struct TypedRequester<'r, M: MessageKind, S: Sender, T: HandleTables, H: Hooks, C: Clock> {
+    channel: SharedRequest<'r, S, T, H, C>,
+    kind: PhantomData<M>,
+}
+
+impl<M: MessageKind, S: Sender, T: HandleTables, H: Hooks, C: Clock> Clone
+    for TypedRequester<'_, M, S, T, H, C>
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<M: MessageKind, S: Sender, T: HandleTables, H: Hooks, C: Clock> Copy
+    for TypedRequester<'_, M, S, T, H, C>
+{
+}
+
+impl<'r, M: MessageKind, S: Sender, T: HandleTables, H: Hooks, C: Clock>
+    TypedRequester<'r, M, S, T, H, C>
+{
+    /// Send requests of type `M` through `channel`
+    pub fn new(channel: SharedRequest<'r, S, T, H, C>) -> Self {
+        TypedRequester {
+            channel,
+            kind: PhantomData,
+        }
+    }
+
+    /// Get the handle of the request
+    pub fn handle(&self) -> RequestHandle {
+        self.channel.handle()
+    }
+
+    /// Send a request with the payload `bufs`, allocating a new tag
+    pub fn send(&self, ic: MsgIC, bufs: &[&[u8]]) -> RouterResult<SendReport> {
+        self.channel.send(M::TYPE, ic, bufs)
+    }
+
+    /// Send the framed request `msg`, allocating a new tag
+    ///
+    /// Returns [InvalidInput](Error::InvalidInput) if `msg` is not of type `M`.
+    pub fn send_framed(&self, msg: &[u8]) -> RouterResult<SendReport> {
+        let (ic, payload) = unframe::<M>(msg)
+            .map_err(|e| RouterError::from(e).with_handle(self.handle().into()))?;
+        self.send(ic, &[payload])
+    }
+
+    /// Receive a response payload into `buf` without blocking
+    ///
+    /// Returns `Ok(None)` when no response of type `M` is available.
+    pub fn try_recv(&self, buf: &mut [u8]) -> RouterResult<Option<MessageInfo>> {
+        loop {
+            match self.channel.try_recv(buf)? {
+                Some(info) if info.typ != M::TYPE => continue,
+                received => return Ok(received),
+            }
+        }
+    }
+
+    /// Receive a framed response into `buf` without blocking
+    ///
+    /// Returns the length of the framed message, `Ok(None)` when no response of type `M` is
+    /// available.
+    pub fn try_recv_framed(&self, buf: &mut [u8]) -> RouterResult<Option<(MessageInfo, usize)>> {
+        recv_framed(buf, |body| self.try_recv(body))
+    }
+
+    /// Release the request
+    pub fn unbind(self) -> RouterResult<()> {
+        self.channel.unbind()
+    }
+}
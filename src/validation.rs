@@ -0,0 +1,197 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Validation of inbound packets against the MCTP base specification
+
+use mctp::Eid;
+
+use crate::header::{HEADER_LEN, Header};
+
+/// Number of [Violation] classes
+pub const VIOLATION_CLASSES: usize = 3;
+
+/// A class of out-of-spec inbound packets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Violation {
+    /// The reserved bits of the first header byte are set
+    ReservedHeaderBits,
+    /// The source EID is reserved (1 to 7) or the broadcast EID
+    InvalidSourceEid,
+    /// The destination EID is reserved (1 to 7)
+    ReservedDestEid,
+}
+
+impl Violation {
+    /// All violation classes
+    pub const ALL: [Violation; VIOLATION_CLASSES] = [
+        Violation::ReservedHeaderBits,
+        Violation::InvalidSourceEid,
+        Violation::ReservedDestEid,
+    ];
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// How a [Router](crate::Router) treats out-of-spec packets
+///
+/// Each [Violation] class is either rejected, dropping the packet with
+/// [DroppedInvalid](crate::Disposition::DroppedInvalid), or accepted on a best-effort basis.
+/// Violations are counted in both cases, see
+/// [violations()](crate::GenericRouter::violations).
+///
+/// [lenient()](Self::lenient), the default, accepts all classes; packets with reserved header
+/// bits set are processed as if the bits were clear.
+/// [strict()](Self::strict) rejects all classes, e.g. for conformance testing.
+///
+/// With the `serde` feature, a validation is represented by a flag per class, e.g.
+/// `{ reserved_header_bits = true }` rejecting packets with reserved header bits set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "Rejects", into = "Rejects")
+)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Validation {
+    reject: u8,
+}
+
+impl Validation {
+    /// Reject all violations
+    pub const fn strict() -> Self {
+        Validation { reject: !0 }
+    }
+
+    /// Accept all violations
+    pub const fn lenient() -> Self {
+        Validation { reject: 0 }
+    }
+
+    /// Set whether packets with `violation` are rejected
+    pub fn reject(mut self, violation: Violation, reject: bool) -> Self {
+        if reject {
+            self.reject |= violation.bit();
+        } else {
+            self.reject &= !violation.bit();
+        }
+        self
+    }
+
+    /// Check whether packets with `violation` are rejected
+    pub fn rejects(&self, violation: Violation) -> bool {
+        self.reject & violation.bit() != 0
+    }
+}
+
+impl Default for Validation {
+    fn default() -> Self {
+        Self::lenient()
+    }
+}
+
+/// `serde` representation of a [Validation], whether each class is rejected
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct Rejects {
+    reserved_header_bits: bool,
+    invalid_source_eid: bool,
+    reserved_dest_eid: bool,
+}
+
+#[cfg(feature = "serde")]
+impl Default for Rejects {
+    fn default() -> Self {
+        Validation::default().into()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Validation> for Rejects {
+    fn from(v: Validation) -> Self {
+        Rejects {
+            reserved_header_bits: v.rejects(Violation::ReservedHeaderBits),
+            invalid_source_eid: v.rejects(Violation::InvalidSourceEid),
+            reserved_dest_eid: v.rejects(Violation::ReservedDestEid),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Rejects> for Validation {
+    fn from(r: Rejects) -> Self {
+        Validation::lenient()
+            .reject(Violation::ReservedHeaderBits, r.reserved_header_bits)
+            .reject(Violation::InvalidSourceEid, r.invalid_source_eid)
+            .reject(Violation::ReservedDestEid, r.reserved_dest_eid)
+    }
+}
+
+/// Counters of detected violations, indexed by class
+#[derive(Debug, Default)]
+pub(crate) struct ViolationCounters {
+    counts: [usize; VIOLATION_CLASSES],
+}
+
+impl ViolationCounters {
+    pub(crate) fn count(&mut self, violation: Violation) {
+        if let Some(c) = self.counts.get_mut(violation as usize) {
+            *c = c.wrapping_add(1);
+        }
+    }
+
+    pub(crate) fn get(&self, violation: Violation) -> usize {
+        self.counts.get(violation as usize).copied().unwrap_or(0)
+    }
+}
+
+const RESERVED_BITS: u8 = 0xf0;
+
+/// Check `pkt` for violations, calling `found` for each one
+pub(crate) fn check(pkt: &[u8], mut found: impl FnMut(Violation)) {
+    let Some(hdr) = Header::parse(pkt) else {
+        return;
+    };
+    if pkt.first().is_some_and(|b| b & RESERVED_BITS != 0) {
+        found(Violation::ReservedHeaderBits);
+    }
+    if is_reserved(hdr.source) || hdr.source == Eid(0xff) {
+        found(Violation::InvalidSourceEid);
+    }
+    if is_reserved(hdr.dest) {
+        found(Violation::ReservedDestEid);
+    }
+}
+
+/// Copy `pkt` to `buf` with the reserved header bits cleared
+///
+/// Returns `None` if `pkt` doesn't fit.
+pub(crate) fn clear_reserved<'b>(pkt: &[u8], buf: &'b mut [u8]) -> Option<&'b [u8]> {
+    let out = buf.get_mut(..pkt.len())?;
+    out.copy_from_slice(pkt);
+    if let Some(b) = out.first_mut()
+        && pkt.len() >= HEADER_LEN
+    {
+        *b &= !RESERVED_BITS;
+    }
+    Some(out)
+}
+
+fn is_reserved(eid: Eid) -> bool {
+    (1..=7).contains(&eid.0)
+}
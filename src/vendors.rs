@@ -0,0 +1,97 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Vendor command sets reported by Get Vendor Defined Message Support
+
+use mctp::{Error, Result};
+
+use crate::control::{VendorId, VendorSupport};
+
+/// Number of vendor command sets a [Router](crate::Router) reports
+pub const VENDOR_TABLE_SIZE: usize = 4;
+
+/// Registered vendor command sets, in the order of their vendor ID set selectors
+#[derive(Debug)]
+pub(crate) struct Vendors {
+    sets: [Option<VendorSupport>; VENDOR_TABLE_SIZE],
+}
+
+impl Vendors {
+    pub(crate) const fn new() -> Self {
+        Vendors {
+            sets: [None; VENDOR_TABLE_SIZE],
+        }
+    }
+
+    /// Register `support`, unless it is registered already
+    ///
+    /// Returns [NoSpace](Error::NoSpace) if the table is full.
+    pub(crate) fn add(&mut self, support: VendorSupport) -> Result<()> {
+        if self.sets.iter().flatten().any(|s| *s == support) {
+            return Ok(());
+        }
+        let slot = self
+            .sets
+            .iter_mut()
+            .find(|s| s.is_none())
+            .ok_or(Error::NoSpace)?;
+        *slot = Some(support);
+        Ok(())
+    }
+
+    /// Remove all command sets of `vendor`, keeping the others in order
+    ///
+    /// Returns whether any was registered.
+    pub(crate) fn remove(&mut self, vendor: VendorId) -> bool {
+        self.retain(|s| s.vendor != vendor)
+    }
+
+    /// Keep the command sets `keep` returns `true` for, in order
+    ///
+    /// Returns whether any was removed.
+    pub(crate) fn retain(&mut self, keep: impl Fn(&VendorSupport) -> bool) -> bool {
+        let before = self.sets.iter().flatten().count();
+        let mut kept = [None; VENDOR_TABLE_SIZE];
+        for (slot, set) in kept
+            .iter_mut()
+            .zip(self.sets.iter().flatten().filter(|s| keep(s)))
+        {
+            *slot = Some(*set);
+        }
+        self.sets = kept;
+        self.sets.iter().flatten().count() != before
+    }
+
+    /// Check whether `support` is registered
+    pub(crate) fn contains(&self, support: &VendorSupport) -> bool {
+        self.sets.iter().flatten().any(|s| s == support)
+    }
+
+    /// Get the number of command sets that can still be registered
+    pub(crate) fn free(&self) -> usize {
+        self.sets.iter().filter(|s| s.is_none()).count()
+    }
+
+    /// Get the command set for `selector` and the selector of the next one
+    pub(crate) fn get(&self, selector: u8) -> Option<(VendorSupport, Option<u8>)> {
+        let index = usize::from(selector);
+        let support = self.sets.get(index).copied().flatten()?;
+        let next = self
+            .sets
+            .get(index + 1)
+            .is_some_and(|s| s.is_some())
+            .then_some(selector + 1);
+        Some((support, next))
+    }
+}
@@ -0,0 +1,96 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Protocol versions reported by Get MCTP Version Support
+//!
+//! Versions are registered per message type, with `0xff` standing for the base
+//! specification. The base specification and control messages are registered from the start.
+
+use mctp::{Error, Result};
+
+use crate::control::MSG_TYPE_CONTROL;
+use crate::control::codec::{GetVersionSupportResponse, MAX_VERSIONS};
+
+/// Number of message types a [Router](crate::Router) reports versions for, including the
+/// base specification and control messages
+pub const VERSION_TABLE_SIZE: usize = 8;
+
+/// Message type queried for the version of the base specification
+pub const VERSION_BASE_SPEC: u8 = 0xff;
+
+/// DSP0236 1.3.1, the base specification implemented by the stack
+pub const BASE_SPEC_VERSION: [u8; 4] = [0xf1, 0xf3, 0xf1, 0x00];
+
+/// Registered versions by queried message type
+#[derive(Debug)]
+pub(crate) struct Versions {
+    entries: [Option<(u8, GetVersionSupportResponse)>; VERSION_TABLE_SIZE],
+}
+
+impl Versions {
+    pub(crate) fn new() -> Self {
+        let mut versions = Versions {
+            entries: [None; VERSION_TABLE_SIZE],
+        };
+        for typ in [VERSION_BASE_SPEC, MSG_TYPE_CONTROL.0] {
+            let _ = versions.set(typ, &[BASE_SPEC_VERSION]);
+        }
+        versions
+    }
+
+    /// Register `versions` for the message type `typ`, replacing previous ones
+    ///
+    /// An empty `versions` removes the message type.
+    /// Returns [NoSpace](Error::NoSpace) for more than [MAX_VERSIONS] versions or when
+    /// the table is full.
+    pub(crate) fn set(&mut self, typ: u8, versions: &[[u8; 4]]) -> Result<()> {
+        if versions.is_empty() {
+            for slot in self.entries.iter_mut() {
+                if slot.is_some_and(|e| e.0 == typ) {
+                    *slot = None;
+                }
+            }
+            return Ok(());
+        }
+        if versions.len() > MAX_VERSIONS {
+            return Err(Error::NoSpace);
+        }
+        let entry = (typ, GetVersionSupportResponse::new(versions)?);
+        let slot = match self
+            .entries
+            .iter()
+            .position(|e| e.is_some_and(|e| e.0 == typ))
+        {
+            Some(i) => self.entries.get_mut(i),
+            None => self.entries.iter_mut().find(|e| e.is_none()),
+        }
+        .ok_or(Error::NoSpace)?;
+        *slot = Some(entry);
+        Ok(())
+    }
+
+    /// Check whether versions can be set for `typ` without evicting another type
+    pub(crate) fn has_room(&self, typ: u8) -> bool {
+        self.entries.iter().any(|e| e.is_none_or(|e| e.0 == typ))
+    }
+
+    /// Get the versions registered for the message type `typ`
+    pub(crate) fn get(&self, typ: u8) -> Option<&GetVersionSupportResponse> {
+        self.entries
+            .iter()
+            .flatten()
+            .find(|e| e.0 == typ)
+            .map(|e| &e.1)
+    }
+}
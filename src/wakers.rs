@@ -0,0 +1,93 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Wakers of tasks waiting on handles
+//!
+//! Registered by [poll_recv()](crate::GenericRouter::poll_recv) and
+//! [poll_send_complete()](crate::GenericRouter::poll_send_complete) and woken when the
+//! router delivers a message to the handle, an operation of the handle expires or the handle
+//! is reset or unbound. Executor agnostic, only [core::task] is used.
+
+use core::task::Waker;
+
+use crate::Handle;
+
+/// Number of tasks that can wait on handles of a [Router](crate::Router) at the same time
+///
+/// When the table is full, the polling task is woken right away and polls again later.
+pub const WAKER_TABLE_SIZE: usize = 8;
+
+/// What a task waits for on a handle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Interest {
+    Recv,
+    SendComplete,
+}
+
+#[derive(Debug)]
+pub(crate) struct Wakers {
+    slots: [Option<(Handle, Interest, Waker)>; WAKER_TABLE_SIZE],
+}
+
+impl Wakers {
+    pub(crate) const fn new() -> Self {
+        Wakers {
+            slots: [const { None }; WAKER_TABLE_SIZE],
+        }
+    }
+
+    /// Wake `waker` on the next event of `handle`, replacing the waker registered before
+    ///
+    /// Wakes `waker` right away if the table is full.
+    pub(crate) fn register(&mut self, handle: Handle, interest: Interest, waker: &Waker) {
+        let existing = self
+            .slots
+            .iter_mut()
+            .flatten()
+            .find(|(h, i, _)| (*h, *i) == (handle, interest));
+        if let Some((_, _, w)) = existing {
+            w.clone_from(waker);
+            return;
+        }
+        match self.slots.iter_mut().find(|s| s.is_none()) {
+            Some(slot) => *slot = Some((handle, interest, waker.clone())),
+            None => waker.wake_by_ref(),
+        }
+    }
+
+    /// Forget `waker` if it is the one registered for `interest` in `handle`
+    ///
+    /// Another task that registered for the same handle and interest since is kept.
+    pub(crate) fn cancel(&mut self, handle: Handle, interest: Interest, waker: &Waker) {
+        for slot in self.slots.iter_mut() {
+            if slot
+                .as_ref()
+                .is_some_and(|(h, i, w)| (*h, *i) == (handle, interest) && w.will_wake(waker))
+            {
+                *slot = None;
+            }
+        }
+    }
+
+    /// Wake all tasks waiting on `handle`
+    pub(crate) fn wake(&mut self, handle: Handle) {
+        for slot in self.slots.iter_mut() {
+            if slot.as_ref().is_some_and(|(h, _, _)| *h == handle)
+                && let Some((_, _, waker)) = slot.take()
+            {
+                waker.wake();
+            }
+        }
+    }
+}
@@ -0,0 +1,345 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Command line tool sending MCTP control commands and capturing traffic
+//!
+//! A reference application of the [Router](mctp_lib::Router) on a host, in the manner of
+//! `mctp-util`. Requires the `util` feature.
+//!
+//! Packets are carried with the framing of the serial binding (DSP0253), either over a
+//! serial device or a TCP connection tunneling the byte stream (e.g. `socat` bridging a
+//! PTY, or a QEMU `-chardev socket`).
+//!
+//! ```text
+//! mctp-util (--serial PATH | --tcp HOST:PORT) [OPTIONS] COMMAND
+//!
+//! Options:
+//!   --eid EID          own EID (default 8)
+//!   --timeout MS       time to wait for a response (default 1000)
+//!   --capture FILE     write all packets to FILE in pcapng format
+//!   -v                 print all packets
+//!
+//! Commands:
+//!   get-eid DEST
+//!   get-uuid DEST
+//!   get-types DEST
+//!   get-versions DEST [TYPE]       TYPE defaults to the base specification (0xff)
+//!   get-vendors DEST [SELECTOR]
+//!   raw DEST TYPE [BYTE...]        send a request of message type TYPE, dump the response
+//!   listen SECONDS                 only capture traffic
+//! ```
+//!
+//! Numbers are decimal or hexadecimal with a `0x` prefix.
+
+use std::env;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::net::TcpStream;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+use std::thread::{sleep, spawn};
+use std::time::{Duration, Instant};
+
+use embedded_io_adapters::std::FromStd;
+use mctp::{Eid, MsgType};
+use mctp_lib::control::{self, ControlHeader, ControlResponse};
+use mctp_lib::pcapng::PcapngWriter;
+use mctp_lib::serial::MctpSerialHandler;
+use mctp_lib::{Direction, Sender, VERSION_BASE_SPEC};
+use standalone::Stack;
+use standalone::serial_sender::IoSerialSender;
+use standalone::util::update_loop;
+
+const USAGE: &str = "usage: mctp-util (--serial PATH | --tcp HOST:PORT) [--eid EID] \
+[--timeout MS] [--capture FILE] [-v] COMMAND [ARGS...]
+commands: get-eid DEST, get-uuid DEST, get-types DEST, get-versions DEST [TYPE],
+          get-vendors DEST [SELECTOR], raw DEST TYPE [BYTE...], listen SECONDS";
+
+const DEFAULT_EID: Eid = Eid(8);
+const DEFAULT_TIMEOUT_MILLIS: u64 = 1000;
+const RESPONSE_BUF_LEN: usize = 1024;
+
+/// Byte stream carrying the serial framing
+enum Transport {
+    Serial(String),
+    Tcp(String),
+}
+
+struct Options {
+    transport: Transport,
+    eid: Eid,
+    timeout: Duration,
+    capture: Option<String>,
+    verbose: bool,
+    command: Vec<String>,
+}
+
+/// Records packets in both directions to the capture file and the console
+#[derive(Clone)]
+struct Tap {
+    start: Instant,
+    capture: Option<Arc<Mutex<PcapngWriter<BufWriter<File>>>>>,
+    verbose: bool,
+}
+
+impl Tap {
+    fn record(&self, direction: Direction, pkt: &[u8]) {
+        let now_millis = self.start.elapsed().as_millis() as u64;
+        if self.verbose {
+            let arrow = match direction {
+                Direction::Inbound => "<-",
+                Direction::Outbound => "->",
+            };
+            println!("{now_millis:>8} ms {arrow} {}", hex(pkt));
+        }
+        if let Some(capture) = &self.capture
+            && let Ok(mut capture) = capture.lock()
+            && let Err(e) = capture.write_packet(direction, now_millis, pkt)
+        {
+            eprintln!("capture failed: {e}");
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(capture) = &self.capture
+            && let Ok(mut capture) = capture.lock()
+            && let Err(e) = capture.flush()
+        {
+            eprintln!("capture failed: {e}");
+        }
+    }
+}
+
+/// A [Sender] passing outbound packets through a [Tap]
+struct TapSender<S: Sender> {
+    inner: S,
+    tap: Tap,
+}
+
+impl<S: Sender> Sender for TapSender<S> {
+    type Packet = S::Packet;
+
+    fn send_packet(&mut self, eid: Eid, pkt: &[u8]) -> mctp::Result<()> {
+        self.tap.record(Direction::Outbound, pkt);
+        self.inner.send_packet(eid, pkt)
+    }
+
+    fn get_mtu(&self) -> usize {
+        self.inner.get_mtu()
+    }
+}
+
+type UtilStack = Stack<TapSender<IoSerialSender<Box<dyn Write + Send>>>>;
+
+fn main() -> ExitCode {
+    let options = match parse_options(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let (writer, reader): (Box<dyn Write + Send>, Box<dyn Read + Send>) = match &options.transport {
+        Transport::Serial(path) => {
+            let file = File::options()
+                .read(true)
+                .write(true)
+                .open(path)
+                .map_err(|e| format!("{path}: {e}"))?;
+            let reader = file.try_clone().map_err(|e| e.to_string())?;
+            (Box::new(file), Box::new(reader))
+        }
+        Transport::Tcp(addr) => {
+            let stream = TcpStream::connect(addr).map_err(|e| format!("{addr}: {e}"))?;
+            let reader = stream.try_clone().map_err(|e| e.to_string())?;
+            (Box::new(stream), Box::new(reader))
+        }
+    };
+    let capture = match &options.capture {
+        Some(path) => {
+            let file = File::create(path).map_err(|e| format!("{path}: {e}"))?;
+            let writer = PcapngWriter::new(BufWriter::new(file)).map_err(|e| e.to_string())?;
+            Some(Arc::new(Mutex::new(writer)))
+        }
+        None => None,
+    };
+    let tap = Tap {
+        start: Instant::now(),
+        capture,
+        verbose: options.verbose,
+    };
+
+    let sender = TapSender {
+        inner: IoSerialSender::new(writer),
+        tap: tap.clone(),
+    };
+    let mut stack: UtilStack = Stack::new(sender);
+    stack.set_eid(options.eid).map_err(|e| e.to_string())?;
+    let update_stack = stack.clone();
+    spawn(move || update_loop(update_stack));
+    let inbound_stack = stack.clone();
+    let inbound_tap = tap.clone();
+    spawn(move || inbound_loop(inbound_stack, reader, inbound_tap));
+
+    let result = run_command(&mut stack, &options);
+    tap.flush();
+    result
+}
+
+fn run_command(stack: &mut UtilStack, options: &Options) -> Result<(), String> {
+    let mut args = options.command.iter().map(String::as_str);
+    let command = args.next().ok_or("missing command")?;
+    if command == "listen" {
+        let secs = parse_number(args.next().ok_or("missing duration")?)?;
+        sleep(Duration::from_secs(secs));
+        return Ok(());
+    }
+    let dest = Eid(parse_byte(args.next().ok_or("missing destination EID")?)?);
+    let (code, data) = match command {
+        "get-eid" => (control::CMD_GET_ENDPOINT_ID, vec![]),
+        "get-uuid" => (control::CMD_GET_ENDPOINT_UUID, vec![]),
+        "get-types" => (control::CMD_GET_MESSAGE_TYPE_SUPPORT, vec![]),
+        "get-versions" => {
+            let typ = args.next().map_or(Ok(VERSION_BASE_SPEC), parse_byte)?;
+            (control::CMD_GET_VERSION_SUPPORT, vec![typ])
+        }
+        "get-vendors" => {
+            let selector = args.next().map_or(Ok(0), parse_byte)?;
+            (control::CMD_GET_VENDOR_MESSAGE_SUPPORT, vec![selector])
+        }
+        "raw" => {
+            let typ = MsgType(parse_byte(args.next().ok_or("missing message type")?)?);
+            let payload = args.map(parse_byte).collect::<Result<Vec<_>, _>>()?;
+            let mut buf = [0; RESPONSE_BUF_LEN];
+            let (typ, _, response) = stack
+                .call(dest, typ, &payload, options.timeout, &mut buf)
+                .map_err(|e| e.to_string())?;
+            println!("type {:#04x}: {}", typ.0, hex(response));
+            return Ok(());
+        }
+        _ => return Err(format!("unknown command {command}")),
+    };
+    if args.next().is_some() {
+        return Err("too many arguments".into());
+    }
+
+    let header = ControlHeader {
+        request: true,
+        datagram: false,
+        instance_id: 0,
+        command: code,
+    };
+    let mut request = header.to_bytes().to_vec();
+    request.extend_from_slice(&data);
+    let mut buf = [0; RESPONSE_BUF_LEN];
+    let (typ, _, response) = stack
+        .call(
+            dest,
+            control::MSG_TYPE_CONTROL,
+            &request,
+            options.timeout,
+            &mut buf,
+        )
+        .map_err(|e| e.to_string())?;
+    match ControlHeader::parse(response) {
+        Some(h) if typ == control::MSG_TYPE_CONTROL && h == header.response() => (),
+        _ => return Err(format!("unexpected response: {}", hex(response))),
+    }
+    let body = response
+        .get(control::CONTROL_HEADER_LEN..)
+        .unwrap_or_default();
+    match ControlResponse::decode(code, body) {
+        Ok(decoded) => println!("{decoded:#x?}"),
+        Err(_) => println!("undecodable response: {}", hex(body)),
+    }
+    Ok(())
+}
+
+/// Read packets from `reader` into the stack, passing them through `tap`
+fn inbound_loop(mut stack: UtilStack, reader: Box<dyn Read + Send>, tap: Tap) {
+    let mut reader = FromStd::new(BufReader::new(reader));
+    let mut serial = MctpSerialHandler::new();
+    loop {
+        match serial.recv_sync(&mut reader) {
+            Ok(pkt) => {
+                tap.record(Direction::Inbound, pkt);
+                if let Err(e) = stack.inbound(pkt) {
+                    eprintln!("inbound packet dropped: {e}");
+                }
+            }
+            Err(e) => eprintln!("error receiving packet: {e}"),
+        }
+    }
+}
+
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut transport = None;
+    let mut eid = DEFAULT_EID;
+    let mut timeout = Duration::from_millis(DEFAULT_TIMEOUT_MILLIS);
+    let mut capture = None;
+    let mut verbose = false;
+    let mut command = Vec::new();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+        match arg.as_str() {
+            "--serial" => transport = Some(Transport::Serial(value()?)),
+            "--tcp" => transport = Some(Transport::Tcp(value()?)),
+            "--eid" => eid = Eid(parse_byte(&value()?)?),
+            "--timeout" => timeout = Duration::from_millis(parse_number(&value()?)?),
+            "--capture" => capture = Some(value()?),
+            "-v" => verbose = true,
+            _ => {
+                command.push(arg);
+                command.extend(args.by_ref());
+            }
+        }
+    }
+    Ok(Options {
+        transport: transport.ok_or("no transport given")?,
+        eid,
+        timeout,
+        capture,
+        verbose,
+        command,
+    })
+}
+
+fn parse_number(s: &str) -> Result<u64, String> {
+    let parsed = match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    parsed.map_err(|_| format!("invalid number {s}"))
+}
+
+fn parse_byte(s: &str) -> Result<u8, String> {
+    u8::try_from(parse_number(s)?).map_err(|_| format!("{s} is out of range"))
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -17,34 +17,40 @@
 //! Intended for use in examples and tests.
 
 pub mod serial_sender;
+pub mod virtio;
 
 use std::collections::HashMap;
 use std::sync::{Arc, Condvar, Mutex};
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use mctp::{Eid, Error, Listener, MsgIC, MsgType, ReqChannel, RespChannel, Tag};
-use mctp_lib::{AppCookie, Router, Sender};
+use mctp_lib::clock::StdClock;
+use mctp_lib::{
+    Handle, ListenerHandle, NoHooks, RequestHandle, Router, RouterConfig, Sender, message_body,
+};
 
 const MAX_LISTENER_HANDLES: usize = 128;
 const MAX_REQ_HANDLES: usize = 128;
 
+/// The [Router] used by the stack, with its time taken from [std::time]
+type StdRouter<S> = Router<S, MAX_LISTENER_HANDLES, MAX_REQ_HANDLES, NoHooks, StdClock>;
+
 /// STD MCTP stack
 ///
 /// Encapsulates a inner [Router] in a thread safe and sharable manner.
 /// Provides implementations for the [mctp] traits that hold references to the `stack`.
 pub struct Stack<S: Sender> {
-    inner: Arc<Mutex<Router<S, MAX_LISTENER_HANDLES, MAX_REQ_HANDLES>>>,
+    inner: Arc<Mutex<StdRouter<S>>>,
     /// Notifiers to inform _requests_ and _listeners_ about new messages.
-    notifiers: Arc<Mutex<HashMap<AppCookie, Arc<Condvar>>>>,
-    start_time: Instant,
+    notifiers: Arc<Mutex<HashMap<Handle, Arc<Condvar>>>>,
 }
 
 /// A request implementing [ReqChannel]
 #[derive(Debug)]
 pub struct Request<S: Sender> {
     /// Thread safe reference to a stack
-    stack: Arc<Mutex<Router<S, MAX_LISTENER_HANDLES, MAX_REQ_HANDLES>>>,
-    cookie: AppCookie,
+    stack: Arc<Mutex<StdRouter<S>>>,
+    handle: RequestHandle,
     /// The [Condvar] that nofifies the request once the response is available
     notifier: Arc<Condvar>,
     timeout: Option<Duration>,
@@ -53,17 +59,19 @@ pub struct Request<S: Sender> {
 /// A listener implementing [Listener]
 #[derive(Debug)]
 pub struct ReqListener<S: Sender> {
-    stack: Arc<Mutex<Router<S, MAX_LISTENER_HANDLES, MAX_REQ_HANDLES>>>,
-    notifiers: Arc<Mutex<HashMap<AppCookie, Arc<Condvar>>>>,
-    cookie: AppCookie,
+    stack: Arc<Mutex<StdRouter<S>>>,
+    notifiers: Arc<Mutex<HashMap<Handle, Arc<Condvar>>>>,
+    handle: ListenerHandle,
     notifier: Arc<Condvar>,
     timeout: Option<Duration>,
 }
 /// A response for a request received by a [ReqListener]
 #[derive(Debug)]
 pub struct Response<S: Sender> {
-    stack: Arc<Mutex<Router<S, MAX_LISTENER_HANDLES, MAX_REQ_HANDLES>>>,
-    notifiers: Arc<Mutex<HashMap<AppCookie, Arc<Condvar>>>>,
+    stack: Arc<Mutex<StdRouter<S>>>,
+    notifiers: Arc<Mutex<HashMap<Handle, Arc<Condvar>>>>,
+    /// The listener that received the request
+    listener: ListenerHandle,
     tag: Tag,
     typ: MsgType,
     remote_eid: Eid,
@@ -71,11 +79,15 @@ pub struct Response<S: Sender> {
 
 impl<S: Sender> Stack<S> {
     pub fn new(outbound: S) -> Self {
-        let inner = Router::new(Eid(0), 0, outbound);
+        let inner = Router::new_with_clock(
+            RouterConfig::new(Eid(0)),
+            StdClock::new(),
+            outbound,
+            NoHooks,
+        );
         Self {
             inner: Arc::new(Mutex::new(inner)),
             notifiers: Arc::new(Mutex::new(HashMap::new())),
-            start_time: Instant::now(),
         }
     }
     pub fn request(&mut self, dest: Eid, timeout: Option<Duration>) -> mctp::Result<Request<S>> {
@@ -86,10 +98,10 @@ impl<S: Sender> Stack<S> {
             .req(dest)?;
         let mut notifiers = self.notifiers.lock().map_err(|_| Error::InternalError)?;
         let notifier = Arc::new(Condvar::new());
-        notifiers.insert(handle, Arc::clone(&notifier));
+        notifiers.insert(handle.into(), Arc::clone(&notifier));
         Ok(Request {
             stack: self.inner.clone(),
-            cookie: handle,
+            handle,
             notifier,
             timeout,
             tag: None,
@@ -107,23 +119,48 @@ impl<S: Sender> Stack<S> {
             .listener(typ)?;
         let mut notifiers = self.notifiers.lock().map_err(|_| Error::InternalError)?;
         let notifier = Arc::new(Condvar::new());
-        notifiers.insert(handle, Arc::clone(&notifier));
+        notifiers.insert(handle.into(), Arc::clone(&notifier));
         Ok(ReqListener {
             stack: self.inner.clone(),
-            cookie: handle,
+            handle,
             notifier,
             timeout,
             notifiers: Arc::clone(&self.notifiers),
         })
     }
 
+    /// Send a request of type `typ` to `dest` and wait up to `timeout` for the response
+    ///
+    /// Allocates a request for the exchange and releases it before returning.
+    /// The response payload is copied to `buf`, a response larger than `buf` is dropped and
+    /// reported as [NoSpace](Error::NoSpace).
+    pub fn call<'f>(
+        &mut self,
+        dest: Eid,
+        typ: MsgType,
+        payload: &[u8],
+        timeout: Duration,
+        buf: &'f mut [u8],
+    ) -> mctp::Result<(MsgType, MsgIC, &'f mut [u8])> {
+        let mut req = self.request(dest, Some(timeout))?;
+        let response = req.send(typ, payload).and_then(|()| req.recv(buf));
+        // failing to release the request must not hide the outcome of the exchange
+        if let Ok(mut notifiers) = self.notifiers.lock() {
+            notifiers.remove(&req.handle.into());
+        }
+        if let Ok(mut inner) = self.inner.lock() {
+            let _ = inner.unbind(req.handle);
+        }
+        response
+    }
+
     pub fn inbound(&mut self, pkt: &[u8]) -> Result<(), Error> {
-        let cookie = self
+        let handle = self
             .inner
             .lock()
             .map_err(|_| Error::InternalError)?
             .inbound(pkt)?;
-        if let Some(handle) = cookie {
+        if let Some(handle) = handle {
             let notifiers = self.notifiers.lock().map_err(|_| Error::InternalError)?;
             let notifier = notifiers.get(&handle);
             notifier.inspect(|c| c.notify_all());
@@ -131,14 +168,11 @@ impl<S: Sender> Stack<S> {
         Ok(())
     }
 
-    /// Call the update function of the inner stack with the current timestamp
+    /// Update the inner stack with the current timestamp
     ///
-    /// Convenience function that gets the current timestamp by calculating the duration since the stack was initialized (using [std::time]).
+    /// The time is measured since the stack was initialized (using [std::time]).
     pub fn update(&mut self) -> Result<u64, Error> {
-        self.inner
-            .lock()
-            .map_err(|_| Error::InternalError)?
-            .update(Instant::now().duration_since(self.start_time).as_millis() as u64)
+        self.inner.lock().map_err(|_| Error::InternalError)?.poll()
     }
 
     /// Set the stacks EID
@@ -155,7 +189,6 @@ impl<S: Sender> Clone for Stack<S> {
         Self {
             inner: Arc::clone(&self.inner),
             notifiers: Arc::clone(&self.notifiers),
-            start_time: self.start_time,
         }
     }
 }
@@ -167,12 +200,12 @@ impl<S: Sender> ReqChannel for Request<S> {
         integrity_check: mctp::MsgIC,
         bufs: &[&[u8]],
     ) -> mctp::Result<()> {
-        let tag = self
+        let report = self
             .stack
             .lock()
             .map_err(|_| Error::InternalError)?
-            .send_vectored(None, typ, None, integrity_check, self.cookie, bufs)?;
-        self.tag = Some(tag);
+            .send_vectored(None, typ, None, integrity_check, self.handle, bufs)?;
+        self.tag = Some(report.tag);
         Ok(())
     }
 
@@ -185,15 +218,16 @@ impl<S: Sender> ReqChannel for Request<S> {
         };
         let mut stack = self.stack.lock().unwrap();
         loop {
-            if let Some(mut msg) = stack.recv(self.cookie) {
+            if let Some(mut msg) = stack.recv(self.handle) {
                 if msg.tag.tag() != tag.tag() {
                     msg.retain();
                     return Err(Error::InternalError);
                 }
-                buf.get_mut(..msg.payload.len())
+                let payload = message_body(&msg);
+                buf.get_mut(..payload.len())
                     .ok_or(Error::NoSpace)?
-                    .copy_from_slice(msg.payload);
-                return Ok((msg.typ, msg.ic, &mut buf[..msg.payload.len()]));
+                    .copy_from_slice(payload);
+                return Ok((msg.typ, msg.ic, &mut buf[..payload.len()]));
             }
             if let Some(timeout) = self.timeout {
                 let (stack_result, timeout_result) =
@@ -226,18 +260,20 @@ impl<S: Sender> Listener for ReqListener<S> {
     ) -> mctp::Result<(MsgType, MsgIC, &'f mut [u8], Self::RespChannel<'_>)> {
         let mut stack = self.stack.lock().unwrap();
         loop {
-            if let Some(msg) = stack.recv(self.cookie) {
-                buf.get_mut(..msg.payload.len())
+            if let Some(msg) = stack.recv(self.handle) {
+                let payload = message_body(&msg);
+                buf.get_mut(..payload.len())
                     .ok_or(Error::NoSpace)?
-                    .copy_from_slice(msg.payload);
+                    .copy_from_slice(payload);
                 let resp = Response {
                     stack: Arc::clone(&self.stack),
+                    listener: self.handle,
                     tag: Tag::Unowned(msg.tag.tag()),
                     remote_eid: msg.source,
                     typ: msg.typ,
                     notifiers: Arc::clone(&self.notifiers),
                 };
-                return Ok((msg.typ, msg.ic, &mut buf[..msg.payload.len()], resp));
+                return Ok((msg.typ, msg.ic, &mut buf[..payload.len()], resp));
             }
             if let Some(timeout) = self.timeout {
                 let (stack_result, timeout_result) =
@@ -266,7 +302,7 @@ impl<S: Sender> RespChannel for Response<S> {
                 self.typ,
                 Some(self.tag),
                 integrity_check,
-                AppCookie(255), // TODO improve this in mctp-lib
+                self.listener,
                 bufs,
             )?;
         Ok(())
@@ -284,10 +320,10 @@ impl<S: Sender> RespChannel for Response<S> {
             .req(self.remote_eid)?;
         let mut notifiers = self.notifiers.lock().map_err(|_| Error::InternalError)?;
         let notifier = Arc::new(Condvar::new());
-        notifiers.insert(handle, Arc::clone(&notifier));
+        notifiers.insert(handle.into(), Arc::clone(&notifier));
         Ok(Request {
             stack: self.stack.clone(),
-            cookie: handle,
+            handle,
             notifier,
             timeout: None,
             tag: None,
@@ -342,3 +378,90 @@ pub mod util {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    use mctp::{Eid, Error, Listener, MsgType, RespChannel};
+    use mctp_lib::Sender;
+
+    use crate::Stack;
+
+    /// Sends packets to a channel, fails to send without one
+    struct ChannelSender(Option<mpsc::Sender<Vec<u8>>>);
+
+    impl Sender for ChannelSender {
+        type Packet = [u8; 64];
+
+        fn send_packet(&mut self, _eid: Eid, pkt: &[u8]) -> mctp::Result<()> {
+            let tx = self.0.as_ref().ok_or(Error::TxFailure)?;
+            tx.send(pkt.to_vec()).map_err(|_| Error::TxFailure)
+        }
+
+        fn get_mtu(&self) -> usize {
+            64
+        }
+    }
+
+    /// Check that `stack` holds no requests
+    fn assert_released(stack: &Stack<ChannelSender>) {
+        assert_eq!(stack.inner.lock().unwrap().requests().count(), 0);
+        assert!(stack.notifiers.lock().unwrap().is_empty());
+    }
+
+    /// `call()` releases its request on success, failure and timeout
+    #[test]
+    fn call() {
+        let (tx_a, rx_a) = mpsc::channel();
+        let (tx_b, rx_b) = mpsc::channel();
+        let mut requester = Stack::new(ChannelSender(Some(tx_a)));
+        requester.set_eid(Eid(8)).unwrap();
+        let mut responder = Stack::new(ChannelSender(Some(tx_b)));
+        responder.set_eid(Eid(9)).unwrap();
+        let mut listener = responder
+            .listener(MsgType(1), Some(Duration::from_secs(1)))
+            .unwrap();
+
+        // answers two requests with 10 bytes each, then hands back the channel of the requester
+        let mut relay = requester.clone();
+        let responding = thread::spawn(move || {
+            for _ in 0..2 {
+                responder.inbound(&rx_a.recv().unwrap()).unwrap();
+                let mut buf = [0; 16];
+                let (_, _, _, mut resp) = listener.recv(&mut buf).unwrap();
+                resp.send(&[4; 10]).unwrap();
+                relay.inbound(&rx_b.recv().unwrap()).unwrap();
+            }
+            rx_a
+        });
+
+        let timeout = Duration::from_secs(1);
+        let mut buf = [0; 16];
+        let (typ, _, payload) = requester
+            .call(Eid(9), MsgType(1), &[1, 2, 3], timeout, &mut buf)
+            .unwrap();
+        assert_eq!(typ, MsgType(1));
+        assert_eq!(payload, [4; 10]);
+        assert_released(&requester);
+
+        let mut small = [0; 4];
+        let response = requester.call(Eid(9), MsgType(1), &[1, 2, 3], timeout, &mut small);
+        assert!(matches!(response, Err(Error::NoSpace)));
+        assert_released(&requester);
+        let _rx_a = responding.join().unwrap();
+
+        // nobody answers
+        let timeout = Duration::from_millis(10);
+        let response = requester.call(Eid(9), MsgType(1), &[1], timeout, &mut buf);
+        assert!(matches!(response, Err(Error::TimedOut)));
+        assert_released(&requester);
+
+        let mut failing = Stack::new(ChannelSender(None));
+        let response = failing.call(Eid(9), MsgType(1), &[1], timeout, &mut buf);
+        assert!(matches!(response, Err(Error::TxFailure)));
+        assert_released(&failing);
+    }
+}
@@ -16,7 +16,7 @@ use embedded_io_adapters::std::FromStd;
 use mctp::Error;
 use std::io::Write;
 
-use mctp_lib::{Sender, fragment::SendOutput, serial::MctpSerialHandler};
+use mctp_lib::{Sender, serial::MctpSerialHandler};
 
 pub struct IoSerialSender<W: Write> {
     writer: FromStd<W>,
@@ -32,24 +32,11 @@ impl<W: Write> IoSerialSender<W> {
 }
 
 impl<W: Write> Sender for IoSerialSender<W> {
-    fn send_vectored(
-        &mut self,
-        _eid: mctp::Eid,
-        mut fragmenter: mctp_lib::fragment::Fragmenter,
-        payload: &[&[u8]],
-    ) -> mctp::Result<mctp::Tag> {
-        loop {
-            let mut pkt = [0; mctp_lib::serial::MTU_MAX];
-            let fragment = fragmenter.fragment_vectored(payload, &mut pkt);
-            match fragment {
-                SendOutput::Packet(items) => {
-                    self.serial_handler.send_sync(items, &mut self.writer)?;
-                    self.writer.inner_mut().flush().map_err(Error::Io)?;
-                }
-                SendOutput::Complete { tag, cookie: _ } => return Ok(tag),
-                SendOutput::Error { err, cookie: _ } => return Err(err),
-            }
-        }
+    type Packet = [u8; mctp_lib::serial::MTU_MAX];
+
+    fn send_packet(&mut self, _eid: mctp::Eid, pkt: &[u8]) -> mctp::Result<()> {
+        self.serial_handler.send_sync(pkt, &mut self.writer)?;
+        self.writer.inner_mut().flush().map_err(Error::Io)
     }
 
     fn get_mtu(&self) -> usize {
@@ -0,0 +1,79 @@
+// Copyright 2025
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! MCTP between virtual machines and the host over virtio-serial or vsock
+//!
+//! Both channels are byte streams, so packets are carried with the framing of the serial
+//! binding (DSP0253), as on a physical serial line:
+//!
+//! - in the guest, a virtio-serial port shows up as a character device, see
+//!   [open_guest_port()],
+//! - on the host, QEMU exposes the other end of the port as a UNIX socket
+//!   (`-chardev socket,path=...,server=on`), see [connect_host()],
+//! - vsock streams (e.g. from the `vsock` crate) are used as they are.
+//!
+//! [attach()] connects a [Stack] to such a channel and drives it from background threads.
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+#[cfg(unix)]
+use std::path::Path;
+use std::thread::spawn;
+
+use mctp::Eid;
+
+use crate::Stack;
+use crate::serial_sender::IoSerialSender;
+use crate::util::{inbound_loop, update_loop};
+
+/// Directory the guest kernel creates named virtio-serial port devices in
+pub const VIRTIO_PORTS_DIR: &str = "/dev/virtio-ports";
+
+/// Open the virtio-serial port `name` from inside the guest
+///
+/// `name` is the name given to the port on the host (`-device virtserialport,name=...`).
+pub fn open_guest_port(name: &str) -> io::Result<File> {
+    File::options()
+        .read(true)
+        .write(true)
+        .open(format!("{VIRTIO_PORTS_DIR}/{name}"))
+}
+
+/// Connect to the host side of a virtio-serial port exposed as a UNIX socket at `path`
+#[cfg(unix)]
+pub fn connect_host(path: impl AsRef<Path>) -> io::Result<UnixStream> {
+    UnixStream::connect(path)
+}
+
+/// Create a [Stack] with EID `eid` on a stream channel
+///
+/// `writer` and `reader` are the two halves of the channel, usually obtained with the
+/// `try_clone()` of the stream. Spawns threads reading inbound packets and updating the
+/// stack, which run for the lifetime of the process.
+pub fn attach<W, R>(writer: W, reader: R, eid: Eid) -> mctp::Result<Stack<IoSerialSender<W>>>
+where
+    W: Write + Send + 'static,
+    R: Read + Send + 'static,
+{
+    let mut stack = Stack::new(IoSerialSender::new(writer));
+    stack.set_eid(eid)?;
+
+    let update_stack = stack.clone();
+    spawn(move || update_loop(update_stack));
+    let driver_stack = stack.clone();
+    spawn(move || inbound_loop(driver_stack, reader));
+    Ok(stack)
+}